@@ -0,0 +1,188 @@
+//!
+//! Which methods call which other methods, for all classes on a class path
+//!
+
+use crate::{ArchiveError, ClassPath, Result};
+use cs_parser::{
+    decode_code, u2, AttributeInfoInner, ClassFile, ConstantPool, CpInfoInner, Instruction,
+    ParseErr,
+};
+use rayon::prelude::*;
+use std::fmt::{Display, Formatter};
+
+/// A method by the internal name of its class, its name and its descriptor
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodId {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// The instruction that calls a method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+/// One invoke instruction. The callee is the method that the instruction refers to, which can
+/// be inherited or overridden, so calls of `a/B.toString()` that `a/B` doesn't declare still have
+/// `a/B` as the class
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Call {
+    pub caller: MethodId,
+    pub callee: MethodId,
+    pub kind: CallKind,
+    /// The offset of the instruction in the code of the caller
+    pub offset: u32,
+}
+
+/// The calls of all methods of a set of classes. Calls through `invokedynamic`, like lambdas and
+/// string concatenation, are not included
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    calls: Vec<Call>,
+}
+
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The calls of every class on the class path, the classes are parsed on the rayon thread pool.
+    /// Classes that are hidden by an earlier entry of the class path are skipped, like `java` does.
+    /// Classes that can't be read, parsed or decoded are left out of the graph, their errors are
+    /// returned in the order of the class names
+    pub fn of_class_path(class_path: &ClassPath) -> Result<(Self, Vec<ArchiveError>)> {
+        let results = class_path
+            .class_names()?
+            .into_par_iter()
+            .map(|name| {
+                let entry = format!("{}.class", name);
+                let Some(bytes) = class_path.find_bytes(&name)? else {
+                    return Ok(Vec::new());
+                };
+                cs_parser::parse_class_file(&bytes)
+                    .and_then(|class| class_calls(&class))
+                    .map_err(|err| ArchiveError::Parse { entry, err })
+            })
+            .collect::<Vec<Result<_>>>();
+        let mut graph = Self::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(calls) => graph.calls.extend(calls),
+                Err(err) => errors.push(err),
+            }
+        }
+        Ok((graph, errors))
+    }
+
+    /// The calls of the classes, the code of their methods is decoded
+    pub fn from_classes<'a>(
+        classes: impl IntoIterator<Item = &'a ClassFile>,
+    ) -> std::result::Result<Self, ParseErr> {
+        let mut graph = Self::new();
+        for class in classes {
+            graph.add_class(class)?;
+        }
+        Ok(graph)
+    }
+
+    /// Adds the calls of all methods of the class
+    pub fn add_class(&mut self, class: &ClassFile) -> std::result::Result<(), ParseErr> {
+        self.calls.extend(class_calls(class)?);
+        Ok(())
+    }
+
+    /// All calls, in the order of the classes and of the instructions in their methods
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    /// The calls of the method with the name in the class, for example `java/lang/Runtime` and
+    /// `exec`. Without a descriptor, the calls of all overloads are returned
+    pub fn callers<'a>(
+        &'a self,
+        class: &'a str,
+        name: &'a str,
+        descriptor: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a Call> + 'a {
+        self.calls.iter().filter(move |call| {
+            call.callee.class == class
+                && call.callee.name == name
+                && descriptor.is_none_or(|descriptor| call.callee.descriptor == descriptor)
+        })
+    }
+
+    /// The calls that the method makes
+    pub fn callees<'a>(&'a self, caller: &'a MethodId) -> impl Iterator<Item = &'a Call> + 'a {
+        self.calls.iter().filter(move |call| call.caller == *caller)
+    }
+}
+
+/// The calls of all methods with code in the class. Operands that aren't method references are
+/// skipped, the verifier rejects them
+fn class_calls(class: &ClassFile) -> std::result::Result<Vec<Call>, ParseErr> {
+    let cp = &class.constant_pool;
+    let class_name = class.this_class.get(cp).name_index.get(cp);
+    let mut calls = Vec::new();
+    for method in &class.methods {
+        let Some(code) = method
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.inner {
+                AttributeInfoInner::Code { code, .. } => Some(code),
+                _ => None,
+            })
+        else {
+            continue;
+        };
+        let caller = MethodId {
+            class: class_name.to_owned(),
+            name: method.name_index.get(cp).to_owned(),
+            descriptor: method.descriptor_index.get(cp).to_owned(),
+        };
+        for (offset, instruction) in decode_code(code)? {
+            let (kind, index) = match instruction {
+                Instruction::Invokevirtual(index) => (CallKind::Virtual, index),
+                Instruction::Invokespecial(index) => (CallKind::Special, index),
+                Instruction::Invokestatic(index) => (CallKind::Static, index),
+                Instruction::Invokeinterface { index, .. } => (CallKind::Interface, index),
+                _ => continue,
+            };
+            if let Some(callee) = method_ref(index, cp) {
+                calls.push(Call {
+                    caller: caller.clone(),
+                    callee,
+                    kind,
+                    offset,
+                });
+            }
+        }
+    }
+    Ok(calls)
+}
+
+fn method_ref(index: u2, cp: &ConstantPool) -> Option<MethodId> {
+    let (class, name_and_type) = match &cp.entry(index)?.inner {
+        CpInfoInner::MethodRef(method) => (method.class_index, method.name_and_type_index),
+        CpInfoInner::InterfaceMethodref(method) => (method.class_index, method.name_and_type_index),
+        _ => return None,
+    };
+    let name_and_type = name_and_type.get(cp);
+    Some(MethodId {
+        class: class.get(cp).name_index.get(cp).to_owned(),
+        name: name_and_type.name_index.get(cp).to_owned(),
+        descriptor: name_and_type.descriptor_index.get(cp).to_owned(),
+    })
+}
+
+/// Like `java/lang/Runtime.exec(Ljava/lang/String;)Ljava/lang/Process;`
+impl Display for MethodId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}{}", self.class, self.name, self.descriptor)
+    }
+}
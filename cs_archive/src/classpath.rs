@@ -0,0 +1,213 @@
+//!
+//! Finding classes by their name in directories and archives
+//!
+
+use crate::jimage::{is_jimage, JImage};
+use crate::{ArchiveError, Result};
+use cs_parser::ClassFile;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// The header that jmod files have before the zip archive
+const JMOD_MAGIC: &[u8] = b"JM\x01\x00";
+
+/// A list of directories and archives that classes are loaded from, like the `-cp` of `java`.
+/// The entries are searched in the order they were added, and every class is only parsed once
+#[derive(Debug, Default)]
+pub struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+    /// The parsed classes by their internal name
+    cache: Mutex<HashMap<String, Arc<ClassFile>>>,
+}
+
+#[derive(Debug)]
+enum ClassPathEntry {
+    /// The class `a/b/C` is the file `a/b/C.class` below the directory
+    Directory(PathBuf),
+    /// The archive is kept in memory, clones share the data. The classes are below the prefix,
+    /// which is `classes/` in jmods
+    Archive {
+        archive: ZipArchive<Cursor<Arc<[u8]>>>,
+        prefix: &'static str,
+    },
+    /// The modules of a JDK
+    Image(JImage),
+}
+
+impl ClassPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A class path from a list separated like the `PATH` variable of the platform, for example
+    /// `build/classes:lib/a.jar`
+    pub fn from_list(list: &str) -> Result<Self> {
+        let mut class_path = Self::new();
+        for path in std::env::split_paths(list) {
+            class_path.push(path)?;
+        }
+        Ok(class_path)
+    }
+
+    /// Adds a directory, or an archive if the path is a file, see `push_archive`
+    pub fn push(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            self.push_directory(path);
+            Ok(())
+        } else {
+            self.push_archive(path)
+        }
+    }
+
+    /// Adds a directory with the package structure of the classes. It is only read once classes
+    /// are looked up in it
+    pub fn push_directory(&mut self, path: impl Into<PathBuf>) {
+        self.entries.push(ClassPathEntry::Directory(path.into()));
+    }
+
+    /// Adds a jar, a jmod or the `lib/modules` file of a JDK, which is read into memory
+    /// immediately
+    pub fn push_archive(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let bytes = std::fs::read(path)?;
+        let entry = if is_jimage(&bytes) {
+            ClassPathEntry::Image(JImage::new(bytes)?)
+        } else if let Some(zip) = bytes.strip_prefix(JMOD_MAGIC) {
+            ClassPathEntry::Archive {
+                archive: ZipArchive::new(Cursor::new(zip.into()))?,
+                prefix: "classes/",
+            }
+        } else {
+            ClassPathEntry::Archive {
+                archive: ZipArchive::new(Cursor::new(bytes.into()))?,
+                prefix: "",
+            }
+        };
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Adds the classes of the JDK installed at `java_home`, from `lib/modules`, the jmods if the
+    /// modules file is missing, or `jre/lib/rt.jar` before Java 9
+    pub fn push_java_home(&mut self, java_home: impl AsRef<Path>) -> Result<()> {
+        let java_home = java_home.as_ref();
+        let modules = java_home.join("lib").join("modules");
+        if modules.is_file() {
+            return self.push_archive(modules);
+        }
+        let jmods = java_home.join("jmods");
+        if jmods.is_dir() {
+            let mut files = std::fs::read_dir(jmods)?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>>>()?;
+            files.retain(|file| file.extension().is_some_and(|ext| ext == "jmod"));
+            files.sort();
+            for file in files {
+                self.push_archive(file)?;
+            }
+            return Ok(());
+        }
+        self.push_archive(java_home.join("jre").join("lib").join("rt.jar"))
+    }
+
+    /// The bytes of the class with the internal name like `java/lang/String` from the first entry
+    /// that contains it, or `None` if no entry does
+    pub fn find_bytes(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let file_name = format!("{}.class", name);
+        for entry in &self.entries {
+            let bytes = match entry {
+                ClassPathEntry::Directory(dir) => match std::fs::read(dir.join(&file_name)) {
+                    Ok(bytes) => bytes,
+                    Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err.into()),
+                },
+                ClassPathEntry::Archive { archive, prefix } => {
+                    let mut archive = archive.clone();
+                    let mut entry = match archive.by_name(&format!("{}{}", prefix, file_name)) {
+                        Ok(entry) => entry,
+                        Err(ZipError::FileNotFound) => continue,
+                        Err(err) => return Err(err.into()),
+                    };
+                    let mut bytes = Vec::with_capacity(entry.size() as usize);
+                    entry.read_to_end(&mut bytes)?;
+                    bytes
+                }
+                ClassPathEntry::Image(image) => match image.find_class(name)? {
+                    Some(bytes) => bytes.to_vec(),
+                    None => continue,
+                },
+            };
+            return Ok(Some(bytes));
+        }
+        Ok(None)
+    }
+
+    /// The internal names of all classes on the class path, sorted and without duplicates. The
+    /// classes in `META-INF/` of archives are not included, like the versions of multi-release jars
+    pub fn class_names(&self) -> Result<Vec<String>> {
+        let mut names = BTreeSet::new();
+        for entry in &self.entries {
+            match entry {
+                ClassPathEntry::Directory(dir) => walk_directory(dir, "", &mut names)?,
+                ClassPathEntry::Archive { archive, prefix } => {
+                    names.extend(archive.file_names().filter_map(|name| {
+                        let name = name.strip_prefix(prefix)?.strip_suffix(".class")?;
+                        (!name.starts_with("META-INF/")).then(|| name.to_owned())
+                    }))
+                }
+                ClassPathEntry::Image(image) => names.extend(image.class_names()?),
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    /// The parsed class with the internal name like `java/lang/String`, or `None` if no entry
+    /// contains it. Classes are only parsed the first time they are resolved
+    pub fn resolve(&self, name: &str) -> Result<Option<Arc<ClassFile>>> {
+        if let Some(class) = self.cache.lock().unwrap().get(name) {
+            return Ok(Some(Arc::clone(class)));
+        }
+        let Some(bytes) = self.find_bytes(name)? else {
+            return Ok(None);
+        };
+        // the lock isn't held while parsing, if another thread was faster its class is used
+        let class = cs_parser::parse_class_file(&bytes).map_err(|err| ArchiveError::Parse {
+            entry: format!("{}.class", name),
+            err,
+        })?;
+        let mut cache = self.cache.lock().unwrap();
+        let class = cache.entry(name.to_owned()).or_insert(Arc::new(class));
+        Ok(Some(Arc::clone(class)))
+    }
+}
+
+/// Adds the names of the class files below the directory, whose package is `package`
+fn walk_directory(dir: &Path, package: &str, names: &mut BTreeSet<String>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // like a missing class, a missing directory has no classes
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        let name = match package {
+            "" => file_name.to_owned(),
+            _ => format!("{}/{}", package, file_name),
+        };
+        if entry.file_type()?.is_dir() {
+            walk_directory(&entry.path(), &name, names)?;
+        } else if let Some(class) = name.strip_suffix(".class") {
+            names.insert(class.to_owned());
+        }
+    }
+    Ok(())
+}
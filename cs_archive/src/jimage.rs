@@ -0,0 +1,248 @@
+//!
+//! Reading classes from the `lib/modules` file of a JDK, which uses the undocumented jimage format
+//!
+//! The file starts with an index of all resources, followed by their contents:
+//! - the header of 7 u32s, in the byte order of the platform the JDK was built for
+//! - the redirect table, used to find the slot of a name in the offsets table from its hash
+//! - the offsets table, the offset of the location of each resource
+//! - the locations, each a list of attributes like the module or the content offset
+//! - the strings the locations refer to, terminated by a null byte
+//!
+
+use crate::{ArchiveError, Result};
+use std::collections::HashMap;
+
+const MAGIC: u32 = 0xCAFE_DADA;
+const HEADER_SIZE: usize = 7 * 4;
+const HASH_MULTIPLIER: u32 = 0x0100_0193;
+
+/// The attributes of a location
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+
+/// A jimage file that is read into memory
+#[derive(Debug)]
+pub struct JImage {
+    bytes: Vec<u8>,
+    big_endian: bool,
+    table_length: usize,
+    redirect_start: usize,
+    offsets_start: usize,
+    locations_start: usize,
+    strings_start: usize,
+    /// The index ends where the contents of the resources start
+    index_end: usize,
+    /// The module of each package, for example `java/lang` to `java.base`
+    packages: HashMap<String, String>,
+}
+
+/// Whether the file starts with the magic number of a jimage, in either byte order
+pub fn is_jimage(bytes: &[u8]) -> bool {
+    let magic = MAGIC.to_le_bytes();
+    bytes.starts_with(&magic) || bytes.starts_with(&MAGIC.to_be_bytes())
+}
+
+fn invalid(msg: &str) -> ArchiveError {
+    ArchiveError::Image(msg.to_owned())
+}
+
+impl JImage {
+    pub fn new(bytes: Vec<u8>) -> Result<Self> {
+        let big_endian = if bytes.starts_with(&MAGIC.to_le_bytes()) {
+            false
+        } else if bytes.starts_with(&MAGIC.to_be_bytes()) {
+            true
+        } else {
+            return Err(invalid("Invalid magic number"));
+        };
+        let mut image = Self {
+            bytes,
+            big_endian,
+            table_length: 0,
+            redirect_start: HEADER_SIZE,
+            offsets_start: 0,
+            locations_start: 0,
+            strings_start: 0,
+            index_end: 0,
+            packages: HashMap::new(),
+        };
+
+        let version = image.u32(4)?;
+        if version >> 16 != 1 {
+            return Err(ArchiveError::Image(format!(
+                "Unsupported version {}.{}",
+                version >> 16,
+                version & 0xFFFF
+            )));
+        }
+        image.table_length = image.u32(16)? as usize;
+        let locations_size = image.u32(20)? as usize;
+        let strings_size = image.u32(24)? as usize;
+        image.offsets_start = image.redirect_start + image.table_length * 4;
+        image.locations_start = image.offsets_start + image.table_length * 4;
+        image.strings_start = image.locations_start + locations_size;
+        image.index_end = image.strings_start + strings_size;
+        if image.index_end > image.bytes.len() {
+            return Err(invalid("The index is longer than the file"));
+        }
+
+        for slot in 0..image.table_length {
+            let location = image.location(slot)?;
+            if image.string(location[ATTRIBUTE_EXTENSION])? == "class" {
+                let package = image.string(location[ATTRIBUTE_PARENT])?.to_owned();
+                let module = image.string(location[ATTRIBUTE_MODULE])?.to_owned();
+                image.packages.entry(package).or_insert(module);
+            }
+        }
+        Ok(image)
+    }
+
+    /// The bytes of the class with the internal name like `java/lang/String`, looked up in the
+    /// module that contains its package
+    pub fn find_class(&self, name: &str) -> Result<Option<&[u8]>> {
+        let package = name.rsplit_once('/').map_or("", |(package, _)| package);
+        match self.packages.get(package) {
+            Some(module) => self.find_resource(&format!("/{}/{}.class", module, name)),
+            None => Ok(None),
+        }
+    }
+
+    /// The internal names of all classes in the image, in the order of the index
+    pub fn class_names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for slot in 0..self.table_length {
+            let location = self.location(slot)?;
+            if self.string(location[ATTRIBUTE_EXTENSION])? == "class" {
+                let parent = self.string(location[ATTRIBUTE_PARENT])?;
+                let base = self.string(location[ATTRIBUTE_BASE])?;
+                names.push(match parent {
+                    "" => base.to_owned(),
+                    _ => format!("{}/{}", parent, base),
+                });
+            }
+        }
+        Ok(names)
+    }
+
+    /// The contents of the resource with the full name like `/java.base/java/lang/String.class`
+    pub fn find_resource(&self, name: &str) -> Result<Option<&[u8]>> {
+        if self.table_length == 0 {
+            return Ok(None);
+        }
+        let index = hash(name, HASH_MULTIPLIER) as usize % self.table_length;
+        let redirect = self.u32(self.redirect_start + index * 4)? as i32;
+        let slot = match redirect {
+            0 => return Ok(None),
+            // the slot is stored directly
+            ..0 => (-1 - redirect) as usize,
+            // the hash with this seed gives the slot
+            _ => hash(name, redirect as u32) as usize % self.table_length,
+        };
+        if slot >= self.table_length {
+            return Err(invalid("Redirect to a slot outside the table"));
+        }
+        let location = self.location(slot)?;
+        // other names can have the same hash
+        if self.location_name(&location)? != name {
+            return Ok(None);
+        }
+        if location[ATTRIBUTE_COMPRESSED] != 0 {
+            return Err(ArchiveError::Image(format!(
+                "{} is compressed, which is not supported",
+                name
+            )));
+        }
+        let start = self.index_end + location[ATTRIBUTE_OFFSET] as usize;
+        let end = start + location[ATTRIBUTE_UNCOMPRESSED] as usize;
+        self.bytes
+            .get(start..end)
+            .map(Some)
+            .ok_or_else(|| invalid("A resource is outside of the file"))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32> {
+        let bytes = self
+            .bytes
+            .get(offset..offset + 4)
+            .ok_or_else(|| invalid("Unexpected end of the index"))?;
+        let bytes = bytes.try_into().expect("4 bytes");
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// The attributes of the location in the slot, indexed by their kind
+    fn location(&self, slot: usize) -> Result<[u64; 8]> {
+        let offset = self.u32(self.offsets_start + slot * 4)? as usize;
+        let locations = &self.bytes[self.locations_start..self.strings_start];
+        let mut attributes = [0; 8];
+        let mut i = offset;
+        loop {
+            let &header = locations
+                .get(i)
+                .ok_or_else(|| invalid("A location is outside of the index"))?;
+            // the kind is in the upper 5 bits, the length of the value minus one in the lower 3
+            let kind = (header >> 3) as usize;
+            if kind == 0 {
+                return Ok(attributes);
+            }
+            let len = (header & 0b111) as usize + 1;
+            let value = locations
+                .get(i + 1..i + 1 + len)
+                .ok_or_else(|| invalid("A location is outside of the index"))?;
+            *attributes
+                .get_mut(kind)
+                .ok_or_else(|| invalid("Unknown location attribute"))? = value
+                .iter()
+                .fold(0, |value, &byte| value << 8 | u64::from(byte));
+            i += 1 + len;
+        }
+    }
+
+    /// The null terminated string at the offset in the strings
+    fn string(&self, offset: u64) -> Result<&str> {
+        let strings = &self.bytes[self.strings_start..self.index_end];
+        let string = strings
+            .get(offset as usize..)
+            .and_then(|rest| rest.split(|&byte| byte == 0).next())
+            .ok_or_else(|| invalid("A string is outside of the index"))?;
+        std::str::from_utf8(string).map_err(|_| invalid("A string is not valid UTF-8"))
+    }
+
+    /// The full name of the resource, `/module/parent/base.extension`
+    fn location_name(&self, location: &[u64; 8]) -> Result<String> {
+        let mut name = String::new();
+        let module = self.string(location[ATTRIBUTE_MODULE])?;
+        if !module.is_empty() {
+            name.push('/');
+            name.push_str(module);
+            name.push('/');
+        }
+        let parent = self.string(location[ATTRIBUTE_PARENT])?;
+        if !parent.is_empty() {
+            name.push_str(parent);
+            name.push('/');
+        }
+        name.push_str(self.string(location[ATTRIBUTE_BASE])?);
+        let extension = self.string(location[ATTRIBUTE_EXTENSION])?;
+        if !extension.is_empty() {
+            name.push('.');
+            name.push_str(extension);
+        }
+        Ok(name)
+    }
+}
+
+/// The hash the JDK uses for names, over their UTF-8 bytes
+fn hash(name: &str, seed: u32) -> u32 {
+    name.bytes().fold(seed, |hash, byte| {
+        hash.wrapping_mul(HASH_MULTIPLIER) ^ u32::from(byte)
+    }) & 0x7FFF_FFFF
+}
@@ -0,0 +1,126 @@
+//!
+//! Reading class files out of archives like jars
+//!
+
+mod callgraph;
+mod classpath;
+mod jimage;
+#[cfg(test)]
+mod test;
+
+pub use callgraph::{Call, CallGraph, CallKind, MethodId};
+pub use classpath::ClassPath;
+pub use jimage::JImage;
+
+use cs_parser::{ClassFile, ParseErr};
+use rayon::prelude::*;
+use std::fmt::{Display, Formatter};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// The full name of an entry in an archive, for example `java/lang/String.class`
+pub type EntryName = String;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(std::io::Error),
+    Zip(ZipError),
+    /// An entry of the archive is not a valid class file
+    Parse {
+        entry: EntryName,
+        err: ParseErr,
+    },
+    /// The `lib/modules` file of a JDK is malformed
+    Image(String),
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not read archive: {}", err),
+            Self::Zip(err) => write!(f, "Invalid archive: {}", err),
+            Self::Parse { entry, err } => write!(f, "{}: {}", entry, err),
+            Self::Image(msg) => write!(f, "Invalid modules image: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ZipError> for ArchiveError {
+    fn from(err: ZipError) -> Self {
+        Self::Zip(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ArchiveError>;
+
+/// Which entries of an archive get parsed
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    /// Only parse entries whose name starts with this prefix, for example `java/util/`
+    pub prefix: Option<String>,
+    /// Also parse the classes in `META-INF/`, for example the versioned classes of multi-release jars
+    pub include_meta_inf: bool,
+}
+
+impl ArchiveOptions {
+    fn matches(&self, name: &str) -> bool {
+        name.ends_with(".class")
+            && (self.include_meta_inf || !name.starts_with("META-INF/"))
+            && self
+                .prefix
+                .as_deref()
+                .is_none_or(|prefix| name.starts_with(prefix))
+    }
+}
+
+/// Reads all class files in the archive at `path` on the rayon thread pool  
+/// The archive is read into memory once, the entries are decompressed in parallel
+pub fn archive_entries(
+    path: impl AsRef<Path>,
+    options: &ArchiveOptions,
+) -> Result<impl ParallelIterator<Item = Result<(EntryName, Vec<u8>)>>> {
+    let bytes: Arc<[u8]> = std::fs::read(path)?.into();
+    let archive = ZipArchive::new(Cursor::new(bytes))?;
+    let entries = (0..archive.len())
+        .filter(|&index| {
+            archive
+                .name_for_index(index)
+                .is_some_and(|name| options.matches(name))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(entries.into_par_iter().map_init(
+        move || archive.clone(),
+        |archive, index| {
+            let mut entry = archive.by_index(index)?;
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut bytes)?;
+            Ok((entry.name().to_owned(), bytes))
+        },
+    ))
+}
+
+/// Parses all class files in the archive at `path` on the rayon thread pool, see `archive_entries`
+pub fn parse_archive(
+    path: impl AsRef<Path>,
+    options: &ArchiveOptions,
+) -> Result<impl ParallelIterator<Item = Result<(EntryName, ClassFile)>>> {
+    Ok(archive_entries(path, options)?.map(|entry| {
+        let (name, bytes) = entry?;
+        match cs_parser::parse_class_file(&bytes) {
+            Ok(class) => Ok((name, class)),
+            Err(err) => Err(ArchiveError::Parse { entry: name, err }),
+        }
+    }))
+}
@@ -0,0 +1,308 @@
+use super::*;
+
+#[test]
+fn parse_test_jar() {
+    let mut results = parse_archive("testdata/test.jar", &ArchiveOptions::default())
+        .unwrap()
+        .map(|result| match result {
+            Ok((name, class)) => (name, Ok(class.methods.len())),
+            Err(ArchiveError::Parse { entry, .. }) => (entry, Err(())),
+            Err(err) => panic!("Unexpected error {}", err),
+        })
+        .collect::<Vec<_>>();
+    results.sort();
+
+    assert_eq!(
+        results,
+        vec![
+            ("Test.class".to_string(), Ok(1)),
+            ("Test2.class".to_string(), Ok(3)),
+            ("pkg/Attributes.class".to_string(), Ok(3)),
+            ("pkg/Broken.class".to_string(), Err(())),
+        ]
+    );
+}
+
+#[test]
+fn parse_test_jar_with_prefix() {
+    let options = ArchiveOptions {
+        prefix: Some("pkg/A".to_string()),
+        ..ArchiveOptions::default()
+    };
+    let names = parse_archive("testdata/test.jar", &options)
+        .unwrap()
+        .map(|result| result.unwrap().0)
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, vec!["pkg/Attributes.class".to_string()]);
+}
+
+/// Parses every class file, jar, jmod and jimage (`lib/modules`) below the directory in
+/// `COLDSQUARE_CORPUS` (for example a JDK or a maven repository) and reports all failures grouped
+/// by their message  
+/// `COLDSQUARE_CORPUS=/usr/lib/jvm cargo test -p cs_archive corpus -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn corpus() {
+    let root = std::env::var("COLDSQUARE_CORPUS").expect("COLDSQUARE_CORPUS is not set");
+    let mut report = CorpusReport::default();
+    // panics are collected into the report instead
+    std::panic::set_hook(Box::new(|_| {}));
+    walk_corpus(Path::new(&root), &mut report);
+    let _ = std::panic::take_hook();
+
+    println!("parsed {} classes", report.parsed);
+    let mut failures = report.failures.iter().collect::<Vec<_>>();
+    failures.sort_by_key(|(_, classes)| std::cmp::Reverse(classes.len()));
+    for (message, classes) in &failures {
+        println!("{:>6}  {}", classes.len(), message);
+        println!("        e.g. {}", classes[0]);
+    }
+
+    assert!(failures.is_empty(), "{} kinds of failures", failures.len());
+}
+
+#[derive(Default)]
+struct CorpusReport {
+    parsed: usize,
+    /// The error message, and the classes that failed with it
+    failures: std::collections::HashMap<String, Vec<String>>,
+}
+
+impl CorpusReport {
+    fn parse(&mut self, name: String, bytes: &[u8]) {
+        let result = std::panic::catch_unwind(|| cs_parser::parse_class_file(bytes));
+        let message = match result {
+            Ok(Ok(_)) => {
+                self.parsed += 1;
+                return;
+            }
+            Ok(Err(err)) => err.to_string(),
+            Err(panic) => format!(
+                "panic: {}",
+                panic
+                    .downcast_ref::<String>()
+                    .map(String::as_str)
+                    .or_else(|| panic.downcast_ref::<&str>().copied())
+                    .unwrap_or("<unknown>")
+            ),
+        };
+        self.failures.entry(message).or_default().push(name);
+    }
+}
+
+fn walk_corpus(path: &Path, report: &mut CorpusReport) {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            walk_corpus(&entry, report);
+        }
+        return;
+    }
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("class") => report.parse(path.display().to_string(), &std::fs::read(path).unwrap()),
+        Some("jar") => {
+            let mut archive = match std::fs::File::open(path)
+                .map_err(ArchiveError::from)
+                .and_then(|file| Ok(ZipArchive::new(file)?))
+            {
+                Ok(archive) => archive,
+                Err(err) => {
+                    println!("skipping {}: {}", path.display(), err);
+                    return;
+                }
+            };
+            for index in 0..archive.len() {
+                let mut entry = archive.by_index(index).unwrap();
+                if !entry.name().ends_with(".class") {
+                    continue;
+                }
+                let name = format!("{}!{}", path.display(), entry.name());
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes).unwrap();
+                report.parse(name, &bytes);
+            }
+        }
+        Some("jmod") => walk_class_path(path, report),
+        None if path.file_name().is_some_and(|name| name == "modules") => {
+            walk_class_path(path, report)
+        }
+        _ => {}
+    }
+}
+
+/// Parses the classes of a jmod or a jimage with the readers of `ClassPath`
+fn walk_class_path(path: &Path, report: &mut CorpusReport) {
+    let mut class_path = ClassPath::new();
+    if let Err(err) = class_path.push_archive(path) {
+        println!("skipping {}: {}", path.display(), err);
+        return;
+    }
+    for name in class_path.class_names().unwrap() {
+        let bytes = class_path.find_bytes(&name).unwrap().unwrap();
+        report.parse(format!("{}!{}.class", path.display(), name), &bytes);
+    }
+}
+
+#[test]
+fn corpus_walks_jmods() {
+    let mut report = CorpusReport::default();
+    walk_corpus(Path::new("testdata"), &mut report);
+    // the classes of `small.jmod`, and all but `pkg/Broken.class` of `test.jar`
+    assert_eq!(report.parsed, 5);
+    assert_eq!(
+        report.failures.values().flatten().collect::<Vec<_>>(),
+        ["testdata/test.jar!pkg/Broken.class"]
+    );
+}
+
+#[test]
+fn class_path_resolves_in_order() {
+    // a directory with a different class in `Test.class` than the jar
+    let dir = std::env::temp_dir().join(format!("cs_archive_class_path_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let test2 = std::fs::read("../cs_parser/testdata/Test2.class").unwrap();
+    std::fs::write(dir.join("Test.class"), &test2).unwrap();
+
+    let mut class_path = ClassPath::new();
+    class_path.push(&dir).unwrap();
+    class_path.push("testdata/test.jar").unwrap();
+
+    assert_eq!(class_path.find_bytes("Test").unwrap().unwrap(), test2);
+    assert!(class_path.resolve("pkg/Attributes").unwrap().is_some());
+
+    let test = class_path.resolve("Test").unwrap().unwrap();
+    assert_eq!(test.methods.len(), 3);
+    assert!(Arc::ptr_eq(
+        &test,
+        &class_path.resolve("Test").unwrap().unwrap()
+    ));
+
+    assert!(class_path.resolve("java/lang/Missing").unwrap().is_none());
+    assert!(matches!(
+        class_path.resolve("pkg/Broken"),
+        Err(ArchiveError::Parse { entry, .. }) if entry == "pkg/Broken.class"
+    ));
+    assert!(ClassPath::from_list("testdata/missing.jar").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn class_path_jmod() {
+    let mut class_path = ClassPath::new();
+    class_path.push("testdata/small.jmod").unwrap();
+
+    let small = class_path.resolve("pkg/Small").unwrap().unwrap();
+    assert_eq!(small.methods.len(), 2);
+    assert!(class_path.resolve("module-info").unwrap().is_some());
+    // only the classes of the module can be found
+    assert!(class_path.resolve("Small").unwrap().is_none());
+    assert_eq!(
+        class_path.class_names().unwrap(),
+        vec!["module-info".to_string(), "pkg/Small".to_string()]
+    );
+}
+
+/// Loads classes from the JDK in `JAVA_HOME`, which needs a real JDK and is therefore ignored  
+/// `JAVA_HOME=/usr/lib/jvm/java-17-openjdk-amd64 cargo test -p cs_archive java_home -- --ignored`
+#[test]
+#[ignore]
+fn class_path_java_home() {
+    let java_home = std::env::var("JAVA_HOME").expect("JAVA_HOME is not set");
+    let mut class_path = ClassPath::new();
+    class_path.push_java_home(&java_home).unwrap();
+
+    let object = class_path.resolve("java/lang/Object").unwrap().unwrap();
+    let cp = &object.constant_pool;
+    assert_eq!(
+        object.this_class.get(cp).name_index.get(cp),
+        "java/lang/Object"
+    );
+    for name in [
+        "java/lang/String",
+        "java/util/Map$Entry",
+        "java/util/concurrent/ConcurrentHashMap",
+        "javax/sql/DataSource",
+    ] {
+        let bytes = class_path.find_bytes(name).unwrap();
+        assert!(
+            bytes.is_some_and(|bytes| bytes.starts_with(&[0xCA, 0xFE, 0xBA, 0xBE])),
+            "{} not found",
+            name
+        );
+    }
+    assert!(class_path.resolve("java/lang/Missing").unwrap().is_none());
+    assert!(class_path.resolve("Missing").unwrap().is_none());
+
+    let names = class_path.class_names().unwrap();
+    assert!(names.iter().any(|name| name == "java/util/Map$Entry"));
+    assert!(names.iter().all(|name| !name.ends_with(".class")));
+}
+
+#[test]
+fn call_graph() {
+    let mut class_path = ClassPath::new();
+    class_path.push("../cs_vm/testdata").unwrap();
+    let (graph, errors) = CallGraph::of_class_path(&class_path).unwrap();
+    assert!(errors.is_empty());
+
+    let method = |class: &str, name: &str, descriptor: &str| MethodId {
+        class: class.to_string(),
+        name: name.to_string(),
+        descriptor: descriptor.to_string(),
+    };
+    let main = method("Loading", "main", "([Ljava/lang/String;)V");
+    let callees = graph
+        .callees(&main)
+        .map(|call| (call.callee.to_string(), call.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        callees,
+        vec![
+            ("Counter.<init>()V".to_string(), CallKind::Special),
+            ("Counter.add(I)V".to_string(), CallKind::Virtual),
+            ("Counter.add(I)V".to_string(), CallKind::Virtual),
+            (
+                "java/io/PrintStream.println(I)V".to_string(),
+                CallKind::Virtual
+            ),
+            (
+                "java/io/PrintStream.println(I)V".to_string(),
+                CallKind::Virtual
+            ),
+            ("Unused.<init>()V".to_string(), CallKind::Special),
+            ("Squares.square(I)I".to_string(), CallKind::Static),
+            (
+                "java/io/PrintStream.println(I)V".to_string(),
+                CallKind::Virtual
+            ),
+        ]
+    );
+
+    let adds = graph.callers("Counter", "add", None).collect::<Vec<_>>();
+    assert_eq!(adds.len(), 2);
+    assert!(adds.iter().all(|call| call.caller == main));
+    assert!(adds[0].offset < adds[1].offset);
+    // `Counter()` calls the constructor of its super class
+    assert!(graph
+        .callers("Base", "<init>", Some("()V"))
+        .any(|call| call.caller == method("Counter", "<init>", "()V")));
+    assert_eq!(graph.callers("Counter", "add", Some("(J)V")).count(), 0);
+    assert_eq!(graph.callers("java/lang/Runtime", "exec", None).count(), 0);
+
+    let mut class_path = ClassPath::new();
+    class_path.push("testdata/test.jar").unwrap();
+    // the broken class doesn't stop the other classes from being added
+    let (graph, errors) = CallGraph::of_class_path(&class_path).unwrap();
+    assert!(matches!(
+        &errors[..],
+        [ArchiveError::Parse { entry, .. }] if entry == "pkg/Broken.class"
+    ));
+    assert!(!graph.calls().is_empty());
+}
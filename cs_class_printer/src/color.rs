@@ -0,0 +1,45 @@
+//!
+//! ANSI colors for the text output
+//!
+
+use std::fmt::Display;
+
+/// Highlights parts of the output, or leaves them as they are if colors are disabled
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    color: bool,
+}
+
+impl Style {
+    pub fn new(color: bool) -> Self {
+        Self { color }
+    }
+
+    fn paint(self, code: &str, text: impl Display) -> String {
+        if self.color {
+            format!("\x1B[{}m{}\x1B[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Java keywords like `class` or `extends`, bold magenta
+    pub fn keyword(self, text: impl Display) -> String {
+        self.paint("1;35", text)
+    }
+
+    /// Class names and descriptors, cyan
+    pub fn type_(self, text: impl Display) -> String {
+        self.paint("36", text)
+    }
+
+    /// The kinds of constants in the constant pool, yellow
+    pub fn constant_kind(self, text: impl Display) -> String {
+        self.paint("33", text)
+    }
+
+    /// Access and module flags, blue
+    pub fn flag(self, text: impl Display) -> String {
+        self.paint("34", text)
+    }
+}
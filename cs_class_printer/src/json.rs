@@ -0,0 +1,62 @@
+//!
+//! Writes the class tree as JSON
+//!
+
+use crate::tree::Node;
+use std::io;
+use std::io::Write;
+
+/// Writes the node as indented JSON, followed by a newline
+pub fn write_json<W: Write>(mut w: W, node: &Node) -> Result<(), io::Error> {
+    write_node(&mut w, node, 0)?;
+    writeln!(w)
+}
+
+fn write_node<W: Write>(w: &mut W, node: &Node, depth: usize) -> Result<(), io::Error> {
+    let indent = "  ".repeat(depth + 1);
+    match node {
+        Node::Null => write!(w, "null"),
+        Node::Bool(value) => write!(w, "{}", value),
+        Node::Number(number) => write!(w, "{}", number),
+        Node::Str(str) => write!(w, "{}", json_string(str)),
+        Node::List(values) if values.is_empty() => write!(w, "[]"),
+        Node::List(values) => {
+            writeln!(w, "[")?;
+            for (i, value) in values.iter().enumerate() {
+                write!(w, "{}", indent)?;
+                write_node(w, value, depth + 1)?;
+                writeln!(w, "{}", if i + 1 < values.len() { "," } else { "" })?;
+            }
+            write!(w, "{}]", &indent[2..])
+        }
+        Node::Map(entries) if entries.is_empty() => write!(w, "{{}}"),
+        Node::Map(entries) => {
+            writeln!(w, "{{")?;
+            for (i, (key, value)) in entries.iter().enumerate() {
+                write!(w, "{}{}: ", indent, json_string(key))?;
+                write_node(w, value, depth + 1)?;
+                writeln!(w, "{}", if i + 1 < entries.len() { "," } else { "" })?;
+            }
+            write!(w, "{}}}", &indent[2..])
+        }
+    }
+}
+
+/// Quotes and escapes a string for JSON
+pub fn json_string(str: &str) -> String {
+    let mut json = String::with_capacity(str.len() + 2);
+    json.push('"');
+    for char in str.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
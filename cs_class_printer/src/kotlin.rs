@@ -0,0 +1,801 @@
+//!
+//! Decoding the `kotlin.Metadata` annotation that kotlinc adds to every class, for the `kotlin`
+//! feature
+//!
+
+use cs_parser::{
+    Annotation, AnnotationElementValue, AnnotationElementValueValue, AttributeInfoInner, ClassFile,
+    ConstantPool, CpInfoInner,
+};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KotlinErr(pub String);
+
+impl Display for KotlinErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not decode Kotlin metadata: {}", self.0)
+    }
+}
+
+impl std::error::Error for KotlinErr {}
+
+type Result<T> = std::result::Result<T, KotlinErr>;
+
+/// The decoded `kotlin.Metadata` of a class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KotlinMetadata {
+    /// What kotlinc generated the class for, like `class` or `file facade`
+    pub kind: &'static str,
+    /// The version of the metadata format, like `1.9.0`
+    pub version: String,
+    /// The declarations in Kotlin syntax, like `data class kotlin.Pair<out A, out B>`
+    pub declarations: Vec<String>,
+}
+
+/// The `kotlin.Metadata` annotation of the class, decoded. `None` if the class has none
+pub fn kotlin_metadata(class: &ClassFile) -> Option<Result<KotlinMetadata>> {
+    let cp = &class.constant_pool;
+    let annotation = class.attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::RuntimeVisibleAnnotations { annotations } => annotations
+            .iter()
+            .find(|annotation| annotation.type_index.get(cp) == "Lkotlin/Metadata;"),
+        _ => None,
+    })?;
+    Some(decode(annotation, cp))
+}
+
+fn decode(annotation: &Annotation, cp: &ConstantPool) -> Result<KotlinMetadata> {
+    let element = |name: &str| {
+        annotation
+            .element_value_pairs
+            .iter()
+            .find(|pair| pair.element_name_index.get(cp) == name)
+            .map(|pair| &pair.element_name_name)
+    };
+    let kind = match element("k") {
+        Some(value) => int(value, cp).ok_or_else(|| KotlinErr("Invalid kind".to_string()))?,
+        None => 1,
+    };
+    let version = element("mv")
+        .map(|value| array(value, |value| int(value, cp)))
+        .unwrap_or_default()
+        .iter()
+        .map(i32::to_string)
+        .collect::<Vec<_>>()
+        .join(".");
+    let d1 = element("d1")
+        .map(|value| array(value, |value| string(value, cp)))
+        .unwrap_or_default();
+    let d2 = element("d2")
+        .map(|value| array(value, |value| string(value, cp)))
+        .unwrap_or_default();
+
+    let (kind, declarations) = match kind {
+        1 => (
+            "class",
+            decode_data(&d1, &d2, |decoder, class| decoder.class(class))?,
+        ),
+        2 => (
+            "file facade",
+            decode_data(&d1, &d2, |decoder, package| decoder.package(package))?,
+        ),
+        // lambdas and other classes that only exist on the JVM
+        3 => ("synthetic class", Vec::new()),
+        4 => (
+            "multi-file class facade",
+            d1.iter()
+                .map(|part| format!("part {}", part.replace('/', ".")))
+                .collect(),
+        ),
+        5 => (
+            "multi-file class part",
+            decode_data(&d1, &d2, |decoder, package| decoder.package(package))?,
+        ),
+        kind => return Err(KotlinErr(format!("Unknown kind {}", kind))),
+    };
+    Ok(KotlinMetadata {
+        kind,
+        version,
+        declarations,
+    })
+}
+
+fn int(value: &AnnotationElementValue, cp: &ConstantPool) -> Option<i32> {
+    match &value.value {
+        AnnotationElementValueValue::ConstValueIndex { index } => match index.get(cp) {
+            CpInfoInner::Integer(int) => Some(int.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn string<'a>(value: &AnnotationElementValue, cp: &'a ConstantPool) -> Option<&'a str> {
+    match &value.value {
+        AnnotationElementValueValue::ConstValueIndex { index } => match index.get(cp) {
+            CpInfoInner::Utf8(utf8) => Some(&*utf8.bytes),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The elements of an array value that `element` accepts
+fn array<'a, T>(
+    value: &'a AnnotationElementValue,
+    element: impl Fn(&'a AnnotationElementValue) -> Option<T>,
+) -> Vec<T> {
+    match &value.value {
+        AnnotationElementValueValue::ArrayValue { values } => {
+            values.iter().filter_map(element).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Decodes the protobuf data in `d1`, which starts with the string table for the strings in `d2`
+/// and is followed by the message that `declarations` decodes
+fn decode_data(
+    d1: &[&str],
+    d2: &[&str],
+    declarations: impl FnOnce(&mut Decoder<'_>, &Message<'_>) -> Result<Vec<String>>,
+) -> Result<Vec<String>> {
+    let bytes = decode_bytes(d1)?;
+    let mut position = 0;
+    let length = varint(&bytes, &mut position)? as usize;
+    let table = bytes
+        .get(position..position.saturating_add(length))
+        .ok_or_else(|| KotlinErr("The string table is cut off".to_string()))?;
+    let table = Message::parse(table)?;
+    let message = Message::parse(&bytes[position + length..])?;
+    let mut decoder = Decoder::new(&table, d2, &message)?;
+    declarations(&mut decoder, &message)
+}
+
+/// The bytes of the `d1` strings. Since Kotlin 1.1 they start with a NUL and every char is a byte
+fn decode_bytes(d1: &[&str]) -> Result<Vec<u8>> {
+    if !d1.first().is_some_and(|first| first.starts_with('\0')) {
+        return Err(KotlinErr(
+            "Only the byte encoding of Kotlin 1.1 and later is supported".to_string(),
+        ));
+    }
+    d1.iter()
+        .flat_map(|string| string.chars())
+        .skip(1)
+        .map(|char| {
+            u8::try_from(char)
+                .map_err(|_| KotlinErr(format!("Invalid character {:?} in the data", char)))
+        })
+        .collect()
+}
+
+fn varint(bytes: &[u8], position: &mut usize) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| KotlinErr("Unexpected end of the data".to_string()))?;
+        *position += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(KotlinErr("Varint is longer than 64 bits".to_string()))
+}
+
+/// A protobuf message, with the fields in the order they appear in the data
+#[derive(Debug, Clone, Default)]
+struct Message<'a> {
+    fields: Vec<(u32, Field<'a>)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// A 32 or 64 bit value, which the metadata doesn't use
+    Fixed,
+}
+
+impl<'a> Message<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let mut fields = Vec::new();
+        let mut position = 0;
+        while position < bytes.len() {
+            let key = varint(bytes, &mut position)?;
+            let number = (key >> 3) as u32;
+            let field = match key & 7 {
+                0 => Field::Varint(varint(bytes, &mut position)?),
+                1 | 5 => {
+                    position += if key & 7 == 1 { 8 } else { 4 };
+                    Field::Fixed
+                }
+                2 => {
+                    let length = varint(bytes, &mut position)? as usize;
+                    let value = bytes
+                        .get(position..position.saturating_add(length))
+                        .ok_or_else(|| KotlinErr(format!("Field {} is cut off", number)))?;
+                    position += length;
+                    Field::Bytes(value)
+                }
+                wire_type => {
+                    return Err(KotlinErr(format!(
+                        "Unsupported wire type {} of field {}",
+                        wire_type, number
+                    )))
+                }
+            };
+            fields.push((number, field));
+        }
+        if position > bytes.len() {
+            return Err(KotlinErr("Unexpected end of the data".to_string()));
+        }
+        Ok(Self { fields })
+    }
+
+    fn values(&self, number: u32) -> impl Iterator<Item = Field<'a>> + '_ {
+        self.fields
+            .iter()
+            .filter(move |(field, _)| *field == number)
+            .map(|(_, value)| *value)
+    }
+
+    /// An `int32`, `bool` or enum field, the last value wins like in protobuf
+    fn int(&self, number: u32) -> Option<i32> {
+        self.values(number)
+            .filter_map(|value| match value {
+                Field::Varint(value) => Some(value as i32),
+                _ => None,
+            })
+            .last()
+    }
+
+    fn required(&self, number: u32, what: &str) -> Result<i32> {
+        self.int(number)
+            .ok_or_else(|| KotlinErr(format!("Missing {}", what)))
+    }
+
+    /// A repeated `int32` field, which can be packed
+    fn ints(&self, number: u32) -> Result<Vec<i32>> {
+        let mut ints = Vec::new();
+        for value in self.values(number) {
+            match value {
+                Field::Varint(value) => ints.push(value as i32),
+                Field::Bytes(bytes) => {
+                    let mut position = 0;
+                    while position < bytes.len() {
+                        ints.push(varint(bytes, &mut position)? as i32);
+                    }
+                }
+                Field::Fixed => {}
+            }
+        }
+        Ok(ints)
+    }
+
+    fn messages(&self, number: u32) -> Result<Vec<Message<'a>>> {
+        self.values(number)
+            .filter_map(|value| match value {
+                Field::Bytes(bytes) => Some(Message::parse(bytes)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn message(&self, number: u32) -> Result<Option<Message<'a>>> {
+        Ok(self.messages(number)?.pop())
+    }
+
+    fn string(&self, number: u32) -> Result<Option<&'a str>> {
+        match self.values(number).last() {
+            Some(Field::Bytes(bytes)) => std::str::from_utf8(bytes)
+                .map(Some)
+                .map_err(|_| KotlinErr(format!("Field {} is not UTF-8", number))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The strings that the string table records can refer to instead of storing them in `d2`
+const PREDEFINED_STRINGS: &[&str] = &[
+    "kotlin/Any",
+    "kotlin/Nothing",
+    "kotlin/Unit",
+    "kotlin/Throwable",
+    "kotlin/Number",
+    "kotlin/Byte",
+    "kotlin/Double",
+    "kotlin/Float",
+    "kotlin/Int",
+    "kotlin/Long",
+    "kotlin/Short",
+    "kotlin/Boolean",
+    "kotlin/Char",
+    "kotlin/CharSequence",
+    "kotlin/String",
+    "kotlin/Comparable",
+    "kotlin/Enum",
+    "kotlin/Array",
+    "kotlin/ByteArray",
+    "kotlin/DoubleArray",
+    "kotlin/FloatArray",
+    "kotlin/IntArray",
+    "kotlin/LongArray",
+    "kotlin/ShortArray",
+    "kotlin/BooleanArray",
+    "kotlin/CharArray",
+    "kotlin/Cloneable",
+    "kotlin/Annotation",
+    "kotlin/collections/Iterable",
+    "kotlin/collections/MutableIterable",
+    "kotlin/collections/Collection",
+    "kotlin/collections/MutableCollection",
+    "kotlin/collections/List",
+    "kotlin/collections/MutableList",
+    "kotlin/collections/Set",
+    "kotlin/collections/MutableSet",
+    "kotlin/collections/Map",
+    "kotlin/collections/MutableMap",
+    "kotlin/collections/Map.Entry",
+    "kotlin/collections/MutableMap.MutableEntry",
+    "kotlin/collections/Iterator",
+    "kotlin/collections/MutableIterator",
+    "kotlin/collections/ListIterator",
+    "kotlin/collections/MutableListIterator",
+];
+
+/// Decodes the declarations of a `Class` or `Package` message into Kotlin syntax
+struct Decoder<'a> {
+    /// The string table record of every index, a record with a `range` covers several
+    records: Vec<Message<'a>>,
+    d2: &'a [&'a str],
+    types: Vec<Message<'a>>,
+    /// The types in `types` from this index on are nullable
+    first_nullable: Option<usize>,
+    /// The names of the type parameters in scope by their id
+    type_parameters: HashMap<i32, String>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(table: &Message<'a>, d2: &'a [&'a str], message: &Message<'a>) -> Result<Self> {
+        let mut records = Vec::new();
+        for record in table.messages(1)? {
+            for _ in 0..record.int(1).unwrap_or(1) {
+                records.push(record.clone());
+            }
+        }
+        let type_table = message.message(30)?.unwrap_or_default();
+        Ok(Self {
+            records,
+            d2,
+            types: type_table.messages(1)?,
+            first_nullable: type_table
+                .int(2)
+                .and_then(|first| usize::try_from(first).ok()),
+            type_parameters: HashMap::new(),
+        })
+    }
+
+    /// The string at the index, which the record of the index can take from elsewhere and change
+    fn string(&self, index: i32) -> Result<String> {
+        let missing = || KotlinErr(format!("Missing string {}", index));
+        let index = usize::try_from(index).map_err(|_| missing())?;
+        let Some(record) = self.records.get(index) else {
+            return self
+                .d2
+                .get(index)
+                .map(|s| s.to_string())
+                .ok_or_else(missing);
+        };
+        let predefined = record
+            .int(2)
+            .and_then(|predefined| PREDEFINED_STRINGS.get(usize::try_from(predefined).ok()?))
+            .copied();
+        let mut string = match (record.string(6)?, predefined) {
+            (Some(string), _) | (None, Some(string)) => string.to_string(),
+            (None, None) => self.d2.get(index).ok_or_else(missing)?.to_string(),
+        };
+        if let [begin, end, ..] = record.ints(4)?[..] {
+            if 0 <= begin && begin <= end {
+                string = string
+                    .chars()
+                    .skip(begin as usize)
+                    .take((end - begin) as usize)
+                    .collect();
+            }
+        }
+        if let [from, to, ..] = record.ints(5)?[..] {
+            if let (Some(from), Some(to)) = (char::from_u32(from as u32), char::from_u32(to as u32))
+            {
+                string = string.replace(from, &to.to_string());
+            }
+        }
+        match record.int(3) {
+            // an internal name to a class name, `a/B$C` to `a/B.C`
+            Some(1) => string = string.replace('$', "."),
+            // a descriptor to a class name, `La/B$C;` to `a/B.C`
+            Some(2) => {
+                let mut chars = string.chars();
+                chars.next();
+                chars.next_back();
+                string = chars.as_str().replace('$', ".");
+            }
+            _ => {}
+        }
+        Ok(string)
+    }
+
+    /// A class name with dots, like `kotlin.collections.Map.Entry`
+    fn class_name(&self, index: i32) -> Result<String> {
+        Ok(self
+            .string(index)?
+            .trim_start_matches('.')
+            .replace('/', "."))
+    }
+
+    fn type_(&self, type_: &Message<'_>) -> Result<String> {
+        let mut name = if let Some(class) = type_.int(6) {
+            self.class_name(class)?
+        } else if let Some(id) = type_.int(7) {
+            self.type_parameters
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("T{}", id))
+        } else if let Some(name) = type_.int(9) {
+            self.string(name)?
+        } else if let Some(alias) = type_.int(12) {
+            self.class_name(alias)?
+        } else {
+            return Err(KotlinErr(
+                "Type without a class or type parameter".to_string(),
+            ));
+        };
+        let mut arguments = Vec::new();
+        for argument in type_.messages(2)? {
+            let projection = match argument.int(1).unwrap_or(2) {
+                0 => "in ",
+                1 => "out ",
+                3 => {
+                    arguments.push("*".to_string());
+                    continue;
+                }
+                _ => "",
+            };
+            let argument = self
+                .type_field(&argument, 2, 3)?
+                .ok_or_else(|| KotlinErr("Type argument without a type".to_string()))?;
+            arguments.push(format!("{}{}", projection, argument));
+        }
+        if !arguments.is_empty() {
+            name = format!("{}<{}>", name, arguments.join(", "));
+        }
+        if type_.int(1).unwrap_or(0) & 1 != 0 {
+            name.insert_str(0, "suspend ");
+        }
+        if type_.int(3) == Some(1) {
+            name.push('?');
+        }
+        Ok(name)
+    }
+
+    /// The type in the field `field`, or the one in the type table at the index in the field `id`
+    fn type_field(&self, message: &Message<'_>, field: u32, id: u32) -> Result<Option<String>> {
+        if let Some(type_) = message.message(field)? {
+            return self.type_(&type_).map(Some);
+        }
+        message.int(id).map(|id| self.table_type(id)).transpose()
+    }
+
+    fn table_type(&self, id: i32) -> Result<String> {
+        let index = usize::try_from(id).unwrap_or(usize::MAX);
+        let type_ = self
+            .types
+            .get(index)
+            .ok_or_else(|| KotlinErr(format!("Missing type {}", id)))?;
+        let mut name = self.type_(type_)?;
+        if self.first_nullable.is_some_and(|first| index >= first) && !name.ends_with('?') {
+            name.push('?');
+        }
+        Ok(name)
+    }
+
+    /// The type parameters like `<out T : kotlin.Number>`, which are in scope from then on
+    fn type_parameters(&mut self, parameters: &[Message<'_>]) -> Result<String> {
+        let mut names = Vec::new();
+        for parameter in parameters {
+            let name = self.string(parameter.required(2, "type parameter name")?)?;
+            let id = parameter.required(1, "type parameter id")?;
+            self.type_parameters.insert(id, name.clone());
+            names.push(name);
+        }
+        // the bounds can refer to all of them
+        let mut declarations = Vec::new();
+        for (parameter, name) in parameters.iter().zip(names) {
+            let mut declaration = String::new();
+            if parameter.int(3) == Some(1) {
+                declaration.push_str("reified ");
+            }
+            match parameter.int(4).unwrap_or(2) {
+                0 => declaration.push_str("in "),
+                1 => declaration.push_str("out "),
+                _ => {}
+            }
+            declaration.push_str(&name);
+            let mut bounds = Vec::new();
+            for bound in parameter.messages(5)? {
+                bounds.push(self.type_(&bound)?);
+            }
+            for bound in parameter.ints(6)? {
+                bounds.push(self.table_type(bound)?);
+            }
+            if !bounds.is_empty() {
+                declaration = format!("{} : {}", declaration, bounds.join(" & "));
+            }
+            declarations.push(declaration);
+        }
+        Ok(if declarations.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", declarations.join(", "))
+        })
+    }
+
+    fn class(&mut self, class: &Message<'_>) -> Result<Vec<String>> {
+        let flags = class.int(1).unwrap_or(6);
+        let name = self.class_name(class.required(3, "class name")?)?;
+        let kind = match (flags >> 6) & 7 {
+            1 if flag(flags, 14) => "fun interface",
+            1 => "interface",
+            2 => "enum class",
+            3 => "enum entry",
+            4 => "annotation class",
+            5 => "object",
+            6 => "companion object",
+            _ => "class",
+        };
+        let mut declaration = visibility(flags).to_string();
+        // interfaces are always abstract
+        if !(kind.ends_with("interface") && modality(flags) == "abstract ") {
+            declaration.push_str(modality(flags));
+        }
+        for (bit, modifier) in [
+            (12, "expect "),
+            (11, "external "),
+            (9, "inner "),
+            (10, "data "),
+            (13, "value "),
+        ] {
+            if flag(flags, bit) {
+                declaration.push_str(modifier);
+            }
+        }
+        let type_parameters = self.type_parameters(&class.messages(5)?)?;
+        declaration = format!("{}{} {}{}", declaration, kind, name, type_parameters);
+        let mut supertypes = Vec::new();
+        for supertype in class.messages(6)? {
+            supertypes.push(self.type_(&supertype)?);
+        }
+        for supertype in class.ints(2)? {
+            supertypes.push(self.table_type(supertype)?);
+        }
+        supertypes.retain(|supertype| supertype != "kotlin.Any");
+        if !supertypes.is_empty() {
+            declaration = format!("{} : {}", declaration, supertypes.join(", "));
+        }
+
+        let mut declarations = vec![declaration];
+        if let Some(companion) = class.int(4) {
+            declarations.push(format!("companion object {}", self.string(companion)?));
+        }
+        for entry in class.messages(13)? {
+            let name = self.string(entry.required(1, "enum entry name")?)?;
+            declarations.push(format!("enum entry {}", name));
+        }
+        for constructor in class.messages(8)? {
+            let flags = constructor.int(1).unwrap_or(6);
+            let parameters = self.value_parameters(&constructor.messages(2)?)?;
+            declarations.push(format!("{}constructor({})", visibility(flags), parameters));
+        }
+        self.members(class, 10, 9, 11, &mut declarations)?;
+        for nested in class.ints(7)? {
+            declarations.push(format!("nested class {}", self.string(nested)?));
+        }
+        for subclass in class.ints(16)? {
+            declarations.push(format!("sealed subclass {}", self.class_name(subclass)?));
+        }
+        Ok(declarations)
+    }
+
+    fn package(&mut self, package: &Message<'_>) -> Result<Vec<String>> {
+        let mut declarations = Vec::new();
+        self.members(package, 4, 3, 5, &mut declarations)?;
+        Ok(declarations)
+    }
+
+    /// The properties, functions and type aliases in the fields with these numbers
+    fn members(
+        &mut self,
+        message: &Message<'_>,
+        properties: u32,
+        functions: u32,
+        type_aliases: u32,
+        declarations: &mut Vec<String>,
+    ) -> Result<()> {
+        for property in message.messages(properties)? {
+            declarations.push(self.scoped(|decoder| decoder.property(&property))?);
+        }
+        for function in message.messages(functions)? {
+            declarations.push(self.scoped(|decoder| decoder.function(&function))?);
+        }
+        for alias in message.messages(type_aliases)? {
+            declarations.push(self.scoped(|decoder| decoder.type_alias(&alias))?);
+        }
+        Ok(())
+    }
+
+    /// Decodes a declaration whose type parameters are only in scope in the declaration
+    fn scoped(&mut self, decode: impl FnOnce(&mut Self) -> Result<String>) -> Result<String> {
+        let outer = self.type_parameters.clone();
+        let declaration = decode(self);
+        self.type_parameters = outer;
+        declaration
+    }
+
+    fn property(&mut self, property: &Message<'_>) -> Result<String> {
+        let flags = property.int(11).unwrap_or(518);
+        let name = self.string(property.required(2, "property name")?)?;
+        let mut declaration = format!("{}{}", visibility(flags), modality(flags));
+        for (bit, modifier) in [
+            (16, "expect "),
+            (14, "external "),
+            (11, "const "),
+            (12, "lateinit "),
+        ] {
+            if flag(flags, bit) {
+                declaration.push_str(modifier);
+            }
+        }
+        declaration.push_str(if flag(flags, 8) { "var " } else { "val " });
+        let type_parameters = self.type_parameters(&property.messages(4)?)?;
+        if !type_parameters.is_empty() {
+            declaration = format!("{}{} ", declaration, type_parameters);
+        }
+        if let Some(receiver) = self.type_field(property, 5, 10)? {
+            declaration = format!("{}{}.", declaration, receiver);
+        }
+        let type_ = self
+            .type_field(property, 3, 9)?
+            .ok_or_else(|| KotlinErr(format!("Property {} without a type", name)))?;
+        Ok(format!(
+            "{}{}: {}{}",
+            declaration,
+            name,
+            type_,
+            member_kind(flags)
+        ))
+    }
+
+    fn function(&mut self, function: &Message<'_>) -> Result<String> {
+        let flags = function.int(9).unwrap_or(6);
+        let name = self.string(function.required(2, "function name")?)?;
+        let mut declaration = format!("{}{}", visibility(flags), modality(flags));
+        for (bit, modifier) in [
+            (14, "expect "),
+            (12, "external "),
+            (11, "tailrec "),
+            (10, "inline "),
+            (9, "infix "),
+            (8, "operator "),
+            (13, "suspend "),
+        ] {
+            if flag(flags, bit) {
+                declaration.push_str(modifier);
+            }
+        }
+        declaration.push_str("fun ");
+        let type_parameters = self.type_parameters(&function.messages(4)?)?;
+        if !type_parameters.is_empty() {
+            declaration = format!("{}{} ", declaration, type_parameters);
+        }
+        if let Some(receiver) = self.type_field(function, 5, 8)? {
+            declaration = format!("{}{}.", declaration, receiver);
+        }
+        let parameters = self.value_parameters(&function.messages(6)?)?;
+        let return_type = self
+            .type_field(function, 3, 7)?
+            .ok_or_else(|| KotlinErr(format!("Function {} without a return type", name)))?;
+        Ok(format!(
+            "{}{}({}): {}{}",
+            declaration,
+            name,
+            parameters,
+            return_type,
+            member_kind(flags)
+        ))
+    }
+
+    fn type_alias(&mut self, alias: &Message<'_>) -> Result<String> {
+        let flags = alias.int(1).unwrap_or(6);
+        let name = self.string(alias.required(2, "type alias name")?)?;
+        let type_parameters = self.type_parameters(&alias.messages(3)?)?;
+        let type_ = self
+            .type_field(alias, 4, 5)?
+            .ok_or_else(|| KotlinErr(format!("Type alias {} without a type", name)))?;
+        Ok(format!(
+            "{}typealias {}{} = {}",
+            visibility(flags),
+            name,
+            type_parameters,
+            type_
+        ))
+    }
+
+    fn value_parameters(&self, parameters: &[Message<'_>]) -> Result<String> {
+        let mut declarations = Vec::new();
+        for parameter in parameters {
+            let flags = parameter.int(1).unwrap_or(0);
+            let name = self.string(parameter.required(2, "parameter name")?)?;
+            let mut declaration = String::new();
+            if flag(flags, 2) {
+                declaration.push_str("crossinline ");
+            }
+            if flag(flags, 3) {
+                declaration.push_str("noinline ");
+            }
+            let type_ = match self.type_field(parameter, 4, 6)? {
+                Some(element) => {
+                    declaration.push_str("vararg ");
+                    element
+                }
+                None => self
+                    .type_field(parameter, 3, 5)?
+                    .ok_or_else(|| KotlinErr(format!("Parameter {} without a type", name)))?,
+            };
+            declaration = format!("{}{}: {}", declaration, name, type_);
+            // the default value itself is only in the code
+            if flag(flags, 1) {
+                declaration.push_str(" = ...");
+            }
+            declarations.push(declaration);
+        }
+        Ok(declarations.join(", "))
+    }
+}
+
+fn flag(flags: i32, bit: u32) -> bool {
+    flags & (1 << bit) != 0
+}
+
+/// The visibility in bits 1 to 3 of the flags, nothing for public
+fn visibility(flags: i32) -> &'static str {
+    match (flags >> 1) & 7 {
+        0 => "internal ",
+        1 | 4 => "private ",
+        2 => "protected ",
+        5 => "local ",
+        _ => "",
+    }
+}
+
+/// The modality in bits 4 and 5 of the flags, nothing for final
+fn modality(flags: i32) -> &'static str {
+    match (flags >> 4) & 3 {
+        1 => "open ",
+        2 => "abstract ",
+        3 => "sealed ",
+        _ => "",
+    }
+}
+
+/// A comment for members that kotlinc generated or delegates, from bits 6 and 7 of the flags
+fn member_kind(flags: i32) -> &'static str {
+    match (flags >> 6) & 3 {
+        1 => " // fake override",
+        2 => " // delegation",
+        3 => " // synthesized",
+        _ => "",
+    }
+}
@@ -1,13 +1,97 @@
+use crate::json::write_json;
+use crate::tree::class_tree;
 use crate::ui::display_class;
+use crate::yaml::write_yaml;
 use cs_parser::ClassFile;
 
+mod color;
+mod json;
+#[cfg(feature = "kotlin")]
+mod kotlin;
+#[cfg(test)]
+mod test;
+mod tree;
 mod ui;
+mod yaml;
+
+pub use json::json_string;
+#[cfg(feature = "kotlin")]
+pub use kotlin::{kotlin_metadata, KotlinErr, KotlinMetadata};
+
+/// Options for `print_with`
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptions {
+    /// Highlights keywords, types and flags with ANSI colors, which should only be used if the
+    /// output is a terminal
+    pub color: bool,
+    pub sections: Sections,
+}
+
+/// The parts of the class that are printed after the class declaration. The default is a
+/// summary of the attributes and members
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sections {
+    /// Every entry of the constant pool with its index
+    pub constants: bool,
+    /// The names of the class attributes and the content of module, record and bootstrap method
+    /// attributes and of type annotations
+    pub attributes: bool,
+    pub fields: bool,
+    pub methods: bool,
+    /// The disassembled code below each method, only if `methods` are printed
+    pub code: bool,
+}
+
+impl Sections {
+    /// No sections, only the class declaration
+    pub const NONE: Self = Self {
+        constants: false,
+        attributes: false,
+        fields: false,
+        methods: false,
+        code: false,
+    };
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Self {
+            attributes: true,
+            fields: true,
+            methods: true,
+            ..Self::NONE
+        }
+    }
+}
 
 /// Pretty-prints a class file
 pub fn print(class_file: &ClassFile) {
+    print_with(class_file, &PrintOptions::default())
+}
+
+/// Pretty-prints a class file with the options
+pub fn print_with(class_file: &ClassFile, options: &PrintOptions) {
+    let stdout = std::io::stdout();
+
+    if let Err(why) = display_class(stdout.lock(), class_file, options) {
+        eprintln!("{}", why);
+    }
+}
+
+/// Prints the whole class file, including the constant pool and all attributes, as JSON
+pub fn print_json(class_file: &ClassFile) {
+    let stdout = std::io::stdout();
+
+    if let Err(why) = write_json(stdout.lock(), &class_tree(class_file)) {
+        eprintln!("{}", why);
+    }
+}
+
+/// Prints the same structure as `print_json` as YAML, which is easier to compare between builds
+pub fn print_yaml(class_file: &ClassFile) {
     let stdout = std::io::stdout();
 
-    if let Err(why) = display_class(stdout.lock(), class_file) {
+    if let Err(why) = write_yaml(stdout.lock(), &class_tree(class_file)) {
         eprintln!("{}", why);
     }
 }
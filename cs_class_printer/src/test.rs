@@ -0,0 +1,500 @@
+use crate::color::Style;
+use crate::json::{json_string, write_json};
+use crate::tree::Node;
+use crate::ui::display_class;
+use crate::yaml::write_yaml;
+use crate::{PrintOptions, Sections};
+use cs_parser::{ClassAccessFlag, ClassFile, ClassFileBuilder, FieldAccessFlag, MethodAccessFlag};
+
+fn json(node: &Node) -> String {
+    let mut out = Vec::new();
+    write_json(&mut out, node).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn yaml(node: &Node) -> String {
+    let mut out = Vec::new();
+    write_yaml(&mut out, node).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The output of `info` with only the class declaration and the members
+fn printed(class: &ClassFile, color: bool) -> String {
+    let options = PrintOptions {
+        color,
+        sections: Sections {
+            fields: true,
+            methods: true,
+            ..Sections::NONE
+        },
+    };
+    let mut out = Vec::new();
+    display_class(&mut out, class, &options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn json_escaping() {
+    assert_eq!(json_string("java/lang/Object"), r#""java/lang/Object""#);
+    assert_eq!(json_string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+    // DEL is not a control character in JSON
+    assert_eq!(
+        json_string("\n\r\t\0\u{1}\u{1f}\u{7f}"),
+        "\"\\n\\r\\t\\u0000\\u0001\\u001f\u{7f}\""
+    );
+    // non-BMP characters are valid in JSON as they are
+    assert_eq!(json_string("\u{1F980} é"), "\"\u{1F980} é\"");
+
+    let node = Node::Map(vec![
+        ("name".to_string(), Node::Str("a\"b".to_string())),
+        ("empty".to_string(), Node::List(Vec::new())),
+        (
+            "values".to_string(),
+            Node::List(vec![
+                Node::Null,
+                Node::Bool(true),
+                Node::Number("1.5".to_string()),
+            ]),
+        ),
+    ]);
+    assert_eq!(
+        json(&node),
+        r#"{
+  "name": "a\"b",
+  "empty": [],
+  "values": [
+    null,
+    true,
+    1.5
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn yaml_escaping() {
+    let node = Node::Map(
+        [
+            "java/lang/Object",
+            "main([Ljava/lang/String;)V",
+            "<init>()V",
+            "",
+            "true",
+            "Null",
+            "say \"hi\"",
+            "a: b",
+            "- item",
+            "\0\n",
+            "\u{1F980}",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (format!("k{}", i), Node::Str(value.to_string())))
+        .collect(),
+    );
+    assert_eq!(
+        yaml(&node),
+        r#"k0: java/lang/Object
+k1: main([Ljava/lang/String;)V
+k2: "<init>()V"
+k3: ""
+k4: "true"
+k5: "Null"
+k6: "say \"hi\""
+k7: "a: b"
+k8: "- item"
+k9: "\u0000\n"
+k10: "🦀"
+"#
+    );
+
+    let node = Node::List(vec![
+        Node::Map(vec![
+            ("name".to_string(), Node::Str("x".to_string())),
+            ("flags".to_string(), Node::List(Vec::new())),
+        ]),
+        Node::Map(Vec::new()),
+    ]);
+    assert_eq!(yaml(&node), "- name: x\n  flags: []\n- {}\n");
+    assert_eq!(
+        yaml(&Node::Str("key: value".to_string())),
+        "\"key: value\"\n"
+    );
+}
+
+#[test]
+fn class_modifiers() {
+    let declaration = |flags: &[ClassAccessFlag]| {
+        let mut class = ClassFileBuilder::new("pkg/Kind").build();
+        class.access_flags = flags.iter().fold(0.into(), |flags, flag| flags | *flag);
+        printed(&class, false).lines().nth(2).unwrap().to_string()
+    };
+    use ClassAccessFlag::*;
+    assert_eq!(
+        declaration(&[Public, Super]),
+        "public class pkg/Kind extends java/lang/Object {"
+    );
+    assert_eq!(
+        declaration(&[Public, Final, Super]),
+        "public final class pkg/Kind extends java/lang/Object {"
+    );
+    assert_eq!(
+        declaration(&[Abstract, Super]),
+        "abstract class pkg/Kind extends java/lang/Object {"
+    );
+    // interfaces are always abstract
+    assert_eq!(
+        declaration(&[Public, Interface, Abstract]),
+        "public interface pkg/Kind extends java/lang/Object {"
+    );
+    assert_eq!(
+        declaration(&[Public, Final, Super, Enum]),
+        "public final enum pkg/Kind extends java/lang/Object {"
+    );
+    assert_eq!(
+        declaration(&[Public, Interface, Abstract, Annotation]),
+        "public @interface pkg/Kind extends java/lang/Object {"
+    );
+}
+
+#[test]
+fn member_modifiers() {
+    let class = ClassFileBuilder::new("pkg/Members")
+        .add_field(
+            FieldAccessFlag::PRIVATE | FieldAccessFlag::STATIC | FieldAccessFlag::FINAL,
+            "CONSTANT",
+            "I",
+        )
+        .add_field(
+            FieldAccessFlag::PROTECTED | FieldAccessFlag::VOLATILE | FieldAccessFlag::TRANSIENT,
+            "state",
+            "J",
+        )
+        .add_method(
+            MethodAccessFlag::PUBLIC | MethodAccessFlag::ABSTRACT,
+            "run",
+            "()V",
+            None,
+        )
+        .add_method(
+            MethodAccessFlag::PUBLIC | MethodAccessFlag::STATIC | MethodAccessFlag::NATIVE,
+            "now",
+            "()J",
+            None,
+        )
+        .build();
+    let printed = printed(&class, false);
+    assert!(
+        printed.contains("  private static final I CONSTANT\n"),
+        "{}",
+        printed
+    );
+    assert!(
+        printed.contains("  protected transient volatile J state\n"),
+        "{}",
+        printed
+    );
+    assert!(
+        printed.contains("  public abstract ()V run\n"),
+        "{}",
+        printed
+    );
+    assert!(
+        printed.contains("  public static native ()J now\n"),
+        "{}",
+        printed
+    );
+}
+
+#[test]
+fn colors() {
+    assert_eq!(Style::new(false).keyword("class"), "class");
+    assert_eq!(Style::new(true).keyword("class"), "\x1B[1;35mclass\x1B[0m");
+    assert_eq!(Style::new(true).type_("I"), "\x1B[36mI\x1B[0m");
+
+    let class = ClassFileBuilder::new("pkg/Colored").build();
+    assert!(!printed(&class, false).contains('\x1B'));
+    assert!(printed(&class, true).contains("\x1B[1;35mclass\x1B[0m \x1B[36mpkg/Colored\x1B[0m"));
+}
+
+#[cfg(feature = "kotlin")]
+mod kotlin {
+    use crate::ui::display_class;
+    use crate::{kotlin_metadata, KotlinErr, KotlinMetadata, PrintOptions};
+    use cs_parser::{
+        parse_class_file, write_class_file, Annotation, AnnotationElementValue,
+        AnnotationElementValuePair, AnnotationElementValueValue, AttributeInfo, AttributeInfoInner,
+        ClassFile, ClassFileBuilder, ConstantPoolBuilder, FromPool,
+    };
+
+    /// Encodes protobuf fields, the varints and messages the metadata consists of
+    #[derive(Default)]
+    struct Proto(Vec<u8>);
+
+    impl Proto {
+        fn varint(bytes: &mut Vec<u8>, mut value: u64) {
+            while value >= 0x80 {
+                bytes.push(value as u8 | 0x80);
+                value >>= 7;
+            }
+            bytes.push(value as u8);
+        }
+
+        fn int(mut self, field: u64, value: u64) -> Self {
+            Self::varint(&mut self.0, field << 3);
+            Self::varint(&mut self.0, value);
+            self
+        }
+
+        fn message(mut self, field: u64, message: Proto) -> Self {
+            Self::varint(&mut self.0, field << 3 | 2);
+            Self::varint(&mut self.0, message.0.len() as u64);
+            self.0.extend(message.0);
+            self
+        }
+    }
+
+    fn class_type(name: u64) -> Proto {
+        Proto::default().int(6, name)
+    }
+
+    fn type_parameter_type(id: u64) -> Proto {
+        Proto::default().int(7, id)
+    }
+
+    /// The value `mv`, `d1` or `d2` of a `kotlin.Metadata` annotation
+    fn element(
+        pool: &mut ConstantPoolBuilder,
+        name: &str,
+        tag: u8,
+        values: Vec<FromPool<cs_parser::CpInfoInner>>,
+    ) -> AnnotationElementValuePair {
+        AnnotationElementValuePair {
+            element_name_index: pool.utf8(name),
+            element_name_name: AnnotationElementValue {
+                tag: b'[',
+                value: AnnotationElementValueValue::ArrayValue {
+                    values: values
+                        .into_iter()
+                        .map(|index| AnnotationElementValue {
+                            tag,
+                            value: AnnotationElementValueValue::ConstValueIndex { index },
+                        })
+                        .collect(),
+                },
+            },
+        }
+    }
+
+    /// A class with a `kotlin.Metadata` annotation of the kind with the data in `d1` and `d2`
+    fn kotlin_class(kind: i32, d1: &[u8], d2: &[&str]) -> ClassFile {
+        let mut builder = ClassFileBuilder::new("pkg/Pair");
+        let pool = builder.constant_pool();
+        // kotlinc splits the data into strings of at most 65535 bytes
+        let (first, second) = d1.split_at(d1.len() / 2);
+        let d1 = [first, second]
+            .iter()
+            .enumerate()
+            .map(|(index, bytes)| {
+                let marker = if index == 0 { "\0" } else { "" };
+                let string = bytes
+                    .iter()
+                    .map(|&byte| char::from(byte))
+                    .collect::<String>();
+                FromPool::from(pool.utf8(&format!("{}{}", marker, string)).inner())
+            })
+            .collect();
+        let d2 = d2
+            .iter()
+            .map(|string| FromPool::from(pool.utf8(string).inner()))
+            .collect();
+        let version = [1, 9, 0]
+            .into_iter()
+            .map(|part| FromPool::from(pool.integer(part).inner()))
+            .collect();
+        let annotation = Annotation {
+            type_index: pool.utf8("Lkotlin/Metadata;"),
+            num_element_value_pairs: 4,
+            element_value_pairs: vec![
+                AnnotationElementValuePair {
+                    element_name_index: pool.utf8("k"),
+                    element_name_name: AnnotationElementValue {
+                        tag: b'I',
+                        value: AnnotationElementValueValue::ConstValueIndex {
+                            index: FromPool::from(pool.integer(kind).inner()),
+                        },
+                    },
+                },
+                element(pool, "mv", b'I', version),
+                element(pool, "d1", b's', d1),
+                element(pool, "d2", b's', d2),
+            ],
+        };
+        let name = pool.utf8("RuntimeVisibleAnnotations");
+        let mut class = builder.build();
+        class.attributes.push(AttributeInfo {
+            attribute_name_index: name,
+            attribute_length: 0,
+            inner: AttributeInfoInner::RuntimeVisibleAnnotations {
+                annotations: vec![annotation],
+            },
+            original: None,
+        });
+        // the data goes through modified UTF-8, which encodes the NUL in two bytes
+        parse_class_file(&write_class_file(&class).unwrap()).unwrap()
+    }
+
+    fn metadata(kind: i32, d1: &[u8], d2: &[&str]) -> Option<Result<KotlinMetadata, KotlinErr>> {
+        kotlin_metadata(&kotlin_class(kind, d1, d2))
+    }
+
+    /// The strings in `d2`, the string table adds the predefined `INT` and `STRING` after them
+    const STRINGS: &[&str] = &[
+        "pkg/Pair",
+        "A",
+        "first",
+        "second",
+        "fetch",
+        "id",
+        "java/io/Serializable",
+        "component1",
+    ];
+    const INT: u64 = 8;
+    const STRING: u64 = 9;
+
+    fn string_table() -> Proto {
+        Proto::default()
+            .message(1, Proto::default().int(1, 8))
+            .message(1, Proto::default().int(2, 8))
+            .message(1, Proto::default().int(2, 14))
+    }
+
+    fn with_string_table(message: Proto) -> Vec<u8> {
+        let table = string_table().0;
+        let mut bytes = Vec::new();
+        Proto::varint(&mut bytes, table.len() as u64);
+        bytes.extend(table);
+        bytes.extend(message.0);
+        bytes
+    }
+
+    #[test]
+    fn data_class() {
+        let public = 3 << 1;
+        let nullable_string = class_type(STRING).int(3, 1);
+        let class = Proto::default()
+            .int(1, public | 1 << 10)
+            .int(3, 0)
+            .message(5, Proto::default().int(1, 0).int(2, 1).int(4, 1))
+            .message(6, class_type(6))
+            .message(
+                8,
+                Proto::default()
+                    .message(
+                        2,
+                        Proto::default()
+                            .int(2, 2)
+                            .message(3, type_parameter_type(0)),
+                    )
+                    .message(
+                        2,
+                        Proto::default()
+                            .int(1, 1 << 1)
+                            .int(2, 3)
+                            .message(3, nullable_string),
+                    ),
+            )
+            .message(
+                10,
+                Proto::default()
+                    .int(11, public | 1 << 9)
+                    .int(2, 2)
+                    .message(3, type_parameter_type(0)),
+            )
+            // the type of `second` is in the type table, where it is nullable
+            .message(
+                10,
+                Proto::default()
+                    .int(11, public | 1 << 8 | 1 << 9 | 1 << 10)
+                    .int(2, 3)
+                    .int(9, 0),
+            )
+            .message(
+                9,
+                Proto::default()
+                    .int(9, public | 1 << 13)
+                    .int(2, 4)
+                    .message(6, Proto::default().int(2, 5).message(3, class_type(INT)))
+                    .message(3, class_type(STRING).int(3, 1)),
+            )
+            .message(
+                9,
+                Proto::default()
+                    .int(9, public | 3 << 6 | 1 << 8)
+                    .int(2, 7)
+                    .message(3, type_parameter_type(0)),
+            )
+            .message(
+                30,
+                Proto::default().message(1, class_type(STRING)).int(2, 0),
+            );
+
+        let class = kotlin_class(1, &with_string_table(class), STRINGS);
+        let metadata = kotlin_metadata(&class).unwrap().unwrap();
+        assert_eq!(metadata.kind, "class");
+        assert_eq!(metadata.version, "1.9.0");
+        assert_eq!(
+            metadata.declarations,
+            [
+                "data class pkg.Pair<out A> : java.io.Serializable",
+                "constructor(first: A, second: kotlin.String? = ...)",
+                "val first: A",
+                "var second: kotlin.String?",
+                "suspend fun fetch(id: kotlin.Int): kotlin.String?",
+                "operator fun component1(): A // synthesized",
+            ]
+        );
+
+        let mut printed = Vec::new();
+        display_class(&mut printed, &class, &PrintOptions::default()).unwrap();
+        let printed = String::from_utf8(printed).unwrap();
+        assert!(printed.contains(
+            " Kotlin class (metadata 1.9.0):\n  data class pkg.Pair<out A> : java.io.Serializable\n"
+        ));
+    }
+
+    #[test]
+    fn file_facade() {
+        let package = Proto::default().message(
+            3,
+            Proto::default()
+                .int(9, 6 | 1 << 10)
+                .message(4, Proto::default().int(1, 0).int(2, 1).int(3, 1))
+                .int(2, 4)
+                .message(5, type_parameter_type(0))
+                .message(3, class_type(INT)),
+        );
+        let metadata = metadata(2, &with_string_table(package), STRINGS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.kind, "file facade");
+        assert_eq!(
+            metadata.declarations,
+            ["inline fun <reified A> A.fetch(): kotlin.Int"]
+        );
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let class = ClassFileBuilder::new("pkg/Pair").build();
+        assert_eq!(kotlin_metadata(&class), None);
+
+        let mut cut_off = with_string_table(Proto::default().message(9, Proto::default()));
+        cut_off.pop();
+        assert_eq!(
+            metadata(1, &cut_off, STRINGS),
+            Some(Err(KotlinErr("Unexpected end of the data".to_string())))
+        );
+    }
+}
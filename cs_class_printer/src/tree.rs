@@ -0,0 +1,787 @@
+//!
+//! The whole class as a tree of plain values, for the structured output formats
+//!
+
+use crate::ui::constant_kind;
+use cs_parser::cp_info::{self, FromPool, MethodHandleIndex};
+use cs_parser::{
+    decode_code, display_instruction, display_method_handle, Annotation, AnnotationElementValue,
+    AnnotationElementValueValue, AttributeInfo, AttributeInfoInner, ClassFile, ConstantPool,
+    CpInfo, CpInfoInner, TypeAnnotation,
+};
+
+/// A value in the tree. Maps keep the order of their keys
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    /// A number, already formatted as it should appear in the output
+    Number(String),
+    Str(String),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+impl From<bool> for Node {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+macro_rules! from_int {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Node {
+                fn from(value: $int) -> Self {
+                    Self::Number(value.to_string())
+                }
+            }
+        )*
+    };
+}
+
+from_int!(u8, u16, u32, i8, i16, i32, i64, usize);
+
+impl From<f32> for Node {
+    fn from(value: f32) -> Self {
+        if value.is_finite() {
+            Self::Number(format!("{:?}", value))
+        } else {
+            Self::Str(value.to_string())
+        }
+    }
+}
+
+impl From<f64> for Node {
+    fn from(value: f64) -> Self {
+        if value.is_finite() {
+            Self::Number(format!("{:?}", value))
+        } else {
+            Self::Str(value.to_string())
+        }
+    }
+}
+
+impl From<&str> for Node {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_owned())
+    }
+}
+
+impl From<String> for Node {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<T: Into<Node>> From<Option<T>> for Node {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+fn map<const N: usize>(entries: [(&str, Node); N]) -> Node {
+    Node::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect(),
+    )
+}
+
+/// The names of the set flags, like `ACC_PUBLIC`, followed by the unknown bits in hex
+fn flags(flags: impl std::fmt::Display) -> Node {
+    let flags = flags.to_string();
+    Node::List(
+        flags
+            .split(", ")
+            .filter(|flag| !flag.is_empty())
+            .map(Node::from)
+            .collect(),
+    )
+}
+
+/// The version, constant pool, members and attributes of the class
+pub fn class_tree(class: &ClassFile) -> Node {
+    let cp = &class.constant_pool;
+    map([
+        ("magic", format!("{:#X}", class.magic).into()),
+        ("minor_version", class.minor_version.into()),
+        ("major_version", class.major_version.into()),
+        (
+            "constant_pool",
+            Node::List(
+                cp.iter_with_indices()
+                    .map(|(index, info)| constant(index, info, cp))
+                    .collect(),
+            ),
+        ),
+        ("access_flags", flags(class.access_flags)),
+        (
+            "this_class",
+            class.this_class.get(cp).name_index.get(cp).into(),
+        ),
+        (
+            "super_class",
+            class
+                .super_class
+                .maybe_get(cp)
+                .map(|class| class.name_index.get(cp))
+                .into(),
+        ),
+        (
+            "interfaces",
+            Node::List(
+                class
+                    .interfaces
+                    .iter()
+                    .map(|interface| interface.get(cp).name_index.get(cp).into())
+                    .collect(),
+            ),
+        ),
+        (
+            "fields",
+            Node::List(
+                class
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        map([
+                            ("access_flags", flags(field.access_flags)),
+                            ("name", field.name_index.get(cp).into()),
+                            ("descriptor", field.descriptor_index.get(cp).into()),
+                            ("attributes", attributes(&field.attributes, cp)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "methods",
+            Node::List(
+                class
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        map([
+                            ("access_flags", flags(method.access_flags)),
+                            ("name", method.name_index.get(cp).into()),
+                            ("descriptor", method.descriptor_index.get(cp).into()),
+                            ("attributes", attributes(&method.attributes, cp)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("attributes", attributes(&class.attributes, cp)),
+    ])
+}
+
+fn constant(index: u16, info: &CpInfo, cp: &ConstantPool) -> Node {
+    let class_name = |class: &cp_info::Class| Node::from(class.name_index.get(cp));
+    let name_and_type = |index: FromPool<cp_info::NameAndType>| {
+        let name_and_type = index.get(cp);
+        (
+            Node::from(name_and_type.name_index.get(cp)),
+            Node::from(name_and_type.descriptor_index.get(cp)),
+        )
+    };
+    let mut entries = vec![
+        ("index".to_owned(), index.into()),
+        ("kind".to_owned(), constant_kind(&info.inner).into()),
+    ];
+    let mut entry = |key: &str, value: Node| entries.push((key.to_owned(), value));
+    match &info.inner {
+        CpInfoInner::Class(class) => {
+            entry("name", class_name(class));
+        }
+        CpInfoInner::Fieldref(cp_info::Fieldref {
+            class_index,
+            name_and_type_index,
+        })
+        | CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
+            class_index,
+            name_and_type_index,
+        })
+        | CpInfoInner::MethodRef(cp_info::MethodRef {
+            class_index,
+            name_and_type_index,
+        }) => {
+            let (name, descriptor) = name_and_type(*name_and_type_index);
+            entry("class", class_name(class_index.get(cp)));
+            entry("name", name);
+            entry("descriptor", descriptor);
+        }
+        CpInfoInner::String(string) => {
+            entry("value", string.string_index.get(cp).into());
+        }
+        CpInfoInner::Integer(int) => {
+            entry("value", int.value().into());
+        }
+        CpInfoInner::Float(float) => {
+            entry("value", float.value().into());
+        }
+        CpInfoInner::Long(long) => {
+            entry("value", long.value().into());
+        }
+        CpInfoInner::Double(double) => {
+            entry("value", double.value().into());
+        }
+        CpInfoInner::NameAndType(name_and_type) => {
+            entry("name", name_and_type.name_index.get(cp).into());
+            entry("descriptor", name_and_type.descriptor_index.get(cp).into());
+        }
+        CpInfoInner::Utf8(utf8) => {
+            entry("value", utf8.bytes.as_ref().into());
+        }
+        CpInfoInner::MethodHandle(handle) => {
+            let reference = match handle.reference_index {
+                MethodHandleIndex::Field(index) => index.inner(),
+                MethodHandleIndex::Method(index) => index.inner(),
+                MethodHandleIndex::MethodOrInterface(index) => index.inner(),
+                MethodHandleIndex::Interface(index) => index.inner(),
+            };
+            entry("reference_kind", handle.reference_kind.into());
+            entry("reference_index", reference.into());
+            entry("handle", display_method_handle(handle, cp).into());
+        }
+        CpInfoInner::MethodType(method_type) => {
+            entry("descriptor", method_type.descriptor_index.get(cp).into());
+        }
+        CpInfoInner::Dynamic(cp_info::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+        | CpInfoInner::InvokeDynamic(cp_info::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }) => {
+            let (name, descriptor) = name_and_type(*name_and_type_index);
+            entry("bootstrap_method", (*bootstrap_method_attr_index).into());
+            entry("name", name);
+            entry("descriptor", descriptor);
+        }
+        CpInfoInner::Module(module) => {
+            entry("name", module.name_index.get(cp).into());
+        }
+        CpInfoInner::Package(package) => {
+            entry("name", package.name_index.get(cp).into());
+        }
+        CpInfoInner::Unusable => {}
+    }
+    Node::Map(entries)
+}
+
+fn attributes(attributes: &[AttributeInfo], cp: &ConstantPool) -> Node {
+    Node::List(attributes.iter().map(|attr| attribute(attr, cp)).collect())
+}
+
+/// The name and length of the attribute, and the content of the attributes that are not only
+/// interesting for the JVM. Others, like the `StackMapTable`, only have the name and length
+fn attribute(attr: &AttributeInfo, cp: &ConstantPool) -> Node {
+    let class_name = |index: FromPool<cp_info::Class>| Node::from(index.get(cp).name_index.get(cp));
+    let mut entries = vec![
+        (
+            "name".to_owned(),
+            Node::from(attr.attribute_name_index.get(cp)),
+        ),
+        ("length".to_owned(), attr.attribute_length.into()),
+    ];
+    let mut entry = |key: &str, value: Node| entries.push((key.to_owned(), value));
+    match &attr.inner {
+        AttributeInfoInner::ConstantValue {
+            constantvalue_index,
+        } => entry(
+            "value",
+            match constantvalue_index.get(cp) {
+                CpInfoInner::Integer(int) => int.value().into(),
+                CpInfoInner::Float(float) => float.value().into(),
+                CpInfoInner::Long(long) => long.value().into(),
+                CpInfoInner::Double(double) => double.value().into(),
+                CpInfoInner::String(string) => string.string_index.get(cp).into(),
+                info => format!("{:?}", info).into(),
+            },
+        ),
+        AttributeInfoInner::Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes: code_attributes,
+        } => {
+            entry("max_stack", (*max_stack).into());
+            entry("max_locals", (*max_locals).into());
+            entry(
+                "code",
+                match decode_code(code) {
+                    Ok(instructions) => Node::List(
+                        instructions
+                            .iter()
+                            .map(|(offset, instruction)| {
+                                map([
+                                    ("offset", (*offset).into()),
+                                    (
+                                        "instruction",
+                                        display_instruction(*offset, instruction, cp).into(),
+                                    ),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                    // the code is still shown when it can't be decoded
+                    Err(err) => map([("error", err.to_string().into())]),
+                },
+            );
+            entry(
+                "exception_table",
+                Node::List(
+                    exception_table
+                        .iter()
+                        .map(|exception| {
+                            map([
+                                ("start_pc", exception.start_pc.into()),
+                                ("end_pc", exception.end_pc.into()),
+                                ("handler_pc", exception.handler_pc.into()),
+                                (
+                                    "catch_type",
+                                    match cp.entry(exception.catch_type).map(|info| &info.inner) {
+                                        Some(CpInfoInner::Class(class)) => {
+                                            class.name_index.get(cp).into()
+                                        }
+                                        _ => Node::Null,
+                                    },
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            );
+            entry("attributes", attributes(code_attributes, cp));
+        }
+        AttributeInfoInner::Exceptions {
+            exception_index_table,
+        } => entry(
+            "exceptions",
+            Node::List(
+                exception_index_table
+                    .iter()
+                    .map(|&index| match cp.entry(index).map(|info| &info.inner) {
+                        Some(CpInfoInner::Class(class)) => class.name_index.get(cp).into(),
+                        _ => index.into(),
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::InnerClasses { classes } => entry(
+            "classes",
+            Node::List(
+                classes
+                    .iter()
+                    .map(|class| {
+                        map([
+                            ("inner_class", class_name(class.inner_class_info_index)),
+                            (
+                                "outer_class",
+                                class
+                                    .outer_class_info_index
+                                    .maybe_get(cp)
+                                    .map(|class| class.name_index.get(cp))
+                                    .into(),
+                            ),
+                            (
+                                "inner_name",
+                                class.inner_class_name_index.maybe_get(cp).into(),
+                            ),
+                            ("access_flags", flags(class.inner_class_access_flags)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::EnclosingMethod {
+            class_index,
+            method_index,
+        } => {
+            let method = method_index.maybe_get(cp);
+            entry("class", class_name(*class_index));
+            entry(
+                "method",
+                method.map(|method| method.name_index.get(cp)).into(),
+            );
+            entry(
+                "descriptor",
+                method.map(|method| method.descriptor_index.get(cp)).into(),
+            );
+        }
+        AttributeInfoInner::NestHost { host_class_index } => {
+            entry("host", class_name(*host_class_index))
+        }
+        AttributeInfoInner::NestMembers { classes } => entry(
+            "classes",
+            Node::List(classes.iter().map(|&class| class_name(class)).collect()),
+        ),
+        AttributeInfoInner::Signature { signature_index } => {
+            entry("signature", signature_index.get(cp).into())
+        }
+        AttributeInfoInner::SourceFile { sourcefile_index } => {
+            entry("source_file", sourcefile_index.get(cp).into())
+        }
+        AttributeInfoInner::SourceDebugExtension { debug_extension } => entry(
+            "debug_extension",
+            String::from_utf8_lossy(debug_extension).into_owned().into(),
+        ),
+        AttributeInfoInner::LineNumberTable { line_number_table } => entry(
+            "line_numbers",
+            Node::List(
+                line_number_table
+                    .iter()
+                    .map(|line| {
+                        map([
+                            ("start_pc", line.start_pc.into()),
+                            ("line_number", line.line_number.into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::LocalVariableTable {
+            local_variable_table,
+        }
+        | AttributeInfoInner::LocalVariableTypeTable {
+            local_variable_table,
+        } => entry(
+            "local_variables",
+            Node::List(
+                local_variable_table
+                    .iter()
+                    .map(|variable| {
+                        map([
+                            ("start_pc", variable.start_pc.into()),
+                            ("length", variable.length.into()),
+                            ("name", variable.name_index.get(cp).into()),
+                            (
+                                "descriptor",
+                                variable.descriptor_or_signature_index.get(cp).into(),
+                            ),
+                            ("index", variable.index.into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::RuntimeVisibleAnnotations { annotations }
+        | AttributeInfoInner::RuntimeInvisibleAnnotations { annotations } => entry(
+            "annotations",
+            Node::List(
+                annotations
+                    .iter()
+                    .map(|annotation| annotation_tree(annotation, cp))
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::RuntimeVisibleParameterAnnotations {
+            parameter_annotations,
+        }
+        | AttributeInfoInner::RuntimeInvisibleParameterAnnotations {
+            parameter_annotations,
+        } => entry(
+            "parameters",
+            Node::List(
+                parameter_annotations
+                    .iter()
+                    .map(|parameter| {
+                        Node::List(
+                            parameter
+                                .annotations
+                                .iter()
+                                .map(|annotation| annotation_tree(annotation, cp))
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::RuntimeVisibleTypeAnnotations { annotations }
+        | AttributeInfoInner::RuntimeInvisibleTypeAnnotations { annotations } => entry(
+            "annotations",
+            Node::List(
+                annotations
+                    .iter()
+                    .map(|annotation| type_annotation_tree(annotation, cp))
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::MethodParameters { parameters } => entry(
+            "parameters",
+            Node::List(
+                parameters
+                    .iter()
+                    .map(|parameter| {
+                        map([
+                            ("name", parameter.name_index.maybe_get(cp).into()),
+                            ("access_flags", flags(parameter.access_flags)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::AnnotationDefault { default_value } => {
+            entry("default_value", element_value_tree(default_value, cp))
+        }
+        AttributeInfoInner::BootstrapMethods { bootstrap_methods } => entry(
+            "bootstrap_methods",
+            Node::List(
+                bootstrap_methods
+                    .iter()
+                    .map(|method| {
+                        map([
+                            (
+                                "method",
+                                display_method_handle(method.bootstrap_method_ref.get(cp), cp)
+                                    .into(),
+                            ),
+                            (
+                                "arguments",
+                                Node::List(
+                                    method
+                                        .bootstrap_arguments
+                                        .iter()
+                                        .map(|argument| argument.inner().into())
+                                        .collect(),
+                                ),
+                            ),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::Module(module) => {
+            let module_name =
+                |index: FromPool<cp_info::Module>| Node::from(index.get(cp).name_index.get(cp));
+            entry("module", module_name(module.module_name_index));
+            entry("flags", flags(module.module_flags));
+            entry("version", module.module_version_index.maybe_get(cp).into());
+            entry(
+                "requires",
+                Node::List(
+                    module
+                        .requires
+                        .iter()
+                        .map(|requires| {
+                            map([
+                                ("module", module_name(requires.requires_index)),
+                                ("flags", flags(requires.requires_flags)),
+                                (
+                                    "version",
+                                    requires.requires_version_index.maybe_get(cp).into(),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            );
+            let packages = |packages: Vec<(&str, _, &Vec<_>)>| {
+                Node::List(
+                    packages
+                        .into_iter()
+                        .map(|(package, package_flags, to): (_, _, &Vec<_>)| {
+                            map([
+                                ("package", package.into()),
+                                ("flags", flags(package_flags)),
+                                (
+                                    "to",
+                                    Node::List(to.iter().map(|&to| module_name(to)).collect()),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                )
+            };
+            entry(
+                "exports",
+                packages(
+                    module
+                        .exports
+                        .iter()
+                        .map(|exports| {
+                            (
+                                exports.exports_index.get(cp).name_index.get(cp),
+                                exports.exports_flags,
+                                &exports.exports_to_index,
+                            )
+                        })
+                        .collect(),
+                ),
+            );
+            entry(
+                "opens",
+                packages(
+                    module
+                        .opens
+                        .iter()
+                        .map(|opens| {
+                            (
+                                opens.opens_index.get(cp).name_index.get(cp),
+                                opens.opens_flags,
+                                &opens.opens_to_index,
+                            )
+                        })
+                        .collect(),
+                ),
+            );
+            entry(
+                "uses",
+                Node::List(
+                    module
+                        .uses_index
+                        .iter()
+                        .map(|&class| class_name(class))
+                        .collect(),
+                ),
+            );
+            entry(
+                "provides",
+                Node::List(
+                    module
+                        .provides
+                        .iter()
+                        .map(|provides| {
+                            map([
+                                ("service", class_name(provides.provides_index)),
+                                (
+                                    "with",
+                                    Node::List(
+                                        provides
+                                            .provides_with_index
+                                            .iter()
+                                            .map(|&class| class_name(class))
+                                            .collect(),
+                                    ),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            );
+        }
+        AttributeInfoInner::ModulePackages { package_index } => entry(
+            "packages",
+            Node::List(
+                package_index
+                    .iter()
+                    .map(|package| package.get(cp).name_index.get(cp).into())
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::ModuleMainClass { main_class_index } => {
+            entry("main_class", class_name(*main_class_index))
+        }
+        AttributeInfoInner::Record { components } => entry(
+            "components",
+            Node::List(
+                components
+                    .iter()
+                    .map(|component| {
+                        map([
+                            ("name", component.name_index.get(cp).into()),
+                            ("descriptor", component.descriptor_index.get(cp).into()),
+                            ("attributes", attributes(&component.attributes, cp)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        AttributeInfoInner::CompilationID {
+            compilation_id_index: index,
+        }
+        | AttributeInfoInner::SourceID {
+            source_id_index: index,
+        } => entry("value", index.get(cp).into()),
+        _ => {}
+    }
+    Node::Map(entries)
+}
+
+fn annotation_tree(annotation: &Annotation, cp: &ConstantPool) -> Node {
+    map([
+        ("type", annotation.type_index.get(cp).into()),
+        (
+            "elements",
+            Node::Map(
+                annotation
+                    .element_value_pairs
+                    .iter()
+                    .map(|pair| {
+                        (
+                            pair.element_name_index.get(cp).to_owned(),
+                            element_value_tree(&pair.element_name_name, cp),
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn type_annotation_tree(annotation: &TypeAnnotation, cp: &ConstantPool) -> Node {
+    map([
+        ("target_type", annotation.target_type.into()),
+        ("target_path", annotation.target_path.to_string().into()),
+        ("type", annotation.type_index.get(cp).into()),
+        (
+            "elements",
+            Node::Map(
+                annotation
+                    .element_value_pairs
+                    .iter()
+                    .map(|pair| {
+                        (
+                            pair.element_name_index.get(cp).to_owned(),
+                            element_value_tree(&pair.element_name_name, cp),
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+/// Constants become plain values, the other kinds a map saying what they are
+fn element_value_tree(value: &AnnotationElementValue, cp: &ConstantPool) -> Node {
+    match &value.value {
+        AnnotationElementValueValue::ConstValueIndex { index } => {
+            match (value.tag, index.get(cp)) {
+                (b'Z', CpInfoInner::Integer(int)) => (int.value() != 0).into(),
+                (b'C', CpInfoInner::Integer(int)) => match char::from_u32(int.bytes) {
+                    Some(char) => char.to_string().into(),
+                    None => int.value().into(),
+                },
+                (_, CpInfoInner::Integer(int)) => int.value().into(),
+                (_, CpInfoInner::Float(float)) => float.value().into(),
+                (_, CpInfoInner::Long(long)) => long.value().into(),
+                (_, CpInfoInner::Double(double)) => double.value().into(),
+                (_, CpInfoInner::Utf8(utf8)) => utf8.bytes.as_ref().into(),
+                (_, info) => format!("{:?}", info).into(),
+            }
+        }
+        AnnotationElementValueValue::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => map([
+            ("enum", type_name_index.get(cp).into()),
+            ("name", const_name_index.get(cp).into()),
+        ]),
+        AnnotationElementValueValue::ClassInfoIndex { index } => {
+            map([("class", index.get(cp).into())])
+        }
+        AnnotationElementValueValue::AnnotationValue { annotation } => {
+            map([("annotation", annotation_tree(annotation, cp))])
+        }
+        AnnotationElementValueValue::ArrayValue { values } => Node::List(
+            values
+                .iter()
+                .map(|value| element_value_tree(value, cp))
+                .collect(),
+        ),
+    }
+}
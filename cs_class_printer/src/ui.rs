@@ -1,9 +1,27 @@
-use cs_parser::ClassFile;
+use cs_model::names::internal_to_binary;
+use cs_model::{ClassSignature, FieldSignature, MethodSignature};
+use cs_parser::cp_info;
+use cs_parser::{
+    disassemble_listing, display_constant, display_method_handle, display_name_and_type,
+    Annotation, AnnotationElementValue, AnnotationElementValueValue, AttributeInfo,
+    AttributeInfoInner, ClassAccessFlag, ClassAccessFlags, ClassFile, ConstantPool, CpInfoInner,
+    FieldAccessFlag, FieldAccessFlags, MethodAccessFlag, MethodAccessFlags, MethodInfo, Module,
+    NestingKind, TypeAnnotation, TypeAnnotationTarget,
+};
 use std::io;
 use std::io::Write;
+use std::str::FromStr;
 
-pub fn display_class<W: Write>(mut w: W, class: &ClassFile) -> Result<(), io::Error> {
+use crate::color::Style;
+use crate::PrintOptions;
+
+pub fn display_class<W: Write>(
+    mut w: W,
+    class: &ClassFile,
+    options: &PrintOptions,
+) -> Result<(), io::Error> {
     let cp = &class.constant_pool;
+    let style = Style::new(options.color);
 
     writeln!(
         w,
@@ -13,57 +31,743 @@ pub fn display_class<W: Write>(mut w: W, class: &ClassFile) -> Result<(), io::Er
 
     writeln!(w)?;
 
+    let name = class.this_class.get(cp).name_index.get(cp);
+    let declaration = match parse_signature::<ClassSignature>(&class.attributes, cp) {
+        Some(signature) => style.type_(signature.java_declaration(name)),
+        None => format!(
+            "{} {} {}{}",
+            style.type_(name),
+            style.keyword("extends"),
+            style.type_(match class.super_class.maybe_get(cp) {
+                None => "<none>",
+                Some(class) => class.name_index.get(cp),
+            }),
+            if class.interfaces.is_empty() {
+                "".to_string()
+            } else {
+                format!(
+                    " {} {}",
+                    style.keyword("implements"),
+                    class
+                        .interfaces
+                        .iter()
+                        .map(|i| i.get(cp))
+                        .map(|i| style.type_(i.name_index.get(cp)))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            },
+        ),
+    };
     writeln!(
         w,
-        "class {} extends {}{} {{",
-        &class.this_class.get(cp).name_index.get(cp),
-        match class.super_class.maybe_get(cp) {
-            None => "<none>",
-            Some(class) => class.name_index.get(cp),
-        },
-        if class.interfaces.is_empty() {
-            "".to_string()
-        } else {
-            format!(
-                " implements {}",
-                class
-                    .interfaces
-                    .iter()
-                    .map(|i| i.get(cp))
-                    .map(|i| i.name_index.get(cp))
-                    .collect::<Vec<_>>()
-                    .join(",")
-            )
-        },
+        "{}{} {} {{",
+        class_modifiers(class.access_flags, style),
+        style.keyword(class_kind(class.access_flags)),
+        declaration
     )?;
 
+    let nesting = class.nesting_info();
+    if nesting.kind != NestingKind::TopLevel
+        || nesting.nest_host.is_some()
+        || !nesting.nest_members.is_empty()
+    {
+        writeln!(w, " Nesting: {}", nesting)?;
+    }
+
+    if options.sections.constants {
+        display_constants(&mut w, cp, style)?;
+    }
+
+    if options.sections.attributes {
+        display_attributes(&mut w, class, style)?;
+    }
+
+    if options.sections.fields {
+        writeln!(w, " Fields:")?;
+        for field in &class.fields {
+            writeln!(
+                w,
+                "  {}{} {}",
+                field_modifiers(field.access_flags, style),
+                style.type_(
+                    match parse_signature::<FieldSignature>(&field.attributes, cp) {
+                        Some(signature) => signature.to_string(),
+                        None => field.descriptor_index.get(cp).to_string(),
+                    }
+                ),
+                &field.name_index.get(cp)
+            )?;
+        }
+        writeln!(w)?;
+    }
+
+    if options.sections.methods {
+        writeln!(w, " Methods:")?;
+        for method in &class.methods {
+            let name = method.name_index.get(cp);
+            write!(
+                w,
+                "  {}{}",
+                method_modifiers(method.access_flags, style),
+                match parse_signature::<MethodSignature>(&method.attributes, cp) {
+                    Some(signature) => style.type_(signature.java_declaration(name)),
+                    None => format!("{} {}", style.type_(method.descriptor_index.get(cp)), name),
+                }
+            )?;
+            for attr in &method.attributes {
+                if let AttributeInfoInner::AnnotationDefault { default_value } = &attr.inner {
+                    write!(
+                        w,
+                        " {} {}",
+                        style.keyword("default"),
+                        element_value(default_value, cp)
+                    )?;
+                }
+            }
+            writeln!(w)?;
+            if options.sections.code {
+                display_code(&mut w, method, cp)?;
+            }
+        }
+    }
+
+    writeln!(w, "}}")?;
+    Ok(())
+}
+
+/// Lists the constant pool like `javap -v`, for example `   #2 = Class              java/lang/Object`
+fn display_constants<W: Write>(mut w: W, cp: &ConstantPool, style: Style) -> Result<(), io::Error> {
+    writeln!(w, " Constant pool:")?;
+    let width = cp.count().to_string().len() + 1;
+    for (index, info) in cp.iter_with_indices() {
+        let value = match &info.inner {
+            CpInfoInner::Fieldref(cp_info::Fieldref {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::MethodRef(cp_info::MethodRef {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
+            }) => format!(
+                "{}.{}",
+                class_index.get(cp).name_index.get(cp),
+                display_name_and_type(name_and_type_index.get(cp), cp)
+            ),
+            CpInfoInner::NameAndType(name_and_type) => display_name_and_type(name_and_type, cp),
+            CpInfoInner::Utf8(utf8) => utf8.bytes.escape_debug().to_string(),
+            CpInfoInner::InvokeDynamic(invoke_dynamic) => format!(
+                "{} {}",
+                invoke_dynamic.bootstrap_method_attr_index,
+                display_name_and_type(invoke_dynamic.name_and_type_index.get(cp), cp)
+            ),
+            CpInfoInner::Module(cp_info::Module { name_index })
+            | CpInfoInner::Package(cp_info::Package { name_index }) => {
+                name_index.get(cp).to_string()
+            }
+            info => display_constant(info, cp),
+        };
+        writeln!(
+            w,
+            "  {:>width$} = {} {}",
+            format!("#{}", index),
+            style.constant_kind(format!("{:<18}", constant_kind(&info.inner))),
+            value,
+            width = width,
+        )?;
+    }
+    writeln!(w)
+}
+
+/// The name of the kind of constant in the specification, like `Methodref`
+pub(crate) fn constant_kind(info: &CpInfoInner) -> &'static str {
+    match info {
+        CpInfoInner::Class(_) => "Class",
+        CpInfoInner::Fieldref(_) => "Fieldref",
+        CpInfoInner::MethodRef(_) => "Methodref",
+        CpInfoInner::InterfaceMethodref(_) => "InterfaceMethodref",
+        CpInfoInner::String(_) => "String",
+        CpInfoInner::Integer(_) => "Integer",
+        CpInfoInner::Float(_) => "Float",
+        CpInfoInner::Long(_) => "Long",
+        CpInfoInner::Double(_) => "Double",
+        CpInfoInner::NameAndType(_) => "NameAndType",
+        CpInfoInner::Utf8(_) => "Utf8",
+        CpInfoInner::MethodHandle(_) => "MethodHandle",
+        CpInfoInner::MethodType(_) => "MethodType",
+        CpInfoInner::Dynamic(_) => "Dynamic",
+        CpInfoInner::InvokeDynamic(_) => "InvokeDynamic",
+        CpInfoInner::Module(_) => "Module",
+        CpInfoInner::Package(_) => "Package",
+        CpInfoInner::Unusable => "Unusable",
+    }
+}
+
+/// The disassembled code of the method below its declaration with labels and source lines,
+/// nothing for abstract and native methods
+fn display_code<W: Write>(
+    mut w: W,
+    method: &MethodInfo,
+    cp: &ConstantPool,
+) -> Result<(), io::Error> {
+    for attr in &method.attributes {
+        if let AttributeInfoInner::Code {
+            code, attributes, ..
+        } = &attr.inner
+        {
+            let line_numbers = attributes
+                .iter()
+                .filter_map(|attr| match &attr.inner {
+                    AttributeInfoInner::LineNumberTable { line_number_table } => {
+                        Some(line_number_table)
+                    }
+                    _ => None,
+                })
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            match disassemble_listing(code, &line_numbers, cp) {
+                Ok(code) => {
+                    for line in code.lines() {
+                        writeln!(w, "    {}", line)?;
+                    }
+                }
+                Err(err) => writeln!(w, "    invalid code: {}", err)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The attributes of the class, with the content of those that are not shown anywhere else
+fn display_attributes<W: Write>(
+    mut w: W,
+    class: &ClassFile,
+    style: Style,
+) -> Result<(), io::Error> {
+    let cp = &class.constant_pool;
+
     writeln!(w, " Attributes:")?;
     for attr in &class.attributes {
-        writeln!(w, "  {}", &attr.attribute_name_index.get(cp))?;
+        match attr.inner {
+            AttributeInfoInner::CompilationID {
+                compilation_id_index: index,
+            }
+            | AttributeInfoInner::SourceID {
+                source_id_index: index,
+            } => writeln!(
+                w,
+                "  {} {}",
+                &attr.attribute_name_index.get(cp),
+                index.get(cp)
+            )?,
+            _ => writeln!(w, "  {}", &attr.attribute_name_index.get(cp))?,
+        }
     }
     writeln!(w)?;
 
-    writeln!(w, " Fields:")?;
-    for field in &class.fields {
-        writeln!(
+    for attr in &class.attributes {
+        match &attr.inner {
+            AttributeInfoInner::Module(module) => {
+                writeln!(w, " Module:")?;
+                display_module(&mut w, module, cp, style)?;
+                for attr in &class.attributes {
+                    match &attr.inner {
+                        AttributeInfoInner::ModulePackages { package_index } => writeln!(
+                            w,
+                            "  {} {}",
+                            style.keyword("packages"),
+                            package_index
+                                .iter()
+                                .map(|package| internal_to_binary(
+                                    package.get(cp).name_index.get(cp)
+                                ))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        )?,
+                        AttributeInfoInner::ModuleMainClass { main_class_index } => writeln!(
+                            w,
+                            "  {} {}",
+                            style.keyword("main class"),
+                            style.type_(internal_to_binary(
+                                main_class_index.get(cp).name_index.get(cp)
+                            ))
+                        )?,
+                        _ => {}
+                    }
+                }
+                writeln!(w)?;
+            }
+            AttributeInfoInner::Record { components } => {
+                writeln!(w, " Record components:")?;
+                for component in components {
+                    writeln!(
+                        w,
+                        "  {} {}",
+                        style.type_(component.descriptor_index.get(cp)),
+                        component.name_index.get(cp)
+                    )?;
+                }
+                writeln!(w)?;
+            }
+            AttributeInfoInner::BootstrapMethods { bootstrap_methods } => {
+                writeln!(w, " Bootstrap methods:")?;
+                for (i, method) in bootstrap_methods.iter().enumerate() {
+                    writeln!(
+                        w,
+                        "  {}: {}",
+                        i,
+                        display_method_handle(method.bootstrap_method_ref.get(cp), cp)
+                    )?;
+                    for argument in &method.bootstrap_arguments {
+                        writeln!(w, "    {}", display_constant(argument.get(cp), cp))?;
+                    }
+                }
+                writeln!(w)?;
+            }
+            _ => {}
+        }
+    }
+
+    let type_annotations = class_type_annotations(class);
+    if !type_annotations.is_empty() {
+        writeln!(w, " Type annotations:")?;
+        for (location, annotation) in type_annotations {
+            write!(
+                w,
+                "  {}: {} {}",
+                location,
+                style.type_(format!("@{}", annotation.type_index.get(cp))),
+                describe_target(annotation)
+            )?;
+            if !annotation.target_path.path.is_empty() {
+                write!(w, ", location={}", annotation.target_path)?;
+            }
+            writeln!(w)?;
+        }
+        writeln!(w)?;
+    }
+
+    #[cfg(feature = "kotlin")]
+    match crate::kotlin::kotlin_metadata(class) {
+        Some(Ok(metadata)) => {
+            writeln!(
+                w,
+                " Kotlin {} (metadata {}):",
+                metadata.kind, metadata.version
+            )?;
+            for declaration in &metadata.declarations {
+                writeln!(w, "  {}", declaration)?;
+            }
+            writeln!(w)?;
+        }
+        Some(Err(err)) => writeln!(w, " {}\n", err)?,
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// The generic signature from the `Signature` attribute, `None` if there is none or it is invalid
+fn parse_signature<S: FromStr>(attributes: &[AttributeInfo], cp: &ConstantPool) -> Option<S> {
+    attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::Signature { signature_index } => {
+            S::from_str(signature_index.get(cp)).ok()
+        }
+        _ => None,
+    })
+}
+
+/// The keyword declaring the kind of class, like `interface` or `@interface`
+fn class_kind(flags: ClassAccessFlags) -> &'static str {
+    if flags.contains(ClassAccessFlag::MODULE) {
+        "module"
+    } else if flags.contains(ClassAccessFlag::Annotation) {
+        "@interface"
+    } else if flags.contains(ClassAccessFlag::Interface) {
+        "interface"
+    } else if flags.contains(ClassAccessFlag::Enum) {
+        "enum"
+    } else {
+        "class"
+    }
+}
+
+/// The modifiers of the class, `abstract` is left out for interfaces where it is implied
+fn class_modifiers(flags: ClassAccessFlags, style: Style) -> String {
+    let interface = flags.contains(ClassAccessFlag::Interface);
+    modifiers(
+        style,
+        &[
+            (flags.contains(ClassAccessFlag::Public), "public"),
+            (
+                flags.contains(ClassAccessFlag::Abstract) && !interface,
+                "abstract",
+            ),
+            (flags.contains(ClassAccessFlag::Final), "final"),
+        ],
+    )
+}
+
+fn field_modifiers(flags: FieldAccessFlags, style: Style) -> String {
+    modifiers(
+        style,
+        &[
+            (flags.contains(FieldAccessFlag::PUBLIC), "public"),
+            (flags.contains(FieldAccessFlag::PROTECTED), "protected"),
+            (flags.contains(FieldAccessFlag::PRIVATE), "private"),
+            (flags.contains(FieldAccessFlag::STATIC), "static"),
+            (flags.contains(FieldAccessFlag::FINAL), "final"),
+            (flags.contains(FieldAccessFlag::TRANSIENT), "transient"),
+            (flags.contains(FieldAccessFlag::VOLATILE), "volatile"),
+        ],
+    )
+}
+
+fn method_modifiers(flags: MethodAccessFlags, style: Style) -> String {
+    modifiers(
+        style,
+        &[
+            (flags.contains(MethodAccessFlag::PUBLIC), "public"),
+            (flags.contains(MethodAccessFlag::PROTECTED), "protected"),
+            (flags.contains(MethodAccessFlag::PRIVATE), "private"),
+            (flags.contains(MethodAccessFlag::ABSTRACT), "abstract"),
+            (flags.contains(MethodAccessFlag::STATIC), "static"),
+            (flags.contains(MethodAccessFlag::FINAL), "final"),
+            (
+                flags.contains(MethodAccessFlag::SYNCHRONIZED),
+                "synchronized",
+            ),
+            (flags.contains(MethodAccessFlag::NATIVE), "native"),
+            (flags.contains(MethodAccessFlag::STRICT), "strictfp"),
+        ],
+    )
+}
+
+/// The set modifiers in the order of the Java style guide, each followed by a space
+fn modifiers(style: Style, modifiers: &[(bool, &str)]) -> String {
+    modifiers
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, modifier)| format!("{} ", style.keyword(modifier)))
+        .collect()
+}
+
+/// Displays a module descriptor similar to `java --describe-module`
+fn display_module<W: Write>(
+    w: &mut W,
+    module: &Module,
+    cp: &ConstantPool,
+    style: Style,
+) -> Result<(), io::Error> {
+    write!(
+        w,
+        "  {}",
+        module.module_name_index.get(cp).name_index.get(cp)
+    )?;
+    if let Some(version) = module.module_version_index.maybe_get(cp) {
+        write!(w, "@{}", version)?;
+    }
+    writeln!(
+        w,
+        "{}",
+        flag_names(
+            style,
+            module.module_flags.bits(),
+            &[
+                (0x0020, "open"),
+                (0x1000, "synthetic"),
+                (0x8000, "mandated")
+            ]
+        )
+    )?;
+
+    for requires in &module.requires {
+        write!(
             w,
             "  {} {}",
-            &field.descriptor_index.get(cp),
-            &field.name_index.get(cp)
+            style.keyword("requires"),
+            requires.requires_index.get(cp).name_index.get(cp)
+        )?;
+        if let Some(version) = requires.requires_version_index.maybe_get(cp) {
+            write!(w, "@{}", version)?;
+        }
+        writeln!(
+            w,
+            "{}",
+            flag_names(
+                style,
+                requires.requires_flags.bits(),
+                &[
+                    (0x0020, "transitive"),
+                    (0x0040, "static"),
+                    (0x1000, "synthetic"),
+                    (0x8000, "mandated"),
+                ]
+            )
         )?;
     }
-    writeln!(w)?;
 
-    writeln!(w, " Methods:")?;
-    for method in &class.methods {
+    for exports in &module.exports {
+        let package = exports.exports_index.get(cp).name_index.get(cp);
+        let to = exports
+            .exports_to_index
+            .iter()
+            .map(|module| module.get(cp).name_index.get(cp))
+            .collect::<Vec<_>>();
+        if to.is_empty() {
+            writeln!(
+                w,
+                "  {} {}",
+                style.keyword("exports"),
+                internal_to_binary(package)
+            )?;
+        } else {
+            writeln!(
+                w,
+                "  {} {} {} {}",
+                style.keyword("qualified exports"),
+                internal_to_binary(package),
+                style.keyword("to"),
+                to.join(" ")
+            )?;
+        }
+    }
+
+    for opens in &module.opens {
+        let package = opens.opens_index.get(cp).name_index.get(cp);
+        let to = opens
+            .opens_to_index
+            .iter()
+            .map(|module| module.get(cp).name_index.get(cp))
+            .collect::<Vec<_>>();
+        if to.is_empty() {
+            writeln!(
+                w,
+                "  {} {}",
+                style.keyword("opens"),
+                internal_to_binary(package)
+            )?;
+        } else {
+            writeln!(
+                w,
+                "  {} {} {} {}",
+                style.keyword("qualified opens"),
+                internal_to_binary(package),
+                style.keyword("to"),
+                to.join(" ")
+            )?;
+        }
+    }
+
+    for uses in &module.uses_index {
         writeln!(
             w,
             "  {} {}",
-            &method.descriptor_index.get(cp),
-            &method.name_index.get(cp),
+            style.keyword("uses"),
+            style.type_(internal_to_binary(uses.get(cp).name_index.get(cp)))
+        )?;
+    }
+
+    for provides in &module.provides {
+        writeln!(
+            w,
+            "  {} {} {} {}",
+            style.keyword("provides"),
+            style.type_(internal_to_binary(
+                provides.provides_index.get(cp).name_index.get(cp)
+            )),
+            style.keyword("with"),
+            provides
+                .provides_with_index
+                .iter()
+                .map(|class| style.type_(internal_to_binary(class.get(cp).name_index.get(cp))))
+                .collect::<Vec<_>>()
+                .join(" ")
         )?;
     }
 
-    writeln!(w, "}}")?;
     Ok(())
 }
+
+/// Formats an annotation element value like Java source, for example `{"a", "b"}` or `Kind.CLASS`
+fn element_value(value: &AnnotationElementValue, cp: &ConstantPool) -> String {
+    match &value.value {
+        AnnotationElementValueValue::ConstValueIndex { index } => {
+            match (value.tag, index.get(cp)) {
+                (b'Z', CpInfoInner::Integer(int)) => (int.bytes != 0).to_string(),
+                (b'C', CpInfoInner::Integer(int)) => match char::from_u32(int.bytes) {
+                    Some(char) => format!("{:?}", char),
+                    None => int.bytes.to_string(),
+                },
+                (_, CpInfoInner::Integer(int)) => int.value().to_string(),
+                (_, CpInfoInner::Float(float)) => format!("{:?}f", float.value()),
+                (_, CpInfoInner::Long(long)) => format!("{}L", long.value()),
+                (_, CpInfoInner::Double(double)) => format!("{:?}", double.value()),
+                (_, CpInfoInner::Utf8(utf8)) => format!("{:?}", utf8.bytes),
+                (_, info) => format!("{:?}", info),
+            }
+        }
+        AnnotationElementValueValue::EnumConstValue {
+            type_name_index,
+            const_name_index,
+        } => format!("{}.{}", type_name_index.get(cp), const_name_index.get(cp)),
+        AnnotationElementValueValue::ClassInfoIndex { index } => format!("{}.class", index.get(cp)),
+        AnnotationElementValueValue::AnnotationValue { annotation } => {
+            display_annotation(annotation, cp)
+        }
+        AnnotationElementValueValue::ArrayValue { values } => format!(
+            "{{{}}}",
+            values
+                .iter()
+                .map(|value| element_value(value, cp))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn display_annotation(annotation: &Annotation, cp: &ConstantPool) -> String {
+    format!(
+        "@{}({})",
+        annotation.type_index.get(cp),
+        annotation
+            .element_value_pairs
+            .iter()
+            .map(|pair| format!(
+                "{}={}",
+                pair.element_name_index.get(cp),
+                element_value(&pair.element_name_name, cp)
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// All type annotations of the class, its members and their code, with a description of where they are
+fn class_type_annotations(class: &ClassFile) -> Vec<(String, &TypeAnnotation)> {
+    let cp = &class.constant_pool;
+    let mut annotations = Vec::new();
+    collect_type_annotations(&class.attributes, "class", &mut annotations);
+    for field in &class.fields {
+        let location = format!("field {}", field.name_index.get(cp));
+        collect_type_annotations(&field.attributes, &location, &mut annotations);
+    }
+    for method in &class.methods {
+        let location = format!(
+            "method {}{}",
+            method.name_index.get(cp),
+            method.descriptor_index.get(cp)
+        );
+        collect_type_annotations(&method.attributes, &location, &mut annotations);
+    }
+    annotations
+}
+
+fn collect_type_annotations<'a>(
+    attributes: &'a [AttributeInfo],
+    location: &str,
+    annotations: &mut Vec<(String, &'a TypeAnnotation)>,
+) {
+    for attr in attributes {
+        match &attr.inner {
+            AttributeInfoInner::RuntimeVisibleTypeAnnotations {
+                annotations: type_annotations,
+            }
+            | AttributeInfoInner::RuntimeInvisibleTypeAnnotations {
+                annotations: type_annotations,
+            } => annotations.extend(
+                type_annotations
+                    .iter()
+                    .map(|annotation| (location.to_owned(), annotation)),
+            ),
+            AttributeInfoInner::Code { attributes, .. } => {
+                collect_type_annotations(attributes, location, annotations)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Describes the annotated type like javap, for example `METHOD_FORMAL_PARAMETER, param_index=0`
+fn describe_target(annotation: &TypeAnnotation) -> String {
+    let kind = match annotation.target_type {
+        0x00 => "CLASS_TYPE_PARAMETER",
+        0x01 => "METHOD_TYPE_PARAMETER",
+        0x10 => "CLASS_EXTENDS",
+        0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+        0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+        0x13 => "FIELD",
+        0x14 => "METHOD_RETURN",
+        0x15 => "METHOD_RECEIVER",
+        0x16 => "METHOD_FORMAL_PARAMETER",
+        0x17 => "THROWS",
+        0x40 => "LOCAL_VARIABLE",
+        0x41 => "RESOURCE_VARIABLE",
+        0x42 => "EXCEPTION_PARAMETER",
+        0x43 => "INSTANCEOF",
+        0x44 => "NEW",
+        0x45 => "CONSTRUCTOR_REFERENCE",
+        0x46 => "METHOD_REFERENCE",
+        0x47 => "CAST",
+        0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        0x4A => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        _ => "METHOD_REFERENCE_TYPE_ARGUMENT",
+    };
+    let info = match &annotation.target_info {
+        TypeAnnotationTarget::TypeParameter {
+            type_parameter_index,
+        } => format!(", param_index={}", type_parameter_index),
+        TypeAnnotationTarget::Supertype { supertype_index } => {
+            format!(", type_index={}", *supertype_index as i16)
+        }
+        TypeAnnotationTarget::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => format!(
+            ", param_index={}, bound_index={}",
+            type_parameter_index, bound_index
+        ),
+        TypeAnnotationTarget::Empty => String::new(),
+        TypeAnnotationTarget::FormalParameter {
+            formal_parameter_index,
+        } => format!(", param_index={}", formal_parameter_index),
+        TypeAnnotationTarget::Throws { throws_type_index } => {
+            format!(", type_index={}", throws_type_index)
+        }
+        TypeAnnotationTarget::Localvar { table } => format!(
+            ", {}",
+            table
+                .iter()
+                .map(|var| format!(
+                    "{{start_pc={}, length={}, index={}}}",
+                    var.start_pc, var.length, var.index
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        TypeAnnotationTarget::Catch {
+            exception_table_index,
+        } => format!(", exception_index={}", exception_table_index),
+        TypeAnnotationTarget::Offset { offset } => format!(", offset={}", offset),
+        TypeAnnotationTarget::TypeArgument {
+            offset,
+            type_argument_index,
+        } => format!(", offset={}, type_index={}", offset, type_argument_index),
+    };
+    format!("{}{}", kind, info)
+}
+
+/// Renders the names of all set flags, each prefixed with a space
+fn flag_names(style: Style, flags: u16, names: &[(u16, &str)]) -> String {
+    names
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| format!(" {}", style.flag(name)))
+        .collect()
+}
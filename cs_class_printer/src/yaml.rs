@@ -0,0 +1,97 @@
+//!
+//! Writes the class tree as YAML
+//!
+
+use crate::json::json_string;
+use crate::tree::Node;
+use std::io;
+use std::io::Write;
+
+/// Writes the node as a block style YAML document. Every scalar is on its own line, so two dumps
+/// can be compared with a line based diff
+pub fn write_yaml<W: Write>(mut w: W, node: &Node) -> Result<(), io::Error> {
+    if is_block(node) {
+        write_block(&mut w, node, 0, false)
+    } else {
+        writeln!(w, "{}", scalar(node))
+    }
+}
+
+/// Non-empty lists and maps are written as indented blocks, everything else inline
+fn is_block(node: &Node) -> bool {
+    match node {
+        Node::List(values) => !values.is_empty(),
+        Node::Map(entries) => !entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Writes the entries of a block, the first line continues the current one if `inline` is set,
+/// like the first key of a map in a list after the `-`
+fn write_block<W: Write>(
+    w: &mut W,
+    node: &Node,
+    depth: usize,
+    inline: bool,
+) -> Result<(), io::Error> {
+    let indent = "  ".repeat(depth);
+    let start = |i: usize| if i == 0 && inline { " " } else { &indent };
+    match node {
+        Node::List(values) => {
+            for (i, value) in values.iter().enumerate() {
+                write!(w, "{}-", start(i))?;
+                if is_block(value) {
+                    write_block(w, value, depth + 1, true)?;
+                } else {
+                    writeln!(w, " {}", scalar(value))?;
+                }
+            }
+        }
+        Node::Map(entries) => {
+            for (i, (key, value)) in entries.iter().enumerate() {
+                write!(w, "{}{}:", start(i), string(key))?;
+                if is_block(value) {
+                    writeln!(w)?;
+                    write_block(w, value, depth + 1, false)?;
+                } else {
+                    writeln!(w, " {}", scalar(value))?;
+                }
+            }
+        }
+        _ => unreachable!("only lists and maps are written as blocks"),
+    }
+    Ok(())
+}
+
+fn scalar(node: &Node) -> String {
+    match node {
+        Node::Null => "null".to_owned(),
+        Node::Bool(value) => value.to_string(),
+        Node::Number(number) => number.clone(),
+        Node::Str(str) => string(str),
+        Node::List(_) => "[]".to_owned(),
+        Node::Map(_) => "{}".to_owned(),
+    }
+}
+
+/// Names and descriptors are written as they are, anything that could be mistaken for another
+/// kind of value or contains special characters is quoted
+fn string(str: &str) -> String {
+    let plain = str
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && str
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || "_/.$<>()[];-".contains(char))
+        && !matches!(
+            str.to_ascii_lowercase().as_str(),
+            "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+        );
+    if plain {
+        str.to_owned()
+    } else {
+        // double quoted YAML strings use the same escapes as JSON
+        json_string(str)
+    }
+}
@@ -1,9 +1,17 @@
 #![allow(dead_code)]
 
+pub mod names;
+mod signature;
 #[cfg(test)]
 mod test;
 
+pub use signature::{
+    ClassSignature, ClassTypeSignature, FieldSignature, MethodSignature, SimpleClassTypeSignature,
+    TypeArgument, TypeParameter, TypeSignature,
+};
+
 use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 #[derive(Debug)]
@@ -71,6 +79,14 @@ impl FromStr for FieldDescriptor {
 }
 
 impl FieldType {
+    /// The number of local variable or operand stack slots a value takes up, 2 for `long` and `double`
+    pub fn slot_count(&self) -> usize {
+        match self {
+            Self::Long | Self::Double => 2,
+            _ => 1,
+        }
+    }
+
     /// Consumes as much chars as needed from the char iterator and tries to parse itself
     pub fn from_char_iter<I>(chars: &mut I) -> Result<Self, ParseErr>
     where
@@ -111,6 +127,45 @@ impl FieldType {
     }
 }
 
+impl FieldType {
+    /// The descriptor of the type, like `[Ljava/lang/String;`
+    pub fn to_descriptor(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl FieldDescriptor {
+    pub fn to_descriptor(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl MethodDescriptor {
+    /// The descriptor of the method, like `(I[J)Ljava/lang/String;`
+    pub fn to_descriptor(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn parameters(&self) -> &[FieldType] {
+        &self.parameters
+    }
+
+    pub fn return_type(&self) -> &MethodType {
+        &self.return_
+    }
+
+    /// The number of parameters
+    pub fn arity(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// The number of local variable slots the arguments take up, where `long` and `double` take
+    /// up two. Instance methods need one more slot for `this`
+    pub fn argument_slot_count(&self) -> usize {
+        self.parameters.iter().map(FieldType::slot_count).sum()
+    }
+}
+
 impl FromStr for MethodDescriptor {
     type Err = ParseErr;
 
@@ -142,3 +197,46 @@ impl FromStr for MethodDescriptor {
         })
     }
 }
+
+/// Writes the type as it appears in descriptors, so it can be parsed again
+impl Display for FieldType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Byte => f.write_str("B"),
+            Self::Char => f.write_str("C"),
+            Self::Double => f.write_str("D"),
+            Self::Float => f.write_str("F"),
+            Self::Int => f.write_str("I"),
+            Self::Long => f.write_str("J"),
+            Self::Object(name) => write!(f, "L{};", name),
+            Self::Short => f.write_str("S"),
+            Self::Boolean => f.write_str("Z"),
+            Self::Array(component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+impl Display for FieldDescriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Display for MethodType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Some(type_) => type_.fmt(f),
+            Self::Void => f.write_str("V"),
+        }
+    }
+}
+
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("(")?;
+        for parameter in &self.parameters {
+            parameter.fmt(f)?;
+        }
+        write!(f, "){}", self.return_)
+    }
+}
@@ -0,0 +1,96 @@
+//!
+//! Converting between the forms class names take in class files and in Java
+//!
+
+use crate::FieldType;
+
+/// Converts an internal name like `java/lang/String` to a binary name like `java.lang.String`.
+/// Works the same for package names
+pub fn internal_to_binary(internal: &str) -> String {
+    internal.replace('/', ".")
+}
+
+/// Converts a binary name like `java.lang.String` to an internal name like `java/lang/String`
+pub fn binary_to_internal(binary: &str) -> String {
+    binary.replace('.', "/")
+}
+
+/// The internal name of the class or array type of a field descriptor, as it is used in `Class`
+/// constants. `Ljava/lang/String;` becomes `java/lang/String`, array descriptors are their own
+/// internal name. `None` for primitive types and invalid descriptors
+pub fn descriptor_to_internal(descriptor: &str) -> Option<&str> {
+    match parse_whole(descriptor)? {
+        FieldType::Object(_) => Some(&descriptor[1..descriptor.len() - 1]),
+        FieldType::Array(_) => Some(descriptor),
+        _ => None,
+    }
+}
+
+/// The field descriptor of the class or array with the internal name, `java/lang/String` becomes
+/// `Ljava/lang/String;` and array names like `[I` stay as they are
+pub fn internal_to_descriptor(internal: &str) -> String {
+    if internal.starts_with('[') {
+        internal.to_owned()
+    } else {
+        format!("L{};", internal)
+    }
+}
+
+/// The name `Class.getName()` returns for the type of a field descriptor, like `java.lang.String`,
+/// `[Ljava.lang.String;` for arrays or `int`. `None` for invalid descriptors
+pub fn descriptor_to_class_name(descriptor: &str) -> Option<String> {
+    Some(match parse_whole(descriptor)? {
+        FieldType::Object(name) => internal_to_binary(&name),
+        FieldType::Array(_) => internal_to_binary(descriptor),
+        primitive => primitive_name(&primitive)?.to_owned(),
+    })
+}
+
+/// The field descriptor of a name as returned by `Class.getName()`, the reverse of
+/// `descriptor_to_class_name`. `None` if the name is empty or an invalid array name
+pub fn class_name_to_descriptor(name: &str) -> Option<String> {
+    if name.starts_with('[') {
+        let descriptor = binary_to_internal(name);
+        parse_whole(&descriptor)?;
+        return Some(descriptor);
+    }
+    let primitive = [
+        FieldType::Byte,
+        FieldType::Char,
+        FieldType::Double,
+        FieldType::Float,
+        FieldType::Int,
+        FieldType::Long,
+        FieldType::Short,
+        FieldType::Boolean,
+    ]
+    .into_iter()
+    .find(|primitive| primitive_name(primitive) == Some(name));
+    match primitive {
+        Some(primitive) => Some(primitive.to_descriptor()),
+        None if name.is_empty() => None,
+        None => Some(internal_to_descriptor(&binary_to_internal(name))),
+    }
+}
+
+/// The Java keyword of a primitive type, like `int`. `None` for class and array types
+pub fn primitive_name(type_: &FieldType) -> Option<&'static str> {
+    Some(match type_ {
+        FieldType::Byte => "byte",
+        FieldType::Char => "char",
+        FieldType::Double => "double",
+        FieldType::Float => "float",
+        FieldType::Int => "int",
+        FieldType::Long => "long",
+        FieldType::Short => "short",
+        FieldType::Boolean => "boolean",
+        FieldType::Object(_) | FieldType::Array(_) => return None,
+    })
+}
+
+/// Parses the descriptor, which must not have anything after the type
+fn parse_whole(descriptor: &str) -> Option<FieldType> {
+    let mut chars = descriptor.chars();
+    let type_ = FieldType::from_char_iter(&mut chars).ok()?;
+    chars.as_str().is_empty().then_some(type_)
+}
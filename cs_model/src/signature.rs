@@ -0,0 +1,421 @@
+//!
+//! Generic signatures from the `Signature` attribute, and rendering them as Java source
+//!
+
+use crate::names::{internal_to_binary, primitive_name};
+use crate::{FieldType, ParseErr};
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+/// The signature of a generic class, like `<T:Ljava/lang/Object;>Ljava/util/AbstractList<TT;>;`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub super_class: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+/// The signature of a generic method, like `<T:Ljava/lang/Object;>(Ljava/util/List<TT;>;)TT;`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<TypeSignature>,
+    /// `None` for `void`
+    pub return_: Option<TypeSignature>,
+    /// Class types or type variables
+    pub throws: Vec<TypeSignature>,
+}
+
+/// The signature of a field with a generic type, like `Ljava/util/List<Ljava/lang/String;>;`.
+/// It is always a class type, a type variable or an array
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FieldSignature(pub TypeSignature);
+
+/// A type parameter like `T extends Comparable<T>`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TypeParameter {
+    pub name: String,
+    /// The bound that is a class, `None` if all bounds are interfaces
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>,
+}
+
+/// A type that may use type variables and type arguments
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TypeSignature {
+    /// A primitive type, never `FieldType::Object` or `FieldType::Array`
+    Base(FieldType),
+    Class(ClassTypeSignature),
+    /// The name of a type parameter, like `T`
+    TypeVariable(String),
+    Array(Box<TypeSignature>),
+}
+
+/// A class type with type arguments, like `java/util/Map$Entry<TK;TV;>`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ClassTypeSignature {
+    /// The outermost class with its package like `java/util/Map`, followed by the simple names
+    /// of the inner classes like `Entry`, each with their own type arguments
+    pub path: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+/// A type argument, like `? extends Number`
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TypeArgument {
+    /// `?`
+    Wildcard,
+    /// `? extends T`
+    Extends(TypeSignature),
+    /// `? super T`
+    Super(TypeSignature),
+    Exact(TypeSignature),
+}
+
+impl FromStr for ClassSignature {
+    type Err = ParseErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let type_parameters = parser.type_parameters()?;
+        let super_class = parser.class_type()?;
+        let mut interfaces = Vec::new();
+        while parser.chars.peek().is_some() {
+            interfaces.push(parser.class_type()?);
+        }
+        Ok(Self {
+            type_parameters,
+            super_class,
+            interfaces,
+        })
+    }
+}
+
+impl FromStr for MethodSignature {
+    type Err = ParseErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let type_parameters = parser.type_parameters()?;
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.eat(')') {
+            parameters.push(parser.type_signature()?);
+        }
+        let return_ = if parser.eat('V') {
+            None
+        } else {
+            Some(parser.type_signature()?)
+        };
+        let mut throws = Vec::new();
+        while parser.eat('^') {
+            throws.push(parser.reference_type()?);
+        }
+        parser.end()?;
+        Ok(Self {
+            type_parameters,
+            parameters,
+            return_,
+            throws,
+        })
+    }
+}
+
+impl FromStr for FieldSignature {
+    type Err = ParseErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let signature = parser.reference_type()?;
+        parser.end()?;
+        Ok(Self(signature))
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            chars: s.chars().peekable(),
+        }
+    }
+
+    /// Consumes the char if it is next
+    fn eat(&mut self, char: char) -> bool {
+        self.chars.next_if_eq(&char).is_some()
+    }
+
+    fn expect(&mut self, char: char) -> Result<(), ParseErr> {
+        match self.chars.next() {
+            Some(next) if next == char => Ok(()),
+            Some(next) => Err(ParseErr::string(format!(
+                "Expected '{}', found '{}'",
+                char, next
+            ))),
+            None => Err(ParseErr::string(format!(
+                "Expected '{}' before end of string",
+                char
+            ))),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), ParseErr> {
+        match self.chars.next() {
+            None => Ok(()),
+            Some(char) => Err(ParseErr::string(format!(
+                "Unexpected '{}' after the signature",
+                char
+            ))),
+        }
+    }
+
+    /// Takes chars until one of the delimiters, which is not consumed
+    fn identifier(&mut self, delimiters: &[char]) -> Result<String, ParseErr> {
+        let mut identifier = String::new();
+        while let Some(char) = self.chars.next_if(|char| !delimiters.contains(char)) {
+            identifier.push(char);
+        }
+        if identifier.is_empty() {
+            Err(ParseErr::str("Expected an identifier"))
+        } else {
+            Ok(identifier)
+        }
+    }
+
+    /// Empty if there are no type parameters
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, ParseErr> {
+        let mut type_parameters = Vec::new();
+        if self.eat('<') {
+            while !self.eat('>') {
+                let name = self.identifier(&[':', '>'])?;
+                self.expect(':')?;
+                let class_bound = match self.chars.peek() {
+                    Some(':') => None,
+                    _ => Some(self.reference_type()?),
+                };
+                let mut interface_bounds = Vec::new();
+                while self.eat(':') {
+                    interface_bounds.push(self.reference_type()?);
+                }
+                type_parameters.push(TypeParameter {
+                    name,
+                    class_bound,
+                    interface_bounds,
+                });
+            }
+        }
+        Ok(type_parameters)
+    }
+
+    fn type_signature(&mut self) -> Result<TypeSignature, ParseErr> {
+        let base = match self.chars.peek() {
+            Some('B') => FieldType::Byte,
+            Some('C') => FieldType::Char,
+            Some('D') => FieldType::Double,
+            Some('F') => FieldType::Float,
+            Some('I') => FieldType::Int,
+            Some('J') => FieldType::Long,
+            Some('S') => FieldType::Short,
+            Some('Z') => FieldType::Boolean,
+            _ => return self.reference_type(),
+        };
+        let _ = self.chars.next();
+        Ok(TypeSignature::Base(base))
+    }
+
+    /// A class type, type variable or array
+    fn reference_type(&mut self) -> Result<TypeSignature, ParseErr> {
+        match self.chars.peek() {
+            Some('L') => Ok(TypeSignature::Class(self.class_type()?)),
+            Some('T') => {
+                let _ = self.chars.next();
+                let name = self.identifier(&[';'])?;
+                self.expect(';')?;
+                Ok(TypeSignature::TypeVariable(name))
+            }
+            Some('[') => {
+                let _ = self.chars.next();
+                Ok(TypeSignature::Array(Box::new(self.type_signature()?)))
+            }
+            Some(char) => Err(ParseErr::string(format!(
+                "Invalid char in signature {}",
+                char
+            ))),
+            None => Err(ParseErr::str("Expected a type before end of string")),
+        }
+    }
+
+    fn class_type(&mut self) -> Result<ClassTypeSignature, ParseErr> {
+        self.expect('L')?;
+        let mut path = Vec::new();
+        loop {
+            let name = self.identifier(&['<', '.', ';'])?;
+            let mut type_arguments = Vec::new();
+            if self.eat('<') {
+                while !self.eat('>') {
+                    type_arguments.push(match self.chars.peek() {
+                        Some('*') => {
+                            let _ = self.chars.next();
+                            TypeArgument::Wildcard
+                        }
+                        Some('+') => {
+                            let _ = self.chars.next();
+                            TypeArgument::Extends(self.reference_type()?)
+                        }
+                        Some('-') => {
+                            let _ = self.chars.next();
+                            TypeArgument::Super(self.reference_type()?)
+                        }
+                        _ => TypeArgument::Exact(self.reference_type()?),
+                    });
+                }
+            }
+            path.push(SimpleClassTypeSignature {
+                name,
+                type_arguments,
+            });
+            if self.eat(';') {
+                return Ok(ClassTypeSignature { path });
+            }
+            self.expect('.')?;
+        }
+    }
+}
+
+impl ClassSignature {
+    /// Renders the declaration of the class like Java source, for example
+    /// `Box<T extends java.lang.Number> extends java.lang.Object implements java.util.function.Supplier<T>`
+    pub fn java_declaration(&self, name: &str) -> String {
+        let mut declaration = format!(
+            "{}{} extends {}",
+            internal_to_binary(name),
+            TypeParameters(&self.type_parameters),
+            self.super_class
+        );
+        if !self.interfaces.is_empty() {
+            declaration.push_str(" implements ");
+            declaration.push_str(&join(&self.interfaces));
+        }
+        declaration
+    }
+}
+
+impl MethodSignature {
+    /// Renders the declaration of the method like Java source, for example
+    /// `<T extends java.lang.Comparable<T>> java.util.List<T> sort(java.util.List<T>)`
+    pub fn java_declaration(&self, name: &str) -> String {
+        let mut declaration = String::new();
+        if !self.type_parameters.is_empty() {
+            declaration.push_str(&format!("{} ", TypeParameters(&self.type_parameters)));
+        }
+        match &self.return_ {
+            Some(return_) => declaration.push_str(&return_.to_string()),
+            None => declaration.push_str("void"),
+        }
+        declaration.push_str(&format!(" {}({})", name, join(&self.parameters)));
+        if !self.throws.is_empty() {
+            declaration.push_str(&format!(" throws {}", join(&self.throws)));
+        }
+        declaration
+    }
+}
+
+/// The type parameters in angle brackets, nothing if there are none
+struct TypeParameters<'a>(&'a [TypeParameter]);
+
+impl Display for TypeParameters<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "<{}>", join(self.0))
+        }
+    }
+}
+
+fn join<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Display for FieldSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// `T extends A & B`, a bound of only `java.lang.Object` is left out
+impl Display for TypeParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let object = |bound: &&TypeSignature| match bound {
+            TypeSignature::Class(class) => {
+                class.path.len() == 1 && class.path[0].name == "java/lang/Object"
+            }
+            _ => false,
+        };
+        let bounds = self
+            .class_bound
+            .iter()
+            .filter(|bound| !object(bound))
+            .chain(&self.interface_bounds)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        f.write_str(&self.name)?;
+        if !bounds.is_empty() {
+            write!(f, " extends {}", bounds.join(" & "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The type like Java source, for example `java.util.Map.Entry<K, V>[]` or `int`
+impl Display for TypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeSignature::Base(base) => {
+                f.write_str(primitive_name(base).expect("base types are primitive"))
+            }
+            TypeSignature::Class(class) => class.fmt(f),
+            TypeSignature::TypeVariable(name) => f.write_str(name),
+            TypeSignature::Array(component) => write!(f, "{}[]", component),
+        }
+    }
+}
+
+impl Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, class) in self.path.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            f.write_str(&internal_to_binary(&class.name))?;
+            if !class.type_arguments.is_empty() {
+                write!(f, "<{}>", join(&class.type_arguments))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for TypeArgument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeArgument::Wildcard => f.write_str("?"),
+            TypeArgument::Extends(bound) => write!(f, "? extends {}", bound),
+            TypeArgument::Super(bound) => write!(f, "? super {}", bound),
+            TypeArgument::Exact(argument) => argument.fmt(f),
+        }
+    }
+}
@@ -55,7 +55,7 @@ fn field_descriptor() {
 
 #[test]
 fn method_descriptor() {
-    let descriptors = vec![
+    let descriptors = [
         MethodDescriptor::from_str("()V").unwrap(),
         MethodDescriptor::from_str("(B)V").unwrap(),
         MethodDescriptor::from_str("([ZZ)Ljava/lang/Object;").unwrap(),
@@ -124,3 +124,155 @@ fn method_descriptor() {
         .zip(expected_descriptors.iter())
         .for_each(|(a, b)| assert_eq!(a, b));
 }
+
+#[test]
+fn signatures() {
+    let class = ClassSignature::from_str(
+        "<K::Ljava/lang/Comparable<TK;>;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;TV;>;Ljava/lang/Iterable<Ljava/util/Map$Entry<TK;TV;>;>;",
+    )
+    .unwrap();
+    assert_eq!(class.type_parameters.len(), 2);
+    assert_eq!(class.type_parameters[0].class_bound, None);
+    assert_eq!(
+        class.java_declaration("Tree"),
+        "Tree<K extends java.lang.Comparable<K>, V> extends java.util.AbstractMap<K, V> implements java.lang.Iterable<java.util.Map$Entry<K, V>>"
+    );
+
+    let method = MethodSignature::from_str(
+        "<T::Ljava/lang/Comparable<-TT;>;>(Ljava/util/List<+TT;>;[I)Ljava/util/List<TT;>;^TE;^Ljava/io/IOException;",
+    )
+    .unwrap();
+    assert_eq!(
+        method.java_declaration("sort"),
+        "<T extends java.lang.Comparable<? super T>> java.util.List<T> sort(java.util.List<? extends T>, int[]) throws E, java.io.IOException"
+    );
+    assert_eq!(
+        MethodSignature::from_str("()V")
+            .unwrap()
+            .java_declaration("run"),
+        "void run()"
+    );
+
+    let field = FieldSignature::from_str("Lcom/example/Outer<TT;>.Inner<*>;").unwrap();
+    assert_eq!(
+        field.0,
+        TypeSignature::Class(ClassTypeSignature {
+            path: vec![
+                SimpleClassTypeSignature {
+                    name: "com/example/Outer".to_string(),
+                    type_arguments: vec![TypeArgument::Exact(TypeSignature::TypeVariable(
+                        "T".to_string()
+                    ))],
+                },
+                SimpleClassTypeSignature {
+                    name: "Inner".to_string(),
+                    type_arguments: vec![TypeArgument::Wildcard],
+                },
+            ],
+        })
+    );
+    assert_eq!(field.to_string(), "com.example.Outer<T>.Inner<?>");
+
+    let invalid = [
+        "",
+        "I",
+        "Ljava/util/List<>",
+        "Ljava/util/List",
+        "TT",
+        "[",
+        "LA;LB;",
+    ];
+    for signature in invalid {
+        assert!(
+            FieldSignature::from_str(signature).is_err(),
+            "{}",
+            signature
+        );
+    }
+    assert!(MethodSignature::from_str("(I").is_err());
+    assert!(MethodSignature::from_str("<T>()V").is_err());
+    assert!(ClassSignature::from_str("<T:>Ljava/lang/Object;").is_err());
+}
+
+#[test]
+fn method_descriptor_accessors() {
+    let descriptor = MethodDescriptor::from_str("(IJLjava/lang/String;[DD)V").unwrap();
+    assert_eq!(descriptor.arity(), 5);
+    assert_eq!(descriptor.argument_slot_count(), 7);
+    assert_eq!(descriptor.parameters()[1], FieldType::Long);
+    assert_eq!(descriptor.return_type(), &MethodType::Void);
+
+    let descriptor = MethodDescriptor::from_str("()[J").unwrap();
+    assert_eq!(descriptor.arity(), 0);
+    assert_eq!(descriptor.argument_slot_count(), 0);
+    assert_eq!(
+        descriptor.return_type(),
+        &MethodType::Some(FieldType::Array(Box::new(FieldType::Long)))
+    );
+}
+
+#[test]
+fn descriptor_round_trip() {
+    for descriptor in [
+        "B",
+        "[[Z",
+        "J",
+        "Ljava/lang/String;",
+        "[[[Ljava/lang/Object;",
+    ] {
+        let parsed = FieldDescriptor::from_str(descriptor).unwrap();
+        assert_eq!(parsed.to_descriptor(), descriptor);
+        assert_eq!(parsed.0.to_string(), descriptor);
+    }
+    for descriptor in [
+        "()V",
+        "(IJ)Z",
+        "([Ljava/lang/String;)V",
+        "(DLjava/util/List;[[C)[Ljava/lang/Object;",
+    ] {
+        let parsed = MethodDescriptor::from_str(descriptor).unwrap();
+        assert_eq!(parsed.to_descriptor(), descriptor);
+        assert_eq!(
+            MethodDescriptor::from_str(&parsed.to_string()).unwrap(),
+            parsed
+        );
+    }
+}
+
+#[test]
+fn name_conversions() {
+    use crate::names::*;
+
+    assert_eq!(
+        internal_to_binary("java/util/Map$Entry"),
+        "java.util.Map$Entry"
+    );
+    assert_eq!(binary_to_internal("java.lang.String"), "java/lang/String");
+
+    assert_eq!(
+        descriptor_to_internal("Ljava/lang/String;"),
+        Some("java/lang/String")
+    );
+    assert_eq!(descriptor_to_internal("[[I"), Some("[[I"));
+    assert_eq!(descriptor_to_internal("I"), None);
+    assert_eq!(descriptor_to_internal("Ljava/lang/String;I"), None);
+    assert_eq!(
+        internal_to_descriptor("java/lang/String"),
+        "Ljava/lang/String;"
+    );
+    assert_eq!(internal_to_descriptor("[I"), "[I");
+
+    for (descriptor, name) in [
+        ("I", "int"),
+        ("Z", "boolean"),
+        ("Ljava/lang/String;", "java.lang.String"),
+        ("[Ljava/lang/String;", "[Ljava.lang.String;"),
+        ("[[J", "[[J"),
+    ] {
+        assert_eq!(descriptor_to_class_name(descriptor).as_deref(), Some(name));
+        assert_eq!(class_name_to_descriptor(name).as_deref(), Some(descriptor));
+    }
+    assert_eq!(descriptor_to_class_name("V"), None);
+    assert_eq!(class_name_to_descriptor(""), None);
+    assert_eq!(class_name_to_descriptor("[java.lang.String"), None);
+}
@@ -0,0 +1,18 @@
+//!
+//! Parses arbitrary bytes, which may fail but must not panic
+//!
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(class) = cs_parser::parse_class_file(data) {
+        for method in &class.methods {
+            for attribute in &method.attributes {
+                if let cs_parser::AttributeInfoInner::Code { code, .. } = &attribute.inner {
+                    let _ = cs_parser::decode_code(code);
+                }
+            }
+        }
+    }
+});
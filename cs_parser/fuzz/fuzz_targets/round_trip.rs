@@ -0,0 +1,20 @@
+//!
+//! Every class that parses is written back to a class file that parses into the same class.
+//! Writing it the first time drops what the parser ignores, like bytes after the content of an
+//! attribute, so the class is only compared once it was written and parsed again
+//!
+#![no_main]
+
+use cs_parser::{parse_class_file, write_class_file};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(class) = parse_class_file(data) else {
+        return;
+    };
+    let written = write_class_file(&class).expect("a parsed class can be written");
+    let class = parse_class_file(&written).expect("a written class can be parsed");
+    let rewritten = write_class_file(&class).expect("a parsed class can be written");
+    assert_eq!(parse_class_file(&rewritten).unwrap(), class);
+    assert_eq!(rewritten, written);
+});
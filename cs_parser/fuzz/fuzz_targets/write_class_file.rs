@@ -0,0 +1,14 @@
+//!
+//! Writes classes built from the model, whose indices don't have to be valid. The writer may
+//! reject them and the parser may reject its output, but neither may panic
+//!
+#![no_main]
+
+use cs_parser::ClassFile;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|class: ClassFile| {
+    if let Ok(written) = cs_parser::write_class_file(&class) {
+        let _ = cs_parser::parse_class_file(&written);
+    }
+});
@@ -0,0 +1,198 @@
+//!
+//! Reading annotations and their values without looking up the constant pool by hand
+//!
+
+use crate::{
+    Annotation, AnnotationElementValue, AnnotationElementValueValue, AttributeInfo,
+    AttributeInfoInner, ClassFile, ConstantPool, CpInfoInner, FieldInfo, MethodInfo,
+};
+
+/// An annotation of a class, field or method
+#[derive(Debug, Clone, Copy)]
+pub struct AnnotationView<'a> {
+    annotation: &'a Annotation,
+    visible: bool,
+    cp: &'a ConstantPool,
+}
+
+/// The value of an annotation element, taken from the constant pool
+#[derive(Debug, Clone)]
+pub enum AnnotationValue<'a> {
+    Byte(i8),
+    /// A UTF-16 code unit
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Boolean(bool),
+    Str(&'a str),
+    Enum {
+        /// The field descriptor of the enum type, for example `Ljava/lang/annotation/RetentionPolicy;`
+        type_: &'a str,
+        name: &'a str,
+    },
+    /// The return descriptor of the class, for example `Ljava/lang/Object;` or `V` for `void.class`
+    Class(&'a str),
+    Annotation(AnnotationView<'a>),
+    Array(Vec<AnnotationValue<'a>>),
+}
+
+impl<'a> AnnotationView<'a> {
+    /// The field descriptor of the annotation type, for example `Lorg/junit/Test;`
+    pub fn type_(&self) -> &'a str {
+        self.annotation.type_index.get(self.cp)
+    }
+
+    /// Whether the annotation is visible to reflection
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// The value of the element, `None` if it is not set, even if it has a default value
+    pub fn get(&self, name: &str) -> Option<AnnotationValue<'a>> {
+        self.values()
+            .find(|(element, _)| *element == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The elements that are set, with their names
+    pub fn values(&self) -> impl Iterator<Item = (&'a str, AnnotationValue<'a>)> + 'a {
+        let (cp, visible) = (self.cp, self.visible);
+        self.annotation.element_value_pairs.iter().map(move |pair| {
+            (
+                pair.element_name_index.get(cp),
+                AnnotationValue::new(&pair.element_name_name, visible, cp),
+            )
+        })
+    }
+
+    pub fn raw(&self) -> &'a Annotation {
+        self.annotation
+    }
+}
+
+impl<'a> AnnotationValue<'a> {
+    /// Panics if the constant does not match the tag, which is checked while parsing
+    fn new(value: &'a AnnotationElementValue, visible: bool, cp: &'a ConstantPool) -> Self {
+        match &value.value {
+            AnnotationElementValueValue::ConstValueIndex { index } => {
+                match (value.tag, index.get(cp)) {
+                    (b'B', CpInfoInner::Integer(int)) => Self::Byte(int.value() as i8),
+                    (b'C', CpInfoInner::Integer(int)) => Self::Char(int.value() as u16),
+                    (b'S', CpInfoInner::Integer(int)) => Self::Short(int.value() as i16),
+                    (b'Z', CpInfoInner::Integer(int)) => Self::Boolean(int.value() != 0),
+                    (b'I', CpInfoInner::Integer(int)) => Self::Int(int.value()),
+                    (b'J', CpInfoInner::Long(long)) => Self::Long(long.value()),
+                    (b'F', CpInfoInner::Float(float)) => Self::Float(float.value()),
+                    (b'D', CpInfoInner::Double(double)) => Self::Double(double.value()),
+                    (b's', CpInfoInner::Utf8(utf8)) => Self::Str(&utf8.bytes),
+                    (tag, info) => unreachable!(
+                        "Annotation element with tag '{}' has the constant {:?}",
+                        tag as char, info
+                    ),
+                }
+            }
+            AnnotationElementValueValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => Self::Enum {
+                type_: type_name_index.get(cp),
+                name: const_name_index.get(cp),
+            },
+            AnnotationElementValueValue::ClassInfoIndex { index } => Self::Class(index.get(cp)),
+            AnnotationElementValueValue::AnnotationValue { annotation } => {
+                Self::Annotation(AnnotationView {
+                    annotation,
+                    visible,
+                    cp,
+                })
+            }
+            AnnotationElementValueValue::ArrayValue { values } => Self::Array(
+                values
+                    .iter()
+                    .map(|value| Self::new(value, visible, cp))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The visible and invisible annotations in the attributes
+pub(crate) fn annotations<'a>(
+    attributes: &'a [AttributeInfo],
+    cp: &'a ConstantPool,
+) -> impl Iterator<Item = AnnotationView<'a>> {
+    attributes
+        .iter()
+        .filter_map(|attr| match &attr.inner {
+            AttributeInfoInner::RuntimeVisibleAnnotations { annotations } => {
+                Some((annotations, true))
+            }
+            AttributeInfoInner::RuntimeInvisibleAnnotations { annotations } => {
+                Some((annotations, false))
+            }
+            _ => None,
+        })
+        .flat_map(move |(annotations, visible)| {
+            annotations.iter().map(move |annotation| AnnotationView {
+                annotation,
+                visible,
+                cp,
+            })
+        })
+}
+
+impl ClassFile {
+    /// The visible and invisible annotations of the class
+    pub fn annotations(&self) -> impl Iterator<Item = AnnotationView<'_>> {
+        annotations(&self.attributes, &self.constant_pool)
+    }
+
+    /// The annotation of the class with the type, like `Ljava/lang/Deprecated;`
+    pub fn annotation(&self, type_: &str) -> Option<AnnotationView<'_>> {
+        self.annotations()
+            .find(|annotation| annotation.type_() == type_)
+    }
+}
+
+impl MethodInfo {
+    /// The visible and invisible annotations of the method
+    pub fn annotations<'a>(
+        &'a self,
+        cp: &'a ConstantPool,
+    ) -> impl Iterator<Item = AnnotationView<'a>> {
+        annotations(&self.attributes, cp)
+    }
+
+    /// The annotation of the method with the type, like `Lorg/junit/Test;`
+    pub fn annotation<'a>(
+        &'a self,
+        cp: &'a ConstantPool,
+        type_: &str,
+    ) -> Option<AnnotationView<'a>> {
+        self.annotations(cp)
+            .find(|annotation| annotation.type_() == type_)
+    }
+}
+
+impl FieldInfo {
+    /// The visible and invisible annotations of the field
+    pub fn annotations<'a>(
+        &'a self,
+        cp: &'a ConstantPool,
+    ) -> impl Iterator<Item = AnnotationView<'a>> {
+        annotations(&self.attributes, cp)
+    }
+
+    /// The annotation of the field with the type, like `Ljava/lang/Deprecated;`
+    pub fn annotation<'a>(
+        &'a self,
+        cp: &'a ConstantPool,
+        type_: &str,
+    ) -> Option<AnnotationView<'a>> {
+        self.annotations(cp)
+            .find(|annotation| annotation.type_() == type_)
+    }
+}
@@ -0,0 +1,485 @@
+//!
+//! Turning instructions back into bytecode, the inverse of `decode_code`
+//!
+
+use crate::*;
+
+/// The longest code a method can have
+const MAX_CODE_LENGTH: usize = u2::MAX as usize;
+
+type EncodeResult<T> = std::result::Result<T, WriteErr>;
+
+/// Encodes instructions into the code of a method.
+///
+/// Branch offsets are taken as they are, use an `Assembler` to compute them from labels.
+/// Local variable indices and increments that don't fit into a byte are encoded with `wide`
+pub fn encode_code(instructions: &[Instruction]) -> EncodeResult<Vec<u1>> {
+    let mut code = Vec::new();
+    for instruction in instructions {
+        encode_instruction(&mut code, instruction)?;
+    }
+    check_length(&code)?;
+    Ok(code)
+}
+
+/// A position in the code of an `Assembler`. Branches can refer to it before it is placed
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// Builds the code of a method from instructions and labels, computing the branch offsets
+///
+/// Branches that are too far for a two byte offset are replaced by `goto_w` or `jsr_w`.
+/// Conditional branches don't have a long form, so they jump over a `goto_w` with the inverted condition instead
+///
+/// ```
+/// # use cs_parser::{Assembler, Instruction};
+/// let mut asm = Assembler::new();
+/// let end = asm.new_label();
+/// asm.push(Instruction::Iload0);
+/// asm.branch(Instruction::Ifeq, end);
+/// asm.push(Instruction::Iinc { index: 0, value: -1 });
+/// asm.place(end);
+/// asm.push(Instruction::Return);
+/// let code = asm.assemble().unwrap().code;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    items: Vec<Item>,
+    /// The index of the item each label is placed before
+    labels: Vec<Option<usize>>,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Instruction(Instruction),
+    Branch {
+        branch: fn(i32) -> Instruction,
+        target: Label,
+    },
+    Tableswitch {
+        low: i32,
+        default: Label,
+        targets: Vec<Label>,
+    },
+    Lookupswitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+}
+
+/// The result of `Assembler::assemble`
+#[derive(Debug, Clone)]
+pub struct AssembledCode {
+    pub code: Vec<u1>,
+    label_offsets: Vec<Option<u32>>,
+}
+
+impl AssembledCode {
+    /// The offset in the code a label was placed at, for exception tables or debug attributes
+    pub fn offset(&self, label: Label) -> Option<u32> {
+        self.label_offsets.get(label.0).copied().flatten()
+    }
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new label that can be placed later
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Places the label before the next instruction
+    ///
+    /// Panics if the label was already placed
+    pub fn place(&mut self, label: Label) {
+        let position = &mut self.labels[label.0];
+        assert!(position.is_none(), "The label was already placed");
+        *position = Some(self.items.len());
+    }
+
+    /// Adds an instruction. The offsets of branch instructions added like this are kept as they are
+    pub fn push(&mut self, instruction: Instruction) {
+        self.items.push(Item::Instruction(instruction));
+    }
+
+    /// Adds a branch to `target`, like `asm.branch(Instruction::Goto, label)`
+    ///
+    /// Panics if `branch` does not create a branch instruction
+    pub fn branch(&mut self, branch: fn(i32) -> Instruction, target: Label) {
+        assert!(
+            branch(0).is_branch(),
+            "{} is not a branch instruction",
+            branch(0).mnemonic()
+        );
+        self.items.push(Item::Branch { branch, target });
+    }
+
+    /// Adds a `tableswitch` for the keys starting from `low`, with one target for each key
+    pub fn tableswitch(&mut self, low: i32, default: Label, targets: Vec<Label>) {
+        self.items.push(Item::Tableswitch {
+            low,
+            default,
+            targets,
+        });
+    }
+
+    /// Adds a `lookupswitch`, the pairs are sorted by their key
+    pub fn lookupswitch(&mut self, default: Label, mut pairs: Vec<(i32, Label)>) {
+        pairs.sort_by_key(|(key, _)| *key);
+        self.items.push(Item::Lookupswitch { default, pairs });
+    }
+
+    /// Lays out the code and encodes it. Fails if a label is used without being placed,
+    /// or if the code gets too long
+    pub fn assemble(&self) -> EncodeResult<AssembledCode> {
+        // start with short branches and make them long until all offsets fit
+        let mut long = vec![false; self.items.len()];
+        let offsets = loop {
+            let offsets = self.layout(&long)?;
+            let mut changed = false;
+            for (i, item) in self.items.iter().enumerate() {
+                if let Item::Branch { target, .. } = item {
+                    let branch = self.target(&offsets, *target)? - offsets[i] as i64;
+                    if !long[i] && i16::try_from(branch).is_err() {
+                        long[i] = true;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break offsets;
+            }
+        };
+
+        let mut code = Vec::with_capacity(offsets[self.items.len()]);
+        for (i, item) in self.items.iter().enumerate() {
+            let offset = offsets[i] as i64;
+            let branch = |target: Label| -> EncodeResult<i32> {
+                Ok((self.target(&offsets, target)? - offset) as i32)
+            };
+            match item {
+                Item::Instruction(instruction) => encode_instruction(&mut code, instruction)?,
+                Item::Branch {
+                    branch: create,
+                    target,
+                } if long[i] => {
+                    let goto = match create(0) {
+                        Instruction::Goto(_) | Instruction::GotoW(_) => {
+                            Instruction::GotoW(branch(*target)?)
+                        }
+                        Instruction::Jsr(_) | Instruction::JsrW(_) => {
+                            Instruction::JsrW(branch(*target)?)
+                        }
+                        instruction => {
+                            // if not the condition, skip the goto_w after this instruction
+                            let inverted =
+                                invert_condition(&instruction).expect("a conditional branch");
+                            encode_instruction(&mut code, &inverted(3 + 5))?;
+                            Instruction::GotoW(branch(*target)? - 3)
+                        }
+                    };
+                    encode_instruction(&mut code, &goto)?;
+                }
+                Item::Branch {
+                    branch: create,
+                    target,
+                } => encode_instruction(&mut code, &create(branch(*target)?))?,
+                Item::Tableswitch {
+                    low,
+                    default,
+                    targets,
+                } => {
+                    let instruction = Instruction::Tableswitch {
+                        default: branch(*default)?,
+                        low: *low,
+                        high: table_high(*low, targets)?,
+                        offsets: targets
+                            .iter()
+                            .map(|target| branch(*target))
+                            .collect::<EncodeResult<_>>()?,
+                    };
+                    encode_instruction(&mut code, &instruction)?;
+                }
+                Item::Lookupswitch { default, pairs } => {
+                    let instruction = Instruction::Lookupswitch {
+                        default: branch(*default)?,
+                        pairs: pairs
+                            .iter()
+                            .map(|(key, target)| Ok((*key, branch(*target)?)))
+                            .collect::<EncodeResult<_>>()?,
+                    };
+                    encode_instruction(&mut code, &instruction)?;
+                }
+            }
+        }
+        check_length(&code)?;
+
+        let label_offsets = self
+            .labels
+            .iter()
+            .map(|position| position.map(|item| offsets[item] as u32))
+            .collect();
+        Ok(AssembledCode {
+            code,
+            label_offsets,
+        })
+    }
+
+    /// The offset of every item with the given branch sizes, and the length of the code at the end
+    fn layout(&self, long: &[bool]) -> EncodeResult<Vec<usize>> {
+        let mut offsets = Vec::with_capacity(self.items.len() + 1);
+        let mut code = Vec::new();
+        for (item, long) in self.items.iter().zip(long) {
+            offsets.push(code.len());
+            match item {
+                Item::Instruction(instruction) => encode_instruction(&mut code, instruction)?,
+                Item::Branch { branch, .. } => {
+                    let size = match (long, branch(0)) {
+                        (_, Instruction::GotoW(_) | Instruction::JsrW(_)) => 5,
+                        (false, _) => 3,
+                        (true, Instruction::Goto(_) | Instruction::Jsr(_)) => 5,
+                        (true, _) => 3 + 5,
+                    };
+                    code.resize(code.len() + size, 0);
+                }
+                // the offsets don't change the size of a switch
+                Item::Tableswitch { low, targets, .. } => {
+                    let instruction = Instruction::Tableswitch {
+                        default: 0,
+                        low: *low,
+                        high: table_high(*low, targets)?,
+                        offsets: vec![0; targets.len()],
+                    };
+                    encode_instruction(&mut code, &instruction)?;
+                }
+                Item::Lookupswitch { pairs, .. } => {
+                    let instruction = Instruction::Lookupswitch {
+                        default: 0,
+                        pairs: pairs.iter().map(|(key, _)| (*key, 0)).collect(),
+                    };
+                    encode_instruction(&mut code, &instruction)?;
+                }
+            }
+            check_length(&code)?;
+        }
+        offsets.push(code.len());
+        Ok(offsets)
+    }
+
+    fn target(&self, offsets: &[usize], label: Label) -> EncodeResult<i64> {
+        match self.labels[label.0] {
+            Some(item) => Ok(offsets[item] as i64),
+            None => Err(WriteErr(format!(
+                "Label {} is used but never placed",
+                label.0
+            ))),
+        }
+    }
+}
+
+impl Instruction {
+    /// Whether this is a `goto`, `jsr` or conditional branch with a single target
+    pub fn is_branch(&self) -> bool {
+        matches!(
+            self,
+            Self::Goto(_) | Self::Jsr(_) | Self::GotoW(_) | Self::JsrW(_)
+        ) || invert_condition(self).is_some()
+    }
+}
+
+/// The conditional branch that jumps exactly when `instruction` doesn't
+fn invert_condition(instruction: &Instruction) -> Option<fn(i32) -> Instruction> {
+    Some(match instruction {
+        Instruction::Ifeq(_) => Instruction::Ifne,
+        Instruction::Ifne(_) => Instruction::Ifeq,
+        Instruction::Iflt(_) => Instruction::Ifge,
+        Instruction::Ifge(_) => Instruction::Iflt,
+        Instruction::Ifgt(_) => Instruction::Ifle,
+        Instruction::Ifle(_) => Instruction::Ifgt,
+        Instruction::IfIcmpeq(_) => Instruction::IfIcmpne,
+        Instruction::IfIcmpne(_) => Instruction::IfIcmpeq,
+        Instruction::IfIcmplt(_) => Instruction::IfIcmpge,
+        Instruction::IfIcmpge(_) => Instruction::IfIcmplt,
+        Instruction::IfIcmpgt(_) => Instruction::IfIcmple,
+        Instruction::IfIcmple(_) => Instruction::IfIcmpgt,
+        Instruction::IfAcmpeq(_) => Instruction::IfAcmpne,
+        Instruction::IfAcmpne(_) => Instruction::IfAcmpeq,
+        Instruction::Ifnull(_) => Instruction::Ifnonnull,
+        Instruction::Ifnonnull(_) => Instruction::Ifnull,
+        _ => return None,
+    })
+}
+
+/// The constructor of a branch with a single target, to create it again with another offset
+pub(crate) fn branch_constructor(instruction: &Instruction) -> Option<fn(i32) -> Instruction> {
+    match instruction {
+        Instruction::Goto(_) => Some(Instruction::Goto),
+        Instruction::GotoW(_) => Some(Instruction::GotoW),
+        Instruction::Jsr(_) => Some(Instruction::Jsr),
+        Instruction::JsrW(_) => Some(Instruction::JsrW),
+        // the inverse of the inverse is the same condition
+        _ => invert_condition(&invert_condition(instruction)?(0)),
+    }
+}
+
+fn table_high(low: i32, targets: &[Label]) -> EncodeResult<i32> {
+    i32::try_from(low as i64 + targets.len() as i64 - 1)
+        .map_err(|_| WriteErr(format!("Too many tableswitch targets after {}", low)))
+}
+
+fn check_length(code: &[u1]) -> EncodeResult<()> {
+    if code.len() > MAX_CODE_LENGTH {
+        return Err(WriteErr(format!(
+            "Code of {} bytes is longer than the maximum of {}",
+            code.len(),
+            MAX_CODE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// The operands of the switch instructions start at a multiple of four from the start of the code
+fn pad(code: &mut Vec<u1>) {
+    code.resize(code.len().next_multiple_of(4), 0);
+}
+
+fn encode_instruction(code: &mut Vec<u1>, instruction: &Instruction) -> EncodeResult<()> {
+    let opcode = instruction.opcode();
+    match instruction {
+        Instruction::Iload(index)
+        | Instruction::Lload(index)
+        | Instruction::Fload(index)
+        | Instruction::Dload(index)
+        | Instruction::Aload(index)
+        | Instruction::Istore(index)
+        | Instruction::Lstore(index)
+        | Instruction::Fstore(index)
+        | Instruction::Dstore(index)
+        | Instruction::Astore(index)
+        | Instruction::Ret(index) => match u1::try_from(*index) {
+            Ok(index) => code.extend([opcode, index]),
+            Err(_) => {
+                code.extend([0xc4, opcode]);
+                code.extend(index.to_be_bytes());
+            }
+        },
+        Instruction::Iinc { index, value } => match (u1::try_from(*index), i8::try_from(*value)) {
+            (Ok(index), Ok(value)) => code.extend([opcode, index, value as u1]),
+            _ => {
+                code.extend([0xc4, opcode]);
+                code.extend(index.to_be_bytes());
+                code.extend(value.to_be_bytes());
+            }
+        },
+        Instruction::Bipush(value) => code.extend([opcode, *value as u1]),
+        Instruction::Sipush(value) => {
+            code.push(opcode);
+            code.extend(value.to_be_bytes());
+        }
+        Instruction::Ldc(index) | Instruction::Newarray(index) => code.extend([opcode, *index]),
+        Instruction::LdcW(index)
+        | Instruction::Ldc2W(index)
+        | Instruction::Getstatic(index)
+        | Instruction::Putstatic(index)
+        | Instruction::Getfield(index)
+        | Instruction::Putfield(index)
+        | Instruction::Invokevirtual(index)
+        | Instruction::Invokespecial(index)
+        | Instruction::Invokestatic(index)
+        | Instruction::New(index)
+        | Instruction::Anewarray(index)
+        | Instruction::Checkcast(index)
+        | Instruction::Instanceof(index) => {
+            code.push(opcode);
+            code.extend(index.to_be_bytes());
+        }
+        Instruction::Invokeinterface { index, count } => {
+            code.push(opcode);
+            code.extend(index.to_be_bytes());
+            code.extend([*count, 0]);
+        }
+        Instruction::Invokedynamic(index) => {
+            code.push(opcode);
+            code.extend(index.to_be_bytes());
+            code.extend([0, 0]);
+        }
+        Instruction::Multianewarray { index, dimensions } => {
+            code.push(opcode);
+            code.extend(index.to_be_bytes());
+            code.push(*dimensions);
+        }
+        Instruction::GotoW(branch) | Instruction::JsrW(branch) => {
+            code.push(opcode);
+            code.extend(branch.to_be_bytes());
+        }
+        Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            offsets,
+        } => {
+            if i64::from(*high) - i64::from(*low) + 1 != offsets.len() as i64 {
+                return Err(WriteErr(format!(
+                    "tableswitch from {} to {} with {} offsets",
+                    low,
+                    high,
+                    offsets.len()
+                )));
+            }
+            code.push(opcode);
+            pad(code);
+            for value in [*default, *low, *high].iter().chain(offsets) {
+                code.extend(value.to_be_bytes());
+            }
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                return Err(WriteErr(
+                    "lookupswitch keys are not sorted in increasing order".to_string(),
+                ));
+            }
+            code.push(opcode);
+            pad(code);
+            code.extend(default.to_be_bytes());
+            code.extend((pairs.len() as u32).to_be_bytes());
+            for (key, branch) in pairs {
+                code.extend(key.to_be_bytes());
+                code.extend(branch.to_be_bytes());
+            }
+        }
+        Instruction::Ifeq(branch)
+        | Instruction::Ifne(branch)
+        | Instruction::Iflt(branch)
+        | Instruction::Ifge(branch)
+        | Instruction::Ifgt(branch)
+        | Instruction::Ifle(branch)
+        | Instruction::IfIcmpeq(branch)
+        | Instruction::IfIcmpne(branch)
+        | Instruction::IfIcmplt(branch)
+        | Instruction::IfIcmpge(branch)
+        | Instruction::IfIcmpgt(branch)
+        | Instruction::IfIcmple(branch)
+        | Instruction::IfAcmpeq(branch)
+        | Instruction::IfAcmpne(branch)
+        | Instruction::Goto(branch)
+        | Instruction::Jsr(branch)
+        | Instruction::Ifnull(branch)
+        | Instruction::Ifnonnull(branch) => {
+            let branch = i16::try_from(*branch).map_err(|_| {
+                WriteErr(format!(
+                    "{} offset {} does not fit into two bytes",
+                    instruction.mnemonic(),
+                    branch
+                ))
+            })?;
+            code.push(opcode);
+            code.extend(branch.to_be_bytes());
+        }
+        _ => code.push(opcode),
+    }
+    Ok(())
+}
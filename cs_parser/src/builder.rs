@@ -0,0 +1,382 @@
+//!
+//! Assembling a `ClassFile` from names and descriptors, without handling constant pool indices by hand
+//!
+
+use crate::*;
+use std::collections::HashMap;
+
+/// The body of a method for `ClassFileBuilder::add_method`
+#[derive(Debug, Clone, Default)]
+pub struct MethodCode {
+    pub max_stack: u2,
+    pub max_locals: u2,
+    /// Constant pool indices in the code can be obtained from `ClassFileBuilder::constant_pool`
+    pub code: Vec<u1>,
+    pub exception_table: Vec<AttributeCodeException>,
+}
+
+/// Builds a `ClassFile` step by step, adding the required constant pool entries along the way
+///
+/// The class targets version 52 (Java 8) by default. No `StackMapTable` is computed, so methods
+/// with branches need a version below 50 or the frames added by hand.
+///
+/// ```
+/// # use cs_parser::ClassFileBuilder;
+/// let class = ClassFileBuilder::new("com/example/Foo")
+///     .super_class("java/lang/Object")
+///     .add_default_constructor()
+///     .build();
+/// ```
+///
+/// Panics if the constant pool grows beyond `u2::MAX` entries
+#[derive(Debug, Clone)]
+pub struct ClassFileBuilder {
+    minor_version: u2,
+    major_version: u2,
+    constant_pool: ConstantPoolBuilder,
+    access_flags: ClassAccessFlags,
+    this_class: FromPool<cp_info::Class>,
+    super_class: Option<String>,
+    interfaces: Vec<FromPool<cp_info::Class>>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeInfo>,
+}
+
+impl ClassFileBuilder {
+    /// A public class with the binary name `name`, like `java/lang/String`, extending `java/lang/Object`
+    pub fn new(name: &str) -> Self {
+        let mut builder = Self {
+            minor_version: 0,
+            major_version: 52,
+            constant_pool: ConstantPoolBuilder::new(),
+            access_flags: ClassAccessFlag::Public | ClassAccessFlag::Super,
+            this_class: 0.into(),
+            super_class: Some("java/lang/Object".to_string()),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        builder.this_class = builder.constant_pool.class(name);
+        builder
+    }
+
+    pub fn version(mut self, major_version: u2, minor_version: u2) -> Self {
+        self.major_version = major_version;
+        self.minor_version = minor_version;
+        self
+    }
+
+    pub fn access_flags(mut self, access_flags: impl Into<ClassAccessFlags>) -> Self {
+        self.access_flags = access_flags.into();
+        self
+    }
+
+    pub fn super_class(mut self, name: &str) -> Self {
+        self.super_class = Some(name.to_string());
+        self
+    }
+
+    /// Removes the super class, which is only valid for `java/lang/Object` itself
+    pub fn no_super_class(mut self) -> Self {
+        self.super_class = None;
+        self
+    }
+
+    pub fn add_interface(mut self, name: &str) -> Self {
+        let interface = self.constant_pool.class(name);
+        self.interfaces.push(interface);
+        self
+    }
+
+    /// Adds a field
+    pub fn add_field(
+        mut self,
+        access_flags: impl Into<FieldAccessFlags>,
+        name: &str,
+        descriptor: &str,
+    ) -> Self {
+        let field = FieldInfo {
+            access_flags: access_flags.into(),
+            name_index: self.constant_pool.utf8(name),
+            descriptor_index: self.constant_pool.utf8(descriptor),
+            attributes: Vec::new(),
+        };
+        self.fields.push(field);
+        self
+    }
+
+    /// Adds a method, abstract and native methods have no code
+    pub fn add_method(
+        mut self,
+        access_flags: impl Into<MethodAccessFlags>,
+        name: &str,
+        descriptor: &str,
+        code: Option<MethodCode>,
+    ) -> Self {
+        let name_index = self.constant_pool.utf8(name);
+        let descriptor_index = self.constant_pool.utf8(descriptor);
+        let attributes = match code {
+            Some(code) => vec![AttributeInfo {
+                attribute_name_index: self.constant_pool.utf8("Code"),
+                attribute_length: 12 + code.code.len() as u4 + 8 * code.exception_table.len() as u4,
+                inner: AttributeInfoInner::Code {
+                    max_stack: code.max_stack,
+                    max_locals: code.max_locals,
+                    code: code.code.into(),
+                    exception_table: code.exception_table,
+                    attributes: Vec::new(),
+                },
+                original: None,
+            }],
+            None => Vec::new(),
+        };
+        self.methods.push(MethodInfo {
+            access_flags: access_flags.into(),
+            name_index,
+            descriptor_index,
+            attributes,
+        });
+        self
+    }
+
+    /// Adds a public constructor without parameters that calls the one of the super class
+    pub fn add_default_constructor(mut self) -> Self {
+        let super_class = self.super_class.clone().unwrap_or_default();
+        let constructor = self
+            .constant_pool
+            .method_ref(&super_class, "<init>", "()V")
+            .inner();
+        let [high, low] = constructor.to_be_bytes();
+        let code = MethodCode {
+            max_stack: 1,
+            max_locals: 1,
+            // aload_0, invokespecial, return
+            code: vec![0x2a, 0xb7, high, low, 0xb1],
+            exception_table: Vec::new(),
+        };
+        self.add_method(MethodAccessFlag::PUBLIC, "<init>", "()V", Some(code))
+    }
+
+    /// Adds a `SourceFile` attribute
+    pub fn source_file(mut self, name: &str) -> Self {
+        let attribute = AttributeInfo {
+            attribute_name_index: self.constant_pool.utf8("SourceFile"),
+            attribute_length: 2,
+            inner: AttributeInfoInner::SourceFile {
+                sourcefile_index: self.constant_pool.utf8(name),
+            },
+            original: None,
+        };
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn build(mut self) -> ClassFile {
+        let super_class = match self.super_class.take() {
+            Some(name) => self.constant_pool.class(&name).inner().into(),
+            None => 0.into(),
+        };
+        ClassFile {
+            magic: MAGIC,
+            minor_version: self.minor_version,
+            major_version: self.major_version,
+            constant_pool: self.constant_pool.build(),
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attributes: self.attributes,
+        }
+    }
+
+    /// The constant pool so far, to add the constants the code of a method refers to
+    pub fn constant_pool(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.constant_pool
+    }
+}
+
+/// Adds entries to a constant pool, reusing an equal entry if there is one already.
+/// `Long` and `Double` take up two indices
+///
+/// Panics if the constant pool grows beyond `u2::MAX` entries
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPoolBuilder {
+    constant_pool: ConstantPool,
+    indices: HashMap<CpInfoInner, u2>,
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues with an existing constant pool, like the one of a parsed class.
+    /// Its entries keep their indices
+    pub fn from_pool(constant_pool: ConstantPool) -> Self {
+        let mut indices = HashMap::new();
+        for (index, info) in constant_pool.iter_with_indices() {
+            indices.entry(info.inner.clone()).or_insert(index);
+        }
+        Self {
+            constant_pool,
+            indices,
+        }
+    }
+
+    pub fn utf8(&mut self, value: &str) -> FromPool<cp_info::Utf8> {
+        self.intern(
+            1,
+            CpInfoInner::Utf8(cp_info::Utf8 {
+                bytes: value.into(),
+            }),
+        )
+    }
+
+    /// A class by its binary name, like `java/lang/String`, or an array type by its descriptor
+    pub fn class(&mut self, name: &str) -> FromPool<cp_info::Class> {
+        let name_index = self.utf8(name);
+        self.intern(7, CpInfoInner::Class(cp_info::Class { name_index }))
+    }
+
+    pub fn string(&mut self, value: &str) -> FromPool<cp_info::String> {
+        let string_index = self.utf8(value);
+        self.intern(8, CpInfoInner::String(cp_info::String { string_index }))
+    }
+
+    pub fn integer(&mut self, value: i32) -> FromPool<cp_info::Integer> {
+        self.intern(
+            3,
+            CpInfoInner::Integer(cp_info::Integer { bytes: value as u4 }),
+        )
+    }
+
+    pub fn float(&mut self, value: f32) -> FromPool<cp_info::Float> {
+        self.intern(
+            4,
+            CpInfoInner::Float(cp_info::Float {
+                bytes: value.to_bits(),
+            }),
+        )
+    }
+
+    pub fn name_and_type(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+    ) -> FromPool<cp_info::NameAndType> {
+        let name_index = self.utf8(name);
+        let descriptor_index = self.utf8(descriptor);
+        self.intern(
+            12,
+            CpInfoInner::NameAndType(cp_info::NameAndType {
+                name_index,
+                descriptor_index,
+            }),
+        )
+    }
+
+    pub fn field_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> FromPool<cp_info::Fieldref> {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.intern(
+            9,
+            CpInfoInner::Fieldref(cp_info::Fieldref {
+                class_index,
+                name_and_type_index,
+            }),
+        )
+    }
+
+    pub fn method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> FromPool<cp_info::MethodRef> {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.intern(
+            10,
+            CpInfoInner::MethodRef(cp_info::MethodRef {
+                class_index,
+                name_and_type_index,
+            }),
+        )
+    }
+
+    pub fn interface_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> FromPool<cp_info::InterfaceMethodref> {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, descriptor);
+        self.intern(
+            11,
+            CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
+            }),
+        )
+    }
+
+    pub fn long(&mut self, value: i64) -> FromPool<cp_info::Long> {
+        self.intern(
+            5,
+            CpInfoInner::Long(cp_info::Long {
+                high_bytes: (value >> 32) as u4,
+                low_bytes: value as u4,
+            }),
+        )
+    }
+
+    pub fn double(&mut self, value: f64) -> FromPool<cp_info::Double> {
+        let bits = value.to_bits();
+        self.intern(
+            6,
+            CpInfoInner::Double(cp_info::Double {
+                high_bytes: (bits >> 32) as u4,
+                low_bytes: bits as u4,
+            }),
+        )
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> FromPool<cp_info::MethodType> {
+        let descriptor_index = self.utf8(descriptor);
+        self.intern(
+            16,
+            CpInfoInner::MethodType(cp_info::MethodType { descriptor_index }),
+        )
+    }
+
+    /// The entries added so far
+    pub fn build(self) -> ConstantPool {
+        self.constant_pool
+    }
+
+    /// Returns the index of an equal entry, or adds it
+    fn intern<T>(&mut self, tag: u1, inner: CpInfoInner) -> FromPool<T> {
+        if let Some(&index) = self.indices.get(&inner) {
+            return index.into();
+        }
+        let two_slots = matches!(inner, CpInfoInner::Long(_) | CpInfoInner::Double(_));
+        let full = u2::try_from(self.constant_pool.count())
+            .ok()
+            .filter(|&index| index < u2::MAX - u2::from(two_slots))
+            .is_none();
+        assert!(!full, "The constant pool is full");
+        self.indices
+            .insert(inner.clone(), self.constant_pool.count() as u2);
+        self.constant_pool.push(CpInfo { tag, inner }).into()
+    }
+}
@@ -0,0 +1,826 @@
+//!
+//! Turning the bytecode of simple methods back into Java-like statements
+//!
+
+use crate::disassemble::branch_targets;
+use crate::*;
+use cs_model::names::primitive_name;
+use cs_model::{FieldDescriptor, FieldType, MethodDescriptor, MethodType};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+/// Decompiles the code of a method into Java-like statements, one per line, with the labels and
+/// source lines of `disassemble_listing`:
+///
+/// ```text
+/// // line 5
+/// local1 = arg0 * 2;
+/// if (local1 <= 10) goto L0;
+/// return local1;
+/// L0:
+/// return 0;
+/// ```
+///
+/// Branches stay `goto`s and locals are named after the `LocalVariableTable` if there is one.
+/// Methods with exception handlers, switches, subroutines, monitors, `invokedynamic` or values
+/// that are on the stack at a branch are disassembled instead, below a comment with the reason.
+/// `None` for methods without code
+pub fn decompile(class: &ClassFile, method: &MethodInfo) -> Result<Option<String>> {
+    let cp = &class.constant_pool;
+    let Some((code, exception_table, attributes)) =
+        method.attributes.iter().find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code {
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => Some((code, exception_table, attributes)),
+            _ => None,
+        })
+    else {
+        return Ok(None);
+    };
+    let line_numbers = attributes
+        .iter()
+        .filter_map(|attr| match &attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => Some(line_number_table),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    let instructions = decode_code(code)?;
+
+    let mut decompiler = Decompiler {
+        cp,
+        class_name: class.this_class.get(cp).name_index.get(cp),
+        locals: Locals::new(method, attributes, cp),
+        stack: Vec::new(),
+        lines: Vec::new(),
+        labels: HashMap::new(),
+    };
+    let result = match exception_table.is_empty() {
+        true => decompiler.statements(&instructions, code.len() as u32, &line_numbers),
+        false => Err("exception handlers are not supported".to_owned()),
+    };
+    Ok(Some(match result {
+        Ok(()) => decompiler.lines.join("\n"),
+        Err(reason) => format!(
+            "// not decompiled: {}\n{}",
+            reason,
+            disassemble_listing(code, &line_numbers, cp)?
+        ),
+    }))
+}
+
+/// Why the method can't be decompiled
+type Unsupported = String;
+
+struct Decompiler<'a> {
+    cp: &'a ConstantPool,
+    class_name: &'a str,
+    locals: Locals<'a>,
+    stack: Vec<Expr>,
+    lines: Vec<String>,
+    labels: HashMap<i64, String>,
+}
+
+/// A value on the operand stack as Java source
+#[derive(Debug, Clone)]
+struct Expr {
+    text: String,
+    /// Whether the expression needs parentheses when it is an operand
+    compound: bool,
+    /// Whether the value is a `long` or `double`, which takes up two stack slots
+    wide: bool,
+    /// Whether evaluating the expression has effects, so it is kept as a statement when the value
+    /// is popped and can't be duplicated
+    effects: bool,
+    /// The offset of the `new` instruction of an object that isn't initialized yet
+    new: Option<u32>,
+    /// The operands of `lcmp` and the other comparisons, which the next `if` compares
+    comparison: Option<Box<(Expr, Expr)>>,
+}
+
+impl Expr {
+    fn atom(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            compound: false,
+            wide: false,
+            effects: false,
+            new: None,
+            comparison: None,
+        }
+    }
+
+    fn compound(text: String) -> Self {
+        Self {
+            compound: true,
+            ..Self::atom(text)
+        }
+    }
+
+    fn wide(self, wide: bool) -> Self {
+        Self { wide, ..self }
+    }
+
+    fn effects(self) -> Self {
+        Self {
+            effects: true,
+            ..self
+        }
+    }
+
+    /// The text as an operand of another expression
+    fn operand(&self) -> String {
+        match self.compound {
+            true => format!("({})", self.text),
+            false => self.text.clone(),
+        }
+    }
+}
+
+impl<'a> Decompiler<'a> {
+    fn statements(
+        &mut self,
+        instructions: &[(u32, Instruction)],
+        code_length: u32,
+        line_numbers: &[AttributeLineNumber],
+    ) -> std::result::Result<(), Unsupported> {
+        let targets = instructions
+            .iter()
+            .flat_map(|(offset, instruction)| branch_targets(*offset, instruction))
+            .collect::<BTreeSet<_>>();
+        self.labels = targets
+            .into_iter()
+            .enumerate()
+            .map(|(i, target)| (target, format!("L{}", i)))
+            .collect();
+
+        for (i, (offset, instruction)) in instructions.iter().enumerate() {
+            let next = instructions
+                .get(i + 1)
+                .map_or(code_length, |(offset, _)| *offset);
+            for line in line_numbers
+                .iter()
+                .filter(|line| u32::from(line.start_pc) == *offset)
+            {
+                self.lines.push(format!("// line {}", line.line_number));
+            }
+            if let Some(label) = self.labels.get(&i64::from(*offset)) {
+                if !self.stack.is_empty() {
+                    return Err(format!("there are values on the stack at {}", label));
+                }
+                self.lines.push(format!("{}:", label));
+            }
+            self.instruction(*offset, next, instruction)?;
+        }
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        offset: u32,
+        next: u32,
+        instruction: &Instruction,
+    ) -> std::result::Result<(), Unsupported> {
+        use Instruction as I;
+        let unsupported = || {
+            Err(format!(
+                "{} at {} is not supported",
+                instruction.mnemonic(),
+                offset
+            ))
+        };
+        match instruction {
+            I::Nop => {}
+            I::AconstNull => self.push(Expr::atom("null")),
+            I::IconstM1 => self.push(Expr::atom("-1")),
+            I::Iconst0 => self.push(Expr::atom("0")),
+            I::Iconst1 => self.push(Expr::atom("1")),
+            I::Iconst2 => self.push(Expr::atom("2")),
+            I::Iconst3 => self.push(Expr::atom("3")),
+            I::Iconst4 => self.push(Expr::atom("4")),
+            I::Iconst5 => self.push(Expr::atom("5")),
+            I::Lconst0 => self.push(Expr::atom("0L").wide(true)),
+            I::Lconst1 => self.push(Expr::atom("1L").wide(true)),
+            I::Fconst0 => self.push(Expr::atom("0.0F")),
+            I::Fconst1 => self.push(Expr::atom("1.0F")),
+            I::Fconst2 => self.push(Expr::atom("2.0F")),
+            I::Dconst0 => self.push(Expr::atom("0.0").wide(true)),
+            I::Dconst1 => self.push(Expr::atom("1.0").wide(true)),
+            I::Bipush(value) => self.push(Expr::atom(value.to_string())),
+            I::Sipush(value) => self.push(Expr::atom(value.to_string())),
+            I::Ldc(index) => self.push(self.constant((*index).into(), offset)?),
+            I::LdcW(index) | I::Ldc2W(index) => self.push(self.constant(*index, offset)?),
+            I::Iload(index) | I::Fload(index) | I::Aload(index) => self.load(*index, offset, false),
+            I::Lload(index) | I::Dload(index) => self.load(*index, offset, true),
+            I::Iload0 | I::Fload0 | I::Aload0 => self.load(0, offset, false),
+            I::Iload1 | I::Fload1 | I::Aload1 => self.load(1, offset, false),
+            I::Iload2 | I::Fload2 | I::Aload2 => self.load(2, offset, false),
+            I::Iload3 | I::Fload3 | I::Aload3 => self.load(3, offset, false),
+            I::Lload0 | I::Dload0 => self.load(0, offset, true),
+            I::Lload1 | I::Dload1 => self.load(1, offset, true),
+            I::Lload2 | I::Dload2 => self.load(2, offset, true),
+            I::Lload3 | I::Dload3 => self.load(3, offset, true),
+            I::Iaload | I::Faload | I::Aaload | I::Baload | I::Caload | I::Saload => {
+                self.array_load(offset, false)?
+            }
+            I::Laload | I::Daload => self.array_load(offset, true)?,
+            I::Istore(index)
+            | I::Lstore(index)
+            | I::Fstore(index)
+            | I::Dstore(index)
+            | I::Astore(index) => self.store(*index, offset, next)?,
+            I::Istore0 | I::Lstore0 | I::Fstore0 | I::Dstore0 | I::Astore0 => {
+                self.store(0, offset, next)?
+            }
+            I::Istore1 | I::Lstore1 | I::Fstore1 | I::Dstore1 | I::Astore1 => {
+                self.store(1, offset, next)?
+            }
+            I::Istore2 | I::Lstore2 | I::Fstore2 | I::Dstore2 | I::Astore2 => {
+                self.store(2, offset, next)?
+            }
+            I::Istore3 | I::Lstore3 | I::Fstore3 | I::Dstore3 | I::Astore3 => {
+                self.store(3, offset, next)?
+            }
+            I::Iastore
+            | I::Lastore
+            | I::Fastore
+            | I::Dastore
+            | I::Aastore
+            | I::Bastore
+            | I::Castore
+            | I::Sastore => {
+                let value = self.pop(offset)?;
+                let index = self.pop(offset)?;
+                let array = self.pop(offset)?;
+                self.statement(format!(
+                    "{}[{}] = {}",
+                    array.operand(),
+                    index.text,
+                    value.text
+                ));
+            }
+            I::Pop => {
+                let value = self.pop(offset)?;
+                self.discard(value);
+            }
+            I::Pop2 => {
+                let value = self.pop(offset)?;
+                if !value.wide {
+                    let below = self.pop(offset)?;
+                    self.discard(below);
+                }
+                self.discard(value);
+            }
+            I::Dup => {
+                let value = self.pop(offset)?;
+                if value.effects && value.new.is_none() {
+                    return Err(format!("the value at {} is used twice", offset));
+                }
+                self.push(value.clone());
+                self.push(value);
+            }
+            I::Iadd | I::Ladd | I::Fadd | I::Dadd => self.binary("+", offset)?,
+            I::Isub | I::Lsub | I::Fsub | I::Dsub => self.binary("-", offset)?,
+            I::Imul | I::Lmul | I::Fmul | I::Dmul => self.binary("*", offset)?,
+            I::Idiv | I::Ldiv | I::Fdiv | I::Ddiv => self.binary("/", offset)?,
+            I::Irem | I::Lrem | I::Frem | I::Drem => self.binary("%", offset)?,
+            I::Ishl | I::Lshl => self.binary("<<", offset)?,
+            I::Ishr | I::Lshr => self.binary(">>", offset)?,
+            I::Iushr | I::Lushr => self.binary(">>>", offset)?,
+            I::Iand | I::Land => self.binary("&", offset)?,
+            I::Ior | I::Lor => self.binary("|", offset)?,
+            I::Ixor | I::Lxor => self.binary("^", offset)?,
+            I::Ineg | I::Lneg | I::Fneg | I::Dneg => {
+                let value = self.pop(offset)?;
+                let wide = value.wide;
+                self.push(Expr::compound(format!("-{}", value.operand())).wide(wide));
+            }
+            I::Iinc { index, value } => {
+                let name = self.locals.name(*index, offset);
+                match value {
+                    1 => self.statement(format!("{}++", name)),
+                    -1 => self.statement(format!("{}--", name)),
+                    i16::MIN..=-1 => self.statement(format!("{} -= {}", name, -i32::from(*value))),
+                    _ => self.statement(format!("{} += {}", name, value)),
+                }
+            }
+            I::I2l | I::F2l | I::D2l => self.cast("long", true, offset)?,
+            I::I2f | I::L2f | I::D2f => self.cast("float", false, offset)?,
+            I::I2d | I::L2d | I::F2d => self.cast("double", true, offset)?,
+            I::L2i | I::F2i | I::D2i => self.cast("int", false, offset)?,
+            I::I2b => self.cast("byte", false, offset)?,
+            I::I2c => self.cast("char", false, offset)?,
+            I::I2s => self.cast("short", false, offset)?,
+            I::Lcmp | I::Fcmpl | I::Fcmpg | I::Dcmpl | I::Dcmpg => {
+                let right = self.pop(offset)?;
+                let left = self.pop(offset)?;
+                let class = match instruction {
+                    I::Lcmp => "Long",
+                    I::Fcmpl | I::Fcmpg => "Float",
+                    _ => "Double",
+                };
+                let mut comparison =
+                    Expr::atom(format!("{}.compare({}, {})", class, left.text, right.text));
+                comparison.effects = left.effects || right.effects;
+                comparison.comparison = Some(Box::new((left, right)));
+                self.push(comparison);
+            }
+            I::Ifeq(branch) => self.compare_zero("==", offset, *branch)?,
+            I::Ifne(branch) => self.compare_zero("!=", offset, *branch)?,
+            I::Iflt(branch) => self.compare_zero("<", offset, *branch)?,
+            I::Ifge(branch) => self.compare_zero(">=", offset, *branch)?,
+            I::Ifgt(branch) => self.compare_zero(">", offset, *branch)?,
+            I::Ifle(branch) => self.compare_zero("<=", offset, *branch)?,
+            I::IfIcmpeq(branch) | I::IfAcmpeq(branch) => self.compare("==", offset, *branch)?,
+            I::IfIcmpne(branch) | I::IfAcmpne(branch) => self.compare("!=", offset, *branch)?,
+            I::IfIcmplt(branch) => self.compare("<", offset, *branch)?,
+            I::IfIcmpge(branch) => self.compare(">=", offset, *branch)?,
+            I::IfIcmpgt(branch) => self.compare(">", offset, *branch)?,
+            I::IfIcmple(branch) => self.compare("<=", offset, *branch)?,
+            I::Ifnull(branch) => {
+                let value = self.pop(offset)?;
+                self.branch(format!("{} == null", value.operand()), offset, *branch)?;
+            }
+            I::Ifnonnull(branch) => {
+                let value = self.pop(offset)?;
+                self.branch(format!("{} != null", value.operand()), offset, *branch)?;
+            }
+            I::Goto(branch) | I::GotoW(branch) => {
+                if !self.stack.is_empty() {
+                    return Err(format!("there are values on the stack at {}", offset));
+                }
+                let label = self.label(offset, *branch);
+                self.statement(format!("goto {}", label));
+            }
+            I::Ireturn | I::Lreturn | I::Freturn | I::Dreturn | I::Areturn => {
+                let value = self.pop(offset)?;
+                self.statement(format!("return {}", value.text));
+                self.stack.clear();
+            }
+            I::Return => {
+                self.statement("return".to_owned());
+                self.stack.clear();
+            }
+            I::Getstatic(index) => {
+                let (class, name, descriptor) = self.member(*index, offset)?;
+                self.push(
+                    Expr::atom(format!("{}.{}", class_type(class), name)).wide(is_wide(descriptor)),
+                );
+            }
+            I::Putstatic(index) => {
+                let (class, name, _) = self.member(*index, offset)?;
+                let value = self.pop(offset)?;
+                self.statement(format!("{}.{} = {}", class_type(class), name, value.text));
+            }
+            I::Getfield(index) => {
+                let (_, name, descriptor) = self.member(*index, offset)?;
+                let object = self.pop(offset)?;
+                let mut field = Expr::atom(format!("{}.{}", object.operand(), name));
+                field.effects = object.effects;
+                self.push(field.wide(is_wide(descriptor)));
+            }
+            I::Putfield(index) => {
+                let (_, name, _) = self.member(*index, offset)?;
+                let value = self.pop(offset)?;
+                let object = self.pop(offset)?;
+                self.statement(format!("{}.{} = {}", object.operand(), name, value.text));
+            }
+            I::Invokevirtual(index) | I::Invokeinterface { index, .. } => {
+                self.invoke(*index, offset, false, false)?
+            }
+            I::Invokespecial(index) => self.invoke(*index, offset, false, true)?,
+            I::Invokestatic(index) => self.invoke(*index, offset, true, false)?,
+            I::New(index) => {
+                let class = self.class(*index, offset)?;
+                let mut new = Expr::atom(format!("new {}", class)).effects();
+                new.new = Some(offset);
+                self.push(new);
+            }
+            I::Newarray(atype) => {
+                let component = match atype {
+                    4 => "boolean",
+                    5 => "char",
+                    6 => "float",
+                    7 => "double",
+                    8 => "byte",
+                    9 => "short",
+                    10 => "int",
+                    11 => "long",
+                    _ => return unsupported(),
+                };
+                let length = self.pop(offset)?;
+                self.push(new_array(component, &length));
+            }
+            I::Anewarray(index) => {
+                let component = self.class(*index, offset)?;
+                let length = self.pop(offset)?;
+                self.push(new_array(&component, &length));
+            }
+            I::Arraylength => {
+                let array = self.pop(offset)?;
+                let mut length = Expr::atom(format!("{}.length", array.operand()));
+                length.effects = array.effects;
+                self.push(length);
+            }
+            I::Athrow => {
+                let value = self.pop(offset)?;
+                self.statement(format!("throw {}", value.text));
+                self.stack.clear();
+            }
+            I::Checkcast(index) => {
+                let class = self.class(*index, offset)?;
+                let value = self.pop(offset)?;
+                let mut cast = Expr::compound(format!("({}) {}", class, value.operand()));
+                cast.effects = value.effects;
+                self.push(cast);
+            }
+            I::Instanceof(index) => {
+                let class = self.class(*index, offset)?;
+                let value = self.pop(offset)?;
+                let mut test = Expr::compound(format!("{} instanceof {}", value.operand(), class));
+                test.effects = value.effects;
+                self.push(test);
+            }
+            I::DupX1
+            | I::DupX2
+            | I::Dup2
+            | I::Dup2X1
+            | I::Dup2X2
+            | I::Swap
+            | I::Jsr(_)
+            | I::JsrW(_)
+            | I::Ret(_)
+            | I::Tableswitch { .. }
+            | I::Lookupswitch { .. }
+            | I::Invokedynamic(_)
+            | I::Monitorenter
+            | I::Monitorexit
+            | I::Multianewarray { .. } => return unsupported(),
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, expr: Expr) {
+        self.stack.push(expr);
+    }
+
+    fn pop(&mut self, offset: u32) -> std::result::Result<Expr, Unsupported> {
+        self.stack
+            .pop()
+            .ok_or_else(|| format!("the stack is empty at {}", offset))
+    }
+
+    fn statement(&mut self, text: String) {
+        self.lines.push(format!("{};", text));
+    }
+
+    /// Keeps a popped value as a statement if it has effects
+    fn discard(&mut self, value: Expr) {
+        if value.effects {
+            self.statement(value.text);
+        }
+    }
+
+    fn load(&mut self, index: u2, offset: u32, wide: bool) {
+        let name = self.locals.name(index, offset);
+        self.push(Expr::atom(name).wide(wide));
+    }
+
+    /// The local variable table covers a variable from the instruction after the store
+    fn store(&mut self, index: u2, offset: u32, next: u32) -> std::result::Result<(), Unsupported> {
+        let value = self.pop(offset)?;
+        let name = self.locals.name(index, next);
+        self.statement(format!("{} = {}", name, value.text));
+        Ok(())
+    }
+
+    fn array_load(&mut self, offset: u32, wide: bool) -> std::result::Result<(), Unsupported> {
+        let index = self.pop(offset)?;
+        let array = self.pop(offset)?;
+        let mut element = Expr::atom(format!("{}[{}]", array.operand(), index.text));
+        element.effects = array.effects || index.effects;
+        self.push(element.wide(wide));
+        Ok(())
+    }
+
+    fn binary(&mut self, operator: &str, offset: u32) -> std::result::Result<(), Unsupported> {
+        let right = self.pop(offset)?;
+        let left = self.pop(offset)?;
+        let mut result = Expr::compound(format!(
+            "{} {} {}",
+            left.operand(),
+            operator,
+            right.operand()
+        ));
+        // shifts have the type of the left operand, the others have the same types on both sides
+        result.wide = left.wide;
+        result.effects = left.effects || right.effects;
+        self.push(result);
+        Ok(())
+    }
+
+    fn cast(
+        &mut self,
+        type_: &str,
+        wide: bool,
+        offset: u32,
+    ) -> std::result::Result<(), Unsupported> {
+        let value = self.pop(offset)?;
+        let mut cast = Expr::compound(format!("({}) {}", type_, value.operand())).wide(wide);
+        cast.effects = value.effects;
+        self.push(cast);
+        Ok(())
+    }
+
+    /// An `if` that compares with 0, or the operands of the comparison before it
+    fn compare_zero(
+        &mut self,
+        operator: &str,
+        offset: u32,
+        branch: i32,
+    ) -> std::result::Result<(), Unsupported> {
+        let value = self.pop(offset)?;
+        let condition = match &value.comparison {
+            Some(comparison) => format!(
+                "{} {} {}",
+                comparison.0.operand(),
+                operator,
+                comparison.1.operand()
+            ),
+            None => format!("{} {} 0", value.operand(), operator),
+        };
+        self.branch(condition, offset, branch)
+    }
+
+    fn compare(
+        &mut self,
+        operator: &str,
+        offset: u32,
+        branch: i32,
+    ) -> std::result::Result<(), Unsupported> {
+        let right = self.pop(offset)?;
+        let left = self.pop(offset)?;
+        let condition = format!("{} {} {}", left.operand(), operator, right.operand());
+        self.branch(condition, offset, branch)
+    }
+
+    fn branch(
+        &mut self,
+        condition: String,
+        offset: u32,
+        branch: i32,
+    ) -> std::result::Result<(), Unsupported> {
+        if !self.stack.is_empty() {
+            return Err(format!("there are values on the stack at {}", offset));
+        }
+        let label = self.label(offset, branch);
+        self.statement(format!("if ({}) goto {}", condition, label));
+        Ok(())
+    }
+
+    fn label(&self, offset: u32, branch: i32) -> String {
+        let target = i64::from(offset) + i64::from(branch);
+        match self.labels.get(&target) {
+            Some(label) => label.clone(),
+            None => target.to_string(),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        index: u2,
+        offset: u32,
+        is_static: bool,
+        is_special: bool,
+    ) -> std::result::Result<(), Unsupported> {
+        let (class, name, descriptor) = self.member(index, offset)?;
+        let descriptor = MethodDescriptor::from_str(descriptor)
+            .map_err(|_| format!("the descriptor {} at {} is invalid", descriptor, offset))?;
+        let mut arguments = (0..descriptor.arity())
+            .map(|_| self.pop(offset))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        arguments.reverse();
+        let arguments = arguments
+            .iter()
+            .map(|argument| argument.text.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let call = match is_static {
+            true => format!("{}.{}({})", class_type(class), name, arguments),
+            false => {
+                let receiver = self.pop(offset)?;
+                match (name, receiver.new) {
+                    ("<init>", Some(new)) => {
+                        let constructed =
+                            Expr::atom(format!("new {}({})", class_type(class), arguments))
+                                .effects();
+                        // the copies that `dup` left on the stack are the constructed object
+                        let copies = self
+                            .stack
+                            .iter_mut()
+                            .filter(|value| value.new == Some(new))
+                            .map(|copy| *copy = constructed.clone())
+                            .count();
+                        if copies == 0 {
+                            self.statement(constructed.text);
+                        }
+                        return Ok(());
+                    }
+                    ("<init>", None) if receiver.text == "this" => {
+                        let call = match class == self.class_name {
+                            true => "this",
+                            false => "super",
+                        };
+                        self.statement(format!("{}({})", call, arguments));
+                        return Ok(());
+                    }
+                    ("<init>", None) => {
+                        return Err(format!(
+                            "the constructor call at {} is not supported",
+                            offset
+                        ));
+                    }
+                    _ if is_special && receiver.text == "this" && class != self.class_name => {
+                        format!("super.{}({})", name, arguments)
+                    }
+                    _ => format!("{}.{}({})", receiver.operand(), name, arguments),
+                }
+            }
+        };
+        match descriptor.return_type() {
+            MethodType::Void => self.statement(call),
+            MethodType::Some(type_) => {
+                let wide = type_.slot_count() == 2;
+                self.push(Expr::atom(call).wide(wide).effects());
+            }
+        }
+        Ok(())
+    }
+
+    /// The class, name and descriptor of a field or method reference
+    fn member(
+        &self,
+        index: u2,
+        offset: u32,
+    ) -> std::result::Result<(&'a str, &'a str, &'a str), Unsupported> {
+        let cp = self.cp;
+        let (class, name_and_type) = match self.cp.entry(index).map(|info| &info.inner) {
+            Some(CpInfoInner::Fieldref(field)) => (field.class_index, field.name_and_type_index),
+            Some(CpInfoInner::MethodRef(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            Some(CpInfoInner::InterfaceMethodref(method)) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            _ => return Err(format!("the operand at {} is not a member", offset)),
+        };
+        let name_and_type = name_and_type.get(cp);
+        Ok((
+            class.get(cp).name_index.get(cp),
+            name_and_type.name_index.get(cp),
+            name_and_type.descriptor_index.get(cp),
+        ))
+    }
+
+    /// The Java name of a `Class` constant
+    fn class(&self, index: u2, offset: u32) -> std::result::Result<String, Unsupported> {
+        match self.cp.entry(index).map(|info| &info.inner) {
+            Some(CpInfoInner::Class(class)) => Ok(class_type(class.name_index.get(self.cp))),
+            _ => Err(format!("the operand at {} is not a class", offset)),
+        }
+    }
+
+    /// A constant loaded by `ldc` as a Java literal
+    fn constant(&self, index: u2, offset: u32) -> std::result::Result<Expr, Unsupported> {
+        let cp = self.cp;
+        Ok(match self.cp.entry(index).map(|info| &info.inner) {
+            Some(CpInfoInner::Integer(integer)) => Expr::atom((integer.bytes as i32).to_string()),
+            Some(CpInfoInner::Float(float)) => {
+                Expr::atom(float_literal(f32::from_bits(float.bytes), "Float", "F"))
+            }
+            Some(CpInfoInner::Long(long)) => {
+                let value = (u64::from(long.high_bytes) << 32 | u64::from(long.low_bytes)) as i64;
+                Expr::atom(format!("{}L", value)).wide(true)
+            }
+            Some(CpInfoInner::Double(double)) => {
+                let bits = u64::from(double.high_bytes) << 32 | u64::from(double.low_bytes);
+                Expr::atom(float_literal(f64::from_bits(bits), "Double", "")).wide(true)
+            }
+            Some(CpInfoInner::String(string)) => {
+                Expr::atom(format!("{:?}", string.string_index.get(cp)))
+            }
+            Some(CpInfoInner::Class(class)) => {
+                Expr::atom(format!("{}.class", class_type(class.name_index.get(cp))))
+            }
+            _ => {
+                return Err(format!(
+                    "the constant loaded at {} is not supported",
+                    offset
+                ))
+            }
+        })
+    }
+}
+
+/// The names of the local variables
+struct Locals<'a> {
+    table: Vec<&'a AttributeLocalVariableTable>,
+    cp: &'a ConstantPool,
+    /// The number of the parameter in each slot, `None` for `this`
+    parameters: HashMap<u2, Option<usize>>,
+}
+
+impl<'a> Locals<'a> {
+    fn new(method: &MethodInfo, attributes: &'a [AttributeInfo], cp: &'a ConstantPool) -> Self {
+        let mut parameters = HashMap::new();
+        let mut slot = 0;
+        if !method.access_flags.contains(MethodAccessFlag::STATIC) {
+            parameters.insert(0, None);
+            slot = 1;
+        }
+        if let Ok(descriptor) = MethodDescriptor::from_str(method.descriptor_index.get(cp)) {
+            for (i, parameter) in descriptor.parameters().iter().enumerate() {
+                parameters.insert(slot, Some(i));
+                slot += parameter.slot_count() as u2;
+            }
+        }
+        let table = attributes
+            .iter()
+            .filter_map(|attr| match &attr.inner {
+                AttributeInfoInner::LocalVariableTable {
+                    local_variable_table,
+                } => Some(local_variable_table),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        Self {
+            table,
+            cp,
+            parameters,
+        }
+    }
+
+    /// The name of the variable in the slot at the offset
+    fn name(&self, index: u2, offset: u32) -> String {
+        let variable = self.table.iter().find(|variable| {
+            let start = u32::from(variable.start_pc);
+            variable.index == index && (start..start + u32::from(variable.length)).contains(&offset)
+        });
+        match (variable, self.parameters.get(&index)) {
+            (Some(variable), _) => variable.name_index.get(self.cp).to_owned(),
+            (None, Some(None)) => "this".to_owned(),
+            (None, Some(Some(parameter))) => format!("arg{}", parameter),
+            (None, None) => format!("local{}", index),
+        }
+    }
+}
+
+fn is_wide(descriptor: &str) -> bool {
+    matches!(descriptor, "J" | "D")
+}
+
+/// The Java name of a class or array type with the simple names of classes, `java/lang/String`
+/// becomes `String` and `[[I` becomes `int[][]`
+fn class_type(internal: &str) -> String {
+    match internal.starts_with('[') {
+        true => match FieldDescriptor::from_str(internal) {
+            Ok(descriptor) => java_type(&descriptor.0),
+            Err(_) => internal.to_owned(),
+        },
+        false => internal.rsplit('/').next().unwrap_or(internal).to_owned(),
+    }
+}
+
+fn java_type(type_: &FieldType) -> String {
+    match type_ {
+        FieldType::Object(name) => class_type(name),
+        FieldType::Array(component) => format!("{}[]", java_type(component)),
+        primitive => primitive_name(primitive)
+            .expect("the other types are primitive")
+            .to_owned(),
+    }
+}
+
+/// `new int[length]`, with the dimensions of an array component after the length like
+/// `new int[length][]`
+fn new_array(component: &str, length: &Expr) -> Expr {
+    let (base, dimensions) = component.split_at(component.find('[').unwrap_or(component.len()));
+    Expr::atom(format!("new {}[{}]{}", base, length.text, dimensions)).effects()
+}
+
+/// A `float` or `double` literal, with the constants of the class for the values that don't have one
+fn float_literal(value: impl Into<f64> + Debug + Copy, class: &str, suffix: &str) -> String {
+    match value.into() {
+        wide if wide.is_nan() => format!("{}.NaN", class),
+        f64::INFINITY => format!("{}.POSITIVE_INFINITY", class),
+        f64::NEG_INFINITY => format!("{}.NEGATIVE_INFINITY", class),
+        _ => format!("{:?}{}", value, suffix),
+    }
+}
@@ -0,0 +1,371 @@
+//!
+//! Comparing two classes by their content, independent of the layout of their constant pools
+//!
+
+use crate::disassemble::describe_constant;
+use crate::write::write_resolved_attribute;
+use crate::{u2, AttributeInfo, AttributeInfoInner, ClassFile, ConstantPool};
+use std::fmt::{Display, Formatter};
+
+/// A way in which a class differs from an older version of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The class itself, or the field or method that differs
+    pub member: Member,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Member {
+    Class,
+    Field { name: String, descriptor: String },
+    Method { name: String, descriptor: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The field or method only exists in the new class
+    Added,
+    /// The field or method only exists in the old class
+    Removed,
+    /// The internal name of the class
+    Name {
+        old: String,
+        new: String,
+    },
+    /// The major and minor version
+    Version {
+        old: (u2, u2),
+        new: (u2, u2),
+    },
+    /// The flags as they are displayed, like `ACC_PUBLIC, ACC_STATIC`
+    AccessFlags {
+        old: String,
+        new: String,
+    },
+    SuperClass {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    InterfaceAdded(String),
+    InterfaceRemoved(String),
+    /// The value of a field from its `ConstantValue` attribute, like `1`, `2L` or `"text"`
+    ConstantValue {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// An attribute with the name only exists in the new class or member
+    AttributeAdded(String),
+    /// An attribute with the name only exists in the old class or member
+    AttributeRemoved(String),
+    /// The attribute with the name has a different content. The `Code` of a method is compared
+    /// by its instructions and the constants they use
+    AttributeChanged(String),
+}
+
+/// The differences between the old and the new version of a class. Constants are compared by
+/// their value, so classes that only differ in the order of their constant pools are equal.
+///
+/// Fields and methods are matched by their name and descriptor, so changing the descriptor shows
+/// up as one removed and one added member
+pub fn diff_classes(old: &ClassFile, new: &ClassFile) -> Vec<Difference> {
+    let (old_cp, new_cp) = (&old.constant_pool, &new.constant_pool);
+    let mut diff = Diff {
+        old_cp,
+        new_cp,
+        differences: Vec::new(),
+    };
+
+    let class = Member::Class;
+    let name = |class: &ClassFile| {
+        let cp = &class.constant_pool;
+        class.this_class.get(cp).name_index.get(cp).to_owned()
+    };
+    if name(old) != name(new) {
+        diff.push(
+            &class,
+            Change::Name {
+                old: name(old),
+                new: name(new),
+            },
+        );
+    }
+    let version = |class: &ClassFile| (class.major_version, class.minor_version);
+    if version(old) != version(new) {
+        diff.push(
+            &class,
+            Change::Version {
+                old: version(old),
+                new: version(new),
+            },
+        );
+    }
+    diff.flags(
+        &class,
+        old.access_flags.to_string(),
+        new.access_flags.to_string(),
+    );
+    let super_class = |class: &ClassFile| {
+        let cp = &class.constant_pool;
+        class
+            .super_class
+            .maybe_get(cp)
+            .map(|class| class.name_index.get(cp).to_owned())
+    };
+    if super_class(old) != super_class(new) {
+        diff.push(
+            &class,
+            Change::SuperClass {
+                old: super_class(old),
+                new: super_class(new),
+            },
+        );
+    }
+    let interfaces = |class: &ClassFile| {
+        let cp = &class.constant_pool;
+        class
+            .interfaces
+            .iter()
+            .map(|interface| interface.get(cp).name_index.get(cp).to_owned())
+            .collect::<Vec<_>>()
+    };
+    let (old_interfaces, new_interfaces) = (interfaces(old), interfaces(new));
+    for interface in old_interfaces
+        .iter()
+        .filter(|i| !new_interfaces.contains(i))
+    {
+        diff.push(&class, Change::InterfaceRemoved(interface.clone()));
+    }
+    for interface in new_interfaces
+        .iter()
+        .filter(|i| !old_interfaces.contains(i))
+    {
+        diff.push(&class, Change::InterfaceAdded(interface.clone()));
+    }
+    diff.attributes(&class, &old.attributes, &new.attributes);
+
+    diff.members(
+        &old.fields,
+        &new.fields,
+        |field| (field.name_index.inner(), field.descriptor_index.inner()),
+        |name, descriptor| Member::Field { name, descriptor },
+        |diff, member, old_field, new_field| {
+            diff.flags(
+                member,
+                old_field.access_flags.to_string(),
+                new_field.access_flags.to_string(),
+            );
+            let old_value = constant_value(&old_field.attributes, diff.old_cp);
+            let new_value = constant_value(&new_field.attributes, diff.new_cp);
+            if old_value != new_value {
+                diff.push(
+                    member,
+                    Change::ConstantValue {
+                        old: old_value,
+                        new: new_value,
+                    },
+                );
+            }
+            diff.attributes(member, &old_field.attributes, &new_field.attributes);
+        },
+    );
+    diff.members(
+        &old.methods,
+        &new.methods,
+        |method| (method.name_index.inner(), method.descriptor_index.inner()),
+        |name, descriptor| Member::Method { name, descriptor },
+        |diff, member, old_method, new_method| {
+            diff.flags(
+                member,
+                old_method.access_flags.to_string(),
+                new_method.access_flags.to_string(),
+            );
+            diff.attributes(member, &old_method.attributes, &new_method.attributes);
+        },
+    );
+
+    diff.differences
+}
+
+struct Diff<'a> {
+    old_cp: &'a ConstantPool,
+    new_cp: &'a ConstantPool,
+    differences: Vec<Difference>,
+}
+
+impl Diff<'_> {
+    fn push(&mut self, member: &Member, change: Change) {
+        self.differences.push(Difference {
+            member: member.clone(),
+            change,
+        });
+    }
+
+    fn flags(&mut self, member: &Member, old: String, new: String) {
+        if old != new {
+            self.push(member, Change::AccessFlags { old, new });
+        }
+    }
+
+    /// Reports the removed and added members, and compares the members that are in both classes
+    fn members<T>(
+        &mut self,
+        old: &[T],
+        new: &[T],
+        indices: impl Fn(&T) -> (u2, u2),
+        member: impl Fn(String, String) -> Member,
+        compare: impl Fn(&mut Self, &Member, &T, &T),
+    ) {
+        let old = named(old, self.old_cp, &indices);
+        let new = named(new, self.new_cp, &indices);
+        for (name, descriptor, _) in &old {
+            if !new.iter().any(|(n, d, _)| n == name && d == descriptor) {
+                self.push(&member(name.clone(), descriptor.clone()), Change::Removed);
+            }
+        }
+        for (name, descriptor, new_item) in &new {
+            let member = member(name.clone(), descriptor.clone());
+            match old.iter().find(|(n, d, _)| n == name && d == descriptor) {
+                Some((_, _, old_item)) => compare(self, &member, old_item, new_item),
+                None => self.push(&member, Change::Added),
+            }
+        }
+    }
+
+    /// Compares the attributes with the same name, the `ConstantValue` of fields is compared
+    /// separately
+    fn attributes(&mut self, member: &Member, old: &[AttributeInfo], new: &[AttributeInfo]) {
+        let old = resolved_attributes(old, self.old_cp);
+        let new = resolved_attributes(new, self.new_cp);
+        for (name, _) in old
+            .iter()
+            .filter(|(name, _)| !new.iter().any(|(n, _)| n == name))
+        {
+            self.push(member, Change::AttributeRemoved(name.clone()));
+        }
+        for (name, new_contents) in &new {
+            match old.iter().find(|(n, _)| n == name) {
+                Some((_, old_contents)) if old_contents != new_contents => {
+                    self.push(member, Change::AttributeChanged(name.clone()))
+                }
+                Some(_) => {}
+                None => self.push(member, Change::AttributeAdded(name.clone())),
+            }
+        }
+    }
+}
+
+/// The members with their name and descriptor
+fn named<'a, T>(
+    members: &'a [T],
+    cp: &ConstantPool,
+    indices: &impl Fn(&T) -> (u2, u2),
+) -> Vec<(String, String, &'a T)> {
+    members
+        .iter()
+        .map(|member| {
+            let (name, descriptor) = indices(member);
+            (
+                describe_name(name, cp),
+                describe_name(descriptor, cp),
+                member,
+            )
+        })
+        .collect()
+}
+
+fn describe_name(index: u2, cp: &ConstantPool) -> String {
+    cp.utf8(index)
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("#{}", index))
+}
+
+/// The contents of the attributes with constants instead of indices, grouped by the name in the
+/// order they first appear
+fn resolved_attributes(
+    attributes: &[AttributeInfo],
+    cp: &ConstantPool,
+) -> Vec<(String, Vec<Option<Vec<u8>>>)> {
+    let mut resolved: Vec<(String, Vec<_>)> = Vec::new();
+    for attribute in attributes {
+        if matches!(attribute.inner, AttributeInfoInner::ConstantValue { .. }) {
+            continue;
+        }
+        let name = describe_name(attribute.attribute_name_index.inner(), cp);
+        let contents = write_resolved_attribute(&attribute.inner, cp).ok();
+        match resolved.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, all)) => all.push(contents),
+            None => resolved.push((name, vec![contents])),
+        }
+    }
+    resolved
+}
+
+fn constant_value(attributes: &[AttributeInfo], cp: &ConstantPool) -> Option<String> {
+    attributes
+        .iter()
+        .find_map(|attribute| match &attribute.inner {
+            AttributeInfoInner::ConstantValue {
+                constantvalue_index,
+            } => Some(describe_constant(constantvalue_index.inner(), cp)),
+            _ => None,
+        })
+}
+
+impl Display for Member {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Class => f.write_str("class"),
+            Self::Field { name, descriptor } => write!(f, "field {}:{}", name, descriptor),
+            Self::Method { name, descriptor } => write!(f, "method {}{}", name, descriptor),
+        }
+    }
+}
+
+/// One line starting with `+` for additions, `-` for removals and `~` for changes
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let member = &self.member;
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_owned());
+        match &self.change {
+            Change::Added => write!(f, "+ {}", member),
+            Change::Removed => write!(f, "- {}", member),
+            Change::Name { old, new } => write!(f, "~ {}: name {} -> {}", member, old, new),
+            Change::Version { old, new } => write!(
+                f,
+                "~ {}: version {}.{} -> {}.{}",
+                member, old.0, old.1, new.0, new.1
+            ),
+            Change::AccessFlags { old, new } => {
+                let or_none =
+                    |flags: &str| if flags.is_empty() { "none" } else { flags }.to_owned();
+                write!(
+                    f,
+                    "~ {}: flags {} -> {}",
+                    member,
+                    or_none(old),
+                    or_none(new)
+                )
+            }
+            Change::SuperClass { old, new } => write!(
+                f,
+                "~ {}: super class {} -> {}",
+                member,
+                or_none(old),
+                or_none(new)
+            ),
+            Change::InterfaceAdded(name) => write!(f, "+ {}: interface {}", member, name),
+            Change::InterfaceRemoved(name) => write!(f, "- {}: interface {}", member, name),
+            Change::ConstantValue { old, new } => write!(
+                f,
+                "~ {}: constant value {} -> {}",
+                member,
+                or_none(old),
+                or_none(new)
+            ),
+            Change::AttributeAdded(name) => write!(f, "+ {}: attribute {}", member, name),
+            Change::AttributeRemoved(name) => write!(f, "- {}: attribute {}", member, name),
+            Change::AttributeChanged(name) => write!(f, "~ {}: attribute {} changed", member, name),
+        }
+    }
+}
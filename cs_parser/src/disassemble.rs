@@ -0,0 +1,394 @@
+//!
+//! Rendering bytecode as text, with the constant pool operands resolved
+//!
+
+use crate::cp_info::{MethodHandle, MethodHandleIndex, NameAndType};
+use crate::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// Disassembles the code of a method, one instruction per line, like
+/// `    3: invokevirtual java/io/PrintStream.println:(Ljava/lang/String;)V`
+///
+/// Branch targets are absolute offsets into the code
+pub fn disassemble(code: &[u1], cp: &ConstantPool) -> Result<String> {
+    let instructions = decode_code(code)?;
+    Ok(instructions
+        .iter()
+        .map(|(offset, instruction)| {
+            format!(
+                "{:>5}: {}",
+                offset,
+                display_instruction(*offset, instruction, cp)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Disassembles the code like `javap -c -l`. Branch targets are labels like `L0`, which are
+/// printed above the instruction they point to, together with the source lines from the
+/// `LineNumberTable` that start at it:
+///
+/// ```text
+/// // line 16
+/// L0:
+///    28: bipush 10
+/// ```
+pub fn disassemble_listing(
+    code: &[u1],
+    line_numbers: &[AttributeLineNumber],
+    cp: &ConstantPool,
+) -> Result<String> {
+    listing(code, line_numbers, cp, &|line| format!("// line {}", line))
+}
+
+/// Disassembles the code like `disassemble_listing`, but with the lines of the source file the
+/// code was compiled from in place of their numbers, like `objdump -S`:
+///
+/// ```text
+/// // 16: for (int i = 1; i <= 10; i++) {
+/// L0:
+///    28: bipush 10
+/// ```
+///
+/// Lines that are not in the source are printed like `// line 16`
+pub fn disassemble_with_source(
+    code: &[u1],
+    line_numbers: &[AttributeLineNumber],
+    source: &str,
+    cp: &ConstantPool,
+) -> Result<String> {
+    let source = source.lines().collect::<Vec<_>>();
+    listing(code, line_numbers, cp, &|line| match usize::from(line)
+        .checked_sub(1)
+        .and_then(|line| source.get(line))
+    {
+        Some(text) => format!("// {}: {}", line, text.trim()),
+        None => format!("// line {}", line),
+    })
+}
+
+/// The listing with `line` rendering the source lines
+fn listing(
+    code: &[u1],
+    line_numbers: &[AttributeLineNumber],
+    cp: &ConstantPool,
+    line: &dyn Fn(u2) -> String,
+) -> Result<String> {
+    let instructions = decode_code(code)?;
+    let targets = instructions
+        .iter()
+        .flat_map(|(offset, instruction)| branch_targets(*offset, instruction))
+        .collect::<BTreeSet<_>>();
+    let labels = targets
+        .into_iter()
+        .enumerate()
+        .map(|(i, target)| (target, format!("L{}", i)))
+        .collect::<HashMap<_, _>>();
+    let label = |target: i64| match labels.get(&target) {
+        Some(label) => label.clone(),
+        None => target.to_string(),
+    };
+
+    let mut lines = Vec::new();
+    for (offset, instruction) in &instructions {
+        for number in line_numbers
+            .iter()
+            .filter(|number| u32::from(number.start_pc) == *offset)
+        {
+            lines.push(line(number.line_number));
+        }
+        if let Some(label) = labels.get(&i64::from(*offset)) {
+            lines.push(format!("{}:", label));
+        }
+        lines.push(format!(
+            "{:>5}: {}",
+            offset,
+            render_instruction(*offset, instruction, cp, &label)
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Renders a single instruction at `offset` with its operands.
+/// Constant pool indices that don't point to a fitting entry are shown as `#index`
+pub fn display_instruction(offset: u32, instruction: &Instruction, cp: &ConstantPool) -> String {
+    render_instruction(offset, instruction, cp, &|target| target.to_string())
+}
+
+/// Renders the instruction with `label` rendering the absolute branch targets
+fn render_instruction(
+    offset: u32,
+    instruction: &Instruction,
+    cp: &ConstantPool,
+    label: &dyn Fn(i64) -> String,
+) -> String {
+    let mnemonic = instruction.mnemonic();
+    let target = |branch: i32| label(offset as i64 + branch as i64);
+    let operands = match instruction {
+        Instruction::Bipush(value) => value.to_string(),
+        Instruction::Sipush(value) => value.to_string(),
+        Instruction::Ldc(index) => loadable_operand((*index).into(), cp),
+        Instruction::LdcW(index) | Instruction::Ldc2W(index) => loadable_operand(*index, cp),
+        Instruction::Iload(index)
+        | Instruction::Lload(index)
+        | Instruction::Fload(index)
+        | Instruction::Dload(index)
+        | Instruction::Aload(index)
+        | Instruction::Istore(index)
+        | Instruction::Lstore(index)
+        | Instruction::Fstore(index)
+        | Instruction::Dstore(index)
+        | Instruction::Astore(index)
+        | Instruction::Ret(index) => index.to_string(),
+        Instruction::Iinc { index, value } => format!("{}, {}", index, value),
+        Instruction::Ifeq(branch)
+        | Instruction::Ifne(branch)
+        | Instruction::Iflt(branch)
+        | Instruction::Ifge(branch)
+        | Instruction::Ifgt(branch)
+        | Instruction::Ifle(branch)
+        | Instruction::IfIcmpeq(branch)
+        | Instruction::IfIcmpne(branch)
+        | Instruction::IfIcmplt(branch)
+        | Instruction::IfIcmpge(branch)
+        | Instruction::IfIcmpgt(branch)
+        | Instruction::IfIcmple(branch)
+        | Instruction::IfAcmpeq(branch)
+        | Instruction::IfAcmpne(branch)
+        | Instruction::Goto(branch)
+        | Instruction::Jsr(branch)
+        | Instruction::Ifnull(branch)
+        | Instruction::Ifnonnull(branch)
+        | Instruction::GotoW(branch)
+        | Instruction::JsrW(branch) => target(*branch),
+        Instruction::Tableswitch {
+            default,
+            low,
+            offsets,
+            ..
+        } => {
+            let cases = offsets
+                .iter()
+                .zip(*low..)
+                .map(|(branch, key)| format!("{}: {}, ", key, target(*branch)))
+                .collect::<String>();
+            format!("{{ {}default: {} }}", cases, target(*default))
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let cases = pairs
+                .iter()
+                .map(|(key, branch)| format!("{}: {}, ", key, target(*branch)))
+                .collect::<String>();
+            format!("{{ {}default: {} }}", cases, target(*default))
+        }
+        Instruction::Getstatic(index)
+        | Instruction::Putstatic(index)
+        | Instruction::Getfield(index)
+        | Instruction::Putfield(index)
+        | Instruction::Invokevirtual(index)
+        | Instruction::Invokespecial(index)
+        | Instruction::Invokestatic(index)
+        | Instruction::Invokeinterface { index, .. } => member_operand(*index, cp),
+        Instruction::Invokedynamic(index) => match constant(*index, cp) {
+            Some(CpInfoInner::InvokeDynamic(dynamic)) => format!(
+                "#{} {}",
+                dynamic.bootstrap_method_attr_index,
+                display_name_and_type(dynamic.name_and_type_index.get(cp), cp)
+            ),
+            _ => format!("#{}", index),
+        },
+        Instruction::New(index)
+        | Instruction::Anewarray(index)
+        | Instruction::Checkcast(index)
+        | Instruction::Instanceof(index) => class_operand(*index, cp),
+        Instruction::Newarray(atype) => match atype {
+            4 => "boolean".to_string(),
+            5 => "char".to_string(),
+            6 => "float".to_string(),
+            7 => "double".to_string(),
+            8 => "byte".to_string(),
+            9 => "short".to_string(),
+            10 => "int".to_string(),
+            11 => "long".to_string(),
+            _ => atype.to_string(),
+        },
+        Instruction::Multianewarray { index, dimensions } => {
+            format!("{}, {}", class_operand(*index, cp), dimensions)
+        }
+        _ => return mnemonic.to_string(),
+    };
+    format!("{} {}", mnemonic, operands)
+}
+
+/// The absolute offsets the instruction at `offset` can branch to
+pub(crate) fn branch_targets(offset: u32, instruction: &Instruction) -> Vec<i64> {
+    let target = |branch: &i32| offset as i64 + *branch as i64;
+    match instruction {
+        Instruction::Ifeq(branch)
+        | Instruction::Ifne(branch)
+        | Instruction::Iflt(branch)
+        | Instruction::Ifge(branch)
+        | Instruction::Ifgt(branch)
+        | Instruction::Ifle(branch)
+        | Instruction::IfIcmpeq(branch)
+        | Instruction::IfIcmpne(branch)
+        | Instruction::IfIcmplt(branch)
+        | Instruction::IfIcmpge(branch)
+        | Instruction::IfIcmpgt(branch)
+        | Instruction::IfIcmple(branch)
+        | Instruction::IfAcmpeq(branch)
+        | Instruction::IfAcmpne(branch)
+        | Instruction::Goto(branch)
+        | Instruction::Jsr(branch)
+        | Instruction::Ifnull(branch)
+        | Instruction::Ifnonnull(branch)
+        | Instruction::GotoW(branch)
+        | Instruction::JsrW(branch) => vec![target(branch)],
+        Instruction::Tableswitch {
+            default, offsets, ..
+        } => offsets.iter().chain([default]).map(target).collect(),
+        Instruction::Lookupswitch { default, pairs } => pairs
+            .iter()
+            .map(|(_, branch)| branch)
+            .chain([default])
+            .map(target)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Renders a constant that can be loaded with `ldc` or passed to a bootstrap method
+pub fn display_constant(info: &CpInfoInner, cp: &ConstantPool) -> String {
+    match info {
+        CpInfoInner::Integer(int) => int.value().to_string(),
+        CpInfoInner::Float(float) => format!("{:?}f", float.value()),
+        CpInfoInner::Long(long) => format!("{}L", long.value()),
+        CpInfoInner::Double(double) => format!("{:?}", double.value()),
+        CpInfoInner::Class(class) => class.name_index.get(cp).to_string(),
+        CpInfoInner::String(string) => format!("{:?}", string.string_index.get(cp)),
+        CpInfoInner::MethodHandle(handle) => display_method_handle(handle, cp),
+        CpInfoInner::MethodType(method_type) => method_type.descriptor_index.get(cp).to_string(),
+        CpInfoInner::Dynamic(dynamic) => format!(
+            "dynamic {} {}",
+            dynamic.bootstrap_method_attr_index,
+            display_name_and_type(dynamic.name_and_type_index.get(cp), cp)
+        ),
+        info => format!("{:?}", info),
+    }
+}
+
+/// Renders a method handle like `REF_invokeStatic java/lang/Integer.valueOf:(I)Ljava/lang/Integer;`
+pub fn display_method_handle(handle: &MethodHandle, cp: &ConstantPool) -> String {
+    let kind = match handle.reference_kind {
+        1 => "REF_getField",
+        2 => "REF_getStatic",
+        3 => "REF_putField",
+        4 => "REF_putStatic",
+        5 => "REF_invokeVirtual",
+        6 => "REF_invokeStatic",
+        7 => "REF_invokeSpecial",
+        8 => "REF_newInvokeSpecial",
+        9 => "REF_invokeInterface",
+        _ => "REF_unknown",
+    };
+    let (class, name_and_type_index) = match handle.reference_index {
+        MethodHandleIndex::Field(field) => {
+            let field = field.get(cp);
+            (field.class_index, field.name_and_type_index)
+        }
+        MethodHandleIndex::Method(method) => {
+            let method = method.get(cp);
+            (method.class_index, method.name_and_type_index)
+        }
+        MethodHandleIndex::MethodOrInterface(method) => method.get(cp),
+        MethodHandleIndex::Interface(method) => {
+            let method = method.get(cp);
+            (method.class_index, method.name_and_type_index)
+        }
+    };
+    format!(
+        "{} {}.{}",
+        kind,
+        class.get(cp).name_index.get(cp),
+        display_name_and_type(name_and_type_index.get(cp), cp)
+    )
+}
+
+/// Renders a name and type like `println:(Ljava/lang/String;)V`
+pub fn display_name_and_type(name_and_type: &NameAndType, cp: &ConstantPool) -> String {
+    format!(
+        "{}:{}",
+        name_and_type.name_index.get(cp),
+        name_and_type.descriptor_index.get(cp)
+    )
+}
+
+/// Renders any constant without the indices it contains, so constants of different pools can be
+/// compared
+pub(crate) fn describe_constant(index: u2, cp: &ConstantPool) -> String {
+    match constant(index, cp) {
+        Some(CpInfoInner::Utf8(utf8)) => format!("{:?}", utf8.bytes.as_str()),
+        Some(
+            CpInfoInner::Fieldref(_)
+            | CpInfoInner::MethodRef(_)
+            | CpInfoInner::InterfaceMethodref(_),
+        ) => member_operand(index, cp),
+        Some(CpInfoInner::NameAndType(name_and_type)) => display_name_and_type(name_and_type, cp),
+        Some(CpInfoInner::InvokeDynamic(dynamic)) => format!(
+            "invokedynamic {} {}",
+            dynamic.bootstrap_method_attr_index,
+            display_name_and_type(dynamic.name_and_type_index.get(cp), cp)
+        ),
+        Some(CpInfoInner::Module(module)) => format!("module {}", module.name_index.get(cp)),
+        Some(CpInfoInner::Package(package)) => format!("package {}", package.name_index.get(cp)),
+        Some(info) => display_constant(info, cp),
+        None => format!("#{}", index),
+    }
+}
+
+/// The entry at `index`, if there is one
+fn constant(index: u2, cp: &ConstantPool) -> Option<&CpInfoInner> {
+    cp.entry(index).map(|info| &info.inner)
+}
+
+fn loadable_operand(index: u2, cp: &ConstantPool) -> String {
+    match constant(index, cp) {
+        Some(
+            info @ (CpInfoInner::Integer(_)
+            | CpInfoInner::Float(_)
+            | CpInfoInner::Long(_)
+            | CpInfoInner::Double(_)
+            | CpInfoInner::Class(_)
+            | CpInfoInner::String(_)
+            | CpInfoInner::MethodHandle(_)
+            | CpInfoInner::MethodType(_)
+            | CpInfoInner::Dynamic(_)),
+        ) => display_constant(info, cp),
+        _ => format!("#{}", index),
+    }
+}
+
+fn class_operand(index: u2, cp: &ConstantPool) -> String {
+    match constant(index, cp) {
+        Some(CpInfoInner::Class(class)) => class.name_index.get(cp).to_string(),
+        _ => format!("#{}", index),
+    }
+}
+
+/// A field or method as `class.name:descriptor`
+fn member_operand(index: u2, cp: &ConstantPool) -> String {
+    let (class, name_and_type) = match constant(index, cp) {
+        Some(CpInfoInner::Fieldref(field)) => (field.class_index, field.name_and_type_index),
+        Some(CpInfoInner::MethodRef(method)) => (method.class_index, method.name_and_type_index),
+        Some(CpInfoInner::InterfaceMethodref(method)) => {
+            (method.class_index, method.name_and_type_index)
+        }
+        _ => return format!("#{}", index),
+    };
+    format!(
+        "{}.{}",
+        class.get(cp).name_index.get(cp),
+        display_name_and_type(name_and_type.get(cp), cp)
+    )
+}
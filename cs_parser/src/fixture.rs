@@ -0,0 +1,150 @@
+//!
+//! Class files assembled byte by byte for the tests, to cover edge cases without a JDK and
+//! without going through the writer
+//!
+
+use crate::{u1, u2, MAGIC};
+use std::collections::HashMap;
+
+/// A class with the name `Fixture` and no members
+pub(crate) fn empty_class() -> Vec<u1> {
+    Fixture::new("Fixture").bytes()
+}
+
+/// A class with the static method `run()V` that has the code
+pub(crate) fn class_with_method(code: &[u1]) -> Vec<u1> {
+    Fixture::new("Fixture")
+        .method("run", "()V", 2, 1, code)
+        .bytes()
+}
+
+/// A class with the attributes, given by their name and content
+pub(crate) fn class_with_attributes(attributes: &[(&str, &[u1])]) -> Vec<u1> {
+    attributes
+        .iter()
+        .fold(Fixture::new("Fixture"), |fixture, (name, content)| {
+            fixture.attribute(name, content)
+        })
+        .bytes()
+}
+
+/// The bytes of a version 52 class that extends `java/lang/Object`. The constant pool only has
+/// the `Utf8` and `Class` constants, which are added when they are used
+#[derive(Debug)]
+pub(crate) struct Fixture {
+    constant_pool: Vec<u1>,
+    constant_count: u2,
+    utf8: HashMap<String, u2>,
+    this_class: u2,
+    super_class: u2,
+    methods: Vec<Vec<u1>>,
+    attributes: Vec<Vec<u1>>,
+}
+
+impl Fixture {
+    pub(crate) fn new(name: &str) -> Self {
+        let mut fixture = Self {
+            constant_pool: Vec::new(),
+            constant_count: 0,
+            utf8: HashMap::new(),
+            this_class: 0,
+            super_class: 0,
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        fixture.this_class = fixture.class(name);
+        fixture.super_class = fixture.class("java/lang/Object");
+        fixture
+    }
+
+    /// The index of the `Utf8` constant, which has to be ASCII
+    pub(crate) fn utf8(&mut self, value: &str) -> u2 {
+        assert!(value.is_ascii(), "Fixture constants have to be ASCII");
+        if let Some(&index) = self.utf8.get(value) {
+            return index;
+        }
+        self.constant_pool.push(1);
+        self.constant_pool
+            .extend_from_slice(&(value.len() as u2).to_be_bytes());
+        self.constant_pool.extend_from_slice(value.as_bytes());
+        let index = self.next_index();
+        self.utf8.insert(value.to_owned(), index);
+        index
+    }
+
+    /// The index of a new `Class` constant
+    pub(crate) fn class(&mut self, name: &str) -> u2 {
+        let name_index = self.utf8(name);
+        self.constant_pool.push(7);
+        self.constant_pool
+            .extend_from_slice(&name_index.to_be_bytes());
+        self.next_index()
+    }
+
+    fn next_index(&mut self) -> u2 {
+        self.constant_count += 1;
+        self.constant_count
+    }
+
+    /// Adds a `public static` method with a `Code` attribute without exception handlers
+    pub(crate) fn method(
+        mut self,
+        name: &str,
+        descriptor: &str,
+        max_stack: u2,
+        max_locals: u2,
+        code: &[u1],
+    ) -> Self {
+        let mut content = Vec::new();
+        content.extend_from_slice(&max_stack.to_be_bytes());
+        content.extend_from_slice(&max_locals.to_be_bytes());
+        content.extend_from_slice(&(code.len() as u32).to_be_bytes());
+        content.extend_from_slice(code);
+        // exception_table_length and attributes_count
+        content.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut method = Vec::new();
+        method.extend_from_slice(&0x0009_u16.to_be_bytes());
+        method.extend_from_slice(&self.utf8(name).to_be_bytes());
+        method.extend_from_slice(&self.utf8(descriptor).to_be_bytes());
+        method.extend_from_slice(&1_u16.to_be_bytes());
+        method.extend(self.encode_attribute("Code", &content));
+        self.methods.push(method);
+        self
+    }
+
+    /// Adds an attribute of the class, the content is not checked
+    pub(crate) fn attribute(mut self, name: &str, content: &[u1]) -> Self {
+        let attribute = self.encode_attribute(name, content);
+        self.attributes.push(attribute);
+        self
+    }
+
+    fn encode_attribute(&mut self, name: &str, content: &[u1]) -> Vec<u1> {
+        let mut attribute = Vec::new();
+        attribute.extend_from_slice(&self.utf8(name).to_be_bytes());
+        attribute.extend_from_slice(&(content.len() as u32).to_be_bytes());
+        attribute.extend_from_slice(content);
+        attribute
+    }
+
+    pub(crate) fn bytes(&self) -> Vec<u1> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC.to_be_bytes());
+        // minor and major version
+        bytes.extend_from_slice(&[0, 0, 0, 52]);
+        bytes.extend_from_slice(&(self.constant_count + 1).to_be_bytes());
+        bytes.extend_from_slice(&self.constant_pool);
+        // public super
+        bytes.extend_from_slice(&0x0021_u16.to_be_bytes());
+        bytes.extend_from_slice(&self.this_class.to_be_bytes());
+        bytes.extend_from_slice(&self.super_class.to_be_bytes());
+        // no interfaces and fields
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        for items in [&self.methods, &self.attributes] {
+            bytes.extend_from_slice(&(items.len() as u2).to_be_bytes());
+            bytes.extend(items.iter().flatten());
+        }
+        bytes
+    }
+}
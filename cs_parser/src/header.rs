@@ -0,0 +1,47 @@
+//!
+//! The magic number and the version at the start of every class file
+//!
+
+use crate::{u2, u4, Data, ParseErr, Result};
+
+pub const MAGIC: u4 = 0xCAFEBABE;
+/// The major version of the first class files, from JDK 1.0.2
+pub const MIN_MAJOR_VERSION: u2 = 45;
+/// The major version of Java 27, the newest one this crate knows
+pub const MAX_MAJOR_VERSION: u2 = 71;
+/// The major version of Java 12, since which the minor version is only used for preview features
+const PREVIEW_MAJOR_VERSION: u2 = 56;
+
+/// Reads the magic number, the minor and the major version and validates them
+pub(crate) fn parse_header(data: &mut Data) -> Result<(u4, u2, u2)> {
+    let magic = data.u4()?;
+    if magic != MAGIC {
+        return Err(ParseErr::new(format!(
+            "Invalid magic number: {:#X}, this is not a class file",
+            magic
+        ))
+        .at(0));
+    }
+    let minor_version = data.u2()?;
+    let major_version = data.u2()?;
+    validate_header(major_version, minor_version).map_err(|err| err.at(4))?;
+    Ok((magic, minor_version, major_version))
+}
+
+/// Checks that the version exists and is supported by this crate.
+/// Since Java 12, the minor version has to be 0, or 65535 for classes that use preview features
+pub fn validate_header(major_version: u2, minor_version: u2) -> Result<()> {
+    if !(MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major_version) {
+        return Err(ParseErr::new(format!(
+            "Unsupported class file version {}.{}, only major versions {} to {} are supported",
+            major_version, minor_version, MIN_MAJOR_VERSION, MAX_MAJOR_VERSION
+        )));
+    }
+    if major_version >= PREVIEW_MAJOR_VERSION && !matches!(minor_version, 0 | 0xFFFF) {
+        return Err(ParseErr::new(format!(
+            "Invalid minor version {} for major version {}, it has to be 0 or 65535",
+            minor_version, major_version
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,995 @@
+//!
+//! Decoding the bytecode of a `Code` attribute into instructions
+//!
+
+use crate::{u1, u2, Data, ParseErr, Result};
+
+/// A JVM instruction with its operands
+///
+/// Local variable indices are `u2`, so that the `wide` forms of the instructions are included.
+/// Branch offsets are relative to the start of the instruction, and an `i32` for every branch.
+/// Constant pool indices are not checked
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    /// An index into the constant pool
+    Ldc(u1),
+    LdcW(u2),
+    Ldc2W(u2),
+    Iload(u2),
+    Lload(u2),
+    Fload(u2),
+    Dload(u2),
+    Aload(u2),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+    Istore(u2),
+    Lstore(u2),
+    Fstore(u2),
+    Dstore(u2),
+    Astore(u2),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc {
+        index: u2,
+        value: i16,
+    },
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(i32),
+    Ifne(i32),
+    Iflt(i32),
+    Ifge(i32),
+    Ifgt(i32),
+    Ifle(i32),
+    IfIcmpeq(i32),
+    IfIcmpne(i32),
+    IfIcmplt(i32),
+    IfIcmpge(i32),
+    IfIcmpgt(i32),
+    IfIcmple(i32),
+    IfAcmpeq(i32),
+    IfAcmpne(i32),
+    Goto(i32),
+    Jsr(i32),
+    Ret(u2),
+    Tableswitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        /// The offsets for `low..=high`
+        offsets: Vec<i32>,
+    },
+    Lookupswitch {
+        default: i32,
+        /// The matches and their offsets, sorted by the match
+        pairs: Vec<(i32, i32)>,
+    },
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+    Getstatic(u2),
+    Putstatic(u2),
+    Getfield(u2),
+    Putfield(u2),
+    Invokevirtual(u2),
+    Invokespecial(u2),
+    Invokestatic(u2),
+    Invokeinterface {
+        index: u2,
+        /// The size of the arguments including the receiver, where `long` and `double` count twice
+        count: u1,
+    },
+    Invokedynamic(u2),
+    New(u2),
+    /// The element type, from 4 (`boolean`) to 11 (`long`)
+    Newarray(u1),
+    Anewarray(u2),
+    Arraylength,
+    Athrow,
+    Checkcast(u2),
+    Instanceof(u2),
+    Monitorenter,
+    Monitorexit,
+    Multianewarray {
+        index: u2,
+        dimensions: u1,
+    },
+    Ifnull(i32),
+    Ifnonnull(i32),
+    GotoW(i32),
+    JsrW(i32),
+}
+
+impl Instruction {
+    /// The opcode of the instruction. Instructions with a `wide` form have the opcode of the short form
+    pub fn opcode(&self) -> u1 {
+        match self {
+            Self::Nop => 0x00,
+            Self::AconstNull => 0x01,
+            Self::IconstM1 => 0x02,
+            Self::Iconst0 => 0x03,
+            Self::Iconst1 => 0x04,
+            Self::Iconst2 => 0x05,
+            Self::Iconst3 => 0x06,
+            Self::Iconst4 => 0x07,
+            Self::Iconst5 => 0x08,
+            Self::Lconst0 => 0x09,
+            Self::Lconst1 => 0x0a,
+            Self::Fconst0 => 0x0b,
+            Self::Fconst1 => 0x0c,
+            Self::Fconst2 => 0x0d,
+            Self::Dconst0 => 0x0e,
+            Self::Dconst1 => 0x0f,
+            Self::Bipush(_) => 0x10,
+            Self::Sipush(_) => 0x11,
+            Self::Ldc(_) => 0x12,
+            Self::LdcW(_) => 0x13,
+            Self::Ldc2W(_) => 0x14,
+            Self::Iload(_) => 0x15,
+            Self::Lload(_) => 0x16,
+            Self::Fload(_) => 0x17,
+            Self::Dload(_) => 0x18,
+            Self::Aload(_) => 0x19,
+            Self::Iload0 => 0x1a,
+            Self::Iload1 => 0x1b,
+            Self::Iload2 => 0x1c,
+            Self::Iload3 => 0x1d,
+            Self::Lload0 => 0x1e,
+            Self::Lload1 => 0x1f,
+            Self::Lload2 => 0x20,
+            Self::Lload3 => 0x21,
+            Self::Fload0 => 0x22,
+            Self::Fload1 => 0x23,
+            Self::Fload2 => 0x24,
+            Self::Fload3 => 0x25,
+            Self::Dload0 => 0x26,
+            Self::Dload1 => 0x27,
+            Self::Dload2 => 0x28,
+            Self::Dload3 => 0x29,
+            Self::Aload0 => 0x2a,
+            Self::Aload1 => 0x2b,
+            Self::Aload2 => 0x2c,
+            Self::Aload3 => 0x2d,
+            Self::Iaload => 0x2e,
+            Self::Laload => 0x2f,
+            Self::Faload => 0x30,
+            Self::Daload => 0x31,
+            Self::Aaload => 0x32,
+            Self::Baload => 0x33,
+            Self::Caload => 0x34,
+            Self::Saload => 0x35,
+            Self::Istore(_) => 0x36,
+            Self::Lstore(_) => 0x37,
+            Self::Fstore(_) => 0x38,
+            Self::Dstore(_) => 0x39,
+            Self::Astore(_) => 0x3a,
+            Self::Istore0 => 0x3b,
+            Self::Istore1 => 0x3c,
+            Self::Istore2 => 0x3d,
+            Self::Istore3 => 0x3e,
+            Self::Lstore0 => 0x3f,
+            Self::Lstore1 => 0x40,
+            Self::Lstore2 => 0x41,
+            Self::Lstore3 => 0x42,
+            Self::Fstore0 => 0x43,
+            Self::Fstore1 => 0x44,
+            Self::Fstore2 => 0x45,
+            Self::Fstore3 => 0x46,
+            Self::Dstore0 => 0x47,
+            Self::Dstore1 => 0x48,
+            Self::Dstore2 => 0x49,
+            Self::Dstore3 => 0x4a,
+            Self::Astore0 => 0x4b,
+            Self::Astore1 => 0x4c,
+            Self::Astore2 => 0x4d,
+            Self::Astore3 => 0x4e,
+            Self::Iastore => 0x4f,
+            Self::Lastore => 0x50,
+            Self::Fastore => 0x51,
+            Self::Dastore => 0x52,
+            Self::Aastore => 0x53,
+            Self::Bastore => 0x54,
+            Self::Castore => 0x55,
+            Self::Sastore => 0x56,
+            Self::Pop => 0x57,
+            Self::Pop2 => 0x58,
+            Self::Dup => 0x59,
+            Self::DupX1 => 0x5a,
+            Self::DupX2 => 0x5b,
+            Self::Dup2 => 0x5c,
+            Self::Dup2X1 => 0x5d,
+            Self::Dup2X2 => 0x5e,
+            Self::Swap => 0x5f,
+            Self::Iadd => 0x60,
+            Self::Ladd => 0x61,
+            Self::Fadd => 0x62,
+            Self::Dadd => 0x63,
+            Self::Isub => 0x64,
+            Self::Lsub => 0x65,
+            Self::Fsub => 0x66,
+            Self::Dsub => 0x67,
+            Self::Imul => 0x68,
+            Self::Lmul => 0x69,
+            Self::Fmul => 0x6a,
+            Self::Dmul => 0x6b,
+            Self::Idiv => 0x6c,
+            Self::Ldiv => 0x6d,
+            Self::Fdiv => 0x6e,
+            Self::Ddiv => 0x6f,
+            Self::Irem => 0x70,
+            Self::Lrem => 0x71,
+            Self::Frem => 0x72,
+            Self::Drem => 0x73,
+            Self::Ineg => 0x74,
+            Self::Lneg => 0x75,
+            Self::Fneg => 0x76,
+            Self::Dneg => 0x77,
+            Self::Ishl => 0x78,
+            Self::Lshl => 0x79,
+            Self::Ishr => 0x7a,
+            Self::Lshr => 0x7b,
+            Self::Iushr => 0x7c,
+            Self::Lushr => 0x7d,
+            Self::Iand => 0x7e,
+            Self::Land => 0x7f,
+            Self::Ior => 0x80,
+            Self::Lor => 0x81,
+            Self::Ixor => 0x82,
+            Self::Lxor => 0x83,
+            Self::Iinc { .. } => 0x84,
+            Self::I2l => 0x85,
+            Self::I2f => 0x86,
+            Self::I2d => 0x87,
+            Self::L2i => 0x88,
+            Self::L2f => 0x89,
+            Self::L2d => 0x8a,
+            Self::F2i => 0x8b,
+            Self::F2l => 0x8c,
+            Self::F2d => 0x8d,
+            Self::D2i => 0x8e,
+            Self::D2l => 0x8f,
+            Self::D2f => 0x90,
+            Self::I2b => 0x91,
+            Self::I2c => 0x92,
+            Self::I2s => 0x93,
+            Self::Lcmp => 0x94,
+            Self::Fcmpl => 0x95,
+            Self::Fcmpg => 0x96,
+            Self::Dcmpl => 0x97,
+            Self::Dcmpg => 0x98,
+            Self::Ifeq(_) => 0x99,
+            Self::Ifne(_) => 0x9a,
+            Self::Iflt(_) => 0x9b,
+            Self::Ifge(_) => 0x9c,
+            Self::Ifgt(_) => 0x9d,
+            Self::Ifle(_) => 0x9e,
+            Self::IfIcmpeq(_) => 0x9f,
+            Self::IfIcmpne(_) => 0xa0,
+            Self::IfIcmplt(_) => 0xa1,
+            Self::IfIcmpge(_) => 0xa2,
+            Self::IfIcmpgt(_) => 0xa3,
+            Self::IfIcmple(_) => 0xa4,
+            Self::IfAcmpeq(_) => 0xa5,
+            Self::IfAcmpne(_) => 0xa6,
+            Self::Goto(_) => 0xa7,
+            Self::Jsr(_) => 0xa8,
+            Self::Ret(_) => 0xa9,
+            Self::Tableswitch { .. } => 0xaa,
+            Self::Lookupswitch { .. } => 0xab,
+            Self::Ireturn => 0xac,
+            Self::Lreturn => 0xad,
+            Self::Freturn => 0xae,
+            Self::Dreturn => 0xaf,
+            Self::Areturn => 0xb0,
+            Self::Return => 0xb1,
+            Self::Getstatic(_) => 0xb2,
+            Self::Putstatic(_) => 0xb3,
+            Self::Getfield(_) => 0xb4,
+            Self::Putfield(_) => 0xb5,
+            Self::Invokevirtual(_) => 0xb6,
+            Self::Invokespecial(_) => 0xb7,
+            Self::Invokestatic(_) => 0xb8,
+            Self::Invokeinterface { .. } => 0xb9,
+            Self::Invokedynamic(_) => 0xba,
+            Self::New(_) => 0xbb,
+            Self::Newarray(_) => 0xbc,
+            Self::Anewarray(_) => 0xbd,
+            Self::Arraylength => 0xbe,
+            Self::Athrow => 0xbf,
+            Self::Checkcast(_) => 0xc0,
+            Self::Instanceof(_) => 0xc1,
+            Self::Monitorenter => 0xc2,
+            Self::Monitorexit => 0xc3,
+            Self::Multianewarray { .. } => 0xc5,
+            Self::Ifnull(_) => 0xc6,
+            Self::Ifnonnull(_) => 0xc7,
+            Self::GotoW(_) => 0xc8,
+            Self::JsrW(_) => 0xc9,
+        }
+    }
+
+    /// The name of the instruction, like `iload_0`. Instructions with a `wide` form have the name of the short form
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Nop => "nop",
+            Self::AconstNull => "aconst_null",
+            Self::IconstM1 => "iconst_m1",
+            Self::Iconst0 => "iconst_0",
+            Self::Iconst1 => "iconst_1",
+            Self::Iconst2 => "iconst_2",
+            Self::Iconst3 => "iconst_3",
+            Self::Iconst4 => "iconst_4",
+            Self::Iconst5 => "iconst_5",
+            Self::Lconst0 => "lconst_0",
+            Self::Lconst1 => "lconst_1",
+            Self::Fconst0 => "fconst_0",
+            Self::Fconst1 => "fconst_1",
+            Self::Fconst2 => "fconst_2",
+            Self::Dconst0 => "dconst_0",
+            Self::Dconst1 => "dconst_1",
+            Self::Bipush(_) => "bipush",
+            Self::Sipush(_) => "sipush",
+            Self::Ldc(_) => "ldc",
+            Self::LdcW(_) => "ldc_w",
+            Self::Ldc2W(_) => "ldc2_w",
+            Self::Iload(_) => "iload",
+            Self::Lload(_) => "lload",
+            Self::Fload(_) => "fload",
+            Self::Dload(_) => "dload",
+            Self::Aload(_) => "aload",
+            Self::Iload0 => "iload_0",
+            Self::Iload1 => "iload_1",
+            Self::Iload2 => "iload_2",
+            Self::Iload3 => "iload_3",
+            Self::Lload0 => "lload_0",
+            Self::Lload1 => "lload_1",
+            Self::Lload2 => "lload_2",
+            Self::Lload3 => "lload_3",
+            Self::Fload0 => "fload_0",
+            Self::Fload1 => "fload_1",
+            Self::Fload2 => "fload_2",
+            Self::Fload3 => "fload_3",
+            Self::Dload0 => "dload_0",
+            Self::Dload1 => "dload_1",
+            Self::Dload2 => "dload_2",
+            Self::Dload3 => "dload_3",
+            Self::Aload0 => "aload_0",
+            Self::Aload1 => "aload_1",
+            Self::Aload2 => "aload_2",
+            Self::Aload3 => "aload_3",
+            Self::Iaload => "iaload",
+            Self::Laload => "laload",
+            Self::Faload => "faload",
+            Self::Daload => "daload",
+            Self::Aaload => "aaload",
+            Self::Baload => "baload",
+            Self::Caload => "caload",
+            Self::Saload => "saload",
+            Self::Istore(_) => "istore",
+            Self::Lstore(_) => "lstore",
+            Self::Fstore(_) => "fstore",
+            Self::Dstore(_) => "dstore",
+            Self::Astore(_) => "astore",
+            Self::Istore0 => "istore_0",
+            Self::Istore1 => "istore_1",
+            Self::Istore2 => "istore_2",
+            Self::Istore3 => "istore_3",
+            Self::Lstore0 => "lstore_0",
+            Self::Lstore1 => "lstore_1",
+            Self::Lstore2 => "lstore_2",
+            Self::Lstore3 => "lstore_3",
+            Self::Fstore0 => "fstore_0",
+            Self::Fstore1 => "fstore_1",
+            Self::Fstore2 => "fstore_2",
+            Self::Fstore3 => "fstore_3",
+            Self::Dstore0 => "dstore_0",
+            Self::Dstore1 => "dstore_1",
+            Self::Dstore2 => "dstore_2",
+            Self::Dstore3 => "dstore_3",
+            Self::Astore0 => "astore_0",
+            Self::Astore1 => "astore_1",
+            Self::Astore2 => "astore_2",
+            Self::Astore3 => "astore_3",
+            Self::Iastore => "iastore",
+            Self::Lastore => "lastore",
+            Self::Fastore => "fastore",
+            Self::Dastore => "dastore",
+            Self::Aastore => "aastore",
+            Self::Bastore => "bastore",
+            Self::Castore => "castore",
+            Self::Sastore => "sastore",
+            Self::Pop => "pop",
+            Self::Pop2 => "pop2",
+            Self::Dup => "dup",
+            Self::DupX1 => "dup_x1",
+            Self::DupX2 => "dup_x2",
+            Self::Dup2 => "dup2",
+            Self::Dup2X1 => "dup2_x1",
+            Self::Dup2X2 => "dup2_x2",
+            Self::Swap => "swap",
+            Self::Iadd => "iadd",
+            Self::Ladd => "ladd",
+            Self::Fadd => "fadd",
+            Self::Dadd => "dadd",
+            Self::Isub => "isub",
+            Self::Lsub => "lsub",
+            Self::Fsub => "fsub",
+            Self::Dsub => "dsub",
+            Self::Imul => "imul",
+            Self::Lmul => "lmul",
+            Self::Fmul => "fmul",
+            Self::Dmul => "dmul",
+            Self::Idiv => "idiv",
+            Self::Ldiv => "ldiv",
+            Self::Fdiv => "fdiv",
+            Self::Ddiv => "ddiv",
+            Self::Irem => "irem",
+            Self::Lrem => "lrem",
+            Self::Frem => "frem",
+            Self::Drem => "drem",
+            Self::Ineg => "ineg",
+            Self::Lneg => "lneg",
+            Self::Fneg => "fneg",
+            Self::Dneg => "dneg",
+            Self::Ishl => "ishl",
+            Self::Lshl => "lshl",
+            Self::Ishr => "ishr",
+            Self::Lshr => "lshr",
+            Self::Iushr => "iushr",
+            Self::Lushr => "lushr",
+            Self::Iand => "iand",
+            Self::Land => "land",
+            Self::Ior => "ior",
+            Self::Lor => "lor",
+            Self::Ixor => "ixor",
+            Self::Lxor => "lxor",
+            Self::Iinc { .. } => "iinc",
+            Self::I2l => "i2l",
+            Self::I2f => "i2f",
+            Self::I2d => "i2d",
+            Self::L2i => "l2i",
+            Self::L2f => "l2f",
+            Self::L2d => "l2d",
+            Self::F2i => "f2i",
+            Self::F2l => "f2l",
+            Self::F2d => "f2d",
+            Self::D2i => "d2i",
+            Self::D2l => "d2l",
+            Self::D2f => "d2f",
+            Self::I2b => "i2b",
+            Self::I2c => "i2c",
+            Self::I2s => "i2s",
+            Self::Lcmp => "lcmp",
+            Self::Fcmpl => "fcmpl",
+            Self::Fcmpg => "fcmpg",
+            Self::Dcmpl => "dcmpl",
+            Self::Dcmpg => "dcmpg",
+            Self::Ifeq(_) => "ifeq",
+            Self::Ifne(_) => "ifne",
+            Self::Iflt(_) => "iflt",
+            Self::Ifge(_) => "ifge",
+            Self::Ifgt(_) => "ifgt",
+            Self::Ifle(_) => "ifle",
+            Self::IfIcmpeq(_) => "if_icmpeq",
+            Self::IfIcmpne(_) => "if_icmpne",
+            Self::IfIcmplt(_) => "if_icmplt",
+            Self::IfIcmpge(_) => "if_icmpge",
+            Self::IfIcmpgt(_) => "if_icmpgt",
+            Self::IfIcmple(_) => "if_icmple",
+            Self::IfAcmpeq(_) => "if_acmpeq",
+            Self::IfAcmpne(_) => "if_acmpne",
+            Self::Goto(_) => "goto",
+            Self::Jsr(_) => "jsr",
+            Self::Ret(_) => "ret",
+            Self::Tableswitch { .. } => "tableswitch",
+            Self::Lookupswitch { .. } => "lookupswitch",
+            Self::Ireturn => "ireturn",
+            Self::Lreturn => "lreturn",
+            Self::Freturn => "freturn",
+            Self::Dreturn => "dreturn",
+            Self::Areturn => "areturn",
+            Self::Return => "return",
+            Self::Getstatic(_) => "getstatic",
+            Self::Putstatic(_) => "putstatic",
+            Self::Getfield(_) => "getfield",
+            Self::Putfield(_) => "putfield",
+            Self::Invokevirtual(_) => "invokevirtual",
+            Self::Invokespecial(_) => "invokespecial",
+            Self::Invokestatic(_) => "invokestatic",
+            Self::Invokeinterface { .. } => "invokeinterface",
+            Self::Invokedynamic(_) => "invokedynamic",
+            Self::New(_) => "new",
+            Self::Newarray(_) => "newarray",
+            Self::Anewarray(_) => "anewarray",
+            Self::Arraylength => "arraylength",
+            Self::Athrow => "athrow",
+            Self::Checkcast(_) => "checkcast",
+            Self::Instanceof(_) => "instanceof",
+            Self::Monitorenter => "monitorenter",
+            Self::Monitorexit => "monitorexit",
+            Self::Multianewarray { .. } => "multianewarray",
+            Self::Ifnull(_) => "ifnull",
+            Self::Ifnonnull(_) => "ifnonnull",
+            Self::GotoW(_) => "goto_w",
+            Self::JsrW(_) => "jsr_w",
+        }
+    }
+
+    /// The index into the constant pool that the instruction takes as its operand, if it has one
+    pub fn constant_index(&self) -> Option<u2> {
+        match *self {
+            Self::Ldc(index) => Some(index.into()),
+            Self::LdcW(index)
+            | Self::Ldc2W(index)
+            | Self::Getstatic(index)
+            | Self::Putstatic(index)
+            | Self::Getfield(index)
+            | Self::Putfield(index)
+            | Self::Invokevirtual(index)
+            | Self::Invokespecial(index)
+            | Self::Invokestatic(index)
+            | Self::Invokeinterface { index, .. }
+            | Self::Invokedynamic(index)
+            | Self::New(index)
+            | Self::Anewarray(index)
+            | Self::Checkcast(index)
+            | Self::Instanceof(index)
+            | Self::Multianewarray { index, .. } => Some(index),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the bytecode of a method, returning every instruction with its offset in the code
+pub fn decode_code(code: &[u1]) -> Result<Vec<(u32, Instruction)>> {
+    let mut data = Data::new(code);
+    let mut instructions = Vec::new();
+    while data.remaining() > 0 {
+        let pc = data.pointer as u32;
+        let instruction = decode_instruction(&mut data).map_err(|err| err.at(pc as usize))?;
+        instructions.push((pc, instruction));
+    }
+    Ok(instructions)
+}
+
+fn decode_instruction(data: &mut Data) -> Result<Instruction> {
+    let opcode = data.u1()?;
+    Ok(match opcode {
+        0x00 => Instruction::Nop,
+        0x01 => Instruction::AconstNull,
+        0x02 => Instruction::IconstM1,
+        0x03 => Instruction::Iconst0,
+        0x04 => Instruction::Iconst1,
+        0x05 => Instruction::Iconst2,
+        0x06 => Instruction::Iconst3,
+        0x07 => Instruction::Iconst4,
+        0x08 => Instruction::Iconst5,
+        0x09 => Instruction::Lconst0,
+        0x0a => Instruction::Lconst1,
+        0x0b => Instruction::Fconst0,
+        0x0c => Instruction::Fconst1,
+        0x0d => Instruction::Fconst2,
+        0x0e => Instruction::Dconst0,
+        0x0f => Instruction::Dconst1,
+        0x10 => Instruction::Bipush(data.u1()? as i8),
+        0x11 => Instruction::Sipush(data.u2()? as i16),
+        0x12 => Instruction::Ldc(data.u1()?),
+        0x13 => Instruction::LdcW(data.u2()?),
+        0x14 => Instruction::Ldc2W(data.u2()?),
+        0x15 => Instruction::Iload(data.u1()?.into()),
+        0x16 => Instruction::Lload(data.u1()?.into()),
+        0x17 => Instruction::Fload(data.u1()?.into()),
+        0x18 => Instruction::Dload(data.u1()?.into()),
+        0x19 => Instruction::Aload(data.u1()?.into()),
+        0x1a => Instruction::Iload0,
+        0x1b => Instruction::Iload1,
+        0x1c => Instruction::Iload2,
+        0x1d => Instruction::Iload3,
+        0x1e => Instruction::Lload0,
+        0x1f => Instruction::Lload1,
+        0x20 => Instruction::Lload2,
+        0x21 => Instruction::Lload3,
+        0x22 => Instruction::Fload0,
+        0x23 => Instruction::Fload1,
+        0x24 => Instruction::Fload2,
+        0x25 => Instruction::Fload3,
+        0x26 => Instruction::Dload0,
+        0x27 => Instruction::Dload1,
+        0x28 => Instruction::Dload2,
+        0x29 => Instruction::Dload3,
+        0x2a => Instruction::Aload0,
+        0x2b => Instruction::Aload1,
+        0x2c => Instruction::Aload2,
+        0x2d => Instruction::Aload3,
+        0x2e => Instruction::Iaload,
+        0x2f => Instruction::Laload,
+        0x30 => Instruction::Faload,
+        0x31 => Instruction::Daload,
+        0x32 => Instruction::Aaload,
+        0x33 => Instruction::Baload,
+        0x34 => Instruction::Caload,
+        0x35 => Instruction::Saload,
+        0x36 => Instruction::Istore(data.u1()?.into()),
+        0x37 => Instruction::Lstore(data.u1()?.into()),
+        0x38 => Instruction::Fstore(data.u1()?.into()),
+        0x39 => Instruction::Dstore(data.u1()?.into()),
+        0x3a => Instruction::Astore(data.u1()?.into()),
+        0x3b => Instruction::Istore0,
+        0x3c => Instruction::Istore1,
+        0x3d => Instruction::Istore2,
+        0x3e => Instruction::Istore3,
+        0x3f => Instruction::Lstore0,
+        0x40 => Instruction::Lstore1,
+        0x41 => Instruction::Lstore2,
+        0x42 => Instruction::Lstore3,
+        0x43 => Instruction::Fstore0,
+        0x44 => Instruction::Fstore1,
+        0x45 => Instruction::Fstore2,
+        0x46 => Instruction::Fstore3,
+        0x47 => Instruction::Dstore0,
+        0x48 => Instruction::Dstore1,
+        0x49 => Instruction::Dstore2,
+        0x4a => Instruction::Dstore3,
+        0x4b => Instruction::Astore0,
+        0x4c => Instruction::Astore1,
+        0x4d => Instruction::Astore2,
+        0x4e => Instruction::Astore3,
+        0x4f => Instruction::Iastore,
+        0x50 => Instruction::Lastore,
+        0x51 => Instruction::Fastore,
+        0x52 => Instruction::Dastore,
+        0x53 => Instruction::Aastore,
+        0x54 => Instruction::Bastore,
+        0x55 => Instruction::Castore,
+        0x56 => Instruction::Sastore,
+        0x57 => Instruction::Pop,
+        0x58 => Instruction::Pop2,
+        0x59 => Instruction::Dup,
+        0x5a => Instruction::DupX1,
+        0x5b => Instruction::DupX2,
+        0x5c => Instruction::Dup2,
+        0x5d => Instruction::Dup2X1,
+        0x5e => Instruction::Dup2X2,
+        0x5f => Instruction::Swap,
+        0x60 => Instruction::Iadd,
+        0x61 => Instruction::Ladd,
+        0x62 => Instruction::Fadd,
+        0x63 => Instruction::Dadd,
+        0x64 => Instruction::Isub,
+        0x65 => Instruction::Lsub,
+        0x66 => Instruction::Fsub,
+        0x67 => Instruction::Dsub,
+        0x68 => Instruction::Imul,
+        0x69 => Instruction::Lmul,
+        0x6a => Instruction::Fmul,
+        0x6b => Instruction::Dmul,
+        0x6c => Instruction::Idiv,
+        0x6d => Instruction::Ldiv,
+        0x6e => Instruction::Fdiv,
+        0x6f => Instruction::Ddiv,
+        0x70 => Instruction::Irem,
+        0x71 => Instruction::Lrem,
+        0x72 => Instruction::Frem,
+        0x73 => Instruction::Drem,
+        0x74 => Instruction::Ineg,
+        0x75 => Instruction::Lneg,
+        0x76 => Instruction::Fneg,
+        0x77 => Instruction::Dneg,
+        0x78 => Instruction::Ishl,
+        0x79 => Instruction::Lshl,
+        0x7a => Instruction::Ishr,
+        0x7b => Instruction::Lshr,
+        0x7c => Instruction::Iushr,
+        0x7d => Instruction::Lushr,
+        0x7e => Instruction::Iand,
+        0x7f => Instruction::Land,
+        0x80 => Instruction::Ior,
+        0x81 => Instruction::Lor,
+        0x82 => Instruction::Ixor,
+        0x83 => Instruction::Lxor,
+        0x84 => Instruction::Iinc {
+            index: data.u1()?.into(),
+            value: (data.u1()? as i8).into(),
+        },
+        0x85 => Instruction::I2l,
+        0x86 => Instruction::I2f,
+        0x87 => Instruction::I2d,
+        0x88 => Instruction::L2i,
+        0x89 => Instruction::L2f,
+        0x8a => Instruction::L2d,
+        0x8b => Instruction::F2i,
+        0x8c => Instruction::F2l,
+        0x8d => Instruction::F2d,
+        0x8e => Instruction::D2i,
+        0x8f => Instruction::D2l,
+        0x90 => Instruction::D2f,
+        0x91 => Instruction::I2b,
+        0x92 => Instruction::I2c,
+        0x93 => Instruction::I2s,
+        0x94 => Instruction::Lcmp,
+        0x95 => Instruction::Fcmpl,
+        0x96 => Instruction::Fcmpg,
+        0x97 => Instruction::Dcmpl,
+        0x98 => Instruction::Dcmpg,
+        0x99 => Instruction::Ifeq((data.u2()? as i16).into()),
+        0x9a => Instruction::Ifne((data.u2()? as i16).into()),
+        0x9b => Instruction::Iflt((data.u2()? as i16).into()),
+        0x9c => Instruction::Ifge((data.u2()? as i16).into()),
+        0x9d => Instruction::Ifgt((data.u2()? as i16).into()),
+        0x9e => Instruction::Ifle((data.u2()? as i16).into()),
+        0x9f => Instruction::IfIcmpeq((data.u2()? as i16).into()),
+        0xa0 => Instruction::IfIcmpne((data.u2()? as i16).into()),
+        0xa1 => Instruction::IfIcmplt((data.u2()? as i16).into()),
+        0xa2 => Instruction::IfIcmpge((data.u2()? as i16).into()),
+        0xa3 => Instruction::IfIcmpgt((data.u2()? as i16).into()),
+        0xa4 => Instruction::IfIcmple((data.u2()? as i16).into()),
+        0xa5 => Instruction::IfAcmpeq((data.u2()? as i16).into()),
+        0xa6 => Instruction::IfAcmpne((data.u2()? as i16).into()),
+        0xa7 => Instruction::Goto((data.u2()? as i16).into()),
+        0xa8 => Instruction::Jsr((data.u2()? as i16).into()),
+        0xa9 => Instruction::Ret(data.u1()?.into()),
+        0xaa => {
+            skip_padding(data)?;
+            let default = data.u4()? as i32;
+            let low = data.u4()? as i32;
+            let high = data.u4()? as i32;
+            if high < low {
+                return Err(ParseErr::new(format!(
+                    "tableswitch with high {} below low {}",
+                    high, low
+                )));
+            }
+            let count = (i64::from(high) - i64::from(low) + 1) as usize;
+            check_remaining(data, count, 4)?;
+            let offsets = (0..count)
+                .map(|_| Ok(data.u4()? as i32))
+                .collect::<Result<_>>()?;
+            Instruction::Tableswitch {
+                default,
+                low,
+                high,
+                offsets,
+            }
+        }
+        0xab => {
+            skip_padding(data)?;
+            let default = data.u4()? as i32;
+            let count = data.u4()? as i32;
+            let count = usize::try_from(count)
+                .map_err(|_| ParseErr::new(format!("lookupswitch with {} pairs", count)))?;
+            check_remaining(data, count, 8)?;
+            let pairs = (0..count)
+                .map(|_| Ok((data.u4()? as i32, data.u4()? as i32)))
+                .collect::<Result<_>>()?;
+            Instruction::Lookupswitch { default, pairs }
+        }
+        0xac => Instruction::Ireturn,
+        0xad => Instruction::Lreturn,
+        0xae => Instruction::Freturn,
+        0xaf => Instruction::Dreturn,
+        0xb0 => Instruction::Areturn,
+        0xb1 => Instruction::Return,
+        0xb2 => Instruction::Getstatic(data.u2()?),
+        0xb3 => Instruction::Putstatic(data.u2()?),
+        0xb4 => Instruction::Getfield(data.u2()?),
+        0xb5 => Instruction::Putfield(data.u2()?),
+        0xb6 => Instruction::Invokevirtual(data.u2()?),
+        0xb7 => Instruction::Invokespecial(data.u2()?),
+        0xb8 => Instruction::Invokestatic(data.u2()?),
+        0xb9 => {
+            let index = data.u2()?;
+            let count = data.u1()?;
+            // always 0
+            data.u1()?;
+            Instruction::Invokeinterface { index, count }
+        }
+        0xba => {
+            let index = data.u2()?;
+            // always 0
+            data.u2()?;
+            Instruction::Invokedynamic(index)
+        }
+        0xbb => Instruction::New(data.u2()?),
+        0xbc => Instruction::Newarray(data.u1()?),
+        0xbd => Instruction::Anewarray(data.u2()?),
+        0xbe => Instruction::Arraylength,
+        0xbf => Instruction::Athrow,
+        0xc0 => Instruction::Checkcast(data.u2()?),
+        0xc1 => Instruction::Instanceof(data.u2()?),
+        0xc2 => Instruction::Monitorenter,
+        0xc3 => Instruction::Monitorexit,
+        0xc5 => Instruction::Multianewarray {
+            index: data.u2()?,
+            dimensions: data.u1()?,
+        },
+        0xc6 => Instruction::Ifnull((data.u2()? as i16).into()),
+        0xc7 => Instruction::Ifnonnull((data.u2()? as i16).into()),
+        0xc8 => Instruction::GotoW(data.u4()? as i32),
+        0xc9 => Instruction::JsrW(data.u4()? as i32),
+        0xc4 => decode_wide(data)?,
+        _ => return Err(ParseErr::new(format!("Invalid opcode 0x{:02x}", opcode))),
+    })
+}
+
+/// The instruction after `wide`, with a two byte local variable index
+fn decode_wide(data: &mut Data) -> Result<Instruction> {
+    let opcode = data.u1()?;
+    let index = data.u2()?;
+    Ok(match opcode {
+        0x15 => Instruction::Iload(index),
+        0x16 => Instruction::Lload(index),
+        0x17 => Instruction::Fload(index),
+        0x18 => Instruction::Dload(index),
+        0x19 => Instruction::Aload(index),
+        0x36 => Instruction::Istore(index),
+        0x37 => Instruction::Lstore(index),
+        0x38 => Instruction::Fstore(index),
+        0x39 => Instruction::Dstore(index),
+        0x3a => Instruction::Astore(index),
+        0xa9 => Instruction::Ret(index),
+        0x84 => Instruction::Iinc {
+            index,
+            value: data.u2()? as i16,
+        },
+        _ => {
+            return Err(ParseErr::new(format!(
+                "Invalid opcode 0x{:02x} after wide",
+                opcode
+            )))
+        }
+    })
+}
+
+/// The operands of the switch instructions start at a multiple of four from the start of the code
+fn skip_padding(data: &mut Data) -> Result<()> {
+    let padding = (4 - data.pointer % 4) % 4;
+    data.bytes(padding)?;
+    Ok(())
+}
+
+fn check_remaining(data: &Data, count: usize, size: usize) -> Result<()> {
+    if count.saturating_mul(size) > data.remaining() {
+        return Err(ParseErr::new(format!(
+            "Switch with {} entries, but only {} bytes remain",
+            count,
+            data.remaining()
+        )));
+    }
+    Ok(())
+}
@@ -1,101 +1,310 @@
+mod annotations;
+mod assemble;
+mod builder;
+mod decompile;
+mod diff;
+mod disassemble;
+#[cfg(test)]
+mod fixture;
+mod header;
+mod instruction;
+mod limits;
+mod lookup;
+mod memory;
 mod model;
+mod mutf8;
+mod nesting;
+mod optimize;
+mod resolved;
+mod retarget;
+mod skim;
+mod stack_map;
+mod subroutines;
 #[cfg(test)]
 mod test;
+mod write;
 
 use crate::cp_info::ValidateCpInfo;
+pub use annotations::{AnnotationValue, AnnotationView};
+pub use assemble::{encode_code, AssembledCode, Assembler, Label};
+pub use builder::{ClassFileBuilder, ConstantPoolBuilder, MethodCode};
+pub use decompile::decompile;
+pub use diff::{diff_classes, Change, Difference, Member};
+pub use disassemble::{
+    disassemble, disassemble_listing, disassemble_with_source, display_constant,
+    display_instruction, display_method_handle, display_name_and_type,
+};
+pub use header::{validate_header, MAGIC, MAX_MAJOR_VERSION, MIN_MAJOR_VERSION};
+pub use instruction::{decode_code, Instruction};
+pub use limits::ParseLimits;
 pub use model::*;
+pub use mutf8::{from_mutf8, to_mutf8};
+pub use nesting::{Enclosing, NestingInfo, NestingKind};
+pub use optimize::OptimizeErr;
+pub use resolved::{
+    ResolvedAnnotation, ResolvedClass, ResolvedField, ResolvedMethod, ResolvedValue,
+};
+pub use retarget::RetargetErr;
+pub use skim::{skim_class_file, ClassSummary, MemberSummary};
+pub use stack_map::StackMapErr;
 use std::fmt::{Display, Formatter};
+pub use subroutines::InlineErr;
+pub use write::{write_class_file, write_class_file_with, WriteErr, WriteOptions};
 
 #[derive(Debug)]
-pub struct ParseErr(String);
+pub struct ParseErr {
+    message: String,
+    /// The offset in the parsed input
+    offset: Option<usize>,
+    /// The name of the attribute that was parsed and the offset in its content
+    attribute: Option<(String, usize)>,
+}
+
+impl ParseErr {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            offset: None,
+            attribute: None,
+        }
+    }
+
+    /// Sets the offset, unless a more precise one is already known
+    fn at(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Sets the attribute, unless the error happened in a nested attribute
+    fn in_attribute(mut self, name: &str, offset: usize) -> Self {
+        self.attribute.get_or_insert((name.to_string(), offset));
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The offset in the input where the error happened, if it is known
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The name of the attribute whose content could not be parsed
+    pub fn attribute(&self) -> Option<&str> {
+        self.attribute.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// The offset of the error in the content of the attribute
+    pub fn attribute_offset(&self) -> Option<usize> {
+        self.attribute.as_ref().map(|(_, offset)| *offset)
+    }
+}
 
 impl Display for ParseErr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Could not parse class file: {}", self.0)
+        write!(f, "Could not parse class file")?;
+        if let Some(offset) = self.offset {
+            write!(f, ", error at offset {:#X}", offset)?;
+        }
+        if let Some((name, offset)) = &self.attribute {
+            write!(
+                f,
+                " while parsing {} attribute (at {:#X} in it)",
+                name, offset
+            )?;
+        }
+        write!(f, ": {}", self.message)
     }
 }
 
 impl std::error::Error for ParseErr {}
 
+/// An attribute that was skipped by `parse_class_file_lenient`
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// Why the attribute could not be parsed
+    pub error: ParseErr,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Skipped attribute: {}", self.error)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, ParseErr>;
 
 #[derive(Clone)]
 struct Data<'a> {
     data: &'a [u1],
     pointer: usize,
+    /// The shared buffer `data` comes from, if there is one. Allows slicing out `Bytes` without copying
+    shared: Option<&'a Bytes>,
+    limits: ParseLimits,
+    /// How deeply annotation values are nested at the pointer
+    depth: usize,
 }
 
-/// Parses the class file into a `ClassFile` structure
+/// Parses the class file into a `ClassFile` structure  
+/// The input is copied once, the attributes, method bodies and most strings share that buffer
 pub fn parse_class_file(data: &[u1]) -> Result<ClassFile> {
-    let mut data = Data::new(data);
-    ClassFile::parse(&mut data, &[])
+    parse_class_file_with(data, &ParseLimits::default(), &mut None)
+}
+
+/// Parses the class file like `parse_class_file`, but fails if it exceeds the limits
+pub fn parse_class_file_with_limits(data: &[u1], limits: &ParseLimits) -> Result<ClassFile> {
+    parse_class_file_with(data, limits, &mut None)
+}
+
+/// Parses the class file like `parse_class_file`, but attributes that cannot be parsed are kept as
+/// `AttributeInfoInner::Unknown` and reported as diagnostics instead  
+/// Errors outside of attributes still fail, the rest of the class cannot be parsed after them
+pub fn parse_class_file_lenient(data: &[u1]) -> Result<(ClassFile, Vec<Diagnostic>)> {
+    let mut diagnostics = Some(Vec::new());
+    let class = parse_class_file_with(data, &ParseLimits::default(), &mut diagnostics)?;
+    Ok((class, diagnostics.unwrap_or_default()))
+}
+
+/// Fails on the first invalid attribute if `diagnostics` is `None`
+fn parse_class_file_with(
+    data: &[u1],
+    limits: &ParseLimits,
+    diagnostics: &mut Option<Vec<Diagnostic>>,
+) -> Result<ClassFile> {
+    let bytes = Bytes::from(data);
+    let mut data = Data::shared(&bytes).with_limits(limits);
+    let mut class =
+        ClassFile::parse(&mut data, &ConstantPool::new()).map_err(|err| err.at(data.position()))?;
+    resolve_attributes(&mut class, limits, diagnostics)?;
+    Ok(class)
 }
 
 impl<'a> Data<'a> {
     fn new(data: &'a [u1]) -> Self {
-        Data { data, pointer: 0 }
+        Data {
+            data,
+            pointer: 0,
+            shared: None,
+            limits: ParseLimits::default(),
+            depth: 0,
+        }
+    }
+
+    fn shared(bytes: &'a Bytes) -> Self {
+        Data {
+            data: bytes,
+            pointer: 0,
+            shared: Some(bytes),
+            limits: ParseLimits::default(),
+            depth: 0,
+        }
+    }
+
+    fn with_limits(mut self, limits: &ParseLimits) -> Self {
+        self.limits = *limits;
+        self
+    }
+
+    /// The offset of the next byte in the input, or in the shared buffer if there is one
+    fn position(&self) -> usize {
+        self.shared.map_or(0, Bytes::start) + self.pointer
+    }
+
+    /// The number of bytes that have not been read yet
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pointer)
+    }
+
+    /// Reads the next `n` bytes as one slice
+    fn bytes(&mut self, n: usize) -> Result<&'a [u1]> {
+        let bytes = self
+            .pointer
+            .checked_add(n)
+            .and_then(|end| self.data.get(self.pointer..end))
+            .ok_or_else(|| {
+                ParseErr::new(format!(
+                    "Expected {} more bytes, but only {} are left",
+                    n,
+                    self.remaining()
+                ))
+                .at(self.position())
+            })?;
+        self.pointer += n;
+        Ok(bytes)
+    }
+
+    /// Reads the next `n` bytes as `Bytes`, sharing the buffer if possible
+    fn shared_bytes(&mut self, n: usize) -> Result<Bytes> {
+        let start = self.pointer;
+        let bytes = self.bytes(n)?;
+        Ok(match self.shared {
+            Some(shared) => shared.slice(start..start + n),
+            None => Bytes::from(bytes),
+        })
+    }
+
+    /// Reads the next `N` bytes into an array
+    fn array<const N: usize>(&mut self) -> Result<[u1; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
     }
 
     fn u1(&mut self) -> Result<u1> {
-        let item = self.data.get(self.pointer).cloned();
-        self.pointer += 1;
-        item.ok_or_else(|| ParseErr("No u1 left".to_string()))
+        Ok(u1::from_be_bytes(self.array()?))
     }
 
     fn u2(&mut self) -> Result<u2> {
-        Ok(((self.u1()? as u2) << 8) | self.u1()? as u2)
+        Ok(u2::from_be_bytes(self.array()?))
     }
 
     /// Parses a u2 and validates it in the constant pool
-    fn cp<T: ValidateCpInfo>(&mut self, pool: &[CpInfo]) -> Result<FromPool<T>> {
+    fn cp<T: ValidateCpInfo>(&mut self, pool: &ConstantPool) -> Result<FromPool<T>> {
         let index = self.u2()?;
         T::validate_cp_info(pool, index)?;
         Ok(index.into())
     }
 
-    fn u4(&mut self) -> Result<u4> {
-        Ok(((self.u2()? as u4) << 16) | self.u2()? as u4)
-    }
-
-    fn last_u1(&self) -> Result<u1> {
-        self.data
-            .get(self.pointer - 1)
-            .cloned()
-            .ok_or_else(|| ParseErr("Last u1 not found".to_string()))
-    }
-
-    fn last_u2(&self) -> Result<u2> {
-        let last2u1 = self
-            .data
-            .get(self.pointer - 2)
-            .cloned()
-            .ok_or_else(|| ParseErr("Last u2 not found".to_string()))?;
-        Ok(((last2u1 as u2) << 8) | self.last_u1()? as u2)
+    /// Parses a u2 in a constant pool entry, it is checked by `CpInfo::validate_references` once
+    /// the whole constant pool is parsed
+    fn unchecked_cp<T>(&mut self) -> Result<FromPool<T>> {
+        Ok(self.u2()?.into())
     }
 
-    fn last_u4(&self) -> Result<u4> {
-        let last2u1 = self
-            .data
-            .get(self.pointer - 3)
-            .cloned()
-            .ok_or_else(|| ParseErr("Last 2 u1 in last u4 not found".to_string()))?;
-        let last3u1 = self
-            .data
-            .get(self.pointer - 4)
-            .cloned()
-            .ok_or_else(|| ParseErr("Last 3 u1 in last u4 not found".to_string()))?;
-        Ok(((last3u1 as u4) << 24) | ((last2u1 as u4) << 16) | self.last_u2()? as u4)
+    fn u4(&mut self) -> Result<u4> {
+        Ok(u4::from_be_bytes(self.array()?))
     }
 }
 
 trait Parse {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self>
+    /// The minimum number of bytes a value takes up in the class file
+    const MIN_SIZE: usize;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self>
     where
         Self: Sized;
 }
 
-fn parse_vec<T: Parse, S: Into<usize>>(len: S, data: &mut Data, cp: &[CpInfo]) -> Result<Vec<T>> {
+fn parse_vec<T: Parse, S: Into<usize>>(
+    len: S,
+    data: &mut Data,
+    cp: &ConstantPool,
+) -> Result<Vec<T>> {
     let len = len.into();
+    if len.saturating_mul(T::MIN_SIZE) > data.remaining() {
+        return Err(ParseErr::new(format!(
+            "Declared {} {} (at least {} bytes each), but only {} bytes remain",
+            len,
+            std::any::type_name::<T>()
+                .split('<')
+                .next()
+                .and_then(|path| path.rsplit("::").next())
+                .unwrap_or_default(),
+            T::MIN_SIZE,
+            data.remaining()
+        )));
+    }
     let mut vec = Vec::with_capacity(len);
     for _ in 0..len {
         vec.push(T::parse(data, cp)?);
@@ -106,7 +315,9 @@ fn parse_vec<T: Parse, S: Into<usize>>(len: S, data: &mut Data, cp: &[CpInfo]) -
 macro_rules! parse_primitive {
     ($($value:ident),*) => {
         $(impl Parse for $value {
-            fn parse(data: &mut Data, _cp: &[CpInfo]) -> Result<Self>
+            const MIN_SIZE: usize = std::mem::size_of::<$value>();
+
+            fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self>
             where
                 Self: Sized,
             {
@@ -122,30 +333,33 @@ impl<T> Parse for FromPool<T>
 where
     T: ValidateCpInfo,
 {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 2;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         data.cp(cp)
     }
 }
 
 impl Parse for ClassFile {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
-        let magic = data.u4()?;
-        assert_eq!(magic, 0xCAFEBABE);
-        let minor_version = data.u2()?;
-        let major_version = data.u2()?;
-        dbg!("reached constant pool");
-        let constant_pool = parse_vec(data.u2()? - 1, data, cp)?; // the minus one is important
-        dbg!("after constant pool");
+    const MIN_SIZE: usize = 24;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
+        let (magic, minor_version, major_version) = header::parse_header(data)?;
+        let constant_pool = parse_constant_pool(data.u2()?, data)?;
         let cp = &constant_pool;
-        let access_flags = data.u2()?;
+        let access_flags = data.u2()?.into();
         let this_class = data.cp(cp)?;
         let super_class = data.cp(cp)?;
         let interfaces = parse_vec(data.u2()?, data, cp)?;
-        let fields = parse_vec(data.u2()?, data, cp)?;
-        let methods = parse_vec(data.u2()?, data, cp)?;
+        let field_count = data.u2()?;
+        ParseLimits::check(field_count.into(), data.limits.max_fields, "fields")?;
+        let fields = parse_vec(field_count, data, cp)?;
+        let method_count = data.u2()?;
+        ParseLimits::check(method_count.into(), data.limits.max_methods, "methods")?;
+        let methods = parse_vec(method_count, data, cp)?;
         let attributes = parse_vec(data.u2()?, data, cp)?;
 
-        let mut class = Self {
+        Ok(Self {
             magic,
             minor_version,
             major_version,
@@ -157,48 +371,143 @@ impl Parse for ClassFile {
             fields,
             methods,
             attributes,
-        };
-        resolve_attributes(&mut class)?;
-        Ok(class)
+        })
+    }
+}
+
+/// `count` is one more than the number of entries. `Long` and `Double` take up two entries,
+/// the second one is `Unusable`
+fn parse_constant_pool(count: u2, data: &mut Data) -> Result<ConstantPool> {
+    // every class needs at least the `Class` and `Utf8` entries of its own name
+    if count <= 1 {
+        return Err(ParseErr::new(format!(
+            "The constant pool count is {}, but a class needs at least its own name",
+            count
+        )));
+    }
+    let count = usize::from(count) - 1;
+    ParseLimits::check(
+        count,
+        data.limits.max_constant_pool,
+        "constant pool entries",
+    )?;
+    // a `Long` or `Double` is still more than `MIN_SIZE` bytes per entry
+    if count.saturating_mul(CpInfo::MIN_SIZE) > data.remaining() {
+        return Err(ParseErr::new(format!(
+            "Declared {} CpInfo (at least {} bytes each), but only {} bytes remain",
+            count,
+            CpInfo::MIN_SIZE,
+            data.remaining()
+        )));
+    }
+    let mut constant_pool = ConstantPool::from(Vec::with_capacity(count));
+    let mut offsets = Vec::with_capacity(count);
+    while constant_pool.len() < count {
+        offsets.push(data.position());
+        // the entries can refer to later ones, so they are checked once all of them are parsed
+        let info = CpInfo::parse(data, &ConstantPool::new())?;
+        constant_pool.push(info);
+        // the `Unusable` entry after a `Long` or `Double`
+        offsets.resize(constant_pool.len(), data.position());
+    }
+    if constant_pool.len() > count {
+        return Err(ParseErr::new(
+            "The last constant takes up two entries, but only one is left",
+        ));
+    }
+    for (i, (info, offset)) in constant_pool.iter().zip(offsets).enumerate() {
+        info.validate_references(&constant_pool).map_err(|err| {
+            ParseErr::new(format!("Constant pool entry {}: {}", i + 1, err.message)).at(offset)
+        })?;
+    }
+    Ok(constant_pool)
+}
+
+impl CpInfo {
+    /// Checks the indices of other entries in this entry, which can't be checked while the
+    /// constant pool is parsed
+    fn validate_references(&self, pool: &ConstantPool) -> Result<()> {
+        match &self.inner {
+            CpInfoInner::Class(class) => class.name_index.validate(pool),
+            CpInfoInner::Fieldref(cp_info::Fieldref {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::MethodRef(cp_info::MethodRef {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
+            }) => {
+                class_index.validate(pool)?;
+                name_and_type_index.validate(pool)
+            }
+            CpInfoInner::String(string) => string.string_index.validate(pool),
+            CpInfoInner::NameAndType(name_and_type) => {
+                name_and_type.name_index.validate(pool)?;
+                name_and_type.descriptor_index.validate(pool)
+            }
+            CpInfoInner::MethodHandle(handle) => match handle.reference_index {
+                cp_info::MethodHandleIndex::Field(index) => index.validate(pool),
+                cp_info::MethodHandleIndex::Method(index) => index.validate(pool),
+                cp_info::MethodHandleIndex::MethodOrInterface(index) => index.validate(pool),
+                cp_info::MethodHandleIndex::Interface(index) => index.validate(pool),
+            },
+            CpInfoInner::MethodType(method_type) => method_type.descriptor_index.validate(pool),
+            CpInfoInner::Dynamic(dynamic) => dynamic.name_and_type_index.validate(pool),
+            CpInfoInner::InvokeDynamic(dynamic) => dynamic.name_and_type_index.validate(pool),
+            CpInfoInner::Module(module) => module.name_index.validate(pool),
+            CpInfoInner::Package(package) => package.name_index.validate(pool),
+            CpInfoInner::Integer(_)
+            | CpInfoInner::Float(_)
+            | CpInfoInner::Long(_)
+            | CpInfoInner::Double(_)
+            | CpInfoInner::Utf8(_)
+            | CpInfoInner::Unusable => Ok(()),
+        }
     }
 }
 
 impl Parse for CpInfo {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 3;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
         let tag = data.u1()?;
 
         Ok(match tag {
             7 => Self {
                 tag,
                 inner: CpInfoInner::Class(cp_info::Class {
-                    name_index: data.cp(cp)?,
+                    name_index: data.unchecked_cp()?,
                 }),
             },
             9 => Self {
                 tag,
                 inner: CpInfoInner::Fieldref(cp_info::Fieldref {
-                    class_index: data.cp(cp)?,
-                    name_and_type_index: data.cp(cp)?,
+                    class_index: data.unchecked_cp()?,
+                    name_and_type_index: data.unchecked_cp()?,
                 }),
             },
             10 => Self {
                 tag,
                 inner: CpInfoInner::MethodRef(cp_info::MethodRef {
-                    class_index: data.cp(cp)?,
-                    name_and_type_index: data.cp(cp)?,
+                    class_index: data.unchecked_cp()?,
+                    name_and_type_index: data.unchecked_cp()?,
                 }),
             },
             11 => Self {
                 tag,
                 inner: CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
-                    class_index: data.cp(cp)?,
-                    name_and_type_index: data.cp(cp)?,
+                    class_index: data.unchecked_cp()?,
+                    name_and_type_index: data.unchecked_cp()?,
                 }),
             },
             8 => Self {
                 tag,
                 inner: CpInfoInner::String(cp_info::String {
-                    string_index: data.cp(cp)?,
+                    string_index: data.unchecked_cp()?,
                 }),
             },
             3 => Self {
@@ -226,76 +535,85 @@ impl Parse for CpInfo {
             12 => Self {
                 tag,
                 inner: CpInfoInner::NameAndType(cp_info::NameAndType {
-                    name_index: data.cp(cp)?,
-                    descriptor_index: data.cp(cp)?,
-                }),
-            },
-            1 => Self {
-                tag,
-                inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: String::from_utf8(parse_vec(data.u2()?, data, cp)?).map_err(|err| {
-                        ParseErr(format!("Invalid utf8 in CpInfo::Utf8: {}", err))
-                    })?,
-                }),
-            },
-            15 => Self {
-                tag,
-                inner: CpInfoInner::MethodHandle(cp_info::MethodHandle {
-                    reference_kind: data.u1()?,
-                    reference_index: match data.last_u1()? {
-                        1..=4 => cp_info::MethodHandleIndex::Field(data.cp(cp)?),
-                        5..=8 => cp_info::MethodHandleIndex::Method(data.cp(cp)?),
-                        9 => cp_info::MethodHandleIndex::Interface(data.cp(cp)?),
-                        n => {
-                            return Err(ParseErr(format!(
-                                "Invalid MethodHandle reference kind: {}",
-                                n
-                            )))
-                        }
-                    },
+                    name_index: data.unchecked_cp()?,
+                    descriptor_index: data.unchecked_cp()?,
                 }),
             },
+            1 => {
+                let length = data.u2()?;
+                Self {
+                    tag,
+                    inner: CpInfoInner::Utf8(cp_info::Utf8 {
+                        bytes: SharedStr::from_mutf8(data.shared_bytes(length.into())?)?,
+                    }),
+                }
+            }
+            15 => {
+                let reference_kind = data.u1()?;
+                Self {
+                    tag,
+                    inner: CpInfoInner::MethodHandle(cp_info::MethodHandle {
+                        reference_kind,
+                        reference_index: match reference_kind {
+                            1..=4 => cp_info::MethodHandleIndex::Field(data.unchecked_cp()?),
+                            5 | 8 => cp_info::MethodHandleIndex::Method(data.unchecked_cp()?),
+                            6 | 7 => {
+                                cp_info::MethodHandleIndex::MethodOrInterface(data.unchecked_cp()?)
+                            }
+                            9 => cp_info::MethodHandleIndex::Interface(data.unchecked_cp()?),
+                            n => {
+                                return Err(ParseErr::new(format!(
+                                    "Invalid MethodHandle reference kind: {}",
+                                    n
+                                )))
+                            }
+                        },
+                    }),
+                }
+            }
             16 => Self {
                 tag,
                 inner: CpInfoInner::MethodType(cp_info::MethodType {
-                    descriptor_index: data.cp(cp)?,
+                    descriptor_index: data.unchecked_cp()?,
                 }),
             },
             17 => Self {
                 tag,
                 inner: CpInfoInner::Dynamic(cp_info::Dynamic {
                     bootstrap_method_attr_index: data.u2()?,
-                    name_and_type_index: data.cp(cp)?,
+                    name_and_type_index: data.unchecked_cp()?,
                 }),
             },
             18 => Self {
                 tag,
                 inner: CpInfoInner::InvokeDynamic(cp_info::InvokeDynamic {
                     bootstrap_method_attr_index: data.u2()?,
-                    name_and_type_index: data.cp(cp)?,
+                    name_and_type_index: data.unchecked_cp()?,
                 }),
             },
             19 => Self {
                 tag,
                 inner: CpInfoInner::Module(cp_info::Module {
-                    name_index: data.cp(cp)?,
+                    name_index: data.unchecked_cp()?,
                 }),
             },
             20 => Self {
                 tag,
                 inner: CpInfoInner::Package(cp_info::Package {
-                    name_index: data.cp(cp)?,
+                    name_index: data.unchecked_cp()?,
                 }),
             },
-            _ => return Err(ParseErr(format!("Invalid CPInfo tag: {}", tag))),
+            _ => return Err(ParseErr::new(format!("Invalid CPInfo tag: {}", tag))),
         })
     }
 }
 
 impl Parse for FieldInfo {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 8;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
-            access_flags: data.u2()?,
+            access_flags: data.u2()?.into(),
             name_index: data.cp(cp)?,
             descriptor_index: data.cp(cp)?,
             attributes: parse_vec(data.u2()?, data, cp)?,
@@ -304,9 +622,11 @@ impl Parse for FieldInfo {
 }
 
 impl Parse for MethodInfo {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 8;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
-            access_flags: data.u2()?,
+            access_flags: data.u2()?.into(),
             name_index: data.cp(cp)?,
             descriptor_index: data.cp(cp)?,
             attributes: parse_vec(data.u2()?, data, cp)?,
@@ -315,30 +635,39 @@ impl Parse for MethodInfo {
 }
 
 impl Parse for AttributeInfo {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        let attribute_name_index = data.cp(cp)?;
+        let attribute_length = data.u4()?;
         Ok(Self {
-            attribute_name_index: data.cp(cp)?,
-            attribute_length: data.u4()?,
+            attribute_name_index,
+            attribute_length,
+            original: None,
             inner: AttributeInfoInner::Unknown {
-                attribute_content: parse_vec(data.last_u4()? as usize, data, cp)?,
+                attribute_content: data.shared_bytes(attribute_length as usize)?,
             },
         })
     }
 }
 
 impl Parse for AttributeCodeException {
-    fn parse(data: &mut Data, _cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 8;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
-            start_pc: data.last_u2()?,
-            end_pc: data.last_u2()?,
-            handler_pc: data.last_u2()?,
-            catch_type: data.last_u2()?,
+            start_pc: data.u2()?,
+            end_pc: data.u2()?,
+            handler_pc: data.u2()?,
+            catch_type: data.u2()?,
         })
     }
 }
 
 impl Parse for StackMapFrame {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 1;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         let frame_type = data.u1()?;
 
         Ok(match frame_type {
@@ -363,7 +692,7 @@ impl Parse for StackMapFrame {
             252..=254 => Self::AppendFrame {
                 frame_type,
                 offset_delta: data.u2()?,
-                locals: parse_vec(data.last_u2()?, data, cp)?,
+                locals: parse_vec(frame_type - 251, data, cp)?,
             },
             255 => Self::FullFrame {
                 frame_type,
@@ -372,7 +701,7 @@ impl Parse for StackMapFrame {
                 stack: parse_vec(data.u2()?, data, cp)?,
             },
             _ => {
-                return Err(ParseErr(format!(
+                return Err(ParseErr::new(format!(
                     "Invalid StackMapFrame type: {}",
                     frame_type
                 )))
@@ -382,7 +711,9 @@ impl Parse for StackMapFrame {
 }
 
 impl Parse for VerificationTypeInfo {
-    fn parse(data: &mut Data, _cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 1;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         let tag = data.u1()?;
         Ok(match tag {
             0 => Self::Top { tag },
@@ -394,14 +725,14 @@ impl Parse for VerificationTypeInfo {
             6 => Self::UninitializedThis { tag },
             7 => Self::Object {
                 tag,
-                cpool_index: data.u2()?.into(),
+                cpool_index: data.cp(cp)?,
             },
             8 => Self::Uninitialized {
                 tag,
                 offset: data.u2()?,
             },
             _ => {
-                return Err(ParseErr(format!(
+                return Err(ParseErr::new(format!(
                     "Invalid VerificationTypeInfo tag: {}",
                     tag
                 )))
@@ -410,19 +741,34 @@ impl Parse for VerificationTypeInfo {
     }
 }
 
+impl Parse for AttributeMethodParameter {
+    const MIN_SIZE: usize = 4;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            name_index: data.cp(cp)?,
+            access_flags: data.u2()?.into(),
+        })
+    }
+}
+
 impl Parse for AttributeInnerClass {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 8;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             inner_class_info_index: data.cp(cp)?,
             outer_class_info_index: data.cp(cp)?,
             inner_class_name_index: data.cp(cp)?,
-            inner_class_access_flags: data.u2()?,
+            inner_class_access_flags: data.u2()?.into(),
         })
     }
 }
 
 impl Parse for AttributeLineNumber {
-    fn parse(data: &mut Data, _cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 4;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             start_pc: data.u2()?,
             line_number: data.u2()?,
@@ -430,8 +776,24 @@ impl Parse for AttributeLineNumber {
     }
 }
 
+impl Parse for AttributeCharacterRange {
+    const MIN_SIZE: usize = 14;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            start_pc: data.u2()?,
+            end_pc: data.u2()?,
+            character_range_start: data.u4()?,
+            character_range_end: data.u4()?,
+            flags: data.u2()?,
+        })
+    }
+}
+
 impl Parse for AttributeLocalVariableTable {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 10;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             start_pc: data.u2()?,
             length: data.u2()?,
@@ -443,17 +805,23 @@ impl Parse for AttributeLocalVariableTable {
 }
 
 impl Parse for Annotation {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 4;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        let type_index = data.cp(cp)?;
+        let num_element_value_pairs = data.u2()?;
         Ok(Self {
-            type_index: data.cp(cp)?,
-            num_element_value_pairs: data.u2()?,
-            element_value_pairs: parse_vec(data.last_u2()?, data, cp)?,
+            type_index,
+            num_element_value_pairs,
+            element_value_pairs: parse_vec(num_element_value_pairs, data, cp)?,
         })
     }
 }
 
 impl Parse for AnnotationElementValuePair {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 5;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             element_name_index: data.cp(cp)?,
             element_name_name: AnnotationElementValue::parse(data, cp)?,
@@ -462,21 +830,45 @@ impl Parse for AnnotationElementValuePair {
 }
 
 impl Parse for AnnotationElementValue {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
-        Ok(Self {
-            tag: data.u1()?,
-            value: AnnotationElementValueValue::parse(data, cp)?,
-        })
+    const MIN_SIZE: usize = 3;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        data.depth += 1;
+        ParseLimits::check(
+            data.depth,
+            data.limits.max_annotation_depth,
+            "levels of nested annotation values",
+        )?;
+        let tag = data.u1()?;
+        let value = AnnotationElementValueValue::parse_with_tag(tag, data, cp)?;
+        data.depth -= 1;
+        Ok(Self { tag, value })
     }
 }
 
-impl Parse for AnnotationElementValueValue {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
-        let tag = data.last_u1()? as char;
+impl AnnotationElementValueValue {
+    /// Parses the value, the `tag` of the containing `AnnotationElementValue` decides its kind
+    fn parse_with_tag(tag: u1, data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        let tag = tag as char;
         Ok(match tag {
-            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => Self::ConstValueIndex {
-                index: data.u2()?.into(),
-            },
+            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
+                let index: FromPool<CpInfoInner> = data.cp(cp)?;
+                let matches = matches!(
+                    (tag, cp.entry(index.inner()).map(|info| &info.inner)),
+                    ('J', Some(CpInfoInner::Long(_)))
+                        | ('F', Some(CpInfoInner::Float(_)))
+                        | ('D', Some(CpInfoInner::Double(_)))
+                        | ('s', Some(CpInfoInner::Utf8(_)))
+                        | ('B' | 'C' | 'I' | 'S' | 'Z', Some(CpInfoInner::Integer(_)))
+                );
+                if !matches {
+                    return Err(ParseErr::new(format!(
+                        "Annotation element value with tag '{}' has the wrong constant type",
+                        tag
+                    )));
+                }
+                Self::ConstValueIndex { index }
+            }
             'e' => Self::EnumConstValue {
                 type_name_index: data.cp(cp)?,
                 const_name_index: data.cp(cp)?,
@@ -491,7 +883,7 @@ impl Parse for AnnotationElementValueValue {
                 values: parse_vec(data.u2()?, data, cp)?,
             },
             _ => {
-                return Err(ParseErr(format!(
+                return Err(ParseErr::new(format!(
                     "Invalid AnnotationElementValueValue tag: {}",
                     tag
                 )))
@@ -501,15 +893,136 @@ impl Parse for AnnotationElementValueValue {
 }
 
 impl Parse for ParameterAnnotation {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 2;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             annotations: parse_vec(data.u2()?, data, cp)?,
         })
     }
 }
 
+impl Parse for TypeAnnotation {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        let target_type = data.u1()?;
+        let target_info = TypeAnnotationTarget::parse_with_type(target_type, data, cp)?;
+        let target_path = TypePath::parse(data, cp)?;
+        let type_index = data.cp(cp)?;
+        let num_element_value_pairs = data.u2()?;
+        Ok(Self {
+            target_type,
+            target_info,
+            target_path,
+            type_index,
+            num_element_value_pairs,
+            element_value_pairs: parse_vec(num_element_value_pairs, data, cp)?,
+        })
+    }
+}
+
+impl TypeAnnotationTarget {
+    /// Parses the target info, the `target_type` of the containing `TypeAnnotation` decides its kind
+    fn parse_with_type(target_type: u1, data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(match target_type {
+            0x00 | 0x01 => Self::TypeParameter {
+                type_parameter_index: data.u1()?,
+            },
+            0x10 => Self::Supertype {
+                supertype_index: data.u2()?,
+            },
+            0x11 | 0x12 => Self::TypeParameterBound {
+                type_parameter_index: data.u1()?,
+                bound_index: data.u1()?,
+            },
+            0x13..=0x15 => Self::Empty,
+            0x16 => Self::FormalParameter {
+                formal_parameter_index: data.u1()?,
+            },
+            0x17 => Self::Throws {
+                throws_type_index: data.u2()?,
+            },
+            0x40 | 0x41 => Self::Localvar {
+                table: parse_vec(data.u2()?, data, cp)?,
+            },
+            0x42 => Self::Catch {
+                exception_table_index: data.u2()?,
+            },
+            0x43..=0x46 => Self::Offset { offset: data.u2()? },
+            0x47..=0x4B => Self::TypeArgument {
+                offset: data.u2()?,
+                type_argument_index: data.u1()?,
+            },
+            _ => {
+                return Err(ParseErr::new(format!(
+                    "Invalid TypeAnnotation target type: {:#04x}",
+                    target_type
+                )))
+            }
+        })
+    }
+}
+
+impl Parse for TypeAnnotationLocalvar {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            start_pc: data.u2()?,
+            length: data.u2()?,
+            index: data.u2()?,
+        })
+    }
+}
+
+impl Parse for TypePath {
+    const MIN_SIZE: usize = 1;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            path: parse_vec(data.u1()?, data, cp)?,
+        })
+    }
+}
+
+impl Parse for TypePathEntry {
+    const MIN_SIZE: usize = 2;
+
+    fn parse(data: &mut Data, _cp: &ConstantPool) -> Result<Self> {
+        let type_path_kind = data.u1()?;
+        let type_argument_index = data.u1()?;
+        Ok(match type_path_kind {
+            0 => Self::Array,
+            1 => Self::Nested,
+            2 => Self::WildcardBound,
+            3 => Self::TypeArgument(type_argument_index),
+            _ => {
+                return Err(ParseErr::new(format!(
+                    "Invalid TypePath kind: {}",
+                    type_path_kind
+                )))
+            }
+        })
+    }
+}
+
+impl Parse for RecordComponentInfo {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            name_index: data.cp(cp)?,
+            descriptor_index: data.cp(cp)?,
+            attributes: parse_vec(data.u2()?, data, cp)?,
+        })
+    }
+}
+
 impl Parse for BootstrapMethod {
-    fn parse(data: &mut Data, cp: &[CpInfo]) -> Result<Self> {
+    const MIN_SIZE: usize = 4;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
         Ok(Self {
             bootstrap_method_ref: data.cp(cp)?,
             bootstrap_arguments: parse_vec(data.u2()?, data, cp)?,
@@ -517,73 +1030,140 @@ impl Parse for BootstrapMethod {
     }
 }
 
-fn resolve_attributes(class: &mut ClassFile) -> Result<()> {
-    let pool = &class.constant_pool;
+impl Parse for Module {
+    const MIN_SIZE: usize = 16;
 
-    class
-        .attributes
-        .iter_mut()
-        .map(|attr| attr.resolve_attribute(pool))
-        .collect::<Result<Vec<()>>>()?;
-
-    class
-        .methods
-        .iter_mut()
-        .map(|method| {
-            method
-                .attributes
-                .iter_mut()
-                .map(|attr| attr.resolve_attribute(pool))
-                .collect::<Result<Vec<()>>>()
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            module_name_index: data.cp(cp)?,
+            module_flags: data.u2()?.into(),
+            module_version_index: data.cp(cp)?,
+            requires: parse_vec(data.u2()?, data, cp)?,
+            exports: parse_vec(data.u2()?, data, cp)?,
+            opens: parse_vec(data.u2()?, data, cp)?,
+            uses_index: parse_vec(data.u2()?, data, cp)?,
+            provides: parse_vec(data.u2()?, data, cp)?,
         })
-        .collect::<Result<Vec<_>>>()?;
-
-    class
-        .fields
-        .iter_mut()
-        .map(|method| {
-            method
-                .attributes
-                .iter_mut()
-                .map(|attr| attr.resolve_attribute(pool))
-                .collect::<Result<Vec<()>>>()
+    }
+}
+
+impl Parse for ModuleRequires {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            requires_index: data.cp(cp)?,
+            requires_flags: data.u2()?.into(),
+            requires_version_index: data.cp(cp)?,
+        })
+    }
+}
+
+impl Parse for ModuleExports {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            exports_index: data.cp(cp)?,
+            exports_flags: data.u2()?.into(),
+            exports_to_index: parse_vec(data.u2()?, data, cp)?,
         })
-        .collect::<Result<Vec<_>>>()?;
+    }
+}
+
+impl Parse for ModuleOpens {
+    const MIN_SIZE: usize = 6;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            opens_index: data.cp(cp)?,
+            opens_flags: data.u2()?.into(),
+            opens_to_index: parse_vec(data.u2()?, data, cp)?,
+        })
+    }
+}
 
+impl Parse for ModuleProvides {
+    const MIN_SIZE: usize = 4;
+
+    fn parse(data: &mut Data, cp: &ConstantPool) -> Result<Self> {
+        Ok(Self {
+            provides_index: data.cp(cp)?,
+            provides_with_index: parse_vec(data.u2()?, data, cp)?,
+        })
+    }
+}
+
+fn resolve_attributes(
+    class: &mut ClassFile,
+    limits: &ParseLimits,
+    diagnostics: &mut Option<Vec<Diagnostic>>,
+) -> Result<()> {
+    let pool = &class.constant_pool;
+    resolve_all(&mut class.attributes, pool, limits, diagnostics)?;
+    for method in &mut class.methods {
+        resolve_all(&mut method.attributes, pool, limits, diagnostics)?;
+    }
+    for field in &mut class.fields {
+        resolve_all(&mut field.attributes, pool, limits, diagnostics)?;
+    }
     Ok(())
 }
 
-impl AttributeInfo {
-    fn resolve_attribute(&mut self, pool: &[CpInfo]) -> Result<()> {
-        // this is a borrow checker hack, but it works :(
-        let attr = std::mem::replace(
-            self,
-            AttributeInfo {
-                attribute_name_index: 0.into(),
-                attribute_length: 0,
-                inner: AttributeInfoInner::__Empty,
-            },
-        );
+/// Attributes that fail to parse are left `Unknown` and added to `diagnostics`, if it is `Some`
+fn resolve_all(
+    attributes: &mut [AttributeInfo],
+    pool: &ConstantPool,
+    limits: &ParseLimits,
+    diagnostics: &mut Option<Vec<Diagnostic>>,
+) -> Result<()> {
+    for attr in attributes {
+        if let Err(error) = attr.resolve_attribute(pool, limits, diagnostics) {
+            match diagnostics {
+                Some(diagnostics) => diagnostics.push(Diagnostic { error }),
+                None => return Err(error),
+            }
+        }
+    }
+    Ok(())
+}
 
-        let (&index, &len, content) = match &attr {
-            AttributeInfo {
-                attribute_name_index,
-                attribute_length,
-                inner: AttributeInfoInner::Unknown { attribute_content },
-            } => (attribute_name_index, attribute_length, attribute_content),
+impl AttributeInfo {
+    /// Parses the content of an `Unknown` attribute, which stays unchanged if it fails
+    fn resolve_attribute(
+        &mut self,
+        pool: &ConstantPool,
+        limits: &ParseLimits,
+        diagnostics: &mut Option<Vec<Diagnostic>>,
+    ) -> Result<()> {
+        let content = match &self.inner {
+            AttributeInfoInner::Unknown { attribute_content } => attribute_content.clone(),
             _ => unreachable!("Attribute already resolved"),
         };
-        let info = match pool.get((index.inner()) as usize - 1) {
+        let index = self.attribute_name_index;
+        let info = match pool.slot(index.inner()) {
             Some(CpInfo {
                 inner: CpInfoInner::Utf8(cp_info::Utf8 { bytes, .. }),
                 ..
             }) => bytes,
-            Some(_) => return Err(ParseErr("Attribute name is not CpInfo::Utf8".to_string())),
-            _ => return Err(ParseErr("Constant Pool index out of Bounds".to_string())),
+            Some(_) => {
+                return Err(ParseErr::new("Attribute name is not CpInfo::Utf8").at(content.start()))
+            }
+            _ => return Err(ParseErr::new("Constant Pool index out of Bounds").at(content.start())),
         };
 
-        let mut data = Data::new(content);
-        self.resolve_attribute_inner(index, len, info, &mut data, pool)
+        let mut data = Data::shared(&content).with_limits(limits);
+        self.resolve_attribute_inner(
+            index,
+            self.attribute_length,
+            info,
+            &mut data,
+            pool,
+            diagnostics,
+        )
+        .map_err(|err| err.in_attribute(info, data.pointer).at(data.position()))?;
+        self.original = Some(content);
+        Ok(())
     }
 
     fn resolve_attribute_inner(
@@ -592,7 +1172,8 @@ impl AttributeInfo {
         attribute_length: u32,
         name: &str,
         data: &mut Data,
-        cp: &[CpInfo],
+        cp: &ConstantPool,
+        diagnostics: &mut Option<Vec<Diagnostic>>,
     ) -> Result<()> {
         let _ = std::mem::replace(
             self,
@@ -600,6 +1181,7 @@ impl AttributeInfo {
                 "ConstantValue" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::ConstantValue {
                         constantvalue_index: data.cp(cp)?,
                     },
@@ -608,10 +1190,19 @@ impl AttributeInfo {
                     let mut code = Self {
                         attribute_name_index,
                         attribute_length,
+                        original: None,
                         inner: AttributeInfoInner::Code {
                             max_stack: data.u2()?,
                             max_locals: data.u2()?,
-                            code: parse_vec(data.u4()? as usize, data, cp)?,
+                            code: {
+                                let code_length = data.u4()? as usize;
+                                ParseLimits::check(
+                                    code_length,
+                                    data.limits.max_code_length,
+                                    "bytes of code",
+                                )?;
+                                data.shared_bytes(code_length)?
+                            },
                             exception_table: parse_vec(data.u2()?, data, cp)?,
                             attributes: parse_vec(data.u2()?, data, cp)?,
                         },
@@ -620,26 +1211,28 @@ impl AttributeInfo {
                         ref mut attributes, ..
                     } = code.inner
                     {
-                        attributes
-                            .iter_mut()
-                            .map(|attr| attr.resolve_attribute(cp))
-                            .collect::<Result<Vec<()>>>()?;
+                        resolve_all(attributes, cp, &data.limits, diagnostics)?;
                     } else {
                         unreachable!()
                     }
                     code
                 }
-                "StackMapTable" => Self {
-                    attribute_name_index,
-                    attribute_length,
-                    inner: AttributeInfoInner::StackMapTable {
-                        number_of_entries: data.u2()?,
-                        entries: parse_vec(data.last_u2()?, data, cp)?,
-                    },
-                },
+                "StackMapTable" => {
+                    let number_of_entries = data.u2()?;
+                    Self {
+                        attribute_name_index,
+                        attribute_length,
+                        original: None,
+                        inner: AttributeInfoInner::StackMapTable {
+                            number_of_entries,
+                            entries: parse_vec(number_of_entries, data, cp)?,
+                        },
+                    }
+                }
                 "Exceptions" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::Exceptions {
                         exception_index_table: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -647,6 +1240,7 @@ impl AttributeInfo {
                 "InnerClasses" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::InnerClasses {
                         classes: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -654,19 +1248,38 @@ impl AttributeInfo {
                 "EnclosingMethod" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::EnclosingMethod {
                         class_index: data.cp(cp)?,
                         method_index: data.cp(cp)?,
                     },
                 },
+                "NestHost" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::NestHost {
+                        host_class_index: data.cp(cp)?,
+                    },
+                },
+                "NestMembers" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::NestMembers {
+                        classes: parse_vec(data.u2()?, data, cp)?,
+                    },
+                },
                 "Synthetic" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::Synthetic,
                 },
                 "Signature" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::Signature {
                         signature_index: data.cp(cp)?,
                     },
@@ -674,6 +1287,7 @@ impl AttributeInfo {
                 "SourceFile" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::SourceFile {
                         sourcefile_index: data.cp(cp)?,
                     },
@@ -681,13 +1295,15 @@ impl AttributeInfo {
                 "SourceDebugExtension" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::SourceDebugExtension {
-                        debug_extension: parse_vec(data.last_u2()?, data, cp)?,
+                        debug_extension: data.bytes(attribute_length as usize)?.to_vec(),
                     },
                 },
                 "LineNumberTable" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::LineNumberTable {
                         line_number_table: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -695,6 +1311,7 @@ impl AttributeInfo {
                 "LocalVariableTable" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::LocalVariableTable {
                         local_variable_table: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -702,18 +1319,29 @@ impl AttributeInfo {
                 "LocalVariableTypeTable" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::LocalVariableTypeTable {
                         local_variable_table: parse_vec(data.u2()?, data, cp)?,
                     },
                 },
+                "MethodParameters" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::MethodParameters {
+                        parameters: parse_vec(data.u1()?, data, cp)?,
+                    },
+                },
                 "Deprecated" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::Deprecated,
                 },
                 "RuntimeVisibleAnnotations" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::RuntimeVisibleAnnotations {
                         annotations: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -721,6 +1349,7 @@ impl AttributeInfo {
                 "RuntimeInvisibleAnnotations" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::RuntimeInvisibleAnnotations {
                         annotations: parse_vec(data.u2()?, data, cp)?,
                     },
@@ -728,6 +1357,7 @@ impl AttributeInfo {
                 "RuntimeVisibleParameterAnnotations" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::RuntimeVisibleParameterAnnotations {
                         parameter_annotations: parse_vec(data.u1()?, data, cp)?,
                     },
@@ -735,28 +1365,110 @@ impl AttributeInfo {
                 "RuntimeInvisibleParameterAnnotations" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::RuntimeInvisibleParameterAnnotations {
                         parameter_annotations: parse_vec(data.u1()?, data, cp)?,
                     },
                 },
+                "RuntimeVisibleTypeAnnotations" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::RuntimeVisibleTypeAnnotations {
+                        annotations: parse_vec(data.u2()?, data, cp)?,
+                    },
+                },
+                "RuntimeInvisibleTypeAnnotations" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::RuntimeInvisibleTypeAnnotations {
+                        annotations: parse_vec(data.u2()?, data, cp)?,
+                    },
+                },
                 "AnnotationDefault" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::AnnotationDefault {
-                        default_value: AnnotationElementValue {
-                            tag: data.u1()?,
-                            value: AnnotationElementValueValue::parse(data, cp)?,
-                        },
+                        default_value: AnnotationElementValue::parse(data, cp)?,
                     },
                 },
                 "BootstrapMethods" => Self {
                     attribute_name_index,
                     attribute_length,
+                    original: None,
                     inner: AttributeInfoInner::BootstrapMethods {
                         bootstrap_methods: parse_vec(data.u2()?, data, cp)?,
                     },
                 },
-                name => return Err(ParseErr(format!("Invalid Attribute name: {}", name))),
+                "Module" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::Module(Box::new(Module::parse(data, cp)?)),
+                },
+                "ModulePackages" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::ModulePackages {
+                        package_index: parse_vec(data.u2()?, data, cp)?,
+                    },
+                },
+                "ModuleMainClass" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::ModuleMainClass {
+                        main_class_index: data.cp(cp)?,
+                    },
+                },
+                "Record" => {
+                    let mut components: Vec<RecordComponentInfo> = parse_vec(data.u2()?, data, cp)?;
+                    for component in &mut components {
+                        resolve_all(&mut component.attributes, cp, &data.limits, diagnostics)?;
+                    }
+                    Self {
+                        attribute_name_index,
+                        attribute_length,
+                        original: None,
+                        inner: AttributeInfoInner::Record { components },
+                    }
+                }
+                "CharacterRangeTable" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::CharacterRangeTable {
+                        character_range_table: parse_vec(data.u2()?, data, cp)?,
+                    },
+                },
+                "CompilationID" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::CompilationID {
+                        compilation_id_index: data.cp(cp)?,
+                    },
+                },
+                "SourceID" => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::SourceID {
+                        source_id_index: data.cp(cp)?,
+                    },
+                },
+                // unknown attributes must be ignored
+                _ => Self {
+                    attribute_name_index,
+                    attribute_length,
+                    original: None,
+                    inner: AttributeInfoInner::Unknown {
+                        attribute_content: data.shared_bytes(data.remaining())?,
+                    },
+                },
             },
         );
 
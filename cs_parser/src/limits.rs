@@ -0,0 +1,47 @@
+//!
+//! Limits on the size of parsed classes, to reject malicious inputs early
+//!
+
+use crate::{ParseErr, Result};
+
+/// Limits checked while parsing, see `parse_class_file_with_limits`
+///
+/// The defaults only reject classes that the JVM rejects too, and annotation values nested more than
+/// 64 levels deep. Lower them for untrusted inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The maximum number of constant pool entries, `Long` and `Double` count twice
+    pub max_constant_pool: usize,
+    pub max_fields: usize,
+    pub max_methods: usize,
+    /// The maximum length of the code of a method in bytes
+    pub max_code_length: usize,
+    /// How deeply annotations and arrays may be nested in the values of annotations
+    pub max_annotation_depth: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_constant_pool: u16::MAX as usize,
+            max_fields: u16::MAX as usize,
+            max_methods: u16::MAX as usize,
+            // the code_length must be less than 65536
+            max_code_length: u16::MAX as usize,
+            max_annotation_depth: 64,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Fails if there are more than `limit` of `what`
+    pub(crate) fn check(count: usize, limit: usize, what: &str) -> Result<()> {
+        if count > limit {
+            return Err(ParseErr::new(format!(
+                "{} {} exceed the limit of {}",
+                count, what, limit
+            )));
+        }
+        Ok(())
+    }
+}
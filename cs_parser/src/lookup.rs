@@ -0,0 +1,70 @@
+//!
+//! Finding fields and methods by their name, and the strings that methods load
+//!
+
+use crate::resolved::invalid_descriptor;
+use crate::{
+    decode_code, AttributeInfoInner, ClassFile, CpInfoInner, FieldAccessFlags, FieldInfo,
+    Instruction, MethodInfo, Result,
+};
+use cs_model::FieldDescriptor;
+use std::str::FromStr;
+
+impl ClassFile {
+    /// The first method with the name and, if it is `Some`, the descriptor like `(I)V`
+    pub fn find_method(&self, name: &str, descriptor: Option<&str>) -> Option<&MethodInfo> {
+        let cp = &self.constant_pool;
+        self.methods.iter().find(|method| {
+            method.name_index.get(cp) == name
+                && descriptor.is_none_or(|descriptor| method.descriptor_index.get(cp) == descriptor)
+        })
+    }
+
+    /// The first field with the name. Only generated classes have multiple fields with the same
+    /// name, which then differ in their descriptor
+    pub fn find_field(&self, name: &str) -> Option<&FieldInfo> {
+        let cp = &self.constant_pool;
+        self.fields
+            .iter()
+            .find(|field| field.name_index.get(cp) == name)
+    }
+
+    /// The fields with their name, parsed descriptor and flags
+    pub fn typed_fields(
+        &self,
+    ) -> impl Iterator<Item = Result<(&str, FieldDescriptor, FieldAccessFlags, &FieldInfo)>> {
+        let cp = &self.constant_pool;
+        self.fields.iter().map(move |field| {
+            let descriptor = field.descriptor_index.get(cp);
+            let parsed = FieldDescriptor::from_str(descriptor)
+                .map_err(|err| invalid_descriptor(descriptor, err))?;
+            Ok((field.name_index.get(cp), parsed, field.access_flags, field))
+        })
+    }
+
+    /// The `CONSTANT_String` values that the methods load with `ldc` and `ldc_w`, with the method
+    /// and the offset of the instruction. Operands that aren't strings are skipped
+    pub fn string_loads(&self) -> Result<Vec<(&MethodInfo, u32, &str)>> {
+        let cp = &self.constant_pool;
+        let mut loads = Vec::new();
+        for method in &self.methods {
+            let Some(code) = method.attributes.iter().find_map(|attr| match &attr.inner {
+                AttributeInfoInner::Code { code, .. } => Some(code),
+                _ => None,
+            }) else {
+                continue;
+            };
+            for (offset, instruction) in decode_code(code)? {
+                let index = match instruction {
+                    Instruction::Ldc(index) => index.into(),
+                    Instruction::LdcW(index) => index,
+                    _ => continue,
+                };
+                if let Some(CpInfoInner::String(string)) = cp.entry(index).map(|info| &info.inner) {
+                    loads.push((method, offset, string.string_index.get(cp)));
+                }
+            }
+        }
+        Ok(loads)
+    }
+}
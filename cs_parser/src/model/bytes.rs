@@ -0,0 +1,223 @@
+use crate::mutf8::{as_plain_utf8, decode, to_mutf8};
+use crate::{u1, Result};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+///
+/// A cheaply clonable slice of a shared, reference counted byte buffer
+///
+/// Attribute contents and method bodies are slices of the buffer of the whole class file,
+/// so they do not have to be copied during parsing
+#[derive(Clone)]
+pub struct Bytes {
+    buf: Arc<[u1]>,
+    range: Range<usize>,
+}
+
+impl Bytes {
+    /// Returns a slice of these bytes, sharing the same buffer  
+    /// Panics if the range is out of bounds
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        assert!(range.start <= range.end && range.end <= self.len());
+        Self {
+            buf: self.buf.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        }
+    }
+
+    /// The offset of this slice in the shared buffer
+    pub(crate) fn start(&self) -> usize {
+        self.range.start
+    }
+
+    /// Whether the shared buffer contains more bytes than this slice
+    pub fn is_shared(&self) -> bool {
+        self.buf.len() != self.len()
+    }
+
+    /// Copies the slice into its own buffer, so that the rest of the shared buffer can be freed
+    pub fn unshare(&mut self) {
+        if self.is_shared() {
+            *self = Self::from(&**self);
+        }
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u1];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf[self.range.clone()]
+    }
+}
+
+impl AsRef<[u1]> for Bytes {
+    fn as_ref(&self) -> &[u1] {
+        self
+    }
+}
+
+impl From<&[u1]> for Bytes {
+    fn from(bytes: &[u1]) -> Self {
+        Self {
+            buf: bytes.into(),
+            range: 0..bytes.len(),
+        }
+    }
+}
+
+impl From<Vec<u1>> for Bytes {
+    fn from(bytes: Vec<u1>) -> Self {
+        let range = 0..bytes.len();
+        Self {
+            buf: bytes.into(),
+            range,
+        }
+    }
+}
+
+impl Debug for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq for Bytes {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Bytes {}
+
+impl Hash for Bytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+/// A string that shares the buffer of the class file like `Bytes`
+///
+/// The contents of `CONSTANT_Utf8` entries are only copied if their modified UTF-8 differs from UTF-8
+///
+/// Unpaired surrogates are legal in modified UTF-8 but not in a `str`, they are replaced with `U+FFFD`
+/// and the original modified UTF-8 is kept to write the string back unchanged
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct SharedStr {
+    /// Always valid UTF-8
+    bytes: Bytes,
+    /// The modified UTF-8 if it contains unpaired surrogates, boxed because it is very rare
+    original: Option<Box<Bytes>>,
+}
+
+impl SharedStr {
+    /// Decodes modified UTF-8, sharing the buffer if it is also valid UTF-8
+    pub fn from_mutf8(bytes: Bytes) -> Result<Self> {
+        if as_plain_utf8(&bytes).is_some() {
+            return Ok(Self {
+                bytes,
+                original: None,
+            });
+        }
+        let (str, lossy) = decode(&bytes)?;
+        Ok(Self {
+            original: lossy.then(|| Box::new(bytes)),
+            ..str.into()
+        })
+    }
+
+    /// The modified UTF-8 the string was decoded from if it contains unpaired surrogates, which
+    /// are replaced with `U+FFFD` in the string
+    pub fn original_mutf8(&self) -> Option<&[u1]> {
+        self.original.as_deref().map(|original| &**original)
+    }
+
+    /// Encodes the string as modified UTF-8, unpaired surrogates are written like they were read
+    pub fn to_mutf8(&self) -> Cow<'_, [u1]> {
+        match self.original_mutf8() {
+            Some(original) => Cow::Borrowed(original),
+            None => Cow::Owned(to_mutf8(self)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self
+    }
+
+    /// Whether the shared buffer contains more than this string
+    pub fn is_shared(&self) -> bool {
+        self.bytes.is_shared()
+            || self
+                .original
+                .as_ref()
+                .is_some_and(|original| original.is_shared())
+    }
+
+    /// Copies the string into its own buffer, so that the rest of the shared buffer can be freed
+    pub fn unshare(&mut self) {
+        self.bytes.unshare();
+        if let Some(original) = &mut self.original {
+            original.unshare();
+        }
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the bytes are checked to be valid UTF-8 when the `SharedStr` is created
+        unsafe { std::str::from_utf8_unchecked(&self.bytes) }
+    }
+}
+
+impl AsRef<str> for SharedStr {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(str: &str) -> Self {
+        Self {
+            bytes: Bytes::from(str.as_bytes()),
+            original: None,
+        }
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(str: String) -> Self {
+        Self {
+            bytes: Bytes::from(str.into_bytes()),
+            original: None,
+        }
+    }
+}
+
+impl Debug for SharedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl Display for SharedStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&**self, f)
+    }
+}
+
+impl PartialEq<str> for SharedStr {
+    fn eq(&self, other: &str) -> bool {
+        &**self == other
+    }
+}
+
+impl PartialEq<&str> for SharedStr {
+    fn eq(&self, other: &&str) -> bool {
+        &**self == *other
+    }
+}
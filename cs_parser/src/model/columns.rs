@@ -0,0 +1,108 @@
+//!
+//! A struct-of-arrays layout of the constant pool, for the `columnar` feature
+//!
+
+use super::cp_info::*;
+use super::{u1, u2, CpInfo, CpInfoInner};
+use std::mem::size_of;
+
+/// A constant pool entry type that has its own column
+pub(crate) trait Column: Sized {
+    /// The tag of the entry, JVMS §4.4
+    const TAG: u1;
+    fn column(columns: &Columns) -> &[Self];
+}
+
+macro_rules! columns {
+    ($($name:ident: $column:ident = $tag:literal),* $(,)?) => {
+        /// The entries of the constant pool split up by their type.
+        ///
+        /// `tags` has the tag of the entry at each index, and `rows` the position of the entry in
+        /// the column of that tag. Looking up a `Class` only touches these two arrays and the
+        /// dense array of `Class` entries, instead of the much larger `CpInfo`s of the pool
+        #[derive(Debug, Default, Clone)]
+        pub(crate) struct Columns {
+            /// 0 for index 0 and the second index of a `Long` or `Double`
+            tags: Vec<u1>,
+            rows: Vec<u2>,
+            $($column: Vec<$name>,)*
+        }
+
+        impl Columns {
+            pub(crate) fn new(entries: &[CpInfo]) -> Self {
+                let mut columns = Self {
+                    tags: Vec::with_capacity(entries.len() + 1),
+                    rows: Vec::with_capacity(entries.len() + 1),
+                    ..Self::default()
+                };
+                columns.tags.push(0);
+                columns.rows.push(0);
+                for info in entries {
+                    let (tag, row) = match &info.inner {
+                        $(CpInfoInner::$name(value) => {
+                            columns.$column.push(value.clone());
+                            ($tag, columns.$column.len() - 1)
+                        })*
+                        CpInfoInner::Unusable => (0, 0),
+                    };
+                    columns.tags.push(tag);
+                    columns.rows.push(row as u2);
+                }
+                columns
+            }
+
+            /// The number of bytes of the arrays, the strings share the buffers of the pool
+            pub(crate) fn heap_size(&self) -> usize {
+                self.tags.capacity() * size_of::<u1>()
+                    + self.rows.capacity() * size_of::<u2>()
+                    $(+ self.$column.capacity() * size_of::<$name>())*
+            }
+        }
+
+        $(
+            impl Column for $name {
+                const TAG: u1 = $tag;
+
+                #[inline]
+                fn column(columns: &Columns) -> &[Self] {
+                    &columns.$column
+                }
+            }
+        )*
+    };
+}
+
+columns!(
+    Utf8: utf8 = 1,
+    Integer: integer = 3,
+    Float: float = 4,
+    Long: long = 5,
+    Double: double = 6,
+    Class: class = 7,
+    String: string = 8,
+    Fieldref: fieldref = 9,
+    MethodRef: method_ref = 10,
+    InterfaceMethodref: interface_methodref = 11,
+    NameAndType: name_and_type = 12,
+    MethodHandle: method_handle = 15,
+    MethodType: method_type = 16,
+    Dynamic: dynamic = 17,
+    InvokeDynamic: invoke_dynamic = 18,
+    Module: module = 19,
+    Package: package = 20,
+);
+
+impl Columns {
+    /// The tag of the entry at `index`, 0 for the second index of a `Long` or `Double`
+    #[inline]
+    pub(crate) fn tag(&self, index: u2) -> u1 {
+        self.tags[usize::from(index)]
+    }
+
+    /// The entry at a checked index, like `FromPool::get`
+    #[inline]
+    pub(crate) fn get<T: Column>(&self, index: u2) -> &T {
+        debug_assert_eq!(self.tag(index), T::TAG);
+        &T::column(self)[usize::from(self.rows[usize::from(index)])]
+    }
+}
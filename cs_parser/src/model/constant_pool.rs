@@ -0,0 +1,177 @@
+//!
+//! The constant pool of a class, indexed from 1
+//!
+
+#[cfg(feature = "columnar")]
+use super::columns::Columns;
+use super::cp_info::{FromCpInfo, Utf8};
+use super::{u2, CpInfo, CpInfoInner, FromPool};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "columnar")]
+use std::sync::OnceLock;
+
+/// The entries of the constant pool.
+///
+/// The first entry has the index 1. `Long` and `Double` take up two indices, the second one is an
+/// `Unusable` entry. It derefs to the slice of all entries, where the entry at `index` is at `index - 1`
+///
+/// With the `columnar` feature, the entries are also stored split up by their type the first time
+/// an index is resolved with `FromPool::get`, which then only reads the arrays of that type. The
+/// split up entries are dropped when the pool is changed
+#[derive(Default, Clone)]
+pub struct ConstantPool {
+    pub(crate) entries: Vec<CpInfo>,
+    #[cfg(feature = "columnar")]
+    columns: OnceLock<Columns>,
+}
+
+impl ConstantPool {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            #[cfg(feature = "columnar")]
+            columns: OnceLock::new(),
+        }
+    }
+
+    #[cfg(feature = "columnar")]
+    #[inline]
+    pub(crate) fn columns(&self) -> &Columns {
+        self.columns.get_or_init(|| Columns::new(&self.entries))
+    }
+
+    /// Drops the split up entries after a change of the entries, they are split up again when needed
+    #[inline]
+    pub(crate) fn changed(&mut self) {
+        #[cfg(feature = "columnar")]
+        self.columns.take();
+    }
+
+    /// The number of bytes of the split up entries, if they were split up yet
+    pub(crate) fn columns_heap_size(&self) -> usize {
+        #[cfg(feature = "columnar")]
+        return self.columns.get().map_or(0, Columns::heap_size);
+        #[cfg(not(feature = "columnar"))]
+        0
+    }
+
+    /// Adds an entry and returns its index, `Long` and `Double` are followed by an `Unusable` entry
+    pub fn push(&mut self, info: CpInfo) -> u2 {
+        self.changed();
+        let index = (self.entries.len() + 1) as u2;
+        let two_slots = matches!(info.inner, CpInfoInner::Long(_) | CpInfoInner::Double(_));
+        self.entries.push(info);
+        if two_slots {
+            self.entries.push(CpInfo {
+                tag: 0,
+                inner: CpInfoInner::Unusable,
+            });
+        }
+        index
+    }
+
+    /// The `constant_pool_count` of the class file, one more than the number of entries
+    pub fn count(&self) -> usize {
+        self.entries.len() + 1
+    }
+
+    /// Gets the value of a checked index, see `FromPool::get`
+    #[inline]
+    pub fn get<'pool, T: FromCpInfo<'pool>>(&'pool self, index: FromPool<T>) -> T::Target {
+        index.get(self)
+    }
+
+    /// The entry at `index`, `None` for 0, out of bounds indices and the second index of a
+    /// `Long` or `Double`
+    pub fn entry(&self, index: u2) -> Option<&CpInfo> {
+        let info = self.entries.get(usize::from(index).checked_sub(1)?)?;
+        match info.inner {
+            CpInfoInner::Unusable => None,
+            _ => Some(info),
+        }
+    }
+
+    /// The string at `index`, if it is a `Utf8` entry
+    pub fn utf8(&self, index: u2) -> Option<&str> {
+        match &self.entry(index)?.inner {
+            CpInfoInner::Utf8(Utf8 { bytes }) => Some(bytes.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The entries with their index, without the `Unusable` ones
+    pub fn iter_with_indices(&self) -> impl Iterator<Item = (u2, &CpInfo)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| !matches!(info.inner, CpInfoInner::Unusable))
+            .map(|(i, info)| (i as u2 + 1, info))
+    }
+
+    /// The entry at `index` like `entry`, but including `Unusable` entries
+    pub(crate) fn slot(&self, index: u2) -> Option<&CpInfo> {
+        self.entries.get(usize::from(index).checked_sub(1)?)
+    }
+
+    pub fn into_entries(self) -> Vec<CpInfo> {
+        self.entries
+    }
+}
+
+/// The entries must already contain the `Unusable` entries after `Long` and `Double`
+impl From<Vec<CpInfo>> for ConstantPool {
+    fn from(entries: Vec<CpInfo>) -> Self {
+        Self {
+            entries,
+            #[cfg(feature = "columnar")]
+            columns: OnceLock::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ConstantPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstantPool")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+impl PartialEq for ConstantPool {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for ConstantPool {}
+
+impl Hash for ConstantPool {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.entries.hash(state)
+    }
+}
+
+impl Deref for ConstantPool {
+    type Target = [CpInfo];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for ConstantPool {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.changed();
+        &mut self.entries
+    }
+}
+
+impl<'a> IntoIterator for &'a ConstantPool {
+    type Item = &'a CpInfo;
+    type IntoIter = std::slice::Iter<'a, CpInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{u1, u2, u4, CpInfo, CpInfoInner, ParseErr};
+use crate::{u1, u2, u4, ConstantPool, CpInfo, CpInfoInner, ParseErr, SharedStr};
 use std::marker::PhantomData;
 
 ///
@@ -6,7 +6,8 @@ use std::marker::PhantomData;
 /// `T` -> What type the target value is supposed to be. Create an enum if multiple values can be there
 ///
 /// The value this is pointing at must *always* be a entry of the correct type T
-/// Type checking is done at parse time, so that the value can be get with minimal overhead
+/// Type checking is done at parse time, so that the value can be get with minimal overhead.
+/// Indices that are created by hand can be checked with `validate`
 #[repr(transparent)]
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FromPool<T> {
@@ -34,12 +35,19 @@ impl<T> FromPool<T> {
     }
 }
 
+impl<T: ValidateCpInfo> FromPool<T> {
+    /// Checks that the index is in bounds and points at an entry of the correct type
+    pub fn validate(&self, pool: &ConstantPool) -> Result<(), ParseErr> {
+        T::validate_cp_info(pool, self.inner)
+    }
+}
+
 impl<'pool, T> FromPool<T>
 where
     T: FromCpInfo<'pool>,
 {
     #[inline]
-    pub fn get(&self, pool: &'pool [CpInfo]) -> T::Target {
+    pub fn get(&self, pool: &'pool ConstantPool) -> T::Target {
         T::from_cp_info_with_index(pool, self.inner)
     }
 }
@@ -49,7 +57,7 @@ where
     T: FromCpInfo<'pool>,
 {
     #[inline]
-    pub fn maybe_get(&self, pool: &'pool [CpInfo]) -> Option<T::Target> {
+    pub fn maybe_get(&self, pool: &'pool ConstantPool) -> Option<T::Target> {
         if self.inner == 0 {
             None
         } else {
@@ -61,14 +69,14 @@ where
 pub trait ValidateCpInfo {
     /// check that the constant pool entry has the correct type
     /// `index` is the original, non-null index (it can be 0 optional constants)
-    fn validate_cp_info(info: &[CpInfo], index: u2) -> Result<(), ParseErr>;
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr>;
 }
 
 pub trait FromCpInfo<'pool>: ValidateCpInfo {
     type Target;
     fn from_cp_info(info: &'pool CpInfo) -> Self::Target;
-    fn from_cp_info_with_index(info: &'pool [CpInfo], index: u2) -> Self::Target {
-        Self::from_cp_info(&info[index as usize - 1])
+    fn from_cp_info_with_index(info: &'pool ConstantPool, index: u2) -> Self::Target {
+        Self::from_cp_info(&info.entries[usize::from(index) - 1])
     }
 }
 
@@ -83,7 +91,7 @@ where
         unreachable!("FromPool<Option<T>> should always be get through `from_cp_info_with_index`")
     }
 
-    fn from_cp_info_with_index(info: &'pool [CpInfo], index: u2) -> Self::Target {
+    fn from_cp_info_with_index(info: &'pool ConstantPool, index: u2) -> Self::Target {
         if index == 0 {
             None
         } else {
@@ -96,7 +104,7 @@ impl<T> ValidateCpInfo for Option<T>
 where
     T: ValidateCpInfo,
 {
-    fn validate_cp_info(info: &[CpInfo], index: u2) -> Result<(), ParseErr> {
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
         if index == 0 {
             Ok(())
         } else {
@@ -118,25 +126,22 @@ macro_rules! impl_try_from_cp {
                         _kind => unreachable!(),
                     }
                 }
+
+                #[cfg(feature = "columnar")]
+                #[inline]
+                fn from_cp_info_with_index(info: &'pool ConstantPool, index: u2) -> Self::Target {
+                    info.columns().get(index)
+                }
             }
 
             impl ValidateCpInfo for $name {
-                fn validate_cp_info(info: &[CpInfo], index: u2) -> Result<(), ParseErr> {
+                fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
                     if index == 0 {
-                        return Err(ParseErr("Index must not be 0".to_string()));
-                    }
-
-                    if info.len() == 0 {
-                        return Ok(());
+                        return Err(ParseErr::new("Index must not be 0"));
                     }
-                    // todo this here might actually be an empty constant pool depending on whether is is still parsing the constant pool
-                    // it needs to be checked after testing
-                    // not now
-                    // pls
-                    // i hate this
-                    match &info[index as usize - 1].inner {
+                    match &entry(info, index)?.inner {
                         CpInfoInner::$name(_) => Ok(()),
-                        kind => Err(ParseErr(format!(
+                        kind => Err(ParseErr::new(format!(
                             concat!("Expected '", stringify!($name), "', found '{:?}'"),
                             kind
                         ))),
@@ -156,17 +161,118 @@ impl<'pool> FromCpInfo<'pool> for CpInfoInner {
 }
 
 impl ValidateCpInfo for CpInfoInner {
-    fn validate_cp_info(_info: &[CpInfo], _index: u2) -> Result<(), ParseErr> {
-        Ok(())
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
+        if index == 0 {
+            return Err(ParseErr::new("Index must not be 0"));
+        }
+        match entry(info, index)?.inner {
+            CpInfoInner::Unusable => Err(ParseErr::new(format!(
+                "Constant pool index {} is the second half of a Long or Double",
+                index
+            ))),
+            _ => Ok(()),
+        }
     }
 }
 
+/// The entry at the non-null `index`
+fn entry(info: &ConstantPool, index: u2) -> Result<&CpInfo, ParseErr> {
+    info.slot(index).ok_or_else(|| {
+        ParseErr::new(format!(
+            "Constant pool index {} out of bounds, there are {} entries",
+            index,
+            info.len()
+        ))
+    })
+}
+
+/// Any constant that can be loaded with `ldc` or passed to a bootstrap method: `Integer`, `Float`,
+/// `Long`, `Double`, `Class`, `String`, `MethodHandle`, `MethodType` or `Dynamic`
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Loadable;
+
+impl<'pool> FromCpInfo<'pool> for Loadable {
+    type Target = &'pool CpInfoInner;
+
+    fn from_cp_info(info: &'pool CpInfo) -> Self::Target {
+        &info.inner
+    }
+}
+
+impl ValidateCpInfo for Loadable {
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
+        if index == 0 {
+            return Err(ParseErr::new("Index must not be 0"));
+        }
+        match &info.entry(index).map(|info| &info.inner) {
+            Some(
+                CpInfoInner::Integer(_)
+                | CpInfoInner::Float(_)
+                | CpInfoInner::Long(_)
+                | CpInfoInner::Double(_)
+                | CpInfoInner::Class(_)
+                | CpInfoInner::String(_)
+                | CpInfoInner::MethodHandle(_)
+                | CpInfoInner::MethodType(_)
+                | CpInfoInner::Dynamic(_),
+            ) => Ok(()),
+            Some(kind) => Err(ParseErr::new(format!(
+                "Expected a loadable constant, found '{:?}'",
+                kind
+            ))),
+            None => Err(ParseErr::new(format!(
+                "Constant pool index {} out of bounds",
+                index
+            ))),
+        }
+    }
+}
+
+/// A `MethodRef` or an `InterfaceMethodref`, which method handles of the kinds 6
+/// (`REF_invokeStatic`) and 7 (`REF_invokeSpecial`) can point at since version 52, JVMS §4.4.8.
+/// It gets the class and the name and type of either
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MethodOrInterfaceRef;
+
+impl<'pool> FromCpInfo<'pool> for MethodOrInterfaceRef {
+    type Target = (FromPool<Class>, FromPool<NameAndType>);
+
+    fn from_cp_info(info: &'pool CpInfo) -> Self::Target {
+        match &info.inner {
+            CpInfoInner::MethodRef(method) => (method.class_index, method.name_and_type_index),
+            CpInfoInner::InterfaceMethodref(method) => {
+                (method.class_index, method.name_and_type_index)
+            }
+            _kind => unreachable!(),
+        }
+    }
+}
+
+impl ValidateCpInfo for MethodOrInterfaceRef {
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
+        if index == 0 {
+            return Err(ParseErr::new("Index must not be 0"));
+        }
+        match &entry(info, index)?.inner {
+            CpInfoInner::MethodRef(_) | CpInfoInner::InterfaceMethodref(_) => Ok(()),
+            kind => Err(ParseErr::new(format!(
+                "Expected 'MethodRef' or 'InterfaceMethodref', found '{:?}'",
+                kind
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Class {
     pub name_index: FromPool<Utf8>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Fieldref {
     /// May be a class or interface type
     pub class_index: FromPool<Class>,
@@ -174,6 +280,7 @@ pub struct Fieldref {
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MethodRef {
     /// Must be a class type
     pub class_index: FromPool<Class>,
@@ -181,6 +288,7 @@ pub struct MethodRef {
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct InterfaceMethodref {
     /// Must be an interface type
     pub class_index: FromPool<Class>,
@@ -188,17 +296,20 @@ pub struct InterfaceMethodref {
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct String {
     pub string_index: FromPool<Utf8>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Integer {
     // Big endian
     pub bytes: u4,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Float {
     /// IEEE 754 floating-point single format, big endian
     pub bytes: u4,
@@ -206,6 +317,7 @@ pub struct Float {
 
 /// 8 byte constants take up two spaces in the constant pool
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Long {
     /// Big endian
     pub high_bytes: u4,
@@ -215,6 +327,7 @@ pub struct Long {
 
 /// 8 byte constants take up two spaces in the constant pool
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Double {
     /// IEEE 754 floating-point double format, big endian
     pub high_bytes: u4,
@@ -222,41 +335,74 @@ pub struct Double {
     pub low_bytes: u4,
 }
 
+impl Integer {
+    pub fn value(&self) -> i32 {
+        self.bytes as i32
+    }
+}
+
+impl Float {
+    pub fn value(&self) -> f32 {
+        f32::from_bits(self.bytes)
+    }
+}
+
+impl Long {
+    pub fn value(&self) -> i64 {
+        (u64::from(self.high_bytes) << 32 | u64::from(self.low_bytes)) as i64
+    }
+}
+
+impl Double {
+    pub fn value(&self) -> f64 {
+        f64::from_bits(u64::from(self.high_bytes) << 32 | u64::from(self.low_bytes))
+    }
+}
+
 /// Any field or method, without the class it belongs to
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct NameAndType {
     pub name_index: FromPool<Utf8>,
     pub descriptor_index: FromPool<Utf8>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Utf8 {
-    /// Contains modified UTF-8
-    pub bytes: std::string::String,
+    /// Decoded from modified UTF-8, sharing the buffer of the class file if possible
+    pub bytes: SharedStr,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MethodHandle {
-    /// The kind of method handle (0-9)
-    /// If the kind is 1-4, the entry must be `FieldRef`. If the kind is 5-8, the entry must be `MethodRef`
+    /// The kind of method handle (1-9)
+    /// If the kind is 1-4, the entry must be `FieldRef`. If the kind is 5 or 8, the entry must be
+    /// `MethodRef`, if it is 6 or 7 it can also be an `InterfaceMethodref`.
     /// If the kind is 9, the entry must be `InterfaceMethodRef`
     pub reference_kind: u1,
     pub reference_index: MethodHandleIndex,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum MethodHandleIndex {
     Field(FromPool<Fieldref>),
     Method(FromPool<MethodRef>),
+    /// The kinds 6 and 7, which call static and private methods of interfaces as well
+    MethodOrInterface(FromPool<MethodOrInterfaceRef>),
     Interface(FromPool<InterfaceMethodref>),
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MethodType {
     pub descriptor_index: FromPool<Utf8>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Dynamic {
     /// Must be a valid index into the `bootstrap_methods` array of the bootstrap method table of this class field
     pub bootstrap_method_attr_index: u2,
@@ -264,6 +410,7 @@ pub struct Dynamic {
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct InvokeDynamic {
     /// Must be a valid index into the `bootstrap_methods` array of the bootstrap method table of this class field
     pub bootstrap_method_attr_index: u2,
@@ -271,11 +418,13 @@ pub struct InvokeDynamic {
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Module {
     pub name_index: FromPool<Utf8>,
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Package {
     pub name_index: FromPool<Utf8>,
 }
@@ -302,17 +451,14 @@ impl_try_from_cp!(
 );
 
 impl ValidateCpInfo for Utf8 {
-    fn validate_cp_info(info: &[CpInfo], index: u2) -> Result<(), ParseErr> {
+    fn validate_cp_info(info: &ConstantPool, index: u2) -> Result<(), ParseErr> {
         if index == 0 {
-            return Err(ParseErr("Index must not be 0".to_string()));
+            return Err(ParseErr::new("Index must not be 0"));
         }
-        if info.len() == 0 {
-            return Ok(());
-        }
-        match &info[index as usize - 1].inner {
+        match &entry(info, index)?.inner {
             CpInfoInner::Utf8(_) => Ok(()),
-            kind => Err(ParseErr(format!(
-                concat!("Expected '", stringify!($name), "', found '{:?}'"),
+            kind => Err(ParseErr::new(format!(
+                "Expected 'Utf8', found '{:?}'",
                 kind
             ))),
         }
@@ -330,4 +476,10 @@ impl<'pool> FromCpInfo<'pool> for Utf8 {
             _ => unreachable!(),
         }
     }
+
+    #[cfg(feature = "columnar")]
+    #[inline]
+    fn from_cp_info_with_index(info: &'pool ConstantPool, index: u2) -> Self::Target {
+        &info.columns().get::<Utf8>(index).bytes
+    }
 }
@@ -0,0 +1,339 @@
+//!
+//! The access and property flags of classes, members and modules
+//!
+
+use super::u2;
+use std::fmt::{Display, Formatter};
+use std::ops::{BitOr, BitOrAssign};
+
+/// Defines an enum of single flags and a set type wrapping the `u2` mask of them
+macro_rules! access_flags {
+    (
+        $(#[$flag_meta:meta])*
+        $flag:ident,
+        $(#[$set_meta:meta])*
+        $set:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $value:literal => $name:literal,
+            )*
+        }
+    ) => {
+        $(#[$flag_meta])*
+        #[repr(u16)]
+        #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+        pub enum $flag {
+            $(
+                $(#[$variant_meta])*
+                $variant = $value,
+            )*
+        }
+
+        impl $flag {
+            /// All flags, ordered by their value
+            pub const ALL: &'static [Self] = &[$(Self::$variant),*];
+
+            pub const fn bits(self) -> u2 {
+                self as u2
+            }
+
+            /// The name of the flag in the specification, for example `ACC_PUBLIC`
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+
+        impl Display for $flag {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        $(#[$set_meta])*
+        ///
+        /// Bits that are not defined are kept, so the flags are written back unchanged
+        #[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+        pub struct $set(u2);
+
+        impl $set {
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            pub const fn from_bits(bits: u2) -> Self {
+                Self(bits)
+            }
+
+            pub const fn bits(self) -> u2 {
+                self.0
+            }
+
+            pub const fn contains(self, flag: $flag) -> bool {
+                self.0 & flag as u2 != 0
+            }
+
+            pub fn insert(&mut self, flag: $flag) {
+                self.0 |= flag as u2;
+            }
+
+            pub fn remove(&mut self, flag: $flag) {
+                self.0 &= !(flag as u2);
+            }
+
+            /// The set flags, ordered by their value
+            pub fn iter(self) -> impl Iterator<Item = $flag> {
+                $flag::ALL.iter().copied().filter(move |flag| self.contains(*flag))
+            }
+
+            /// The set bits that are not a flag
+            pub fn unknown_bits(self) -> u2 {
+                $flag::ALL.iter().fold(self.0, |bits, flag| bits & !(*flag as u2))
+            }
+        }
+
+        impl From<$flag> for $set {
+            fn from(flag: $flag) -> Self {
+                Self(flag as u2)
+            }
+        }
+
+        impl From<u2> for $set {
+            fn from(bits: u2) -> Self {
+                Self(bits)
+            }
+        }
+
+        impl From<$set> for u2 {
+            fn from(flags: $set) -> Self {
+                flags.0
+            }
+        }
+
+        impl BitOr for $flag {
+            type Output = $set;
+
+            fn bitor(self, rhs: Self) -> $set {
+                $set(self as u2 | rhs as u2)
+            }
+        }
+
+        impl BitOr<$flag> for $set {
+            type Output = Self;
+
+            fn bitor(self, rhs: $flag) -> Self {
+                Self(self.0 | rhs as u2)
+            }
+        }
+
+        impl BitOr for $set {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl BitOrAssign<$flag> for $set {
+            fn bitor_assign(&mut self, rhs: $flag) {
+                self.insert(rhs);
+            }
+        }
+
+        /// The names of the set flags separated by `, ` like `javap` prints them, followed by the
+        /// unknown bits in hex
+        impl Display for $set {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                let mut first = true;
+                let mut separator = |f: &mut Formatter<'_>| {
+                    let separator = if first { "" } else { ", " };
+                    first = false;
+                    f.write_str(separator)
+                };
+                for flag in self.iter() {
+                    separator(f)?;
+                    f.write_str(flag.name())?;
+                }
+                if self.unknown_bits() != 0 {
+                    separator(f)?;
+                    write!(f, "{:#06X}", self.unknown_bits())?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+access_flags! {
+    /// Access Flags of a class
+    ClassAccessFlag,
+    /// The `access_flags` of a class
+    ClassAccessFlags {
+        /// Declared public; may be accessed from outside its package.
+        Public = 0x0001 => "ACC_PUBLIC",
+        /// Declared final; no subclasses allowed.
+        Final = 0x0010 => "ACC_FINAL",
+        /// Treat superclass methods specially when invoked by the invokespecial instruction.
+        Super = 0x0020 => "ACC_SUPER",
+        /// Is an interface, not a class.
+        Interface = 0x0200 => "ACC_INTERFACE",
+        /// Declared abstract; must not be instantiated.
+        Abstract = 0x0400 => "ACC_ABSTRACT",
+        /// Declared synthetic; not present in the source code.
+        Synthetic = 0x1000 => "ACC_SYNTHETIC",
+        /// Declared as an annotation type.
+        Annotation = 0x2000 => "ACC_ANNOTATION",
+        /// Declared as an enum type.
+        Enum = 0x4000 => "ACC_ENUM",
+        /// Is a module, not a class or interface.
+        MODULE = 0x8000 => "ACC_MODULE",
+    }
+}
+
+access_flags! {
+    /// Access Flags of a method
+    MethodAccessFlag,
+    /// The `access_flags` of a method
+    MethodAccessFlags {
+        /// Declared public; may be accessed from outside its package.
+        PUBLIC = 0x0001 => "ACC_PUBLIC",
+        /// Declared private; accessible only within the defining class.
+        PRIVATE = 0x0002 => "ACC_PRIVATE",
+        /// Declared protected; may be accessed within subclasses.
+        PROTECTED = 0x0004 => "ACC_PROTECTED",
+        /// Declared static.
+        STATIC = 0x0008 => "ACC_STATIC",
+        /// Declared final; must not be overridden.
+        FINAL = 0x0010 => "ACC_FINAL",
+        /// Declared synchronized; invocation is wrapped by a monitor use.
+        SYNCHRONIZED = 0x0020 => "ACC_SYNCHRONIZED",
+        /// A bridge method, generated by the compiler.
+        BRIDGE = 0x0040 => "ACC_BRIDGE",
+        /// Declared with variable number of arguments.
+        VARARGS = 0x0080 => "ACC_VARARGS",
+        /// Declared native; implemented in a language other than Java.
+        NATIVE = 0x0100 => "ACC_NATIVE",
+        /// Declared abstract; no implementation is provided.
+        ABSTRACT = 0x0400 => "ACC_ABSTRACT",
+        /// Declared strictfp; floating-point mode is FP-strict.
+        STRICT = 0x0800 => "ACC_STRICT",
+        /// Declared synthetic; not present in the source code.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+    }
+}
+
+access_flags! {
+    /// Access flags for an inner class
+    InnerClassAccessFlag,
+    /// The `inner_class_access_flags` of an entry in the `InnerClasses` attribute
+    InnerClassAccessFlags {
+        /// Marked or implicitly public in source.
+        PUBLIC = 0x0001 => "ACC_PUBLIC",
+        /// Marked private in source.
+        PRIVATE = 0x0002 => "ACC_PRIVATE",
+        /// Marked protected in source.
+        PROTECTED = 0x0004 => "ACC_PROTECTED",
+        /// Marked or implicitly static in source.
+        STATIC = 0x0008 => "ACC_STATIC",
+        /// Marked final in source.
+        FINAL = 0x0010 => "ACC_FINAL",
+        /// Was an interface in source.
+        INTERFACE = 0x0200 => "ACC_INTERFACE",
+        /// Marked or implicitly abstract in source.
+        ABSTRACT = 0x0400 => "ACC_ABSTRACT",
+        /// Declared synthetic; not present in the source code.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// Declared as an annotation type.
+        ANNOTATION = 0x2000 => "ACC_ANNOTATION",
+        /// Declared as an enum type.
+        ENUM = 0x4000 => "ACC_ENUM",
+    }
+}
+
+access_flags! {
+    /// Access flags for a field
+    FieldAccessFlag,
+    /// The `access_flags` of a field
+    FieldAccessFlags {
+        /// Declared public; may be accessed from outside its package.
+        PUBLIC = 0x0001 => "ACC_PUBLIC",
+        /// Declared private; usable only within the defining class.
+        PRIVATE = 0x0002 => "ACC_PRIVATE",
+        /// Declared protected; may be accessed within subclasses.
+        PROTECTED = 0x0004 => "ACC_PROTECTED",
+        /// Declared static.
+        STATIC = 0x0008 => "ACC_STATIC",
+        /// Declared final; never directly assigned to after object construction (JLS §17.5).
+        FINAL = 0x0010 => "ACC_FINAL",
+        /// Declared volatile; cannot be cached.
+        VOLATILE = 0x0040 => "ACC_VOLATILE",
+        /// Declared transient; not written or read by a persistent object manager.
+        TRANSIENT = 0x0080 => "ACC_TRANSIENT",
+        /// Declared synthetic; not present in the source code.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// Declared as an element of an enum.
+        ENUM = 0x4000 => "ACC_ENUM",
+    }
+}
+
+access_flags! {
+    /// Flags of a parameter in the `MethodParameters` attribute
+    MethodParameterFlag,
+    /// The `access_flags` of a parameter in the `MethodParameters` attribute
+    MethodParameterFlags {
+        /// The parameter was declared final.
+        FINAL = 0x0010 => "ACC_FINAL",
+        /// The parameter was not explicitly or implicitly declared in source code.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// The parameter was implicitly declared in source code.
+        MANDATED = 0x8000 => "ACC_MANDATED",
+    }
+}
+
+access_flags! {
+    /// Flags of a module
+    ModuleFlag,
+    /// The `module_flags` of the `Module` attribute
+    ModuleFlags {
+        /// Indicates that this module is open.
+        OPEN = 0x0020 => "ACC_OPEN",
+        /// Indicates that this module was not explicitly or implicitly declared.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// Indicates that this module was implicitly declared.
+        MANDATED = 0x8000 => "ACC_MANDATED",
+    }
+}
+
+access_flags! {
+    /// Flags of a dependence of a module
+    RequiresFlag,
+    /// The `requires_flags` of a dependence of a module
+    RequiresFlags {
+        /// Any module which depends on the current module implicitly declares a dependence on the
+        /// module indicated by this entry.
+        TRANSITIVE = 0x0020 => "ACC_TRANSITIVE",
+        /// This dependence is mandatory at compile time, but optional at run time.
+        #[allow(non_camel_case_types)]
+        STATIC_PHASE = 0x0040 => "ACC_STATIC_PHASE",
+        /// This dependence was not explicitly or implicitly declared in the source of the module
+        /// declaration.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// This dependence was implicitly declared in the source of the module declaration.
+        MANDATED = 0x8000 => "ACC_MANDATED",
+    }
+}
+
+access_flags! {
+    /// Flags of a package exported or opened by a module
+    ModulePackageFlag,
+    /// The `exports_flags` or `opens_flags` of a package of a module
+    ModulePackageFlags {
+        /// The package was not explicitly or implicitly declared in the source of the module
+        /// declaration.
+        SYNTHETIC = 0x1000 => "ACC_SYNTHETIC",
+        /// The package was implicitly declared in the source of the module declaration.
+        MANDATED = 0x8000 => "ACC_MANDATED",
+    }
+}
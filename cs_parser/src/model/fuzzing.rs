@@ -0,0 +1,77 @@
+//!
+//! `Arbitrary` for the model types that can't derive it, for structure aware fuzzing
+//!
+
+use super::{u1, u2, Bytes, ConstantPool, CpInfo, CpInfoInner, FromPool, SharedStr};
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Any index, it doesn't have to point at an entry of the type
+impl<'a, T> Arbitrary<'a> for FromPool<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(u2::arbitrary(u)?.into())
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u2::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Bytes {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(<&[u1]>::arbitrary(u)?.into())
+    }
+}
+
+impl<'a> Arbitrary<'a> for SharedStr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(<&str>::arbitrary(u)?.into())
+    }
+}
+
+/// The tag always matches the constant
+impl<'a> Arbitrary<'a> for CpInfo {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let inner = CpInfoInner::arbitrary(u)?;
+        Ok(Self {
+            tag: tag(&inner),
+            inner,
+        })
+    }
+}
+
+/// `Long` and `Double` are followed by an `Unusable` entry, which is not used anywhere else
+impl<'a> Arbitrary<'a> for ConstantPool {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut pool = Self::new();
+        for info in u.arbitrary_iter::<CpInfo>()? {
+            let info = info?;
+            if info.inner != CpInfoInner::Unusable && pool.entries.len() < u2::MAX as usize - 2 {
+                pool.push(info);
+            }
+        }
+        Ok(pool)
+    }
+}
+
+fn tag(inner: &CpInfoInner) -> u1 {
+    match inner {
+        CpInfoInner::Utf8(_) => 1,
+        CpInfoInner::Integer(_) => 3,
+        CpInfoInner::Float(_) => 4,
+        CpInfoInner::Long(_) => 5,
+        CpInfoInner::Double(_) => 6,
+        CpInfoInner::Class(_) => 7,
+        CpInfoInner::String(_) => 8,
+        CpInfoInner::Fieldref(_) => 9,
+        CpInfoInner::MethodRef(_) => 10,
+        CpInfoInner::InterfaceMethodref(_) => 11,
+        CpInfoInner::NameAndType(_) => 12,
+        CpInfoInner::MethodHandle(_) => 15,
+        CpInfoInner::MethodType(_) => 16,
+        CpInfoInner::Dynamic(_) => 17,
+        CpInfoInner::InvokeDynamic(_) => 18,
+        CpInfoInner::Module(_) => 19,
+        CpInfoInner::Package(_) => 20,
+        CpInfoInner::Unusable => 0,
+    }
+}
@@ -6,10 +6,20 @@
 //! todo poart to [SE16](https://docs.oracle.com/javase/specs/jvms/se16/html/jvms-4.html)
 #![allow(dead_code)]
 
+mod bytes;
+#[cfg(feature = "columnar")]
+mod columns;
+mod constant_pool;
 /// All of the Constants in the Constant Pool
 pub mod cp_info;
+mod flags;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
 
+pub use bytes::{Bytes, SharedStr};
+pub use constant_pool::ConstantPool;
 pub use cp_info::FromPool;
+pub use flags::*;
 
 // The types used in the specs
 #[allow(non_camel_case_types)]
@@ -23,6 +33,7 @@ pub type u4 = u32;
 /// # Represents a .class file
 ///
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ClassFile {
     /// Magic number identifying the format (= 0xCAFEBABE)
     pub magic: u4,
@@ -32,9 +43,9 @@ pub struct ClassFile {
     pub major_version: u2,
     /// `constant_pool_count` = Number of entries in the constant pool + 1  
     /// The constant pool. Indexed from 1 to constant_pool_count - 1
-    pub constant_pool: Vec<CpInfo>,
+    pub constant_pool: ConstantPool,
     /// Mask of `ClassAccessFlag` used to denote access permissions
-    pub access_flags: u2,
+    pub access_flags: ClassAccessFlags,
     /// A valid index into the `constant_pool` table. The entry must be a `Class`
     pub this_class: FromPool<cp_info::Class>,
     /// Zero or a valid index into the `constant_pool` table
@@ -59,6 +70,7 @@ pub struct CpInfo {
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum CpInfoInner {
     Class(cp_info::Class),
     Fieldref(cp_info::Fieldref),
@@ -80,13 +92,16 @@ pub enum CpInfoInner {
     InvokeDynamic(cp_info::InvokeDynamic),
     Module(cp_info::Module),
     Package(cp_info::Package),
+    /// The index after a `Long` or `Double`, which is valid but must not be used
+    Unusable,
 }
 
 /// Information about a field
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FieldInfo {
     /// Mask of `FieldAccessFlag` used to denote access permissions
-    pub access_flags: u2,
+    pub access_flags: FieldAccessFlags,
     /// Entry must be `Utf8`
     pub name_index: FromPool<cp_info::Utf8>,
     /// Entry must be `Utf8`
@@ -96,9 +111,10 @@ pub struct FieldInfo {
 
 /// Information about a method
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MethodInfo {
     /// Mask of `MethodAccessFlag` used to denote access permissions
-    pub access_flags: u2,
+    pub access_flags: MethodAccessFlags,
     /// Index to the `constant_pool` of the method name, must be `Utf8`
     pub name_index: FromPool<cp_info::Utf8>,
     /// Index to the `constant_pool` of the method descriptor, must be `Utf8`
@@ -113,21 +129,45 @@ pub struct MethodInfo {
 /// `attribute_length`: The length of the subsequent bytes, does not include the first 6
 ///
 /// _index: Index to the `constant_pool` table of any type
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AttributeInfo {
     pub attribute_name_index: FromPool<cp_info::Utf8>,
     pub attribute_length: u4,
     /// The attribute value
     pub inner: AttributeInfoInner,
+    /// The content as it was parsed, `None` for attributes that were not parsed.
+    /// It is written back verbatim by `WriteOptions::preserve_original` if `inner` was not changed,
+    /// and does not count towards the equality of attributes
+    pub original: Option<Bytes>,
+}
+
+impl PartialEq for AttributeInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.attribute_name_index == other.attribute_name_index
+            && self.attribute_length == other.attribute_length
+            && self.inner == other.inner
+    }
+}
+
+impl Eq for AttributeInfo {}
+
+impl std::hash::Hash for AttributeInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.attribute_name_index.hash(state);
+        self.attribute_length.hash(state);
+        self.inner.hash(state);
+    }
 }
 
 /// The Attributes, without the two common fields
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum AttributeInfoInner {
     __Empty,
     /// The exact kind of attribute is not known yet and will be resolved later in the process
     Unknown {
-        attribute_content: Vec<u1>,
+        attribute_content: Bytes,
     },
     /// Only on fields, the constant value of that field
     ConstantValue {
@@ -141,7 +181,7 @@ pub enum AttributeInfoInner {
         /// The number of the local variables array, including the parameters
         max_locals: u2,
         /// The JVM bytecode of this method
-        code: Vec<u1>,
+        code: Bytes,
         /// The exception handlers for this method
         exception_table: Vec<AttributeCodeException>,
         /// The attributes of the code
@@ -166,8 +206,18 @@ pub enum AttributeInfoInner {
     EnclosingMethod {
         /// Must be a `Class` constant, the innermost enclosing class
         class_index: FromPool<cp_info::Class>,
-        /// Must be zero or `NameAndType`
-        method_index: FromPool<cp_info::NameAndType>,
+        /// Must be zero or `NameAndType`, zero if the class is not enclosed by a method
+        method_index: FromPool<Option<cp_info::NameAndType>>,
+    },
+    /// Only on a `ClassFile`, names the host of the nest the class belongs to
+    NestHost {
+        /// Must be a `Class` constant
+        host_class_index: FromPool<cp_info::Class>,
+    },
+    /// Only on a `ClassFile`, the nest host lists all other members of its nest
+    NestMembers {
+        /// Must be `Class` constants
+        classes: Vec<FromPool<cp_info::Class>>,
     },
     /// Can be on `ClassFile`, `FieldInfo`,or `MethodInfo`.
     /// Every generated class has to have this attribute or the `Synthetic` Accessor modifier
@@ -219,6 +269,19 @@ pub enum AttributeInfoInner {
     RuntimeInvisibleParameterAnnotations {
         parameter_annotations: Vec<ParameterAnnotation>,
     },
+    /// Can be on `ClassFile`, `FieldInfo`, `MethodInfo` or the `Code` attribute. Annotations on types used in
+    /// declarations and expressions, visible during runtime
+    RuntimeVisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation>,
+    },
+    /// Same as `RuntimeVisibleTypeAnnotations`, but invisible to reflection
+    RuntimeInvisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation>,
+    },
+    /// Only on `MethodInfo`, the names and flags of the formal parameters
+    MethodParameters {
+        parameters: Vec<AttributeMethodParameter>,
+    },
     /// Only on `MethodInfo`, on those representing elements of annotation types, the default value of the element
     AnnotationDefault {
         default_value: AnnotationElementValue,
@@ -229,18 +292,39 @@ pub enum AttributeInfoInner {
     },
     /// Only on `ClassFile`, where there may be one at most. Specifies packages exported and opened by a module
     Module(Box<Module>),
-
-    // todo
-    MethodParameters,
-    ModulePackages,
-    ModuleMainClass,
-    NestHost,
-    NestMembers,
-    Record,
+    /// Only on `ClassFile` with a `Module` attribute. All packages of the module, exported or not
+    ModulePackages {
+        /// Each entry must be a `Package`
+        package_index: Vec<FromPool<cp_info::Package>>,
+    },
+    /// Only on `ClassFile` with a `Module` attribute. The main class of the module
+    ModuleMainClass {
+        /// Must be a `Class`
+        main_class_index: FromPool<cp_info::Class>,
+    },
+    /// Only on `ClassFile`, the class is a record with these components
+    Record {
+        components: Vec<RecordComponentInfo>,
+    },
+    /// Nonstandard, emitted by `javac -Xjcov`. Only on the `Code` attribute, maps code ranges to source character ranges
+    CharacterRangeTable {
+        character_range_table: Vec<AttributeCharacterRange>,
+    },
+    /// Nonstandard, emitted by `javac -Xjcov`. Only on `ClassFile`, identifies the compilation
+    CompilationID {
+        /// Must be `Utf8`, the time of the compilation in milliseconds
+        compilation_id_index: FromPool<cp_info::Utf8>,
+    },
+    /// Nonstandard, emitted by `javac -Xjcov`. Only on `ClassFile`, identifies the source file
+    SourceID {
+        /// Must be `Utf8`, the last modification time of the source file in milliseconds
+        source_id_index: FromPool<cp_info::Utf8>,
+    },
 }
 
 /// An exception handler in the JVM bytecode array
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AttributeCodeException {
     /// The ranges in the code in which the handler is active. Must be a valid index into the code array.
     /// The `start_pc` is inclusive
@@ -258,6 +342,7 @@ pub struct AttributeCodeException {
 /// Specifies the type state at a particular bytecode offset
 /// Has a offset_delta, the offset is calculated by adding offset_delta + 1 to the previous offset
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum StackMapFrame {
     /// Exactly the same locals as the previous frame and zero stack items, offset_delta is frame_type
     SameFrame {
@@ -302,6 +387,7 @@ pub enum StackMapFrame {
 
 /// A stack value/local variable type `StackMapFrame`
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum VerificationTypeInfo {
     Top {
         tag: u1, // 0
@@ -337,19 +423,31 @@ pub enum VerificationTypeInfo {
 
 /// A struct for the `AttributeInfo::InnerClasses`
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AttributeInnerClass {
     /// Must be a `Class`
     pub inner_class_info_index: FromPool<cp_info::Class>,
-    /// Must be 0 or a `Class`
-    pub outer_class_info_index: FromPool<cp_info::Class>,
-    /// Must be 0 or `Utf8`
-    pub inner_class_name_index: FromPool<cp_info::Utf8>,
-    /// Must be a mask of `InnerClassAccessFlags`
-    pub inner_class_access_flags: u2,
+    /// Must be 0 or a `Class`, 0 for top level, local and anonymous classes
+    pub outer_class_info_index: FromPool<Option<cp_info::Class>>,
+    /// Must be 0 or `Utf8`, 0 for anonymous classes
+    pub inner_class_name_index: FromPool<Option<cp_info::Utf8>>,
+    /// Must be a mask of `InnerClassAccessFlag`
+    pub inner_class_access_flags: InnerClassAccessFlags,
+}
+
+/// A parameter for `AttributeInfo::MethodParameters`
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AttributeMethodParameter {
+    /// Must be 0 or `Utf8`, 0 for a parameter without a name
+    pub name_index: FromPool<Option<cp_info::Utf8>>,
+    /// Must be a mask of `MethodParameterFlag`
+    pub access_flags: MethodParameterFlags,
 }
 
 /// Line number information for `AttributeInfo::LineNumberTable`
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AttributeLineNumber {
     /// Index into the code array where a new line in the source begins
     pub start_pc: u2,
@@ -357,8 +455,25 @@ pub struct AttributeLineNumber {
     pub line_number: u2,
 }
 
+/// Character range information for `AttributeInfo::CharacterRangeTable`
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AttributeCharacterRange {
+    /// Index into the code array where the range starts, inclusive
+    pub start_pc: u2,
+    /// Index into the code array where the range ends, inclusive
+    pub end_pc: u2,
+    /// The start of the range in the source, encoded as `line << 10 | column`
+    pub character_range_start: u4,
+    /// The end of the range in the source, encoded as `line << 10 | column`
+    pub character_range_end: u4,
+    /// The kind of the range, for example 0x0001 (CRT_STATEMENT) or 0x0002 (CRT_BLOCK)
+    pub flags: u2,
+}
+
 /// Local variable information for `AttributeInfo::LocalVariableTable` and `AttributeInfo::LocalVariableTypeTable`
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AttributeLocalVariableTable {
     /// The local variable must have a value between `start_pc` and `start_pc + length`. Must be a valid opcode
     pub start_pc: u2,
@@ -374,6 +489,7 @@ pub struct AttributeLocalVariableTable {
 
 /// A runtime-visible annotation to the program
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Annotation {
     /// Must be `Utf8`
     pub type_index: FromPool<cp_info::Utf8>,
@@ -385,6 +501,7 @@ pub struct Annotation {
 
 /// A element-value pair in the `Annotation`
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AnnotationElementValuePair {
     /// Must be `Utf8`
     pub element_name_index: FromPool<cp_info::Utf8>,
@@ -393,6 +510,7 @@ pub struct AnnotationElementValuePair {
 
 /// The value of an `AnnotationElementValuePair`
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AnnotationElementValue {
     /// B, C, D, F, I, J, S, Z or s, e, c, @,
     pub tag: u1,
@@ -401,6 +519,7 @@ pub struct AnnotationElementValue {
 
 /// The value of a `AnnotationElementValue`
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum AnnotationElementValueValue {
     /// If the tag is B, C, D, F, I, J, S, Z, or s.
     ConstValueIndex {
@@ -430,73 +549,191 @@ pub enum AnnotationElementValueValue {
 
 /// Used in `AttributeInfo::RuntimeVisibleParameterAnnotations`
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ParameterAnnotation {
     pub annotations: Vec<Annotation>,
 }
 
+/// An annotation on a type, used in `AttributeInfo::RuntimeVisibleTypeAnnotations`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TypeAnnotation {
+    /// The kind of target, decides the kind of `target_info`
+    pub target_type: u1,
+    /// Which type in the declaration or expression is annotated
+    pub target_info: TypeAnnotationTarget,
+    /// Which part of the type is annotated
+    pub target_path: TypePath,
+    /// Must be `Utf8`
+    pub type_index: FromPool<cp_info::Utf8>,
+    pub num_element_value_pairs: u2,
+    pub element_value_pairs: Vec<AnnotationElementValuePair>,
+}
+
+/// The `target_info` of a `TypeAnnotation`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum TypeAnnotationTarget {
+    /// If the target type is 0x00 or 0x01, a type parameter of a generic class or method
+    TypeParameter { type_parameter_index: u1 },
+    /// If the target type is 0x10, a type in the extends (65535) or implements clause of a class
+    Supertype { supertype_index: u2 },
+    /// If the target type is 0x11 or 0x12, a bound of a type parameter of a generic class or method
+    TypeParameterBound {
+        type_parameter_index: u1,
+        bound_index: u1,
+    },
+    /// If the target type is 0x13, 0x14 or 0x15, the type of a field, the return type or the receiver type
+    Empty,
+    /// If the target type is 0x16, the type of a method parameter
+    FormalParameter { formal_parameter_index: u1 },
+    /// If the target type is 0x17, a type in the throws clause. Index into the `Exceptions` attribute
+    Throws { throws_type_index: u2 },
+    /// If the target type is 0x40 or 0x41, the type of a local or resource variable
+    Localvar { table: Vec<TypeAnnotationLocalvar> },
+    /// If the target type is 0x42, the type in an exception parameter. Index into the exception table
+    Catch { exception_table_index: u2 },
+    /// If the target type is 0x43 to 0x46, the type in an instanceof, new or method reference expression
+    Offset { offset: u2 },
+    /// If the target type is 0x47 to 0x4B, a type argument of a cast, constructor or method call or method reference
+    TypeArgument { offset: u2, type_argument_index: u1 },
+}
+
+/// A range in the code in which a local variable has a value, used in `TypeAnnotationTarget::Localvar`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TypeAnnotationLocalvar {
+    pub start_pc: u2,
+    pub length: u2,
+    /// The variable must be at `index` in the local variable array
+    pub index: u2,
+}
+
+/// The path to the annotated part of a type, for example the `String` in `List<String>`
+/// The path is empty if the type itself is annotated
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TypePath {
+    pub path: Vec<TypePathEntry>,
+}
+
+/// A step deeper into a type, used in `TypePath`
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum TypePathEntry {
+    /// Kind 0, the element type of an array type
+    Array,
+    /// Kind 1, a type nested in the type
+    Nested,
+    /// Kind 2, the bound of a wildcard type argument
+    WildcardBound,
+    /// Kind 3, the type argument with the index of a parameterized type
+    TypeArgument(u1),
+}
+
+impl std::fmt::Display for TypePath {
+    /// Formats the path like javap, for example `[TYPE_ARGUMENT(0), ARRAY]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (i, entry) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match entry {
+                TypePathEntry::Array => write!(f, "ARRAY")?,
+                TypePathEntry::Nested => write!(f, "INNER_TYPE")?,
+                TypePathEntry::WildcardBound => write!(f, "WILDCARD")?,
+                TypePathEntry::TypeArgument(index) => write!(f, "TYPE_ARGUMENT({})", index)?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// A component of a record, used in `AttributeInfo::Record`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RecordComponentInfo {
+    /// Must be `Utf8`
+    pub name_index: FromPool<cp_info::Utf8>,
+    /// Must be `Utf8`, a field descriptor
+    pub descriptor_index: FromPool<cp_info::Utf8>,
+    /// Can contain `Signature` and annotation attributes
+    pub attributes: Vec<AttributeInfo>,
+}
+
 /// Used in `AttributeInfo::BootstrapMethods `
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct BootstrapMethod {
     /// Must be a `MethodHandle`
     pub bootstrap_method_ref: FromPool<cp_info::MethodHandle>,
-    /// Each argument is a cpool entry. The constants must be `String, Class, Integer, Long, Float, Double, MethodHandle, or MethodType`
-    pub bootstrap_arguments: Vec<FromPool<CpInfoInner>>,
+    /// Each argument is a loadable constant, which can be a `Dynamic` constant itself
+    pub bootstrap_arguments: Vec<FromPool<cp_info::Loadable>>,
 }
 
 /// Used in `AttributeInfo::Module`
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Module {
-    pub module_name_index: FromPool<cp_info::Utf8>,
+    /// Must be a `Module`
+    pub module_name_index: FromPool<cp_info::Module>,
     /// The following flags exist
     /// * 0x0020 (ACC_OPEN) - Indicates that this module is open.
     /// * 0x1000 (ACC_SYNTHETIC) - Indicates that this module was not explicitly or implicitly declared.
     /// * 0x8000 (ACC_MANDATED) - Indicates that this module was implicitly declared.
-    pub module_flags: u2,
+    pub module_flags: ModuleFlags,
     /// The version of the module
     pub module_version_index: FromPool<Option<cp_info::Utf8>>,
     /// If the module is `java.base`, the Vec must be empty
     pub requires: Vec<ModuleRequires>,
     pub exports: Vec<ModuleExports>,
     pub opens: Vec<ModuleOpens>,
-    pub uses_index: Vec<u2>,
+    /// Each entry must be a `Class`, representing a service interface the module may discover
+    pub uses_index: Vec<FromPool<cp_info::Class>>,
     pub provides: Vec<ModuleProvides>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ModuleRequires {
     pub requires_index: FromPool<cp_info::Module>,
     /// * 0x0020 (ACC_TRANSITIVE) - Indicates that any module which depends on the current module, implicitly declares a dependence on the module indicated by this entry.
     /// * 0x0040 (ACC_STATIC_PHASE) - Indicates that this dependence is mandatory in the static phase, i.e., at compile time, but is optional in the dynamic phase, i.e., at run time.
     /// * 0x1000 (ACC_SYNTHETIC) - Indicates that this dependence was not explicitly or implicitly declared in the source of the module declaration.
     /// * 0x8000 (ACC_MANDATED) - Indicates that this dependence was implicitly declared in the source of the module declaration.
+    ///
     /// If the current module is not java.base, and the class file version number is 54.0 or above, then neither ACC_TRANSITIVE nor ACC_STATIC_PHASE may be set in requires_flags.
-    pub requires_flags: u2,
+    pub requires_flags: RequiresFlags,
     pub requires_version_index: FromPool<Option<cp_info::Utf8>>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ModuleExports {
     pub exports_index: FromPool<cp_info::Package>,
     /// * 0x1000 (ACC_SYNTHETIC) - Indicates that this export was not explicitly or implicitly declared in the source of the module declaration.
     /// * 0x8000 (ACC_MANDATED) - Indicates that this export was implicitly declared in the source of the module declaration.
-    pub exports_flags: u2,
+    pub exports_flags: ModulePackageFlags,
     /// If there are no exports, the package is *unqualified*, allowing unrestricted access  
     /// If there are exports, the package is *qualified*, only allowing the following modules can access it
     pub exports_to_index: Vec<FromPool<cp_info::Module>>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ModuleOpens {
-    pub opens_index: FromPool<cp_info::Module>,
+    pub opens_index: FromPool<cp_info::Package>,
     /// * 0x1000 (ACC_SYNTHETIC) - Indicates that this opening was not explicitly or implicitly declared in the source of the module declaration.
     /// * 0x8000 (ACC_MANDATED) - Indicates that this opening was implicitly declared in the source of the module declaration.
-    pub opens_flags: u2,
+    pub opens_flags: ModulePackageFlags,
     /// If there are no exports, the package is *unqualified*, allowing unrestricted reflective access  
     /// If there are exports, the package is *qualified*, only allowing the following modules can reflectively access it
     pub opens_to_index: Vec<FromPool<cp_info::Module>>,
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 /// A service interface for which this module represents an implementation
 pub struct ModuleProvides {
     /// Represents the interface
@@ -504,109 +741,3 @@ pub struct ModuleProvides {
     /// Represents the implementations, must be nonzero
     pub provides_with_index: Vec<FromPool<cp_info::Class>>,
 }
-
-/////// Access Flags
-
-/// Access Flags of a class
-#[repr(u16)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub enum ClassAccessFlag {
-    /// Declared public; may be accessed from outside its package.
-    Public = 0x0001,
-    /// Declared final; no subclasses allowed.
-    Final = 0x0010,
-    /// Treat superclass methods specially when invoked by the invokespecial instruction.
-    Super = 0x0020,
-    /// Is an interface, not a class.
-    Interface = 0x0200,
-    /// Declared abstract; must not be instantiated.
-    Abstract = 0x0400,
-    /// Declared synthetic; not present in the source code.
-    Synthetic = 0x1000,
-    /// Declared as an annotation type.
-    Annotation = 0x2000,
-    /// Declared as an enum type.
-    Enum = 0x4000,
-    /// Is a module, not a class or interface.
-    MODULE = 0x8000,
-}
-
-/// Access Flags of a method
-#[repr(u16)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub enum MethodAccessFlag {
-    /// Declared public; may be accessed from outside its package.
-    PUBLIC = 0x0001,
-    /// Declared private; accessible only within the defining class.
-    PRIVATE = 0x0002,
-    /// Declared protected; may be accessed within subclasses.
-    PROTECTED = 0x0004,
-    /// Declared static.
-    STATIC = 0x0008,
-    /// Declared final; must not be overridden.
-    FINAL = 0x0010,
-    /// Declared synchronized; invocation is wrapped by a monitor use.
-    SYNCHRONIZED = 0x0020,
-    /// A bridge method, generated by the compiler.
-    BRIDGE = 0x0040,
-    /// Declared with variable number of arguments.
-    VARARGS = 0x0080,
-    /// Declared native; implemented in a language other than Java.
-    NATIVE = 0x0100,
-    /// Declared abstract; no implementation is provided.
-    ABSTRACT = 0x0400,
-    /// Declared strictfp; floating-point mode is FP-strict.
-    STRICT = 0x0800,
-    // /Declared synthetic; not present in the source code.
-    SYNTHETIC = 0x1000,
-}
-
-/// Access flags for an inner class
-#[repr(u16)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub enum InnerClassAccessFlags {
-    /// Marked or implicitly public in source.
-    PUBLIC = 0x0001,
-    /// Marked private in source.
-    PRIVATE = 0x0002,
-    /// Marked protected in source.
-    PROTECTED = 0x0004,
-    /// Marked or implicitly static in source.
-    STATIC = 0x0008,
-    /// Marked final in source.
-    FINAL = 0x0010,
-    /// Was an interface in source.
-    INTERFACE = 0x0200,
-    /// Marked or implicitly abstract in source.
-    ABSTRACT = 0x0400,
-    /// Declared synthetic; not present in the source code.
-    SYNTHETIC = 0x1000,
-    /// Declared as an annotation type.
-    ANNOTATION = 0x2000,
-    /// Declared as an enum type.
-    ENUM = 0x4000,
-}
-
-/// Access flags for a field
-#[repr(u16)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub enum FieldAccessFlags {
-    /// Declared public; may be accessed from outside its package.
-    PUBLIC = 0x0001,
-    /// Declared private; usable only within the defining class.
-    PRIVATE = 0x0002,
-    /// Declared protected; may be accessed within subclasses.
-    PROTECTED = 0x0004,
-    /// Declared static.
-    STATIC = 0x0008,
-    /// Declared final; never directly assigned to after object construction (JLS §17.5).
-    FINAL = 0x0010,
-    /// Declared volatile; cannot be cached.
-    VOLATILE = 0x0040,
-    /// Declared transient; not written or read by a persistent object manager.
-    TRANSIENT = 0x0080,
-    /// Declared synthetic; not present in the source code.
-    SYNTHETIC = 0x1000,
-    /// Declared as an element of an enum.
-    ENUM = 0x4000,
-}
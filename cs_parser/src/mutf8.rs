@@ -0,0 +1,104 @@
+//!
+//! The modified UTF-8 used by `CONSTANT_Utf8`
+//!
+//! It differs from UTF-8 in two ways: NUL is encoded with two bytes, and supplementary
+//! characters are encoded as a surrogate pair with three bytes each
+//!
+
+use crate::{ParseErr, Result};
+
+/// Decodes modified UTF-8. Unpaired surrogates cannot be represented in a `String`,
+/// they are replaced with `U+FFFD`. `SharedStr::from_mutf8` keeps the original bytes of such strings
+pub fn from_mutf8(bytes: &[u8]) -> Result<String> {
+    Ok(decode(bytes)?.0)
+}
+
+/// Decodes modified UTF-8, and whether unpaired surrogates were replaced
+pub(crate) fn decode(bytes: &[u8]) -> Result<(String, bool)> {
+    if let Some(str) = as_plain_utf8(bytes) {
+        return Ok((str.to_owned(), false));
+    }
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let unit = match byte {
+            0x01..=0x7F => {
+                i += 1;
+                byte as u16
+            }
+            0xC0..=0xDF => {
+                let unit = (byte as u16 & 0x1F) << 6 | continuation(bytes, i + 1)?;
+                i += 2;
+                unit
+            }
+            0xE0..=0xEF => {
+                let unit = (byte as u16 & 0x0F) << 12
+                    | continuation(bytes, i + 1)? << 6
+                    | continuation(bytes, i + 2)?;
+                i += 3;
+                unit
+            }
+            _ => {
+                return Err(ParseErr::new(format!(
+                    "Invalid modified utf8 byte 0x{:02X} at {}",
+                    byte, i
+                )))
+            }
+        };
+        units.push(unit);
+    }
+    let mut lossy = false;
+    let str = char::decode_utf16(units)
+        .map(|char| {
+            char.unwrap_or_else(|_| {
+                lossy = true;
+                char::REPLACEMENT_CHARACTER
+            })
+        })
+        .collect();
+    Ok((str, lossy))
+}
+
+/// The string if the modified UTF-8 is also valid UTF-8 with the same meaning, which is the case
+/// for almost all strings in class files
+pub(crate) fn as_plain_utf8(bytes: &[u8]) -> Option<&str> {
+    // without NUL and four byte sequences, valid UTF-8 means the same in both encodings
+    if bytes.iter().any(|&byte| byte == 0 || byte >= 0xF0) {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok()
+}
+
+fn continuation(bytes: &[u8], i: usize) -> Result<u16> {
+    match bytes.get(i) {
+        Some(byte) if byte & 0xC0 == 0x80 => Ok(*byte as u16 & 0x3F),
+        Some(byte) => Err(ParseErr::new(format!(
+            "Invalid modified utf8 continuation byte 0x{:02X} at {}",
+            byte, i
+        ))),
+        None => Err(ParseErr::new(
+            "Modified utf8 ends in the middle of a character",
+        )),
+    }
+}
+
+/// Encodes a string as modified UTF-8
+pub fn to_mutf8(str: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(str.len());
+    for unit in str.encode_utf16() {
+        match unit {
+            0x01..=0x7F => bytes.push(unit as u8),
+            0x00 | 0x80..=0x7FF => {
+                bytes.extend([0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8])
+            }
+            _ => bytes.extend([
+                0xE0 | (unit >> 12) as u8,
+                0x80 | (unit >> 6 & 0x3F) as u8,
+                0x80 | (unit & 0x3F) as u8,
+            ]),
+        }
+    }
+    bytes
+}
@@ -0,0 +1,169 @@
+//!
+//! Where a class was declared in the source, reconstructed from `InnerClasses`, `EnclosingMethod`,
+//! `NestHost`, `NestMembers` and `Synthetic`
+//!
+
+use crate::{AttributeInfoInner, ClassAccessFlag, ClassFile};
+use std::fmt::{Display, Formatter};
+
+/// How a class is nested in other classes
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct NestingInfo {
+    pub kind: NestingKind,
+    /// The internal name of the nest host, `None` if the class hosts its own nest
+    pub nest_host: Option<String>,
+    /// The internal names of the other members of the nest, only present on the nest host
+    pub nest_members: Vec<String>,
+    /// The internal names of the member classes declared directly in this class
+    pub member_classes: Vec<String>,
+    /// Generated by the compiler, through the flag or the `Synthetic` attribute
+    pub synthetic: bool,
+}
+
+/// The kind of declaration of a class
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum NestingKind {
+    TopLevel,
+    /// Declared as a member of another class
+    Member {
+        /// The internal name of the declaring class
+        outer_class: String,
+        /// The name in the source
+        simple_name: String,
+    },
+    /// Declared in a block
+    Local {
+        enclosing: Enclosing,
+        /// The name in the source
+        simple_name: String,
+    },
+    /// Declared in an expression
+    Anonymous {
+        enclosing: Enclosing,
+    },
+}
+
+/// The innermost class and method a local or anonymous class is declared in
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Enclosing {
+    /// The internal name of the class
+    pub class: String,
+    /// The name and descriptor of the method, `None` for initializers of fields
+    pub method: Option<(String, String)>,
+}
+
+impl ClassFile {
+    /// Where the class was declared in the source, combining all attributes about nesting
+    pub fn nesting_info(&self) -> NestingInfo {
+        let cp = &self.constant_pool;
+        let this_class = self.this_class.get(cp).name_index.get(cp);
+
+        let mut info = NestingInfo {
+            kind: NestingKind::TopLevel,
+            nest_host: None,
+            nest_members: Vec::new(),
+            member_classes: Vec::new(),
+            synthetic: self.access_flags.contains(ClassAccessFlag::Synthetic),
+        };
+        let mut enclosing = None;
+        let mut inner_class = None;
+
+        for attr in &self.attributes {
+            match &attr.inner {
+                AttributeInfoInner::InnerClasses { classes } => {
+                    for class in classes {
+                        let inner_name = class.inner_class_info_index.get(cp).name_index.get(cp);
+                        let outer_name = class
+                            .outer_class_info_index
+                            .maybe_get(cp)
+                            .map(|outer| outer.name_index.get(cp));
+                        if inner_name == this_class {
+                            inner_class =
+                                Some((outer_name, class.inner_class_name_index.maybe_get(cp)));
+                        } else if outer_name == Some(this_class) {
+                            info.member_classes.push(inner_name.to_owned());
+                        }
+                    }
+                }
+                AttributeInfoInner::EnclosingMethod {
+                    class_index,
+                    method_index,
+                } => {
+                    enclosing = Some(Enclosing {
+                        class: class_index.get(cp).name_index.get(cp).to_owned(),
+                        method: method_index.maybe_get(cp).map(|method| {
+                            (
+                                method.name_index.get(cp).to_owned(),
+                                method.descriptor_index.get(cp).to_owned(),
+                            )
+                        }),
+                    })
+                }
+                AttributeInfoInner::NestHost { host_class_index } => {
+                    info.nest_host = Some(host_class_index.get(cp).name_index.get(cp).to_owned())
+                }
+                AttributeInfoInner::NestMembers { classes } => {
+                    info.nest_members = classes
+                        .iter()
+                        .map(|class| class.get(cp).name_index.get(cp).to_owned())
+                        .collect()
+                }
+                AttributeInfoInner::Synthetic => info.synthetic = true,
+                _ => {}
+            }
+        }
+
+        info.kind = match (inner_class, enclosing) {
+            (Some((Some(outer_class), Some(simple_name))), _) => NestingKind::Member {
+                outer_class: outer_class.to_owned(),
+                simple_name: simple_name.to_owned(),
+            },
+            (Some((None, Some(simple_name))), Some(enclosing)) => NestingKind::Local {
+                enclosing,
+                simple_name: simple_name.to_owned(),
+            },
+            (Some((None, None)), Some(enclosing)) => NestingKind::Anonymous { enclosing },
+            _ => NestingKind::TopLevel,
+        };
+        info
+    }
+}
+
+impl Display for Enclosing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.method {
+            Some((name, descriptor)) => write!(f, "{}.{}{}", self.class, name, descriptor),
+            None => write!(f, "{}", self.class),
+        }
+    }
+}
+
+impl Display for NestingInfo {
+    /// Describes the declaration in one line, for example `local class Local declared in Foo.bar()V`
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.synthetic {
+            write!(f, "synthetic ")?;
+        }
+        match &self.kind {
+            NestingKind::TopLevel => write!(f, "top level class")?,
+            NestingKind::Member {
+                outer_class,
+                simple_name,
+            } => write!(f, "member class {} of {}", simple_name, outer_class)?,
+            NestingKind::Local {
+                enclosing,
+                simple_name,
+            } => write!(f, "local class {} declared in {}", simple_name, enclosing)?,
+            NestingKind::Anonymous { enclosing } => {
+                write!(f, "anonymous class declared in {}", enclosing)?
+            }
+        }
+        if let Some(host) = &self.nest_host {
+            write!(f, ", nest host {}", host)?;
+        }
+        if !self.nest_members.is_empty() {
+            write!(f, ", nest members {}", self.nest_members.join(" "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,791 @@
+//!
+//! A peephole optimizer for the code of methods
+//!
+
+use crate::assemble::branch_constructor;
+use crate::disassemble::branch_targets;
+use crate::subroutines::falls_through;
+use crate::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Debug)]
+pub struct OptimizeErr(pub String);
+
+impl Display for OptimizeErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not optimize code: {}", self.0)
+    }
+}
+
+impl std::error::Error for OptimizeErr {}
+
+type OptimizeResult<T> = std::result::Result<T, OptimizeErr>;
+
+impl ClassFile {
+    /// Rewrites the code of every method until none of these rewrites applies anymore:
+    /// - `nop`s are removed
+    /// - branches to a `goto` branch to its target instead, a `goto` to the next instruction
+    ///   is removed
+    /// - arithmetic on `int` constants, like `iconst_2 iconst_3 imul`, is folded into one
+    ///   constant, if it fits into a `sipush` or is in the constant pool already
+    /// - stores to local variables that are not read afterwards are replaced by a `pop`
+    /// - unreachable code is removed
+    ///
+    /// The exception table, `LineNumberTable` and local variable tables are moved along. Methods
+    /// with subroutines, or with other attributes in their code, are left unchanged.
+    /// Changed methods lose their `StackMapTable`, classes of version 51 and above need them
+    /// computed again, like `cs_verifier::optimize` does.
+    /// Returns the number of methods that were changed
+    pub fn optimize(&mut self) -> OptimizeResult<usize> {
+        let cp = &self.constant_pool;
+        let mut optimized = 0;
+        for method in &mut self.methods {
+            let name = method.name_index.get(cp);
+            let descriptor = method.descriptor_index.get(cp);
+            for attr in &mut method.attributes {
+                if let AttributeInfoInner::Code {
+                    max_locals,
+                    code,
+                    exception_table,
+                    attributes,
+                    ..
+                } = &mut attr.inner
+                {
+                    let changed = optimize_code(*max_locals, code, exception_table, attributes, cp)
+                        .map_err(|OptimizeErr(message)| {
+                            OptimizeErr(format!("{}{}: {}", name, descriptor, message))
+                        })?;
+                    if changed {
+                        // max_stack, max_locals, the lengths of the code, exception table and
+                        // attributes, and the name index and length of each attribute
+                        attr.attribute_length = 12
+                            + code.len() as u4
+                            + 8 * exception_table.len() as u4
+                            + attributes
+                                .iter()
+                                .map(|attr| 6 + attr.attribute_length)
+                                .sum::<u4>();
+                        optimized += 1;
+                    }
+                }
+            }
+        }
+        Ok(optimized)
+    }
+}
+
+/// Optimizes the code, returns whether it changed
+fn optimize_code(
+    max_locals: u2,
+    code: &mut Bytes,
+    exception_table: &mut Vec<AttributeCodeException>,
+    attributes: &mut Vec<AttributeInfo>,
+    cp: &ConstantPool,
+) -> OptimizeResult<bool> {
+    let instructions = decode_code(code).map_err(|err| OptimizeErr(err.to_string()))?;
+    let has_subroutines = instructions.iter().any(|(_, instruction)| {
+        matches!(
+            instruction,
+            Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+        )
+    });
+    let has_other_attributes = attributes.iter().any(|attr| {
+        !matches!(
+            attr.inner,
+            AttributeInfoInner::LineNumberTable { .. }
+                | AttributeInfoInner::LocalVariableTable { .. }
+                | AttributeInfoInner::LocalVariableTypeTable { .. }
+                | AttributeInfoInner::StackMapTable { .. }
+        )
+    });
+    if has_subroutines || has_other_attributes {
+        return Ok(false);
+    }
+
+    let mut peephole = Peephole {
+        nodes: instructions
+            .into_iter()
+            .map(|(offset, instruction)| Node {
+                offset,
+                op: Op::new(offset, instruction),
+            })
+            .collect(),
+        moved: HashMap::new(),
+        end: code.len() as u32,
+        exception_table,
+        max_locals: max_locals.into(),
+        cp,
+    };
+    let mut changed = false;
+    loop {
+        let mut changed_now = peephole.remove_nops();
+        changed_now |= peephole.collapse_gotos();
+        changed_now |= peephole.fold_constants();
+        changed_now |= peephole.eliminate_dead_stores();
+        changed_now |= peephole.remove_unreachable();
+        if !changed_now {
+            break;
+        }
+        changed = true;
+    }
+    if !changed {
+        return Ok(false);
+    }
+
+    let mut asm = Assembler::new();
+    let labels = peephole
+        .nodes
+        .iter()
+        .map(|node| node.offset)
+        .chain([peephole.end])
+        .map(|offset| (offset, asm.new_label()))
+        .collect::<HashMap<_, _>>();
+    let label = |offset: u32| labels[&peephole.resolve(offset)];
+    let mut exceptions = Vec::new();
+    for exception in exception_table.iter() {
+        let start = peephole.resolve(exception.start_pc.into());
+        let end = peephole.resolve(exception.end_pc.into());
+        if start < end {
+            exceptions.push((
+                label(start),
+                label(end),
+                label(exception.handler_pc.into()),
+                exception.catch_type,
+            ));
+        }
+    }
+    let mut lines = HashMap::new();
+    let mut variables = Vec::new();
+    for (index, attr) in attributes.iter().enumerate() {
+        match &attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => {
+                let mut table = line_number_table.iter().collect::<Vec<_>>();
+                // the line of a removed instruction moves to the next one, unless it has its own
+                table.sort_by_key(|line| line.start_pc);
+                let moved = lines.entry(index).or_insert_with(BTreeMap::new);
+                for line in table {
+                    let start = peephole.resolve(line.start_pc.into());
+                    if start < peephole.end {
+                        moved.insert(start, (label(start), line.line_number));
+                    }
+                }
+            }
+            AttributeInfoInner::LocalVariableTable {
+                local_variable_table,
+            }
+            | AttributeInfoInner::LocalVariableTypeTable {
+                local_variable_table,
+            } => {
+                for (position, variable) in local_variable_table.iter().enumerate() {
+                    let start = u32::from(variable.start_pc);
+                    let end = peephole.resolve(start + u32::from(variable.length));
+                    let start = peephole.resolve(start);
+                    if start < end {
+                        let labels = (label(start), label(end));
+                        variables.push((index, position, labels));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for node in &peephole.nodes {
+        let targets = node.op.targets().map(&label).collect::<Vec<_>>();
+        asm.place(labels[&node.offset]);
+        match &node.op {
+            Op::Plain(instruction) => asm.push(instruction.clone()),
+            Op::Branch(branch, _) => asm.branch(*branch, targets[0]),
+            Op::Tableswitch { low, .. } => {
+                let (default, targets) = targets.split_last().expect("a default");
+                asm.tableswitch(*low, *default, targets.to_vec())
+            }
+            Op::Lookupswitch { pairs, .. } => {
+                let (default, targets) = targets.split_last().expect("a default");
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, _)| *key)
+                    .zip(targets.iter().copied());
+                asm.lookupswitch(*default, pairs.collect())
+            }
+        }
+    }
+    asm.place(labels[&peephole.end]);
+    let assembled = asm
+        .assemble()
+        .map_err(|WriteErr(message)| OptimizeErr(message))?;
+    let offset = |label| {
+        let offset = assembled.offset(label).expect("all labels are placed");
+        u2::try_from(offset).expect("the code is at most u2::MAX bytes long")
+    };
+
+    *exception_table = exceptions
+        .into_iter()
+        .map(|(start, end, handler, catch_type)| AttributeCodeException {
+            start_pc: offset(start),
+            end_pc: offset(end),
+            handler_pc: offset(handler),
+            catch_type,
+        })
+        .collect();
+    let mut variables = variables.into_iter().peekable();
+    for (index, attr) in attributes.iter_mut().enumerate() {
+        match &mut attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => {
+                *line_number_table = lines[&index]
+                    .values()
+                    .map(|(label, line_number)| AttributeLineNumber {
+                        start_pc: offset(*label),
+                        line_number: *line_number,
+                    })
+                    .collect();
+                attr.attribute_length = 2 + 4 * line_number_table.len() as u4;
+            }
+            AttributeInfoInner::LocalVariableTable {
+                local_variable_table,
+            }
+            | AttributeInfoInner::LocalVariableTypeTable {
+                local_variable_table,
+            } => {
+                let mut table = Vec::new();
+                while let Some((_, position, (start, end))) =
+                    variables.next_if(|(attr, ..)| *attr == index)
+                {
+                    table.push(AttributeLocalVariableTable {
+                        start_pc: offset(start),
+                        length: offset(end) - offset(start),
+                        ..local_variable_table[position]
+                    });
+                }
+                *local_variable_table = table;
+                attr.attribute_length = 2 + 10 * local_variable_table.len() as u4;
+            }
+            _ => {}
+        }
+    }
+    attributes.retain(|attr| !matches!(attr.inner, AttributeInfoInner::StackMapTable { .. }));
+    *code = assembled.code.into();
+    Ok(true)
+}
+
+/// An instruction whose branch targets are the original offsets of instructions
+#[derive(Debug, Clone)]
+enum Op {
+    Plain(Instruction),
+    Branch(fn(i32) -> Instruction, u32),
+    Tableswitch {
+        low: i32,
+        default: u32,
+        targets: Vec<u32>,
+    },
+    Lookupswitch {
+        default: u32,
+        pairs: Vec<(i32, u32)>,
+    },
+}
+
+impl Op {
+    fn new(offset: u32, instruction: Instruction) -> Self {
+        let targets = branch_targets(offset, &instruction)
+            .into_iter()
+            .map(|target| target as u32)
+            .collect::<Vec<_>>();
+        match instruction {
+            Instruction::Tableswitch { low, .. } => {
+                let (default, targets) = targets.split_last().expect("a default");
+                Self::Tableswitch {
+                    low,
+                    default: *default,
+                    targets: targets.to_vec(),
+                }
+            }
+            Instruction::Lookupswitch { pairs, .. } => {
+                let (default, targets) = targets.split_last().expect("a default");
+                Self::Lookupswitch {
+                    default: *default,
+                    pairs: pairs
+                        .iter()
+                        .map(|(key, _)| *key)
+                        .zip(targets.to_vec())
+                        .collect(),
+                }
+            }
+            instruction => match branch_constructor(&instruction) {
+                Some(branch) => Self::Branch(branch, targets[0]),
+                None => Self::Plain(instruction),
+            },
+        }
+    }
+
+    /// The targets of a branch or switch, the default of a switch last
+    fn targets(&self) -> impl Iterator<Item = u32> + '_ {
+        let targets: Box<dyn Iterator<Item = &u32>> = match self {
+            Self::Plain(_) => Box::new(std::iter::empty()),
+            Self::Branch(_, target) => Box::new(std::iter::once(target)),
+            Self::Tableswitch {
+                default, targets, ..
+            } => Box::new(targets.iter().chain([default])),
+            Self::Lookupswitch { default, pairs } => {
+                Box::new(pairs.iter().map(|(_, target)| target).chain([default]))
+            }
+        };
+        targets.copied()
+    }
+
+    fn targets_mut(&mut self) -> Vec<&mut u32> {
+        match self {
+            Self::Plain(_) => Vec::new(),
+            Self::Branch(_, target) => vec![target],
+            Self::Tableswitch {
+                default, targets, ..
+            } => targets.iter_mut().chain([default]).collect(),
+            Self::Lookupswitch { default, pairs } => pairs
+                .iter_mut()
+                .map(|(_, target)| target)
+                .chain([default])
+                .collect(),
+        }
+    }
+
+    fn is_goto(&self) -> bool {
+        match self {
+            Self::Branch(branch, _) => {
+                matches!(branch(0), Instruction::Goto(_) | Instruction::GotoW(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether execution can continue with the next instruction
+    fn falls_through(&self) -> bool {
+        match self {
+            Self::Plain(instruction) => falls_through(instruction),
+            Self::Branch(branch, _) => falls_through(&branch(0)),
+            Self::Tableswitch { .. } | Self::Lookupswitch { .. } => false,
+        }
+    }
+}
+
+struct Node {
+    /// The original offset of the instruction, or of the first one it replaced
+    offset: u32,
+    op: Op,
+}
+
+struct Peephole<'a> {
+    nodes: Vec<Node>,
+    /// The original offsets of removed instructions, and the one of the instruction after them
+    moved: HashMap<u32, u32>,
+    /// The length of the original code, the offset after the last instruction
+    end: u32,
+    /// With the original offsets
+    exception_table: &'a [AttributeCodeException],
+    max_locals: usize,
+    cp: &'a ConstantPool,
+}
+
+impl Peephole<'_> {
+    /// The offset of the instruction that took the place of the one at `offset`
+    fn resolve(&self, mut offset: u32) -> u32 {
+        while let Some(next) = self.moved.get(&offset) {
+            offset = *next;
+        }
+        offset
+    }
+
+    fn index_of(&self, offset: u32) -> Option<usize> {
+        self.nodes
+            .binary_search_by_key(&offset, |node| node.offset)
+            .ok()
+    }
+
+    fn remove(&mut self, index: usize) {
+        let next = self
+            .nodes
+            .get(index + 1)
+            .map_or(self.end, |node| node.offset);
+        let node = self.nodes.remove(index);
+        self.moved.insert(node.offset, next);
+    }
+
+    /// The instructions that a branch or an exception handler continues at
+    fn entry_points(&self) -> HashSet<u32> {
+        let handlers = self.exception_table.iter().map(|e| e.handler_pc.into());
+        self.nodes
+            .iter()
+            .flat_map(|node| node.op.targets())
+            .chain(handlers)
+            .map(|target| self.resolve(target))
+            .collect()
+    }
+
+    fn remove_nops(&mut self) -> bool {
+        let mut changed = false;
+        let mut index = 0;
+        while index < self.nodes.len() {
+            if matches!(self.nodes[index].op, Op::Plain(Instruction::Nop)) {
+                self.remove(index);
+                changed = true;
+            } else {
+                index += 1;
+            }
+        }
+        changed
+    }
+
+    fn collapse_gotos(&mut self) -> bool {
+        let mut changed = false;
+        for index in 0..self.nodes.len() {
+            let mut op = self.nodes[index].op.clone();
+            for target in op.targets_mut() {
+                let mut resolved = self.resolve(*target);
+                // a loop of gotos is left as it is
+                for _ in 0..self.nodes.len() {
+                    match self.index_of(resolved).map(|index| &self.nodes[index].op) {
+                        Some(goto @ Op::Branch(_, next)) if goto.is_goto() => {
+                            resolved = self.resolve(*next)
+                        }
+                        _ => break,
+                    }
+                }
+                if resolved != self.resolve(*target) {
+                    *target = resolved;
+                    changed = true;
+                }
+            }
+            self.nodes[index].op = op;
+        }
+
+        let mut index = 0;
+        while index < self.nodes.len() {
+            let node = &self.nodes[index];
+            let next = self
+                .nodes
+                .get(index + 1)
+                .map_or(self.end, |node| node.offset);
+            match &node.op {
+                Op::Branch(_, target) if node.op.is_goto() && self.resolve(*target) == next => {
+                    self.remove(index);
+                    changed = true;
+                }
+                _ => index += 1,
+            }
+        }
+        changed
+    }
+
+    fn fold_constants(&mut self) -> bool {
+        // only the instructions that aren't entry points are removed, so they stay the same
+        let entry_points = self.entry_points();
+        let mut changed = false;
+        let mut index = 0;
+        while index < self.nodes.len() {
+            let constant = |index: usize| match self.nodes.get(index) {
+                Some(Node {
+                    op: Op::Plain(instruction),
+                    ..
+                }) => int_constant(instruction, self.cp),
+                _ => None,
+            };
+            let instruction = |index: usize| match self.nodes.get(index) {
+                Some(Node {
+                    op: Op::Plain(instruction),
+                    offset,
+                }) if !entry_points.contains(offset) => Some(instruction),
+                _ => None,
+            };
+            let folded = match (constant(index), constant(index + 1)) {
+                (Some(a), Some(b)) if instruction(index + 1).is_some() => instruction(index + 2)
+                    .and_then(|operation| fold_binary(operation, a, b))
+                    .map(|value| (value, 2)),
+                (Some(a), _) => instruction(index + 1)
+                    .and_then(|operation| fold_unary(operation, a))
+                    .map(|value| (value, 1)),
+                _ => None,
+            };
+            match folded.and_then(|(value, count)| Some((push_int(value, self.cp)?, count))) {
+                Some((push, count)) => {
+                    self.nodes[index].op = Op::Plain(push);
+                    for _ in 0..count {
+                        self.remove(index + 1);
+                    }
+                    changed = true;
+                }
+                None => index += 1,
+            }
+        }
+        changed
+    }
+
+    /// The indices of the instructions that can be executed after the one at `index`, including
+    /// the exception handlers that cover it
+    fn successors(&self, index: usize) -> Vec<usize> {
+        let node = &self.nodes[index];
+        let mut successors = node
+            .op
+            .targets()
+            .filter_map(|target| self.index_of(self.resolve(target)))
+            .collect::<Vec<_>>();
+        if node.op.falls_through() && index + 1 < self.nodes.len() {
+            successors.push(index + 1);
+        }
+        for exception in self.exception_table {
+            if (u32::from(exception.start_pc)..u32::from(exception.end_pc)).contains(&node.offset) {
+                successors.extend(self.index_of(self.resolve(exception.handler_pc.into())));
+            }
+        }
+        successors
+    }
+
+    fn remove_unreachable(&mut self) -> bool {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut work = vec![0];
+        while let Some(index) = work.pop() {
+            if index >= reachable.len() || reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            work.extend(self.successors(index));
+        }
+        let mut changed = false;
+        for index in (0..self.nodes.len()).rev() {
+            if !reachable[index] {
+                self.remove(index);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Replaces the stores whose value is never read by a `pop`, and removes such `iinc`s
+    fn eliminate_dead_stores(&mut self) -> bool {
+        let successors = (0..self.nodes.len())
+            .map(|index| self.successors(index))
+            .collect::<Vec<_>>();
+        let mut predecessors = vec![Vec::new(); self.nodes.len()];
+        for (index, successors) in successors.iter().enumerate() {
+            for successor in successors {
+                predecessors[*successor].push(index);
+            }
+        }
+        let accesses = self
+            .nodes
+            .iter()
+            .map(|node| match &node.op {
+                Op::Plain(instruction) => local_access(instruction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // the variables that are read before they are written again, after each instruction
+        let mut live_out = vec![Slots::new(self.max_locals); self.nodes.len()];
+        let mut work = (0..self.nodes.len()).collect::<Vec<_>>();
+        while let Some(index) = work.pop() {
+            let mut live_in = live_out[index].clone();
+            match accesses[index] {
+                Some(Access::Store { index, wide }) => {
+                    live_in.remove(index, wide);
+                }
+                Some(Access::Load { index, wide }) => live_in.insert(index, wide),
+                Some(Access::Increment(index)) => live_in.insert(index, false),
+                None => {}
+            }
+            for &predecessor in &predecessors[index] {
+                if live_out[predecessor].union(&live_in) {
+                    work.push(predecessor);
+                }
+            }
+        }
+
+        let mut changed = false;
+        for index in (0..self.nodes.len()).rev() {
+            let live = &live_out[index];
+            match accesses[index] {
+                Some(Access::Store { index: local, wide }) if !live.contains(local, wide) => {
+                    let pop = match wide {
+                        true => Instruction::Pop2,
+                        false => Instruction::Pop,
+                    };
+                    self.nodes[index].op = Op::Plain(pop);
+                    changed = true;
+                }
+                Some(Access::Increment(local)) if !live.contains(local, false) => {
+                    self.remove(index);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        changed
+    }
+}
+
+/// How an instruction uses a local variable, `wide` for `long` and `double`, which take up the
+/// slot after `index` too
+#[derive(Debug, Clone, Copy)]
+enum Access {
+    Load { index: u2, wide: bool },
+    Store { index: u2, wide: bool },
+    Increment(u2),
+}
+
+fn local_access(instruction: &Instruction) -> Option<Access> {
+    use Instruction as I;
+    let load = |index, wide| Some(Access::Load { index, wide });
+    let store = |index, wide| Some(Access::Store { index, wide });
+    match instruction {
+        I::Iload(index) | I::Fload(index) | I::Aload(index) => load(*index, false),
+        I::Lload(index) | I::Dload(index) => load(*index, true),
+        I::Iload0 | I::Fload0 | I::Aload0 => load(0, false),
+        I::Iload1 | I::Fload1 | I::Aload1 => load(1, false),
+        I::Iload2 | I::Fload2 | I::Aload2 => load(2, false),
+        I::Iload3 | I::Fload3 | I::Aload3 => load(3, false),
+        I::Lload0 | I::Dload0 => load(0, true),
+        I::Lload1 | I::Dload1 => load(1, true),
+        I::Lload2 | I::Dload2 => load(2, true),
+        I::Lload3 | I::Dload3 => load(3, true),
+        I::Istore(index) | I::Fstore(index) | I::Astore(index) => store(*index, false),
+        I::Lstore(index) | I::Dstore(index) => store(*index, true),
+        I::Istore0 | I::Fstore0 | I::Astore0 => store(0, false),
+        I::Istore1 | I::Fstore1 | I::Astore1 => store(1, false),
+        I::Istore2 | I::Fstore2 | I::Astore2 => store(2, false),
+        I::Istore3 | I::Fstore3 | I::Astore3 => store(3, false),
+        I::Lstore0 | I::Dstore0 => store(0, true),
+        I::Lstore1 | I::Dstore1 => store(1, true),
+        I::Lstore2 | I::Dstore2 => store(2, true),
+        I::Lstore3 | I::Dstore3 => store(3, true),
+        I::Iinc { index, .. } => Some(Access::Increment(*index)),
+        _ => None,
+    }
+}
+
+/// A set of local variable slots
+#[derive(Debug, Clone)]
+struct Slots(Vec<u64>);
+
+impl Slots {
+    fn new(max_locals: usize) -> Self {
+        // code that uses more slots than `max_locals` is invalid, but shouldn't panic here
+        Self(vec![0; (max_locals + 1).div_ceil(64) + 1])
+    }
+
+    fn slots(index: u2, wide: bool) -> impl Iterator<Item = usize> {
+        let index = usize::from(index);
+        index..index + 1 + usize::from(wide)
+    }
+
+    fn contains(&self, index: u2, wide: bool) -> bool {
+        Self::slots(index, wide).any(|slot| {
+            self.0
+                .get(slot / 64)
+                .is_some_and(|bits| bits & 1 << (slot % 64) != 0)
+        })
+    }
+
+    fn insert(&mut self, index: u2, wide: bool) {
+        for slot in Self::slots(index, wide) {
+            if slot / 64 >= self.0.len() {
+                self.0.resize(slot / 64 + 1, 0);
+            }
+            self.0[slot / 64] |= 1 << (slot % 64);
+        }
+    }
+
+    fn remove(&mut self, index: u2, wide: bool) {
+        for slot in Self::slots(index, wide) {
+            if let Some(bits) = self.0.get_mut(slot / 64) {
+                *bits &= !(1 << (slot % 64));
+            }
+        }
+    }
+
+    /// Adds the slots of `other`, returns whether any were new
+    fn union(&mut self, other: &Self) -> bool {
+        if other.0.len() > self.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let mut changed = false;
+        for (bits, other) in self.0.iter_mut().zip(&other.0) {
+            changed |= *other & !*bits != 0;
+            *bits |= other;
+        }
+        changed
+    }
+}
+
+/// The value an instruction pushes if it pushes an `int` constant
+fn int_constant(instruction: &Instruction, cp: &ConstantPool) -> Option<i32> {
+    let constant = |index: u2| match &cp.entry(index)?.inner {
+        CpInfoInner::Integer(integer) => Some(integer.value()),
+        _ => None,
+    };
+    Some(match instruction {
+        Instruction::IconstM1 => -1,
+        Instruction::Iconst0 => 0,
+        Instruction::Iconst1 => 1,
+        Instruction::Iconst2 => 2,
+        Instruction::Iconst3 => 3,
+        Instruction::Iconst4 => 4,
+        Instruction::Iconst5 => 5,
+        Instruction::Bipush(value) => (*value).into(),
+        Instruction::Sipush(value) => (*value).into(),
+        Instruction::Ldc(index) => constant((*index).into())?,
+        Instruction::LdcW(index) => constant(*index)?,
+        _ => return None,
+    })
+}
+
+/// The shortest instruction that pushes the value, `None` if it needs a constant that isn't in
+/// the pool
+fn push_int(value: i32, cp: &ConstantPool) -> Option<Instruction> {
+    Some(match value {
+        -1 => Instruction::IconstM1,
+        0 => Instruction::Iconst0,
+        1 => Instruction::Iconst1,
+        2 => Instruction::Iconst2,
+        3 => Instruction::Iconst3,
+        4 => Instruction::Iconst4,
+        5 => Instruction::Iconst5,
+        _ => match (i8::try_from(value), i16::try_from(value)) {
+            (Ok(value), _) => Instruction::Bipush(value),
+            (_, Ok(value)) => Instruction::Sipush(value),
+            _ => {
+                let (index, _) = cp.iter_with_indices().find(|(_, info)| {
+                    matches!(&info.inner, CpInfoInner::Integer(integer) if integer.value() == value)
+                })?;
+                match u1::try_from(index) {
+                    Ok(index) => Instruction::Ldc(index),
+                    Err(_) => Instruction::LdcW(index),
+                }
+            }
+        },
+    })
+}
+
+/// The result of an `int` operation on two constants, `None` if it isn't one or would throw
+fn fold_binary(operation: &Instruction, a: i32, b: i32) -> Option<i32> {
+    Some(match operation {
+        Instruction::Iadd => a.wrapping_add(b),
+        Instruction::Isub => a.wrapping_sub(b),
+        Instruction::Imul => a.wrapping_mul(b),
+        Instruction::Idiv if b != 0 => a.wrapping_div(b),
+        Instruction::Irem if b != 0 => a.wrapping_rem(b),
+        Instruction::Iand => a & b,
+        Instruction::Ior => a | b,
+        Instruction::Ixor => a ^ b,
+        // only the lowest five bits of the shift distance are used
+        Instruction::Ishl => a.wrapping_shl(b as u32),
+        Instruction::Ishr => a.wrapping_shr(b as u32),
+        Instruction::Iushr => (a as u32).wrapping_shr(b as u32) as i32,
+        _ => return None,
+    })
+}
+
+fn fold_unary(operation: &Instruction, a: i32) -> Option<i32> {
+    Some(match operation {
+        Instruction::Ineg => a.wrapping_neg(),
+        Instruction::I2b => a as i8 as i32,
+        Instruction::I2c => a as u16 as i32,
+        Instruction::I2s => a as i16 as i32,
+        _ => return None,
+    })
+}
@@ -0,0 +1,264 @@
+//!
+//! An owned view of a class without any constant pool indices
+//!
+
+use crate::annotations::{self, AnnotationValue, AnnotationView};
+use crate::{
+    u2, AttributeInfo, AttributeInfoInner, ClassAccessFlags, ClassFile, ConstantPool, CpInfoInner,
+    FieldAccessFlags, FieldInfo, MethodAccessFlags, MethodInfo, ParseErr, Result,
+};
+use cs_model::{FieldDescriptor, MethodDescriptor};
+use std::str::FromStr;
+
+/// A class with everything taken out of the constant pool, for consumers that don't care about
+/// the class file format. Created with `ResolvedClass::try_from(&class)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedClass {
+    pub minor_version: u2,
+    pub major_version: u2,
+    pub access_flags: ClassAccessFlags,
+    /// The internal name of the class, for example `java/lang/String`
+    pub name: String,
+    /// The internal name of the super class, `None` for `java/lang/Object`
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<ResolvedField>,
+    pub methods: Vec<ResolvedMethod>,
+    /// From the `SourceFile` attribute
+    pub source_file: Option<String>,
+    /// The generic signature from the `Signature` attribute
+    pub signature: Option<String>,
+    pub annotations: Vec<ResolvedAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedField {
+    pub access_flags: FieldAccessFlags,
+    pub name: String,
+    pub descriptor: FieldDescriptor,
+    pub signature: Option<String>,
+    /// The initial value of a static field from the `ConstantValue` attribute, one of `Int`,
+    /// `Long`, `Float`, `Double` or `String`
+    pub constant_value: Option<ResolvedValue>,
+    pub annotations: Vec<ResolvedAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedMethod {
+    pub access_flags: MethodAccessFlags,
+    pub name: String,
+    pub descriptor: MethodDescriptor,
+    pub signature: Option<String>,
+    /// The internal names of the checked exceptions from the `Exceptions` attribute
+    pub exceptions: Vec<String>,
+    pub annotations: Vec<ResolvedAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAnnotation {
+    /// The field descriptor of the annotation type, for example `Ljava/lang/Deprecated;`
+    pub type_descriptor: String,
+    /// Whether the annotation is visible to reflection
+    pub visible: bool,
+    /// The names and values of the elements, elements with the default value are not included
+    pub elements: Vec<(String, ResolvedValue)>,
+}
+
+/// The value of an annotation element or a constant field
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedValue {
+    Byte(i8),
+    /// A UTF-16 code unit
+    Char(u16),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    Long(i64),
+    Short(i16),
+    Boolean(bool),
+    String(String),
+    Enum {
+        /// The field descriptor of the enum type
+        type_descriptor: String,
+        name: String,
+    },
+    /// The return descriptor of the class, for example `Ljava/lang/Object;` or `V` for `void.class`
+    Class(String),
+    Annotation(ResolvedAnnotation),
+    Array(Vec<ResolvedValue>),
+}
+
+impl TryFrom<&ClassFile> for ResolvedClass {
+    type Error = ParseErr;
+
+    fn try_from(class: &ClassFile) -> Result<Self> {
+        let cp = &class.constant_pool;
+        let attributes = &class.attributes;
+        Ok(Self {
+            minor_version: class.minor_version,
+            major_version: class.major_version,
+            access_flags: class.access_flags,
+            name: class.this_class.get(cp).name_index.get(cp).to_owned(),
+            super_class: class
+                .super_class
+                .maybe_get(cp)
+                .map(|class| class.name_index.get(cp).to_owned()),
+            interfaces: class
+                .interfaces
+                .iter()
+                .map(|interface| interface.get(cp).name_index.get(cp).to_owned())
+                .collect(),
+            fields: class
+                .fields
+                .iter()
+                .map(|field| ResolvedField::resolve(field, cp))
+                .collect::<Result<_>>()?,
+            methods: class
+                .methods
+                .iter()
+                .map(|method| ResolvedMethod::resolve(method, cp))
+                .collect::<Result<_>>()?,
+            source_file: attributes.iter().find_map(|attr| match &attr.inner {
+                AttributeInfoInner::SourceFile { sourcefile_index } => {
+                    Some(sourcefile_index.get(cp).to_owned())
+                }
+                _ => None,
+            }),
+            signature: signature(attributes, cp),
+            annotations: annotations(attributes, cp),
+        })
+    }
+}
+
+impl ResolvedField {
+    fn resolve(field: &FieldInfo, cp: &ConstantPool) -> Result<Self> {
+        let descriptor = field.descriptor_index.get(cp);
+        let constant_value = field
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::ConstantValue {
+                    constantvalue_index,
+                } => Some(constant_value(constantvalue_index.get(cp), cp)),
+                _ => None,
+            })
+            .transpose()?;
+        Ok(Self {
+            access_flags: field.access_flags,
+            name: field.name_index.get(cp).to_owned(),
+            descriptor: FieldDescriptor::from_str(descriptor)
+                .map_err(|err| invalid_descriptor(descriptor, err))?,
+            signature: signature(&field.attributes, cp),
+            constant_value,
+            annotations: annotations(&field.attributes, cp),
+        })
+    }
+}
+
+impl ResolvedMethod {
+    fn resolve(method: &MethodInfo, cp: &ConstantPool) -> Result<Self> {
+        let descriptor = method.descriptor_index.get(cp);
+        let exceptions = method
+            .attributes
+            .iter()
+            .filter_map(|attr| match &attr.inner {
+                AttributeInfoInner::Exceptions {
+                    exception_index_table,
+                } => Some(exception_index_table),
+                _ => None,
+            })
+            .flatten()
+            .map(|&index| match cp.entry(index).map(|info| &info.inner) {
+                Some(CpInfoInner::Class(class)) => Ok(class.name_index.get(cp).to_owned()),
+                _ => Err(ParseErr::new(format!(
+                    "Exception at index {} is not a Class constant",
+                    index
+                ))),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            access_flags: method.access_flags,
+            name: method.name_index.get(cp).to_owned(),
+            descriptor: MethodDescriptor::from_str(descriptor)
+                .map_err(|err| invalid_descriptor(descriptor, err))?,
+            signature: signature(&method.attributes, cp),
+            exceptions,
+            annotations: annotations(&method.attributes, cp),
+        })
+    }
+}
+
+impl From<AnnotationView<'_>> for ResolvedAnnotation {
+    fn from(annotation: AnnotationView<'_>) -> Self {
+        Self {
+            type_descriptor: annotation.type_().to_owned(),
+            visible: annotation.is_visible(),
+            elements: annotation
+                .values()
+                .map(|(name, value)| (name.to_owned(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<AnnotationValue<'_>> for ResolvedValue {
+    fn from(value: AnnotationValue<'_>) -> Self {
+        match value {
+            AnnotationValue::Byte(value) => Self::Byte(value),
+            AnnotationValue::Char(value) => Self::Char(value),
+            AnnotationValue::Short(value) => Self::Short(value),
+            AnnotationValue::Int(value) => Self::Int(value),
+            AnnotationValue::Long(value) => Self::Long(value),
+            AnnotationValue::Float(value) => Self::Float(value),
+            AnnotationValue::Double(value) => Self::Double(value),
+            AnnotationValue::Boolean(value) => Self::Boolean(value),
+            AnnotationValue::Str(value) => Self::String(value.to_owned()),
+            AnnotationValue::Enum { type_, name } => Self::Enum {
+                type_descriptor: type_.to_owned(),
+                name: name.to_owned(),
+            },
+            AnnotationValue::Class(descriptor) => Self::Class(descriptor.to_owned()),
+            AnnotationValue::Annotation(annotation) => Self::Annotation(annotation.into()),
+            AnnotationValue::Array(values) => {
+                Self::Array(values.into_iter().map(Self::from).collect())
+            }
+        }
+    }
+}
+
+fn constant_value(info: &CpInfoInner, cp: &ConstantPool) -> Result<ResolvedValue> {
+    Ok(match info {
+        CpInfoInner::Integer(int) => ResolvedValue::Int(int.value()),
+        CpInfoInner::Long(long) => ResolvedValue::Long(long.value()),
+        CpInfoInner::Float(float) => ResolvedValue::Float(float.value()),
+        CpInfoInner::Double(double) => ResolvedValue::Double(double.value()),
+        CpInfoInner::String(string) => {
+            ResolvedValue::String(string.string_index.get(cp).to_owned())
+        }
+        info => {
+            return Err(ParseErr::new(format!(
+                "ConstantValue of a field is {:?}",
+                info
+            )))
+        }
+    })
+}
+
+fn signature(attributes: &[AttributeInfo], cp: &ConstantPool) -> Option<String> {
+    attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::Signature { signature_index } => {
+            Some(signature_index.get(cp).to_owned())
+        }
+        _ => None,
+    })
+}
+
+fn annotations(attributes: &[AttributeInfo], cp: &ConstantPool) -> Vec<ResolvedAnnotation> {
+    annotations::annotations(attributes, cp)
+        .map(ResolvedAnnotation::from)
+        .collect()
+}
+
+pub(crate) fn invalid_descriptor(descriptor: &str, err: cs_model::ParseErr) -> ParseErr {
+    ParseErr::new(format!("Invalid descriptor {}: {}", descriptor, err.0))
+}
@@ -0,0 +1,163 @@
+//!
+//! Changing the class file version of a class
+//!
+
+use crate::disassemble::branch_targets;
+use crate::*;
+
+#[derive(Debug)]
+pub struct RetargetErr(pub String);
+
+impl Display for RetargetErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not retarget class file: {}", self.0)
+    }
+}
+
+impl std::error::Error for RetargetErr {}
+
+/// The first version with the type checking verifier, older versions ignore `StackMapTable`
+const STACK_MAP_VERSION: u2 = 50;
+/// From this version on, every method with branches needs a `StackMapTable`
+const REQUIRED_STACK_MAP_VERSION: u2 = 51;
+/// Preview features are only valid for exactly the version they were compiled for
+const PREVIEW_MINOR_VERSION: u2 = 0xFFFF;
+
+impl ClassFile {
+    /// Changes the major version of the class, the minor version is reset to 0.
+    ///
+    /// Downgrading fails if the class uses a feature the older version does not have, for example
+    /// `invokedynamic` before 51 or dynamic constants before 55. `StackMapTable`s are removed below 50.
+    /// Upgrading a class below 51 to 51 or above needs a `StackMapTable` in every method with
+    /// branches or exception handlers, `cs_verifier::retarget` computes them
+    pub fn retarget(&mut self, major_version: u2) -> std::result::Result<(), RetargetErr> {
+        if self.minor_version == PREVIEW_MINOR_VERSION {
+            return Err(RetargetErr(
+                "Classes using preview features cannot be retargeted".to_string(),
+            ));
+        }
+        if major_version >= REQUIRED_STACK_MAP_VERSION
+            && self.major_version < REQUIRED_STACK_MAP_VERSION
+        {
+            if let Some(method) = self.method_without_stack_map() {
+                return Err(RetargetErr(format!(
+                    "Version {} requires stack map frames, which {} of this class of version {} \
+                     doesn't have, they can be computed with `cs_verifier::retarget`",
+                    major_version, method, self.major_version
+                )));
+            }
+        }
+        if let Some((feature, required)) = self
+            .features()
+            .into_iter()
+            .find(|(_, required)| *required > major_version)
+        {
+            return Err(RetargetErr(format!(
+                "{} requires version {}, but the target is {}",
+                feature, required, major_version
+            )));
+        }
+
+        if major_version < STACK_MAP_VERSION {
+            for method in &mut self.methods {
+                for attr in &mut method.attributes {
+                    if let AttributeInfoInner::Code { attributes, .. } = &mut attr.inner {
+                        let mut removed_length = 0;
+                        attributes.retain(|code_attr| {
+                            let is_stack_map =
+                                matches!(code_attr.inner, AttributeInfoInner::StackMapTable { .. });
+                            if is_stack_map {
+                                // the name index and length take up another 6 bytes
+                                removed_length += code_attr.attribute_length + 6;
+                            }
+                            !is_stack_map
+                        });
+                        attr.attribute_length -= removed_length;
+                    }
+                }
+            }
+        }
+
+        self.major_version = major_version;
+        self.minor_version = 0;
+        Ok(())
+    }
+
+    /// The name and descriptor of the first method with branches or exception handlers but
+    /// without a `StackMapTable`
+    fn method_without_stack_map(&self) -> Option<String> {
+        let cp = &self.constant_pool;
+        self.methods.iter().find_map(|method| {
+            let needs_stack_map = method.attributes.iter().any(|attr| match &attr.inner {
+                AttributeInfoInner::Code {
+                    code,
+                    exception_table,
+                    attributes,
+                    ..
+                } => {
+                    let has_branches = decode_code(code).map_or(true, |instructions| {
+                        instructions.iter().any(|(offset, instruction)| {
+                            !branch_targets(*offset, instruction).is_empty()
+                        })
+                    });
+                    let has_stack_map = attributes
+                        .iter()
+                        .any(|attr| matches!(attr.inner, AttributeInfoInner::StackMapTable { .. }));
+                    (has_branches || !exception_table.is_empty()) && !has_stack_map
+                }
+                _ => false,
+            });
+            needs_stack_map.then(|| {
+                format!(
+                    "{}{}",
+                    method.name_index.get(cp),
+                    method.descriptor_index.get(cp)
+                )
+            })
+        })
+    }
+
+    /// The features used by the class that need a minimum major version
+    fn features(&self) -> Vec<(&'static str, u2)> {
+        let mut features = Vec::new();
+
+        for info in &self.constant_pool {
+            match info.inner {
+                CpInfoInner::MethodHandle(_) => features.push(("A MethodHandle constant", 51)),
+                CpInfoInner::MethodType(_) => features.push(("A MethodType constant", 51)),
+                CpInfoInner::InvokeDynamic(_) => features.push(("invokedynamic", 51)),
+                CpInfoInner::Dynamic(_) => features.push(("A dynamic constant", 55)),
+                CpInfoInner::Module(_) | CpInfoInner::Package(_) => {
+                    features.push(("A module descriptor", 53))
+                }
+                _ => {}
+            }
+        }
+
+        for attr in &self.attributes {
+            match attr.inner {
+                AttributeInfoInner::Module(_) => features.push(("A module descriptor", 53)),
+                AttributeInfoInner::NestHost { .. } | AttributeInfoInner::NestMembers { .. } => {
+                    features.push(("Nest based access control", 55))
+                }
+                AttributeInfoInner::Record { .. } => features.push(("A record", 60)),
+                _ => {}
+            }
+        }
+
+        let cp = &self.constant_pool;
+        let is_interface = self.access_flags.contains(ClassAccessFlag::Interface);
+        let has_method_bodies = self.methods.iter().any(|method| {
+            method.name_index.get(cp) != "<clinit>"
+                && method
+                    .attributes
+                    .iter()
+                    .any(|attr| matches!(attr.inner, AttributeInfoInner::Code { .. }))
+        });
+        if is_interface && has_method_bodies {
+            features.push(("An interface method with a body", 52));
+        }
+
+        features
+    }
+}
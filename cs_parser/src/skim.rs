@@ -0,0 +1,143 @@
+//!
+//! A fast path that only reads the class header and member signatures, for building indexes
+//!
+
+use crate::header::parse_header;
+use crate::{
+    cp_info, parse_constant_pool, u2, ClassAccessFlags, ClassFile, ConstantPool, Data, FromPool,
+    Result,
+};
+
+/// The header information of a class file, without any attributes
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ClassSummary {
+    pub minor_version: u2,
+    pub major_version: u2,
+    pub access_flags: ClassAccessFlags,
+    /// The internal name of the class, for example `java/lang/String`
+    pub this_class: String,
+    /// The internal name of the super class, `None` for `java/lang/Object`
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<MemberSummary>,
+    pub methods: Vec<MemberSummary>,
+}
+
+/// The signature of a field or method
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct MemberSummary {
+    /// The bits of either `FieldAccessFlags` or `MethodAccessFlags`
+    pub access_flags: u2,
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// Reads only the header and the member signatures of the class file into a `ClassSummary`  
+/// Attribute bodies are skipped using their length without being parsed
+pub fn skim_class_file(data: &[u8]) -> Result<ClassSummary> {
+    let mut data = Data::new(data);
+    skim(&mut data).map_err(|err| err.at(data.position()))
+}
+
+fn skim(data: &mut Data) -> Result<ClassSummary> {
+    let (_, minor_version, major_version) = parse_header(data)?;
+    let constant_pool = parse_constant_pool(data.u2()?, data)?;
+    let cp = &constant_pool;
+
+    let access_flags = data.u2()?.into();
+    let this_class: FromPool<cp_info::Class> = data.cp(cp)?;
+    let super_class: FromPool<Option<cp_info::Class>> = data.cp(cp)?;
+    let interface_count = data.u2()?;
+    let mut interfaces = Vec::with_capacity(interface_count.into());
+    for _ in 0..interface_count {
+        let interface: FromPool<cp_info::Class> = data.cp(cp)?;
+        interfaces.push(interface.get(cp).name_index.get(cp).to_owned());
+    }
+    let fields = skim_members(data, cp)?;
+    let methods = skim_members(data, cp)?;
+
+    Ok(ClassSummary {
+        minor_version,
+        major_version,
+        access_flags,
+        this_class: this_class.get(cp).name_index.get(cp).to_owned(),
+        super_class: super_class
+            .maybe_get(cp)
+            .map(|class| class.name_index.get(cp).to_owned()),
+        interfaces,
+        fields,
+        methods,
+    })
+}
+
+fn skim_members(data: &mut Data, cp: &ConstantPool) -> Result<Vec<MemberSummary>> {
+    let count = data.u2()?;
+    let mut members = Vec::with_capacity(count.into());
+    for _ in 0..count {
+        let access_flags = data.u2()?;
+        let name: FromPool<cp_info::Utf8> = data.cp(cp)?;
+        let descriptor: FromPool<cp_info::Utf8> = data.cp(cp)?;
+        for _ in 0..data.u2()? {
+            let _name = data.u2()?;
+            let length = data.u4()?;
+            data.bytes(length as usize)?;
+        }
+        members.push(MemberSummary {
+            access_flags,
+            name: name.get(cp).to_owned(),
+            descriptor: descriptor.get(cp).to_owned(),
+        });
+    }
+    Ok(members)
+}
+
+impl From<&ClassFile> for ClassSummary {
+    fn from(class: &ClassFile) -> Self {
+        let cp = &class.constant_pool;
+        let member =
+            |access_flags, name: &FromPool<cp_info::Utf8>, descriptor: &FromPool<cp_info::Utf8>| {
+                MemberSummary {
+                    access_flags,
+                    name: name.get(cp).to_owned(),
+                    descriptor: descriptor.get(cp).to_owned(),
+                }
+            };
+        Self {
+            minor_version: class.minor_version,
+            major_version: class.major_version,
+            access_flags: class.access_flags,
+            this_class: class.this_class.get(cp).name_index.get(cp).to_owned(),
+            super_class: class
+                .super_class
+                .maybe_get(cp)
+                .map(|class| class.name_index.get(cp).to_owned()),
+            interfaces: class
+                .interfaces
+                .iter()
+                .map(|interface| interface.get(cp).name_index.get(cp).to_owned())
+                .collect(),
+            fields: class
+                .fields
+                .iter()
+                .map(|field| {
+                    member(
+                        field.access_flags.bits(),
+                        &field.name_index,
+                        &field.descriptor_index,
+                    )
+                })
+                .collect(),
+            methods: class
+                .methods
+                .iter()
+                .map(|method| {
+                    member(
+                        method.access_flags.bits(),
+                        &method.name_index,
+                        &method.descriptor_index,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//!
+//! Checking the `StackMapTable` of methods against their code
+//!
+
+use crate::*;
+use std::collections::HashMap;
+
+/// A stack map frame that does not fit the code of its method
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackMapErr {
+    /// The name and descriptor of the method, like `main([Ljava/lang/String;)V`
+    pub method: String,
+    /// The index of the frame in the `StackMapTable`, `None` if the code itself is invalid
+    pub frame: Option<usize>,
+    /// The offset in the code the frame applies to
+    pub offset: Option<u32>,
+    pub message: String,
+}
+
+impl Display for StackMapErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid stack map of {}", self.method)?;
+        if let Some(frame) = self.frame {
+            write!(f, " in frame {}", frame)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for StackMapErr {}
+
+impl ClassFile {
+    /// Checks that the frames of every `StackMapTable` are at the start of an instruction, fit into
+    /// `max_locals` and `max_stack` and only refer to valid constants and `new` instructions.
+    ///
+    /// The types are not checked against the instructions, only the frames on their own
+    pub fn verify_stack_maps(&self) -> std::result::Result<(), StackMapErr> {
+        self.methods
+            .iter()
+            .try_for_each(|method| verify_method(method, &self.constant_pool))
+    }
+}
+
+fn verify_method(method: &MethodInfo, cp: &ConstantPool) -> std::result::Result<(), StackMapErr> {
+    for attr in &method.attributes {
+        if let AttributeInfoInner::Code {
+            max_stack,
+            max_locals,
+            code,
+            attributes,
+            ..
+        } = &attr.inner
+        {
+            for attr in attributes {
+                if let AttributeInfoInner::StackMapTable { entries, .. } = &attr.inner {
+                    let mut verifier = FrameVerifier {
+                        method: format!(
+                            "{}{}",
+                            method.name_index.get(cp),
+                            method.descriptor_index.get(cp)
+                        ),
+                        cp,
+                        max_stack: *max_stack,
+                        max_locals: *max_locals,
+                        instructions: HashMap::new(),
+                    };
+                    verifier.instructions = decode_code(code)
+                        .map_err(|err| verifier.err(None, None, format!("Invalid code: {}", err)))?
+                        .into_iter()
+                        .collect();
+                    let locals = initial_locals(method, cp).ok_or_else(|| {
+                        verifier.err(None, None, "Invalid descriptor".to_string())
+                    })?;
+                    verifier.verify(entries, locals)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+struct FrameVerifier<'a> {
+    method: String,
+    cp: &'a ConstantPool,
+    max_stack: u2,
+    max_locals: u2,
+    instructions: HashMap<u32, Instruction>,
+}
+
+impl FrameVerifier<'_> {
+    /// `locals` are the widths of the local variables of the implicit first frame
+    fn verify(
+        &self,
+        entries: &[StackMapFrame],
+        mut locals: Vec<u2>,
+    ) -> std::result::Result<(), StackMapErr> {
+        let mut previous: Option<u32> = None;
+        for (i, frame) in entries.iter().enumerate() {
+            let err = |offset, message| self.err(Some(i), offset, message);
+            let delta = offset_delta(frame).ok_or_else(|| {
+                err(
+                    None,
+                    format!("Invalid frame type {} for {:?}", frame_type(frame), frame),
+                )
+            })?;
+            let offset = match previous {
+                None => u32::from(delta),
+                Some(previous) => previous + u32::from(delta) + 1,
+            };
+            previous = Some(offset);
+            let err = |message| err(Some(offset), message);
+
+            if !self.instructions.contains_key(&offset) {
+                return Err(err("Not at the start of an instruction".to_string()));
+            }
+
+            let (new_locals, stack): (&[_], &[_]) = match frame {
+                StackMapFrame::SameFrame { .. } | StackMapFrame::SameFrameExtended { .. } => {
+                    (&[], &[])
+                }
+                StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                    (&[], std::slice::from_ref(stack))
+                }
+                StackMapFrame::ChopFrame { frame_type, .. } => {
+                    let chopped = usize::from(251 - frame_type);
+                    if chopped > locals.len() {
+                        return Err(err(format!(
+                            "Chops {} locals, but there are only {}",
+                            chopped,
+                            locals.len()
+                        )));
+                    }
+                    locals.truncate(locals.len() - chopped);
+                    (&[], &[])
+                }
+                StackMapFrame::AppendFrame {
+                    locals: appended, ..
+                } => {
+                    locals.extend(appended.iter().map(width));
+                    (appended, &[])
+                }
+                StackMapFrame::FullFrame {
+                    locals: full,
+                    stack,
+                    ..
+                } => {
+                    locals = full.iter().map(width).collect();
+                    (full, stack)
+                }
+            };
+
+            let locals_size = locals.iter().map(|&width| u32::from(width)).sum::<u32>();
+            if locals_size > u32::from(self.max_locals) {
+                return Err(err(format!(
+                    "{} local variable slots, but max_locals is {}",
+                    locals_size, self.max_locals
+                )));
+            }
+            let stack_size = stack.iter().map(|info| u32::from(width(info))).sum::<u32>();
+            if stack_size > u32::from(self.max_stack) {
+                return Err(err(format!(
+                    "{} stack slots, but max_stack is {}",
+                    stack_size, self.max_stack
+                )));
+            }
+            for info in new_locals.iter().chain(stack) {
+                self.verify_type(info).map_err(err)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_type(&self, info: &VerificationTypeInfo) -> std::result::Result<(), String> {
+        match info {
+            VerificationTypeInfo::Object { cpool_index, .. } => {
+                let index = cpool_index.inner();
+                match self.cp.entry(index) {
+                    Some(CpInfo {
+                        inner: CpInfoInner::Class(_),
+                        ..
+                    }) => Ok(()),
+                    _ => Err(format!(
+                        "Object type with index {}, which is not a Class constant",
+                        index
+                    )),
+                }
+            }
+            VerificationTypeInfo::Uninitialized { offset, .. } => {
+                match self.instructions.get(&u32::from(*offset)) {
+                    Some(Instruction::New(_)) => Ok(()),
+                    _ => Err(format!(
+                        "Uninitialized type created at offset {}, which is not a new instruction",
+                        offset
+                    )),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn err(&self, frame: Option<usize>, offset: Option<u32>, message: String) -> StackMapErr {
+        StackMapErr {
+            method: self.method.clone(),
+            frame,
+            offset,
+            message,
+        }
+    }
+}
+
+fn frame_type(frame: &StackMapFrame) -> u1 {
+    match frame {
+        StackMapFrame::SameFrame { frame_type }
+        | StackMapFrame::SameLocals1StackItemFrame { frame_type, .. }
+        | StackMapFrame::SameLocals1StackItemFrameExtended { frame_type, .. }
+        | StackMapFrame::ChopFrame { frame_type, .. }
+        | StackMapFrame::SameFrameExtended { frame_type, .. }
+        | StackMapFrame::AppendFrame { frame_type, .. }
+        | StackMapFrame::FullFrame { frame_type, .. } => *frame_type,
+    }
+}
+
+/// The offset delta of the frame, `None` if the frame type doesn't match the kind of frame
+fn offset_delta(frame: &StackMapFrame) -> Option<u2> {
+    match *frame {
+        StackMapFrame::SameFrame {
+            frame_type: frame_type @ 0..=63,
+        } => Some(frame_type.into()),
+        StackMapFrame::SameLocals1StackItemFrame {
+            frame_type: frame_type @ 64..=127,
+            ..
+        } => Some(u2::from(frame_type) - 64),
+        StackMapFrame::SameLocals1StackItemFrameExtended {
+            frame_type: 247,
+            offset_delta,
+            ..
+        }
+        | StackMapFrame::ChopFrame {
+            frame_type: 248..=250,
+            offset_delta,
+        }
+        | StackMapFrame::SameFrameExtended {
+            frame_type: 251,
+            offset_delta,
+        }
+        | StackMapFrame::FullFrame {
+            frame_type: 255,
+            offset_delta,
+            ..
+        } => Some(offset_delta),
+        StackMapFrame::AppendFrame {
+            frame_type: frame_type @ 252..=254,
+            offset_delta,
+            ref locals,
+        } if locals.len() == usize::from(frame_type - 251) => Some(offset_delta),
+        _ => None,
+    }
+}
+
+/// The number of slots a value of the type takes up
+fn width(info: &VerificationTypeInfo) -> u2 {
+    match info {
+        VerificationTypeInfo::Long { .. } | VerificationTypeInfo::Double { .. } => 2,
+        _ => 1,
+    }
+}
+
+/// The widths of `this` and the parameters, which make up the implicit first frame
+fn initial_locals(method: &MethodInfo, cp: &ConstantPool) -> Option<Vec<u2>> {
+    let mut locals = Vec::new();
+    if !method.access_flags.contains(MethodAccessFlag::STATIC) {
+        locals.push(1);
+    }
+    let descriptor = method.descriptor_index.get(cp);
+    let mut chars = descriptor.strip_prefix('(')?.chars();
+    loop {
+        let width = match chars.next()? {
+            ')' => return Some(locals),
+            'J' | 'D' => 2,
+            'B' | 'C' | 'F' | 'I' | 'S' | 'Z' => 1,
+            c @ ('L' | '[') => {
+                let mut c = c;
+                while c == '[' {
+                    c = chars.next()?;
+                }
+                if c == 'L' {
+                    chars.by_ref().find(|&c| c == ';')?;
+                }
+                1
+            }
+            _ => return None,
+        };
+        locals.push(width);
+    }
+}
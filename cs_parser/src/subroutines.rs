@@ -0,0 +1,410 @@
+//!
+//! Inlining the `jsr` and `ret` subroutines of old class files
+//!
+
+use crate::assemble::branch_constructor;
+use crate::disassemble::branch_targets;
+use crate::*;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug)]
+pub struct InlineErr(pub String);
+
+impl Display for InlineErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not inline subroutines: {}", self.0)
+    }
+}
+
+impl std::error::Error for InlineErr {}
+
+type InlineResult<T> = std::result::Result<T, InlineErr>;
+
+impl ClassFile {
+    /// Replaces the `jsr` and `ret` subroutines of every method by a copy of the subroutine for
+    /// each `jsr`, like ASM's `JSRInlinerAdapter`. A `jsr` becomes an `aconst_null` in place of
+    /// the return address and a `goto` to its copy, and the `ret` of the copy a `goto` back to the
+    /// instruction after the `jsr`. Unreachable code is removed from these methods.
+    ///
+    /// The exception table, `LineNumberTable` and local variable tables are moved to the copies,
+    /// a `StackMapTable` is removed since classes with subroutines are verified by inference.
+    /// Returns the number of methods that had subroutines
+    pub fn inline_subroutines(&mut self) -> InlineResult<usize> {
+        let cp = &self.constant_pool;
+        let mut inlined = 0;
+        for method in &mut self.methods {
+            let name = method.name_index.get(cp);
+            let descriptor = method.descriptor_index.get(cp);
+            for attr in &mut method.attributes {
+                if let AttributeInfoInner::Code {
+                    code,
+                    exception_table,
+                    attributes,
+                    ..
+                } = &mut attr.inner
+                {
+                    let changed = inline_code(code, exception_table, attributes, cp).map_err(
+                        |InlineErr(message)| {
+                            InlineErr(format!("{}{}: {}", name, descriptor, message))
+                        },
+                    )?;
+                    if changed {
+                        inlined += 1;
+                    }
+                }
+            }
+        }
+        Ok(inlined)
+    }
+}
+
+/// Inlines the subroutines of the code, returns whether it had any
+fn inline_code(
+    code: &mut Bytes,
+    exception_table: &mut Vec<AttributeCodeException>,
+    attributes: &mut Vec<AttributeInfo>,
+    cp: &ConstantPool,
+) -> InlineResult<bool> {
+    let instructions = decode_code(code).map_err(|err| InlineErr(err.to_string()))?;
+    if !instructions.iter().any(|(_, instruction)| {
+        matches!(
+            instruction,
+            Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+        )
+    }) {
+        return Ok(false);
+    }
+    if let Some(attr) = attributes.iter().find(|attr| {
+        !matches!(
+            attr.inner,
+            AttributeInfoInner::LineNumberTable { .. }
+                | AttributeInfoInner::LocalVariableTable { .. }
+                | AttributeInfoInner::LocalVariableTypeTable { .. }
+                | AttributeInfoInner::StackMapTable { .. }
+        )
+    }) {
+        return Err(InlineErr(format!(
+            "The {} attribute can't be moved to the inlined code",
+            cp.utf8(attr.attribute_name_index.inner())
+                .unwrap_or("unnamed")
+        )));
+    }
+
+    let mut inliner = Inliner {
+        index_of: instructions
+            .iter()
+            .enumerate()
+            .map(|(index, (offset, _))| (*offset, index))
+            .collect(),
+        instructions,
+        exception_table,
+        asm: Assembler::new(),
+        instances: Vec::new(),
+    };
+    inliner.instance(0, None)?;
+    let mut next = 0;
+    while next < inliner.instances.len() {
+        inliner.emit(next)?;
+        next += 1;
+    }
+    let assembled = inliner
+        .asm
+        .assemble()
+        .map_err(|WriteErr(message)| InlineErr(message))?;
+    let offset = |label| {
+        let offset = assembled.offset(label).expect("all labels are placed");
+        u2::try_from(offset).expect("the code is at most u2::MAX bytes long")
+    };
+
+    let mut exceptions = Vec::new();
+    for exception in exception_table.iter() {
+        for instance in 0..inliner.instances.len() {
+            let covered = |offset| {
+                (u32::from(exception.start_pc)..u32::from(exception.end_pc)).contains(&offset)
+            };
+            for (start, end) in inliner.runs(instance, covered) {
+                exceptions.push(AttributeCodeException {
+                    start_pc: offset(start),
+                    end_pc: offset(end),
+                    handler_pc: offset(inliner.handler(instance, exception.handler_pc)?),
+                    catch_type: exception.catch_type,
+                });
+            }
+        }
+    }
+    let variables = |table: &[AttributeLocalVariableTable]| {
+        let mut variables = Vec::new();
+        for variable in table {
+            for instance in 0..inliner.instances.len() {
+                let start = u32::from(variable.start_pc);
+                let covered =
+                    |offset| (start..start + u32::from(variable.length)).contains(&offset);
+                for (start, end) in inliner.runs(instance, covered) {
+                    variables.push(AttributeLocalVariableTable {
+                        start_pc: offset(start),
+                        length: offset(end) - offset(start),
+                        ..*variable
+                    });
+                }
+            }
+        }
+        variables
+    };
+    attributes.retain(|attr| !matches!(attr.inner, AttributeInfoInner::StackMapTable { .. }));
+    for attr in attributes.iter_mut() {
+        match &mut attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => {
+                let mut lines = Vec::new();
+                for instance in &inliner.instances {
+                    for &index in &instance.instructions {
+                        let original = inliner.instructions[index].0;
+                        lines.extend(
+                            line_number_table
+                                .iter()
+                                .filter(|line| u32::from(line.start_pc) == original)
+                                .map(|line| AttributeLineNumber {
+                                    start_pc: offset(instance.labels[&original]),
+                                    line_number: line.line_number,
+                                }),
+                        );
+                    }
+                }
+                *line_number_table = lines;
+            }
+            AttributeInfoInner::LocalVariableTable {
+                local_variable_table,
+            }
+            | AttributeInfoInner::LocalVariableTypeTable {
+                local_variable_table,
+            } => *local_variable_table = variables(local_variable_table),
+            _ => {}
+        }
+    }
+    *exception_table = exceptions;
+    *code = assembled.code.into();
+    Ok(true)
+}
+
+struct Inliner<'a> {
+    instructions: Vec<(u32, Instruction)>,
+    index_of: HashMap<u32, usize>,
+    exception_table: &'a [AttributeCodeException],
+    asm: Assembler,
+    /// The main code first, then the copies of the subroutines in the order of their `jsr`s
+    instances: Vec<Instance>,
+}
+
+/// A copy of the main code or of a subroutine
+struct Instance {
+    /// The offset of the first instruction, 0 for the main code
+    entry: u32,
+    /// The copy with the `jsr` and the label after it, `None` for the main code
+    caller: Option<(usize, Label)>,
+    /// The indices of the instructions that are reachable from the entry, in order
+    instructions: Vec<usize>,
+    /// The label of the copy of each instruction by its original offset
+    labels: HashMap<u32, Label>,
+    /// A label after the last instruction
+    end: Label,
+}
+
+impl Inliner<'_> {
+    /// Adds a copy of the code reachable from `entry`
+    fn instance(&mut self, entry: u32, caller: Option<(usize, Label)>) -> InlineResult<usize> {
+        let instructions = self.reachable(entry)?;
+        let labels = instructions
+            .iter()
+            .map(|&index| (self.instructions[index].0, self.asm.new_label()))
+            .collect();
+        self.instances.push(Instance {
+            entry,
+            caller,
+            instructions,
+            labels,
+            end: self.asm.new_label(),
+        });
+        Ok(self.instances.len() - 1)
+    }
+
+    /// The instructions that can be reached from `entry` without going through a `jsr` or `ret`,
+    /// including the exception handlers of the instructions
+    fn reachable(&self, entry: u32) -> InlineResult<Vec<usize>> {
+        let mut reached = BTreeSet::new();
+        let mut work = vec![i64::from(entry)];
+        while let Some(offset) = work.pop() {
+            let index = u32::try_from(offset)
+                .ok()
+                .and_then(|offset| self.index_of.get(&offset))
+                .copied()
+                .ok_or_else(|| {
+                    InlineErr(format!(
+                        "Jump to {}, which is not the start of an instruction",
+                        offset
+                    ))
+                })?;
+            if !reached.insert(index) {
+                continue;
+            }
+            let (offset, instruction) = &self.instructions[index];
+            work.extend(
+                self.exception_table
+                    .iter()
+                    .filter(|exception| {
+                        (u32::from(exception.start_pc)..u32::from(exception.end_pc))
+                            .contains(offset)
+                    })
+                    .map(|exception| i64::from(exception.handler_pc)),
+            );
+            if !matches!(instruction, Instruction::Jsr(_) | Instruction::JsrW(_)) {
+                work.extend(branch_targets(*offset, instruction));
+            }
+            if falls_through(instruction) {
+                let (next, _) = self.instructions.get(index + 1).ok_or_else(|| {
+                    InlineErr("Execution falls off the end of the code".to_string())
+                })?;
+                work.push(i64::from(*next));
+            }
+        }
+        Ok(reached.into_iter().collect())
+    }
+
+    /// Adds the instructions of the copy to the assembler
+    fn emit(&mut self, instance: usize) -> InlineResult<()> {
+        for i in 0..self.instances[instance].instructions.len() {
+            let index = self.instances[instance].instructions[i];
+            let (offset, instruction) = self.instructions[index].clone();
+            let label = |target: i32| {
+                let target = offset as i64 + target as i64;
+                u32::try_from(target)
+                    .ok()
+                    .and_then(|target| self.instances[instance].labels.get(&target))
+                    .copied()
+                    .ok_or_else(|| InlineErr(format!("Jump to {} at {}", target, offset)))
+            };
+            self.asm.place(self.instances[instance].labels[&offset]);
+            match instruction {
+                Instruction::Jsr(branch) | Instruction::JsrW(branch) => {
+                    let entry = u32::try_from(offset as i64 + branch as i64)
+                        .map_err(|_| InlineErr(format!("jsr to {} at {}", branch, offset)))?;
+                    let mut caller = Some(instance);
+                    while let Some(calling) = caller {
+                        if self.instances[calling].caller.is_some()
+                            && self.instances[calling].entry == entry
+                        {
+                            return Err(InlineErr(format!(
+                                "The subroutine at {} calls itself",
+                                entry
+                            )));
+                        }
+                        caller = self.instances[calling].caller.map(|(caller, _)| caller);
+                    }
+                    // the return point falls through after the jsr, so it is in this copy
+                    let (after, _) = &self.instructions[index + 1];
+                    let after = self.instances[instance].labels[after];
+                    let subroutine = self.instance(entry, Some((instance, after)))?;
+                    self.asm.push(Instruction::AconstNull);
+                    self.asm
+                        .branch(Instruction::Goto, self.instances[subroutine].labels[&entry]);
+                }
+                Instruction::Ret(_) => match self.instances[instance].caller {
+                    Some((_, after)) => self.asm.branch(Instruction::Goto, after),
+                    None => {
+                        return Err(InlineErr(format!(
+                            "ret at {} is not in a subroutine",
+                            offset
+                        )))
+                    }
+                },
+                Instruction::Goto(branch) | Instruction::GotoW(branch) => {
+                    self.asm.branch(Instruction::Goto, label(branch)?)
+                }
+                Instruction::Tableswitch {
+                    default,
+                    low,
+                    offsets,
+                    ..
+                } => {
+                    let targets = offsets
+                        .into_iter()
+                        .map(label)
+                        .collect::<InlineResult<_>>()?;
+                    self.asm.tableswitch(low, label(default)?, targets);
+                }
+                Instruction::Lookupswitch { default, pairs } => {
+                    let pairs = pairs
+                        .into_iter()
+                        .map(|(key, branch)| Ok((key, label(branch)?)))
+                        .collect::<InlineResult<_>>()?;
+                    self.asm.lookupswitch(label(default)?, pairs);
+                }
+                instruction => match branch_constructor(&instruction) {
+                    Some(branch) => {
+                        let target = branch_targets(offset, &instruction)[0];
+                        self.asm
+                            .branch(branch, label((target - offset as i64) as i32)?);
+                    }
+                    None => self.asm.push(instruction),
+                },
+            }
+        }
+        self.asm.place(self.instances[instance].end);
+        Ok(())
+    }
+
+    /// The start and end labels of the runs of copied instructions whose original offset is covered
+    fn runs(&self, instance: usize, covered: impl Fn(u32) -> bool) -> Vec<(Label, Label)> {
+        let instance = &self.instances[instance];
+        let mut runs = Vec::new();
+        let mut start = None;
+        for &index in &instance.instructions {
+            let offset = self.instructions[index].0;
+            match (covered(offset), start) {
+                (true, None) => start = Some(instance.labels[&offset]),
+                (false, Some(run)) => {
+                    runs.push((run, instance.labels[&offset]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(run) = start {
+            runs.push((run, instance.end));
+        }
+        runs
+    }
+
+    /// The copy of the exception handler for the instructions of the instance, which is in the
+    /// instance itself or in one of its callers
+    fn handler(&self, instance: usize, handler_pc: u2) -> InlineResult<Label> {
+        let mut current = Some(instance);
+        while let Some(instance) = current {
+            if let Some(label) = self.instances[instance].labels.get(&u32::from(handler_pc)) {
+                return Ok(*label);
+            }
+            current = self.instances[instance].caller.map(|(caller, _)| caller);
+        }
+        Err(InlineErr(format!(
+            "The exception handler at {} is not reachable",
+            handler_pc
+        )))
+    }
+}
+
+/// Whether the instruction after this one can be executed next
+pub(crate) fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::Goto(_)
+            | Instruction::GotoW(_)
+            | Instruction::Ret(_)
+            | Instruction::Tableswitch { .. }
+            | Instruction::Lookupswitch { .. }
+            | Instruction::Ireturn
+            | Instruction::Lreturn
+            | Instruction::Freturn
+            | Instruction::Dreturn
+            | Instruction::Areturn
+            | Instruction::Return
+            | Instruction::Athrow
+    )
+}
@@ -1,38 +1,42 @@
 use super::*;
+use crate::fixture::{self, Fixture};
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
 
 #[test]
 fn data_u1() {
     let bytes = [0xff, 0x00];
-    let mut data = Data {
-        data: &bytes,
-        pointer: 0,
-    };
+    let mut data = Data::new(&bytes);
     assert_eq!(data.u1().unwrap(), 0xff);
     assert_eq!(data.u1().unwrap(), 0x00);
-    assert_eq!(data.last_u1().unwrap(), 0x00);
 }
 
 #[test]
 fn data_u2() {
     let bytes = [0xff, 0x33, 0x11, 0x00];
-    let mut data = Data {
-        data: &bytes,
-        pointer: 0,
-    };
+    let mut data = Data::new(&bytes);
     assert_eq!(data.u2().unwrap(), 0xff33);
     assert_eq!(data.u2().unwrap(), 0x1100);
-    assert_eq!(data.last_u2().unwrap(), 0x1100);
 }
 
 #[test]
 fn data_u4() {
     let bytes = [0xff, 0x33, 0x11, 0x00];
-    let mut data = Data {
-        data: &bytes,
-        pointer: 0,
-    };
+    let mut data = Data::new(&bytes);
     assert_eq!(data.u4().unwrap(), 0xff331100);
-    assert_eq!(data.last_u4().unwrap(), 0xff331100);
+}
+
+#[test]
+fn data_bytes() {
+    let bytes = [0xff, 0x33, 0x11];
+    let mut data = Data::new(&bytes);
+    assert_eq!(data.bytes(2).unwrap(), &[0xff, 0x33]);
+    assert!(data.bytes(2).is_err());
+    assert!(data.u4().is_err());
+    assert_eq!(data.u1().unwrap(), 0x11);
+    assert!(data.u1().is_err());
+    assert_eq!(data.bytes(0).unwrap(), &[]);
 }
 
 #[test]
@@ -42,10 +46,10 @@ fn parse_empty_class() {
 
     assert_eq!(parsed.minor_version, 0);
     assert_eq!(parsed.major_version, 0x003b);
-    assert_eq!(parsed.constant_pool.len() + 1, 0x000d);
+    assert_eq!(parsed.constant_pool.count(), 0x000d);
     assert_eq!(parsed.constant_pool.len(), 12);
     assert_eq!(
-        parsed.constant_pool,
+        parsed.constant_pool[..],
         vec![
             CpInfo {
                 tag: 0x0a,
@@ -70,19 +74,19 @@ fn parse_empty_class() {
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "java/lang/Object".to_string()
+                    bytes: "java/lang/Object".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "<init>".to_string()
+                    bytes: "<init>".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "()V".to_string()
+                    bytes: "()V".into()
                 })
             },
             CpInfo {
@@ -94,36 +98,39 @@ fn parse_empty_class() {
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "Test".to_string()
+                    bytes: "Test".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "Code".to_string()
+                    bytes: "Code".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "LineNumberTable".to_string()
+                    bytes: "LineNumberTable".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "SourceFile".to_string()
+                    bytes: "SourceFile".into()
                 })
             },
             CpInfo {
                 tag: 1,
                 inner: CpInfoInner::Utf8(cp_info::Utf8 {
-                    bytes: "Test.java".to_string()
+                    bytes: "Test.java".into()
                 })
             }
         ]
     );
-    assert_eq!(parsed.access_flags, 0x0021);
+    assert_eq!(
+        parsed.access_flags,
+        ClassAccessFlag::Public | ClassAccessFlag::Super
+    );
     assert_eq!(parsed.this_class, 7.into());
     assert_eq!(parsed.super_class, 2.into());
     assert_eq!(parsed.interfaces.len(), 0);
@@ -131,7 +138,10 @@ fn parse_empty_class() {
     assert_eq!(parsed.fields.len(), 0);
     assert_eq!(parsed.fields, vec![]);
     assert_eq!(parsed.methods.len(), 1);
-    assert_eq!(parsed.methods[0].access_flags, 1);
+    assert_eq!(
+        parsed.methods[0].access_flags,
+        MethodAccessFlag::PUBLIC.into()
+    );
     assert_eq!(parsed.methods[0].name_index, 5.into());
     assert_eq!(parsed.methods[0].descriptor_index, 6.into());
     assert_eq!(parsed.methods[0].attributes.len(), 1);
@@ -143,3 +153,2612 @@ fn more_complex_file() {
     let parsed = parse_class_file(class).unwrap();
     assert_eq!(parsed.magic, 0xCAFEBABE);
 }
+
+#[test]
+fn jcov_attributes() {
+    let class = include_bytes!("../testdata/Jcov.class");
+    let parsed = parse_class_file(class).unwrap();
+    let cp = &parsed.constant_pool;
+
+    assert!(parsed.attributes.iter().any(|attr| matches!(
+        attr.inner,
+        AttributeInfoInner::SourceID { source_id_index } if source_id_index.get(cp).parse::<u64>().is_ok()
+    )));
+    assert!(parsed.attributes.iter().any(|attr| matches!(
+        attr.inner,
+        AttributeInfoInner::CompilationID { compilation_id_index } if compilation_id_index.get(cp).parse::<u64>().is_ok()
+    )));
+
+    let max = &parsed.methods[1];
+    assert_eq!(max.name_index.get(cp), "max");
+    let code_attributes = match &max.attributes[0].inner {
+        AttributeInfoInner::Code { attributes, .. } => attributes,
+        _ => panic!("Expected Code attribute"),
+    };
+    let ranges = code_attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::CharacterRangeTable {
+                character_range_table,
+            } => Some(character_range_table),
+            _ => None,
+        })
+        .unwrap();
+    assert!(!ranges.is_empty());
+    // the `if` statement starts on line 3, column 9
+    assert!(ranges
+        .iter()
+        .any(|range| range.character_range_start == (3 << 10 | 9)));
+}
+
+#[test]
+fn code_attributes() {
+    let class = include_bytes!("../testdata/Attributes.class");
+    let parsed = parse_class_file(class).unwrap();
+    let cp = &parsed.constant_pool;
+
+    let code_of = |name: &str| {
+        let method = parsed
+            .methods
+            .iter()
+            .find(|method| method.name_index.get(cp) == name)
+            .unwrap();
+        match &method.attributes[0].inner {
+            AttributeInfoInner::Code {
+                exception_table,
+                attributes,
+                ..
+            } => (exception_table.clone(), attributes.clone()),
+            _ => panic!("Expected Code attribute"),
+        }
+    };
+    let stack_map = |attributes: &[AttributeInfo]| {
+        attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::StackMapTable { entries, .. } => Some(entries.clone()),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    let (exception_table, attributes) = code_of("parse");
+    assert_eq!(exception_table.len(), 1);
+    assert_eq!(exception_table[0].start_pc, 0);
+    assert_eq!(exception_table[0].end_pc, 4);
+    assert_eq!(exception_table[0].handler_pc, 5);
+    let catch_type: FromPool<cp_info::Class> = exception_table[0].catch_type.into();
+    assert_eq!(
+        catch_type.get(cp).name_index.get(cp),
+        "java/lang/NumberFormatException"
+    );
+    assert!(matches!(
+        stack_map(&attributes)[..],
+        [StackMapFrame::SameLocals1StackItemFrame { frame_type: 69, .. }]
+    ));
+
+    let (_, attributes) = code_of("sum");
+    match &stack_map(&attributes)[..] {
+        [StackMapFrame::AppendFrame {
+            frame_type: 253,
+            offset_delta: 4,
+            locals,
+        }, StackMapFrame::ChopFrame {
+            frame_type: 250,
+            offset_delta: 17,
+        }] => assert_eq!(
+            locals,
+            &[
+                VerificationTypeInfo::Integer { tag: 1 },
+                VerificationTypeInfo::Integer { tag: 1 }
+            ]
+        ),
+        frames => panic!("Unexpected frames {:?}", frames),
+    }
+}
+
+#[test]
+fn annotations() {
+    let class = include_bytes!("../testdata/Attributes.class");
+    let parsed = parse_class_file(class).unwrap();
+    let cp = &parsed.constant_pool;
+
+    let annotations = parsed
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::RuntimeVisibleAnnotations { annotations } => Some(annotations),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(annotations.len(), 1);
+    let annotation = &annotations[0];
+    assert_eq!(annotation.type_index.get(cp), "LAttributes$Marker;");
+    assert_eq!(annotation.num_element_value_pairs, 2);
+    let names = &annotation.element_value_pairs[1];
+    assert_eq!(names.element_name_index.get(cp), "names");
+    assert_eq!(names.element_name_name.tag, b'[');
+    match &names.element_name_name.value {
+        AnnotationElementValueValue::ArrayValue { values } => {
+            assert_eq!(values.len(), 2);
+            assert!(values.iter().all(|value| value.tag == b's'));
+        }
+        value => panic!("Expected array value, found {:?}", value),
+    }
+
+    let marker = include_bytes!("../testdata/Attributes$Marker.class");
+    let parsed = parse_class_file(marker).unwrap();
+    let cp = &parsed.constant_pool;
+    let names = parsed
+        .methods
+        .iter()
+        .find(|method| method.name_index.get(cp) == "names")
+        .unwrap();
+    assert!(matches!(
+        &names.attributes[0].inner,
+        AttributeInfoInner::AnnotationDefault {
+            default_value: AnnotationElementValue { tag: b'[', .. }
+        }
+    ));
+}
+
+#[test]
+fn annotation_values() {
+    let class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+    assert!(class.annotation("Ljava/lang/Deprecated;").is_none());
+    let marker = class.annotation("LAttributes$Marker;").unwrap();
+    assert!(marker.is_visible());
+    assert!(matches!(marker.get("value"), Some(AnnotationValue::Int(3))));
+    match marker.get("names") {
+        Some(AnnotationValue::Array(names)) => assert!(matches!(
+            names[..],
+            [AnnotationValue::Str("a"), AnnotationValue::Str("b")]
+        )),
+        value => panic!("Expected an array, found {:?}", value),
+    }
+    assert!(marker.get("missing").is_none());
+
+    // the retention of the annotation type is an enum
+    let class = parse_class_file(include_bytes!("../testdata/Attributes$Marker.class")).unwrap();
+    let retention = class
+        .annotation("Ljava/lang/annotation/Retention;")
+        .unwrap();
+    assert!(matches!(
+        retention.get("value"),
+        Some(AnnotationValue::Enum {
+            type_: "Ljava/lang/annotation/RetentionPolicy;",
+            name: "RUNTIME"
+        })
+    ));
+}
+
+#[test]
+fn skim_matches_full_parse() {
+    for class in [
+        &include_bytes!("../testdata/Test.class")[..],
+        &include_bytes!("../testdata/Test2.class")[..],
+        &include_bytes!("../testdata/Attributes.class")[..],
+    ] {
+        let skimmed = skim_class_file(class).unwrap();
+        let parsed = parse_class_file(class).unwrap();
+        assert_eq!(skimmed, ClassSummary::from(&parsed));
+    }
+
+    let skimmed = skim_class_file(include_bytes!("../testdata/Test2.class")).unwrap();
+    assert_eq!(skimmed.this_class, "Test2");
+    assert_eq!(skimmed.super_class.as_deref(), Some("java/lang/Object"));
+    assert_eq!(skimmed.fields[0].name, "myField");
+    assert_eq!(skimmed.fields[0].descriptor, "I");
+    assert!(skimmed
+        .methods
+        .iter()
+        .any(|method| method.name == "main" && method.descriptor == "([Ljava/lang/String;)V"));
+
+    // `this_class` is checked against the constant pool before its name is read
+    let class = fixture::empty_class();
+    let this_class = class.len() - 12;
+    for index in [1, 1000] {
+        let mut class = class.clone();
+        class[this_class..this_class + 2].copy_from_slice(&u2::to_be_bytes(index));
+        let err = skim_class_file(&class).unwrap_err();
+        assert_eq!(err.offset(), Some(this_class + 2));
+        assert!(parse_class_file(&class).is_err());
+    }
+}
+
+#[test]
+fn resolved_class() {
+    let class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+    let resolved = ResolvedClass::try_from(&class).unwrap();
+    assert_eq!(resolved.name, "Attributes");
+    assert_eq!(resolved.super_class.as_deref(), Some("java/lang/Object"));
+    assert_eq!(resolved.source_file.as_deref(), Some("Attributes.java"));
+    assert_eq!(
+        resolved.annotations,
+        [ResolvedAnnotation {
+            type_descriptor: "LAttributes$Marker;".to_owned(),
+            visible: true,
+            elements: vec![
+                ("value".to_owned(), ResolvedValue::Int(3)),
+                (
+                    "names".to_owned(),
+                    ResolvedValue::Array(vec![
+                        ResolvedValue::String("a".to_owned()),
+                        ResolvedValue::String("b".to_owned())
+                    ])
+                ),
+            ],
+        }]
+    );
+    let sum = resolved
+        .methods
+        .iter()
+        .find(|method| method.name == "sum")
+        .unwrap();
+    assert_eq!(sum.descriptor, "([I)I".parse().unwrap());
+
+    let class = parse_class_file(include_bytes!("../testdata/Point.class")).unwrap();
+    let resolved = ResolvedClass::try_from(&class).unwrap();
+    assert_eq!(resolved.super_class.as_deref(), Some("java/lang/Record"));
+    let tags = &resolved.fields[1];
+    assert_eq!(tags.name, "tags");
+    assert_eq!(
+        tags.descriptor,
+        cs_model::FieldDescriptor(cs_model::FieldType::Object("java/util/List".to_owned()))
+    );
+    assert_eq!(tags.signature.as_deref(), Some("Ljava/util/List<TT;>;"));
+}
+
+#[test]
+fn find_members() {
+    let class = parse_class_file(include_bytes!("../testdata/Point.class")).unwrap();
+    let cp = &class.constant_pool;
+    let x = class.find_field("x").unwrap();
+    assert_eq!(x.descriptor_index.get(cp), "I");
+    assert!(class.find_field("y").is_none());
+
+    let fields = class
+        .typed_fields()
+        .map(|field| {
+            let (name, descriptor, flags, _) = field.unwrap();
+            (name, descriptor.0, flags)
+        })
+        .collect::<Vec<_>>();
+    let private_final = FieldAccessFlag::PRIVATE | FieldAccessFlag::FINAL;
+    assert_eq!(
+        fields,
+        [
+            ("x", cs_model::FieldType::Int, private_final),
+            (
+                "tags",
+                cs_model::FieldType::Object("java/util/List".to_owned()),
+                private_final
+            ),
+        ]
+    );
+
+    assert!(class.find_method("x", None).is_some());
+    assert!(class.find_method("x", Some("()I")).is_some());
+    assert!(class.find_method("x", Some("()J")).is_none());
+}
+
+#[test]
+fn string_loads() {
+    let mut builder = ClassFileBuilder::new("Greeter").add_default_constructor();
+    let pool = builder.constant_pool();
+    let hello = pool.string("Hello, World!");
+    let answer = pool.integer(42);
+    let mut code = vec![0x12, hello.inner() as u1, 0x57]; // ldc, pop
+    code.extend([0x13, 0, answer.inner() as u1, 0x57]); // ldc_w, pop
+    code.extend([0x13, 0, hello.inner() as u1, 0xb0]); // ldc_w, areturn
+    let greet = MethodCode {
+        max_stack: 1,
+        max_locals: 0,
+        code,
+        exception_table: Vec::new(),
+    };
+    let class = builder
+        .add_method(
+            MethodAccessFlag::STATIC,
+            "greet",
+            "()Ljava/lang/String;",
+            Some(greet),
+        )
+        .add_method(MethodAccessFlag::ABSTRACT, "name", "()V", None)
+        .build();
+
+    let cp = &class.constant_pool;
+    let loads = class
+        .string_loads()
+        .unwrap()
+        .into_iter()
+        .map(|(method, offset, value)| (method.name_index.get(cp), offset, value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        loads,
+        [("greet", 0, "Hello, World!"), ("greet", 7, "Hello, World!")]
+    );
+
+    let point = parse_class_file(include_bytes!("../testdata/Point.class")).unwrap();
+    assert!(point.string_loads().unwrap().is_empty());
+}
+
+#[test]
+fn memory_usage_and_compact() {
+    let class = include_bytes!("../testdata/Attributes.class");
+    let mut parsed = parse_class_file(class).unwrap();
+    let usage = parsed.memory_usage();
+    assert!(usage > class.len());
+
+    parsed.constant_pool.entries.reserve(100);
+    parsed.methods[0].attributes.reserve(10);
+    let over_provisioned = parsed.memory_usage();
+    assert!(over_provisioned > usage);
+
+    let before = parsed.clone();
+    parsed.compact();
+    assert_eq!(parsed, before);
+    assert_eq!(parsed.memory_usage(), usage);
+}
+
+#[test]
+fn declared_count_exceeds_input() {
+    let bytes = [0; 120];
+    let mut data = Data::new(&bytes);
+    let err = parse_vec::<MethodInfo, _>(65535_u16, &mut data, &ConstantPool::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Could not parse class file: Declared 65535 MethodInfo (at least 8 bytes each), but only 120 bytes remain"
+    );
+
+    let mut truncated = include_bytes!("../testdata/Test2.class").to_vec();
+    truncated.truncate(truncated.len() / 2);
+    assert!(parse_class_file(&truncated).is_err());
+}
+
+#[test]
+fn error_offsets() {
+    let class = include_bytes!("../testdata/Test2.class");
+    let code_start = match &parse_class_file(class).unwrap().methods[1].attributes[0].inner {
+        AttributeInfoInner::Code { code, .. } => code.start(),
+        _ => panic!("Expected Code attribute"),
+    };
+    // the code_length in front of the code
+    let mut corrupted = class.to_vec();
+    corrupted[code_start - 4..code_start].copy_from_slice(&0xFFFF_u32.to_be_bytes());
+
+    let err = parse_class_file(&corrupted).unwrap_err();
+    assert_eq!(err.offset(), Some(code_start));
+    assert_eq!(err.attribute(), Some("Code"));
+    assert_eq!(err.attribute_offset(), Some(8));
+    assert!(err.to_string().starts_with(&format!(
+        "Could not parse class file, error at offset {:#X} while parsing Code attribute (at 0x8 in it): Expected 65535 more bytes",
+        code_start
+    )));
+
+    let mut truncated = class.to_vec();
+    truncated.truncate(20);
+    // the constant pool count already declares more constants than fit
+    assert_eq!(parse_class_file(&truncated).unwrap_err().offset(), Some(10));
+    assert_eq!(
+        decode_code(&[0x00, 0xFF]).unwrap_err().to_string(),
+        "Could not parse class file, error at offset 0x1: Invalid opcode 0xff"
+    );
+}
+
+#[test]
+fn lenient_parsing() {
+    let class = include_bytes!("../testdata/Test2.class");
+    let line_numbers = |class: &ClassFile| match &class.methods[1].attributes[0].inner {
+        AttributeInfoInner::Code { attributes, .. } => attributes[0].clone(),
+        _ => panic!("Expected Code attribute"),
+    };
+    let start = line_numbers(&parse_class_file(class).unwrap())
+        .original
+        .unwrap()
+        .start();
+    // the line_number_table_length
+    let mut corrupted = class.to_vec();
+    corrupted[start..start + 2].copy_from_slice(&[0xFF, 0xFF]);
+    assert!(parse_class_file(&corrupted).is_err());
+
+    let (parsed, diagnostics) = parse_class_file_lenient(&corrupted).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].error.attribute(), Some("LineNumberTable"));
+    assert!(matches!(
+        line_numbers(&parsed).inner,
+        AttributeInfoInner::Unknown { .. }
+    ));
+    assert_eq!(parsed.methods.len(), 3);
+
+    let (_, diagnostics) = parse_class_file_lenient(class).unwrap();
+    assert!(diagnostics.is_empty());
+    assert!(parse_class_file_lenient(&corrupted[..20]).is_err());
+}
+
+#[test]
+fn parse_limits() {
+    let class = include_bytes!("../testdata/Attributes.class");
+    let defaults = ParseLimits::default();
+    let parse = |limits: ParseLimits| parse_class_file_with_limits(class, &limits);
+    assert_eq!(parse(defaults).unwrap(), parse_class_file(class).unwrap());
+
+    let err = parse(ParseLimits {
+        max_methods: 2,
+        ..defaults
+    })
+    .unwrap_err();
+    assert_eq!(err.message(), "3 methods exceed the limit of 2");
+    assert!(parse(ParseLimits {
+        max_constant_pool: 10,
+        ..defaults
+    })
+    .is_err());
+
+    let err = parse(ParseLimits {
+        max_code_length: 4,
+        ..defaults
+    })
+    .unwrap_err();
+    assert_eq!(err.attribute(), Some("Code"));
+
+    // the array of names in `@Marker(value = 3, names = {"a", "b"})`
+    let err = parse(ParseLimits {
+        max_annotation_depth: 1,
+        ..defaults
+    })
+    .unwrap_err();
+    assert_eq!(err.attribute(), Some("RuntimeVisibleAnnotations"));
+    assert!(parse(ParseLimits {
+        max_annotation_depth: 2,
+        ..defaults
+    })
+    .is_ok());
+}
+
+#[test]
+fn header_validation() {
+    let class = include_bytes!("../testdata/Test.class");
+    let with_header = |header: [u1; 8]| {
+        let mut class = class.to_vec();
+        class[..8].copy_from_slice(&header);
+        class
+    };
+
+    let err = parse_class_file(&with_header(*b"PK\x03\x04\0\0\0\0")).unwrap_err();
+    assert_eq!(err.offset(), Some(0));
+    assert!(err
+        .message()
+        .starts_with("Invalid magic number: 0x504B0304"));
+    assert!(skim_class_file(&with_header(*b"PK\x03\x04\0\0\0\0")).is_err());
+
+    // 1.1, 1.0 and 255.0
+    assert!(parse_class_file(&with_header([0xCA, 0xFE, 0xBA, 0xBE, 0, 3, 0, 45])).is_ok());
+    let err = parse_class_file(&with_header([0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 44])).unwrap_err();
+    assert_eq!(err.offset(), Some(4));
+    assert!(parse_class_file(&with_header([0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 255])).is_err());
+
+    assert!(validate_header(61, 0xFFFF).is_ok());
+    assert_eq!(
+        validate_header(61, 3).unwrap_err().message(),
+        "Invalid minor version 3 for major version 61, it has to be 0 or 65535"
+    );
+    assert!(validate_header(MAX_MAJOR_VERSION, 0).is_ok());
+    assert!(validate_header(MAX_MAJOR_VERSION + 1, 0).is_err());
+}
+
+#[test]
+fn constant_pool_references() {
+    let class = ClassFileBuilder::new("Refs").build();
+    let name_index = class.this_class.get(&class.constant_pool).name_index;
+    assert!(name_index.validate(&class.constant_pool).is_ok());
+    let out_of_bounds: FromPool<cp_info::Utf8> = 1000.into();
+    assert!(out_of_bounds.validate(&class.constant_pool).is_err());
+    let any: FromPool<CpInfoInner> = 0.into();
+    assert!(any.validate(&class.constant_pool).is_err());
+
+    let bytes = write_class_file(&class).unwrap();
+    let this_class = class.this_class.inner();
+    // the `Class` entry of `Refs`, the name is the `Utf8` entry before it
+    let start = bytes
+        .windows(3)
+        .position(|window| window == [7, 0, this_class as u8 - 1])
+        .unwrap();
+    let with_name_index = |name_index: u2| {
+        let mut bytes = bytes.clone();
+        bytes[start + 1..start + 3].copy_from_slice(&name_index.to_be_bytes());
+        parse_class_file(&bytes).unwrap_err()
+    };
+
+    let err = with_name_index(this_class);
+    assert_eq!(
+        err.message(),
+        format!(
+            "Constant pool entry {}: Expected 'Utf8', found 'Class(Class {{ name_index: FromPool {{ inner: {}, _marker: PhantomData<fn() -> cs_parser::model::cp_info::Utf8> }} }})'",
+            this_class, this_class
+        )
+    );
+    assert_eq!(err.offset(), Some(start));
+    assert!(with_name_index(1000).message().contains("out of bounds"));
+}
+
+#[test]
+fn interface_method_handles() {
+    // the lambda of the static interface method is a `REF_invokeStatic` of an
+    // `InterfaceMethodref`, which is allowed since version 52
+    let class = parse_class_file(include_bytes!("../testdata/InterfaceLambda.class")).unwrap();
+    let cp = &class.constant_pool;
+    let handles = cp
+        .iter_with_indices()
+        .filter_map(|(_, entry)| match &entry.inner {
+            CpInfoInner::MethodHandle(handle) => Some(handle),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let lambda = handles
+        .iter()
+        .find(|handle| display_method_handle(handle, cp).contains("lambda$id$0"))
+        .unwrap();
+    assert!(matches!(
+        lambda.reference_index,
+        cp_info::MethodHandleIndex::MethodOrInterface(_)
+    ));
+    assert_eq!(
+        display_method_handle(lambda, cp),
+        "REF_invokeStatic InterfaceLambda.lambda$id$0:(Ljava/lang/String;)Ljava/lang/String;"
+    );
+
+    // the kinds 6 and 7 still can't point at fields
+    let mut builder = ClassFileBuilder::new("Handles");
+    let field = builder
+        .constant_pool()
+        .field_ref("Handles", "x", "I")
+        .inner();
+    let mut class = builder.build();
+    class.constant_pool.push(CpInfo {
+        tag: 15,
+        inner: CpInfoInner::MethodHandle(cp_info::MethodHandle {
+            reference_kind: 6,
+            reference_index: cp_info::MethodHandleIndex::MethodOrInterface(field.into()),
+        }),
+    });
+    let err = parse_class_file(&write_class_file(&class).unwrap()).unwrap_err();
+    assert!(err
+        .message()
+        .contains("Expected 'MethodRef' or 'InterfaceMethodref'"));
+}
+
+#[test]
+fn empty_constant_pool() {
+    // a class with `constant_pool_count` 1, that refers to `this_class` 5 anyway
+    let mut class = Vec::new();
+    class.extend_from_slice(&MAGIC.to_be_bytes());
+    class.extend_from_slice(&[0, 0, 0, 52]);
+    class.extend_from_slice(&1_u16.to_be_bytes());
+    class.extend_from_slice(&0x0021_u16.to_be_bytes());
+    class.extend_from_slice(&5_u16.to_be_bytes());
+    // no super class, interfaces, fields, methods and attributes
+    class.extend_from_slice(&[0; 10]);
+
+    let err = parse_class_file(&class).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "The constant pool count is 1, but a class needs at least its own name"
+    );
+    assert_eq!(err.offset(), Some(10));
+    assert!(skim_class_file(&class).is_err());
+    class[8..10].copy_from_slice(&0_u16.to_be_bytes());
+    assert!(skim_class_file(&class).is_err());
+
+    // an empty pool is not treated like one that is still being parsed
+    let out_of_bounds: FromPool<cp_info::Class> = 1.into();
+    assert!(out_of_bounds.validate(&ConstantPool::new()).is_err());
+}
+
+#[test]
+fn code_shares_class_buffer() {
+    let class = include_bytes!("../testdata/Test2.class");
+    let mut parsed = parse_class_file(class).unwrap();
+
+    let code = match &parsed.methods[1].attributes[0].inner {
+        AttributeInfoInner::Code { code, .. } => code.clone(),
+        _ => panic!("Expected Code attribute"),
+    };
+    assert!(code.is_shared());
+    let start = class
+        .windows(code.len())
+        .position(|window| window == &code[..])
+        .unwrap();
+    assert_eq!(&code[..], &class[start..start + code.len()]);
+
+    let before = parsed.clone();
+    parsed.compact();
+    assert_eq!(parsed, before);
+    match &parsed.methods[1].attributes[0].inner {
+        AttributeInfoInner::Code { code, .. } => assert!(!code.is_shared()),
+        _ => panic!("Expected Code attribute"),
+    };
+}
+
+#[test]
+fn utf8_shares_class_buffer() {
+    let mut class = parse_class_file(include_bytes!("../testdata/ModifiedUtf8.class")).unwrap();
+    let utf8 = |class: &ClassFile, value: &str| {
+        class
+            .constant_pool
+            .iter()
+            .find_map(|info| match &info.inner {
+                CpInfoInner::Utf8(utf8) if utf8.bytes == value => Some(utf8.bytes.clone()),
+                _ => None,
+            })
+            .unwrap()
+    };
+    assert!(utf8(&class, "java/lang/Object").is_shared());
+    // NUL and supplementary characters are encoded differently in UTF-8, so they have to be copied
+    assert!(!utf8(&class, "a\0b").is_shared());
+    assert!(!utf8(&class, "caf\u{e9} \u{1F600}").is_shared());
+
+    class.compact();
+    assert!(!utf8(&class, "java/lang/Object").is_shared());
+}
+
+#[test]
+fn type_annotations() {
+    let class = include_bytes!("../testdata/TypeAnnotations.class");
+    let parsed = parse_class_file(class).unwrap();
+    let cp = &parsed.constant_pool;
+
+    let type_annotations = |attributes: &[AttributeInfo]| -> Vec<TypeAnnotation> {
+        attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::RuntimeVisibleTypeAnnotations { annotations } => {
+                    Some(annotations.clone())
+                }
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    let bound = &type_annotations(&parsed.attributes)[0];
+    assert_eq!(bound.target_type, 0x11);
+    assert_eq!(
+        bound.target_info,
+        TypeAnnotationTarget::TypeParameterBound {
+            type_parameter_index: 0,
+            bound_index: 0
+        }
+    );
+    assert_eq!(bound.type_index.get(cp), "LTypeAnnotations$A;");
+
+    let paths = parsed
+        .fields
+        .iter()
+        .map(|field| {
+            type_annotations(&field.attributes)[0]
+                .target_path
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            "[TYPE_ARGUMENT(0)]",
+            "[TYPE_ARGUMENT(1), ARRAY]",
+            "[TYPE_ARGUMENT(0), WILDCARD]"
+        ]
+    );
+
+    let create = parsed
+        .methods
+        .iter()
+        .find(|method| method.name_index.get(cp) == "create")
+        .unwrap();
+    assert_eq!(
+        type_annotations(&create.attributes)[0].target_info,
+        TypeAnnotationTarget::FormalParameter {
+            formal_parameter_index: 0
+        }
+    );
+    let code_attributes = create
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code { attributes, .. } => Some(attributes),
+            _ => None,
+        })
+        .unwrap();
+    let code_annotations = type_annotations(code_attributes);
+    assert_eq!(
+        code_annotations[0].target_info,
+        TypeAnnotationTarget::Offset { offset: 0 }
+    );
+    assert_eq!(
+        code_annotations[1].target_info,
+        TypeAnnotationTarget::Localvar {
+            table: vec![TypeAnnotationLocalvar {
+                start_pc: 8,
+                length: 2,
+                index: 2
+            }]
+        }
+    );
+}
+
+#[test]
+fn nesting_info() {
+    let nesting = |class: &[u8]| parse_class_file(class).unwrap().nesting_info();
+
+    let host = nesting(include_bytes!("../testdata/Nesting.class"));
+    assert_eq!(host.kind, NestingKind::TopLevel);
+    assert_eq!(host.nest_host, None);
+    assert_eq!(
+        host.nest_members,
+        ["Nesting$Member", "Nesting$1", "Nesting$1Local"]
+    );
+    assert_eq!(host.member_classes, ["Nesting$Member"]);
+
+    let member = nesting(include_bytes!("../testdata/Nesting$Member.class"));
+    assert_eq!(
+        member.kind,
+        NestingKind::Member {
+            outer_class: "Nesting".to_string(),
+            simple_name: "Member".to_string(),
+        }
+    );
+    assert_eq!(member.nest_host.as_deref(), Some("Nesting"));
+
+    let create = Enclosing {
+        class: "Nesting".to_string(),
+        method: Some(("create".to_string(), "()Ljava/lang/Object;".to_string())),
+    };
+    let local = nesting(include_bytes!("../testdata/Nesting$1Local.class"));
+    assert_eq!(
+        local.kind,
+        NestingKind::Local {
+            enclosing: create.clone(),
+            simple_name: "Local".to_string(),
+        }
+    );
+
+    let anonymous = nesting(include_bytes!("../testdata/Nesting$1.class"));
+    assert_eq!(anonymous.kind, NestingKind::Anonymous { enclosing: create });
+    assert_eq!(
+        anonymous.to_string(),
+        "anonymous class declared in Nesting.create()Ljava/lang/Object;, nest host Nesting"
+    );
+}
+
+#[test]
+fn record() {
+    let class = parse_class_file(include_bytes!("../testdata/Point.class")).unwrap();
+    let cp = &class.constant_pool;
+    let components = class
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Record { components } => Some(components),
+            _ => None,
+        })
+        .unwrap();
+
+    let names = components
+        .iter()
+        .map(|c| (c.name_index.get(cp), c.descriptor_index.get(cp)))
+        .collect::<Vec<_>>();
+    assert_eq!(names, [("x", "I"), ("tags", "Ljava/util/List;")]);
+
+    assert!(components[0].attributes.is_empty());
+    match &components[1].attributes[..] {
+        [AttributeInfo {
+            inner: AttributeInfoInner::Signature { signature_index },
+            ..
+        }] => assert_eq!(signature_index.get(cp), "Ljava/util/List<TT;>;"),
+        attributes => panic!("Expected a signature, got {:?}", attributes),
+    }
+}
+
+#[test]
+fn dynamic_constants() {
+    let class = parse_class_file(include_bytes!("../testdata/Condy.class")).unwrap();
+    let cp = &class.constant_pool;
+    let name_and_type = |dynamic: &cp_info::Dynamic| {
+        let name_and_type = dynamic.name_and_type_index.get(cp);
+        (
+            dynamic.bootstrap_method_attr_index,
+            name_and_type.name_index.get(cp),
+            name_and_type.descriptor_index.get(cp),
+        )
+    };
+
+    let dynamic = cp
+        .iter()
+        .filter_map(|info| match &info.inner {
+            CpInfoInner::Dynamic(dynamic) => Some(name_and_type(dynamic)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dynamic,
+        [(0, "MAX_VALUE", "I"), (1, "_", "Ljava/lang/String;")]
+    );
+
+    let bootstrap_methods = class
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::BootstrapMethods { bootstrap_methods } => Some(bootstrap_methods),
+            _ => None,
+        })
+        .unwrap();
+    match bootstrap_methods[1].bootstrap_arguments[..] {
+        [handle, argument] => {
+            assert!(matches!(handle.get(cp), CpInfoInner::MethodHandle(_)));
+            match argument.get(cp) {
+                CpInfoInner::Dynamic(dynamic) => {
+                    assert_eq!(name_and_type(dynamic), (0, "MAX_VALUE", "I"))
+                }
+                info => panic!("Expected a dynamic constant, got {:?}", info),
+            }
+        }
+        ref arguments => panic!("Expected two arguments, got {:?}", arguments),
+    }
+}
+
+#[test]
+fn module() {
+    let class = parse_class_file(include_bytes!("../testdata/module-info.class")).unwrap();
+    let cp = &class.constant_pool;
+    let module_name = |module: &FromPool<cp_info::Module>| module.get(cp).name_index.get(cp);
+    let package_name = |package: &FromPool<cp_info::Package>| package.get(cp).name_index.get(cp);
+    let class_name = |class: &FromPool<cp_info::Class>| class.get(cp).name_index.get(cp);
+
+    let module = class
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Module(module) => Some(module),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(module_name(&module.module_name_index), "com.example.app");
+    assert_eq!(module.module_version_index.maybe_get(cp), Some("1.0"));
+
+    let requires = module
+        .requires
+        .iter()
+        .map(|requires| {
+            (
+                module_name(&requires.requires_index),
+                requires.requires_flags.bits(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        requires,
+        [
+            ("java.base", 0x8000),
+            ("java.logging", 0x0020),
+            ("java.sql", 0x0040)
+        ]
+    );
+
+    let exports = module
+        .exports
+        .iter()
+        .map(|exports| {
+            let to = exports
+                .exports_to_index
+                .iter()
+                .map(module_name)
+                .collect::<Vec<_>>();
+            (package_name(&exports.exports_index), to)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exports,
+        [
+            ("com/example/app", vec![]),
+            ("com/example/internal", vec!["java.logging"])
+        ]
+    );
+    assert_eq!(module.opens.len(), 1);
+    assert_eq!(
+        package_name(&module.opens[0].opens_index),
+        "com/example/internal"
+    );
+    assert!(module.opens[0].opens_to_index.is_empty());
+
+    let uses = module.uses_index.iter().map(class_name).collect::<Vec<_>>();
+    assert_eq!(uses, ["java/util/spi/ToolProvider"]);
+    assert_eq!(module.provides.len(), 1);
+    assert_eq!(
+        class_name(&module.provides[0].provides_index),
+        "java/util/spi/ToolProvider"
+    );
+    let implementations = module.provides[0]
+        .provides_with_index
+        .iter()
+        .map(class_name)
+        .collect::<Vec<_>>();
+    assert_eq!(implementations, ["com/example/app/Main"]);
+
+    for attr in &class.attributes {
+        match &attr.inner {
+            AttributeInfoInner::ModulePackages { package_index } => {
+                let packages = package_index.iter().map(package_name).collect::<Vec<_>>();
+                assert_eq!(packages, ["com/example/app", "com/example/internal"]);
+            }
+            AttributeInfoInner::ModuleMainClass { main_class_index } => {
+                assert_eq!(class_name(main_class_index), "com/example/app/Main")
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn modified_utf8() {
+    assert_eq!(from_mutf8(b"plain").unwrap(), "plain");
+    assert_eq!(from_mutf8(&[b'a', 0xC0, 0x80, b'b']).unwrap(), "a\0b");
+    let emoji = [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+    assert_eq!(from_mutf8(&emoji).unwrap(), "\u{1F600}");
+    assert_eq!(to_mutf8("\u{1F600}"), emoji);
+    assert_eq!(to_mutf8("a\0b"), [b'a', 0xC0, 0x80, b'b']);
+    assert_eq!(to_mutf8("é"), "é".as_bytes());
+    // an unpaired surrogate
+    assert_eq!(from_mutf8(&emoji[..3]).unwrap(), "\u{FFFD}");
+    assert!(from_mutf8(&[b'a', 0]).is_err());
+    assert!(from_mutf8(&[0xF0, 0x9F, 0x98, 0x80]).is_err());
+    assert!(from_mutf8(&[0xE0, 0x80]).is_err());
+
+    let class = parse_class_file(include_bytes!("../testdata/ModifiedUtf8.class")).unwrap();
+    let cp = &class.constant_pool;
+    let values = class
+        .fields
+        .iter()
+        .flat_map(|field| &field.attributes)
+        .filter_map(|attr| match &attr.inner {
+            AttributeInfoInner::ConstantValue {
+                constantvalue_index,
+            } => match constantvalue_index.get(cp) {
+                CpInfoInner::String(string) => Some(string.string_index.get(cp)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(values, ["a\0b", "café \u{1F600}", "x\u{FFFD}y"]);
+}
+
+#[test]
+fn lone_surrogate() {
+    let bytes = include_bytes!("../testdata/ModifiedUtf8.class");
+    let mut class = parse_class_file(bytes).unwrap();
+    let lone = [b'x', 0xED, 0xA0, 0xBD, b'y'];
+    let utf8 = |class: &ClassFile| {
+        class
+            .constant_pool
+            .iter()
+            .find_map(|info| match &info.inner {
+                CpInfoInner::Utf8(utf8) if utf8.bytes == "x\u{FFFD}y" => Some(utf8.bytes.clone()),
+                _ => None,
+            })
+            .unwrap()
+    };
+    let str = utf8(&class);
+    assert_eq!(str.original_mutf8(), Some(&lone[..]));
+    assert_eq!(&*str.to_mutf8(), lone);
+    assert!(str.is_shared());
+    // a string without unpaired surrogates is encoded again
+    assert_eq!(SharedStr::from("x\u{FFFD}y").original_mutf8(), None);
+    assert_ne!(str, SharedStr::from("x\u{FFFD}y"));
+
+    class.compact();
+    assert!(!utf8(&class).is_shared());
+    assert_eq!(write_class_file(&class).unwrap(), bytes);
+}
+
+#[test]
+fn write_round_trip() {
+    for class in [
+        &include_bytes!("../testdata/Test.class")[..],
+        &include_bytes!("../testdata/Test2.class")[..],
+        &include_bytes!("../testdata/Jcov.class")[..],
+        &include_bytes!("../testdata/Attributes.class")[..],
+        &include_bytes!("../testdata/Attributes$Marker.class")[..],
+        &include_bytes!("../testdata/TypeAnnotations.class")[..],
+        &include_bytes!("../testdata/Nesting.class")[..],
+        &include_bytes!("../testdata/Nesting$1.class")[..],
+        &include_bytes!("../testdata/Point.class")[..],
+        &include_bytes!("../testdata/Condy.class")[..],
+        &include_bytes!("../testdata/module-info.class")[..],
+        &include_bytes!("../testdata/ModifiedUtf8.class")[..],
+        &include_bytes!("../testdata/InterfaceLambda.class")[..],
+    ] {
+        let parsed = parse_class_file(class).unwrap();
+        assert_eq!(write_class_file(&parsed).unwrap(), class);
+    }
+}
+
+#[test]
+fn retarget() {
+    let mut class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+    let has_stack_map = |class: &ClassFile| {
+        class
+            .methods
+            .iter()
+            .flat_map(|m| &m.attributes)
+            .any(|attr| match &attr.inner {
+                AttributeInfoInner::Code { attributes, .. } => attributes
+                    .iter()
+                    .any(|attr| matches!(attr.inner, AttributeInfoInner::StackMapTable { .. })),
+                _ => false,
+            })
+    };
+    assert!(has_stack_map(&class));
+
+    class.retarget(49).unwrap();
+    assert_eq!(class.major_version, 49);
+    assert!(!has_stack_map(&class));
+    let written = parse_class_file(&write_class_file(&class).unwrap()).unwrap();
+    assert_eq!(written, class);
+
+    // the removed frames are only recomputed by `cs_verifier::retarget`
+    assert!(class.retarget(52).is_err());
+    class.retarget(50).unwrap();
+
+    let mut nesting = parse_class_file(include_bytes!("../testdata/Nesting.class")).unwrap();
+    let err = nesting.retarget(52).unwrap_err();
+    assert_eq!(
+        err.0,
+        "Nest based access control requires version 55, but the target is 52"
+    );
+    nesting.retarget(55).unwrap();
+    assert_eq!(nesting.major_version, 55);
+}
+
+#[test]
+fn canonical_write() {
+    let canonical = WriteOptions {
+        canonical: true,
+        ..WriteOptions::default()
+    };
+    let class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+    let mut reordered = class.clone();
+    reordered.attributes.reverse();
+    for method in &mut reordered.methods {
+        method.attributes.reverse();
+    }
+
+    assert_ne!(
+        write_class_file(&class).unwrap(),
+        write_class_file(&reordered).unwrap()
+    );
+    let written = write_class_file_with(&class, &canonical).unwrap();
+    assert_eq!(
+        written,
+        write_class_file_with(&reordered, &canonical).unwrap()
+    );
+    assert_eq!(
+        parse_class_file(&written).unwrap().attributes.len(),
+        class.attributes.len()
+    );
+
+    // the same class with its constants added in a different order
+    let constants = |first: &str, second: &str| {
+        let mut builder = ClassFileBuilder::new("Constants");
+        let pool = builder.constant_pool();
+        let strings = [pool.string(first), pool.string(second)];
+        let (x, y) = match first {
+            "x" => (strings[0], strings[1]),
+            _ => (strings[1], strings[0]),
+        };
+        let mut code = vec![0x12, x.inner() as u1, 0x57]; // ldc, pop
+        code.extend([0x13, 0, y.inner() as u1, 0xb0]); // ldc_w, areturn
+        let get = MethodCode {
+            max_stack: 1,
+            max_locals: 0,
+            code,
+            exception_table: Vec::new(),
+        };
+        builder
+            .add_method(
+                MethodAccessFlag::STATIC,
+                "get",
+                "()Ljava/lang/String;",
+                Some(get),
+            )
+            .build()
+    };
+    let (xy, yx) = (constants("x", "y"), constants("y", "x"));
+    assert_ne!(
+        write_class_file(&xy).unwrap(),
+        write_class_file(&yx).unwrap()
+    );
+    let written = write_class_file_with(&xy, &canonical).unwrap();
+    assert_eq!(written, write_class_file_with(&yx, &canonical).unwrap());
+    let parsed = parse_class_file(&written).unwrap();
+    let loads = parsed
+        .string_loads()
+        .unwrap()
+        .into_iter()
+        .map(|(_, offset, value)| (offset, value))
+        .collect::<Vec<_>>();
+    assert_eq!(loads, [(0, "x"), (3, "y")]);
+}
+
+#[test]
+fn preserve_original() {
+    let mut class = parse_class_file(include_bytes!("../testdata/Test.class")).unwrap();
+    class.constant_pool.push(CpInfo {
+        tag: 1,
+        inner: CpInfoInner::Utf8(cp_info::Utf8 {
+            bytes: "Custom".into(),
+        }),
+    });
+    let custom_index = FromPool::from(class.constant_pool.len() as u2);
+    class.attributes.push(AttributeInfo {
+        attribute_name_index: custom_index,
+        attribute_length: 3,
+        inner: AttributeInfoInner::Unknown {
+            attribute_content: vec![1, 2, 3].into(),
+        },
+        original: None,
+    });
+    // trailing bytes after the source file index are ignored by the parser
+    let source_file = class
+        .attributes
+        .iter_mut()
+        .find(|attr| matches!(attr.inner, AttributeInfoInner::SourceFile { .. }))
+        .unwrap();
+    let mut content = source_file.original.as_deref().unwrap().to_vec();
+    content.extend([0xAB, 0xCD]);
+    source_file.inner = AttributeInfoInner::Unknown {
+        attribute_content: content.into(),
+    };
+    let written = write_class_file(&class).unwrap();
+
+    let preserve = WriteOptions {
+        preserve_original: true,
+        ..WriteOptions::default()
+    };
+    let mut parsed = parse_class_file(&written).unwrap();
+    assert!(parsed.attributes.iter().any(|attr| matches!(
+        &attr.inner,
+        AttributeInfoInner::Unknown { attribute_content } if attribute_content[..] == [1, 2, 3]
+    )));
+    assert_eq!(write_class_file(&parsed).unwrap().len(), written.len() - 2);
+    assert_eq!(write_class_file_with(&parsed, &preserve).unwrap(), written);
+
+    // changed attributes are written again
+    for attr in &mut parsed.attributes {
+        if let AttributeInfoInner::SourceFile { sourcefile_index } = &mut attr.inner {
+            *sourcefile_index = custom_index;
+        }
+    }
+    let changed = parse_class_file(&write_class_file_with(&parsed, &preserve).unwrap()).unwrap();
+    assert_eq!(changed.attributes[0].inner, parsed.attributes[0].inner);
+    assert_eq!(changed.attributes[0].attribute_length, 2);
+}
+
+#[test]
+fn access_flags() {
+    let flags = MethodAccessFlag::PUBLIC | MethodAccessFlag::STATIC;
+    assert!(flags.contains(MethodAccessFlag::STATIC));
+    assert!(!flags.contains(MethodAccessFlag::FINAL));
+    assert_eq!(
+        flags.iter().collect::<Vec<_>>(),
+        [MethodAccessFlag::PUBLIC, MethodAccessFlag::STATIC]
+    );
+    assert_eq!(flags.to_string(), "ACC_PUBLIC, ACC_STATIC");
+    assert_eq!(MethodAccessFlags::empty().to_string(), "");
+
+    // bits without a flag are kept
+    let mut flags = ClassAccessFlags::from_bits(0x0021 | 0x0100);
+    assert_eq!(flags.unknown_bits(), 0x0100);
+    assert_eq!(flags.to_string(), "ACC_PUBLIC, ACC_SUPER, 0x0100");
+    flags.remove(ClassAccessFlag::Super);
+    flags |= ClassAccessFlag::Final;
+    assert_eq!(flags.bits(), 0x0111);
+
+    let class = include_bytes!("../testdata/Test.class");
+    let parsed = parse_class_file(class).unwrap();
+    assert_eq!(parsed.access_flags.to_string(), "ACC_PUBLIC, ACC_SUPER");
+    assert_eq!(write_class_file(&parsed).unwrap(), class);
+}
+
+#[test]
+fn class_file_builder() {
+    let mut builder = ClassFileBuilder::new("Hello")
+        .add_field(FieldAccessFlag::PRIVATE, "count", "I")
+        .add_default_constructor();
+    let pool = builder.constant_pool();
+    let out = pool.field_ref("java/lang/System", "out", "Ljava/io/PrintStream;");
+    let println = pool.method_ref("java/io/PrintStream", "println", "(Ljava/lang/String;)V");
+    let hello = pool.string("Hello, World!");
+    let mut code = vec![0xb2]; // getstatic
+    code.extend(out.inner().to_be_bytes());
+    code.extend([0x12, hello.inner() as u1]); // ldc
+    code.push(0xb6); // invokevirtual
+    code.extend(println.inner().to_be_bytes());
+    code.push(0xb1); // return
+    let main = MethodCode {
+        max_stack: 2,
+        max_locals: 1,
+        code,
+        exception_table: Vec::new(),
+    };
+    let class = builder
+        .add_method(
+            MethodAccessFlag::PUBLIC | MethodAccessFlag::STATIC,
+            "main",
+            "([Ljava/lang/String;)V",
+            Some(main),
+        )
+        .source_file("Hello.java")
+        .build();
+
+    let cp = &class.constant_pool;
+    assert_eq!(class.this_class.get(cp).name_index.get(cp), "Hello");
+    assert_eq!(
+        class.super_class.maybe_get(cp).unwrap().name_index.get(cp),
+        "java/lang/Object"
+    );
+    let methods = class
+        .methods
+        .iter()
+        .map(|method| (method.name_index.get(cp), method.descriptor_index.get(cp)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        [("<init>", "()V"), ("main", "([Ljava/lang/String;)V")]
+    );
+
+    let written = write_class_file(&class).unwrap();
+    assert_eq!(parse_class_file(&written).unwrap(), class);
+}
+
+#[test]
+fn source_debug_extension() {
+    let smap = "SMAP\nHello.kt\nKotlin\n*S Kotlin\n*E\n";
+    let mut builder = ClassFileBuilder::new("Hello");
+    let name = builder.constant_pool().utf8("SourceDebugExtension");
+    let mut class = builder.build();
+    class.attributes.push(AttributeInfo {
+        attribute_name_index: name,
+        attribute_length: smap.len() as u4,
+        original: None,
+        inner: AttributeInfoInner::SourceDebugExtension {
+            debug_extension: smap.as_bytes().to_vec(),
+        },
+    });
+
+    let parsed = parse_class_file(&write_class_file(&class).unwrap()).unwrap();
+    assert_eq!(parsed, class);
+    assert!(matches!(
+        &parsed.attributes[..],
+        [.., AttributeInfo {
+            inner: AttributeInfoInner::SourceDebugExtension { debug_extension },
+            ..
+        }] if debug_extension == smap.as_bytes()
+    ));
+}
+
+#[test]
+fn constant_pool_builder() {
+    let mut pool = ConstantPoolBuilder::new();
+    let object = pool.class("java/lang/Object");
+    let long = pool.long(-1);
+    let to_string = pool.method_ref("java/lang/Object", "toString", "()Ljava/lang/String;");
+    let double = pool.double(0.5);
+    assert_eq!(pool.class("java/lang/Object"), object);
+    assert_eq!(pool.long(-1), long);
+    assert_eq!(
+        pool.method_ref("java/lang/Object", "toString", "()Ljava/lang/String;"),
+        to_string
+    );
+    let name = pool.utf8("toString");
+    let cp = pool.build();
+
+    assert_eq!(object.inner(), 2);
+    assert_eq!(long.inner(), 3);
+    assert_eq!(cp[3].inner, CpInfoInner::Unusable);
+    assert_eq!(to_string.inner(), 8);
+    assert_eq!(double.inner(), 9);
+    assert_eq!(cp.len(), 10);
+    assert_eq!(name.inner(), 5);
+    assert_eq!(name.get(&cp), "toString");
+    assert_eq!(to_string.get(&cp).class_index, object);
+
+    // the second index of a long is skipped when writing and parsing
+    let mut class = ClassFileBuilder::new("Constants").build();
+    let mut pool = ConstantPoolBuilder::from_pool(class.constant_pool);
+    assert_eq!(pool.class("Constants"), class.this_class);
+    pool.long(i64::MAX);
+    pool.double(f64::NAN);
+    let name = pool.utf8("after");
+    class.constant_pool = pool.build();
+    let parsed = parse_class_file(&write_class_file(&class).unwrap()).unwrap();
+    assert_eq!(parsed, class);
+    assert_eq!(name.get(&parsed.constant_pool), "after");
+}
+
+/// `FromPool::get` reads the split up entries with the `columnar` feature  
+/// `cargo test -p cs_parser --features columnar constant_pool_get`
+#[test]
+fn constant_pool_get() {
+    let mut class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+    let cp = &class.constant_pool;
+    for (index, info) in cp.iter_with_indices() {
+        match &info.inner {
+            CpInfoInner::Utf8(utf8) => {
+                assert_eq!(FromPool::<cp_info::Utf8>::from(index).get(cp), &*utf8.bytes)
+            }
+            CpInfoInner::Class(class) => {
+                assert_eq!(FromPool::<cp_info::Class>::from(index).get(cp), class)
+            }
+            CpInfoInner::MethodRef(method) => {
+                assert_eq!(FromPool::<cp_info::MethodRef>::from(index).get(cp), method)
+            }
+            CpInfoInner::NameAndType(name_and_type) => {
+                assert_eq!(
+                    FromPool::<cp_info::NameAndType>::from(index).get(cp),
+                    name_and_type
+                )
+            }
+            CpInfoInner::Integer(integer) => {
+                assert_eq!(FromPool::<cp_info::Integer>::from(index).get(cp), integer)
+            }
+            _ => {}
+        }
+    }
+
+    // changed entries are split up again
+    let name = class.this_class.get(&class.constant_pool).name_index;
+    assert_eq!(name.get(&class.constant_pool), "Attributes");
+    class.constant_pool[usize::from(name.inner()) - 1].inner = CpInfoInner::Utf8(cp_info::Utf8 {
+        bytes: "Renamed".into(),
+    });
+    assert_eq!(name.get(&class.constant_pool), "Renamed");
+    let pushed = FromPool::<cp_info::Utf8>::from(class.constant_pool.push(CpInfo {
+        tag: 1,
+        inner: CpInfoInner::Utf8(cp_info::Utf8 {
+            bytes: "pushed".into(),
+        }),
+    }));
+    assert_eq!(pushed.get(&class.constant_pool), "pushed");
+    let usage = class.memory_usage();
+    class.compact();
+    assert!(class.memory_usage() <= usage);
+    assert_eq!(pushed.get(&class.constant_pool), "pushed");
+}
+
+#[test]
+fn constant_pool_indices() {
+    let mut builder = ConstantPoolBuilder::new();
+    let long = builder.long(1);
+    let name = builder.utf8("name");
+    let cp = builder.build();
+
+    assert_eq!(cp.count(), 4);
+    assert_eq!(cp.get(name), "name");
+    assert_eq!(cp.utf8(name.inner()), Some("name"));
+    assert_eq!(cp.utf8(long.inner()), None);
+    assert!(cp.entry(long.inner()).is_some());
+    // 0, the second index of the long and indices after the end
+    assert!(cp.entry(0).is_none());
+    assert!(cp.entry(long.inner() + 1).is_none());
+    assert!(cp.entry(4).is_none());
+    assert_eq!(
+        cp.iter_with_indices()
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>(),
+        [long.inner(), name.inner()]
+    );
+
+    let mut pushed = ConstantPool::new();
+    for (_, info) in cp.iter_with_indices() {
+        pushed.push(info.clone());
+    }
+    assert_eq!(pushed, cp);
+}
+
+#[test]
+fn decode_instructions() {
+    let class = parse_class_file(include_bytes!("../testdata/Instructions.class")).unwrap();
+    let cp = &class.constant_pool;
+    let code = |name: &str| {
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name_index.get(cp) == name)
+            .unwrap();
+        method
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::Code { code, .. } => Some(decode_code(code).unwrap()),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    match &code("table")[..] {
+        [(0, Instruction::Iload0), (
+            1,
+            Instruction::Tableswitch {
+                default,
+                low: 1,
+                high: 3,
+                offsets,
+            },
+        ), (pc, Instruction::Bipush(10)), ..] => {
+            // the operands are aligned to four bytes
+            assert_eq!(*pc, 1 + 3 + 12 + 3 * 4);
+            assert_eq!(offsets[0], *pc as i32 - 1);
+            assert!(*default > offsets[2]);
+        }
+        instructions => panic!("Unexpected code {:?}", instructions),
+    }
+
+    match &code("lookup")[..] {
+        [(0, Instruction::Iload0), (1, Instruction::Lookupswitch { pairs, .. }), ..] => {
+            assert_eq!(
+                pairs.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+                [1, 1000]
+            );
+        }
+        instructions => panic!("Unexpected code {:?}", instructions),
+    }
+
+    assert_eq!(
+        code("wide"),
+        [
+            (
+                0,
+                Instruction::Iinc {
+                    index: 0,
+                    value: 1000
+                }
+            ),
+            (6, Instruction::Iload0),
+            (7, Instruction::Ireturn),
+        ]
+    );
+
+    let arrays = code("arrays");
+    assert_eq!(arrays[1], (1, Instruction::Newarray(11)));
+    assert!(arrays.iter().any(|(_, instruction)| matches!(
+        instruction,
+        Instruction::Multianewarray { dimensions: 2, .. }
+    )));
+
+    assert!(matches!(
+        code("size")[1],
+        (1, Instruction::Invokeinterface { count: 1, .. })
+    ));
+    assert!(matches!(
+        code("lambda")[0],
+        (0, Instruction::Invokedynamic(_))
+    ));
+
+    assert_eq!(
+        decode_code(&[0x10, 0xff, 0xa7, 0xff, 0xfe]).unwrap(),
+        [(0, Instruction::Bipush(-1)), (2, Instruction::Goto(-2))]
+    );
+    assert!(decode_code(&[0xca]).is_err());
+    assert!(decode_code(&[0xc4, 0x10, 0x00, 0x00]).is_err());
+    assert!(decode_code(&[0x11, 0x00]).is_err());
+}
+
+#[test]
+fn disassemble_code() {
+    let class = parse_class_file(include_bytes!("../testdata/Instructions.class")).unwrap();
+    let cp = &class.constant_pool;
+    let disassembled = |name: &str| {
+        let method = class
+            .methods
+            .iter()
+            .find(|method| method.name_index.get(cp) == name)
+            .unwrap();
+        method
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::Code { code, .. } => Some(disassemble(code, cp).unwrap()),
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    assert_eq!(
+        disassembled("lookup"),
+        "    0: iload_0
+    1: lookupswitch { 1: 28, 1000: 31, default: 34 }
+   28: bipush 10
+   30: ireturn
+   31: bipush 20
+   33: ireturn
+   34: iconst_m1
+   35: ireturn"
+    );
+    assert_eq!(
+        disassembled("wide"),
+        "    0: iinc 0, 1000\n    6: iload_0\n    7: ireturn"
+    );
+    assert_eq!(
+        disassembled("arrays"),
+        "    0: iconst_2
+    1: newarray long
+    3: astore_0
+    4: iconst_3
+    5: aload_0
+    6: arraylength
+    7: multianewarray [[I, 2
+   11: areturn"
+    );
+    assert_eq!(
+        disassembled("size"),
+        "    0: aload_0\n    1: invokeinterface java/util/List.size:()I\n    6: ireturn"
+    );
+    assert_eq!(
+        disassembled("lambda"),
+        "    0: invokedynamic #0 get:()Ljava/util/function/Supplier;\n    5: areturn"
+    );
+    assert!(disassembled("table").contains("tableswitch { 1: 28, 2: 31, 3: 34, default: 37 }"));
+
+    // indices that don't point to a fitting constant are kept as they are
+    assert_eq!(
+        display_instruction(0, &Instruction::Getstatic(0), cp),
+        "getstatic #0"
+    );
+    assert_eq!(
+        display_instruction(0, &Instruction::Ldc(u1::MAX), cp),
+        "ldc #255"
+    );
+}
+
+#[test]
+fn disassemble_with_labels() {
+    let class = parse_class_file(include_bytes!("../testdata/Instructions.class")).unwrap();
+    let cp = &class.constant_pool;
+    let method = class.find_method("lookup", None).unwrap();
+    let (code, attributes) = method
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code {
+                code, attributes, ..
+            } => Some((code, attributes)),
+            _ => None,
+        })
+        .unwrap();
+    let line_numbers = attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => {
+                Some(line_number_table.clone())
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(
+        disassemble_listing(code, &line_numbers, cp).unwrap(),
+        "// line 15
+    0: iload_0
+    1: lookupswitch { 1: L0, 1000: L1, default: L2 }
+// line 16
+L0:
+   28: bipush 10
+   30: ireturn
+// line 17
+L1:
+   31: bipush 20
+   33: ireturn
+// line 18
+L2:
+   34: iconst_m1
+   35: ireturn"
+    );
+    // without line numbers, only the labels are added
+    assert!(!disassemble_listing(code, &[], cp).unwrap().contains("line"));
+}
+
+#[test]
+fn disassemble_source_lines() {
+    let class = parse_class_file(include_bytes!("../../cs_vm/testdata/Hello.class")).unwrap();
+    let Some(AttributeInfoInner::Code {
+        code, attributes, ..
+    }) = class
+        .find_method("main", None)
+        .and_then(|method| method.attributes.first())
+        .map(|attr| &attr.inner)
+    else {
+        panic!("main has no code")
+    };
+    let Some(AttributeInfoInner::LineNumberTable { line_number_table }) =
+        attributes.first().map(|attr| &attr.inner)
+    else {
+        panic!("main has no line numbers")
+    };
+    let cp = &class.constant_pool;
+
+    let source = include_str!("../../cs_vm/testdata/Hello.java");
+    let listing = disassemble_with_source(code, line_number_table, source, cp).unwrap();
+    assert!(listing.starts_with(
+        "// 3: System.out.println(\"Hello, World!\");
+    0: getstatic java/lang/System.out:Ljava/io/PrintStream;"
+    ));
+    assert!(listing.contains(
+        "// 5: for (int i = 1; i <= 10; i++) {
+   10: iconst_1
+   11: istore_2
+L0:
+   12: iload_2"
+    ));
+    // lines past the end of the source are shown by their number
+    let listing = disassemble_with_source(code, line_number_table, "class Hello {", cp).unwrap();
+    assert_eq!(
+        listing,
+        disassemble_listing(code, line_number_table, cp).unwrap()
+    );
+}
+
+#[test]
+fn decompile_methods() {
+    let decompiled = |class: &ClassFile, name: &str| {
+        let method = class.find_method(name, None).unwrap();
+        decompile(class, method).unwrap()
+    };
+
+    let class = parse_class_file(include_bytes!("../testdata/Test2.class")).unwrap();
+    assert_eq!(
+        decompiled(&class, "main").unwrap(),
+        "// line 5
+local1 = 0;
+// line 6
+local1++;
+// line 7
+new Test2().print(local1);
+// line 8
+return;"
+    );
+    assert_eq!(
+        decompiled(&class, "<init>").unwrap(),
+        "// line 1\nsuper();\nreturn;"
+    );
+
+    let class = parse_class_file(include_bytes!("../../cs_vm/testdata/Hello.class")).unwrap();
+    let main = decompiled(&class, "main").unwrap();
+    assert!(main.starts_with(
+        "// line 3
+System.out.println(\"Hello, World!\");
+// line 4
+local1 = 0;
+// line 5
+local2 = 1;
+L0:
+if (local2 > 10) goto L1;
+// line 6
+local1 = local1 + local2;
+// line 5
+local2++;
+goto L0;
+// line 8
+L1:
+System.out.println(local1);"
+    ));
+    assert!(main.contains("System.out.println(10000000000L);"));
+
+    // switches and invokedynamic are disassembled instead
+    let class = parse_class_file(include_bytes!("../testdata/Instructions.class")).unwrap();
+    let table = decompiled(&class, "table").unwrap();
+    assert!(table.starts_with("// not decompiled: tableswitch at 1 is not supported\n// line 6"));
+    assert!(table.contains("1: tableswitch { 1: L0, 2: L1, 3: L2, default: L3 }"));
+    assert!(decompiled(&class, "lambda")
+        .unwrap()
+        .starts_with("// not decompiled: invokedynamic at 0"));
+    assert_eq!(
+        decompiled(&class, "wide").unwrap(),
+        "// line 23\narg0 += 1000;\n// line 24\nreturn arg0;"
+    );
+
+    let class = ClassFileBuilder::new("Abstract")
+        .add_method(MethodAccessFlag::ABSTRACT, "run", "()V", None)
+        .build();
+    assert_eq!(decompiled(&class, "run"), None);
+}
+
+#[test]
+fn inline_subroutines() {
+    // `int x = 0; try {} finally { x += 21; } try {} finally { x += 21; } return x;`
+    let mut builder = ClassFileBuilder::new("Subroutines").version(49, 0);
+    let line_numbers = builder.constant_pool().utf8("LineNumberTable");
+    let mut asm = Assembler::new();
+    let subroutine = asm.new_label();
+    asm.push(Instruction::Iconst0);
+    asm.push(Instruction::Istore0);
+    asm.branch(Instruction::Jsr, subroutine);
+    asm.branch(Instruction::Jsr, subroutine);
+    asm.push(Instruction::Iload0);
+    asm.push(Instruction::Ireturn);
+    asm.place(subroutine);
+    asm.push(Instruction::Astore1);
+    asm.push(Instruction::Iinc {
+        index: 0,
+        value: 21,
+    });
+    asm.push(Instruction::Ret(1));
+    let code = MethodCode {
+        max_stack: 1,
+        max_locals: 2,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let mut class = builder
+        .add_method(MethodAccessFlag::STATIC, "run", "()I", Some(code))
+        .build();
+    let Some(AttributeInfoInner::Code { attributes, .. }) = class.methods[0]
+        .attributes
+        .first_mut()
+        .map(|attr| &mut attr.inner)
+    else {
+        panic!("run has no code")
+    };
+    attributes.push(AttributeInfo {
+        attribute_name_index: line_numbers,
+        attribute_length: 6,
+        inner: AttributeInfoInner::LineNumberTable {
+            line_number_table: vec![AttributeLineNumber {
+                start_pc: 10,
+                line_number: 3,
+            }],
+        },
+        original: None,
+    });
+
+    assert_eq!(class.inline_subroutines().unwrap(), 1);
+    let Some(AttributeInfoInner::Code {
+        code, attributes, ..
+    }) = class.methods[0].attributes.first().map(|attr| &attr.inner)
+    else {
+        panic!("run has no code")
+    };
+    let instructions = decode_code(code)
+        .unwrap()
+        .into_iter()
+        .map(|(_, instruction)| instruction)
+        .collect::<Vec<_>>();
+    let copy = [
+        Instruction::Astore1,
+        Instruction::Iinc {
+            index: 0,
+            value: 21,
+        },
+    ];
+    let expected = [
+        &[
+            Instruction::Iconst0,
+            Instruction::Istore0,
+            Instruction::AconstNull,
+            Instruction::Goto(9),
+            Instruction::AconstNull,
+            Instruction::Goto(12),
+            Instruction::Iload0,
+            Instruction::Ireturn,
+        ][..],
+        &copy,
+        &[Instruction::Goto(-10)],
+        &copy,
+        &[Instruction::Goto(-13)],
+    ]
+    .concat();
+    assert_eq!(instructions, expected);
+    // the line of the subroutine is in both copies
+    let Some(AttributeInfoInner::LineNumberTable { line_number_table }) =
+        attributes.first().map(|attr| &attr.inner)
+    else {
+        panic!("the line numbers were removed")
+    };
+    let lines = line_number_table
+        .iter()
+        .map(|line| (line.start_pc, line.line_number))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [(12, 3), (19, 3)]);
+    // a second time there is nothing to inline
+    assert_eq!(class.inline_subroutines().unwrap(), 0);
+
+    let mut asm = Assembler::new();
+    asm.push(Instruction::Ret(0));
+    let code = MethodCode {
+        max_stack: 0,
+        max_locals: 1,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let mut class = ClassFileBuilder::new("Subroutines")
+        .version(49, 0)
+        .add_method(MethodAccessFlag::STATIC, "run", "()V", Some(code))
+        .build();
+    assert_eq!(
+        class.inline_subroutines().unwrap_err().to_string(),
+        "Could not inline subroutines: run()V: ret at 0 is not in a subroutine"
+    );
+}
+
+#[test]
+fn optimize() {
+    // `int x = 2 * 3; int y = 1; return x;` with a detour through two gotos
+    let mut builder = ClassFileBuilder::new("Optimized").version(49, 0);
+    let line_numbers = builder.constant_pool().utf8("LineNumberTable");
+    let mut asm = Assembler::new();
+    let detour = asm.new_label();
+    let back = asm.new_label();
+    asm.push(Instruction::Nop);
+    asm.push(Instruction::Iconst2);
+    asm.push(Instruction::Iconst3);
+    asm.push(Instruction::Imul);
+    asm.push(Instruction::Istore0);
+    asm.push(Instruction::Iconst1);
+    asm.push(Instruction::Istore1);
+    asm.branch(Instruction::Goto, detour);
+    asm.place(back);
+    asm.push(Instruction::Iload0);
+    asm.push(Instruction::Ireturn);
+    asm.place(detour);
+    asm.branch(Instruction::Goto, back);
+    asm.push(Instruction::Iconst0);
+    asm.push(Instruction::Ireturn);
+    let code = MethodCode {
+        max_stack: 2,
+        max_locals: 2,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    // a division by zero throws, so it isn't folded
+    let mut asm = Assembler::new();
+    asm.push(Instruction::Iconst1);
+    asm.push(Instruction::Iconst0);
+    asm.push(Instruction::Idiv);
+    asm.push(Instruction::Ireturn);
+    let divide = MethodCode {
+        max_stack: 2,
+        max_locals: 0,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let mut class = builder
+        .add_method(MethodAccessFlag::STATIC, "run", "()I", Some(code))
+        .add_method(MethodAccessFlag::STATIC, "divide", "()I", Some(divide))
+        .build();
+    let Some(AttributeInfoInner::Code { attributes, .. }) = class.methods[0]
+        .attributes
+        .first_mut()
+        .map(|attr| &mut attr.inner)
+    else {
+        panic!("run has no code")
+    };
+    attributes.push(AttributeInfo {
+        attribute_name_index: line_numbers,
+        attribute_length: 10,
+        inner: AttributeInfoInner::LineNumberTable {
+            line_number_table: vec![
+                AttributeLineNumber {
+                    start_pc: 0,
+                    line_number: 1,
+                },
+                AttributeLineNumber {
+                    start_pc: 10,
+                    line_number: 2,
+                },
+            ],
+        },
+        original: None,
+    });
+
+    assert_eq!(class.optimize().unwrap(), 1);
+    let Some(AttributeInfoInner::Code {
+        code, attributes, ..
+    }) = class.methods[0].attributes.first().map(|attr| &attr.inner)
+    else {
+        panic!("run has no code")
+    };
+    let instructions = decode_code(code)
+        .unwrap()
+        .into_iter()
+        .map(|(_, instruction)| instruction)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        instructions,
+        [
+            Instruction::Bipush(6),
+            Instruction::Istore0,
+            Instruction::Iconst1,
+            Instruction::Pop,
+            Instruction::Iload0,
+            Instruction::Ireturn,
+        ]
+    );
+    let Some(AttributeInfoInner::LineNumberTable { line_number_table }) =
+        attributes.first().map(|attr| &attr.inner)
+    else {
+        panic!("the line numbers were removed")
+    };
+    let lines = line_number_table
+        .iter()
+        .map(|line| (line.start_pc, line.line_number))
+        .collect::<Vec<_>>();
+    assert_eq!(lines, [(0, 1), (5, 2)]);
+    // a second time there is nothing to optimize
+    assert_eq!(class.optimize().unwrap(), 0);
+    let written = write_class_file(&class).unwrap();
+    assert_eq!(parse_class_file(&written).unwrap(), class);
+}
+
+#[test]
+fn encode_instructions() {
+    let classes: [&[u8]; 4] = [
+        include_bytes!("../testdata/Instructions.class"),
+        include_bytes!("../testdata/Test.class"),
+        include_bytes!("../testdata/Nesting.class"),
+        include_bytes!("../testdata/Attributes.class"),
+    ];
+    for bytes in classes {
+        let class = parse_class_file(bytes).unwrap();
+        for method in &class.methods {
+            for attr in &method.attributes {
+                if let AttributeInfoInner::Code { code, .. } = &attr.inner {
+                    let instructions = decode_code(code)
+                        .unwrap()
+                        .into_iter()
+                        .map(|(_, instruction)| instruction)
+                        .collect::<Vec<_>>();
+                    assert_eq!(&encode_code(&instructions).unwrap()[..], &code[..]);
+                }
+            }
+        }
+    }
+
+    assert_eq!(
+        encode_code(&[
+            Instruction::Iload(300),
+            Instruction::Iinc {
+                index: 1,
+                value: 200
+            },
+            Instruction::Iinc {
+                index: 1,
+                value: -1
+            },
+        ])
+        .unwrap(),
+        [0xc4, 0x15, 0x01, 0x2c, 0xc4, 0x84, 0x00, 0x01, 0x00, 0xc8, 0x84, 0x01, 0xff]
+    );
+    assert!(encode_code(&[Instruction::Goto(40000)]).is_err());
+    assert!(encode_code(&[Instruction::Tableswitch {
+        default: 0,
+        low: 1,
+        high: 3,
+        offsets: vec![0],
+    }])
+    .is_err());
+}
+
+#[test]
+fn assemble_labels() {
+    let mut asm = Assembler::new();
+    let start = asm.new_label();
+    let end = asm.new_label();
+    let one = asm.new_label();
+    asm.place(start);
+    asm.push(Instruction::Iload0);
+    asm.branch(Instruction::Ifeq, end);
+    asm.push(Instruction::Iload0);
+    asm.tableswitch(1, start, vec![one]);
+    asm.place(one);
+    asm.push(Instruction::Iinc {
+        index: 0,
+        value: -1,
+    });
+    asm.branch(Instruction::Goto, start);
+    asm.place(end);
+    asm.push(Instruction::Return);
+    let assembled = asm.assemble().unwrap();
+
+    assert_eq!(
+        decode_code(&assembled.code).unwrap(),
+        [
+            (0, Instruction::Iload0),
+            (1, Instruction::Ifeq(29)),
+            (4, Instruction::Iload0),
+            (
+                5,
+                Instruction::Tableswitch {
+                    default: -5,
+                    low: 1,
+                    high: 1,
+                    offsets: vec![19],
+                }
+            ),
+            (
+                24,
+                Instruction::Iinc {
+                    index: 0,
+                    value: -1
+                }
+            ),
+            (27, Instruction::Goto(-27)),
+            (30, Instruction::Return),
+        ][..]
+    );
+    assert_eq!(assembled.offset(end), Some(30));
+
+    // too far for two byte offsets
+    let mut asm = Assembler::new();
+    let end = asm.new_label();
+    asm.push(Instruction::Iload0);
+    asm.branch(Instruction::Ifne, end);
+    asm.branch(Instruction::Goto, end);
+    for _ in 0..40000 {
+        asm.push(Instruction::Nop);
+    }
+    asm.place(end);
+    asm.push(Instruction::Return);
+    let assembled = asm.assemble().unwrap();
+    let instructions = decode_code(&assembled.code).unwrap();
+    assert_eq!(
+        instructions[..4],
+        [
+            (0, Instruction::Iload0),
+            (1, Instruction::Ifeq(8)),
+            (4, Instruction::GotoW(40000 + 5 + 5)),
+            (9, Instruction::GotoW(40000 + 5)),
+        ]
+    );
+    assert_eq!(assembled.offset(end), Some(40000 + 14));
+
+    let mut asm = Assembler::new();
+    let nowhere = asm.new_label();
+    asm.branch(Instruction::Goto, nowhere);
+    assert!(asm.assemble().is_err());
+}
+
+#[test]
+fn verify_stack_maps() {
+    let classes: [&[u8]; 4] = [
+        include_bytes!("../testdata/Instructions.class"),
+        include_bytes!("../testdata/Attributes.class"),
+        include_bytes!("../testdata/Jcov.class"),
+        include_bytes!("../testdata/Point.class"),
+    ];
+    for bytes in classes {
+        parse_class_file(bytes)
+            .unwrap()
+            .verify_stack_maps()
+            .unwrap();
+    }
+
+    fn code_of<'a>(class: &'a mut ClassFile, name: &str) -> &'a mut AttributeInfoInner {
+        let cp = &class.constant_pool;
+        let method = class
+            .methods
+            .iter_mut()
+            .find(|method| method.name_index.get(cp) == name)
+            .unwrap();
+        &mut method.attributes[0].inner
+    }
+    fn entries(code: &mut AttributeInfoInner) -> &mut Vec<StackMapFrame> {
+        match code {
+            AttributeInfoInner::Code { attributes, .. } => attributes
+                .iter_mut()
+                .find_map(|attr| match &mut attr.inner {
+                    AttributeInfoInner::StackMapTable { entries, .. } => Some(entries),
+                    _ => None,
+                })
+                .unwrap(),
+            _ => panic!("Not a Code attribute"),
+        }
+    }
+    let verify = |change: &dyn Fn(&mut ClassFile)| {
+        let mut class = parse_class_file(include_bytes!("../testdata/Attributes.class")).unwrap();
+        change(&mut class);
+        let err = class.verify_stack_maps().unwrap_err();
+        assert_eq!(err.method, "parse(Ljava/lang/String;)I");
+        err
+    };
+
+    // the handler of the NumberFormatException at offset 5
+    let err = verify(&|class| {
+        let name_index = class.methods[0].name_index.inner();
+        match &mut entries(code_of(class, "parse"))[0] {
+            StackMapFrame::SameLocals1StackItemFrame {
+                stack: VerificationTypeInfo::Object { cpool_index, .. },
+                ..
+            } => *cpool_index = name_index.into(),
+            frame => panic!("Unexpected frame {:?}", frame),
+        }
+    });
+    assert_eq!((err.frame, err.offset), (Some(0), Some(5)));
+    assert!(err.message.contains("not a Class"));
+
+    let err = verify(&|class| {
+        entries(code_of(class, "parse"))[0] = StackMapFrame::SameLocals1StackItemFrame {
+            frame_type: 64 + 2,
+            stack: VerificationTypeInfo::Integer { tag: 1 },
+        }
+    });
+    assert_eq!((err.frame, err.offset), (Some(0), Some(2)));
+    assert_eq!(err.message, "Not at the start of an instruction");
+
+    let err = verify(&|class| {
+        if let AttributeInfoInner::Code { max_stack, .. } = code_of(class, "parse") {
+            *max_stack = 0;
+        }
+    });
+    assert_eq!(err.message, "1 stack slots, but max_stack is 0");
+
+    let err = verify(&|class| {
+        entries(code_of(class, "parse"))[0] = StackMapFrame::ChopFrame {
+            frame_type: 248,
+            offset_delta: 5,
+        }
+    });
+    assert_eq!(err.message, "Chops 3 locals, but there are only 2");
+
+    let err = verify(&|class| {
+        entries(code_of(class, "parse"))[0] = StackMapFrame::FullFrame {
+            frame_type: 255,
+            offset_delta: 5,
+            locals: Vec::new(),
+            stack: vec![VerificationTypeInfo::Uninitialized { tag: 8, offset: 0 }],
+        }
+    });
+    assert!(err.message.contains("not a new instruction"));
+
+    let err = verify(&|class| {
+        entries(code_of(class, "parse"))[0] = StackMapFrame::SameFrame { frame_type: 100 }
+    });
+    assert_eq!((err.frame, err.offset), (Some(0), None));
+}
+
+#[test]
+fn synthesized_classes() {
+    let class = parse_class_file(&fixture::empty_class()).unwrap();
+    let cp = &class.constant_pool;
+    assert_eq!(class.major_version, 52);
+    assert_eq!(class.this_class.get(cp).name_index.get(cp), "Fixture");
+    assert_eq!(
+        class.super_class.maybe_get(cp).unwrap().name_index.get(cp),
+        "java/lang/Object"
+    );
+    assert!(class.methods.is_empty() && class.attributes.is_empty());
+
+    // iconst_1, pop, return
+    let class = parse_class_file(&fixture::class_with_method(&[0x04, 0x57, 0xb1])).unwrap();
+    let method = class.find_method("run", Some("()V")).unwrap();
+    match &method.attributes[0].inner {
+        AttributeInfoInner::Code { code, .. } => assert_eq!(
+            decode_code(code).unwrap(),
+            vec![
+                (0, Instruction::Iconst1),
+                (1, Instruction::Pop),
+                (2, Instruction::Return)
+            ]
+        ),
+        _ => panic!("Expected Code attribute"),
+    }
+    assert!(parse_class_file(&fixture::class_with_method(&[])).is_ok());
+    // the code is only decoded when it is used
+    assert!(parse_class_file(&fixture::class_with_method(&[0xFF])).is_ok());
+}
+
+#[test]
+fn synthesized_attributes() {
+    let class = parse_class_file(&fixture::class_with_attributes(&[
+        ("Custom", &[1, 2, 3]),
+        ("Deprecated", &[]),
+    ]))
+    .unwrap();
+    assert!(matches!(
+        &class.attributes[0].inner,
+        AttributeInfoInner::Unknown { attribute_content } if attribute_content[..] == [1, 2, 3]
+    ));
+    assert_eq!(class.attributes[1].inner, AttributeInfoInner::Deprecated);
+
+    // the index of a SourceFile is missing a byte
+    let err =
+        parse_class_file(&fixture::class_with_attributes(&[("SourceFile", &[0])])).unwrap_err();
+    assert_eq!(err.attribute(), Some("SourceFile"));
+
+    let mut fixture = Fixture::new("Fixture");
+    let source = fixture.utf8("Fixture.java");
+    let class = parse_class_file(
+        &fixture
+            .attribute("SourceFile", &source.to_be_bytes())
+            .bytes(),
+    )
+    .unwrap();
+    assert_eq!(
+        class.attributes[0].inner,
+        AttributeInfoInner::SourceFile {
+            sourcefile_index: source.into()
+        }
+    );
+}
+
+/// Every class file in the test data of the workspace is written back byte for byte
+#[test]
+fn corpus_round_trip() {
+    let canonical = WriteOptions {
+        canonical: true,
+        ..WriteOptions::default()
+    };
+    let mut count = 0;
+    for dir in ["testdata", "../cs_vm/testdata", "../testdata"] {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "class") {
+                let bytes = std::fs::read(&path).unwrap();
+                let parsed = parse_class_file(&bytes).unwrap();
+                assert!(
+                    write_class_file(&parsed).unwrap() == bytes,
+                    "{} changed when it was written",
+                    path.display()
+                );
+                let written = write_class_file_with(&parsed, &canonical).unwrap();
+                let reparsed = parse_class_file(&written).unwrap();
+                assert_eq!(diff_classes(&parsed, &reparsed), [], "{}", path.display());
+                assert!(
+                    write_class_file_with(&reparsed, &canonical).unwrap() == written,
+                    "{} is written differently in canonical mode once it is canonical",
+                    path.display()
+                );
+                count += 1;
+            }
+        }
+    }
+    assert!(count > 20);
+}
+
+/// A constant that is added to the pool of a generated class
+#[derive(Debug, Clone)]
+enum GeneratedConstant {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    MethodRef(String, String, String),
+    MethodType(String),
+}
+
+fn class_name() -> impl Strategy<Value = String> {
+    "[a-z]{1,6}(/[A-Z][A-Za-z0-9_$]{0,8}){1,2}"
+}
+
+fn field_descriptor() -> impl Strategy<Value = String> {
+    "\\[{0,2}([BCDFIJSZ]|L[a-z]{1,4}/[A-Z][a-z]{0,6};)"
+}
+
+fn method_descriptor() -> impl Strategy<Value = String> {
+    (
+        vec(field_descriptor(), 0..4),
+        prop_oneof![Just("V".to_owned()), field_descriptor()],
+    )
+        .prop_map(|(params, ret)| format!("({}){}", params.concat(), ret))
+}
+
+fn constant() -> impl Strategy<Value = GeneratedConstant> {
+    prop_oneof![
+        any::<i32>().prop_map(GeneratedConstant::Integer),
+        any::<f32>().prop_map(GeneratedConstant::Float),
+        any::<i64>().prop_map(GeneratedConstant::Long),
+        any::<f64>().prop_map(GeneratedConstant::Double),
+        // any text, including the NUL and supplementary characters modified UTF-8 encodes differently
+        any::<String>().prop_map(GeneratedConstant::String),
+        (class_name(), "[a-z]{1,8}", method_descriptor())
+            .prop_map(|(class, name, desc)| GeneratedConstant::MethodRef(class, name, desc)),
+        method_descriptor().prop_map(GeneratedConstant::MethodType),
+    ]
+}
+
+fn exception_handler() -> impl Strategy<Value = AttributeCodeException> {
+    (any::<u2>(), any::<u2>(), any::<u2>()).prop_map(|(start_pc, end_pc, handler_pc)| {
+        AttributeCodeException {
+            start_pc,
+            end_pc,
+            handler_pc,
+            catch_type: 0,
+        }
+    })
+}
+
+fn method_code() -> impl Strategy<Value = MethodCode> {
+    (
+        any::<u2>(),
+        any::<u2>(),
+        // the code is not decoded while parsing, so it doesn't have to be valid
+        vec(any::<u1>(), 1..64),
+        vec(exception_handler(), 0..3),
+    )
+        .prop_map(
+            |(max_stack, max_locals, code, exception_table)| MethodCode {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+            },
+        )
+}
+
+/// Classes built with `ClassFileBuilder`, so all constant pool indices are valid
+fn class_file() -> impl Strategy<Value = ClassFile> {
+    let members = (
+        vec((any::<u2>(), "[a-z]{1,8}", field_descriptor()), 0..4),
+        vec(
+            (
+                any::<u2>(),
+                "[a-z]{1,8}",
+                method_descriptor(),
+                option::of(method_code()),
+            ),
+            0..4,
+        ),
+    );
+    (
+        (45..=MAX_MAJOR_VERSION, any::<u2>()),
+        class_name(),
+        option::of(class_name()),
+        vec(class_name(), 0..3),
+        members,
+        vec(constant(), 0..8),
+        option::of("[A-Za-z]{1,8}\\.java"),
+    )
+        .prop_map(
+            |(
+                (major, flags),
+                name,
+                super_class,
+                interfaces,
+                (fields, methods),
+                constants,
+                source_file,
+            )| {
+                let mut builder = ClassFileBuilder::new(&name)
+                    .version(major, 0)
+                    .access_flags(flags);
+                builder = match super_class {
+                    Some(super_class) => builder.super_class(&super_class),
+                    None => builder.no_super_class(),
+                };
+                for interface in &interfaces {
+                    builder = builder.add_interface(interface);
+                }
+                for (flags, name, descriptor) in &fields {
+                    builder = builder.add_field(*flags, name, descriptor);
+                }
+                for (flags, name, descriptor, code) in methods {
+                    builder = builder.add_method(flags, &name, &descriptor, code);
+                }
+                let pool = builder.constant_pool();
+                for constant in constants {
+                    let _ = match constant {
+                        GeneratedConstant::Integer(value) => pool.integer(value).inner(),
+                        GeneratedConstant::Float(value) => pool.float(value).inner(),
+                        GeneratedConstant::Long(value) => pool.long(value).inner(),
+                        GeneratedConstant::Double(value) => pool.double(value).inner(),
+                        GeneratedConstant::String(value) => pool.string(&value).inner(),
+                        GeneratedConstant::MethodRef(class, name, descriptor) => {
+                            pool.method_ref(&class, &name, &descriptor).inner()
+                        }
+                        GeneratedConstant::MethodType(descriptor) => {
+                            pool.method_type(&descriptor).inner()
+                        }
+                    };
+                }
+                if let Some(source_file) = source_file {
+                    builder = builder.source_file(&source_file);
+                }
+                builder.build()
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn generated_classes_round_trip(class in class_file()) {
+        let written = write_class_file(&class).unwrap();
+        prop_assert_eq!(parse_class_file(&written).unwrap(), class);
+    }
+
+    #[test]
+    fn mutated_corpus_round_trip(index in 0..13_usize, offset in any::<usize>(), byte in any::<u1>()) {
+        let class = [
+            &include_bytes!("../testdata/Test.class")[..],
+            &include_bytes!("../testdata/Test2.class")[..],
+            &include_bytes!("../testdata/Jcov.class")[..],
+            &include_bytes!("../testdata/Attributes.class")[..],
+            &include_bytes!("../testdata/Attributes$Marker.class")[..],
+            &include_bytes!("../testdata/TypeAnnotations.class")[..],
+            &include_bytes!("../testdata/Nesting.class")[..],
+            &include_bytes!("../testdata/Nesting$1.class")[..],
+            &include_bytes!("../testdata/Point.class")[..],
+            &include_bytes!("../testdata/Condy.class")[..],
+            &include_bytes!("../testdata/module-info.class")[..],
+            &include_bytes!("../testdata/ModifiedUtf8.class")[..],
+            &include_bytes!("../testdata/InterfaceLambda.class")[..],
+        ][index];
+        let mut mutated = class.to_vec();
+        mutated[offset % class.len()] = byte;
+        // the change may make the class invalid, but if it parses it has to survive being written
+        if let Ok(parsed) = parse_class_file(&mutated) {
+            let written = write_class_file(&parsed).unwrap();
+            let reparsed = parse_class_file(&written).unwrap();
+            prop_assert_eq!(write_class_file(&reparsed).unwrap(), written);
+        }
+    }
+}
+
+#[test]
+fn class_diff() {
+    let class = ClassFileBuilder::new("Diffed")
+        .add_field(FieldAccessFlag::PRIVATE, "count", "I")
+        .add_default_constructor()
+        .build();
+    // the same class with the constants in a different order
+    let mut builder = ClassFileBuilder::new("Diffed");
+    builder
+        .constant_pool()
+        .method_ref("java/lang/Object", "<init>", "()V");
+    let reordered = builder
+        .add_default_constructor()
+        .add_field(FieldAccessFlag::PRIVATE, "count", "I")
+        .build();
+    assert_ne!(class.constant_pool, reordered.constant_pool);
+    assert_eq!(diff_classes(&class, &reordered), []);
+
+    let mut changed = ClassFileBuilder::new("Diffed")
+        .version(61, 0)
+        .add_interface("java/lang/Runnable")
+        .add_field(
+            FieldAccessFlag::PRIVATE | FieldAccessFlag::FINAL,
+            "count",
+            "I",
+        )
+        .add_field(FieldAccessFlag::PUBLIC, "name", "Ljava/lang/String;")
+        .source_file("Diffed.java");
+    let value = changed.constant_pool().integer(3);
+    let attribute_name_index = changed.constant_pool().utf8("ConstantValue");
+    let mut changed = changed.build();
+    changed.fields[0].attributes.push(AttributeInfo {
+        attribute_name_index,
+        attribute_length: 2,
+        inner: AttributeInfoInner::ConstantValue {
+            constantvalue_index: value.inner().into(),
+        },
+        original: None,
+    });
+    let differences = diff_classes(&class, &changed)
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        differences,
+        [
+            "~ class: version 52.0 -> 61.0",
+            "+ class: interface java/lang/Runnable",
+            "+ class: attribute SourceFile",
+            "~ field count:I: flags ACC_PRIVATE -> ACC_PRIVATE, ACC_FINAL",
+            "~ field count:I: constant value none -> 3",
+            "+ field name:Ljava/lang/String;",
+            "- method <init>()V",
+        ]
+    );
+    assert_eq!(
+        diff_classes(&changed, &class)[0].change,
+        Change::Version {
+            old: (61, 0),
+            new: (52, 0)
+        }
+    );
+}
+
+#[test]
+fn code_diff() {
+    let class = |value: i32| {
+        let mut builder = ClassFileBuilder::new("Code");
+        let constant = builder.constant_pool().integer(value).inner();
+        let [high, low] = constant.to_be_bytes();
+        let code = MethodCode {
+            max_stack: 1,
+            max_locals: 0,
+            // ldc_w, ireturn
+            code: vec![0x13, high, low, 0xac],
+            exception_table: Vec::new(),
+        };
+        builder
+            .add_method(MethodAccessFlag::STATIC, "value", "()I", Some(code))
+            .build()
+    };
+    assert_eq!(diff_classes(&class(100_000), &class(100_000)), []);
+    assert_eq!(
+        diff_classes(&class(100_000), &class(200_000)),
+        [Difference {
+            member: Member::Method {
+                name: "value".to_owned(),
+                descriptor: "()I".to_owned()
+            },
+            change: Change::AttributeChanged("Code".to_owned()),
+        }]
+    );
+
+    let old = parse_class_file(include_bytes!("../testdata/Test.class")).unwrap();
+    let new = parse_class_file(include_bytes!("../testdata/Test2.class")).unwrap();
+    assert!(diff_classes(&old, &old).is_empty());
+    assert!(diff_classes(&old, &new).contains(&Difference {
+        member: Member::Method {
+            name: "print".to_owned(),
+            descriptor: "(I)V".to_owned()
+        },
+        change: Change::Added,
+    }));
+}
+
+/// Counts the allocations of each thread, for `parse_allocations`
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        std::alloc::System.realloc(ptr, layout, size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// The strings of the constant pool and the code share the buffer of the class, so parsing only
+/// allocates for the vectors of the model, see the `memory` module
+#[test]
+fn parse_allocations() {
+    let allocations = |constants: usize, code_length: usize| {
+        let mut builder = ClassFileBuilder::new("Allocations");
+        for i in 0..constants {
+            builder.constant_pool().utf8(&format!("constant {}", i));
+        }
+        let code = MethodCode {
+            max_stack: 0,
+            max_locals: 0,
+            code: [vec![0; code_length], vec![0xb1]].concat(),
+            exception_table: Vec::new(),
+        };
+        let class = builder
+            .add_method(MethodAccessFlag::STATIC, "run", "()V", Some(code))
+            .build();
+        let bytes = write_class_file(&class).unwrap();
+        let before = ALLOCATIONS.with(|count| count.get());
+        let parsed = parse_class_file(&bytes).unwrap();
+        let allocations = ALLOCATIONS.with(|count| count.get()) - before;
+        assert_eq!(parsed, class);
+        allocations
+    };
+    let few = allocations(0, 0);
+    assert_eq!(allocations(1000, 0), few);
+    assert_eq!(allocations(0, 10000), few);
+}
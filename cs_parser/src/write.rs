@@ -0,0 +1,896 @@
+//!
+//! Serializing a `ClassFile` back into the class file format
+//!
+//! Counts and lengths are computed from the contents, the stored `attribute_length` and
+//! `num_*` fields are ignored
+//!
+
+use crate::*;
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub struct WriteErr(pub String);
+
+impl Display for WriteErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not write class file: {}", self.0)
+    }
+}
+
+impl std::error::Error for WriteErr {}
+
+type WriteResult = std::result::Result<(), WriteErr>;
+
+/// Options for `write_class_file_with`
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Writes the constant pool sorted by the content of the entries and without duplicates, and
+    /// the attributes of the class, its members and their code sorted by name. Classes that only
+    /// differ in the order of their constants or attributes are written byte for byte the same.
+    /// Constants loaded by `ldc` come first, so their indices still fit into a byte.
+    /// Unknown attributes can contain indices that can't be changed, classes with them keep the
+    /// order of their constant pool
+    pub canonical: bool,
+    /// Writes attributes that were not changed since parsing with their original bytes, instead of
+    /// serializing them again. Their content is kept exactly, including anything the parser ignores.
+    /// Has no effect in canonical mode
+    pub preserve_original: bool,
+}
+
+/// Serializes the class into the class file format
+pub fn write_class_file(class: &ClassFile) -> std::result::Result<Vec<u1>, WriteErr> {
+    write_class_file_with(class, &WriteOptions::default())
+}
+
+/// Serializes the class into the class file format.
+/// The output only depends on the class and the options, writing the same class twice gives the same bytes
+pub fn write_class_file_with(
+    class: &ClassFile,
+    options: &WriteOptions,
+) -> std::result::Result<Vec<u1>, WriteErr> {
+    let canonical_pool = match options.canonical && !has_unknown_attributes(class) {
+        true => Some(CanonicalPool::new(class, options)?),
+        false => None,
+    };
+    let mut out = Output {
+        buf: Vec::with_capacity(1024),
+        pool: &class.constant_pool,
+        options,
+        indices: canonical_pool
+            .as_ref()
+            .map_or(Indices::Original, Indices::Canonical),
+    };
+    class.write(&mut out)?;
+    Ok(out.buf)
+}
+
+/// Serializes the content of an attribute with the constants it references in place of their
+/// indices, and the code of a `Code` attribute disassembled. The result is not a valid attribute,
+/// it is only used to compare attributes of classes with different constant pools
+pub(crate) fn write_resolved_attribute(
+    attribute: &AttributeInfoInner,
+    pool: &ConstantPool,
+) -> std::result::Result<Vec<u1>, WriteErr> {
+    let mut out = Output {
+        buf: Vec::new(),
+        pool,
+        options: &WriteOptions {
+            canonical: true,
+            preserve_original: false,
+        },
+        indices: Indices::Resolved,
+    };
+    attribute.write(&mut out)?;
+    Ok(out.buf)
+}
+
+/// The constant pool of canonical mode
+struct CanonicalPool {
+    /// The old indices of the entries, in their new order
+    order: Vec<u2>,
+    /// The new index of every old index, duplicates have the index of the entry that is kept
+    indices: Vec<u2>,
+    count: u2,
+}
+
+struct Output<'a> {
+    buf: Vec<u1>,
+    pool: &'a ConstantPool,
+    options: &'a WriteOptions,
+    indices: Indices<'a>,
+}
+
+/// How the indices of constants are written
+#[derive(Clone, Copy)]
+enum Indices<'a> {
+    /// As they are stored
+    Original,
+    /// With their index in the canonical constant pool
+    Canonical(&'a CanonicalPool),
+    /// The constants instead of their indices, see `write_resolved_attribute`
+    Resolved,
+    /// The content of the constants, nested to the depth, as the key the canonical pool is sorted by
+    Keys(u8),
+}
+
+impl CanonicalPool {
+    fn new(class: &ClassFile, options: &WriteOptions) -> std::result::Result<Self, WriteErr> {
+        let pool = &class.constant_pool;
+        let keys = pool
+            .iter_with_indices()
+            .map(|(index, info)| {
+                let mut out = Output {
+                    buf: Vec::new(),
+                    pool,
+                    options,
+                    indices: Indices::Keys(0),
+                };
+                info.write(&mut out)?;
+                Ok((index, out.buf))
+            })
+            .collect::<std::result::Result<Vec<_>, WriteErr>>()?;
+        let loaded = ldc_operands(class)?
+            .into_iter()
+            .filter_map(|index| keys.iter().find(|(i, _)| *i == index))
+            .map(|(_, key)| key)
+            .collect::<HashSet<_>>();
+
+        let mut sorted = keys
+            .iter()
+            .map(|(index, key)| (!loaded.contains(key), key, *index))
+            .collect::<Vec<_>>();
+        sorted.sort();
+
+        let mut order = Vec::new();
+        let mut indices = vec![0; pool.count()];
+        let (mut next, mut current) = (1, 0);
+        let mut previous = None;
+        for (_, key, index) in sorted {
+            if previous != Some(key) {
+                previous = Some(key);
+                order.push(index);
+                current = next;
+                next += match pool.entry(index).map(|info| &info.inner) {
+                    Some(CpInfoInner::Long(_) | CpInfoInner::Double(_)) => 2,
+                    _ => 1,
+                };
+            }
+            // there are at most as many entries as before, so the new index fits if the old one did
+            indices[usize::from(index)] = current as u2;
+        }
+        Ok(Self {
+            order,
+            indices,
+            count: len(next)?,
+        })
+    }
+
+    /// The new index, indices that are out of bounds are kept
+    fn index(&self, index: u2) -> u2 {
+        self.indices
+            .get(usize::from(index))
+            .copied()
+            .unwrap_or(index)
+    }
+
+    /// The code with the constant pool indices of the instructions replaced by their new ones
+    fn remap_code(&self, code: &[u1]) -> std::result::Result<Vec<u1>, WriteErr> {
+        let mut remapped = code.to_vec();
+        for (offset, instruction) in decode_code(code).map_err(invalid_code)? {
+            let Some(index) = instruction.constant_index() else {
+                continue;
+            };
+            let (offset, index) = (offset as usize + 1, self.index(index));
+            match instruction {
+                Instruction::Ldc(_) => {
+                    remapped[offset] = u1::try_from(index).map_err(|_| {
+                        WriteErr(format!(
+                            "ldc of the constant {} does not fit into a u1",
+                            index
+                        ))
+                    })?
+                }
+                _ => remapped[offset..offset + 2].copy_from_slice(&index.to_be_bytes()),
+            }
+        }
+        Ok(remapped)
+    }
+}
+
+fn invalid_code(err: ParseErr) -> WriteErr {
+    WriteErr(format!("Invalid code: {}", err))
+}
+
+/// The constants that are loaded by `ldc` in any method, they must keep an index below 256
+fn ldc_operands(class: &ClassFile) -> std::result::Result<Vec<u2>, WriteErr> {
+    let mut operands = Vec::new();
+    for attribute in class.methods.iter().flat_map(|method| &method.attributes) {
+        if let AttributeInfoInner::Code { code, .. } = &attribute.inner {
+            for (_, instruction) in decode_code(code).map_err(invalid_code)? {
+                if let Instruction::Ldc(index) = instruction {
+                    operands.push(index.into());
+                }
+            }
+        }
+    }
+    Ok(operands)
+}
+
+/// Whether the class, its members, their code or its record components have an unknown attribute
+fn has_unknown_attributes(class: &ClassFile) -> bool {
+    fn any_unknown(attributes: &[AttributeInfo]) -> bool {
+        attributes.iter().any(|attribute| match &attribute.inner {
+            AttributeInfoInner::Unknown { .. } => true,
+            AttributeInfoInner::Code { attributes, .. } => any_unknown(attributes),
+            AttributeInfoInner::Record { components } => components
+                .iter()
+                .any(|component| any_unknown(&component.attributes)),
+            _ => false,
+        })
+    }
+    any_unknown(&class.attributes)
+        || class
+            .fields
+            .iter()
+            .any(|field| any_unknown(&field.attributes))
+        || class
+            .methods
+            .iter()
+            .any(|method| any_unknown(&method.attributes))
+}
+
+impl Output<'_> {
+    fn u1(&mut self, value: u1) {
+        self.buf.push(value);
+    }
+
+    fn u2(&mut self, value: u2) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u4(&mut self, value: u4) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u1]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn cp<T>(&mut self, index: FromPool<T>) {
+        self.constant(index.inner());
+    }
+
+    /// Writes the index of a constant, see `Indices`
+    fn constant(&mut self, index: u2) {
+        match self.indices {
+            Indices::Original => self.u2(index),
+            Indices::Canonical(pool) => self.u2(pool.index(index)),
+            Indices::Resolved => {
+                let constant = disassemble::describe_constant(index, self.pool);
+                self.u4(constant.len() as u4);
+                self.bytes(constant.as_bytes());
+            }
+            // entries don't reference each other in cycles in a valid class, the deepest is a
+            // method handle with the method, its class and its name and type
+            Indices::Keys(depth) => match self.pool.entry(index) {
+                Some(info) if depth < 8 => {
+                    self.indices = Indices::Keys(depth + 1);
+                    // the entry itself fails to be written if it is too long
+                    let _ = info.write(self);
+                    self.indices = Indices::Keys(depth);
+                }
+                // no entry has the tag 0
+                _ => self.u1(0),
+            },
+        }
+    }
+
+    /// Writes the length as a `u2` followed by all values
+    fn vec<T: Serialize>(&mut self, values: &[T]) -> WriteResult {
+        self.u2(len(values.len())?);
+        self.values(values)
+    }
+
+    /// Writes the length as a `u1` followed by all values
+    fn short_vec<T: Serialize>(&mut self, values: &[T]) -> WriteResult {
+        let len = u1::try_from(values.len())
+            .map_err(|_| WriteErr(format!("{} values do not fit into a u1", values.len())))?;
+        self.u1(len);
+        self.values(values)
+    }
+
+    fn values<T: Serialize>(&mut self, values: &[T]) -> WriteResult {
+        values.iter().try_for_each(|value| value.write(self))
+    }
+
+    /// Writes the attributes like `vec`, sorted by name in canonical mode
+    fn attributes(&mut self, attributes: &[AttributeInfo]) -> WriteResult {
+        if !self.options.canonical {
+            return self.vec(attributes);
+        }
+        let mut sorted = attributes.iter().collect::<Vec<_>>();
+        let pool = self.pool;
+        // an invalid name sorts first, it will fail to parse anyways
+        sorted.sort_by_key(|attr| pool.utf8(attr.attribute_name_index.inner()));
+        self.u2(len(sorted.len())?);
+        sorted.into_iter().try_for_each(|attr| attr.write(self))
+    }
+}
+
+fn len(len: usize) -> std::result::Result<u2, WriteErr> {
+    u2::try_from(len).map_err(|_| WriteErr(format!("{} values do not fit into a u2", len)))
+}
+
+trait Serialize {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult;
+}
+
+impl<T> Serialize for FromPool<T> {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.constant(self.inner());
+        Ok(())
+    }
+}
+
+impl Serialize for u2 {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(*self);
+        Ok(())
+    }
+}
+
+impl Serialize for ClassFile {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u4(self.magic);
+        out.u2(self.minor_version);
+        out.u2(self.major_version);
+        match out.indices {
+            Indices::Canonical(pool) => {
+                out.u2(pool.count);
+                for &index in &pool.order {
+                    self.constant_pool
+                        .slot(index)
+                        .map_or(Ok(()), |info| info.write(out))?;
+                }
+            }
+            _ => {
+                out.u2(len(self.constant_pool.count())?);
+                out.values(&self.constant_pool)?;
+            }
+        }
+        out.u2(self.access_flags.bits());
+        out.cp(self.this_class);
+        out.cp(self.super_class);
+        out.vec(&self.interfaces)?;
+        out.vec(&self.fields)?;
+        out.vec(&self.methods)?;
+        out.attributes(&self.attributes)
+    }
+}
+
+impl Serialize for CpInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        if let CpInfoInner::Unusable = self.inner {
+            // only the index is taken up, nothing is written
+            return Ok(());
+        }
+        out.u1(self.tag);
+        match &self.inner {
+            CpInfoInner::Unusable => {}
+            CpInfoInner::Class(class) => out.cp(class.name_index),
+            CpInfoInner::Fieldref(cp_info::Fieldref {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::MethodRef(cp_info::MethodRef {
+                class_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::InterfaceMethodref(cp_info::InterfaceMethodref {
+                class_index,
+                name_and_type_index,
+            }) => {
+                out.cp(*class_index);
+                out.cp(*name_and_type_index);
+            }
+            CpInfoInner::String(string) => out.cp(string.string_index),
+            CpInfoInner::Integer(cp_info::Integer { bytes })
+            | CpInfoInner::Float(cp_info::Float { bytes }) => out.u4(*bytes),
+            CpInfoInner::Long(cp_info::Long {
+                high_bytes,
+                low_bytes,
+            })
+            | CpInfoInner::Double(cp_info::Double {
+                high_bytes,
+                low_bytes,
+            }) => {
+                out.u4(*high_bytes);
+                out.u4(*low_bytes);
+            }
+            CpInfoInner::NameAndType(name_and_type) => {
+                out.cp(name_and_type.name_index);
+                out.cp(name_and_type.descriptor_index);
+            }
+            CpInfoInner::Utf8(utf8) => {
+                let bytes = utf8.bytes.to_mutf8();
+                out.u2(len(bytes.len())?);
+                out.bytes(&bytes);
+            }
+            CpInfoInner::MethodHandle(handle) => {
+                out.u1(handle.reference_kind);
+                match handle.reference_index {
+                    cp_info::MethodHandleIndex::Field(index) => out.cp(index),
+                    cp_info::MethodHandleIndex::Method(index) => out.cp(index),
+                    cp_info::MethodHandleIndex::MethodOrInterface(index) => out.cp(index),
+                    cp_info::MethodHandleIndex::Interface(index) => out.cp(index),
+                }
+            }
+            CpInfoInner::MethodType(method_type) => out.cp(method_type.descriptor_index),
+            CpInfoInner::Dynamic(cp_info::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            })
+            | CpInfoInner::InvokeDynamic(cp_info::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }) => {
+                out.u2(*bootstrap_method_attr_index);
+                out.cp(*name_and_type_index);
+            }
+            CpInfoInner::Module(module) => out.cp(module.name_index),
+            CpInfoInner::Package(package) => out.cp(package.name_index),
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for FieldInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.access_flags.bits());
+        out.cp(self.name_index);
+        out.cp(self.descriptor_index);
+        out.attributes(&self.attributes)
+    }
+}
+
+impl Serialize for MethodInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.access_flags.bits());
+        out.cp(self.name_index);
+        out.cp(self.descriptor_index);
+        out.attributes(&self.attributes)
+    }
+}
+
+impl Serialize for AttributeInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.attribute_name_index);
+        if let Some(original) = self.unchanged_original(out) {
+            let length = u4::try_from(original.len())
+                .map_err(|_| WriteErr("Attribute is longer than u4::MAX".to_string()))?;
+            out.u4(length);
+            out.bytes(original);
+            return Ok(());
+        }
+        // the length is patched in once the content is written
+        let length_offset = out.buf.len();
+        out.u4(0);
+        self.inner.write(out)?;
+        let length = u4::try_from(out.buf.len() - length_offset - 4)
+            .map_err(|_| WriteErr("Attribute is longer than u4::MAX".to_string()))?;
+        out.buf[length_offset..length_offset + 4].copy_from_slice(&length.to_be_bytes());
+        Ok(())
+    }
+}
+
+impl AttributeInfo {
+    /// The original content, if it should be preserved and still parses into the same attribute
+    fn unchanged_original(&self, out: &Output<'_>) -> Option<&Bytes> {
+        if !out.options.preserve_original || out.options.canonical {
+            return None;
+        }
+        let original = self.original.as_ref()?;
+        let mut reparsed = AttributeInfo {
+            attribute_name_index: self.attribute_name_index,
+            attribute_length: self.attribute_length,
+            inner: AttributeInfoInner::Unknown {
+                attribute_content: original.clone(),
+            },
+            original: None,
+        };
+        match reparsed.resolve_attribute(out.pool, &ParseLimits::default(), &mut None) {
+            Ok(()) if reparsed.inner == self.inner => Some(original),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for AttributeInfoInner {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        match self {
+            Self::__Empty => return Err(WriteErr("Attribute was not resolved".to_string())),
+            Self::Unknown { attribute_content } => out.bytes(attribute_content),
+            Self::ConstantValue {
+                constantvalue_index,
+            } => out.cp(*constantvalue_index),
+            Self::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+            } => {
+                out.u2(*max_stack);
+                out.u2(*max_locals);
+                out.u4(u4::try_from(code.len())
+                    .map_err(|_| WriteErr("Code is longer than u4::MAX".to_string()))?);
+                match out.indices {
+                    Indices::Canonical(pool) => out.bytes(&pool.remap_code(code)?),
+                    Indices::Resolved => match disassemble(code, out.pool) {
+                        Ok(listing) => out.bytes(listing.as_bytes()),
+                        Err(_) => out.bytes(code),
+                    },
+                    _ => out.bytes(code),
+                }
+                out.vec(exception_table)?;
+                out.attributes(attributes)?;
+            }
+            Self::StackMapTable { entries, .. } => out.vec(entries)?,
+            Self::Exceptions {
+                exception_index_table,
+            } => {
+                out.u2(len(exception_index_table.len())?);
+                for &index in exception_index_table {
+                    out.constant(index);
+                }
+            }
+            Self::InnerClasses { classes } => out.vec(classes)?,
+            Self::EnclosingMethod {
+                class_index,
+                method_index,
+            } => {
+                out.cp(*class_index);
+                out.cp(*method_index);
+            }
+            Self::NestHost { host_class_index } => out.cp(*host_class_index),
+            Self::NestMembers { classes } => out.vec(classes)?,
+            Self::Synthetic | Self::Deprecated => {}
+            Self::Signature { signature_index } => out.cp(*signature_index),
+            Self::SourceFile { sourcefile_index } => out.cp(*sourcefile_index),
+            Self::SourceDebugExtension { debug_extension } => out.bytes(debug_extension),
+            Self::LineNumberTable { line_number_table } => out.vec(line_number_table)?,
+            Self::LocalVariableTable {
+                local_variable_table,
+            }
+            | Self::LocalVariableTypeTable {
+                local_variable_table,
+            } => out.vec(local_variable_table)?,
+            Self::RuntimeVisibleAnnotations { annotations }
+            | Self::RuntimeInvisibleAnnotations { annotations } => out.vec(annotations)?,
+            Self::RuntimeVisibleParameterAnnotations {
+                parameter_annotations,
+            }
+            | Self::RuntimeInvisibleParameterAnnotations {
+                parameter_annotations,
+            } => out.short_vec(parameter_annotations)?,
+            Self::RuntimeVisibleTypeAnnotations { annotations }
+            | Self::RuntimeInvisibleTypeAnnotations { annotations } => out.vec(annotations)?,
+            Self::AnnotationDefault { default_value } => default_value.write(out)?,
+            Self::BootstrapMethods { bootstrap_methods } => out.vec(bootstrap_methods)?,
+            Self::Module(module) => module.write(out)?,
+            Self::CharacterRangeTable {
+                character_range_table,
+            } => out.vec(character_range_table)?,
+            Self::CompilationID {
+                compilation_id_index,
+            } => out.cp(*compilation_id_index),
+            Self::SourceID { source_id_index } => out.cp(*source_id_index),
+            Self::Record { components } => out.vec(components)?,
+            Self::MethodParameters { parameters } => out.short_vec(parameters)?,
+            Self::ModulePackages { package_index } => out.vec(package_index)?,
+            Self::ModuleMainClass { main_class_index } => out.cp(*main_class_index),
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeCodeException {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.start_pc);
+        out.u2(self.end_pc);
+        out.u2(self.handler_pc);
+        out.constant(self.catch_type);
+        Ok(())
+    }
+}
+
+impl Serialize for StackMapFrame {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        match self {
+            Self::SameFrame { frame_type } => out.u1(*frame_type),
+            Self::SameLocals1StackItemFrame { frame_type, stack } => {
+                out.u1(*frame_type);
+                stack.write(out)?;
+            }
+            Self::SameLocals1StackItemFrameExtended {
+                frame_type,
+                offset_delta,
+                stack,
+            } => {
+                out.u1(*frame_type);
+                out.u2(*offset_delta);
+                stack.write(out)?;
+            }
+            Self::ChopFrame {
+                frame_type,
+                offset_delta,
+            }
+            | Self::SameFrameExtended {
+                frame_type,
+                offset_delta,
+            } => {
+                out.u1(*frame_type);
+                out.u2(*offset_delta);
+            }
+            Self::AppendFrame {
+                offset_delta,
+                locals,
+                ..
+            } => {
+                // the number of locals is encoded in the frame type
+                let frame_type = match locals.len() {
+                    len @ 1..=3 => 251 + len as u1,
+                    len => return Err(WriteErr(format!("AppendFrame with {} locals", len))),
+                };
+                out.u1(frame_type);
+                out.u2(*offset_delta);
+                out.values(locals)?;
+            }
+            Self::FullFrame {
+                frame_type,
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                out.u1(*frame_type);
+                out.u2(*offset_delta);
+                out.vec(locals)?;
+                out.vec(stack)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for VerificationTypeInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        match self {
+            Self::Top { tag }
+            | Self::Integer { tag }
+            | Self::Float { tag }
+            | Self::Long { tag }
+            | Self::Double { tag }
+            | Self::Null { tag }
+            | Self::UninitializedThis { tag } => out.u1(*tag),
+            Self::Object { tag, cpool_index } => {
+                out.u1(*tag);
+                out.cp(*cpool_index);
+            }
+            Self::Uninitialized { tag, offset } => {
+                out.u1(*tag);
+                out.u2(*offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeInnerClass {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.inner_class_info_index);
+        out.cp(self.outer_class_info_index);
+        out.cp(self.inner_class_name_index);
+        out.u2(self.inner_class_access_flags.bits());
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeMethodParameter {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.name_index);
+        out.u2(self.access_flags.bits());
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeLineNumber {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.start_pc);
+        out.u2(self.line_number);
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeCharacterRange {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.start_pc);
+        out.u2(self.end_pc);
+        out.u4(self.character_range_start);
+        out.u4(self.character_range_end);
+        out.u2(self.flags);
+        Ok(())
+    }
+}
+
+impl Serialize for AttributeLocalVariableTable {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.start_pc);
+        out.u2(self.length);
+        out.cp(self.name_index);
+        out.cp(self.descriptor_or_signature_index);
+        out.u2(self.index);
+        Ok(())
+    }
+}
+
+impl Serialize for Annotation {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.type_index);
+        out.vec(&self.element_value_pairs)
+    }
+}
+
+impl Serialize for AnnotationElementValuePair {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.element_name_index);
+        self.element_name_name.write(out)
+    }
+}
+
+impl Serialize for AnnotationElementValue {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u1(self.tag);
+        match &self.value {
+            AnnotationElementValueValue::ConstValueIndex { index } => out.cp(*index),
+            AnnotationElementValueValue::EnumConstValue {
+                type_name_index,
+                const_name_index,
+            } => {
+                out.cp(*type_name_index);
+                out.cp(*const_name_index);
+            }
+            AnnotationElementValueValue::ClassInfoIndex { index } => out.cp(*index),
+            AnnotationElementValueValue::AnnotationValue { annotation } => annotation.write(out)?,
+            AnnotationElementValueValue::ArrayValue { values } => out.vec(values)?,
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for ParameterAnnotation {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.vec(&self.annotations)
+    }
+}
+
+impl Serialize for TypeAnnotation {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u1(self.target_type);
+        match &self.target_info {
+            TypeAnnotationTarget::TypeParameter {
+                type_parameter_index,
+            } => out.u1(*type_parameter_index),
+            TypeAnnotationTarget::Supertype { supertype_index } => out.u2(*supertype_index),
+            TypeAnnotationTarget::TypeParameterBound {
+                type_parameter_index,
+                bound_index,
+            } => {
+                out.u1(*type_parameter_index);
+                out.u1(*bound_index);
+            }
+            TypeAnnotationTarget::Empty => {}
+            TypeAnnotationTarget::FormalParameter {
+                formal_parameter_index,
+            } => out.u1(*formal_parameter_index),
+            TypeAnnotationTarget::Throws { throws_type_index } => out.u2(*throws_type_index),
+            TypeAnnotationTarget::Localvar { table } => out.vec(table)?,
+            TypeAnnotationTarget::Catch {
+                exception_table_index,
+            } => out.u2(*exception_table_index),
+            TypeAnnotationTarget::Offset { offset } => out.u2(*offset),
+            TypeAnnotationTarget::TypeArgument {
+                offset,
+                type_argument_index,
+            } => {
+                out.u2(*offset);
+                out.u1(*type_argument_index);
+            }
+        }
+        out.short_vec(&self.target_path.path)?;
+        out.cp(self.type_index);
+        out.vec(&self.element_value_pairs)
+    }
+}
+
+impl Serialize for TypeAnnotationLocalvar {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.u2(self.start_pc);
+        out.u2(self.length);
+        out.u2(self.index);
+        Ok(())
+    }
+}
+
+impl Serialize for TypePathEntry {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        let (kind, type_argument_index) = match self {
+            Self::Array => (0, 0),
+            Self::Nested => (1, 0),
+            Self::WildcardBound => (2, 0),
+            Self::TypeArgument(index) => (3, *index),
+        };
+        out.u1(kind);
+        out.u1(type_argument_index);
+        Ok(())
+    }
+}
+
+impl Serialize for RecordComponentInfo {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.name_index);
+        out.cp(self.descriptor_index);
+        out.attributes(&self.attributes)
+    }
+}
+
+impl Serialize for BootstrapMethod {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.bootstrap_method_ref);
+        out.vec(&self.bootstrap_arguments)
+    }
+}
+
+impl Serialize for Module {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.module_name_index);
+        out.u2(self.module_flags.bits());
+        out.cp(self.module_version_index);
+        out.vec(&self.requires)?;
+        out.vec(&self.exports)?;
+        out.vec(&self.opens)?;
+        out.vec(&self.uses_index)?;
+        out.vec(&self.provides)
+    }
+}
+
+impl Serialize for ModuleRequires {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.requires_index);
+        out.u2(self.requires_flags.bits());
+        out.cp(self.requires_version_index);
+        Ok(())
+    }
+}
+
+impl Serialize for ModuleExports {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.exports_index);
+        out.u2(self.exports_flags.bits());
+        out.vec(&self.exports_to_index)
+    }
+}
+
+impl Serialize for ModuleOpens {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.opens_index);
+        out.u2(self.opens_flags.bits());
+        out.vec(&self.opens_to_index)
+    }
+}
+
+impl Serialize for ModuleProvides {
+    fn write(&self, out: &mut Output<'_>) -> WriteResult {
+        out.cp(self.provides_index);
+        out.vec(&self.provides_with_index)
+    }
+}
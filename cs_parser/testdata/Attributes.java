@@ -0,0 +1,28 @@
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+@Attributes.Marker(value = 3, names = {"a", "b"})
+class Attributes {
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Marker {
+        int value();
+
+        String[] names() default {};
+    }
+
+    int sum(int[] values) {
+        int sum = 0;
+        for (int i = 0; i < values.length; i++) {
+            sum += values[i];
+        }
+        return sum;
+    }
+
+    int parse(String s) {
+        try {
+            return Integer.parseInt(s);
+        } catch (NumberFormatException e) {
+            return -1;
+        }
+    }
+}
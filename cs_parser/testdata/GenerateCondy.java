@@ -0,0 +1,111 @@
+import java.io.DataOutputStream;
+import java.io.FileOutputStream;
+import java.io.IOException;
+
+/**
+ * javac does not emit dynamic constants, so `Condy.class` is written by hand. `Condy.get()` loads
+ * `Integer.toString(Integer.MAX_VALUE)`, where the argument is a dynamic constant itself.
+ * Run with `java GenerateCondy.java`
+ */
+public class GenerateCondy {
+    public static void main(String[] args) throws IOException {
+        try (var out = new DataOutputStream(new FileOutputStream("Condy.class"))) {
+            out.writeInt(0xCAFEBABE);
+            out.writeShort(0);
+            out.writeShort(55);
+
+            out.writeShort(36);
+            utf8(out, "Condy"); // 1
+            ref(out, 7, 1); // 2
+            utf8(out, "java/lang/Object"); // 3
+            ref(out, 7, 3); // 4
+            utf8(out, "java/lang/invoke/ConstantBootstraps"); // 5
+            ref(out, 7, 5); // 6
+            utf8(out, "getStaticFinal"); // 7
+            utf8(out, "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/Class;)Ljava/lang/Object;"); // 8
+            refs(out, 12, 7, 8); // 9
+            refs(out, 10, 6, 9); // 10
+            handle(out, 10); // 11
+            utf8(out, "invoke"); // 12
+            utf8(out, "(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/Class;Ljava/lang/invoke/MethodHandle;[Ljava/lang/Object;)Ljava/lang/Object;"); // 13
+            refs(out, 12, 12, 13); // 14
+            refs(out, 10, 6, 14); // 15
+            handle(out, 15); // 16
+            utf8(out, "java/lang/Integer"); // 17
+            ref(out, 7, 17); // 18
+            utf8(out, "MAX_VALUE"); // 19
+            utf8(out, "I"); // 20
+            refs(out, 12, 19, 20); // 21
+            refs(out, 17, 0, 21); // 22, Integer.MAX_VALUE
+            utf8(out, "toString"); // 23
+            utf8(out, "(I)Ljava/lang/String;"); // 24
+            refs(out, 12, 23, 24); // 25
+            refs(out, 10, 18, 25); // 26
+            handle(out, 26); // 27
+            utf8(out, "_"); // 28
+            utf8(out, "Ljava/lang/String;"); // 29
+            refs(out, 12, 28, 29); // 30
+            refs(out, 17, 1, 30); // 31, Integer.toString(#22)
+            utf8(out, "get"); // 32
+            utf8(out, "()Ljava/lang/String;"); // 33
+            utf8(out, "Code"); // 34
+            utf8(out, "BootstrapMethods"); // 35
+
+            out.writeShort(0x0021);
+            out.writeShort(2);
+            out.writeShort(4);
+            out.writeShort(0);
+            out.writeShort(0);
+
+            out.writeShort(1);
+            out.writeShort(0x0009);
+            out.writeShort(32);
+            out.writeShort(33);
+            out.writeShort(1);
+            out.writeShort(34);
+            out.writeInt(15);
+            out.writeShort(1);
+            out.writeShort(0);
+            out.writeInt(3);
+            out.writeByte(0x12); // ldc #31
+            out.writeByte(31);
+            out.writeByte(0xb0); // areturn
+            out.writeShort(0);
+            out.writeShort(0);
+
+            out.writeShort(1);
+            out.writeShort(35);
+            out.writeInt(16);
+            out.writeShort(2);
+            out.writeShort(11);
+            out.writeShort(1);
+            out.writeShort(18);
+            out.writeShort(16);
+            out.writeShort(2);
+            out.writeShort(27);
+            out.writeShort(22);
+        }
+    }
+
+    static void utf8(DataOutputStream out, String value) throws IOException {
+        out.writeByte(1);
+        out.writeUTF(value);
+    }
+
+    static void ref(DataOutputStream out, int tag, int index) throws IOException {
+        out.writeByte(tag);
+        out.writeShort(index);
+    }
+
+    static void refs(DataOutputStream out, int tag, int first, int second) throws IOException {
+        out.writeByte(tag);
+        out.writeShort(first);
+        out.writeShort(second);
+    }
+
+    static void handle(DataOutputStream out, int method) throws IOException {
+        out.writeByte(15);
+        out.writeByte(6); // REF_invokeStatic
+        out.writeShort(method);
+    }
+}
@@ -0,0 +1,39 @@
+import java.util.List;
+import java.util.function.Supplier;
+
+public class Instructions {
+    static int table(int i) {
+        switch (i) {
+            case 1: return 10;
+            case 2: return 20;
+            case 3: return 30;
+            default: return -1;
+        }
+    }
+
+    static int lookup(int i) {
+        switch (i) {
+            case 1: return 10;
+            case 1000: return 20;
+            default: return -1;
+        }
+    }
+
+    static int wide(int i) {
+        i += 1000;
+        return i;
+    }
+
+    static Object arrays() {
+        long[] longs = new long[2];
+        return new int[3][longs.length];
+    }
+
+    static int size(List<String> list) {
+        return list.size();
+    }
+
+    static Supplier<String> lambda() {
+        return () -> "lambda";
+    }
+}
@@ -0,0 +1,15 @@
+import java.util.function.Function;
+
+public interface InterfaceLambda {
+    static Function<String, String> id() {
+        return x -> x;
+    }
+
+    default Function<String, Integer> length() {
+        return s -> s.length() + offset();
+    }
+
+    private int offset() {
+        return 0;
+    }
+}
@@ -0,0 +1,8 @@
+class Jcov {
+    int max(int a, int b) {
+        if (a > b) {
+            return a;
+        }
+        return b;
+    }
+}
@@ -0,0 +1,5 @@
+public class ModifiedUtf8 {
+    public static final String NUL = "a\0b";
+    public static final String EMOJI = "caf\u00e9 \ud83d\ude00";
+    public static final String LONE_SURROGATE = "x\ud83dy";
+}
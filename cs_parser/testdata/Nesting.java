@@ -0,0 +1,10 @@
+class Nesting {
+    class Member {}
+
+    static Object create() {
+        class Local {}
+        return new Object() {
+            Local local = new Local();
+        };
+    }
+}
@@ -0,0 +1,3 @@
+import java.util.List;
+
+public record Point<T>(int x, List<T> tags) {}
@@ -0,0 +1,21 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.List;
+import java.util.Map;
+
+class TypeAnnotations<T extends @TypeAnnotations.A Object> {
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target(ElementType.TYPE_USE)
+    @interface A {}
+
+    List<@A String> names;
+    Map<String, @A String[]> values;
+    List<? extends @A Number> numbers;
+
+    Object create(@A int size) {
+        @A Object local = new @A Object();
+        return local;
+    }
+}
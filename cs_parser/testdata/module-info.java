@@ -0,0 +1,9 @@
+module com.example.app {
+    requires transitive java.logging;
+    requires static java.sql;
+    exports com.example.app;
+    exports com.example.internal to java.logging;
+    opens com.example.internal;
+    uses java.util.spi.ToolProvider;
+    provides java.util.spi.ToolProvider with com.example.app.Main;
+}
@@ -0,0 +1,661 @@
+//!
+//! The effect of each instruction on the types of a frame
+//!
+
+use crate::frame::{Frame, MethodContext, VResult};
+use crate::types::{
+    array_element, array_of, class_name, constant, field_type, member_ref, method_type, VType,
+    THROWABLE,
+};
+use cs_parser::{CpInfoInner, Instruction};
+
+/// Where execution can continue after an instruction
+pub(crate) struct Flow {
+    pub(crate) targets: Vec<u32>,
+    pub(crate) falls_through: bool,
+}
+
+impl Flow {
+    fn next() -> Self {
+        Self {
+            targets: Vec::new(),
+            falls_through: true,
+        }
+    }
+
+    fn end() -> Self {
+        Self {
+            targets: Vec::new(),
+            falls_through: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Invoke {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+impl MethodContext<'_> {
+    /// Changes the frame like the instruction at `offset` does
+    pub(crate) fn execute(
+        &self,
+        frame: &mut Frame,
+        offset: u32,
+        instruction: &Instruction,
+    ) -> VResult<Flow> {
+        use Instruction as I;
+        use VType::{Double, Float, Integer, Long, Null};
+
+        let target = |branch: i32| {
+            u32::try_from(i64::from(offset) + i64::from(branch)).map_err(|_| {
+                format!(
+                    "Branch to negative offset {}",
+                    offset as i64 + branch as i64
+                )
+            })
+        };
+        let object = || VType::reference(crate::types::OBJECT);
+
+        match instruction {
+            I::Nop => {}
+            I::AconstNull => self.push(frame, Null)?,
+            I::IconstM1
+            | I::Iconst0
+            | I::Iconst1
+            | I::Iconst2
+            | I::Iconst3
+            | I::Iconst4
+            | I::Iconst5
+            | I::Bipush(_)
+            | I::Sipush(_) => self.push(frame, Integer)?,
+            I::Lconst0 | I::Lconst1 => self.push(frame, Long)?,
+            I::Fconst0 | I::Fconst1 | I::Fconst2 => self.push(frame, Float)?,
+            I::Dconst0 | I::Dconst1 => self.push(frame, Double)?,
+            I::Ldc(index) => {
+                let value = self.loadable((*index).into())?;
+                if value.size() != 1 {
+                    return Err("ldc of a long or double".to_string());
+                }
+                self.push(frame, value)?;
+            }
+            I::LdcW(index) => {
+                let value = self.loadable(*index)?;
+                if value.size() != 1 {
+                    return Err("ldc_w of a long or double".to_string());
+                }
+                self.push(frame, value)?;
+            }
+            I::Ldc2W(index) => {
+                let value = self.loadable(*index)?;
+                if value.size() != 2 {
+                    return Err("ldc2_w of a value that is not a long or double".to_string());
+                }
+                self.push(frame, value)?;
+            }
+
+            I::Iload(index) => self.load(frame, (*index).into(), &Integer)?,
+            I::Lload(index) => self.load(frame, (*index).into(), &Long)?,
+            I::Fload(index) => self.load(frame, (*index).into(), &Float)?,
+            I::Dload(index) => self.load(frame, (*index).into(), &Double)?,
+            I::Aload(index) => self.load(frame, (*index).into(), &object())?,
+            I::Iload0 => self.load(frame, 0, &Integer)?,
+            I::Iload1 => self.load(frame, 1, &Integer)?,
+            I::Iload2 => self.load(frame, 2, &Integer)?,
+            I::Iload3 => self.load(frame, 3, &Integer)?,
+            I::Lload0 => self.load(frame, 0, &Long)?,
+            I::Lload1 => self.load(frame, 1, &Long)?,
+            I::Lload2 => self.load(frame, 2, &Long)?,
+            I::Lload3 => self.load(frame, 3, &Long)?,
+            I::Fload0 => self.load(frame, 0, &Float)?,
+            I::Fload1 => self.load(frame, 1, &Float)?,
+            I::Fload2 => self.load(frame, 2, &Float)?,
+            I::Fload3 => self.load(frame, 3, &Float)?,
+            I::Dload0 => self.load(frame, 0, &Double)?,
+            I::Dload1 => self.load(frame, 1, &Double)?,
+            I::Dload2 => self.load(frame, 2, &Double)?,
+            I::Dload3 => self.load(frame, 3, &Double)?,
+            I::Aload0 => self.load(frame, 0, &object())?,
+            I::Aload1 => self.load(frame, 1, &object())?,
+            I::Aload2 => self.load(frame, 2, &object())?,
+            I::Aload3 => self.load(frame, 3, &object())?,
+
+            I::Iaload => self.array_load(frame, &["[I"], Integer)?,
+            I::Laload => self.array_load(frame, &["[J"], Long)?,
+            I::Faload => self.array_load(frame, &["[F"], Float)?,
+            I::Daload => self.array_load(frame, &["[D"], Double)?,
+            I::Baload => self.array_load(frame, &["[B", "[Z"], Integer)?,
+            I::Caload => self.array_load(frame, &["[C"], Integer)?,
+            I::Saload => self.array_load(frame, &["[S"], Integer)?,
+            I::Aaload => {
+                self.pop_expect(frame, &Integer)?;
+                let element = match self.pop_array(frame)? {
+                    None => Null,
+                    Some(array) => match array_element(&array) {
+                        Some(element @ VType::Reference(_)) => element,
+                        _ => return Err(format!("aaload from {}", array)),
+                    },
+                };
+                self.push(frame, element)?;
+            }
+
+            I::Istore(index) => self.store_popped(frame, (*index).into(), &Integer)?,
+            I::Lstore(index) => self.store_popped(frame, (*index).into(), &Long)?,
+            I::Fstore(index) => self.store_popped(frame, (*index).into(), &Float)?,
+            I::Dstore(index) => self.store_popped(frame, (*index).into(), &Double)?,
+            I::Astore(index) => self.store_reference(frame, (*index).into())?,
+            I::Istore0 => self.store_popped(frame, 0, &Integer)?,
+            I::Istore1 => self.store_popped(frame, 1, &Integer)?,
+            I::Istore2 => self.store_popped(frame, 2, &Integer)?,
+            I::Istore3 => self.store_popped(frame, 3, &Integer)?,
+            I::Lstore0 => self.store_popped(frame, 0, &Long)?,
+            I::Lstore1 => self.store_popped(frame, 1, &Long)?,
+            I::Lstore2 => self.store_popped(frame, 2, &Long)?,
+            I::Lstore3 => self.store_popped(frame, 3, &Long)?,
+            I::Fstore0 => self.store_popped(frame, 0, &Float)?,
+            I::Fstore1 => self.store_popped(frame, 1, &Float)?,
+            I::Fstore2 => self.store_popped(frame, 2, &Float)?,
+            I::Fstore3 => self.store_popped(frame, 3, &Float)?,
+            I::Dstore0 => self.store_popped(frame, 0, &Double)?,
+            I::Dstore1 => self.store_popped(frame, 1, &Double)?,
+            I::Dstore2 => self.store_popped(frame, 2, &Double)?,
+            I::Dstore3 => self.store_popped(frame, 3, &Double)?,
+            I::Astore0 => self.store_reference(frame, 0)?,
+            I::Astore1 => self.store_reference(frame, 1)?,
+            I::Astore2 => self.store_reference(frame, 2)?,
+            I::Astore3 => self.store_reference(frame, 3)?,
+
+            I::Iastore => self.array_store(frame, &["[I"], Integer)?,
+            I::Lastore => self.array_store(frame, &["[J"], Long)?,
+            I::Fastore => self.array_store(frame, &["[F"], Float)?,
+            I::Dastore => self.array_store(frame, &["[D"], Double)?,
+            I::Bastore => self.array_store(frame, &["[B", "[Z"], Integer)?,
+            I::Castore => self.array_store(frame, &["[C"], Integer)?,
+            I::Sastore => self.array_store(frame, &["[S"], Integer)?,
+            I::Aastore => {
+                // whether the value fits into the array is checked at runtime
+                self.pop_reference(frame)?;
+                self.pop_expect(frame, &Integer)?;
+                if let Some(array) = self.pop_array(frame)? {
+                    if !matches!(array_element(&array), Some(VType::Reference(_))) {
+                        return Err(format!("aastore into {}", array));
+                    }
+                }
+            }
+
+            I::Pop => {
+                self.pop_slots(frame, 1)?;
+            }
+            I::Pop2 => {
+                self.pop_slots(frame, 2)?;
+            }
+            I::Dup => self.dup(frame, 1, 0)?,
+            I::DupX1 => self.dup(frame, 1, 1)?,
+            I::DupX2 => self.dup(frame, 1, 2)?,
+            I::Dup2 => self.dup(frame, 2, 0)?,
+            I::Dup2X1 => self.dup(frame, 2, 1)?,
+            I::Dup2X2 => self.dup(frame, 2, 2)?,
+            I::Swap => {
+                let top = self.pop_slots(frame, 1)?;
+                let below = self.pop_slots(frame, 1)?;
+                for value in top.into_iter().chain(below) {
+                    self.push(frame, value)?;
+                }
+            }
+
+            I::Iadd | I::Isub | I::Imul | I::Idiv | I::Irem | I::Iand | I::Ior | I::Ixor => {
+                self.operation(frame, &[Integer, Integer], Some(Integer))?
+            }
+            I::Ishl | I::Ishr | I::Iushr => {
+                self.operation(frame, &[Integer, Integer], Some(Integer))?
+            }
+            I::Ladd | I::Lsub | I::Lmul | I::Ldiv | I::Lrem | I::Land | I::Lor | I::Lxor => {
+                self.operation(frame, &[Long, Long], Some(Long))?
+            }
+            I::Lshl | I::Lshr | I::Lushr => self.operation(frame, &[Long, Integer], Some(Long))?,
+            I::Fadd | I::Fsub | I::Fmul | I::Fdiv | I::Frem => {
+                self.operation(frame, &[Float, Float], Some(Float))?
+            }
+            I::Dadd | I::Dsub | I::Dmul | I::Ddiv | I::Drem => {
+                self.operation(frame, &[Double, Double], Some(Double))?
+            }
+            I::Ineg | I::I2b | I::I2c | I::I2s => {
+                self.operation(frame, &[Integer], Some(Integer))?
+            }
+            I::Lneg => self.operation(frame, &[Long], Some(Long))?,
+            I::Fneg => self.operation(frame, &[Float], Some(Float))?,
+            I::Dneg => self.operation(frame, &[Double], Some(Double))?,
+            I::Iinc { index, .. } => {
+                let value = self.local(frame, (*index).into(), 1)?;
+                if value != &Integer {
+                    return Err(format!(
+                        "iinc of local variable {} of type {:?}",
+                        index, value
+                    ));
+                }
+            }
+            I::I2l => self.operation(frame, &[Integer], Some(Long))?,
+            I::I2f => self.operation(frame, &[Integer], Some(Float))?,
+            I::I2d => self.operation(frame, &[Integer], Some(Double))?,
+            I::L2i => self.operation(frame, &[Long], Some(Integer))?,
+            I::L2f => self.operation(frame, &[Long], Some(Float))?,
+            I::L2d => self.operation(frame, &[Long], Some(Double))?,
+            I::F2i => self.operation(frame, &[Float], Some(Integer))?,
+            I::F2l => self.operation(frame, &[Float], Some(Long))?,
+            I::F2d => self.operation(frame, &[Float], Some(Double))?,
+            I::D2i => self.operation(frame, &[Double], Some(Integer))?,
+            I::D2l => self.operation(frame, &[Double], Some(Long))?,
+            I::D2f => self.operation(frame, &[Double], Some(Float))?,
+            I::Lcmp => self.operation(frame, &[Long, Long], Some(Integer))?,
+            I::Fcmpl | I::Fcmpg => self.operation(frame, &[Float, Float], Some(Integer))?,
+            I::Dcmpl | I::Dcmpg => self.operation(frame, &[Double, Double], Some(Integer))?,
+
+            I::Ifeq(branch)
+            | I::Ifne(branch)
+            | I::Iflt(branch)
+            | I::Ifge(branch)
+            | I::Ifgt(branch)
+            | I::Ifle(branch) => {
+                self.pop_expect(frame, &Integer)?;
+                return Ok(Flow {
+                    targets: vec![target(*branch)?],
+                    falls_through: true,
+                });
+            }
+            I::IfIcmpeq(branch)
+            | I::IfIcmpne(branch)
+            | I::IfIcmplt(branch)
+            | I::IfIcmpge(branch)
+            | I::IfIcmpgt(branch)
+            | I::IfIcmple(branch) => {
+                self.pop_expect(frame, &Integer)?;
+                self.pop_expect(frame, &Integer)?;
+                return Ok(Flow {
+                    targets: vec![target(*branch)?],
+                    falls_through: true,
+                });
+            }
+            I::IfAcmpeq(branch) | I::IfAcmpne(branch) => {
+                self.pop_reference(frame)?;
+                self.pop_reference(frame)?;
+                return Ok(Flow {
+                    targets: vec![target(*branch)?],
+                    falls_through: true,
+                });
+            }
+            I::Ifnull(branch) | I::Ifnonnull(branch) => {
+                self.pop_reference(frame)?;
+                return Ok(Flow {
+                    targets: vec![target(*branch)?],
+                    falls_through: true,
+                });
+            }
+            I::Goto(branch) | I::GotoW(branch) => {
+                return Ok(Flow {
+                    targets: vec![target(*branch)?],
+                    falls_through: false,
+                })
+            }
+            I::Jsr(_) | I::JsrW(_) | I::Ret(_) => {
+                return Err("jsr and ret are not supported by the verifier".to_string())
+            }
+            I::Tableswitch {
+                default, offsets, ..
+            } => {
+                self.pop_expect(frame, &Integer)?;
+                return Ok(Flow {
+                    targets: std::iter::once(default)
+                        .chain(offsets)
+                        .map(|branch| target(*branch))
+                        .collect::<VResult<_>>()?,
+                    falls_through: false,
+                });
+            }
+            I::Lookupswitch { default, pairs } => {
+                self.pop_expect(frame, &Integer)?;
+                return Ok(Flow {
+                    targets: std::iter::once(default)
+                        .chain(pairs.iter().map(|(_, branch)| branch))
+                        .map(|branch| target(*branch))
+                        .collect::<VResult<_>>()?,
+                    falls_through: false,
+                });
+            }
+
+            I::Ireturn => return self.return_value(frame, Some(&Integer), "ireturn"),
+            I::Lreturn => return self.return_value(frame, Some(&Long), "lreturn"),
+            I::Freturn => return self.return_value(frame, Some(&Float), "freturn"),
+            I::Dreturn => return self.return_value(frame, Some(&Double), "dreturn"),
+            I::Areturn => return self.return_value(frame, Some(&object()), "areturn"),
+            I::Return => return self.return_value(frame, None, "return"),
+            I::Athrow => {
+                self.pop_expect(frame, &VType::reference(THROWABLE))?;
+                return Ok(Flow::end());
+            }
+
+            I::Getstatic(index) => {
+                let (_, _, descriptor) = self.member(*index)?;
+                self.push(frame, self.field_type(descriptor)?)?;
+            }
+            I::Putstatic(index) => {
+                let (_, _, descriptor) = self.member(*index)?;
+                self.pop_expect(frame, &self.field_type(descriptor)?)?;
+            }
+            I::Getfield(index) => {
+                let (class, _, descriptor) = self.member(*index)?;
+                self.pop_expect(frame, &VType::reference(class))?;
+                self.push(frame, self.field_type(descriptor)?)?;
+            }
+            I::Putfield(index) => {
+                let (class, _, descriptor) = self.member(*index)?;
+                self.pop_expect(frame, &self.field_type(descriptor)?)?;
+                // constructors can set their own fields before calling the super constructor
+                if frame.stack.last() == Some(&VType::UninitializedThis) && class == self.this_class
+                {
+                    self.pop(frame)?;
+                } else {
+                    self.pop_expect(frame, &VType::reference(class))?;
+                }
+            }
+
+            I::Invokevirtual(index) => self.invoke(frame, *index, Invoke::Virtual, None)?,
+            I::Invokespecial(index) => self.invoke(frame, *index, Invoke::Special, None)?,
+            I::Invokestatic(index) => self.invoke(frame, *index, Invoke::Static, None)?,
+            I::Invokeinterface { index, count } => {
+                self.invoke(frame, *index, Invoke::Interface, Some(*count))?
+            }
+            I::Invokedynamic(index) => {
+                let name_and_type = match constant(self.cp, *index) {
+                    Some(CpInfoInner::InvokeDynamic(dynamic)) => {
+                        dynamic.name_and_type_index.get(self.cp)
+                    }
+                    _ => return Err(format!("invokedynamic of invalid constant {}", index)),
+                };
+                let descriptor = name_and_type.descriptor_index.get(self.cp);
+                self.call(frame, descriptor)?;
+            }
+
+            I::New(index) => {
+                let class = self.class(*index)?;
+                if class.starts_with('[') {
+                    return Err(format!("new of array type {}", class));
+                }
+                self.push(frame, VType::Uninitialized(offset))?;
+            }
+            I::Newarray(atype) => {
+                let array = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => return Err(format!("newarray of invalid type {}", atype)),
+                };
+                self.operation(frame, &[Integer], Some(VType::reference(array)))?;
+            }
+            I::Anewarray(index) => {
+                let array = array_of(self.class(*index)?);
+                self.operation(frame, &[Integer], Some(VType::Reference(array)))?;
+            }
+            I::Arraylength => {
+                self.pop_array(frame)?;
+                self.push(frame, Integer)?;
+            }
+            I::Checkcast(index) => {
+                let class = self.class(*index)?;
+                self.pop_expect(frame, &object())?;
+                self.push(frame, VType::reference(class))?;
+            }
+            I::Instanceof(index) => {
+                self.class(*index)?;
+                self.operation(frame, &[object()], Some(Integer))?;
+            }
+            I::Monitorenter | I::Monitorexit => self.operation(frame, &[object()], None)?,
+            I::Multianewarray { index, dimensions } => {
+                let class = self.class(*index)?;
+                let array_dimensions = class.len() - class.trim_start_matches('[').len();
+                if *dimensions == 0 || usize::from(*dimensions) > array_dimensions {
+                    return Err(format!(
+                        "multianewarray of {} with {} dimensions",
+                        class, dimensions
+                    ));
+                }
+                for _ in 0..*dimensions {
+                    self.pop_expect(frame, &Integer)?;
+                }
+                self.push(frame, VType::reference(class))?;
+            }
+        }
+        Ok(Flow::next())
+    }
+
+    /// Pops the operands, where the last one is on top of the stack, and pushes the result
+    fn operation(
+        &self,
+        frame: &mut Frame,
+        operands: &[VType],
+        result: Option<VType>,
+    ) -> VResult<()> {
+        for operand in operands.iter().rev() {
+            self.pop_expect(frame, operand)?;
+        }
+        match result {
+            Some(result) => self.push(frame, result),
+            None => Ok(()),
+        }
+    }
+
+    /// Pops an array, `None` if it is `null`
+    fn pop_array(&self, frame: &mut Frame) -> VResult<Option<String>> {
+        match self.pop(frame)? {
+            VType::Null => Ok(None),
+            VType::Reference(array) if array.starts_with('[') => Ok(Some(array)),
+            value => Err(format!("Expected an array on the stack, found {:?}", value)),
+        }
+    }
+
+    /// Pops an array of one of the `arrays` types
+    fn pop_array_of(&self, frame: &mut Frame, arrays: &[&str]) -> VResult<()> {
+        match self.pop_array(frame)? {
+            Some(array) if !arrays.contains(&array.as_str()) => Err(format!(
+                "Expected an array of type {} on the stack, found {}",
+                arrays.join(" or "),
+                array
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn array_load(&self, frame: &mut Frame, arrays: &[&str], element: VType) -> VResult<()> {
+        self.pop_expect(frame, &VType::Integer)?;
+        self.pop_array_of(frame, arrays)?;
+        self.push(frame, element)
+    }
+
+    fn array_store(&self, frame: &mut Frame, arrays: &[&str], element: VType) -> VResult<()> {
+        self.pop_expect(frame, &element)?;
+        self.pop_expect(frame, &VType::Integer)?;
+        self.pop_array_of(frame, arrays)
+    }
+
+    fn store_popped(&self, frame: &mut Frame, index: usize, expected: &VType) -> VResult<()> {
+        let value = self.pop_expect(frame, expected)?;
+        self.store(frame, index, value)
+    }
+
+    fn store_reference(&self, frame: &mut Frame, index: usize) -> VResult<()> {
+        let value = self.pop_reference(frame)?;
+        self.store(frame, index, value)
+    }
+
+    /// Duplicates the top `slots` slots of the stack below the `below` slots under them
+    fn dup(&self, frame: &mut Frame, slots: usize, below: usize) -> VResult<()> {
+        let top = self.pop_slots(frame, slots)?;
+        let under = self.pop_slots(frame, below)?;
+        for value in top.iter().chain(&under).chain(&top) {
+            self.push(frame, value.clone())?;
+        }
+        Ok(())
+    }
+
+    fn return_value(
+        &self,
+        frame: &mut Frame,
+        value: Option<&VType>,
+        mnemonic: &str,
+    ) -> VResult<Flow> {
+        match (value, &self.return_type) {
+            (None, None) => {
+                if frame.this_uninit {
+                    return Err("Returns before the super constructor is called".to_string());
+                }
+            }
+            (Some(VType::Reference(_)), Some(return_type @ VType::Reference(_))) => {
+                self.pop_expect(frame, return_type)?;
+            }
+            (Some(value), Some(return_type)) if value == return_type => {
+                self.pop_expect(frame, return_type)?;
+            }
+            (_, return_type) => {
+                return Err(format!(
+                    "{} in a method that returns {:?}",
+                    mnemonic, return_type
+                ))
+            }
+        }
+        Ok(Flow::end())
+    }
+
+    /// Pops the arguments of a call and pushes its result
+    fn call(&self, frame: &mut Frame, descriptor: &str) -> VResult<()> {
+        let (parameters, return_type) =
+            method_type(descriptor).ok_or_else(|| format!("Invalid descriptor {}", descriptor))?;
+        for parameter in parameters.iter().rev() {
+            self.pop_expect(frame, parameter)?;
+        }
+        match return_type {
+            Some(return_type) => self.push(frame, return_type),
+            None => Ok(()),
+        }
+    }
+
+    fn invoke(
+        &self,
+        frame: &mut Frame,
+        index: u16,
+        kind: Invoke,
+        count: Option<u8>,
+    ) -> VResult<()> {
+        let (class, name, descriptor) = self.member(index)?;
+        let (parameters, return_type) =
+            method_type(descriptor).ok_or_else(|| format!("Invalid descriptor {}", descriptor))?;
+        if let Some(count) = count {
+            let slots = 1 + parameters.iter().map(VType::size).sum::<usize>();
+            if usize::from(count) != slots {
+                return Err(format!(
+                    "invokeinterface with count {}, but the arguments take {} slots",
+                    count, slots
+                ));
+            }
+        }
+        let is_init = name == "<init>";
+        if is_init && kind != Invoke::Special {
+            return Err("Constructors can only be called with invokespecial".to_string());
+        }
+        if name.starts_with('<') && !is_init {
+            return Err(format!("Call of {}", name));
+        }
+
+        for parameter in parameters.iter().rev() {
+            self.pop_expect(frame, parameter)?;
+        }
+        if kind != Invoke::Static {
+            if is_init {
+                let initialized = match self.pop(frame)? {
+                    VType::UninitializedThis => {
+                        let super_class = self.hierarchy.this_class.1.super_class.as_deref();
+                        if class != self.this_class && Some(class) != super_class {
+                            return Err(format!(
+                                "Constructor of {} called on uninitialized this",
+                                class
+                            ));
+                        }
+                        frame.this_uninit = false;
+                        VType::UninitializedThis
+                    }
+                    VType::Uninitialized(created) => {
+                        if self.news.get(&created).map(String::as_str) != Some(class) {
+                            return Err(format!(
+                                "Constructor of {} called on the object created at {}",
+                                class, created
+                            ));
+                        }
+                        VType::Uninitialized(created)
+                    }
+                    value => {
+                        return Err(format!(
+                            "Constructor called on {:?}, which is not uninitialized",
+                            value
+                        ))
+                    }
+                };
+                let class = match initialized {
+                    VType::UninitializedThis => self.this_class,
+                    _ => class,
+                };
+                for value in frame.locals.iter_mut().chain(&mut frame.stack) {
+                    if *value == initialized {
+                        *value = VType::reference(class);
+                    }
+                }
+            } else if kind == Invoke::Special {
+                // private methods and super calls can only be called on this class
+                self.pop_expect(frame, &VType::reference(self.this_class))?;
+            } else {
+                self.pop_expect(frame, &VType::reference(class))?;
+            }
+        }
+        match return_type {
+            Some(return_type) => self.push(frame, return_type),
+            None => Ok(()),
+        }
+    }
+
+    fn member(&self, index: u16) -> VResult<(&str, &str, &str)> {
+        member_ref(self.cp, index)
+            .ok_or_else(|| format!("Constant {} is not a field or method reference", index))
+    }
+
+    fn class(&self, index: u16) -> VResult<&str> {
+        class_name(self.cp, index).ok_or_else(|| format!("Constant {} is not a class", index))
+    }
+
+    fn field_type(&self, descriptor: &str) -> VResult<VType> {
+        field_type(descriptor).ok_or_else(|| format!("Invalid descriptor {}", descriptor))
+    }
+
+    /// The type of a constant loaded with `ldc`
+    fn loadable(&self, index: u16) -> VResult<VType> {
+        Ok(match constant(self.cp, index) {
+            Some(CpInfoInner::Integer(_)) => VType::Integer,
+            Some(CpInfoInner::Float(_)) => VType::Float,
+            Some(CpInfoInner::Long(_)) => VType::Long,
+            Some(CpInfoInner::Double(_)) => VType::Double,
+            Some(CpInfoInner::String(_)) => VType::reference("java/lang/String"),
+            Some(CpInfoInner::Class(_)) => VType::reference("java/lang/Class"),
+            Some(CpInfoInner::MethodType(_)) => VType::reference("java/lang/invoke/MethodType"),
+            Some(CpInfoInner::MethodHandle(_)) => VType::reference("java/lang/invoke/MethodHandle"),
+            Some(CpInfoInner::Dynamic(dynamic)) => {
+                let name_and_type = dynamic.name_and_type_index.get(self.cp);
+                self.field_type(name_and_type.descriptor_index.get(self.cp))?
+            }
+            _ => return Err(format!("ldc of constant {}, which is not loadable", index)),
+        })
+    }
+}
@@ -0,0 +1,184 @@
+//!
+//! The types of the local variables and the operand stack at an instruction
+//!
+
+use crate::hierarchy::Hierarchy;
+use crate::types::VType;
+use cs_parser::ConstantPool;
+use std::collections::HashMap;
+
+pub(crate) type VResult<T> = Result<T, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    /// One entry per slot, the second slot of a `long` or `double` is `Top`
+    pub(crate) locals: Vec<VType>,
+    /// One entry per value, `long` and `double` are a single entry
+    pub(crate) stack: Vec<VType>,
+    /// Whether `this` still has to be initialized by a constructor call
+    pub(crate) this_uninit: bool,
+}
+
+/// Everything about the verified method that doesn't change between instructions
+pub(crate) struct MethodContext<'a> {
+    pub(crate) hierarchy: &'a Hierarchy<'a>,
+    pub(crate) cp: &'a ConstantPool,
+    pub(crate) this_class: &'a str,
+    pub(crate) max_stack: usize,
+    pub(crate) max_locals: usize,
+    /// `None` for `void`
+    pub(crate) return_type: Option<VType>,
+    /// The classes created by the `new` instructions, by their offset
+    pub(crate) news: HashMap<u32, String>,
+}
+
+impl MethodContext<'_> {
+    pub(crate) fn pop(&self, frame: &mut Frame) -> VResult<VType> {
+        frame
+            .stack
+            .pop()
+            .ok_or_else(|| "Stack underflow".to_string())
+    }
+
+    /// Pops a value that has to be assignable to `expected`
+    pub(crate) fn pop_expect(&self, frame: &mut Frame, expected: &VType) -> VResult<VType> {
+        let value = self.pop(frame)?;
+        if !self.hierarchy.is_assignable(&value, expected) {
+            return Err(format!(
+                "Expected {:?} on the stack, found {:?}",
+                expected, value
+            ));
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn pop_reference(&self, frame: &mut Frame) -> VResult<VType> {
+        let value = self.pop(frame)?;
+        if !value.is_reference() {
+            return Err(format!(
+                "Expected a reference on the stack, found {:?}",
+                value
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Pops values that take up exactly `slots` slots, in the order they were on the stack
+    pub(crate) fn pop_slots(&self, frame: &mut Frame, slots: usize) -> VResult<Vec<VType>> {
+        let mut values = Vec::new();
+        let mut popped = 0;
+        while popped < slots {
+            let value = self.pop(frame)?;
+            popped += value.size();
+            values.push(value);
+        }
+        if popped != slots {
+            return Err("Splits a long or double on the stack".to_string());
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    pub(crate) fn push(&self, frame: &mut Frame, value: VType) -> VResult<()> {
+        frame.stack.push(value);
+        let size = frame.stack.iter().map(VType::size).sum::<usize>();
+        if size > self.max_stack {
+            return Err(format!(
+                "Stack of {} slots, but max_stack is {}",
+                size, self.max_stack
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn local<'f>(
+        &self,
+        frame: &'f Frame,
+        index: usize,
+        size: usize,
+    ) -> VResult<&'f VType> {
+        if index + size > self.max_locals {
+            return Err(format!(
+                "Local variable {} out of bounds, max_locals is {}",
+                index, self.max_locals
+            ));
+        }
+        Ok(&frame.locals[index])
+    }
+
+    /// Pushes the local variable at `index`, which has to be of type `expected`
+    pub(crate) fn load(&self, frame: &mut Frame, index: usize, expected: &VType) -> VResult<()> {
+        let value = self.local(frame, index, expected.size())?.clone();
+        let matches = match expected {
+            VType::Reference(_) => value.is_reference(),
+            expected => &value == expected,
+        };
+        if !matches {
+            return Err(format!(
+                "Expected {:?} in local variable {}, found {:?}",
+                expected, index, value
+            ));
+        }
+        self.push(frame, value)
+    }
+
+    pub(crate) fn store(&self, frame: &mut Frame, index: usize, value: VType) -> VResult<()> {
+        let size = value.size();
+        self.local(frame, index, size)?;
+        // overwriting the second half of a long or double makes the whole value unusable
+        if index > 0 && frame.locals[index - 1].size() == 2 {
+            frame.locals[index - 1] = VType::Top;
+        }
+        frame.locals[index] = value;
+        if size == 2 {
+            frame.locals[index + 1] = VType::Top;
+        }
+        Ok(())
+    }
+
+    /// Whether `from` can be used where the frame `to` is expected, like at the target of a branch
+    pub(crate) fn is_frame_assignable(&self, from: &Frame, to: &Frame) -> bool {
+        let assignable = |from: &[VType], to: &[VType]| {
+            from.len() == to.len()
+                && from
+                    .iter()
+                    .zip(to)
+                    .all(|(from, to)| self.hierarchy.is_assignable(from, to))
+        };
+        assignable(&from.locals, &to.locals)
+            && assignable(&from.stack, &to.stack)
+            && (!from.this_uninit || to.this_uninit)
+    }
+
+    /// A frame that both frames can be assigned to
+    pub(crate) fn merge_frames(&self, a: &Frame, b: &Frame) -> VResult<Frame> {
+        if a.stack.len() != b.stack.len() {
+            return Err(format!(
+                "Stack sizes {} and {} do not match",
+                a.stack.len(),
+                b.stack.len()
+            ));
+        }
+        let stack = a
+            .stack
+            .iter()
+            .zip(&b.stack)
+            .map(|(a, b)| {
+                self.hierarchy
+                    .merge(a, b)
+                    .ok_or_else(|| format!("Stack values {:?} and {:?} are incompatible", a, b))
+            })
+            .collect::<VResult<_>>()?;
+        let locals = a
+            .locals
+            .iter()
+            .zip(&b.locals)
+            .map(|(a, b)| self.hierarchy.merge(a, b).unwrap_or(VType::Top))
+            .collect();
+        Ok(Frame {
+            locals,
+            stack,
+            this_uninit: a.this_uninit || b.this_uninit,
+        })
+    }
+}
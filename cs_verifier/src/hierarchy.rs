@@ -0,0 +1,162 @@
+//!
+//! Which reference types can be assigned to each other
+//!
+
+use crate::types::{array_element, VType, OBJECT};
+use cs_parser::{ClassAccessFlag, ClassFile};
+use std::collections::{HashMap, HashSet};
+
+/// What the verifier needs to know about classes other than the one it verifies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassInfo {
+    /// `None` for `java/lang/Object`
+    pub super_class: Option<String>,
+    pub is_interface: bool,
+}
+
+impl ClassInfo {
+    /// The binary name of the class and its info
+    pub fn of(class: &ClassFile) -> (String, Self) {
+        let cp = &class.constant_pool;
+        let name = class.this_class.get(cp).name_index.get(cp).to_string();
+        let super_class = class
+            .super_class
+            .maybe_get(cp)
+            .map(|class| class.name_index.get(cp).to_string());
+        let is_interface = class.access_flags.contains(ClassAccessFlag::Interface);
+        (
+            name,
+            Self {
+                super_class,
+                is_interface,
+            },
+        )
+    }
+}
+
+/// Looks up classes for the verifier.
+///
+/// Whenever a class is not known, the verifier assumes that it can be assigned wherever it is used,
+/// so an empty hierarchy only checks the code structurally for reference types
+pub trait ClassHierarchy {
+    fn class_info(&self, name: &str) -> Option<ClassInfo>;
+}
+
+impl ClassHierarchy for HashMap<String, ClassInfo> {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        self.get(name).cloned()
+    }
+}
+
+/// The hierarchy with the verified class and `java/lang/Object` added
+pub(crate) struct Hierarchy<'a> {
+    pub(crate) classes: &'a dyn ClassHierarchy,
+    pub(crate) this_class: (String, ClassInfo),
+}
+
+/// The upper bound on the depth of a class hierarchy, in case the classes are cyclic
+const MAX_DEPTH: usize = 1000;
+
+impl Hierarchy<'_> {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        if name == self.this_class.0 {
+            Some(self.this_class.1.clone())
+        } else if name == OBJECT {
+            Some(ClassInfo {
+                super_class: None,
+                is_interface: false,
+            })
+        } else {
+            self.classes.class_info(name)
+        }
+    }
+
+    /// The class and its super classes, `None` if one of them is not known
+    fn super_classes(&self, name: &str) -> Option<Vec<String>> {
+        let mut classes = vec![name.to_string()];
+        while let Some(super_class) = self.class_info(classes.last()?)?.super_class {
+            if classes.len() > MAX_DEPTH {
+                return None;
+            }
+            classes.push(super_class);
+        }
+        Some(classes)
+    }
+
+    fn is_interface(&self, name: &str) -> Option<bool> {
+        self.class_info(name).map(|info| info.is_interface)
+    }
+
+    /// Whether a value of type `from` can be used where `to` is expected
+    pub(crate) fn is_assignable(&self, from: &VType, to: &VType) -> bool {
+        match (from, to) {
+            (from, to) if from == to => true,
+            (_, VType::Top) => true,
+            (VType::Null, VType::Reference(_)) => true,
+            (VType::Reference(from), VType::Reference(to)) => self.is_class_assignable(from, to),
+            _ => false,
+        }
+    }
+
+    fn is_class_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.starts_with('['), to.starts_with('[')) {
+            (true, true) => match (array_element(from), array_element(to)) {
+                (Some(VType::Reference(from)), Some(VType::Reference(to))) => {
+                    self.is_class_assignable(&from, &to)
+                }
+                _ => false,
+            },
+            (true, false) => matches!(to, "java/lang/Cloneable" | "java/io/Serializable"),
+            (false, true) => false,
+            // interfaces are treated like `java/lang/Object`, the check happens at runtime
+            (false, false) => match self.super_classes(from) {
+                Some(super_classes) => {
+                    super_classes.iter().any(|class| class == to)
+                        || self.is_interface(to).unwrap_or(true)
+                }
+                None => true,
+            },
+        }
+    }
+
+    /// The most specific type both types can be assigned to, `None` if there is none
+    pub(crate) fn merge(&self, a: &VType, b: &VType) -> Option<VType> {
+        match (a, b) {
+            (a, b) if a == b => Some(a.clone()),
+            (VType::Null, reference @ VType::Reference(_))
+            | (reference @ VType::Reference(_), VType::Null) => Some(reference.clone()),
+            (VType::Reference(a), VType::Reference(b)) => {
+                Some(VType::reference(&self.common_super_class(a, b)))
+            }
+            _ => None,
+        }
+    }
+
+    fn common_super_class(&self, a: &str, b: &str) -> String {
+        // also covers unknown classes, which are assumed to be assignable
+        if self.is_class_assignable(a, b) {
+            return b.to_string();
+        }
+        if self.is_class_assignable(b, a) {
+            return a.to_string();
+        }
+        if a.starts_with('[') || b.starts_with('[') {
+            return OBJECT.to_string();
+        }
+        if self.is_interface(a) != Some(false) || self.is_interface(b) != Some(false) {
+            return OBJECT.to_string();
+        }
+        match (self.super_classes(a), self.super_classes(b)) {
+            (Some(a), Some(b)) => {
+                let a = a.into_iter().collect::<HashSet<_>>();
+                b.into_iter()
+                    .find(|class| a.contains(class))
+                    .unwrap_or_else(|| OBJECT.to_string())
+            }
+            _ => OBJECT.to_string(),
+        }
+    }
+}
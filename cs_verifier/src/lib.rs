@@ -0,0 +1,482 @@
+//!
+//! A bytecode verifier following JVMS §4.10.
+//!
+//! Classes of version 50 and above are verified by type checking, where the types of every
+//! instruction are simulated and checked against the frames of the `StackMapTable`.
+//! Older classes don't have frames, so the types at branch targets are inferred by merging
+//! the types of all the ways to reach them.
+//!
+//! Not checked are access rules, like calls of private methods of other classes. `jsr` and `ret`
+//! are rejected, they are only allowed before version 51 and can be removed with
+//! `ClassFile::inline_subroutines`.
+//!
+
+mod execute;
+mod frame;
+mod hierarchy;
+mod stack_map;
+#[cfg(test)]
+mod test;
+mod types;
+
+use crate::frame::{Frame, MethodContext};
+use crate::hierarchy::Hierarchy;
+use crate::types::{class_name, method_type, VType, THROWABLE};
+use cs_parser::{
+    decode_code, AttributeCodeException, AttributeInfo, AttributeInfoInner, ClassFile, Instruction,
+    MethodAccessFlag, MethodInfo, StackMapFrame,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+
+pub use hierarchy::{ClassHierarchy, ClassInfo};
+pub use stack_map::{compute_stack_maps, optimize, retarget};
+
+/// The first version that is verified by type checking
+const TYPE_CHECKING_VERSION: u16 = 50;
+
+/// A method that did not pass verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyErr {
+    /// The name and descriptor of the method, like `main([Ljava/lang/String;)V`
+    pub method: String,
+    /// The offset of the instruction that failed, `None` if the method as a whole is invalid
+    pub offset: Option<u32>,
+    pub message: String,
+}
+
+impl Display for VerifyErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Verification of {} failed", self.method)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for VerifyErr {}
+
+/// Verifies the code of every method of the class.
+/// `hierarchy` is used to check whether classes can be assigned to each other
+pub fn verify_class(class: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyErr> {
+    let hierarchy = Hierarchy {
+        classes: hierarchy,
+        this_class: ClassInfo::of(class),
+    };
+    for method in &class.methods {
+        verify_method(class, method, &hierarchy)?;
+    }
+    Ok(())
+}
+
+type MethodResult<T> = Result<T, (Option<u32>, String)>;
+
+fn verify_method(
+    class: &ClassFile,
+    method: &MethodInfo,
+    hierarchy: &Hierarchy,
+) -> Result<(), VerifyErr> {
+    let err = method_err(class, method);
+    let Some((code, locals, initial)) = method_code(class, method, hierarchy)? else {
+        return Ok(());
+    };
+    let stack_map = code.attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::StackMapTable { entries, .. } => Some(entries.as_slice()),
+        _ => None,
+    });
+    if class.major_version >= TYPE_CHECKING_VERSION {
+        let checked = code
+            .declared_frames(stack_map.unwrap_or_default(), &locals)
+            .and_then(|frames| code.type_check(initial.clone(), &frames));
+        match checked {
+            // version 50 falls back to inference if type checking fails
+            Err(_) if class.major_version == TYPE_CHECKING_VERSION => {
+                code.infer(initial).map(drop).map_err(err)
+            }
+            checked => checked.map_err(err),
+        }
+    } else {
+        code.infer(initial).map(drop).map_err(err)
+    }
+}
+
+/// Turns the offset and message of a failure into the error for the method
+fn method_err<'a>(
+    class: &'a ClassFile,
+    method: &'a MethodInfo,
+) -> impl Fn((Option<u32>, String)) -> VerifyErr + 'a {
+    let cp = &class.constant_pool;
+    move |(offset, message)| VerifyErr {
+        method: format!(
+            "{}{}",
+            method.name_index.get(cp),
+            method.descriptor_index.get(cp)
+        ),
+        offset,
+        message,
+    }
+}
+
+/// The decoded code of the method, the types of its parameters and its first frame.
+/// `None` if the method has no code
+fn method_code<'a>(
+    class: &'a ClassFile,
+    method: &'a MethodInfo,
+    hierarchy: &'a Hierarchy,
+) -> Result<Option<(MethodCode<'a>, Vec<VType>, Frame)>, VerifyErr> {
+    let cp = &class.constant_pool;
+    let name = method.name_index.get(cp);
+    let descriptor = method.descriptor_index.get(cp);
+    let err = method_err(class, method);
+
+    let (max_stack, max_locals, code, exception_table, attributes) =
+        match method.attributes.iter().find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+            } => Some((max_stack, max_locals, code, exception_table, attributes)),
+            _ => None,
+        }) {
+            Some(code) => code,
+            None => return Ok(None),
+        };
+    let instructions = decode_code(code)
+        .map_err(|parse_err| err((None, format!("Invalid code: {}", parse_err))))?;
+    if let Some((offset, _)) = instructions.iter().find(|(_, instruction)| {
+        matches!(
+            instruction,
+            Instruction::Jsr(_) | Instruction::JsrW(_) | Instruction::Ret(_)
+        )
+    }) {
+        let message = if class.major_version >= 51 {
+            "jsr and ret are not allowed since version 51"
+        } else {
+            "jsr and ret are not supported, inline the subroutines with \
+             `ClassFile::inline_subroutines` first"
+        };
+        return Err(err((Some(*offset), message.to_string())));
+    }
+    let (parameters, return_type) =
+        method_type(descriptor).ok_or_else(|| err((None, "Invalid descriptor".to_string())))?;
+
+    let this_class = hierarchy.this_class.0.as_str();
+    let news = instructions
+        .iter()
+        .filter_map(|(offset, instruction)| match instruction {
+            Instruction::New(index) => Some((*offset, class_name(cp, *index)?.to_string())),
+            _ => None,
+        })
+        .collect();
+    let context = MethodContext {
+        hierarchy,
+        cp,
+        this_class,
+        max_stack: (*max_stack).into(),
+        max_locals: (*max_locals).into(),
+        return_type,
+        news,
+    };
+    let is_static = method.access_flags.contains(MethodAccessFlag::STATIC);
+    let code = MethodCode {
+        context,
+        instructions,
+        exception_table,
+        attributes,
+    };
+
+    let is_init = name == "<init>" && this_class != types::OBJECT;
+    let mut locals = Vec::new();
+    if !is_static {
+        locals.push(if is_init {
+            VType::UninitializedThis
+        } else {
+            VType::reference(this_class)
+        });
+    }
+    locals.extend(parameters);
+    let initial = code
+        .frame(&locals, Vec::new(), is_init)
+        .map_err(|message| err((None, message)))?;
+    Ok(Some((code, locals, initial)))
+}
+
+struct MethodCode<'a> {
+    context: MethodContext<'a>,
+    instructions: Vec<(u32, Instruction)>,
+    exception_table: &'a [AttributeCodeException],
+    /// The attributes of the `Code` attribute
+    attributes: &'a [AttributeInfo],
+}
+
+impl MethodCode<'_> {
+    /// A frame with the local variables in `locals`, where `long` and `double` are a single entry
+    fn frame(
+        &self,
+        locals: &[VType],
+        stack: Vec<VType>,
+        this_uninit: bool,
+    ) -> Result<Frame, String> {
+        let mut slots = Vec::with_capacity(self.context.max_locals);
+        for local in locals {
+            slots.push(local.clone());
+            if local.size() == 2 {
+                slots.push(VType::Top);
+            }
+        }
+        if slots.len() > self.context.max_locals {
+            return Err(format!(
+                "{} local variable slots, but max_locals is {}",
+                slots.len(),
+                self.context.max_locals
+            ));
+        }
+        let stack_size = stack.iter().map(VType::size).sum::<usize>();
+        if stack_size > self.context.max_stack {
+            return Err(format!(
+                "Stack of {} slots, but max_stack is {}",
+                stack_size, self.context.max_stack
+            ));
+        }
+        slots.resize(self.context.max_locals, VType::Top);
+        Ok(Frame {
+            locals: slots,
+            stack,
+            this_uninit,
+        })
+    }
+
+    /// The frames of the `StackMapTable` by their offset. `locals` are the locals of the implicit first frame
+    fn declared_frames(
+        &self,
+        entries: &[StackMapFrame],
+        locals: &[VType],
+    ) -> MethodResult<HashMap<u32, Frame>> {
+        let cp = self.context.cp;
+        let types = |infos: &[_]| {
+            infos
+                .iter()
+                .map(|info| VType::from_verification_type(info, cp))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| (None, "Invalid type in stack map frame".to_string()))
+        };
+
+        let mut locals = locals.to_vec();
+        let mut frames = HashMap::new();
+        let mut previous: Option<u32> = None;
+        for frame in entries {
+            let (delta, stack) = match frame {
+                StackMapFrame::SameFrame { frame_type } => (u16::from(*frame_type), Vec::new()),
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => (
+                    u16::from(frame_type.wrapping_sub(64)),
+                    types(std::slice::from_ref(stack))?,
+                ),
+                StackMapFrame::SameLocals1StackItemFrameExtended {
+                    offset_delta,
+                    stack,
+                    ..
+                } => (*offset_delta, types(std::slice::from_ref(stack))?),
+                StackMapFrame::ChopFrame {
+                    frame_type,
+                    offset_delta,
+                } => {
+                    let chopped = usize::from(251u8.saturating_sub(*frame_type));
+                    locals.truncate(locals.len().saturating_sub(chopped));
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::SameFrameExtended { offset_delta, .. } => {
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::AppendFrame {
+                    offset_delta,
+                    locals: appended,
+                    ..
+                } => {
+                    locals.extend(types(appended)?);
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::FullFrame {
+                    offset_delta,
+                    locals: full,
+                    stack,
+                    ..
+                } => {
+                    locals = types(full)?;
+                    (*offset_delta, types(stack)?)
+                }
+            };
+            let offset = match previous {
+                None => u32::from(delta),
+                Some(previous) => previous + u32::from(delta) + 1,
+            };
+            previous = Some(offset);
+            let this_uninit = locals.contains(&VType::UninitializedThis);
+            let frame = self
+                .frame(&locals, stack, this_uninit)
+                .map_err(|message| (Some(offset), message))?;
+            frames.insert(offset, frame);
+        }
+        Ok(frames)
+    }
+
+    /// Goes through the instructions in order, checking the types against the declared frames
+    fn type_check(&self, initial: Frame, declared: &HashMap<u32, Frame>) -> MethodResult<()> {
+        let context = &self.context;
+        let expect_frame = |offset: u32, frame: &Frame| -> Result<(), String> {
+            match declared.get(&offset) {
+                Some(target) if context.is_frame_assignable(frame, target) => Ok(()),
+                Some(target) => Err(format!(
+                    "{:?} does not match the stack map frame at {}, {:?}",
+                    frame, offset, target
+                )),
+                None => Err(format!("No stack map frame at {}", offset)),
+            }
+        };
+        if let Some(offset) = declared
+            .keys()
+            .find(|offset| self.index_of(**offset).is_none())
+        {
+            return Err((
+                Some(*offset),
+                "Stack map frame not at the start of an instruction".to_string(),
+            ));
+        }
+
+        let mut current = Some(initial);
+        for (offset, instruction) in &self.instructions {
+            let offset = *offset;
+            let on_err = |message| (Some(offset), message);
+            if let Some(declared) = declared.get(&offset) {
+                if let Some(current) = &current {
+                    expect_frame(offset, current).map_err(on_err)?;
+                }
+                current = Some(declared.clone());
+            }
+            let mut frame = current.take().ok_or_else(|| {
+                on_err("No stack map frame after an unconditional branch".to_string())
+            })?;
+            let before = frame.clone();
+            let flow = context
+                .execute(&mut frame, offset, instruction)
+                .map_err(on_err)?;
+            for (handler_pc, handler) in self.handlers(offset, &before, &frame).map_err(on_err)? {
+                expect_frame(handler_pc, &handler).map_err(on_err)?;
+            }
+            for target in flow.targets {
+                expect_frame(target, &frame).map_err(on_err)?;
+            }
+            if flow.falls_through {
+                current = Some(frame);
+            }
+        }
+        match current {
+            Some(_) => Err((
+                self.instructions.last().map(|(offset, _)| *offset),
+                "Execution falls off the end of the code".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Follows every path through the code, merging the frames where paths meet until nothing
+    /// changes. Returns the frames of the reachable instructions by their offset
+    fn infer(&self, initial: Frame) -> MethodResult<HashMap<u32, Frame>> {
+        let context = &self.context;
+        let mut frames: HashMap<u32, Frame> = HashMap::new();
+        let mut work = BTreeSet::new();
+        let merge_into = |frames: &mut HashMap<u32, Frame>,
+                          work: &mut BTreeSet<u32>,
+                          offset: u32,
+                          frame: Frame|
+         -> Result<(), String> {
+            if self.index_of(offset).is_none() {
+                return Err(format!(
+                    "Jump to {}, which is not the start of an instruction",
+                    offset
+                ));
+            }
+            let merged = match frames.get(&offset) {
+                Some(old) => {
+                    let merged = context
+                        .merge_frames(old, &frame)
+                        .map_err(|message| format!("At {}: {}", offset, message))?;
+                    if &merged == old {
+                        return Ok(());
+                    }
+                    merged
+                }
+                None => frame,
+            };
+            frames.insert(offset, merged);
+            work.insert(offset);
+            Ok(())
+        };
+        merge_into(&mut frames, &mut work, 0, initial).map_err(|message| (Some(0), message))?;
+
+        while let Some(offset) = work.pop_first() {
+            let on_err = |message| (Some(offset), message);
+            let index = self.index_of(offset).expect("only instructions are added");
+            let mut frame = frames[&offset].clone();
+            let before = frame.clone();
+            let flow = context
+                .execute(&mut frame, offset, &self.instructions[index].1)
+                .map_err(on_err)?;
+            for (handler_pc, handler) in self.handlers(offset, &before, &frame).map_err(on_err)? {
+                merge_into(&mut frames, &mut work, handler_pc, handler).map_err(on_err)?;
+            }
+            for target in flow.targets {
+                merge_into(&mut frames, &mut work, target, frame.clone()).map_err(on_err)?;
+            }
+            if flow.falls_through {
+                let next = self
+                    .instructions
+                    .get(index + 1)
+                    .ok_or_else(|| on_err("Execution falls off the end of the code".to_string()))?
+                    .0;
+                merge_into(&mut frames, &mut work, next, frame).map_err(on_err)?;
+            }
+        }
+        Ok(frames)
+    }
+
+    /// The frames at the exception handlers that cover the instruction at `offset`, with the
+    /// locals from before and after the instruction
+    fn handlers(
+        &self,
+        offset: u32,
+        before: &Frame,
+        after: &Frame,
+    ) -> Result<Vec<(u32, Frame)>, String> {
+        let mut handlers = Vec::new();
+        for handler in self.exception_table {
+            if !(u32::from(handler.start_pc)..u32::from(handler.end_pc)).contains(&offset) {
+                continue;
+            }
+            let exception = match handler.catch_type {
+                0 => THROWABLE,
+                index => class_name(self.context.cp, index)
+                    .ok_or_else(|| format!("Catch type {} is not a class", index))?,
+            };
+            for locals in [&before.locals, &after.locals] {
+                handlers.push((
+                    handler.handler_pc.into(),
+                    Frame {
+                        locals: locals.clone(),
+                        stack: vec![VType::reference(exception)],
+                        this_uninit: before.this_uninit,
+                    },
+                ));
+            }
+        }
+        Ok(handlers)
+    }
+
+    fn index_of(&self, offset: u32) -> Option<usize> {
+        self.instructions
+            .binary_search_by_key(&offset, |(offset, _)| *offset)
+            .ok()
+    }
+}
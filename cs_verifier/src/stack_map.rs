@@ -0,0 +1,296 @@
+//!
+//! Computing the `StackMapTable`s of classes from the types the verifier infers, so that
+//! classes below version 50 can be upgraded and optimized code can be verified
+//!
+
+use crate::frame::Frame;
+use crate::hierarchy::Hierarchy;
+use crate::types::VType;
+use crate::{method_code, method_err, ClassHierarchy, ClassInfo, MethodCode, VerifyErr};
+use cs_parser::{
+    AttributeInfo, AttributeInfoInner, ClassFile, ConstantPoolBuilder, MethodInfo, OptimizeErr,
+    RetargetErr, StackMapFrame, VerificationTypeInfo,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// From this version on, every method with branches needs a `StackMapTable`
+const REQUIRED_STACK_MAP_VERSION: u16 = 51;
+
+/// Changes the major version of the class like `ClassFile::retarget`, which can't upgrade
+/// classes below version 51 to 51 or above on its own. Their subroutines are inlined and their
+/// `StackMapTable`s computed first. The class is unchanged if it fails
+pub fn retarget(
+    class: &mut ClassFile,
+    major_version: u16,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), RetargetErr> {
+    if major_version < REQUIRED_STACK_MAP_VERSION
+        || class.major_version >= REQUIRED_STACK_MAP_VERSION
+    {
+        return class.retarget(major_version);
+    }
+    let mut retargeted = class.clone();
+    retargeted
+        .inline_subroutines()
+        .map_err(|err| RetargetErr(err.to_string()))?;
+    compute_stack_maps(&mut retargeted, hierarchy).map_err(|err| RetargetErr(err.to_string()))?;
+    retargeted.retarget(major_version)?;
+    *class = retargeted;
+    Ok(())
+}
+
+/// Replaces the `StackMapTable` of every method with branches or exception handlers by one with
+/// the inferred types, the same ones that verification by inference uses.
+/// Fails if a method doesn't pass verification by inference, or has unreachable code, which
+/// can't be given a frame. `jsr` and `ret` have to be inlined first
+pub fn compute_stack_maps(
+    class: &mut ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), VerifyErr> {
+    replace_stack_maps(class, hierarchy, |_| true)
+}
+
+/// Optimizes the class like `ClassFile::optimize`, and computes the `StackMapTable`s that it
+/// removes again for classes of version 51 and above. Classes of version 50 fall back to
+/// verification by inference without them. The class is unchanged if it fails.
+/// Returns the number of methods that were changed
+pub fn optimize(
+    class: &mut ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<usize, OptimizeErr> {
+    let mut optimized = class.clone();
+    let changed = optimized.optimize()?;
+    if changed > 0 && optimized.major_version >= REQUIRED_STACK_MAP_VERSION {
+        // the methods that weren't changed keep their frames
+        replace_stack_maps(&mut optimized, hierarchy, |method| {
+            !method.attributes.iter().any(|attr| match &attr.inner {
+                AttributeInfoInner::Code { attributes, .. } => attributes
+                    .iter()
+                    .any(|attr| matches!(attr.inner, AttributeInfoInner::StackMapTable { .. })),
+                _ => false,
+            })
+        })
+        .map_err(|err| OptimizeErr(err.to_string()))?;
+    }
+    *class = optimized;
+    Ok(changed)
+}
+
+/// Replaces the `StackMapTable`s of the methods that `replace` selects
+fn replace_stack_maps(
+    class: &mut ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+    replace: impl Fn(&MethodInfo) -> bool,
+) -> Result<(), VerifyErr> {
+    let hierarchy = Hierarchy {
+        classes: hierarchy,
+        this_class: ClassInfo::of(class),
+    };
+    let mut method_frames = Vec::new();
+    for method in &class.methods {
+        if !replace(method) {
+            method_frames.push(None);
+            continue;
+        }
+        let frames = match method_code(class, method, &hierarchy)? {
+            Some((code, _, initial)) => {
+                let locals = initial.locals.clone();
+                let frames = code
+                    .stack_map_frames(initial)
+                    .map_err(method_err(class, method))?;
+                Some((locals, frames))
+            }
+            None => None,
+        };
+        method_frames.push(frames);
+    }
+
+    let mut pool = ConstantPoolBuilder::from_pool(std::mem::take(&mut class.constant_pool));
+    for (method, frames) in class.methods.iter_mut().zip(method_frames) {
+        let Some((initial, frames)) = frames else {
+            continue;
+        };
+        let entries = encode_frames(&initial, &frames, &mut pool);
+        let stack_map = (!entries.is_empty()).then(|| AttributeInfo {
+            attribute_name_index: pool.utf8("StackMapTable"),
+            attribute_length: 2 + entries.iter().map(frame_length).sum::<u32>(),
+            inner: AttributeInfoInner::StackMapTable {
+                number_of_entries: entries.len() as u16,
+                entries,
+            },
+            original: None,
+        });
+        for attr in &mut method.attributes {
+            if let AttributeInfoInner::Code { attributes, .. } = &mut attr.inner {
+                // the name index and length take up another 6 bytes
+                attributes.retain(|code_attr| {
+                    let is_stack_map =
+                        matches!(code_attr.inner, AttributeInfoInner::StackMapTable { .. });
+                    if is_stack_map {
+                        attr.attribute_length -= code_attr.attribute_length + 6;
+                    }
+                    !is_stack_map
+                });
+                if let Some(stack_map) = stack_map.clone() {
+                    attr.attribute_length += stack_map.attribute_length + 6;
+                    attributes.push(stack_map);
+                }
+            }
+        }
+    }
+    class.constant_pool = pool.build();
+    Ok(())
+}
+
+impl MethodCode<'_> {
+    /// The inferred frames at the branch targets, exception handlers and the instructions after
+    /// unconditional branches, which are the ones the type checker needs
+    fn stack_map_frames(&self, initial: Frame) -> crate::MethodResult<BTreeMap<u32, Frame>> {
+        let frames = self.infer(initial)?;
+        let mut needed = BTreeMap::new();
+        let mut need = |offset: u32, frames: &HashMap<u32, Frame>| {
+            if let Some(frame) = frames.get(&offset) {
+                needed.insert(offset, frame.clone());
+            }
+        };
+        for handler in self.exception_table {
+            need(handler.handler_pc.into(), &frames);
+        }
+        for (index, (offset, instruction)) in self.instructions.iter().enumerate() {
+            let Some(frame) = frames.get(offset) else {
+                return Err((
+                    Some(*offset),
+                    "Unreachable code can't be given a stack map frame".to_string(),
+                ));
+            };
+            let flow = self
+                .context
+                .execute(&mut frame.clone(), *offset, instruction)
+                .map_err(|message| (Some(*offset), message))?;
+            for target in flow.targets {
+                need(target, &frames);
+            }
+            if !flow.falls_through {
+                if let Some((next, _)) = self.instructions.get(index + 1) {
+                    need(*next, &frames);
+                }
+            }
+        }
+        Ok(needed)
+    }
+}
+
+/// Encodes the frames compactly: the frames with the locals of the previous one and at most one
+/// stack value as `same` frames, all others as full frames. `initial` are the locals of the
+/// implicit first frame
+fn encode_frames(
+    initial: &[VType],
+    frames: &BTreeMap<u32, Frame>,
+    pool: &mut ConstantPoolBuilder,
+) -> Vec<StackMapFrame> {
+    let mut entries = Vec::new();
+    let mut previous_offset = None;
+    let mut previous = encode_locals(initial, pool);
+    for (offset, frame) in frames {
+        let locals = encode_locals(&frame.locals, pool);
+        let mut stack = frame
+            .stack
+            .iter()
+            .map(|value| encode_type(value, pool))
+            .collect::<Vec<_>>();
+        let offset_delta = match previous_offset {
+            None => *offset,
+            Some(previous) => offset - previous - 1,
+        } as u16;
+        let entry = match (locals == previous, stack.len()) {
+            (true, 0) if offset_delta < 64 => StackMapFrame::SameFrame {
+                frame_type: offset_delta as u8,
+            },
+            (true, 0) => StackMapFrame::SameFrameExtended {
+                frame_type: 251,
+                offset_delta,
+            },
+            (true, 1) if offset_delta < 64 => StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 64 + offset_delta as u8,
+                stack: stack.remove(0),
+            },
+            (true, 1) => StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type: 247,
+                offset_delta,
+                stack: stack.remove(0),
+            },
+            _ => StackMapFrame::FullFrame {
+                frame_type: 255,
+                offset_delta,
+                locals: locals.clone(),
+                stack,
+            },
+        };
+        entries.push(entry);
+        previous_offset = Some(*offset);
+        previous = locals;
+    }
+    entries
+}
+
+/// The locals of a frame like a `StackMapTable` has them: `long` and `double` are a single entry,
+/// and unset variables at the end are left out
+fn encode_locals(locals: &[VType], pool: &mut ConstantPoolBuilder) -> Vec<VerificationTypeInfo> {
+    let mut encoded = Vec::new();
+    let mut slots = locals.iter();
+    while let Some(local) = slots.next() {
+        encoded.push(encode_type(local, pool));
+        if local.size() == 2 {
+            slots.next();
+        }
+    }
+    while encoded
+        .last()
+        .is_some_and(|local| matches!(local, VerificationTypeInfo::Top { .. }))
+    {
+        encoded.pop();
+    }
+    encoded
+}
+
+fn encode_type(value: &VType, pool: &mut ConstantPoolBuilder) -> VerificationTypeInfo {
+    match value {
+        VType::Top => VerificationTypeInfo::Top { tag: 0 },
+        VType::Integer => VerificationTypeInfo::Integer { tag: 1 },
+        VType::Float => VerificationTypeInfo::Float { tag: 2 },
+        VType::Double => VerificationTypeInfo::Double { tag: 3 },
+        VType::Long => VerificationTypeInfo::Long { tag: 4 },
+        VType::Null => VerificationTypeInfo::Null { tag: 5 },
+        VType::UninitializedThis => VerificationTypeInfo::UninitializedThis { tag: 6 },
+        VType::Reference(name) => VerificationTypeInfo::Object {
+            tag: 7,
+            cpool_index: pool.class(name),
+        },
+        VType::Uninitialized(offset) => VerificationTypeInfo::Uninitialized {
+            tag: 8,
+            offset: *offset as u16,
+        },
+    }
+}
+
+/// The number of bytes of the frame in the attribute
+fn frame_length(frame: &StackMapFrame) -> u32 {
+    let types = |types: &[VerificationTypeInfo]| -> u32 {
+        types
+            .iter()
+            .map(|info| match info {
+                VerificationTypeInfo::Object { .. }
+                | VerificationTypeInfo::Uninitialized { .. } => 3,
+                _ => 1,
+            })
+            .sum()
+    };
+    match frame {
+        StackMapFrame::SameFrame { .. } => 1,
+        StackMapFrame::SameLocals1StackItemFrame { stack, .. } => 1 + types(&[*stack]),
+        StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => 3 + types(&[*stack]),
+        StackMapFrame::ChopFrame { .. } | StackMapFrame::SameFrameExtended { .. } => 3,
+        StackMapFrame::AppendFrame { locals, .. } => 3 + types(locals),
+        StackMapFrame::FullFrame { locals, stack, .. } => 7 + types(locals) + types(stack),
+    }
+}
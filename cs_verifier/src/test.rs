@@ -0,0 +1,391 @@
+use super::*;
+use crate::types::OBJECT;
+use cs_parser::{
+    parse_class_file, Assembler, ClassFileBuilder, ConstantPoolBuilder, VerificationTypeInfo,
+};
+
+fn fixtures() -> Vec<ClassFile> {
+    [
+        &include_bytes!("../../cs_parser/testdata/Attributes.class")[..],
+        &include_bytes!("../../cs_parser/testdata/Instructions.class")[..],
+        &include_bytes!("../../cs_parser/testdata/Nesting.class")[..],
+        &include_bytes!("../../cs_parser/testdata/Point.class")[..],
+        &include_bytes!("../../cs_parser/testdata/Test.class")[..],
+        &include_bytes!("../../cs_parser/testdata/Test2.class")[..],
+    ]
+    .into_iter()
+    .map(|bytes| parse_class_file(bytes).unwrap())
+    .collect()
+}
+
+/// A class with a single static method, the code is assembled with the constant pool of the class
+fn class_with_method(
+    version: u16,
+    descriptor: &str,
+    max_stack: u16,
+    max_locals: u16,
+    code: impl FnOnce(&mut Assembler, &mut ConstantPoolBuilder),
+) -> ClassFile {
+    let mut builder = ClassFileBuilder::new("Verified").version(version, 0);
+    let mut asm = Assembler::new();
+    code(&mut asm, builder.constant_pool());
+    let code = cs_parser::MethodCode {
+        max_stack,
+        max_locals,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    builder
+        .add_method(MethodAccessFlag::STATIC, "method", descriptor, Some(code))
+        .build()
+}
+
+fn verify_err(class: &ClassFile) -> VerifyErr {
+    verify_class(class, &HashMap::new()).unwrap_err()
+}
+
+#[test]
+fn verify_fixtures() {
+    for class in fixtures() {
+        verify_class(&class, &HashMap::new()).unwrap();
+    }
+}
+
+#[test]
+fn verify_fixtures_by_inference() {
+    for mut class in fixtures() {
+        class.major_version = 49;
+        verify_class(&class, &HashMap::new()).unwrap();
+    }
+}
+
+#[test]
+fn stack_underflow() {
+    let class = class_with_method(52, "()I", 1, 0, |asm, _| {
+        asm.push(Instruction::Iadd);
+        asm.push(Instruction::Ireturn);
+    });
+    let err = verify_err(&class);
+    assert_eq!(err.method, "method()I");
+    assert_eq!(err.offset, Some(0));
+    assert_eq!(err.message, "Stack underflow");
+}
+
+#[test]
+fn wrong_return_type() {
+    let class = class_with_method(52, "()I", 1, 0, |asm, _| {
+        asm.push(Instruction::Fconst0);
+        asm.push(Instruction::Ireturn);
+    });
+    assert_eq!(verify_err(&class).offset, Some(1));
+
+    let class = class_with_method(52, "()V", 1, 0, |asm, _| {
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Ireturn);
+    });
+    assert_eq!(verify_err(&class).offset, Some(1));
+}
+
+#[test]
+fn unset_local_variable() {
+    let class = class_with_method(52, "(I)V", 1, 2, |asm, _| {
+        asm.push(Instruction::Aload1);
+        asm.push(Instruction::Pop);
+        asm.push(Instruction::Return);
+    });
+    assert_eq!(verify_err(&class).offset, Some(0));
+}
+
+#[test]
+fn falling_off_the_end() {
+    let class = class_with_method(52, "()V", 1, 0, |asm, _| {
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Pop);
+    });
+    assert_eq!(verify_err(&class).offset, Some(1));
+}
+
+#[test]
+fn max_stack_exceeded() {
+    let class = class_with_method(52, "()V", 1, 0, |asm, _| {
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Return);
+    });
+    assert_eq!(verify_err(&class).offset, Some(1));
+}
+
+#[test]
+fn missing_stack_map_frame() {
+    let class = class_with_method(52, "(I)I", 1, 1, |asm, _| {
+        let zero = asm.new_label();
+        asm.push(Instruction::Iload0);
+        asm.branch(Instruction::Ifeq, zero);
+        asm.push(Instruction::Iconst1);
+        asm.push(Instruction::Ireturn);
+        asm.place(zero);
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Ireturn);
+    });
+    assert!(verify_class(&class, &HashMap::new()).is_err());
+
+    // without a StackMapTable, the frame at the branch target is inferred
+    let mut class = class;
+    class.major_version = 49;
+    verify_class(&class, &HashMap::new()).unwrap();
+}
+
+#[test]
+fn incompatible_merge() {
+    let class = class_with_method(49, "(I)I", 1, 1, |asm, _| {
+        let zero = asm.new_label();
+        let end = asm.new_label();
+        asm.push(Instruction::Iload0);
+        asm.branch(Instruction::Ifeq, zero);
+        asm.push(Instruction::Iconst1);
+        asm.branch(Instruction::Goto, end);
+        asm.place(zero);
+        asm.push(Instruction::Fconst0);
+        asm.place(end);
+        asm.push(Instruction::Ireturn);
+    });
+    let err = verify_err(&class);
+    assert_eq!(err.offset, Some(8));
+    assert_eq!(
+        err.message,
+        "At 9: Stack values Integer and Float are incompatible"
+    );
+}
+
+#[test]
+fn merged_references() {
+    let mut hierarchy = HashMap::new();
+    for name in ["Base", "Left", "Right"] {
+        let super_class = if name == "Base" { OBJECT } else { "Base" };
+        hierarchy.insert(
+            name.to_string(),
+            ClassInfo {
+                super_class: Some(super_class.to_string()),
+                is_interface: false,
+            },
+        );
+    }
+    let class = |descriptor: &str| {
+        class_with_method(49, descriptor, 1, 3, |asm, _| {
+            let left = asm.new_label();
+            let end = asm.new_label();
+            asm.push(Instruction::Iload0);
+            asm.branch(Instruction::Ifeq, left);
+            asm.push(Instruction::Aload2);
+            asm.branch(Instruction::Goto, end);
+            asm.place(left);
+            asm.push(Instruction::Aload1);
+            asm.place(end);
+            asm.push(Instruction::Areturn);
+        })
+    };
+
+    verify_class(&class("(ILLeft;LRight;)LBase;"), &hierarchy).unwrap();
+    let err = verify_class(&class("(ILLeft;LRight;)LLeft;"), &hierarchy).unwrap_err();
+    assert_eq!(err.offset, Some(9));
+    // without the hierarchy, the classes are assumed to be assignable
+    verify_class(&class("(ILLeft;LRight;)LLeft;"), &HashMap::new()).unwrap();
+}
+
+#[test]
+fn uninitialized_object() {
+    let class = class_with_method(52, "()Ljava/lang/Object;", 2, 0, |asm, cp| {
+        let object = cp.class(OBJECT).inner();
+        asm.push(Instruction::New(object));
+        asm.push(Instruction::Areturn);
+    });
+    assert_eq!(verify_err(&class).offset, Some(3));
+
+    let class = class_with_method(52, "()Ljava/lang/Object;", 2, 0, |asm, cp| {
+        let object = cp.class(OBJECT).inner();
+        let constructor = cp.method_ref(OBJECT, "<init>", "()V").inner();
+        asm.push(Instruction::New(object));
+        asm.push(Instruction::Dup);
+        asm.push(Instruction::Invokespecial(constructor));
+        asm.push(Instruction::Areturn);
+    });
+    verify_class(&class, &HashMap::new()).unwrap();
+}
+
+#[test]
+fn constructor_must_initialize_this() {
+    let class = ClassFileBuilder::new("Verified")
+        .add_default_constructor()
+        .build();
+    verify_class(&class, &HashMap::new()).unwrap();
+
+    let mut asm = Assembler::new();
+    asm.push(Instruction::Return);
+    let code = cs_parser::MethodCode {
+        max_stack: 0,
+        max_locals: 1,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let class = ClassFileBuilder::new("Verified")
+        .add_method(MethodAccessFlag::PUBLIC, "<init>", "()V", Some(code))
+        .build();
+    assert_eq!(verify_err(&class).offset, Some(0));
+}
+
+#[test]
+fn subroutines() {
+    let subroutine = |version| {
+        class_with_method(version, "()V", 1, 1, |asm, _| {
+            let subroutine = asm.new_label();
+            asm.branch(Instruction::Jsr, subroutine);
+            asm.push(Instruction::Return);
+            asm.place(subroutine);
+            asm.push(Instruction::Astore0);
+            asm.push(Instruction::Ret(0));
+        })
+    };
+    let err = verify_err(&subroutine(52));
+    assert_eq!(err.offset, Some(0));
+    assert_eq!(err.message, "jsr and ret are not allowed since version 51");
+
+    let mut class = subroutine(49);
+    assert!(verify_err(&class)
+        .message
+        .contains("ClassFile::inline_subroutines"));
+    class.inline_subroutines().unwrap();
+    verify_class(&class, &HashMap::new()).unwrap();
+}
+
+#[test]
+fn retarget_computes_stack_maps() {
+    for mut class in fixtures() {
+        let version = class.major_version;
+        if class.retarget(49).is_err() {
+            // uses features of newer versions, like nest mates
+            continue;
+        }
+        retarget(&mut class, version, &HashMap::new()).unwrap();
+        assert_eq!(class.major_version, version);
+        verify_class(&class, &HashMap::new()).unwrap();
+        let written = cs_parser::write_class_file(&class).unwrap();
+        assert_eq!(parse_class_file(&written).unwrap(), class);
+    }
+
+    // the merged type of `Left` and `Right` in the frame at the return
+    let mut hierarchy = HashMap::new();
+    for (name, super_class) in [("Base", OBJECT), ("Left", "Base"), ("Right", "Base")] {
+        let info = ClassInfo {
+            super_class: Some(super_class.to_string()),
+            is_interface: false,
+        };
+        hierarchy.insert(name.to_string(), info);
+    }
+    let mut class = class_with_method(49, "(ILLeft;LRight;)LBase;", 1, 3, |asm, _| {
+        let left = asm.new_label();
+        let end = asm.new_label();
+        asm.push(Instruction::Iload0);
+        asm.branch(Instruction::Ifeq, left);
+        asm.push(Instruction::Aload2);
+        asm.branch(Instruction::Goto, end);
+        asm.place(left);
+        asm.push(Instruction::Aload1);
+        asm.place(end);
+        asm.push(Instruction::Areturn);
+    });
+    let err = class.retarget(52).unwrap_err();
+    assert!(err.0.contains("cs_verifier::retarget"), "{}", err);
+    retarget(&mut class, 52, &hierarchy).unwrap();
+    verify_class(&class, &hierarchy).unwrap();
+    let cp = &class.constant_pool;
+    let entries = class.methods[0]
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code { attributes, .. } => Some(attributes),
+            _ => None,
+        })
+        .unwrap()
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::StackMapTable { entries, .. } => Some(entries.clone()),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(matches!(
+        entries[0],
+        StackMapFrame::SameFrame { frame_type: 8 }
+    ));
+    match &entries[1] {
+        StackMapFrame::SameLocals1StackItemFrame {
+            frame_type: 64,
+            stack: VerificationTypeInfo::Object { cpool_index, .. },
+        } => assert_eq!(cpool_index.get(cp).name_index.get(cp), "Base"),
+        frame => panic!("Unexpected frame {:?}", frame),
+    }
+
+    // subroutines are inlined, unreachable code can't get a frame
+    let mut class = class_with_method(49, "()V", 1, 1, |asm, _| {
+        let subroutine = asm.new_label();
+        asm.branch(Instruction::Jsr, subroutine);
+        asm.push(Instruction::Return);
+        asm.place(subroutine);
+        asm.push(Instruction::Astore0);
+        asm.push(Instruction::Ret(0));
+    });
+    retarget(&mut class, 52, &HashMap::new()).unwrap();
+    verify_class(&class, &HashMap::new()).unwrap();
+    let mut class = class_with_method(49, "()V", 0, 0, |asm, _| {
+        asm.push(Instruction::Return);
+        asm.push(Instruction::Return);
+    });
+    let err = retarget(&mut class, 52, &HashMap::new()).unwrap_err();
+    assert_eq!(
+        err.0,
+        "Verification of method()V failed at offset 1: \
+         Unreachable code can't be given a stack map frame"
+    );
+    assert_eq!(class.major_version, 49);
+}
+
+#[test]
+fn optimize_computes_stack_maps() {
+    for mut class in fixtures() {
+        let mut parsed = class.clone();
+        let changed = optimize(&mut class, &HashMap::new()).unwrap();
+        assert_eq!(changed, parsed.optimize().unwrap());
+        verify_class(&class, &HashMap::new()).unwrap();
+        let written = cs_parser::write_class_file(&class).unwrap();
+        assert_eq!(parse_class_file(&written).unwrap(), class);
+    }
+
+    // removing the nop moves the branch target, so its frame is computed again
+    let mut class = class_with_method(52, "(I)I", 1, 1, |asm, _| {
+        let zero = asm.new_label();
+        let end = asm.new_label();
+        asm.push(Instruction::Iload0);
+        asm.branch(Instruction::Ifeq, zero);
+        asm.push(Instruction::Iconst1);
+        asm.branch(Instruction::Goto, end);
+        asm.place(zero);
+        asm.push(Instruction::Nop);
+        asm.push(Instruction::Iconst0);
+        asm.place(end);
+        asm.push(Instruction::Ireturn);
+    });
+    compute_stack_maps(&mut class, &HashMap::new()).unwrap();
+    assert_eq!(optimize(&mut class, &HashMap::new()).unwrap(), 1);
+    verify_class(&class, &HashMap::new()).unwrap();
+    let stack_map = class.methods[0]
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code { attributes, .. } => Some(attributes),
+            _ => None,
+        })
+        .unwrap()
+        .iter()
+        .find(|attr| matches!(attr.inner, AttributeInfoInner::StackMapTable { .. }));
+    assert!(stack_map.is_some());
+}
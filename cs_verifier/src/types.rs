@@ -0,0 +1,160 @@
+//!
+//! The types the verifier tracks for local variables and stack values
+//!
+
+use cs_parser::{ConstantPool, CpInfoInner, VerificationTypeInfo};
+
+/// The type of a local variable or a value on the operand stack
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum VType {
+    /// Unusable, like an unset local variable or the second slot of a `long`
+    Top,
+    /// Also used for `boolean`, `byte`, `char` and `short`
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in a constructor before the super constructor was called
+    UninitializedThis,
+    /// An object created by the `new` instruction at the offset, before its constructor was called
+    Uninitialized(u32),
+    /// A class by its binary name, like `java/lang/String`, or an array by its descriptor, like `[I`
+    Reference(String),
+}
+
+pub const OBJECT: &str = "java/lang/Object";
+pub const THROWABLE: &str = "java/lang/Throwable";
+
+impl VType {
+    pub fn reference(name: &str) -> Self {
+        Self::Reference(name.to_string())
+    }
+
+    /// The number of slots the type takes up
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Long | Self::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            Self::Null | Self::UninitializedThis | Self::Uninitialized(_) | Self::Reference(_)
+        )
+    }
+
+    /// The type of a stack map frame entry
+    pub fn from_verification_type(info: &VerificationTypeInfo, cp: &ConstantPool) -> Option<Self> {
+        Some(match info {
+            VerificationTypeInfo::Top { .. } => Self::Top,
+            VerificationTypeInfo::Integer { .. } => Self::Integer,
+            VerificationTypeInfo::Float { .. } => Self::Float,
+            VerificationTypeInfo::Long { .. } => Self::Long,
+            VerificationTypeInfo::Double { .. } => Self::Double,
+            VerificationTypeInfo::Null { .. } => Self::Null,
+            VerificationTypeInfo::UninitializedThis { .. } => Self::UninitializedThis,
+            VerificationTypeInfo::Object { cpool_index, .. } => {
+                Self::reference(class_name(cp, cpool_index.inner())?)
+            }
+            VerificationTypeInfo::Uninitialized { offset, .. } => {
+                Self::Uninitialized((*offset).into())
+            }
+        })
+    }
+}
+
+/// The entry at `index`, if there is one
+pub fn constant(cp: &ConstantPool, index: u16) -> Option<&CpInfoInner> {
+    cp.entry(index).map(|info| &info.inner)
+}
+
+/// The name of the `Class` constant at `index`
+pub fn class_name(cp: &ConstantPool, index: u16) -> Option<&str> {
+    match constant(cp, index)? {
+        CpInfoInner::Class(class) => Some(class.name_index.get(cp)),
+        _ => None,
+    }
+}
+
+/// The class, name and descriptor of a field or method reference
+pub fn member_ref(cp: &ConstantPool, index: u16) -> Option<(&str, &str, &str)> {
+    let (class, name_and_type) = match constant(cp, index)? {
+        CpInfoInner::Fieldref(field) => (field.class_index, field.name_and_type_index),
+        CpInfoInner::MethodRef(method) => (method.class_index, method.name_and_type_index),
+        CpInfoInner::InterfaceMethodref(method) => (method.class_index, method.name_and_type_index),
+        _ => return None,
+    };
+    let name_and_type = name_and_type.get(cp);
+    Some((
+        class.get(cp).name_index.get(cp),
+        name_and_type.name_index.get(cp),
+        name_and_type.descriptor_index.get(cp),
+    ))
+}
+
+/// Parses a field descriptor from the start of `descriptor`, returning its type and the rest
+pub fn parse_field_type(descriptor: &str) -> Option<(VType, &str)> {
+    let mut chars = descriptor.chars();
+    let vtype = match chars.next()? {
+        'B' | 'C' | 'I' | 'S' | 'Z' => VType::Integer,
+        'F' => VType::Float,
+        'J' => VType::Long,
+        'D' => VType::Double,
+        'L' => {
+            let end = descriptor.find(';')?;
+            return Some((
+                VType::reference(&descriptor[1..end]),
+                &descriptor[end + 1..],
+            ));
+        }
+        '[' => {
+            let dimensions = descriptor.len() - descriptor.trim_start_matches('[').len();
+            let (_, rest) = parse_field_type(&descriptor[dimensions..])?;
+            let end = descriptor.len() - rest.len();
+            return Some((VType::reference(&descriptor[..end]), rest));
+        }
+        _ => return None,
+    };
+    Some((vtype, chars.as_str()))
+}
+
+/// The type of a field descriptor that has to be the whole string
+pub fn field_type(descriptor: &str) -> Option<VType> {
+    match parse_field_type(descriptor)? {
+        (vtype, "") => Some(vtype),
+        _ => None,
+    }
+}
+
+/// The parameter types and the return type of a method descriptor, `None` as return type for `void`
+pub fn method_type(descriptor: &str) -> Option<(Vec<VType>, Option<VType>)> {
+    let mut rest = descriptor.strip_prefix('(')?;
+    let mut parameters = Vec::new();
+    while !rest.starts_with(')') {
+        let (parameter, next) = parse_field_type(rest)?;
+        parameters.push(parameter);
+        rest = next;
+    }
+    let return_type = match &rest[1..] {
+        "V" => None,
+        rest => Some(field_type(rest)?),
+    };
+    Some((parameters, return_type))
+}
+
+/// The element type of an array type like `[I` or `[Ljava/lang/String;`
+pub fn array_element(array: &str) -> Option<VType> {
+    field_type(array.strip_prefix('[')?)
+}
+
+/// The descriptor of an array with elements of the class or array type `name`
+pub fn array_of(name: &str) -> String {
+    if name.starts_with('[') {
+        format!("[{}", name)
+    } else {
+        format!("[L{};", name)
+    }
+}
@@ -0,0 +1,725 @@
+//!
+//! The classes and native methods the VM provides for the parts of the JDK that simple programs
+//! use, like `System.out.println`
+//!
+
+use crate::error::VmError;
+use crate::heap::Heap;
+use crate::thread::Schedule;
+use crate::value::{ObjectRef, Value};
+use cs_model::names::internal_to_binary;
+use cs_parser::{
+    encode_code, ClassAccessFlag, ClassFile, ClassFileBuilder, FieldAccessFlag, FieldAccessFlags,
+    Instruction, MethodAccessFlag, MethodCode,
+};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::sync::{Arc, OnceLock};
+
+pub(crate) const OBJECT_CLASS: &str = "java/lang/Object";
+pub(crate) const STRING_CLASS: &str = "java/lang/String";
+const SYSTEM_CLASS: &str = "java/lang/System";
+const PRINT_STREAM_CLASS: &str = "java/io/PrintStream";
+const RUNNABLE_CLASS: &str = "java/lang/Runnable";
+const THREAD_CLASS: &str = "java/lang/Thread";
+pub(crate) const THROWABLE_CLASS: &str = "java/lang/Throwable";
+/// The field of `Throwable` with the message
+pub(crate) const MESSAGE_FIELD: (&str, &str) = ("detailMessage", "Ljava/lang/String;");
+pub(crate) const REFERENCE_CLASS: &str = "java/lang/ref/Reference";
+const WEAK_REFERENCE_CLASS: &str = "java/lang/ref/WeakReference";
+pub(crate) const REFERENCE_QUEUE_CLASS: &str = "java/lang/ref/ReferenceQueue";
+/// The fields of `Reference` with the object it refers to, the queue it is put on once the object
+/// was collected, and the next reference in that queue
+pub(crate) const REFERENT_FIELD: (&str, &str) = ("referent", "Ljava/lang/Object;");
+pub(crate) const QUEUE_FIELD: (&str, &str) = ("queue", "Ljava/lang/ref/ReferenceQueue;");
+pub(crate) const NEXT_FIELD: (&str, &str) = ("next", "Ljava/lang/ref/Reference;");
+/// The field of `ReferenceQueue` with the reference that was enqueued last
+pub(crate) const QUEUE_HEAD_FIELD: (&str, &str) = ("head", "Ljava/lang/ref/Reference;");
+
+/// The builtin subclasses of `Throwable` with their superclass, which include all the exceptions
+/// and errors the interpreter throws
+const THROWABLES: [(&str, &str); 26] = [
+    ("java/lang/Exception", THROWABLE_CLASS),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    (
+        "java/lang/ArithmeticException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NullPointerException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalArgumentException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IndexOutOfBoundsException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/ArrayIndexOutOfBoundsException",
+        "java/lang/IndexOutOfBoundsException",
+    ),
+    (
+        "java/lang/ArrayStoreException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/NegativeArraySizeException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalThreadStateException",
+        "java/lang/IllegalArgumentException",
+    ),
+    (
+        "java/lang/IllegalStateException",
+        "java/lang/RuntimeException",
+    ),
+    (
+        "java/lang/IllegalMonitorStateException",
+        "java/lang/RuntimeException",
+    ),
+    ("java/lang/Error", THROWABLE_CLASS),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    (
+        "java/lang/StackOverflowError",
+        "java/lang/VirtualMachineError",
+    ),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/ClassFormatError", "java/lang/LinkageError"),
+    (
+        "java/lang/UnsupportedClassVersionError",
+        "java/lang/ClassFormatError",
+    ),
+    ("java/lang/VerifyError", "java/lang/LinkageError"),
+    ("java/lang/ClassCircularityError", "java/lang/LinkageError"),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    (
+        "java/lang/IncompatibleClassChangeError",
+        "java/lang/LinkageError",
+    ),
+    (
+        "java/lang/NoSuchFieldError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/NoSuchMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+    (
+        "java/lang/AbstractMethodError",
+        "java/lang/IncompatibleClassChangeError",
+    ),
+];
+
+/// A method implemented in rust. The arguments start with the object for instance methods
+pub type NativeMethod = fn(&mut NativeContext<'_>, &[Value]) -> Result<Option<Value>, VmError>;
+
+/// The parts of the VM that native methods can use
+pub struct NativeContext<'a> {
+    pub heap: &'a mut Heap,
+    /// Where `System.out` writes to
+    pub stdout: &'a mut dyn Write,
+    /// The `java/lang/Thread`s that were started and haven't finished
+    pub(crate) alive_threads: &'a HashSet<ObjectRef>,
+    /// Set by the natives of `Thread` to switch threads
+    pub(crate) schedule: Schedule,
+}
+
+/// The classes that are used if no class with their name was added:
+/// - `Object` with an empty constructor
+/// - `String` without fields
+/// - `System` with the field `out` and a native `gc`
+/// - `PrintStream` with native `print` and `println` methods
+/// - `Runnable`
+/// - `Thread` with a target `Runnable`, and native methods to start, join and yield threads
+/// - `Throwable` with a message, and the exceptions and errors the interpreter throws, which only
+///   have its constructors
+/// - `Reference` with `get` and `clear`, `WeakReference` with its constructors, whose referents
+///   the garbage collector clears, and `ReferenceQueue` with only `poll`
+pub(crate) fn builtin_class(name: &str) -> Option<Arc<ClassFile>> {
+    static OBJECT: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static RUNNABLE: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static THREAD: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static STRING: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static SYSTEM: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static PRINT_STREAM: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static REFERENCE: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static WEAK_REFERENCE: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    static REFERENCE_QUEUE: OnceLock<Arc<ClassFile>> = OnceLock::new();
+    let class = match name {
+        OBJECT_CLASS => OBJECT.get_or_init(|| {
+            let builder = ClassFileBuilder::new(OBJECT_CLASS).no_super_class();
+            Arc::new(add_code(builder, "<init>", "()V", 1, &[Instruction::Return]).build())
+        }),
+        RUNNABLE_CLASS => RUNNABLE.get_or_init(|| {
+            let class = ClassFileBuilder::new(RUNNABLE_CLASS)
+                .access_flags(
+                    ClassAccessFlag::Public
+                        | ClassAccessFlag::Interface
+                        | ClassAccessFlag::Abstract,
+                )
+                .add_method(
+                    MethodAccessFlag::PUBLIC | MethodAccessFlag::ABSTRACT,
+                    "run",
+                    "()V",
+                    None,
+                )
+                .build();
+            Arc::new(class)
+        }),
+        THREAD_CLASS => THREAD.get_or_init(|| {
+            let mut builder = ClassFileBuilder::new(THREAD_CLASS)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super)
+                .add_interface(RUNNABLE_CLASS)
+                .add_field(FieldAccessFlag::PRIVATE, "target", "Ljava/lang/Runnable;");
+            let pool = builder.constant_pool();
+            let super_constructor = pool.method_ref(OBJECT_CLASS, "<init>", "()V").inner();
+            let target = pool
+                .field_ref(THREAD_CLASS, "target", "Ljava/lang/Runnable;")
+                .inner();
+            let run = pool
+                .interface_method_ref(RUNNABLE_CLASS, "run", "()V")
+                .inner();
+            let constructor = [
+                Instruction::Aload0,
+                Instruction::Invokespecial(super_constructor),
+                Instruction::Return,
+            ];
+            let target_constructor = [
+                Instruction::Aload0,
+                Instruction::Invokespecial(super_constructor),
+                Instruction::Aload0,
+                Instruction::Aload1,
+                Instruction::Putfield(target),
+                Instruction::Return,
+            ];
+            let run_target = [
+                Instruction::Aload0,
+                Instruction::Getfield(target),
+                Instruction::Astore1,
+                Instruction::Aload1,
+                // to the return
+                Instruction::Ifnull(9),
+                Instruction::Aload1,
+                Instruction::Invokeinterface {
+                    index: run,
+                    count: 1,
+                },
+                Instruction::Return,
+            ];
+            builder = add_code(builder, "<init>", "()V", 1, &constructor);
+            builder = add_code(
+                builder,
+                "<init>",
+                "(Ljava/lang/Runnable;)V",
+                2,
+                &target_constructor,
+            );
+            builder = add_code(builder, "run", "()V", 2, &run_target);
+            for (flags, name, descriptor, _) in THREAD_NATIVES {
+                builder =
+                    builder.add_method(flags | MethodAccessFlag::NATIVE, name, descriptor, None);
+            }
+            Arc::new(builder.build())
+        }),
+        STRING_CLASS => STRING.get_or_init(|| {
+            let class = ClassFileBuilder::new(STRING_CLASS)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Final)
+                .build();
+            Arc::new(class)
+        }),
+        SYSTEM_CLASS => SYSTEM.get_or_init(|| {
+            let mut builder = ClassFileBuilder::new(SYSTEM_CLASS)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Final)
+                .add_field(
+                    FieldAccessFlag::PUBLIC | FieldAccessFlag::STATIC | FieldAccessFlag::FINAL,
+                    "out",
+                    "Ljava/io/PrintStream;",
+                );
+            let pool = builder.constant_pool();
+            let print_stream = pool.class(PRINT_STREAM_CLASS).inner();
+            let constructor = pool.method_ref(PRINT_STREAM_CLASS, "<init>", "()V").inner();
+            let out = pool
+                .field_ref(SYSTEM_CLASS, "out", "Ljava/io/PrintStream;")
+                .inner();
+            let code = [
+                Instruction::New(print_stream),
+                Instruction::Dup,
+                Instruction::Invokespecial(constructor),
+                Instruction::Putstatic(out),
+                Instruction::Return,
+            ];
+            builder = add_code(builder, "<clinit>", "()V", 0, &code);
+            let flags = MethodAccessFlag::PUBLIC | MethodAccessFlag::STATIC;
+            let gc = builder.add_method(flags | MethodAccessFlag::NATIVE, "gc", "()V", None);
+            Arc::new(gc.build())
+        }),
+        PRINT_STREAM_CLASS => PRINT_STREAM.get_or_init(|| {
+            let mut builder = ClassFileBuilder::new(PRINT_STREAM_CLASS).add_default_constructor();
+            for (descriptor, _, _) in PRINTED_TYPES {
+                for name in ["print", "println"] {
+                    builder = builder.add_method(
+                        MethodAccessFlag::PUBLIC | MethodAccessFlag::NATIVE,
+                        name,
+                        descriptor,
+                        None,
+                    );
+                }
+            }
+            let flags = MethodAccessFlag::PUBLIC | MethodAccessFlag::NATIVE;
+            Arc::new(builder.add_method(flags, "println", "()V", None).build())
+        }),
+        REFERENCE_CLASS => REFERENCE.get_or_init(|| Arc::new(reference_class())),
+        WEAK_REFERENCE_CLASS => WEAK_REFERENCE.get_or_init(|| {
+            let mut builder = ClassFileBuilder::new(WEAK_REFERENCE_CLASS)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super)
+                .super_class(REFERENCE_CLASS);
+            let pool = builder.constant_pool();
+            let constructor = pool
+                .method_ref(REFERENCE_CLASS, "<init>", "(Ljava/lang/Object;)V")
+                .inner();
+            let queue_constructor = pool
+                .method_ref(REFERENCE_CLASS, "<init>", QUEUE_CONSTRUCTOR)
+                .inner();
+            let code = [
+                Instruction::Aload0,
+                Instruction::Aload1,
+                Instruction::Invokespecial(constructor),
+                Instruction::Return,
+            ];
+            let queue_code = [
+                Instruction::Aload0,
+                Instruction::Aload1,
+                Instruction::Aload2,
+                Instruction::Invokespecial(queue_constructor),
+                Instruction::Return,
+            ];
+            builder = add_code(builder, "<init>", "(Ljava/lang/Object;)V", 2, &code);
+            builder = add_code(builder, "<init>", QUEUE_CONSTRUCTOR, 3, &queue_code);
+            Arc::new(builder.build())
+        }),
+        REFERENCE_QUEUE_CLASS => REFERENCE_QUEUE.get_or_init(|| {
+            let (head, descriptor) = QUEUE_HEAD_FIELD;
+            let mut builder = ClassFileBuilder::new(REFERENCE_QUEUE_CLASS)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super)
+                .add_field(FieldAccessFlag::PRIVATE, head, descriptor)
+                .add_default_constructor();
+            let pool = builder.constant_pool();
+            let head = pool
+                .field_ref(REFERENCE_QUEUE_CLASS, head, descriptor)
+                .inner();
+            let (next, descriptor) = NEXT_FIELD;
+            let next = pool.field_ref(REFERENCE_CLASS, next, descriptor).inner();
+            // removes the reference that was enqueued last, the order isn't specified
+            let poll = [
+                Instruction::Aload0,
+                Instruction::Getfield(head),
+                Instruction::Astore1,
+                Instruction::Aload1,
+                // to the return
+                Instruction::Ifnull(16),
+                Instruction::Aload0,
+                Instruction::Aload1,
+                Instruction::Getfield(next),
+                Instruction::Putfield(head),
+                Instruction::Aload1,
+                Instruction::AconstNull,
+                Instruction::Putfield(next),
+                Instruction::Aload1,
+                Instruction::Areturn,
+            ];
+            builder = add_code(builder, "poll", "()Ljava/lang/ref/Reference;", 2, &poll);
+            Arc::new(builder.build())
+        }),
+        _ => return builtin_throwable(name),
+    };
+    Some(Arc::clone(class))
+}
+
+/// `Throwable` or one of the `THROWABLES`, they are all created the first time one is used
+fn builtin_throwable(name: &str) -> Option<Arc<ClassFile>> {
+    static THROWABLE_CLASSES: OnceLock<HashMap<&str, Arc<ClassFile>>> = OnceLock::new();
+    let classes = THROWABLE_CLASSES.get_or_init(|| {
+        let mut classes = HashMap::new();
+        classes.insert(THROWABLE_CLASS, Arc::new(throwable_class()));
+        for (name, super_class) in THROWABLES {
+            let mut builder = ClassFileBuilder::new(name)
+                .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super)
+                .super_class(super_class);
+            let pool = builder.constant_pool();
+            let constructor = pool.method_ref(super_class, "<init>", "()V").inner();
+            let message_constructor = pool
+                .method_ref(super_class, "<init>", "(Ljava/lang/String;)V")
+                .inner();
+            let code = [
+                Instruction::Aload0,
+                Instruction::Invokespecial(constructor),
+                Instruction::Return,
+            ];
+            let message_code = [
+                Instruction::Aload0,
+                Instruction::Aload1,
+                Instruction::Invokespecial(message_constructor),
+                Instruction::Return,
+            ];
+            builder = add_code(builder, "<init>", "()V", 1, &code);
+            builder = add_code(builder, "<init>", "(Ljava/lang/String;)V", 2, &message_code);
+            classes.insert(name, Arc::new(builder.build()));
+        }
+        classes
+    });
+    classes.get(name).cloned()
+}
+
+/// `Throwable` with the constructors that take no message or a message, and `getMessage`
+fn throwable_class() -> ClassFile {
+    let (field, descriptor) = MESSAGE_FIELD;
+    let mut builder = ClassFileBuilder::new(THROWABLE_CLASS)
+        .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super)
+        .add_field(FieldAccessFlag::PRIVATE, field, descriptor);
+    let pool = builder.constant_pool();
+    let super_constructor = pool.method_ref(OBJECT_CLASS, "<init>", "()V").inner();
+    let message = pool.field_ref(THROWABLE_CLASS, field, descriptor).inner();
+    let constructor = [
+        Instruction::Aload0,
+        Instruction::Invokespecial(super_constructor),
+        Instruction::Return,
+    ];
+    let message_constructor = [
+        Instruction::Aload0,
+        Instruction::Invokespecial(super_constructor),
+        Instruction::Aload0,
+        Instruction::Aload1,
+        Instruction::Putfield(message),
+        Instruction::Return,
+    ];
+    let get_message = [
+        Instruction::Aload0,
+        Instruction::Getfield(message),
+        Instruction::Areturn,
+    ];
+    builder = add_code(builder, "<init>", "()V", 1, &constructor);
+    builder = add_code(
+        builder,
+        "<init>",
+        "(Ljava/lang/String;)V",
+        2,
+        &message_constructor,
+    );
+    builder = add_code(
+        builder,
+        "getMessage",
+        "()Ljava/lang/String;",
+        1,
+        &get_message,
+    );
+    builder.build()
+}
+
+/// The constructor of `Reference` and `WeakReference` with a queue
+const QUEUE_CONSTRUCTOR: &str = "(Ljava/lang/Object;Ljava/lang/ref/ReferenceQueue;)V";
+
+/// The abstract `Reference` with the constructors that take a referent, and a referent and a
+/// queue, `get` and `clear`
+fn reference_class() -> ClassFile {
+    let (referent, referent_descriptor) = REFERENT_FIELD;
+    let (queue, queue_descriptor) = QUEUE_FIELD;
+    let (next, next_descriptor) = NEXT_FIELD;
+    let mut builder = ClassFileBuilder::new(REFERENCE_CLASS)
+        .access_flags(ClassAccessFlag::Public | ClassAccessFlag::Super | ClassAccessFlag::Abstract)
+        .add_field(FieldAccessFlag::PRIVATE, referent, referent_descriptor)
+        .add_field(FieldAccessFlag::PRIVATE, queue, queue_descriptor)
+        .add_field(FieldAccessFlags::empty(), next, next_descriptor);
+    let pool = builder.constant_pool();
+    let super_constructor = pool.method_ref(OBJECT_CLASS, "<init>", "()V").inner();
+    let referent = pool
+        .field_ref(REFERENCE_CLASS, referent, referent_descriptor)
+        .inner();
+    let queue = pool
+        .field_ref(REFERENCE_CLASS, queue, queue_descriptor)
+        .inner();
+    let constructor = [
+        Instruction::Aload0,
+        Instruction::Invokespecial(super_constructor),
+        Instruction::Aload0,
+        Instruction::Aload1,
+        Instruction::Putfield(referent),
+        Instruction::Return,
+    ];
+    let queue_constructor = [
+        Instruction::Aload0,
+        Instruction::Invokespecial(super_constructor),
+        Instruction::Aload0,
+        Instruction::Aload1,
+        Instruction::Putfield(referent),
+        Instruction::Aload0,
+        Instruction::Aload2,
+        Instruction::Putfield(queue),
+        Instruction::Return,
+    ];
+    let get = [
+        Instruction::Aload0,
+        Instruction::Getfield(referent),
+        Instruction::Areturn,
+    ];
+    let clear = [
+        Instruction::Aload0,
+        Instruction::AconstNull,
+        Instruction::Putfield(referent),
+        Instruction::Return,
+    ];
+    builder = add_code(builder, "<init>", "(Ljava/lang/Object;)V", 2, &constructor);
+    builder = add_code(builder, "<init>", QUEUE_CONSTRUCTOR, 3, &queue_constructor);
+    builder = add_code(builder, "get", "()Ljava/lang/Object;", 1, &get);
+    builder = add_code(builder, "clear", "()V", 1, &clear);
+    builder.build()
+}
+
+/// Adds a public method with the code, which doesn't use the stack deeper than 3
+fn add_code(
+    builder: ClassFileBuilder,
+    name: &str,
+    descriptor: &str,
+    max_locals: u16,
+    code: &[Instruction],
+) -> ClassFileBuilder {
+    let code = MethodCode {
+        max_stack: 3,
+        max_locals,
+        code: encode_code(code).expect("valid code"),
+        exception_table: Vec::new(),
+    };
+    let flags = match name {
+        "<clinit>" => MethodAccessFlag::STATIC,
+        _ => MethodAccessFlag::PUBLIC,
+    };
+    builder.add_method(flags, name, descriptor, Some(code))
+}
+
+/// The native methods by their class, name and descriptor, like `java/io/PrintStream.println(I)V`
+pub(crate) fn default_natives() -> HashMap<String, NativeMethod> {
+    let mut natives = HashMap::new();
+    for (descriptor, print, println) in PRINTED_TYPES {
+        natives.insert(format!("{}.print{}", PRINT_STREAM_CLASS, descriptor), print);
+        natives.insert(
+            format!("{}.println{}", PRINT_STREAM_CLASS, descriptor),
+            println,
+        );
+    }
+    natives.insert(
+        format!("{}.println()V", PRINT_STREAM_CLASS),
+        |context, _| write(context, "", true),
+    );
+    for (_, name, descriptor, native) in THREAD_NATIVES {
+        natives.insert(format!("{}.{}{}", THREAD_CLASS, name, descriptor), native);
+    }
+    natives.insert(format!("{}.gc()V", SYSTEM_CLASS), |context, _| {
+        // the garbage collector runs at the safepoint after the call
+        context.heap.request_collection();
+        Ok(None)
+    });
+    natives
+}
+
+/// The descriptors of the `print` and `println` methods of `PrintStream`, and their natives
+const PRINTED_TYPES: [(&str, NativeMethod, NativeMethod); 8] = [
+    (
+        "(Ljava/lang/String;)V",
+        print_object::<false>,
+        print_object::<true>,
+    ),
+    (
+        "(Ljava/lang/Object;)V",
+        print_object::<false>,
+        print_object::<true>,
+    ),
+    ("(I)V", print_int::<false>, print_int::<true>),
+    ("(J)V", print_long::<false>, print_long::<true>),
+    ("(F)V", print_float::<false>, print_float::<true>),
+    ("(D)V", print_double::<false>, print_double::<true>),
+    ("(Z)V", print_boolean::<false>, print_boolean::<true>),
+    ("(C)V", print_char::<false>, print_char::<true>),
+];
+
+/// Writes the text to `System.out`. Like `PrintStream`, errors are not reported to the code
+fn write(context: &mut NativeContext<'_>, text: &str, newline: bool) -> NativeResult {
+    let _ = if newline {
+        writeln!(context.stdout, "{}", text)
+    } else {
+        write!(context.stdout, "{}", text)
+    };
+    Ok(None)
+}
+
+type NativeResult = Result<Option<Value>, VmError>;
+
+/// The argument after the `PrintStream`
+fn argument(args: &[Value]) -> Result<Value, VmError> {
+    args.get(1)
+        .copied()
+        .ok_or_else(|| VmError::InvalidCode("Missing argument of a native method".to_owned()))
+}
+
+/// Strings are printed with their contents. Other objects are printed like the default
+/// `Object.toString`, with an index instead of the hash code, as `toString` is not called.
+/// Arrays are printed with their descriptor, like `[I@2`
+fn print_object<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let text = match argument(args)?.as_reference()? {
+        None => "null".to_owned(),
+        Some(reference) => {
+            let object = context.heap.get(reference);
+            match (&object.string, &object.array) {
+                (Some(string), _) => string.to_string(),
+                (None, Some(array)) => {
+                    format!("{}@{:x}", array.descriptor().replace('/', "."), reference.0)
+                }
+                (None, None) => {
+                    let cp = &object.class.constant_pool;
+                    let name = object.class.this_class.get(cp).name_index.get(cp);
+                    format!("{}@{:x}", internal_to_binary(name), reference.0)
+                }
+            }
+        }
+    };
+    write(context, &text, NEWLINE)
+}
+
+fn print_int<const NEWLINE: bool>(context: &mut NativeContext<'_>, args: &[Value]) -> NativeResult {
+    let n = argument(args)?.as_int()?;
+    write(context, &n.to_string(), NEWLINE)
+}
+
+fn print_long<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let n = argument(args)?.as_long()?;
+    write(context, &n.to_string(), NEWLINE)
+}
+
+fn print_float<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let n = argument(args)?.as_float()?;
+    let text = java_decimal(n.into(), n.to_string(), format!("{:e}", n));
+    write(context, &text, NEWLINE)
+}
+
+fn print_double<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let n = argument(args)?.as_double()?;
+    let text = java_decimal(n, n.to_string(), format!("{:e}", n));
+    write(context, &text, NEWLINE)
+}
+
+fn print_boolean<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let text = if argument(args)?.as_int()? != 0 {
+        "true"
+    } else {
+        "false"
+    };
+    write(context, text, NEWLINE)
+}
+
+fn print_char<const NEWLINE: bool>(
+    context: &mut NativeContext<'_>,
+    args: &[Value],
+) -> NativeResult {
+    let unit = argument(args)?.as_int()? as u16;
+    let text = char::decode_utf16([unit])
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect::<String>();
+    write(context, &text, NEWLINE)
+}
+
+/// Formats a float or double like `Double.toString`, from its shortest representations in rust.
+/// Numbers from 10^-3 up to 10^7 are written without an exponent, and there is always a digit
+/// after the decimal point
+fn java_decimal(n: f64, plain: String, scientific: String) -> String {
+    if n.is_nan() {
+        "NaN".to_owned()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_owned()
+    } else if n == 0.0 || (1e-3..1e7).contains(&n.abs()) {
+        if plain.contains('.') {
+            plain
+        } else {
+            plain + ".0"
+        }
+    } else {
+        let (mantissa, exponent) = scientific.split_once('e').expect("an exponent");
+        let point = if mantissa.contains('.') { "" } else { ".0" };
+        format!("{}{}E{}", mantissa, point, exponent)
+    }
+}
+
+/// The native methods of `Thread` with their access flags
+const THREAD_NATIVES: [(MethodAccessFlag, &str, &str, NativeMethod); 5] = [
+    (MethodAccessFlag::PUBLIC, "start", "()V", start_thread),
+    (MethodAccessFlag::PUBLIC, "join", "()V", join_thread),
+    (MethodAccessFlag::PUBLIC, "isAlive", "()Z", is_alive),
+    (MethodAccessFlag::STATIC, "sleep", "(J)V", sleep),
+    (MethodAccessFlag::STATIC, "yield", "()V", yield_thread),
+];
+
+/// The `Thread` an instance method is invoked on
+fn this_thread(args: &[Value]) -> Result<ObjectRef, VmError> {
+    args.first()
+        .copied()
+        .ok_or_else(|| VmError::InvalidCode("Missing object of a native method".to_owned()))?
+        .as_reference()?
+        .ok_or_else(|| VmError::InvalidCode("Native method invoked on null".to_owned()))
+}
+
+fn start_thread(context: &mut NativeContext<'_>, args: &[Value]) -> NativeResult {
+    context.schedule = Schedule::Start(this_thread(args)?);
+    Ok(None)
+}
+
+/// Waits until the thread finished, threads that weren't started return immediately
+fn join_thread(context: &mut NativeContext<'_>, args: &[Value]) -> NativeResult {
+    if context.alive_threads.contains(&this_thread(args)?) {
+        context.schedule = Schedule::Block;
+    }
+    Ok(None)
+}
+
+fn is_alive(context: &mut NativeContext<'_>, args: &[Value]) -> NativeResult {
+    let alive = context.alive_threads.contains(&this_thread(args)?);
+    Ok(Some(Value::Int(alive.into())))
+}
+
+/// Lets the other threads run, the time is ignored as there are no clocks yet
+fn sleep(context: &mut NativeContext<'_>, args: &[Value]) -> NativeResult {
+    let millis = args
+        .first()
+        .copied()
+        .ok_or_else(|| VmError::InvalidCode("Missing argument of Thread.sleep".to_owned()))?
+        .as_long()?;
+    if millis < 0 {
+        return Err(VmError::throw(
+            "java/lang/IllegalArgumentException",
+            "timeout value is negative",
+        ));
+    }
+    context.schedule = Schedule::Yield;
+    Ok(None)
+}
+
+fn yield_thread(context: &mut NativeContext<'_>, _: &[Value]) -> NativeResult {
+    context.schedule = Schedule::Yield;
+    Ok(None)
+}
@@ -0,0 +1,96 @@
+//!
+//! Hooks for debuggers, which can inspect every instruction before it is executed and pause the
+//! interpreter
+//!
+
+use crate::frame::Frame;
+use crate::value::Value;
+use cs_parser::Instruction;
+use std::collections::HashSet;
+
+/// Called by the interpreter before it executes an instruction
+pub trait Debugger {
+    /// The frame is at the instruction, which is executed once this returns `Continue`.
+    /// After a pause, the instruction is executed without calling this again. An instruction
+    /// that has to wait for another thread is passed again when it is retried
+    fn before_instruction(&mut self, frame: &Frame, instruction: &Instruction) -> DebugAction;
+}
+
+impl<F> Debugger for F
+where
+    F: FnMut(&Frame, &Instruction) -> DebugAction,
+{
+    fn before_instruction(&mut self, frame: &Frame, instruction: &Instruction) -> DebugAction {
+        self(frame, instruction)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    Continue,
+    /// Stop before the instruction, `Interpreter::resume` continues with it
+    Pause,
+}
+
+/// An instruction the interpreter pauses at
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Breakpoint {
+    /// The internal name of the class
+    pub class: String,
+    pub method: String,
+    pub descriptor: String,
+    /// The offset of the instruction in the code
+    pub pc: u32,
+}
+
+impl Breakpoint {
+    fn matches(&self, frame: &Frame) -> bool {
+        let cp = &frame.class.constant_pool;
+        let method = frame.method_info();
+        self.pc == frame.pc()
+            && self.method == method.name_index.get(cp)
+            && self.descriptor == method.descriptor_index.get(cp)
+            && self.class == frame.class.this_class.get(cp).name_index.get(cp)
+    }
+}
+
+/// How far `Interpreter::resume` or `Interpreter::step` got
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Execution {
+    /// The method that was started returned the value, and the threads it started finished
+    Returned(Option<Value>),
+    /// At a breakpoint, after a step or because the debugger paused. The current frame is before
+    /// the instruction that is executed next
+    Paused,
+}
+
+/// The debugger and breakpoints of an interpreter
+#[derive(Default)]
+pub(crate) struct DebugState {
+    pub(crate) debugger: Option<Box<dyn Debugger + Send>>,
+    pub(crate) breakpoints: HashSet<Breakpoint>,
+    /// The interpreter paused before the current instruction, which was already checked
+    pub(crate) paused: bool,
+    /// Pause before the next instruction once one was executed
+    pub(crate) stepping: bool,
+    /// Whether an instruction was executed since the interpreter was resumed
+    pub(crate) executed: bool,
+}
+
+impl DebugState {
+    /// Whether the interpreter pauses before the instruction the frame is at
+    pub(crate) fn pause_before(&mut self, frame: &Frame, instruction: &Instruction) -> bool {
+        if std::mem::take(&mut self.paused) {
+            return false;
+        }
+        let mut pause = self.stepping && self.executed;
+        if !self.breakpoints.is_empty() {
+            pause |= self.breakpoints.iter().any(|point| point.matches(frame));
+        }
+        if let Some(debugger) = &mut self.debugger {
+            pause |= debugger.before_instruction(frame, instruction) == DebugAction::Pause;
+        }
+        self.paused = pause;
+        pause
+    }
+}
@@ -0,0 +1,116 @@
+use cs_parser::ParseErr;
+use cs_verifier::VerifyErr;
+use std::fmt::{Display, Formatter};
+
+/// An error that occurred while loading a class, which is thrown as a java error to the code that
+/// triggered the load instead of aborting the VM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkageError {
+    /// The class file was malformed
+    ClassFormat(String),
+    /// The class file version is not supported by this VM
+    UnsupportedClassVersion { major: u16, minor: u16 },
+    /// The bytecode did not pass verification
+    Verify(String),
+    /// A class with the internal name was already defined
+    DuplicateClass(String),
+    /// The class with the internal name is its own superclass or superinterface
+    ClassCircularity(String),
+}
+
+impl LinkageError {
+    /// The binary name of the `java.lang.LinkageError` subclass that has to be thrown
+    pub fn java_class_name(&self) -> &'static str {
+        match self {
+            Self::ClassFormat(_) => "java/lang/ClassFormatError",
+            Self::UnsupportedClassVersion { .. } => "java/lang/UnsupportedClassVersionError",
+            Self::Verify(_) => "java/lang/VerifyError",
+            Self::DuplicateClass(_) => "java/lang/LinkageError",
+            Self::ClassCircularity(_) => "java/lang/ClassCircularityError",
+        }
+    }
+
+    /// The detail message of the java error
+    pub fn message(&self) -> String {
+        match self {
+            Self::ClassFormat(msg) | Self::Verify(msg) | Self::ClassCircularity(msg) => msg.clone(),
+            Self::DuplicateClass(name) => {
+                format!("attempted duplicate class definition for {}", name)
+            }
+            Self::UnsupportedClassVersion { major, minor } => {
+                format!("Unsupported class file major version {}.{}", major, minor)
+            }
+        }
+    }
+}
+
+impl From<ParseErr> for LinkageError {
+    fn from(err: ParseErr) -> Self {
+        Self::ClassFormat(err.to_string())
+    }
+}
+
+impl From<VerifyErr> for LinkageError {
+    fn from(err: VerifyErr) -> Self {
+        Self::Verify(err.to_string())
+    }
+}
+
+impl Display for LinkageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.java_class_name(), self.message())
+    }
+}
+
+impl std::error::Error for LinkageError {}
+
+/// An error that stops the interpreter. `Linkage` and `Throw` errors are thrown as java exceptions
+/// first, and only stop it if the code doesn't catch them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    /// The method that should be executed does not exist, or it has no code
+    NoSuchMethod(String),
+    /// The code can't be executed, like a jump into the middle of an instruction or a reference to
+    /// a constant of the wrong type. The verifier rejects most of these
+    InvalidCode(String),
+    /// An instruction or a feature the interpreter doesn't support yet
+    Unsupported(String),
+    Linkage(LinkageError),
+    /// All threads wait for monitors or other threads, so none of them can continue
+    Deadlock,
+    /// A java exception like `java/lang/ArithmeticException`, which no frame caught
+    Throw {
+        class: String,
+        message: String,
+    },
+}
+
+impl VmError {
+    pub(crate) fn throw(class: &str, message: impl Into<String>) -> Self {
+        Self::Throw {
+            class: class.to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<LinkageError> for VmError {
+    fn from(err: LinkageError) -> Self {
+        Self::Linkage(err)
+    }
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoSuchMethod(method) => write!(f, "No such method: {}", method),
+            Self::InvalidCode(msg) => write!(f, "Invalid code: {}", msg),
+            Self::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            Self::Linkage(err) => write!(f, "{}", err),
+            Self::Deadlock => write!(f, "Deadlock: all threads are blocked"),
+            Self::Throw { class, message } => write!(f, "{}: {}", class, message),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
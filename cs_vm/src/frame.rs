@@ -0,0 +1,187 @@
+//!
+//! The call frame of a method that is being executed
+//!
+
+use crate::error::VmError;
+use crate::model::{LocalVariables, OperandStack};
+use crate::thread::Lock;
+use crate::value::Value;
+use cs_parser::{AttributeCodeException, AttributeInfoInner, ClassFile, Instruction, MethodInfo};
+use std::sync::Arc;
+
+/// The state of one method invocation: where it is in the code, and its stack and variables
+pub struct Frame {
+    pub class: Arc<ClassFile>,
+    /// The index of the method in the methods of the class
+    pub method: usize,
+    /// The decoded code, with the offset of each instruction
+    instructions: Arc<[(u32, Instruction)]>,
+    /// The index of the current instruction in `instructions`
+    index: usize,
+    pub stack: OperandStack,
+    pub locals: LocalVariables,
+    /// The monitor a `synchronized` method entered, which is exited when it returns
+    pub(crate) lock: Option<Lock>,
+}
+
+impl Frame {
+    /// A frame at the start of the method, with the arguments in the first local variables
+    pub fn new(class: Arc<ClassFile>, method: usize, args: &[Value]) -> Result<Self, VmError> {
+        let info = &class.methods[method];
+        let (max_stack, max_locals, code) = info
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::Code {
+                    max_stack,
+                    max_locals,
+                    code,
+                    ..
+                } => Some((*max_stack, *max_locals, code)),
+                _ => None,
+            })
+            .ok_or_else(|| VmError::NoSuchMethod(format!("{} has no code", name(&class, info))))?;
+        let instructions = cs_parser::decode_code(code)
+            .map_err(|err| VmError::InvalidCode(format!("{}: {}", name(&class, info), err)))?;
+        if instructions.is_empty() {
+            return Err(VmError::InvalidCode(format!(
+                "{} has no instructions",
+                name(&class, info)
+            )));
+        }
+        let slots = args
+            .iter()
+            .map(|arg| if arg.is_wide() { 2 } else { 1 })
+            .sum::<usize>();
+        if slots > usize::from(max_locals) {
+            return Err(VmError::InvalidCode(format!(
+                "{} has arguments for {} slots, but only {} local variables",
+                name(&class, info),
+                slots,
+                max_locals
+            )));
+        }
+
+        let mut locals = LocalVariables::new(max_locals);
+        let mut address = 0;
+        for &arg in args {
+            locals.store(address, arg)?;
+            address += if arg.is_wide() { 2 } else { 1 };
+        }
+        Ok(Self {
+            class,
+            method,
+            instructions: instructions.into(),
+            index: 0,
+            stack: OperandStack::new(max_stack),
+            locals,
+            lock: None,
+        })
+    }
+
+    /// The exception handlers of the code, the first one that matches is used
+    pub fn exception_table(&self) -> &[AttributeCodeException] {
+        self.method_info()
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.inner {
+                AttributeInfoInner::Code {
+                    exception_table, ..
+                } => Some(exception_table.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn method_info(&self) -> &MethodInfo {
+        &self.class.methods[self.method]
+    }
+
+    /// The method like `Example.run(I)V`, for error messages
+    pub fn method_name(&self) -> String {
+        name(&self.class, self.method_info())
+    }
+
+    /// The offset of the current instruction in the code
+    pub fn pc(&self) -> u32 {
+        self.instructions[self.index].0
+    }
+
+    /// The line of the current instruction in the source file, from the `LineNumberTable`
+    pub fn line_number(&self) -> Option<u16> {
+        let pc = self.pc();
+        let AttributeInfoInner::Code { attributes, .. } = &self
+            .method_info()
+            .attributes
+            .iter()
+            .find(|attr| matches!(attr.inner, AttributeInfoInner::Code { .. }))?
+            .inner
+        else {
+            return None;
+        };
+        attributes
+            .iter()
+            .filter_map(|attr| match &attr.inner {
+                AttributeInfoInner::LineNumberTable { line_number_table } => {
+                    Some(line_number_table)
+                }
+                _ => None,
+            })
+            .flatten()
+            .filter(|line| u32::from(line.start_pc) <= pc)
+            .max_by_key(|line| line.start_pc)
+            .map(|line| line.line_number)
+    }
+
+    /// The decoded code, shared so that it can be read while the frame is modified
+    pub fn instructions(&self) -> Arc<[(u32, Instruction)]> {
+        Arc::clone(&self.instructions)
+    }
+
+    /// The index of the current instruction in `instructions`
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Continues with the instruction after the current one
+    pub fn advance(&mut self) -> Result<(), VmError> {
+        if self.index + 1 >= self.instructions.len() {
+            return Err(VmError::InvalidCode(format!(
+                "{} runs past the end of its code",
+                self.method_name()
+            )));
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Continues at the offset relative to the current instruction, like the offsets of branches
+    pub fn jump(&mut self, offset: i32) -> Result<(), VmError> {
+        self.jump_to(i64::from(self.pc()) + i64::from(offset))
+    }
+
+    /// Continues at the instruction with the offset in the code, like the start of a handler
+    pub(crate) fn jump_to(&mut self, target: i64) -> Result<(), VmError> {
+        self.index = self
+            .instructions
+            .binary_search_by_key(&target, |(pc, _)| i64::from(*pc))
+            .map_err(|_| {
+                VmError::InvalidCode(format!(
+                    "{} jumps to {}, which is not the start of an instruction",
+                    self.method_name(),
+                    target
+                ))
+            })?;
+        Ok(())
+    }
+}
+
+fn name(class: &ClassFile, method: &MethodInfo) -> String {
+    let cp = &class.constant_pool;
+    format!(
+        "{}.{}{}",
+        class.this_class.get(cp).name_index.get(cp),
+        method.name_index.get(cp),
+        method.descriptor_index.get(cp)
+    )
+}
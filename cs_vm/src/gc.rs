@@ -0,0 +1,94 @@
+//!
+//! The mark and sweep garbage collector. It runs at safepoints, where all threads are before an
+//! instruction: the current one after a call or a backward branch, the others wait for their turn
+//!
+
+use crate::heap::Heap;
+use crate::value::{ObjectRef, Value};
+use cs_parser::ClassFile;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The subclasses of `java/lang/ref/Reference` that have instances, and the indices of the
+/// fields the collector uses
+pub(crate) struct References {
+    /// The classes by the address of their `ClassFile`
+    pub(crate) classes: HashSet<*const ClassFile>,
+    /// The object of the reference, which isn't traced
+    pub(crate) referent: usize,
+    /// The `ReferenceQueue` the reference is put on once it was cleared
+    pub(crate) queue: usize,
+    /// The next reference in the queue
+    pub(crate) next: usize,
+    /// The first reference in a `ReferenceQueue`, `None` if no queue was created
+    pub(crate) head: Option<usize>,
+}
+
+/// Marks the objects that the roots and the interned strings reach and frees the others. Returns
+/// the number of freed objects.
+/// The references whose referents are only reachable through references are cleared, and pushed
+/// onto the front of their queue if they have one
+pub(crate) fn collect(
+    heap: &mut Heap,
+    roots: impl IntoIterator<Item = ObjectRef>,
+    references: Option<&References>,
+) -> usize {
+    let mut marked = vec![false; heap.slots()];
+    let mut pending = roots.into_iter().collect::<Vec<_>>();
+    pending.extend(heap.interned());
+    let mut reachable_references = Vec::new();
+    while let Some(reference) = pending.pop() {
+        let index = reference.0 as usize;
+        if marked[index] {
+            continue;
+        }
+        marked[index] = true;
+        let object = heap.get(reference);
+        let referent = references
+            .filter(|references| references.classes.contains(&Arc::as_ptr(&object.class)))
+            .map(|references| references.referent);
+        if referent.is_some() {
+            reachable_references.push(reference);
+        }
+        let fields = object
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(field, _)| Some(*field) != referent)
+            .map(|(_, value)| value);
+        let elements = object.array.iter().flat_map(|array| &array.elements);
+        for value in fields.chain(elements) {
+            if let Value::Reference(Some(child)) = value {
+                pending.push(*child);
+            }
+        }
+    }
+
+    if let Some(references) = references {
+        for reference in reachable_references {
+            clear(heap, &marked, references, reference);
+        }
+    }
+    heap.sweep(&marked)
+}
+
+/// Clears the reference if its referent isn't marked, and enqueues it
+fn clear(heap: &mut Heap, marked: &[bool], references: &References, reference: ObjectRef) {
+    let fields = &mut heap.get_mut(reference).fields;
+    let Value::Reference(Some(referent)) = fields[references.referent] else {
+        return;
+    };
+    if marked[referent.0 as usize] {
+        return;
+    }
+    fields[references.referent] = Value::NULL;
+    // a reference is only enqueued once
+    let queue = std::mem::replace(&mut fields[references.queue], Value::NULL);
+    if let (Value::Reference(Some(queue)), Some(head)) = (queue, references.head) {
+        let first = std::mem::replace(
+            &mut heap.get_mut(queue).fields[head],
+            Value::Reference(Some(reference)),
+        );
+        heap.get_mut(reference).fields[references.next] = first;
+    }
+}
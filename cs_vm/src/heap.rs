@@ -0,0 +1,289 @@
+//!
+//! The objects created by the interpreter and the layout of their fields
+//!
+
+use crate::error::VmError;
+use crate::value::{ObjectRef, Value};
+use cs_model::{FieldDescriptor, FieldType};
+use cs_parser::{ClassFile, FieldAccessFlag};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The instance fields of a class, the ones of its superclasses first, or its static fields.
+/// An instance field has the same index in the layouts of all subclasses
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    fields: Vec<FieldSlot>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldSlot {
+    name: String,
+    descriptor: String,
+    /// The value of a new object
+    default: Value,
+}
+
+impl Layout {
+    /// The layout of the class, with the fields of `parent`, the layout of its superclass
+    pub fn new(class: &ClassFile, parent: Option<&Layout>) -> Result<Self, VmError> {
+        let mut layout = parent.cloned().unwrap_or_default();
+        layout.push_fields(class, false)?;
+        Ok(layout)
+    }
+
+    /// The layout of the static fields of the class, which are not inherited
+    pub fn statics(class: &ClassFile) -> Result<Self, VmError> {
+        let mut layout = Self::default();
+        layout.push_fields(class, true)?;
+        Ok(layout)
+    }
+
+    fn push_fields(&mut self, class: &ClassFile, statics: bool) -> Result<(), VmError> {
+        let cp = &class.constant_pool;
+        for field in &class.fields {
+            if field.access_flags.contains(FieldAccessFlag::STATIC) != statics {
+                continue;
+            }
+            let descriptor = field.descriptor_index.get(cp);
+            let FieldDescriptor(field_type) = FieldDescriptor::from_str(descriptor)
+                .map_err(|err| VmError::InvalidCode(format!("{}: {}", descriptor, err.0)))?;
+            self.fields.push(FieldSlot {
+                name: field.name_index.get(cp).to_string(),
+                descriptor: descriptor.to_string(),
+                default: Value::default_for(&field_type),
+            });
+        }
+        Ok(())
+    }
+
+    /// The values of the fields of a new object
+    pub fn defaults(&self) -> Vec<Value> {
+        self.fields.iter().map(|field| field.default).collect()
+    }
+
+    /// The number of fields of an object, including the inherited ones
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The index of the field with the name and descriptor, declared by the class of the layout or
+    /// the closest superclass, as fields of subclasses hide the ones of their superclasses
+    pub fn index_of(&self, name: &str, descriptor: &str) -> Option<usize> {
+        self.fields
+            .iter()
+            .rposition(|field| field.name == name && field.descriptor == descriptor)
+    }
+}
+
+/// An instance of a class, or an array
+#[derive(Debug, Clone)]
+pub struct Object {
+    /// `java/lang/Object` for arrays
+    pub class: Arc<ClassFile>,
+    /// The values of the fields in the order of the layout of the class
+    pub fields: Vec<Value>,
+    /// The contents of a `java/lang/String`, which are kept outside of its fields
+    pub string: Option<Arc<str>>,
+    /// The elements if the object is an array
+    pub array: Option<Array>,
+}
+
+/// The elements of an array and their type, which `aastore` checks the stored references against
+#[derive(Debug, Clone)]
+pub struct Array {
+    /// The type of the elements, like `I` for an `int[]` or `[Ljava/lang/String;` for a `String[][]`
+    pub component: FieldType,
+    /// `boolean`, `byte`, `char` and `short` elements are ints like on the operand stack
+    pub elements: Vec<Value>,
+}
+
+impl Array {
+    /// The descriptor of the type of the array, like `[I`
+    pub fn descriptor(&self) -> String {
+        format!("[{}", self.component.to_descriptor())
+    }
+}
+
+/// The number of objects that are allocated before the next safepoint collects garbage, unless
+/// more objects survived the last collection
+const MIN_COLLECTION_INTERVAL: usize = 1024;
+
+/// The objects that were created and not freed by the garbage collector yet
+#[derive(Debug, Default)]
+pub struct Heap {
+    /// `None` for the objects that were freed, their slots are reused
+    objects: Vec<Option<Object>>,
+    /// The indices of the slots of the freed objects
+    free: Vec<u32>,
+    /// The interned strings by their contents, like the ones of string constants
+    strings: HashMap<Arc<str>, ObjectRef>,
+    /// The number of objects that were allocated since the last collection
+    allocated: usize,
+    /// The number of objects that survived the last collection
+    survivors: usize,
+    /// Set by `System.gc`
+    requested: bool,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the object in a free slot, or a new one
+    fn insert(&mut self, object: Object) -> ObjectRef {
+        self.allocated += 1;
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index as usize] = Some(object);
+                ObjectRef(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjectRef(self.objects.len() as u32 - 1)
+            }
+        }
+    }
+
+    /// Creates an object with the default values in all fields
+    pub fn allocate(&mut self, class: Arc<ClassFile>, layout: &Layout) -> ObjectRef {
+        self.insert(Object {
+            class,
+            fields: layout.defaults(),
+            string: None,
+            array: None,
+        })
+    }
+
+    /// Creates an array with the default value of the component type in all elements, `class` is
+    /// `java/lang/Object`
+    pub fn allocate_array(
+        &mut self,
+        class: Arc<ClassFile>,
+        component: FieldType,
+        length: usize,
+    ) -> ObjectRef {
+        self.insert(Object {
+            class,
+            fields: Vec::new(),
+            string: None,
+            array: Some(Array {
+                elements: vec![Value::default_for(&component); length],
+                component,
+            }),
+        })
+    }
+
+    /// Creates a string with the contents, `class` is `java/lang/String`
+    pub fn allocate_string(
+        &mut self,
+        class: Arc<ClassFile>,
+        layout: &Layout,
+        value: impl Into<Arc<str>>,
+    ) -> ObjectRef {
+        let reference = self.allocate(class, layout);
+        self.get_mut(reference).string = Some(value.into());
+        reference
+    }
+
+    /// The interned string with the contents, which is only created the first time, so that equal
+    /// strings are the same object like `String.intern` does
+    pub fn intern(&mut self, class: Arc<ClassFile>, layout: &Layout, value: &str) -> ObjectRef {
+        if let Some(&reference) = self.strings.get(value) {
+            return reference;
+        }
+        let value = Arc::<str>::from(value);
+        let reference = self.allocate_string(class, layout, Arc::clone(&value));
+        self.strings.insert(value, reference);
+        reference
+    }
+
+    /// The contents of the object if it is a string
+    pub fn string(&self, reference: ObjectRef) -> Option<&str> {
+        self.get(reference).string.as_deref()
+    }
+
+    /// The elements of the object if it is an array
+    pub fn array(&self, reference: ObjectRef) -> Option<&Array> {
+        self.get(reference).array.as_ref()
+    }
+
+    /// The object, which has to be alive
+    pub fn get(&self, reference: ObjectRef) -> &Object {
+        self.objects[reference.0 as usize]
+            .as_ref()
+            .expect("an object that wasn't freed")
+    }
+
+    pub fn get_mut(&mut self, reference: ObjectRef) -> &mut Object {
+        self.objects[reference.0 as usize]
+            .as_mut()
+            .expect("an object that wasn't freed")
+    }
+
+    /// Whether the object wasn't freed
+    pub fn is_alive(&self, reference: ObjectRef) -> bool {
+        matches!(self.objects.get(reference.0 as usize), Some(Some(_)))
+    }
+
+    /// The objects that weren't freed
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectRef, &Object)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(index, object)| Some((ObjectRef(index as u32), object.as_ref()?)))
+    }
+
+    /// The number of objects that weren't freed, including the unreachable ones that weren't
+    /// collected yet
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Makes the next safepoint collect garbage, like `System.gc` does
+    pub fn request_collection(&mut self) {
+        self.requested = true;
+    }
+
+    /// Whether enough objects were allocated since the last collection to collect garbage again,
+    /// at least as many as survived it
+    pub(crate) fn needs_collection(&self) -> bool {
+        self.requested || self.allocated >= MIN_COLLECTION_INTERVAL.max(self.survivors)
+    }
+
+    /// The interned strings, which are never freed
+    pub(crate) fn interned(&self) -> impl Iterator<Item = ObjectRef> + '_ {
+        self.strings.values().copied()
+    }
+
+    /// The number of slots, which `ObjectRef`s are indices into
+    pub(crate) fn slots(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Frees the objects that aren't marked, and returns how many
+    pub(crate) fn sweep(&mut self, marked: &[bool]) -> usize {
+        let mut freed = 0;
+        for (index, object) in self.objects.iter_mut().enumerate() {
+            if object.is_some() && !marked[index] {
+                *object = None;
+                self.free.push(index as u32);
+                freed += 1;
+            }
+        }
+        self.allocated = 0;
+        self.survivors = self.len();
+        self.requested = false;
+        freed
+    }
+}
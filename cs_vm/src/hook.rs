@@ -0,0 +1,38 @@
+//!
+//! Callbacks around the instructions and calls that the interpreter executes, for tracers,
+//! coverage and monitors that only observe the code
+//!
+
+use crate::frame::Frame;
+use crate::value::{ObjectRef, Value};
+use cs_parser::Instruction;
+
+/// Called by the interpreter for every thread, in the order the hooks were added. All methods do
+/// nothing by default. Native methods don't have frames, so their calls are not reported
+pub trait Hook {
+    /// Before the instruction is executed, once the debugger didn't pause. An instruction that
+    /// has to wait for another thread is passed again when it is retried
+    fn before_instruction(&mut self, _frame: &Frame, _instruction: &Instruction) {}
+
+    /// After the instruction changed the stack and the local variables of the frame, before a
+    /// called method starts or the frame returns. The frame is still at the instruction, except
+    /// after branches, which are at their target already
+    fn after_instruction(&mut self, _frame: &Frame, _instruction: &Instruction) {}
+
+    /// The frame of a method that is called, before its first instruction. This includes the
+    /// method that is run, `<clinit>` methods and the `run` methods of started threads
+    fn method_entry(&mut self, _frame: &Frame) {}
+
+    /// The frame of a method that returned or threw, after it was removed. Frames that are removed
+    /// because of an error of the interpreter, like `VmError::Deadlock`, are not passed
+    fn method_exit(&mut self, _frame: &Frame, _exit: MethodExit) {}
+}
+
+/// How a method was exited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MethodExit {
+    /// The method returned the value, `None` for `void`
+    Returned(Option<Value>),
+    /// The method didn't catch the throwable
+    Threw(ObjectRef),
+}
@@ -0,0 +1,2413 @@
+//!
+//! Executing the bytecode of methods, one frame on top of the other
+//!
+
+use crate::builtin::{
+    self, NativeContext, NativeMethod, MESSAGE_FIELD, NEXT_FIELD, OBJECT_CLASS, QUEUE_FIELD,
+    QUEUE_HEAD_FIELD, REFERENCE_CLASS, REFERENCE_QUEUE_CLASS, REFERENT_FIELD, STRING_CLASS,
+};
+use crate::debug::{Breakpoint, DebugState, Debugger, Execution};
+use crate::error::VmError;
+use crate::frame::Frame;
+use crate::gc;
+use crate::heap::{Array, Heap, Layout};
+use crate::hook::{Hook, MethodExit};
+use crate::loader::ClassLoader;
+use crate::model::OperandStack;
+use crate::statics::{InitState, Statics};
+use crate::thread::{Lock, Monitors, Schedule, Thread, ThreadId, ThreadState};
+#[cfg(debug_assertions)]
+use crate::trace;
+use crate::value::{ObjectRef, Value};
+use cs_archive::ClassPath;
+use cs_model::names::internal_to_binary;
+use cs_model::{FieldDescriptor, FieldType, MethodDescriptor};
+use cs_parser::cp_info::{self, FromCpInfo, FromPool};
+use cs_parser::{
+    AttributeInfoInner, ClassAccessFlag, ClassFile, ConstantPool, CpInfoInner, FieldAccessFlag,
+    Instruction, MethodAccessFlag,
+};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The number of instructions a thread executes before the next one gets its turn
+const TIME_SLICE: usize = 1000;
+
+/// The number of frames a thread can have before calls throw a `StackOverflowError`, unless it
+/// is changed with `Interpreter::set_max_frames`
+pub const DEFAULT_MAX_FRAMES: usize = 4096;
+
+/// Executes methods on a stack of frames. The threads the code starts take turns on the thread
+/// that calls the interpreter
+pub struct Interpreter {
+    /// The thread that runs the methods of `run` first, then the started ones
+    threads: Vec<Thread>,
+    /// The index of the thread whose turn it is in `threads`
+    current: usize,
+    /// The id of the next thread that is started
+    next_thread: u32,
+    /// The number of frames of each thread, above which calls throw a `StackOverflowError`
+    max_frames: usize,
+    /// The method that was started and hasn't returned yet
+    invocation: Option<Invocation>,
+    debug: DebugState,
+    hooks: Vec<Box<dyn Hook + Send>>,
+    /// Where every executed instruction is logged to
+    #[cfg(debug_assertions)]
+    trace: Option<Box<dyn Write + Send>>,
+    runtime: Runtime,
+}
+
+/// A method that was started by `Interpreter::start`
+struct Invocation {
+    class: Arc<ClassFile>,
+    method: usize,
+    args: Vec<Value>,
+    /// The number of frames of the first thread below the ones of the invocation
+    base: usize,
+    phase: Phase,
+}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    /// The class of the method is initialized first, `true` while a `<clinit>` runs
+    Initializing(bool),
+    Running,
+    /// The method returned the value, the threads it started still run
+    Joining(Option<Value>),
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self {
+            threads: vec![Thread {
+                id: ThreadId(0),
+                object: None,
+                frames: Vec::new(),
+                state: ThreadState::Runnable,
+            }],
+            current: 0,
+            next_thread: 1,
+            max_frames: DEFAULT_MAX_FRAMES,
+            invocation: None,
+            debug: DebugState::default(),
+            hooks: Vec::new(),
+            #[cfg(debug_assertions)]
+            trace: None,
+            runtime: Runtime::default(),
+        }
+    }
+}
+
+/// The state that all frames share
+struct Runtime {
+    loader: ClassLoader,
+    /// The layouts of the classes that were instantiated or had their fields accessed
+    layouts: HashMap<String, Arc<Layout>>,
+    /// The static fields of the classes whose initialization has started
+    statics: HashMap<String, Statics>,
+    heap: Heap,
+    /// The implementations of `native` methods, by their class, name and descriptor
+    natives: HashMap<String, NativeMethod>,
+    /// Where `System.out` writes to
+    stdout: Box<dyn Write + Send>,
+    monitors: Monitors,
+    /// The thread that executes the instructions
+    thread: ThreadId,
+    /// The `java/lang/Thread`s that were started, they can only be started once
+    started_threads: HashSet<ObjectRef>,
+    /// The started threads that haven't finished yet
+    alive_threads: HashSet<ObjectRef>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            loader: ClassLoader::default(),
+            layouts: HashMap::new(),
+            statics: HashMap::new(),
+            heap: Heap::new(),
+            natives: builtin::default_natives(),
+            stdout: Box::new(io::stdout()),
+            monitors: Monitors::default(),
+            thread: ThreadId::default(),
+            started_threads: HashSet::new(),
+            alive_threads: HashSet::new(),
+        }
+    }
+}
+
+/// What happens after an instruction was executed
+enum Flow {
+    /// Continue with the next instruction
+    Next,
+    /// The instruction was a branch, the frame is already at the target
+    Jumped,
+    /// Execute the frame of a called method on top of the current one
+    Invoke(Frame),
+    /// The method returns the value, `None` for `void`
+    Return(Option<Value>),
+    /// Continue with the next instruction once the other threads had their turn
+    Yield,
+    /// The instruction waits for another thread and has to be executed again later, its
+    /// operands are still on the stack
+    Blocked(ThreadState),
+    /// Start a thread for the `java/lang/Thread` that executes the frame, and continue with the
+    /// next instruction
+    Spawn(Frame, ObjectRef),
+    /// Continue at the handler of the throwable, in this frame or one of its callers
+    Throw(ObjectRef),
+}
+
+/// How the turn of a thread ended
+enum Turn {
+    /// The thread is before an instruction it paused at
+    Paused,
+    /// The thread returned from the frame above its base
+    Returned(Option<Value>),
+    /// The next thread can run
+    Switch {
+        /// Whether any instruction was executed, a thread that was blocked right away didn't
+        progressed: bool,
+    },
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An interpreter that loads the classes that weren't added from the class path
+    pub fn with_class_path(class_path: ClassPath) -> Self {
+        let mut interpreter = Self::default();
+        interpreter.runtime.loader = ClassLoader::new(class_path);
+        interpreter
+    }
+
+    /// The classes that were added or loaded
+    pub fn loader(&self) -> &ClassLoader {
+        &self.runtime.loader
+    }
+
+    /// The frames of the methods the current thread executes, the current one is last
+    pub fn frames(&self) -> &[Frame] {
+        &self.threads[self.current].frames
+    }
+
+    /// The objects that were created and not freed by the garbage collector yet
+    pub fn heap(&self) -> &Heap {
+        &self.runtime.heap
+    }
+
+    /// Frees the objects that the threads, the arguments of the started method and the static
+    /// fields can't reach, and returns how many. References that were returned from the
+    /// interpreter are not roots, they must not be used after a collection.
+    /// This happens automatically at the safepoints after calls and backward branches, once
+    /// enough objects were allocated
+    pub fn collect_garbage(&mut self) -> usize {
+        let threads = self.threads.iter().flat_map(|thread| {
+            let frames = thread.frames.iter().flat_map(|frame| {
+                let lock = match &frame.lock {
+                    Some(Lock::Object(object)) => Some(Value::Reference(Some(*object))),
+                    _ => None,
+                };
+                frame
+                    .stack
+                    .values()
+                    .chain(frame.locals.values())
+                    .chain(lock)
+            });
+            let object = thread.object.map(|object| Value::Reference(Some(object)));
+            frames.chain(object)
+        });
+        let invocation = self.invocation.iter().flat_map(|invocation| {
+            let result = match invocation.phase {
+                Phase::Joining(value) => value,
+                _ => None,
+            };
+            invocation.args.iter().copied().chain(result)
+        });
+        let statics = self
+            .runtime
+            .statics
+            .values()
+            .flat_map(|statics| statics.values.iter().copied());
+        let objects = threads
+            .chain(invocation)
+            .chain(statics)
+            .filter_map(|value| match value {
+                Value::Reference(reference) => reference,
+                _ => None,
+            })
+            .chain(self.runtime.alive_threads.iter().copied())
+            .chain(self.runtime.monitors.locked_objects())
+            .collect::<Vec<_>>();
+        let references = self.runtime.references();
+        let freed = gc::collect(&mut self.runtime.heap, objects, references.as_ref());
+        let heap = &self.runtime.heap;
+        // the slots of freed threads are reused by new objects, which can be started
+        self.runtime
+            .started_threads
+            .retain(|&thread| heap.is_alive(thread));
+        freed
+    }
+
+    /// The static fields of the class with the internal name, `None` if it wasn't initialized
+    pub fn statics(&self, class: &str) -> Option<&Statics> {
+        self.runtime.statics.get(class)
+    }
+
+    /// The stacks of all threads like `jstack` prints them, with the state of each thread and the
+    /// monitors it owns. The frames are printed like `pkg.Example.run:12` with the line of the
+    /// current instruction, if the class has a `LineNumberTable`. The first thread is `main`,
+    /// the started ones are numbered like `Thread-0`
+    pub fn thread_dump(&self) -> String {
+        let mut dump = String::new();
+        for thread in &self.threads {
+            let name = match thread.id.0 {
+                0 => "main".to_owned(),
+                id => format!("Thread-{}", id - 1),
+            };
+            dump.push_str(&format!(
+                "\"{}\" #{} {}\n",
+                name,
+                thread.id.0,
+                thread.state.name()
+            ));
+            for frame in thread.frames.iter().rev() {
+                let cp = &frame.class.constant_pool;
+                let class = frame.class.this_class.get(cp).name_index.get(cp);
+                let method = frame.method_info().name_index.get(cp);
+                dump.push_str(&format!("\tat {}.{}", internal_to_binary(class), method));
+                if let Some(line) = frame.line_number() {
+                    dump.push_str(&format!(":{}", line));
+                }
+                dump.push('\n');
+            }
+            let mut locks = self
+                .runtime
+                .monitors
+                .owned_by(thread.id)
+                .map(|lock| match lock {
+                    Lock::Object(object) => {
+                        let class = &self.runtime.heap.get(*object).class;
+                        let cp = &class.constant_pool;
+                        let name = class.this_class.get(cp).name_index.get(cp);
+                        format!("{}@{:x}", internal_to_binary(name), object.0)
+                    }
+                    Lock::Class(name) => format!("class {}", internal_to_binary(name)),
+                })
+                .collect::<Vec<_>>();
+            locks.sort();
+            for lock in locks {
+                dump.push_str(&format!("\t- locked {}\n", lock));
+            }
+            dump.push('\n');
+        }
+        dump
+    }
+
+    /// Creates a `String[]` with the strings, like the arguments of `main`
+    pub fn string_array(&mut self, strings: &[impl AsRef<str>]) -> Result<ObjectRef, VmError> {
+        let runtime = &mut self.runtime;
+        let length = i32::try_from(strings.len())
+            .map_err(|_| VmError::Unsupported(format!("an array of {} strings", strings.len())))?;
+        let component = FieldType::Object(STRING_CLASS.to_owned());
+        let array = runtime.new_array(component, &[length])?;
+        let class = runtime.class(STRING_CLASS)?;
+        let layout = runtime.layout(STRING_CLASS)?;
+        let elements = strings
+            .iter()
+            .map(|string| {
+                let string =
+                    runtime
+                        .heap
+                        .allocate_string(Arc::clone(&class), &layout, string.as_ref());
+                Value::Reference(Some(string))
+            })
+            .collect();
+        runtime
+            .heap
+            .get_mut(array)
+            .array
+            .as_mut()
+            .expect("an array")
+            .elements = elements;
+        Ok(array)
+    }
+
+    /// Makes the class available to the code, which fails if a class with the same name was
+    /// added or loaded before. The classes of the methods that are run are added automatically
+    pub fn add_class(&mut self, class: Arc<ClassFile>) -> Result<(), VmError> {
+        self.runtime.loader.define(class)
+    }
+
+    /// Implements the `native` method of the class with the internal name, replacing the
+    /// implementation of a builtin native like `java/io/PrintStream.println(I)V`
+    pub fn register_native(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+        method: NativeMethod,
+    ) {
+        let key = format!("{}.{}{}", class, name, descriptor);
+        self.runtime.natives.insert(key, method);
+    }
+
+    /// Makes `System.out` write to `stdout` instead of the standard output of the process
+    pub fn set_stdout(&mut self, stdout: impl Write + Send + 'static) {
+        self.runtime.stdout = Box::new(stdout);
+    }
+
+    /// Limits how deep the calls of each thread can go, like `-Xss` of `java` but counted in
+    /// frames. A call that would exceed the limit throws a `StackOverflowError` instead
+    pub fn set_max_frames(&mut self, frames: usize) {
+        self.max_frames = frames;
+    }
+
+    /// Calls the debugger before every instruction that is executed
+    pub fn set_debugger(&mut self, debugger: impl Debugger + Send + 'static) {
+        self.debug.debugger = Some(Box::new(debugger));
+    }
+
+    pub fn remove_debugger(&mut self) {
+        self.debug.debugger = None;
+    }
+
+    /// Calls the hook around every instruction and method call, after the ones that were added
+    /// before
+    pub fn add_hook(&mut self, hook: impl Hook + Send + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn remove_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// Logs every instruction with its method and the operand stack before it is executed to
+    /// `output`. Tracing is only compiled into debug builds, release builds ignore this
+    pub fn set_trace(&mut self, output: impl Write + Send + 'static) {
+        #[cfg(debug_assertions)]
+        {
+            self.trace = Some(Box::new(output));
+        }
+        #[cfg(not(debug_assertions))]
+        drop(output);
+    }
+
+    /// Pauses before the instruction, returns false if the breakpoint was already added
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        self.debug.breakpoints.insert(breakpoint)
+    }
+
+    /// Returns false if the breakpoint wasn't added
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        self.debug.breakpoints.remove(breakpoint)
+    }
+
+    /// Runs the method with the name and a descriptor like `(I)I` until it returns the value,
+    /// or `None` for `void`. The arguments are stored in the first local variables.
+    /// The class is initialized first if it wasn't yet. The threads the method starts are run
+    /// until they finished as well.
+    /// Pauses of the debugger are ignored, `start` and `resume` can stop at them instead
+    pub fn run(
+        &mut self,
+        class: Arc<ClassFile>,
+        name: &str,
+        descriptor: &str,
+        args: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        self.start(class, name, descriptor, args)?;
+        loop {
+            if let Execution::Returned(value) = self.resume()? {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Prepares to run the method like `run` does, which only starts with `resume` or `step`.
+    /// Fails if the method that was started before hasn't returned yet
+    pub fn start(
+        &mut self,
+        class: Arc<ClassFile>,
+        name: &str,
+        descriptor: &str,
+        args: &[Value],
+    ) -> Result<(), VmError> {
+        let cp = &class.constant_pool;
+        let class_name = class.this_class.get(cp).name_index.get(cp);
+        if self.invocation.is_some() {
+            return Err(VmError::Unsupported(format!(
+                "starting {}.{}{} before the method that was started returned",
+                class_name, name, descriptor
+            )));
+        }
+        let method = find_method(&class, name, descriptor).ok_or_else(|| {
+            VmError::NoSuchMethod(format!("{}.{}{}", class_name, name, descriptor))
+        })?;
+        if self.runtime.loader.loaded(class_name).is_none() {
+            self.add_class(Arc::clone(&class))?;
+        }
+        self.invocation = Some(Invocation {
+            class,
+            method,
+            args: args.to_vec(),
+            base: self.threads[0].frames.len(),
+            phase: Phase::Initializing(false),
+        });
+        Ok(())
+    }
+
+    /// Executes the method that was started until it returns or the interpreter pauses
+    pub fn resume(&mut self) -> Result<Execution, VmError> {
+        let result = self.resume_invocation();
+        if !matches!(result, Ok(Execution::Paused)) {
+            self.invocation = None;
+        }
+        result
+    }
+
+    /// Executes a single instruction of the method that was started, or of a thread it started
+    pub fn step(&mut self) -> Result<Execution, VmError> {
+        self.debug.stepping = true;
+        let result = self.resume();
+        self.debug.stepping = false;
+        result
+    }
+
+    fn resume_invocation(&mut self) -> Result<Execution, VmError> {
+        self.debug.executed = false;
+        loop {
+            let invocation = self
+                .invocation
+                .as_ref()
+                .ok_or_else(|| VmError::Unsupported("resuming without a started method".into()))?;
+            let base = invocation.base;
+            let phase = match invocation.phase {
+                Phase::Initializing(false) => {
+                    let class = &invocation.class;
+                    let cp = &class.constant_pool;
+                    let class_name = class.this_class.get(cp).name_index.get(cp);
+                    match self.runtime.initialize(class_name)? {
+                        Some(clinit) => {
+                            self.enter(clinit);
+                            Phase::Initializing(true)
+                        }
+                        None => {
+                            let frame =
+                                Frame::new(Arc::clone(class), invocation.method, &invocation.args)?;
+                            self.enter(frame);
+                            Phase::Running
+                        }
+                    }
+                }
+                Phase::Initializing(true) => match self.execute(Some(base))? {
+                    Execution::Returned(_) => Phase::Initializing(false),
+                    Execution::Paused => return Ok(Execution::Paused),
+                },
+                Phase::Running => match self.execute(Some(base))? {
+                    Execution::Returned(value) => Phase::Joining(value),
+                    Execution::Paused => return Ok(Execution::Paused),
+                },
+                Phase::Joining(value) => match self.execute(None)? {
+                    Execution::Returned(_) => return Ok(Execution::Returned(value)),
+                    Execution::Paused => return Ok(Execution::Paused),
+                },
+            };
+            self.invocation.as_mut().expect("an invocation").phase = phase;
+        }
+    }
+
+    /// Pushes the frame of a method that is started onto the first thread
+    fn enter(&mut self, frame: Frame) {
+        for hook in &mut self.hooks {
+            hook.method_entry(&frame);
+        }
+        self.threads[0].frames.push(frame);
+    }
+
+    /// Executes the threads until the first one returns from its frames above `base`, or with
+    /// `None` until the started threads finished, or until the interpreter pauses.
+    /// After an error, the started threads and the frames above `base` are removed, and the
+    /// classes whose `<clinit>` was running can't be used anymore
+    fn execute(&mut self, base: Option<usize>) -> Result<Execution, VmError> {
+        let result = self.schedule(base);
+        match result {
+            Ok(Execution::Paused) => return result,
+            Ok(Execution::Returned(_)) => {}
+            Err(_) => {
+                let first = &mut self.threads[0];
+                let base = base.unwrap_or(first.frames.len());
+                let mut frames = first.frames.drain(base..).collect::<Vec<_>>();
+                for thread in self.threads.drain(1..) {
+                    frames.extend(thread.frames);
+                }
+                for frame in &frames {
+                    if let Some(class) = initialized_class(frame) {
+                        let statics = self.runtime.statics.get_mut(class).expect("initializing");
+                        statics.state = InitState::Erroneous;
+                    }
+                }
+                self.runtime.monitors = Monitors::default();
+                self.threads[0].state = ThreadState::Runnable;
+                self.runtime.alive_threads.clear();
+                self.debug.paused = false;
+            }
+        }
+        self.current = 0;
+        result
+    }
+
+    /// Lets the threads take turns, see `execute`. It is a deadlock if none of the threads
+    /// could execute anything during a whole round
+    fn schedule(&mut self, base: Option<usize>) -> Result<Execution, VmError> {
+        // the number of turns in a row in which the threads were blocked
+        let mut idle = 0;
+        loop {
+            // the first thread only runs until it returns from its frames above `base`
+            let first = usize::from(base.is_none());
+            let runnable = self.threads.len() - first;
+            if runnable == 0 {
+                return Ok(Execution::Returned(None));
+            }
+            if self.current < first {
+                self.current = first;
+            }
+            let thread_base = match self.current {
+                0 => base.expect("the first thread runs"),
+                _ => 0,
+            };
+            match self.execute_turn(thread_base)? {
+                Turn::Paused => return Ok(Execution::Paused),
+                Turn::Returned(value) if self.current == 0 => {
+                    return Ok(Execution::Returned(value))
+                }
+                Turn::Returned(_) => {
+                    let thread = self.threads.remove(self.current);
+                    let object = thread.object.expect("a started thread");
+                    self.runtime.alive_threads.remove(&object);
+                    idle = 0;
+                    // the next thread took the place of the finished one
+                    if self.current == self.threads.len() {
+                        self.current = 0;
+                    }
+                    continue;
+                }
+                Turn::Switch { progressed: true } => idle = 0,
+                Turn::Switch { progressed: false } => idle += 1,
+            }
+            if idle >= runnable {
+                return Err(VmError::Deadlock);
+            }
+            self.current = (self.current + 1) % self.threads.len();
+        }
+    }
+
+    /// Executes the current thread until it returns from the frames above `base`, its time slice
+    /// is over or it has to wait. Threads aren't switched during the initialization of a class,
+    /// so that the other threads only see initialized classes
+    fn execute_turn(&mut self, base: usize) -> Result<Turn, VmError> {
+        let id = self.threads[self.current].id;
+        self.runtime.thread = id;
+        let mut executed = 0;
+        loop {
+            let frames = &mut self.threads[self.current].frames;
+            let depth = frames.len();
+            let frame = frames.last_mut().expect("a frame above the base");
+            let instructions = frame.instructions();
+            let index = frame.index();
+            let instruction = &instructions[index].1;
+            if self.debug.pause_before(frame, instruction) {
+                return Ok(Turn::Paused);
+            }
+            self.debug.executed = true;
+            #[cfg(debug_assertions)]
+            if let Some(output) = &mut self.trace {
+                trace::trace(output, id, frame, instruction);
+            }
+            for hook in &mut self.hooks {
+                hook.before_instruction(frame, instruction);
+            }
+            let flow = match step(frame, &mut self.runtime, instruction) {
+                Ok(flow) => flow,
+                Err(err) => Flow::Throw(self.runtime.throwable(err)?),
+            };
+            let safepoint = match flow {
+                Flow::Jumped => frame.index() <= index,
+                _ => is_call(instruction),
+            };
+            if !matches!(flow, Flow::Blocked(_)) {
+                for hook in &mut self.hooks {
+                    hook.after_instruction(frame, instruction);
+                }
+            }
+            match flow {
+                Flow::Next => frame.advance()?,
+                Flow::Jumped => {}
+                Flow::Invoke(callee) if depth < self.max_frames => {
+                    for hook in &mut self.hooks {
+                        hook.method_entry(&callee);
+                    }
+                    frames.push(callee);
+                }
+                Flow::Invoke(callee) => {
+                    // the callee never runs, so it exits its monitor and fails its `<clinit>`
+                    if let Some(lock) = &callee.lock {
+                        self.runtime.monitors.exit(lock, id)?;
+                    }
+                    if let Some(class) = initialized_class(&callee) {
+                        let statics = self.runtime.statics.get_mut(class).expect("initializing");
+                        statics.state = InitState::Erroneous;
+                    }
+                    let error = VmError::throw("java/lang/StackOverflowError", "");
+                    let throwable = self.runtime.throwable(error)?;
+                    throw(frames, &mut self.runtime, &mut self.hooks, base, throwable)?;
+                }
+                Flow::Yield => {
+                    frame.advance()?;
+                    return Ok(Turn::Switch { progressed: true });
+                }
+                Flow::Blocked(state) => {
+                    self.threads[self.current].state = state;
+                    return Ok(Turn::Switch {
+                        progressed: executed > 0,
+                    });
+                }
+                Flow::Spawn(callee, object) => {
+                    frame.advance()?;
+                    for hook in &mut self.hooks {
+                        hook.method_entry(&callee);
+                    }
+                    self.threads.push(Thread {
+                        id: ThreadId(self.next_thread),
+                        object: Some(object),
+                        frames: vec![callee],
+                        state: ThreadState::Runnable,
+                    });
+                    self.next_thread += 1;
+                    self.runtime.alive_threads.insert(object);
+                }
+                Flow::Throw(throwable) => {
+                    throw(frames, &mut self.runtime, &mut self.hooks, base, throwable)?;
+                }
+                Flow::Return(value) => {
+                    let frame = frames.pop().expect("the returning frame");
+                    for hook in &mut self.hooks {
+                        hook.method_exit(&frame, MethodExit::Returned(value));
+                    }
+                    if let Some(lock) = &frame.lock {
+                        self.runtime.monitors.exit(lock, id)?;
+                    }
+                    let initialized = initialized_class(&frame);
+                    if let Some(class) = initialized {
+                        let statics = self.runtime.statics.get_mut(class).expect("initializing");
+                        statics.state = InitState::Initialized;
+                    }
+                    if frames.len() == base {
+                        return Ok(Turn::Returned(value));
+                    }
+                    let caller = frames.last_mut().expect("the caller");
+                    // the instruction that started the initialization runs again
+                    if initialized.is_none() {
+                        if let Some(value) = value {
+                            caller.stack.push(value)?;
+                        }
+                        caller.advance()?;
+                    }
+                }
+            }
+            executed += 1;
+            self.threads[self.current].state = ThreadState::Runnable;
+            if safepoint && self.runtime.heap.needs_collection() {
+                self.collect_garbage();
+            }
+            let frames = &self.threads[self.current].frames;
+            if executed >= TIME_SLICE
+                && !frames
+                    .iter()
+                    .any(|frame| initialized_class(frame).is_some())
+            {
+                return Ok(Turn::Switch { progressed: true });
+            }
+        }
+    }
+}
+
+/// Whether the instruction calls a method, after which the thread is at a safepoint, also when
+/// it entered the callee or the call threw
+fn is_call(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Invokevirtual(_)
+            | Instruction::Invokespecial(_)
+            | Instruction::Invokestatic(_)
+            | Instruction::Invokeinterface { .. }
+            | Instruction::Invokedynamic(_)
+    )
+}
+
+/// Continues at the handler for the throwable in the innermost frame above `base` that has one
+/// for its current instruction. The frames without one are removed, they exit their monitors and
+/// the classes of their `<clinit>` can't be used anymore. Fails with the throwable if no frame
+/// catches it
+fn throw(
+    frames: &mut Vec<Frame>,
+    runtime: &mut Runtime,
+    hooks: &mut [Box<dyn Hook + Send>],
+    base: usize,
+    throwable: ObjectRef,
+) -> Result<(), VmError> {
+    let class = Arc::clone(&runtime.heap.get(throwable).class);
+    while frames.len() > base {
+        let frame = frames.last_mut().expect("a frame above the base");
+        if let Some(handler) = runtime.find_handler(frame, &class)? {
+            frame.stack.clear();
+            frame.stack.push(Value::Reference(Some(throwable)))?;
+            return frame.jump_to(handler.into());
+        }
+        let frame = frames.pop().expect("the frame");
+        for hook in hooks.iter_mut() {
+            hook.method_exit(&frame, MethodExit::Threw(throwable));
+        }
+        if let Some(lock) = &frame.lock {
+            runtime.monitors.exit(lock, runtime.thread)?;
+        }
+        if let Some(class) = initialized_class(&frame) {
+            let statics = runtime.statics.get_mut(class).expect("initializing");
+            statics.state = InitState::Erroneous;
+        }
+    }
+    Err(runtime.uncaught(throwable))
+}
+
+/// The index of the method with the name and descriptor that the class declares
+fn find_method(class: &ClassFile, name: &str, descriptor: &str) -> Option<usize> {
+    let cp = &class.constant_pool;
+    class.methods.iter().position(|method| {
+        method.name_index.get(cp) == name && method.descriptor_index.get(cp) == descriptor
+    })
+}
+
+/// The internal name of the class if the frame is its `<clinit>`
+fn initialized_class(frame: &Frame) -> Option<&str> {
+    let cp = &frame.class.constant_pool;
+    (frame.method_info().name_index.get(cp) == "<clinit>")
+        .then(|| frame.class.this_class.get(cp).name_index.get(cp))
+}
+
+impl Runtime {
+    /// The class with the internal name, which is loaded the first time it is used
+    fn class(&self, name: &str) -> Result<Arc<ClassFile>, VmError> {
+        self.loader.load(name)
+    }
+
+    /// The layout of the class with the internal name, which includes the ones of its superclasses
+    fn layout(&mut self, name: &str) -> Result<Arc<Layout>, VmError> {
+        if let Some(layout) = self.layouts.get(name) {
+            return Ok(Arc::clone(layout));
+        }
+        let class = self.class(name)?;
+        let cp = &class.constant_pool;
+        let parent = match class.super_class.maybe_get(cp) {
+            Some(super_class) => Some(self.layout(super_class.name_index.get(cp))?),
+            None => None,
+        };
+        let layout = Arc::new(Layout::new(&class, parent.as_deref())?);
+        self.layouts.insert(name.to_owned(), Arc::clone(&layout));
+        Ok(layout)
+    }
+
+    /// Starts the initialization of the class with the internal name if it hasn't started yet,
+    /// after the one of its superclass. The static fields get their default values, or the ones
+    /// of their `ConstantValue` attributes.
+    /// Returns the frame of a `<clinit>` that has to run first, the class is not initialized yet
+    /// if it is the one of its superclass
+    fn initialize(&mut self, name: &str) -> Result<Option<Frame>, VmError> {
+        match self.statics.get(name).map(|statics| statics.state) {
+            // a class can be used by its own initialization
+            Some(InitState::Initialized | InitState::Initializing) => return Ok(None),
+            Some(InitState::Erroneous) => {
+                return Err(VmError::throw(
+                    "java/lang/NoClassDefFoundError",
+                    format!("Could not initialize class {}", name),
+                ))
+            }
+            None => {}
+        }
+        let class = self.class(name)?;
+        let cp = &class.constant_pool;
+        if let Some(super_class) = class.super_class.maybe_get(cp) {
+            if let Some(clinit) = self.initialize(super_class.name_index.get(cp))? {
+                return Ok(Some(clinit));
+            }
+        }
+
+        let clinit = class.methods.iter().position(|method| {
+            method.name_index.get(cp) == "<clinit>" && method.descriptor_index.get(cp) == "()V"
+        });
+        let state = match clinit {
+            Some(_) => InitState::Initializing,
+            None => InitState::Initialized,
+        };
+        let mut statics = Statics::new(&class, state)?;
+        for field in &class.fields {
+            if !field.access_flags.contains(FieldAccessFlag::STATIC) {
+                continue;
+            }
+            for attribute in &field.attributes {
+                let AttributeInfoInner::ConstantValue {
+                    constantvalue_index,
+                } = &attribute.inner
+                else {
+                    continue;
+                };
+                let field_name = field.name_index.get(cp);
+                let index = statics
+                    .index_of(field_name, field.descriptor_index.get(cp))
+                    .expect("a static field");
+                let constant = constant::<CpInfoInner>(cp, constantvalue_index.inner())?;
+                let value = match self.constant(cp, constant)? {
+                    Some(value) if value.type_name() == statics.values[index].type_name() => value,
+                    _ => {
+                        return Err(VmError::InvalidCode(format!(
+                            "The constant value of {}.{} doesn't match its type",
+                            name, field_name
+                        )))
+                    }
+                };
+                statics.values[index] = value;
+            }
+        }
+        self.statics.insert(name.to_owned(), statics);
+        clinit
+            .map(|method| Frame::new(Arc::clone(&class), method, &[]))
+            .transpose()
+    }
+
+    /// The value of an `Integer`, `Float`, `Long`, `Double` or `String` constant, `None` for other
+    /// constants
+    fn constant(
+        &mut self,
+        cp: &ConstantPool,
+        constant: &CpInfoInner,
+    ) -> Result<Option<Value>, VmError> {
+        Ok(Some(match constant {
+            CpInfoInner::String(string) => {
+                Value::Reference(Some(self.intern(string.string_index.get(cp))?))
+            }
+            CpInfoInner::Integer(n) => Value::Int(n.value()),
+            CpInfoInner::Float(n) => Value::Float(n.value()),
+            CpInfoInner::Long(n) => Value::Long(n.value()),
+            CpInfoInner::Double(n) => Value::Double(n.value()),
+            _ => return Ok(None),
+        }))
+    }
+
+    /// The class that declares the field the class with the internal name refers to, looked up in
+    /// the class, then its interfaces and then its superclass like JVMS §5.4.3.2 describes.
+    /// Returns whether the field is static as well
+    fn field_owner(
+        &self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<(Arc<ClassFile>, bool)>, VmError> {
+        let class = self.class(class_name)?;
+        let cp = &class.constant_pool;
+        let field = class.fields.iter().find(|field| {
+            field.name_index.get(cp) == name && field.descriptor_index.get(cp) == descriptor
+        });
+        if let Some(field) = field {
+            let is_static = field.access_flags.contains(FieldAccessFlag::STATIC);
+            return Ok(Some((Arc::clone(&class), is_static)));
+        }
+        for interface in &class.interfaces {
+            let owner = self.field_owner(interface.get(cp).name_index.get(cp), name, descriptor)?;
+            if owner.is_some() {
+                return Ok(owner);
+            }
+        }
+        match class.super_class.maybe_get(cp) {
+            Some(super_class) => self.field_owner(super_class.name_index.get(cp), name, descriptor),
+            None => Ok(None),
+        }
+    }
+
+    /// The throwable for an error that the code can catch, a `Throw` or a `Linkage` error. It is
+    /// created without running its constructor. The other errors are returned as they are
+    fn throwable(&mut self, err: VmError) -> Result<ObjectRef, VmError> {
+        let (class_name, message) = match &err {
+            VmError::Throw { class, message } => (class.as_str(), message.clone()),
+            VmError::Linkage(linkage) => (linkage.java_class_name(), linkage.message()),
+            _ => return Err(err),
+        };
+        let throwable = self.class(class_name).and_then(|class| {
+            let layout = self.layout(class_name)?;
+            let string_class = self.class(STRING_CLASS)?;
+            let string_layout = self.layout(STRING_CLASS)?;
+            let message = self
+                .heap
+                .allocate_string(string_class, &string_layout, message);
+            let throwable = self.heap.allocate(class, &layout);
+            let (name, descriptor) = MESSAGE_FIELD;
+            if let Some(index) = layout.index_of(name, descriptor) {
+                self.heap.get_mut(throwable).fields[index] = Value::Reference(Some(message));
+            }
+            Ok(throwable)
+        });
+        // the original error is more useful than the one of a missing class
+        throwable.map_err(|_| err)
+    }
+
+    /// The error for a throwable that no frame caught, with the class and the message
+    fn uncaught(&self, throwable: ObjectRef) -> VmError {
+        let object = self.heap.get(throwable);
+        let cp = &object.class.constant_pool;
+        let class = object.class.this_class.get(cp).name_index.get(cp);
+        let (name, descriptor) = MESSAGE_FIELD;
+        let message = self
+            .layouts
+            .get(class)
+            .and_then(|layout| layout.index_of(name, descriptor))
+            .and_then(|index| object.fields[index].as_reference().ok().flatten())
+            .and_then(|message| self.heap.string(message))
+            .unwrap_or_default();
+        VmError::throw(class, message)
+    }
+
+    /// The start of the first handler of the frame whose range contains the current instruction
+    /// and that catches throwables of the class, JVMS §2.10
+    fn find_handler(&self, frame: &Frame, class: &ClassFile) -> Result<Option<u16>, VmError> {
+        let cp = &frame.class.constant_pool;
+        let pc = frame.pc();
+        for handler in frame.exception_table() {
+            if !(u32::from(handler.start_pc)..u32::from(handler.end_pc)).contains(&pc) {
+                continue;
+            }
+            if handler.catch_type == 0 {
+                return Ok(Some(handler.handler_pc));
+            }
+            let catch_type = constant::<cp_info::Class>(cp, handler.catch_type)?
+                .name_index
+                .get(cp);
+            let class_cp = &class.constant_pool;
+            if class.this_class.get(class_cp).name_index.get(class_cp) == catch_type
+                || self.is_super_class(class, catch_type)?
+            {
+                return Ok(Some(handler.handler_pc));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The interned `java/lang/String` with the contents
+    fn intern(&mut self, value: &str) -> Result<ObjectRef, VmError> {
+        let class = self.class(STRING_CLASS)?;
+        let layout = self.layout(STRING_CLASS)?;
+        Ok(self.heap.intern(class, &layout, value))
+    }
+
+    /// The method the class with the internal name refers to, looked up in the class and its
+    /// superclasses, and then in their interfaces like JVMS §5.4.3.3 describes
+    fn resolve_method(
+        &self,
+        class_name: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<(Arc<ClassFile>, usize), VmError> {
+        let mut classes = Vec::new();
+        let mut current = Some(self.class(class_name)?);
+        while let Some(class) = current {
+            if let Some(method) = find_method(&class, name, descriptor) {
+                return Ok((class, method));
+            }
+            current = self.super_class(&class)?;
+            classes.push(class);
+        }
+        for class in classes {
+            if let Some(found) = self.interface_method(&class, name, descriptor, false)? {
+                return Ok(found);
+            }
+        }
+        Err(VmError::throw(
+            "java/lang/NoSuchMethodError",
+            format!("{}.{}{}", class_name, name, descriptor),
+        ))
+    }
+
+    /// The method in the interfaces of the class or their superinterfaces, only the ones with
+    /// code if `with_code` is set
+    fn interface_method(
+        &self,
+        class: &ClassFile,
+        name: &str,
+        descriptor: &str,
+        with_code: bool,
+    ) -> Result<Option<(Arc<ClassFile>, usize)>, VmError> {
+        let cp = &class.constant_pool;
+        for interface in &class.interfaces {
+            let interface = self.class(interface.get(cp).name_index.get(cp))?;
+            let method = find_method(&interface, name, descriptor).filter(|&method| {
+                let flags = interface.methods[method].access_flags;
+                let excluded = flags.contains(MethodAccessFlag::STATIC)
+                    || flags.contains(MethodAccessFlag::PRIVATE)
+                    || with_code && flags.contains(MethodAccessFlag::ABSTRACT);
+                !excluded
+            });
+            if let Some(method) = method {
+                return Ok(Some((interface, method)));
+            }
+            let found = self.interface_method(&interface, name, descriptor, with_code)?;
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The method that an invocation of an instance method runs on an object of the class: the
+    /// first one that overrides it in the class and its superclasses, or a default method of
+    /// their interfaces, JVMS §5.4.6
+    fn select_method(
+        &self,
+        class: Arc<ClassFile>,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<(Arc<ClassFile>, usize), VmError> {
+        let mut classes = Vec::new();
+        let mut current = Some(class);
+        while let Some(class) = current {
+            let method = find_method(&class, name, descriptor).filter(|&method| {
+                let flags = class.methods[method].access_flags;
+                !flags.contains(MethodAccessFlag::STATIC)
+                    && !flags.contains(MethodAccessFlag::PRIVATE)
+            });
+            if let Some(method) = method {
+                return Ok((class, method));
+            }
+            current = self.super_class(&class)?;
+            classes.push(class);
+        }
+        for class in &classes {
+            if let Some(found) = self.interface_method(class, name, descriptor, true)? {
+                return Ok(found);
+            }
+        }
+        let cp = &classes[0].constant_pool;
+        Err(VmError::throw(
+            "java/lang/AbstractMethodError",
+            format!(
+                "{}.{}{}",
+                classes[0].this_class.get(cp).name_index.get(cp),
+                name,
+                descriptor
+            ),
+        ))
+    }
+
+    fn super_class(&self, class: &ClassFile) -> Result<Option<Arc<ClassFile>>, VmError> {
+        let cp = &class.constant_pool;
+        class
+            .super_class
+            .maybe_get(cp)
+            .map(|super_class| self.class(super_class.name_index.get(cp)))
+            .transpose()
+    }
+
+    /// The subclasses of `Reference` that have instances, for the garbage collector. `None` if
+    /// no `Reference` was created, or it doesn't have the fields of the builtin one
+    fn references(&self) -> Option<gc::References> {
+        let layout = self.layouts.get(REFERENCE_CLASS)?;
+        let field = |(name, descriptor)| layout.index_of(name, descriptor);
+        let (referent, queue, next) = (
+            field(REFERENT_FIELD)?,
+            field(QUEUE_FIELD)?,
+            field(NEXT_FIELD)?,
+        );
+        let classes = self
+            .heap
+            .objects()
+            .map(|(_, object)| &object.class)
+            .filter(|class| {
+                // the superclasses were loaded when the object was created
+                matches!(self.is_super_class(class, REFERENCE_CLASS), Ok(true))
+            })
+            .map(Arc::as_ptr)
+            .collect();
+        let head = self.layouts.get(REFERENCE_QUEUE_CLASS).and_then(|layout| {
+            let (name, descriptor) = QUEUE_HEAD_FIELD;
+            layout.index_of(name, descriptor)
+        });
+        Some(gc::References {
+            classes,
+            referent,
+            queue,
+            next,
+            head,
+        })
+    }
+
+    /// Whether the class with the internal name is a superclass of the class
+    fn is_super_class(&self, class: &ClassFile, name: &str) -> Result<bool, VmError> {
+        let mut current = self.super_class(class)?;
+        while let Some(class) = current {
+            let cp = &class.constant_pool;
+            if class.this_class.get(cp).name_index.get(cp) == name {
+                return Ok(true);
+            }
+            current = self.super_class(&class)?;
+        }
+        Ok(false)
+    }
+
+    /// Whether the class with the internal name is `target`, or a subclass or an implementation of
+    /// it, through its superclasses and the superinterfaces of its interfaces
+    fn is_subclass(&self, name: &str, target: &str) -> Result<bool, VmError> {
+        if name == target {
+            return Ok(true);
+        }
+        let class = self.class(name)?;
+        let cp = &class.constant_pool;
+        if let Some(super_class) = class.super_class.maybe_get(cp) {
+            if self.is_subclass(super_class.name_index.get(cp), target)? {
+                return Ok(true);
+            }
+        }
+        for interface in &class.interfaces {
+            if self.is_subclass(interface.get(cp).name_index.get(cp), target)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether values of the reference type `source` are also of the reference type `target`, like
+    /// the store check of `aastore` decides it, JVMS §6.5
+    fn is_subtype(&self, source: &FieldType, target: &FieldType) -> Result<bool, VmError> {
+        Ok(match (source, target) {
+            (FieldType::Object(source), FieldType::Object(target)) => {
+                self.is_subclass(source, target)?
+            }
+            (FieldType::Array(_), FieldType::Object(target)) => {
+                ARRAY_SUPERTYPES.contains(&target.as_str())
+            }
+            (FieldType::Array(source), FieldType::Array(target)) => {
+                match (&**source, &**target) {
+                    (
+                        FieldType::Object(_) | FieldType::Array(_),
+                        FieldType::Object(_) | FieldType::Array(_),
+                    ) => self.is_subtype(source, target)?,
+                    // arrays of primitives are only the same type
+                    _ => source == target,
+                }
+            }
+            _ => false,
+        })
+    }
+
+    /// The type of the object, `Object` with the name of its class or `Array`
+    fn type_of(&self, object: ObjectRef) -> FieldType {
+        let object = self.heap.get(object);
+        match &object.array {
+            Some(array) => FieldType::Array(Box::new(array.component.clone())),
+            None => {
+                let cp = &object.class.constant_pool;
+                FieldType::Object(
+                    object
+                        .class
+                        .this_class
+                        .get(cp)
+                        .name_index
+                        .get(cp)
+                        .to_owned(),
+                )
+            }
+        }
+    }
+
+    /// A new array of the component type with the first of the lengths, like `newarray`,
+    /// `anewarray` and `multianewarray` create them. With more lengths, its elements are arrays
+    /// with the rest of the lengths. The class of the innermost elements is loaded first
+    fn new_array(&mut self, component: FieldType, lengths: &[i32]) -> Result<ObjectRef, VmError> {
+        if let Some(length) = lengths.iter().find(|&&length| length < 0) {
+            return Err(VmError::throw(
+                "java/lang/NegativeArraySizeException",
+                length.to_string(),
+            ));
+        }
+        let mut element = &component;
+        while let FieldType::Array(inner) = element {
+            element = inner;
+        }
+        if let FieldType::Object(name) = element {
+            self.class(name)?;
+        }
+        self.allocate_arrays(component, lengths)
+    }
+
+    /// Allocates the arrays of `new_array`. The arrays of a length after a zero are not created,
+    /// as there are no elements to put them into
+    fn allocate_arrays(
+        &mut self,
+        component: FieldType,
+        lengths: &[i32],
+    ) -> Result<ObjectRef, VmError> {
+        let class = self.class(OBJECT_CLASS)?;
+        let length = lengths[0] as usize;
+        let array = self.heap.allocate_array(class, component.clone(), length);
+        if let (FieldType::Array(inner), [_, rest @ ..]) = (&component, lengths) {
+            if !rest.is_empty() {
+                for index in 0..length {
+                    let element = self.allocate_arrays((**inner).clone(), rest)?;
+                    let outer = self.heap.get_mut(array).array.as_mut().expect("an array");
+                    outer.elements[index] = Value::Reference(Some(element));
+                }
+            }
+        }
+        Ok(array)
+    }
+}
+
+/// The classes and interfaces that all arrays are subtypes of
+const ARRAY_SUPERTYPES: [&str; 3] = [OBJECT_CLASS, "java/lang/Cloneable", "java/io/Serializable"];
+
+/// Executes the instruction in the frame
+fn step(
+    frame: &mut Frame,
+    runtime: &mut Runtime,
+    instruction: &Instruction,
+) -> Result<Flow, VmError> {
+    let stack = &mut frame.stack;
+    match instruction {
+        Instruction::Nop => {}
+        Instruction::AconstNull => stack.push(Value::NULL)?,
+        Instruction::IconstM1 => stack.push_int(-1)?,
+        Instruction::Iconst0 => stack.push_int(0)?,
+        Instruction::Iconst1 => stack.push_int(1)?,
+        Instruction::Iconst2 => stack.push_int(2)?,
+        Instruction::Iconst3 => stack.push_int(3)?,
+        Instruction::Iconst4 => stack.push_int(4)?,
+        Instruction::Iconst5 => stack.push_int(5)?,
+        Instruction::Lconst0 => stack.push(Value::Long(0))?,
+        Instruction::Lconst1 => stack.push(Value::Long(1))?,
+        Instruction::Fconst0 => stack.push(Value::Float(0.0))?,
+        Instruction::Fconst1 => stack.push(Value::Float(1.0))?,
+        Instruction::Fconst2 => stack.push(Value::Float(2.0))?,
+        Instruction::Dconst0 => stack.push(Value::Double(0.0))?,
+        Instruction::Dconst1 => stack.push(Value::Double(1.0))?,
+        Instruction::Bipush(value) => stack.push_int((*value).into())?,
+        Instruction::Sipush(value) => stack.push_int((*value).into())?,
+        Instruction::Ldc(index) => {
+            let value = load_constant(frame, runtime, (*index).into(), false)?;
+            frame.stack.push(value)?;
+        }
+        Instruction::LdcW(index) => {
+            let value = load_constant(frame, runtime, *index, false)?;
+            frame.stack.push(value)?;
+        }
+        Instruction::Ldc2W(index) => {
+            let value = load_constant(frame, runtime, *index, true)?;
+            frame.stack.push(value)?;
+        }
+
+        Instruction::Iload(index) => load(frame, *index, "int")?,
+        Instruction::Iload0 => load(frame, 0, "int")?,
+        Instruction::Iload1 => load(frame, 1, "int")?,
+        Instruction::Iload2 => load(frame, 2, "int")?,
+        Instruction::Iload3 => load(frame, 3, "int")?,
+        Instruction::Lload(index) => load(frame, *index, "long")?,
+        Instruction::Lload0 => load(frame, 0, "long")?,
+        Instruction::Lload1 => load(frame, 1, "long")?,
+        Instruction::Lload2 => load(frame, 2, "long")?,
+        Instruction::Lload3 => load(frame, 3, "long")?,
+        Instruction::Fload(index) => load(frame, *index, "float")?,
+        Instruction::Fload0 => load(frame, 0, "float")?,
+        Instruction::Fload1 => load(frame, 1, "float")?,
+        Instruction::Fload2 => load(frame, 2, "float")?,
+        Instruction::Fload3 => load(frame, 3, "float")?,
+        Instruction::Dload(index) => load(frame, *index, "double")?,
+        Instruction::Dload0 => load(frame, 0, "double")?,
+        Instruction::Dload1 => load(frame, 1, "double")?,
+        Instruction::Dload2 => load(frame, 2, "double")?,
+        Instruction::Dload3 => load(frame, 3, "double")?,
+        Instruction::Aload(index) => load(frame, *index, "reference")?,
+        Instruction::Aload0 => load(frame, 0, "reference")?,
+        Instruction::Aload1 => load(frame, 1, "reference")?,
+        Instruction::Aload2 => load(frame, 2, "reference")?,
+        Instruction::Aload3 => load(frame, 3, "reference")?,
+        Instruction::Istore(index) => store(frame, *index, "int")?,
+        Instruction::Istore0 => store(frame, 0, "int")?,
+        Instruction::Istore1 => store(frame, 1, "int")?,
+        Instruction::Istore2 => store(frame, 2, "int")?,
+        Instruction::Istore3 => store(frame, 3, "int")?,
+        Instruction::Lstore(index) => store(frame, *index, "long")?,
+        Instruction::Lstore0 => store(frame, 0, "long")?,
+        Instruction::Lstore1 => store(frame, 1, "long")?,
+        Instruction::Lstore2 => store(frame, 2, "long")?,
+        Instruction::Lstore3 => store(frame, 3, "long")?,
+        Instruction::Fstore(index) => store(frame, *index, "float")?,
+        Instruction::Fstore0 => store(frame, 0, "float")?,
+        Instruction::Fstore1 => store(frame, 1, "float")?,
+        Instruction::Fstore2 => store(frame, 2, "float")?,
+        Instruction::Fstore3 => store(frame, 3, "float")?,
+        Instruction::Dstore(index) => store(frame, *index, "double")?,
+        Instruction::Dstore0 => store(frame, 0, "double")?,
+        Instruction::Dstore1 => store(frame, 1, "double")?,
+        Instruction::Dstore2 => store(frame, 2, "double")?,
+        Instruction::Dstore3 => store(frame, 3, "double")?,
+        Instruction::Astore(index) => store(frame, *index, "reference")?,
+        Instruction::Astore0 => store(frame, 0, "reference")?,
+        Instruction::Astore1 => store(frame, 1, "reference")?,
+        Instruction::Astore2 => store(frame, 2, "reference")?,
+        Instruction::Astore3 => store(frame, 3, "reference")?,
+        Instruction::Iinc { index, value } => {
+            let n = frame.locals.load(*index)?.as_int()?;
+            frame
+                .locals
+                .store(*index, Value::Int(n.wrapping_add((*value).into())))?;
+        }
+
+        Instruction::Pop => stack.pop_slots(1)?,
+        Instruction::Pop2 => stack.pop_slots(2)?,
+        Instruction::Dup => stack.dup(1, 0)?,
+        Instruction::DupX1 => stack.dup(1, 1)?,
+        Instruction::DupX2 => stack.dup(1, 2)?,
+        Instruction::Dup2 => stack.dup(2, 0)?,
+        Instruction::Dup2X1 => stack.dup(2, 1)?,
+        Instruction::Dup2X2 => stack.dup(2, 2)?,
+        Instruction::Swap => stack.swap()?,
+
+        Instruction::Iadd => int_op(stack, i32::wrapping_add)?,
+        Instruction::Isub => int_op(stack, i32::wrapping_sub)?,
+        Instruction::Imul => int_op(stack, i32::wrapping_mul)?,
+        Instruction::Idiv => int_division(stack, i32::wrapping_div)?,
+        Instruction::Irem => int_division(stack, i32::wrapping_rem)?,
+        Instruction::Ineg => {
+            let n = stack.pop_int()?;
+            stack.push_int(n.wrapping_neg())?
+        }
+        // only the lowest 5 bits of the shift distance are used
+        Instruction::Ishl => int_op(stack, |a, b| a.wrapping_shl(b as u32))?,
+        Instruction::Ishr => int_op(stack, |a, b| a.wrapping_shr(b as u32))?,
+        Instruction::Iushr => int_op(stack, |a, b| (a as u32).wrapping_shr(b as u32) as i32)?,
+        Instruction::Iand => int_op(stack, |a, b| a & b)?,
+        Instruction::Ior => int_op(stack, |a, b| a | b)?,
+        Instruction::Ixor => int_op(stack, |a, b| a ^ b)?,
+
+        Instruction::Ladd => long_op(stack, i64::wrapping_add)?,
+        Instruction::Lsub => long_op(stack, i64::wrapping_sub)?,
+        Instruction::Lmul => long_op(stack, i64::wrapping_mul)?,
+        Instruction::Ldiv => long_division(stack, i64::wrapping_div)?,
+        Instruction::Lrem => long_division(stack, i64::wrapping_rem)?,
+        Instruction::Lneg => {
+            let n = stack.pop_long()?;
+            stack.push(Value::Long(n.wrapping_neg()))?
+        }
+        // the shift distance is an int, of which only the lowest 6 bits are used
+        Instruction::Lshl => long_shift(stack, i64::wrapping_shl)?,
+        Instruction::Lshr => long_shift(stack, i64::wrapping_shr)?,
+        Instruction::Lushr => long_shift(stack, |a, b| (a as u64).wrapping_shr(b) as i64)?,
+        Instruction::Land => long_op(stack, |a, b| a & b)?,
+        Instruction::Lor => long_op(stack, |a, b| a | b)?,
+        Instruction::Lxor => long_op(stack, |a, b| a ^ b)?,
+
+        // the operations of rust follow IEEE 754 like java, `%` truncates like `frem`
+        Instruction::Fadd => float_op(stack, |a, b| a + b)?,
+        Instruction::Fsub => float_op(stack, |a, b| a - b)?,
+        Instruction::Fmul => float_op(stack, |a, b| a * b)?,
+        Instruction::Fdiv => float_op(stack, |a, b| a / b)?,
+        Instruction::Frem => float_op(stack, |a, b| a % b)?,
+        Instruction::Fneg => {
+            let n = stack.pop_float()?;
+            stack.push(Value::Float(-n))?
+        }
+        Instruction::Dadd => double_op(stack, |a, b| a + b)?,
+        Instruction::Dsub => double_op(stack, |a, b| a - b)?,
+        Instruction::Dmul => double_op(stack, |a, b| a * b)?,
+        Instruction::Ddiv => double_op(stack, |a, b| a / b)?,
+        Instruction::Drem => double_op(stack, |a, b| a % b)?,
+        Instruction::Dneg => {
+            let n = stack.pop_double()?;
+            stack.push(Value::Double(-n))?
+        }
+
+        // `as` saturates and turns NaN into 0 like java when converting to integers
+        Instruction::I2l => convert(stack, OperandStack::pop_int, |n| Value::Long(n.into()))?,
+        Instruction::I2f => convert(stack, OperandStack::pop_int, |n| Value::Float(n as f32))?,
+        Instruction::I2d => convert(stack, OperandStack::pop_int, |n| Value::Double(n.into()))?,
+        Instruction::L2i => convert(stack, OperandStack::pop_long, |n| Value::Int(n as i32))?,
+        Instruction::L2f => convert(stack, OperandStack::pop_long, |n| Value::Float(n as f32))?,
+        Instruction::L2d => convert(stack, OperandStack::pop_long, |n| Value::Double(n as f64))?,
+        Instruction::F2i => convert(stack, OperandStack::pop_float, |n| Value::Int(n as i32))?,
+        Instruction::F2l => convert(stack, OperandStack::pop_float, |n| Value::Long(n as i64))?,
+        Instruction::F2d => convert(stack, OperandStack::pop_float, |n| Value::Double(n.into()))?,
+        Instruction::D2i => convert(stack, OperandStack::pop_double, |n| Value::Int(n as i32))?,
+        Instruction::D2l => convert(stack, OperandStack::pop_double, |n| Value::Long(n as i64))?,
+        Instruction::D2f => convert(stack, OperandStack::pop_double, |n| Value::Float(n as f32))?,
+        Instruction::I2b => convert(stack, OperandStack::pop_int, |n| {
+            Value::Int((n as i8).into())
+        })?,
+        Instruction::I2c => convert(stack, OperandStack::pop_int, |n| {
+            Value::Int((n as u16).into())
+        })?,
+        Instruction::I2s => convert(stack, OperandStack::pop_int, |n| {
+            Value::Int((n as i16).into())
+        })?,
+
+        Instruction::Lcmp => compare(stack, OperandStack::pop_long, 0)?,
+        // the two forms only differ in the result if one of the values is NaN
+        Instruction::Fcmpl => compare(stack, OperandStack::pop_float, -1)?,
+        Instruction::Fcmpg => compare(stack, OperandStack::pop_float, 1)?,
+        Instruction::Dcmpl => compare(stack, OperandStack::pop_double, -1)?,
+        Instruction::Dcmpg => compare(stack, OperandStack::pop_double, 1)?,
+
+        Instruction::Ifeq(offset) => return branch_if(frame, |n| n == 0, *offset),
+        Instruction::Ifne(offset) => return branch_if(frame, |n| n != 0, *offset),
+        Instruction::Iflt(offset) => return branch_if(frame, |n| n < 0, *offset),
+        Instruction::Ifge(offset) => return branch_if(frame, |n| n >= 0, *offset),
+        Instruction::Ifgt(offset) => return branch_if(frame, |n| n > 0, *offset),
+        Instruction::Ifle(offset) => return branch_if(frame, |n| n <= 0, *offset),
+        Instruction::IfIcmpeq(offset) => return branch_if_cmp(frame, |a, b| a == b, *offset),
+        Instruction::IfIcmpne(offset) => return branch_if_cmp(frame, |a, b| a != b, *offset),
+        Instruction::IfIcmplt(offset) => return branch_if_cmp(frame, |a, b| a < b, *offset),
+        Instruction::IfIcmpge(offset) => return branch_if_cmp(frame, |a, b| a >= b, *offset),
+        Instruction::IfIcmpgt(offset) => return branch_if_cmp(frame, |a, b| a > b, *offset),
+        Instruction::IfIcmple(offset) => return branch_if_cmp(frame, |a, b| a <= b, *offset),
+        Instruction::IfAcmpeq(offset) => return branch_if_same(frame, true, *offset),
+        Instruction::IfAcmpne(offset) => return branch_if_same(frame, false, *offset),
+        Instruction::Ifnull(offset) => {
+            let reference = stack.pop()?.as_reference()?;
+            return branch(frame, reference.is_none(), *offset);
+        }
+        Instruction::Ifnonnull(offset) => {
+            let reference = stack.pop()?.as_reference()?;
+            return branch(frame, reference.is_some(), *offset);
+        }
+        Instruction::Goto(offset) | Instruction::GotoW(offset) => {
+            frame.jump(*offset)?;
+            return Ok(Flow::Jumped);
+        }
+        Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            offsets,
+        } => {
+            let index = stack.pop_int()?;
+            let offset = if (*low..=*high).contains(&index) {
+                offsets[(index - low) as usize]
+            } else {
+                *default
+            };
+            frame.jump(offset)?;
+            return Ok(Flow::Jumped);
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let key = stack.pop_int()?;
+            let offset = pairs
+                .binary_search_by_key(&key, |(key, _)| *key)
+                .map_or(*default, |i| pairs[i].1);
+            frame.jump(offset)?;
+            return Ok(Flow::Jumped);
+        }
+
+        Instruction::New(index) => {
+            let cp = &frame.class.constant_pool;
+            let name = constant::<cp_info::Class>(cp, *index)?.name_index.get(cp);
+            let class = runtime.class(name)?;
+            if class.access_flags.contains(ClassAccessFlag::Interface)
+                || class.access_flags.contains(ClassAccessFlag::Abstract)
+            {
+                return Err(VmError::throw("java/lang/InstantiationError", name));
+            }
+            if let Some(clinit) = runtime.initialize(name)? {
+                return Ok(Flow::Invoke(clinit));
+            }
+            let layout = runtime.layout(name)?;
+            let object = runtime.heap.allocate(class, &layout);
+            frame.stack.push(Value::Reference(Some(object)))?;
+        }
+        Instruction::Getfield(index) => {
+            let class = Arc::clone(&frame.class);
+            let field = resolve_field(&class, runtime, *index)?;
+            let object = frame.stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    format!(
+                        "Cannot read field \"{}\" because the object is null",
+                        field.name
+                    ),
+                )
+            })?;
+            let value = *runtime
+                .heap
+                .get(object)
+                .fields
+                .get(field.index)
+                .ok_or_else(|| field.not_in_object())?;
+            frame.stack.push(value)?;
+        }
+        Instruction::Putfield(index) => {
+            let class = Arc::clone(&frame.class);
+            let field = resolve_field(&class, runtime, *index)?;
+            let value = frame.stack.pop()?;
+            if value.type_name() != field.type_name {
+                return Err(VmError::InvalidCode(format!(
+                    "Expected {} to assign to {}, found {:?}",
+                    field.type_name, field.name, value
+                )));
+            }
+            let object = frame.stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    format!(
+                        "Cannot assign field \"{}\" because the object is null",
+                        field.name
+                    ),
+                )
+            })?;
+            *runtime
+                .heap
+                .get_mut(object)
+                .fields
+                .get_mut(field.index)
+                .ok_or_else(|| field.not_in_object())? = value;
+        }
+
+        Instruction::Getstatic(index) => {
+            let class = Arc::clone(&frame.class);
+            let (owner, field) = match resolve_static_field(&class, runtime, *index)? {
+                StaticField::Resolved { owner, index } => (owner, index),
+                StaticField::Initialize(clinit) => return Ok(Flow::Invoke(clinit)),
+            };
+            frame.stack.push(runtime.statics[&owner].values[field])?;
+        }
+        Instruction::Putstatic(index) => {
+            let class = Arc::clone(&frame.class);
+            let (owner, field) = match resolve_static_field(&class, runtime, *index)? {
+                StaticField::Resolved { owner, index } => (owner, index),
+                StaticField::Initialize(clinit) => return Ok(Flow::Invoke(clinit)),
+            };
+            let value = frame.stack.pop()?;
+            let statics = runtime.statics.get_mut(&owner).expect("initialized");
+            if value.type_name() != statics.values[field].type_name() {
+                return Err(VmError::InvalidCode(format!(
+                    "Expected {} to assign to a static field of {}, found {:?}",
+                    statics.values[field].type_name(),
+                    owner,
+                    value
+                )));
+            }
+            statics.values[field] = value;
+        }
+
+        Instruction::Newarray(atype) => {
+            let component = match atype {
+                4 => FieldType::Boolean,
+                5 => FieldType::Char,
+                6 => FieldType::Float,
+                7 => FieldType::Double,
+                8 => FieldType::Byte,
+                9 => FieldType::Short,
+                10 => FieldType::Int,
+                11 => FieldType::Long,
+                _ => {
+                    return Err(VmError::InvalidCode(format!(
+                        "Invalid array type {} in {}",
+                        atype,
+                        frame.method_name()
+                    )))
+                }
+            };
+            let length = stack.pop_int()?;
+            let array = runtime.new_array(component, &[length])?;
+            frame.stack.push(Value::Reference(Some(array)))?;
+        }
+        Instruction::Anewarray(index) => {
+            let cp = &frame.class.constant_pool;
+            let name = constant::<cp_info::Class>(cp, *index)?.name_index.get(cp);
+            let component = class_type(name)?;
+            let length = frame.stack.pop_int()?;
+            let array = runtime.new_array(component, &[length])?;
+            frame.stack.push(Value::Reference(Some(array)))?;
+        }
+        Instruction::Multianewarray { index, dimensions } => {
+            let cp = &frame.class.constant_pool;
+            let name = constant::<cp_info::Class>(cp, *index)?.name_index.get(cp);
+            let array_type = class_type(name)?;
+            let mut depth = 0;
+            let mut element = &array_type;
+            while let FieldType::Array(inner) = element {
+                depth += 1;
+                element = inner;
+            }
+            let FieldType::Array(component) = array_type else {
+                return Err(VmError::InvalidCode(format!(
+                    "multianewarray of the class {}",
+                    name
+                )));
+            };
+            if *dimensions == 0 || usize::from(*dimensions) > depth {
+                return Err(VmError::InvalidCode(format!(
+                    "multianewarray of {} dimensions of {}",
+                    dimensions, name
+                )));
+            }
+            let mut lengths = Vec::with_capacity((*dimensions).into());
+            for _ in 0..*dimensions {
+                lengths.push(frame.stack.pop_int()?);
+            }
+            lengths.reverse();
+            let array = runtime.new_array(*component, &lengths)?;
+            frame.stack.push(Value::Reference(Some(array)))?;
+        }
+        Instruction::Arraylength => {
+            let array = stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    "Cannot read the array length because the array is null",
+                )
+            })?;
+            let array = runtime
+                .heap
+                .array(array)
+                .ok_or_else(|| VmError::InvalidCode("arraylength of an object".to_owned()))?;
+            stack.push_int(array.elements.len() as i32)?;
+        }
+        Instruction::Iaload => array_load(frame, runtime, ElementType::Int)?,
+        Instruction::Laload => array_load(frame, runtime, ElementType::Long)?,
+        Instruction::Faload => array_load(frame, runtime, ElementType::Float)?,
+        Instruction::Daload => array_load(frame, runtime, ElementType::Double)?,
+        Instruction::Aaload => array_load(frame, runtime, ElementType::Reference)?,
+        Instruction::Baload => array_load(frame, runtime, ElementType::Byte)?,
+        Instruction::Caload => array_load(frame, runtime, ElementType::Char)?,
+        Instruction::Saload => array_load(frame, runtime, ElementType::Short)?,
+        Instruction::Iastore => array_store(frame, runtime, ElementType::Int)?,
+        Instruction::Lastore => array_store(frame, runtime, ElementType::Long)?,
+        Instruction::Fastore => array_store(frame, runtime, ElementType::Float)?,
+        Instruction::Dastore => array_store(frame, runtime, ElementType::Double)?,
+        Instruction::Aastore => array_store(frame, runtime, ElementType::Reference)?,
+        Instruction::Bastore => array_store(frame, runtime, ElementType::Byte)?,
+        Instruction::Castore => array_store(frame, runtime, ElementType::Char)?,
+        Instruction::Sastore => array_store(frame, runtime, ElementType::Short)?,
+
+        Instruction::Invokevirtual(index) => return invoke_virtual(frame, runtime, *index),
+        Instruction::Invokespecial(index) => return invoke_special(frame, runtime, *index),
+        Instruction::Invokestatic(index) => return invoke_static(frame, runtime, *index),
+        Instruction::Invokeinterface { index, .. } => {
+            return invoke_virtual(frame, runtime, *index)
+        }
+        Instruction::Monitorenter => {
+            let object = stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    "Cannot enter synchronized block because the object is null",
+                )
+            })?;
+            if !runtime.monitors.enter(Lock::Object(object), runtime.thread) {
+                stack.push(Value::Reference(Some(object)))?;
+                return Ok(Flow::Blocked(ThreadState::Blocked));
+            }
+        }
+        Instruction::Monitorexit => {
+            let object = stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    "Cannot exit synchronized block because the object is null",
+                )
+            })?;
+            runtime
+                .monitors
+                .exit(&Lock::Object(object), runtime.thread)?;
+        }
+        Instruction::Ireturn => return Ok(Flow::Return(Some(Value::Int(stack.pop_int()?)))),
+        Instruction::Lreturn => return Ok(Flow::Return(Some(Value::Long(stack.pop_long()?)))),
+        Instruction::Freturn => return Ok(Flow::Return(Some(Value::Float(stack.pop_float()?)))),
+        Instruction::Dreturn => return Ok(Flow::Return(Some(Value::Double(stack.pop_double()?)))),
+        Instruction::Areturn => {
+            let reference = stack.pop()?.as_reference()?;
+            return Ok(Flow::Return(Some(Value::Reference(reference))));
+        }
+        Instruction::Return => return Ok(Flow::Return(None)),
+        Instruction::Athrow => {
+            let throwable = stack.pop()?.as_reference()?.ok_or_else(|| {
+                VmError::throw(
+                    "java/lang/NullPointerException",
+                    "Cannot throw exception because the value is null",
+                )
+            })?;
+            return Ok(Flow::Throw(throwable));
+        }
+        _ => {
+            return Err(VmError::Unsupported(format!(
+                "{} in {}",
+                instruction.mnemonic(),
+                frame.method_name()
+            )))
+        }
+    }
+    Ok(Flow::Next)
+}
+
+/// Pushes the local variable, which has to be of the type the instruction loads
+fn load(frame: &mut Frame, index: u16, type_name: &str) -> Result<(), VmError> {
+    let value = frame.locals.load(index)?;
+    if value.type_name() != type_name {
+        return Err(VmError::InvalidCode(format!(
+            "Expected {} in local variable {}, found {:?}",
+            type_name, index, value
+        )));
+    }
+    frame.stack.push(value)
+}
+
+/// Pops the value into the local variable, it has to be of the type the instruction stores.
+/// `astore` also stores the return addresses of `jsr`
+fn store(frame: &mut Frame, index: u16, type_name: &str) -> Result<(), VmError> {
+    let value = frame.stack.pop()?;
+    let matches = value.type_name() == type_name
+        || type_name == "reference" && matches!(value, Value::ReturnAddress(_));
+    if !matches {
+        return Err(VmError::InvalidCode(format!(
+            "Expected {} to store in local variable {}, found {:?}",
+            type_name, index, value
+        )));
+    }
+    frame.locals.store(index, value)
+}
+
+/// The `Integer`, `Float`, `Long`, `Double` or `String` constant of `ldc`, `ldc_w` or `ldc2_w`,
+/// which only loads the wide ones. Strings are interned
+fn load_constant(
+    frame: &Frame,
+    runtime: &mut Runtime,
+    index: u16,
+    wide: bool,
+) -> Result<Value, VmError> {
+    let cp = &frame.class.constant_pool;
+    let constant = constant::<cp_info::Loadable>(cp, index)?;
+    let Some(value) = runtime.constant(cp, constant)? else {
+        return Err(VmError::Unsupported(format!(
+            "loading the constant {:?} in {}",
+            constant,
+            frame.method_name()
+        )));
+    };
+    if value.is_wide() != wide {
+        return Err(VmError::InvalidCode(format!(
+            "{} can't load the {} constant {} in {}",
+            if wide { "ldc2_w" } else { "ldc" },
+            value.type_name(),
+            index,
+            frame.method_name()
+        )));
+    }
+    Ok(value)
+}
+
+/// Replaces the two ints on top of the stack with the result of the operation
+fn int_op(stack: &mut OperandStack, op: impl FnOnce(i32, i32) -> i32) -> Result<(), VmError> {
+    let b = stack.pop_int()?;
+    let a = stack.pop_int()?;
+    stack.push_int(op(a, b))
+}
+
+/// Like `int_op`, but dividing by zero throws an `ArithmeticException`
+fn int_division(stack: &mut OperandStack, op: fn(i32, i32) -> i32) -> Result<(), VmError> {
+    let b = stack.pop_int()?;
+    let a = stack.pop_int()?;
+    if b == 0 {
+        return Err(divide_by_zero());
+    }
+    stack.push_int(op(a, b))
+}
+
+/// Replaces the two longs on top of the stack with the result of the operation
+fn long_op(stack: &mut OperandStack, op: impl FnOnce(i64, i64) -> i64) -> Result<(), VmError> {
+    let b = stack.pop_long()?;
+    let a = stack.pop_long()?;
+    stack.push(Value::Long(op(a, b)))
+}
+
+/// Like `long_op`, but dividing by zero throws an `ArithmeticException`
+fn long_division(stack: &mut OperandStack, op: fn(i64, i64) -> i64) -> Result<(), VmError> {
+    let b = stack.pop_long()?;
+    let a = stack.pop_long()?;
+    if b == 0 {
+        return Err(divide_by_zero());
+    }
+    stack.push(Value::Long(op(a, b)))
+}
+
+/// Shifts the long below the int distance on top of the stack
+fn long_shift(stack: &mut OperandStack, op: fn(i64, u32) -> i64) -> Result<(), VmError> {
+    let distance = stack.pop_int()?;
+    let n = stack.pop_long()?;
+    stack.push(Value::Long(op(n, distance as u32)))
+}
+
+fn divide_by_zero() -> VmError {
+    VmError::throw("java/lang/ArithmeticException", "/ by zero")
+}
+
+/// Replaces the two floats on top of the stack with the result of the operation
+fn float_op(stack: &mut OperandStack, op: impl FnOnce(f32, f32) -> f32) -> Result<(), VmError> {
+    let b = stack.pop_float()?;
+    let a = stack.pop_float()?;
+    stack.push(Value::Float(op(a, b)))
+}
+
+/// Replaces the two doubles on top of the stack with the result of the operation
+fn double_op(stack: &mut OperandStack, op: impl FnOnce(f64, f64) -> f64) -> Result<(), VmError> {
+    let b = stack.pop_double()?;
+    let a = stack.pop_double()?;
+    stack.push(Value::Double(op(a, b)))
+}
+
+/// Replaces the value on top of the stack with the converted one
+fn convert<T>(
+    stack: &mut OperandStack,
+    pop: fn(&mut OperandStack) -> Result<T, VmError>,
+    convert: impl FnOnce(T) -> Value,
+) -> Result<(), VmError> {
+    let value = pop(stack)?;
+    stack.push(convert(value))
+}
+
+/// Replaces the two values on top of the stack with -1, 0 or 1 if the first one is less, equal
+/// or greater, or `unordered` if one of them is NaN
+fn compare<T: PartialOrd>(
+    stack: &mut OperandStack,
+    pop: fn(&mut OperandStack) -> Result<T, VmError>,
+    unordered: i32,
+) -> Result<(), VmError> {
+    let b = pop(stack)?;
+    let a = pop(stack)?;
+    stack.push_int(
+        a.partial_cmp(&b)
+            .map_or(unordered, |ordering| ordering as i32),
+    )
+}
+
+/// Jumps if the condition holds for the int on top of the stack
+fn branch_if(frame: &mut Frame, condition: fn(i32) -> bool, offset: i32) -> Result<Flow, VmError> {
+    let n = frame.stack.pop_int()?;
+    branch(frame, condition(n), offset)
+}
+
+/// Jumps if the condition holds for the two ints on top of the stack
+fn branch_if_cmp(
+    frame: &mut Frame,
+    condition: fn(i32, i32) -> bool,
+    offset: i32,
+) -> Result<Flow, VmError> {
+    let b = frame.stack.pop_int()?;
+    let a = frame.stack.pop_int()?;
+    branch(frame, condition(a, b), offset)
+}
+
+/// Jumps if the two references on top of the stack are the same, or not the same
+fn branch_if_same(frame: &mut Frame, same: bool, offset: i32) -> Result<Flow, VmError> {
+    let b = frame.stack.pop()?.as_reference()?;
+    let a = frame.stack.pop()?.as_reference()?;
+    branch(frame, (a == b) == same, offset)
+}
+
+fn branch(frame: &mut Frame, condition: bool, offset: i32) -> Result<Flow, VmError> {
+    if condition {
+        frame.jump(offset)?;
+        Ok(Flow::Jumped)
+    } else {
+        Ok(Flow::Next)
+    }
+}
+
+/// The type of the elements that an array instruction loads or stores
+#[derive(Debug, Clone, Copy)]
+enum ElementType {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+    /// Also `boolean`
+    Byte,
+    Char,
+    Short,
+}
+
+impl ElementType {
+    /// The name in error messages, like `int` in "Cannot load from int array"
+    fn name(self) -> &'static str {
+        match self {
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Float => "float",
+            Self::Double => "double",
+            Self::Reference => "object",
+            Self::Byte => "byte/boolean",
+            Self::Char => "char",
+            Self::Short => "short",
+        }
+    }
+
+    /// Whether the instruction can be used on arrays of the component type
+    fn matches(self, component: &FieldType) -> bool {
+        matches!(
+            (self, component),
+            (Self::Int, FieldType::Int)
+                | (Self::Long, FieldType::Long)
+                | (Self::Float, FieldType::Float)
+                | (Self::Double, FieldType::Double)
+                | (Self::Reference, FieldType::Object(_) | FieldType::Array(_))
+                | (Self::Byte, FieldType::Byte | FieldType::Boolean)
+                | (Self::Char, FieldType::Char)
+                | (Self::Short, FieldType::Short)
+        )
+    }
+}
+
+/// Pops the array that an array instruction like `iaload` uses, which has to have elements of the
+/// type. `action` is like `load from` for the message of the `NullPointerException`
+fn pop_array(
+    frame: &mut Frame,
+    runtime: &Runtime,
+    element_type: ElementType,
+    action: &str,
+) -> Result<ObjectRef, VmError> {
+    let reference = frame.stack.pop()?.as_reference()?.ok_or_else(|| {
+        VmError::throw(
+            "java/lang/NullPointerException",
+            format!(
+                "Cannot {} {} array because the array is null",
+                action,
+                element_type.name()
+            ),
+        )
+    })?;
+    match runtime.heap.array(reference) {
+        Some(array) if element_type.matches(&array.component) => Ok(reference),
+        _ => Err(VmError::InvalidCode(format!(
+            "Expected {} array in {}",
+            element_type.name(),
+            frame.method_name()
+        ))),
+    }
+}
+
+/// The index of the element, or an `ArrayIndexOutOfBoundsException`
+fn element_index(array: &Array, index: i32) -> Result<usize, VmError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&index| index < array.elements.len())
+        .ok_or_else(|| {
+            VmError::throw(
+                "java/lang/ArrayIndexOutOfBoundsException",
+                format!(
+                    "Index {} out of bounds for length {}",
+                    index,
+                    array.elements.len()
+                ),
+            )
+        })
+}
+
+/// Replaces the array and the index on top of the stack with the element
+fn array_load(
+    frame: &mut Frame,
+    runtime: &Runtime,
+    element_type: ElementType,
+) -> Result<(), VmError> {
+    let index = frame.stack.pop_int()?;
+    let reference = pop_array(frame, runtime, element_type, "load from")?;
+    let array = runtime.heap.array(reference).expect("an array");
+    let value = array.elements[element_index(array, index)?];
+    frame.stack.push(value)
+}
+
+/// Pops the value, the index and the array and stores the value in the element. Ints are truncated
+/// to the type of the elements, and references have to be of a subtype of it, otherwise an
+/// `ArrayStoreException` is thrown
+fn array_store(
+    frame: &mut Frame,
+    runtime: &mut Runtime,
+    element_type: ElementType,
+) -> Result<(), VmError> {
+    let value = frame.stack.pop()?;
+    let index = frame.stack.pop_int()?;
+    let reference = pop_array(frame, runtime, element_type, "store to")?;
+    let array = runtime.heap.array(reference).expect("an array");
+    let index = element_index(array, index)?;
+    let value = match (&array.component, value) {
+        (FieldType::Boolean, Value::Int(n)) => Value::Int(n & 1),
+        (FieldType::Byte, Value::Int(n)) => Value::Int((n as i8).into()),
+        (FieldType::Char, Value::Int(n)) => Value::Int((n as u16).into()),
+        (FieldType::Short, Value::Int(n)) => Value::Int((n as i16).into()),
+        (component, Value::Reference(Some(object))) if element_type.matches(component) => {
+            let object_type = runtime.type_of(object);
+            if !runtime.is_subtype(&object_type, component)? {
+                let name = match object_type {
+                    FieldType::Object(name) => internal_to_binary(&name),
+                    array => array.to_descriptor().replace('/', "."),
+                };
+                return Err(VmError::throw("java/lang/ArrayStoreException", name));
+            }
+            value
+        }
+        (component, _) if value.type_name() == Value::default_for(component).type_name() => value,
+        _ => {
+            return Err(VmError::InvalidCode(format!(
+                "Expected {} to store in {} array, found {:?}",
+                Value::default_for(&array.component).type_name(),
+                element_type.name(),
+                value
+            )))
+        }
+    };
+    let array = runtime.heap.get_mut(reference).array.as_mut();
+    array.expect("an array").elements[index] = value;
+    Ok(())
+}
+
+/// The type of the class with the internal name of a `Class` constant, which is a descriptor
+/// for arrays like `[I`
+fn class_type(name: &str) -> Result<FieldType, VmError> {
+    if !name.starts_with('[') {
+        return Ok(FieldType::Object(name.to_owned()));
+    }
+    let FieldDescriptor(field_type) = FieldDescriptor::from_str(name)
+        .map_err(|err| VmError::InvalidCode(format!("{}: {}", name, err.0)))?;
+    Ok(field_type)
+}
+
+/// The class, name and descriptor of the `Methodref` or `InterfaceMethodref` at the index
+fn method_ref(class: &ClassFile, index: u16) -> Result<(&str, &str, &str), VmError> {
+    let cp = &class.constant_pool;
+    let (class_index, name_and_type_index) = match constant::<CpInfoInner>(cp, index)? {
+        CpInfoInner::MethodRef(method) => (method.class_index, method.name_and_type_index),
+        CpInfoInner::InterfaceMethodref(method) => (method.class_index, method.name_and_type_index),
+        other => {
+            return Err(VmError::InvalidCode(format!(
+                "Expected a method reference, found {:?}",
+                other
+            )))
+        }
+    };
+    let name_and_type = name_and_type_index.get(cp);
+    Ok((
+        class_index.get(cp).name_index.get(cp),
+        name_and_type.name_index.get(cp),
+        name_and_type.descriptor_index.get(cp),
+    ))
+}
+
+/// Pops the arguments of the method with the descriptor, and the object it is invoked on first
+/// if there is one
+fn pop_args(
+    stack: &mut OperandStack,
+    descriptor: &str,
+    object: Option<&str>,
+) -> Result<Vec<Value>, VmError> {
+    let arity = MethodDescriptor::from_str(descriptor)
+        .map_err(|err| VmError::InvalidCode(format!("{}: {}", descriptor, err.0)))?
+        .arity();
+    let mut args = Vec::with_capacity(arity + 1);
+    for _ in 0..arity {
+        args.push(stack.pop()?);
+    }
+    if let Some(method) = object {
+        let object = stack.pop()?.as_reference()?.ok_or_else(|| {
+            VmError::throw(
+                "java/lang/NullPointerException",
+                format!("Cannot invoke \"{}\" because the object is null", method),
+            )
+        })?;
+        args.push(Value::Reference(Some(object)));
+    }
+    args.reverse();
+    Ok(args)
+}
+
+/// Errors if the resolved method is static and `is_static` isn't set, or the other way around
+fn check_static(class: &ClassFile, method: usize, is_static: bool) -> Result<(), VmError> {
+    let info = &class.methods[method];
+    if info.access_flags.contains(MethodAccessFlag::STATIC) == is_static {
+        return Ok(());
+    }
+    let cp = &class.constant_pool;
+    Err(VmError::throw(
+        "java/lang/IncompatibleClassChangeError",
+        format!(
+            "Expected {} method {}.{}{}",
+            if is_static { "static" } else { "non-static" },
+            class.this_class.get(cp).name_index.get(cp),
+            info.name_index.get(cp),
+            info.descriptor_index.get(cp)
+        ),
+    ))
+}
+
+/// Executes the selected method, which can't be abstract. Native methods are run right away and
+/// their result is pushed onto the stack of the caller. `synchronized` methods enter the monitor
+/// of the object or the class first, the arguments are pushed back if it has to wait for it
+fn invoke(
+    frame: &mut Frame,
+    runtime: &mut Runtime,
+    class: Arc<ClassFile>,
+    method: usize,
+    args: &[Value],
+) -> Result<Flow, VmError> {
+    let info = &class.methods[method];
+    let cp = &class.constant_pool;
+    let class_name = class.this_class.get(cp).name_index.get(cp);
+    let method_name = || {
+        format!(
+            "{}.{}{}",
+            class_name,
+            info.name_index.get(cp),
+            info.descriptor_index.get(cp)
+        )
+    };
+    if info.access_flags.contains(MethodAccessFlag::ABSTRACT) {
+        return Err(VmError::throw(
+            "java/lang/AbstractMethodError",
+            method_name(),
+        ));
+    }
+    let lock = if info.access_flags.contains(MethodAccessFlag::SYNCHRONIZED) {
+        let lock = if info.access_flags.contains(MethodAccessFlag::STATIC) {
+            Lock::Class(class_name.to_owned())
+        } else {
+            Lock::Object(args[0].as_reference()?.expect("a checked object"))
+        };
+        if !runtime.monitors.enter(lock.clone(), runtime.thread) {
+            push_args(frame, args)?;
+            return Ok(Flow::Blocked(ThreadState::Blocked));
+        }
+        Some(lock)
+    } else {
+        None
+    };
+    if !info.access_flags.contains(MethodAccessFlag::NATIVE) {
+        let mut callee = Frame::new(class, method, args)?;
+        callee.lock = lock;
+        return Ok(Flow::Invoke(callee));
+    }
+
+    let name = method_name();
+    let native = *runtime
+        .natives
+        .get(&name)
+        .ok_or_else(|| VmError::throw("java/lang/UnsatisfiedLinkError", name))?;
+    let mut context = NativeContext {
+        heap: &mut runtime.heap,
+        stdout: &mut runtime.stdout,
+        alive_threads: &runtime.alive_threads,
+        schedule: Schedule::Continue,
+    };
+    let value = native(&mut context, args);
+    let schedule = context.schedule;
+    if let Some(lock) = &lock {
+        runtime.monitors.exit(lock, runtime.thread)?;
+    }
+    let value = value?;
+    if schedule == Schedule::Block {
+        push_args(frame, args)?;
+        return Ok(Flow::Blocked(ThreadState::Waiting));
+    }
+    if let Some(value) = value {
+        frame.stack.push(value)?;
+    }
+    match schedule {
+        Schedule::Continue | Schedule::Block => Ok(Flow::Next),
+        Schedule::Yield => Ok(Flow::Yield),
+        Schedule::Start(thread) => {
+            if !runtime.started_threads.insert(thread) {
+                return Err(VmError::throw(
+                    "java/lang/IllegalThreadStateException",
+                    "The thread was already started",
+                ));
+            }
+            let thread_class = Arc::clone(&runtime.heap.get(thread).class);
+            let (class, method) = runtime.select_method(thread_class, "run", "()V")?;
+            let args = [Value::Reference(Some(thread))];
+            Ok(Flow::Spawn(Frame::new(class, method, &args)?, thread))
+        }
+    }
+}
+
+/// Pushes the arguments of a method that couldn't be invoked yet back onto the stack
+fn push_args(frame: &mut Frame, args: &[Value]) -> Result<(), VmError> {
+    for &arg in args {
+        frame.stack.push(arg)?;
+    }
+    Ok(())
+}
+
+/// Calls a static method with the arguments from the stack, after initializing its class
+fn invoke_static(frame: &mut Frame, runtime: &mut Runtime, index: u16) -> Result<Flow, VmError> {
+    let current = Arc::clone(&frame.class);
+    let (class_name, name, descriptor) = method_ref(&current, index)?;
+    let (class, method) = runtime.resolve_method(class_name, name, descriptor)?;
+    check_static(&class, method, true)?;
+    let cp = &class.constant_pool;
+    if let Some(clinit) = runtime.initialize(class.this_class.get(cp).name_index.get(cp))? {
+        return Ok(Flow::Invoke(clinit));
+    }
+    let args = pop_args(&mut frame.stack, descriptor, None)?;
+    invoke(frame, runtime, class, method, &args)
+}
+
+/// Calls the method that overrides the resolved one in the class of the object, or the resolved
+/// one if it is private. `invokeinterface` selects the method the same way
+fn invoke_virtual(frame: &mut Frame, runtime: &mut Runtime, index: u16) -> Result<Flow, VmError> {
+    let current = Arc::clone(&frame.class);
+    let (class_name, name, descriptor) = method_ref(&current, index)?;
+    let (class, method) = runtime.resolve_method(class_name, name, descriptor)?;
+    check_static(&class, method, false)?;
+    let method_name = format!("{}.{}{}", class_name, name, descriptor);
+    let args = pop_args(&mut frame.stack, descriptor, Some(&method_name))?;
+    let (class, method) = if class.methods[method]
+        .access_flags
+        .contains(MethodAccessFlag::PRIVATE)
+    {
+        (class, method)
+    } else {
+        let object = args[0].as_reference()?.expect("a checked object");
+        let object_class = Arc::clone(&runtime.heap.get(object).class);
+        runtime.select_method(object_class, name, descriptor)?
+    };
+    invoke(frame, runtime, class, method, &args)
+}
+
+/// Calls a constructor, a private method, or a method of a superclass of the current class
+/// with `super.method()`, which is looked up starting at the direct superclass
+fn invoke_special(frame: &mut Frame, runtime: &mut Runtime, index: u16) -> Result<Flow, VmError> {
+    let current = Arc::clone(&frame.class);
+    let (class_name, name, descriptor) = method_ref(&current, index)?;
+    let (class, method) = runtime.resolve_method(class_name, name, descriptor)?;
+    check_static(&class, method, false)?;
+    let method_name = format!("{}.{}{}", class_name, name, descriptor);
+    let args = pop_args(&mut frame.stack, descriptor, Some(&method_name))?;
+    let is_super_call = name != "<init>"
+        && !class.methods[method]
+            .access_flags
+            .contains(MethodAccessFlag::PRIVATE)
+        && current.access_flags.contains(ClassAccessFlag::Super)
+        && runtime.is_super_class(&current, class_name)?;
+    let (class, method) = match runtime.super_class(&current)? {
+        Some(super_class) if is_super_call => {
+            runtime.select_method(super_class, name, descriptor)?
+        }
+        _ => (class, method),
+    };
+    invoke(frame, runtime, class, method, &args)
+}
+
+/// An instance field that a `Fieldref` refers to
+struct ResolvedField<'a> {
+    name: &'a str,
+    /// The index in the layout of the class of the `Fieldref` and its subclasses
+    index: usize,
+    /// The type of the values of the field, like `int` for a `boolean` field
+    type_name: &'static str,
+}
+
+impl ResolvedField<'_> {
+    /// The error if the object has fewer fields than the class of the `Fieldref`
+    fn not_in_object(&self) -> VmError {
+        VmError::InvalidCode(format!(
+            "The field {} is accessed on an object of another class",
+            self.name
+        ))
+    }
+}
+
+/// Finds the instance field the `Fieldref` refers to in its class or the superclasses
+fn resolve_field<'a>(
+    class: &'a ClassFile,
+    runtime: &mut Runtime,
+    index: u16,
+) -> Result<ResolvedField<'a>, VmError> {
+    let cp = &class.constant_pool;
+    let field_ref = constant::<cp_info::Fieldref>(cp, index)?;
+    let class_name = field_ref.class_index.get(cp).name_index.get(cp);
+    let name_and_type = field_ref.name_and_type_index.get(cp);
+    let name = name_and_type.name_index.get(cp);
+    let descriptor = name_and_type.descriptor_index.get(cp);
+    let FieldDescriptor(field_type) = FieldDescriptor::from_str(descriptor)
+        .map_err(|err| VmError::InvalidCode(format!("{}: {}", descriptor, err.0)))?;
+    let index = runtime
+        .layout(class_name)?
+        .index_of(name, descriptor)
+        .ok_or_else(|| VmError::throw("java/lang/NoSuchFieldError", name))?;
+    Ok(ResolvedField {
+        name,
+        index,
+        type_name: Value::default_for(&field_type).type_name(),
+    })
+}
+
+/// A static field that a `Fieldref` refers to
+enum StaticField {
+    /// The class that declares the field is initialized, the field is at the index in its statics
+    Resolved { owner: String, index: usize },
+    /// The `<clinit>` that has to run before the field can be used
+    Initialize(Frame),
+}
+
+/// Finds the static field the `Fieldref` refers to and initializes the class that declares it
+fn resolve_static_field(
+    class: &ClassFile,
+    runtime: &mut Runtime,
+    index: u16,
+) -> Result<StaticField, VmError> {
+    let cp = &class.constant_pool;
+    let field_ref = constant::<cp_info::Fieldref>(cp, index)?;
+    let class_name = field_ref.class_index.get(cp).name_index.get(cp);
+    let name_and_type = field_ref.name_and_type_index.get(cp);
+    let name = name_and_type.name_index.get(cp);
+    let descriptor = name_and_type.descriptor_index.get(cp);
+    let owner = match runtime.field_owner(class_name, name, descriptor)? {
+        Some((owner, true)) => owner,
+        Some((owner, false)) => {
+            let owner_cp = &owner.constant_pool;
+            return Err(VmError::throw(
+                "java/lang/IncompatibleClassChangeError",
+                format!(
+                    "Expected static field {}.{}",
+                    owner.this_class.get(owner_cp).name_index.get(owner_cp),
+                    name
+                ),
+            ));
+        }
+        None => return Err(VmError::throw("java/lang/NoSuchFieldError", name)),
+    };
+    let owner_cp = &owner.constant_pool;
+    let owner = owner.this_class.get(owner_cp).name_index.get(owner_cp);
+    if let Some(clinit) = runtime.initialize(owner)? {
+        return Ok(StaticField::Initialize(clinit));
+    }
+    let index = runtime.statics[owner]
+        .index_of(name, descriptor)
+        .expect("the field of the class");
+    Ok(StaticField::Resolved {
+        owner: owner.to_owned(),
+        index,
+    })
+}
+
+/// The constant at the index, which has to be of type `T`
+fn constant<'pool, T: FromCpInfo<'pool>>(
+    cp: &'pool ConstantPool,
+    index: u16,
+) -> Result<T::Target, VmError> {
+    let index = FromPool::<T>::from(index);
+    index
+        .validate(cp)
+        .map_err(|err| VmError::InvalidCode(err.message().to_owned()))?;
+    Ok(index.get(cp))
+}
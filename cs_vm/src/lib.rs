@@ -1,2 +1,29 @@
-#[allow(dead_code)]
+mod builtin;
+mod debug;
+pub mod error;
+mod frame;
+mod gc;
+mod heap;
+mod hook;
+mod interpreter;
+mod loader;
 mod model;
+mod statics;
+mod stubs;
+#[cfg(test)]
+mod test;
+mod thread;
+#[cfg(debug_assertions)]
+mod trace;
+mod value;
+
+pub use builtin::{NativeContext, NativeMethod};
+pub use debug::{Breakpoint, DebugAction, Debugger, Execution};
+pub use frame::Frame;
+pub use heap::{Array, Heap, Layout, Object};
+pub use hook::{Hook, MethodExit};
+pub use interpreter::{Interpreter, DEFAULT_MAX_FRAMES};
+pub use loader::ClassLoader;
+pub use statics::{InitState, Statics};
+pub use stubs::native_stubs;
+pub use value::{ObjectRef, Value};
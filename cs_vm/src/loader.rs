@@ -0,0 +1,135 @@
+//!
+//! Loading classes from the class path when they are first used
+//!
+
+use crate::builtin::builtin_class;
+use crate::error::{LinkageError, VmError};
+use cs_archive::ClassPath;
+use cs_parser::{ClassFile, MAGIC};
+use cs_verifier::{ClassHierarchy, ClassInfo};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The classes the interpreter can use, by their internal name. Every name is only defined once,
+/// the first time the class is referenced it is loaded from the class path, and after it the
+/// superclass and the interfaces it implements
+#[derive(Debug, Default)]
+pub struct ClassLoader {
+    class_path: ClassPath,
+    state: Mutex<LoaderState>,
+}
+
+#[derive(Debug, Default)]
+struct LoaderState {
+    classes: HashMap<String, Arc<ClassFile>>,
+    /// The classes whose superclasses are being loaded, to find cycles
+    loading: Vec<String>,
+}
+
+impl ClassLoader {
+    pub fn new(class_path: ClassPath) -> Self {
+        Self {
+            class_path,
+            state: Mutex::default(),
+        }
+    }
+
+    /// The class that was defined with the name, without loading it
+    pub fn loaded(&self, name: &str) -> Option<Arc<ClassFile>> {
+        self.state.lock().unwrap().classes.get(name).cloned()
+    }
+
+    /// Defines the class, which may not have been defined or loaded before. Unlike the classes
+    /// from the class path, it is not verified
+    pub fn define(&self, class: Arc<ClassFile>) -> Result<(), VmError> {
+        let cp = &class.constant_pool;
+        let name = class.this_class.get(cp).name_index.get(cp).to_owned();
+        let mut state = self.state.lock().unwrap();
+        if state.classes.contains_key(&name) {
+            return Err(LinkageError::DuplicateClass(name).into());
+        }
+        state.classes.insert(name, class);
+        Ok(())
+    }
+
+    /// The class with the internal name, which is loaded if it wasn't yet. The builtin classes
+    /// are used instead of the ones of the JDK on the class path, which need natives the
+    /// interpreter doesn't have
+    pub fn load(&self, name: &str) -> Result<Arc<ClassFile>, VmError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(class) = state.classes.get(name) {
+                return Ok(Arc::clone(class));
+            }
+            if let Some(class) = builtin_class(name) {
+                state.classes.insert(name.to_owned(), Arc::clone(&class));
+                return Ok(class);
+            }
+            if state.loading.iter().any(|loading| loading == name) {
+                return Err(LinkageError::ClassCircularity(name.to_owned()).into());
+            }
+            state.loading.push(name.to_owned());
+        }
+        // the lock isn't held while the superclasses are loaded, which locks it again
+        let result = self.load_from_class_path(name);
+        let mut state = self.state.lock().unwrap();
+        state.loading.retain(|loading| loading != name);
+        let class = result?;
+        state.classes.insert(name.to_owned(), Arc::clone(&class));
+        Ok(class)
+    }
+
+    /// Parses the class, loads its superclass and interfaces, and verifies it
+    fn load_from_class_path(&self, name: &str) -> Result<Arc<ClassFile>, VmError> {
+        let bytes = self
+            .class_path
+            .find_bytes(name)
+            .map_err(|err| {
+                VmError::throw(
+                    "java/lang/NoClassDefFoundError",
+                    format!("{}: {}", name, err),
+                )
+            })?
+            .ok_or_else(|| VmError::throw("java/lang/NoClassDefFoundError", name))?;
+        if let Some(version) = bytes.get(..8) {
+            let magic = u32::from_be_bytes([version[0], version[1], version[2], version[3]]);
+            let minor = u16::from_be_bytes([version[4], version[5]]);
+            let major = u16::from_be_bytes([version[6], version[7]]);
+            if magic == MAGIC && cs_parser::validate_header(major, minor).is_err() {
+                return Err(LinkageError::UnsupportedClassVersion { major, minor }.into());
+            }
+        }
+        let mut class = cs_parser::parse_class_file(&bytes).map_err(LinkageError::from)?;
+        // the verifier doesn't know subroutines, which are only allowed before version 51
+        if class.major_version < 51 {
+            class
+                .inline_subroutines()
+                .map_err(|err| LinkageError::Verify(err.to_string()))?;
+        }
+
+        let cp = &class.constant_pool;
+        let this_class = class.this_class.get(cp).name_index.get(cp);
+        if this_class != name {
+            return Err(VmError::throw(
+                "java/lang/NoClassDefFoundError",
+                format!("{} (wrong name: {})", name, this_class),
+            ));
+        }
+        if let Some(super_class) = class.super_class.maybe_get(cp) {
+            self.load(super_class.name_index.get(cp))?;
+        }
+        for interface in &class.interfaces {
+            self.load(interface.get(cp).name_index.get(cp))?;
+        }
+        cs_verifier::verify_class(&class, self).map_err(LinkageError::from)?;
+        Ok(Arc::new(class))
+    }
+}
+
+/// The verifier only knows the classes that were loaded already, it assumes that the others can
+/// be assigned wherever they are used
+impl ClassHierarchy for ClassLoader {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        self.loaded(name).map(|class| ClassInfo::of(&class).1)
+    }
+}
@@ -1,89 +1,305 @@
+use crate::error::VmError;
+use crate::value::Value;
+
+/// A slot of the operand stack or the local variables
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Value(Value),
+    /// The second slot of the long or double in the slot below
+    Upper,
+    /// A local variable that was not stored yet, or whose long or double was overwritten in half
+    Empty,
+}
+
+/// The operand stack of a frame, longs and doubles take up two slots. Code that pushes more
+/// than `max_stack` slots or pops from the empty stack fails
 pub struct OperandStack {
-    arr: [u32; 255],
-    sp: u8,
+    slots: Vec<Slot>,
+    max_stack: usize,
 }
 
 impl OperandStack {
-    pub fn new() -> Self {
+    /// An empty stack for up to `max_stack` slots, from the `Code` attribute
+    pub fn new(max_stack: u16) -> Self {
         Self {
-            arr: [0; 255],
-            sp: 0,
+            slots: Vec::with_capacity(max_stack.into()),
+            max_stack: max_stack.into(),
+        }
+    }
+
+    pub fn push(&mut self, value: Value) -> Result<(), VmError> {
+        self.reserve(if value.is_wide() { 2 } else { 1 })?;
+        self.slots.push(Slot::Value(value));
+        if value.is_wide() {
+            self.slots.push(Slot::Upper);
+        }
+        Ok(())
+    }
+
+    /// Checks that `count` more slots fit on the stack
+    fn reserve(&self, count: usize) -> Result<(), VmError> {
+        if self.slots.len() + count > self.max_stack {
+            return Err(VmError::InvalidCode(format!(
+                "Operand stack overflow, it only has {} slots",
+                self.max_stack
+            )));
+        }
+        Ok(())
+    }
+
+    /// Pops the value on top, with both slots of a long or double
+    pub fn pop(&mut self) -> Result<Value, VmError> {
+        match self.pop_slot()? {
+            Slot::Value(value) if !value.is_wide() => Ok(value),
+            Slot::Upper => match self.pop_slot()? {
+                Slot::Value(value) if value.is_wide() => Ok(value),
+                _ => Err(VmError::InvalidCode(
+                    "The upper half of a long or double is on its own on the stack".to_owned(),
+                )),
+            },
+            _ => Err(VmError::InvalidCode(
+                "Half of a long or double is on top of the stack".to_owned(),
+            )),
+        }
+    }
+
+    /// Removes all values, like the handler of an exception does
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// The values from the bottom to the top of the stack
+    pub fn values(&self) -> impl Iterator<Item = Value> + '_ {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Value(value) => Some(*value),
+            _ => None,
+        })
+    }
+
+    fn pop_slot(&mut self) -> Result<Slot, VmError> {
+        self.slots.pop().ok_or_else(underflow)
+    }
+
+    pub fn push_int(&mut self, n: i32) -> Result<(), VmError> {
+        self.push(Value::Int(n))
+    }
+
+    pub fn pop_int(&mut self) -> Result<i32, VmError> {
+        self.pop()?.as_int()
+    }
+
+    pub fn pop_long(&mut self) -> Result<i64, VmError> {
+        self.pop()?.as_long()
+    }
+
+    pub fn pop_float(&mut self) -> Result<f32, VmError> {
+        self.pop()?.as_float()
+    }
+
+    pub fn pop_double(&mut self) -> Result<f64, VmError> {
+        self.pop()?.as_double()
+    }
+
+    /// Removes the top `count` slots, which must not split a long or double
+    pub fn pop_slots(&mut self, count: usize) -> Result<(), VmError> {
+        self.check_boundary(count)?;
+        for _ in 0..count {
+            self.pop_slot()?;
         }
+        Ok(())
     }
 
-    pub fn pop(&mut self) -> u32 {
-        self.sp -= 1;
-        self.arr[self.sp as usize]
+    /// Duplicates the top `count` slots and inserts the copies below the `skip` slots under them,
+    /// like the forms of `dup_x2` or `dup2_x1`. Neither group may split a long or double
+    pub fn dup(&mut self, count: usize, skip: usize) -> Result<(), VmError> {
+        self.check_boundary(count)?;
+        self.check_boundary(count + skip)?;
+        self.reserve(count)?;
+        let moved = self.slots.split_off(self.slots.len() - count - skip);
+        self.slots.extend_from_slice(&moved[skip..]);
+        self.slots.extend(moved);
+        Ok(())
     }
 
-    pub fn push(&mut self, n: u32) {
-        self.arr[self.sp as usize] = n;
-        self.sp += 1;
+    /// Swaps the two values on top, which can't be longs or doubles
+    pub fn swap(&mut self) -> Result<(), VmError> {
+        self.check_boundary(1)?;
+        self.check_boundary(2)?;
+        let len = self.slots.len();
+        self.slots.swap(len - 2, len - 1);
+        Ok(())
     }
 
-    pub fn swap(&mut self) {
-        self.arr
-            .swap((self.sp - 2) as usize, (self.sp - 2) as usize);
+    /// Checks that the top `depth` slots start with the lower half of a value
+    fn check_boundary(&self, depth: usize) -> Result<(), VmError> {
+        let index = self.slots.len().checked_sub(depth).ok_or_else(underflow)?;
+        if self.slots[index] == Slot::Upper {
+            return Err(VmError::InvalidCode(
+                "An instruction splits a long or double on the stack".to_owned(),
+            ));
+        }
+        Ok(())
     }
 }
 
+fn underflow() -> VmError {
+    VmError::InvalidCode("Operand stack underflow".to_owned())
+}
+
+/// The local variables of a frame, longs and doubles take up two slots
 pub struct LocalVariables {
-    arr: [u32; 255],
+    slots: Vec<Slot>,
 }
 
 impl LocalVariables {
-    pub fn new() -> Self {
-        Self { arr: [0; 255] }
+    /// `max_locals` empty slots, from the `Code` attribute
+    pub fn new(max_locals: u16) -> Self {
+        Self {
+            slots: vec![Slot::Empty; max_locals.into()],
+        }
     }
 
-    pub fn store(&mut self, address: u8, value: u32) {
-        self.arr[address as usize] = value;
+    /// Stores the value in the slot at the index, and the slot after it for a long or double.
+    /// A long or double that is overwritten in half can't be loaded anymore
+    pub fn store(&mut self, index: u16, value: Value) -> Result<(), VmError> {
+        let index = usize::from(index);
+        let range = index..index + if value.is_wide() { 2 } else { 1 };
+        if range.end > self.slots.len() {
+            return Err(VmError::InvalidCode(format!(
+                "Stored a {} to local variable {}, but there are only {}",
+                value.type_name(),
+                index,
+                self.slots.len()
+            )));
+        }
+        for i in range.clone() {
+            match self.slots[i] {
+                Slot::Upper if !range.contains(&(i - 1)) => self.slots[i - 1] = Slot::Empty,
+                Slot::Value(old) if old.is_wide() && !range.contains(&(i + 1)) => {
+                    self.slots[i + 1] = Slot::Empty
+                }
+                _ => {}
+            }
+        }
+        self.slots[index] = Slot::Value(value);
+        if value.is_wide() {
+            self.slots[index + 1] = Slot::Upper;
+        }
+        Ok(())
     }
 
-    pub fn store2(&mut self, address: u8, value1: u32, value2: u32) {
-        self.arr[address as usize] = value1;
-        self.arr[address as usize + 1] = value2;
+    /// The values of the local variables that were stored, from the first to the last
+    pub fn values(&self) -> impl Iterator<Item = Value> + '_ {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Value(value) => Some(*value),
+            _ => None,
+        })
     }
 
-    pub fn load(&self, address: u8) -> u32 {
-        self.arr[address as usize]
+    /// The number of slots, `max_locals`
+    pub fn len(&self) -> usize {
+        self.slots.len()
     }
-    pub fn load2(&self, address: u8) -> (u32, u32) {
-        (self.arr[address as usize], self.arr[address as usize + 1])
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn load(&self, index: u16) -> Result<Value, VmError> {
+        match self.slots.get(usize::from(index)) {
+            Some(Slot::Value(value)) => Ok(*value),
+            Some(Slot::Upper) => Err(VmError::InvalidCode(format!(
+                "Local variable {} is the upper half of a long or double",
+                index
+            ))),
+            Some(Slot::Empty) => Err(VmError::InvalidCode(format!(
+                "Local variable {} has no value",
+                index
+            ))),
+            None => Err(VmError::InvalidCode(format!(
+                "Loaded local variable {}, but there are only {}",
+                index,
+                self.slots.len()
+            ))),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{LocalVariables, OperandStack};
+    use crate::value::Value;
 
     #[test]
-    #[ignore]
     fn operand_stack() {
-        let mut stack = OperandStack::new();
+        let mut stack = OperandStack::new(4);
+
+        stack.push_int(10).unwrap();
+        stack.push_int(20).unwrap();
+        stack.push_int(30).unwrap();
+        stack.push_int(40).unwrap();
+        stack.swap().unwrap();
+
+        assert!(stack.push_int(50).is_err());
+        assert!(stack.dup(1, 0).is_err());
+
+        assert_eq!(stack.pop_int(), Ok(30));
+        assert_eq!(stack.pop_int(), Ok(40));
+        assert_eq!(stack.pop_int(), Ok(20));
+        assert_eq!(stack.pop_int(), Ok(10));
+        assert!(stack.pop().is_err());
+        assert!(stack.pop_slots(1).is_err());
+        assert!(stack.swap().is_err());
+    }
+
+    #[test]
+    fn operand_stack_wide() {
+        let mut stack = OperandStack::new(6);
 
-        stack.push(10);
-        stack.push(20);
-        stack.push(30);
-        stack.push(40);
-        stack.swap();
+        stack.push_int(1).unwrap();
+        stack.push(Value::Long(2)).unwrap();
+        // dup2_x1 with a long on top
+        stack.dup(2, 1).unwrap();
+        assert!(stack.swap().is_err());
+        assert!(stack.dup(1, 0).is_err());
 
-        assert_eq!(stack.pop(), 30);
-        assert_eq!(stack.pop(), 40);
-        assert_eq!(stack.pop(), 20);
-        assert_eq!(stack.pop(), 10);
+        assert!(stack.push(Value::Double(3.0)).is_err());
+
+        assert_eq!(stack.pop_long(), Ok(2));
+        assert_eq!(stack.pop_int(), Ok(1));
+        assert!(stack.pop_int().is_err());
     }
 
     #[test]
     fn local_vars() {
-        let mut vars = LocalVariables::new();
+        let mut vars = LocalVariables::new(5);
+
+        vars.store(1, Value::Int(546)).unwrap();
+        vars.store(2, Value::Int(100)).unwrap();
+        vars.store(3, Value::Double(4.5)).unwrap();
+
+        assert_eq!(vars.load(1), Ok(Value::Int(546)));
+        assert_eq!(vars.load(3), Ok(Value::Double(4.5)));
+        assert!(vars.load(4).is_err());
+        assert!(vars.load(0).is_err());
+        assert!(vars.store(4, Value::Long(1)).is_err());
+    }
+
+    #[test]
+    fn local_vars_overwrite_half() {
+        let mut vars = LocalVariables::new(4);
+
+        vars.store(0, Value::Long(1)).unwrap();
+        vars.store(2, Value::Long(2)).unwrap();
+        // overwrites the upper half of the first and the lower half of the second long
+        vars.store(1, Value::Long(3)).unwrap();
 
-        vars.store(1, 546);
-        vars.store(2, 100);
-        vars.store2(3, 100, 466);
+        assert!(vars.load(0).is_err());
+        assert_eq!(vars.load(1), Ok(Value::Long(3)));
+        assert!(vars.load(3).is_err());
 
-        assert_eq!(vars.load(1), 546);
-        assert_eq!(vars.load(3), 100);
-        assert_eq!(vars.load(4), 466);
+        vars.store(2, Value::Int(4)).unwrap();
+        assert!(vars.load(1).is_err());
+        assert_eq!(vars.load(2), Ok(Value::Int(4)));
     }
 }
@@ -0,0 +1,50 @@
+//!
+//! The static fields of classes and the state of their initialization
+//!
+
+use crate::error::VmError;
+use crate::heap::Layout;
+use crate::value::Value;
+use cs_parser::ClassFile;
+
+/// How far the initialization of a class is, see JVMS §5.5. Classes without statics were not
+/// initialized yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitState {
+    /// `<clinit>` is running, and can already use the class
+    Initializing,
+    Initialized,
+    /// `<clinit>` failed, so the class can't be used
+    Erroneous,
+}
+
+/// The static fields of a class whose initialization has started
+#[derive(Debug)]
+pub struct Statics {
+    pub state: InitState,
+    layout: Layout,
+    /// The values in the order of the layout
+    pub values: Vec<Value>,
+}
+
+impl Statics {
+    /// The default values of the static fields of the class
+    pub fn new(class: &ClassFile, state: InitState) -> Result<Self, VmError> {
+        let layout = Layout::statics(class)?;
+        Ok(Self {
+            state,
+            values: layout.defaults(),
+            layout,
+        })
+    }
+
+    /// The index of the field with the name and descriptor in `values`
+    pub fn index_of(&self, name: &str, descriptor: &str) -> Option<usize> {
+        self.layout.index_of(name, descriptor)
+    }
+
+    pub fn get(&self, name: &str, descriptor: &str) -> Option<Value> {
+        self.index_of(name, descriptor)
+            .map(|index| self.values[index])
+    }
+}
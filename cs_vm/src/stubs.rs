@@ -0,0 +1,267 @@
+//!
+//! Generating the Rust side of the `native` methods of a class
+//!
+
+use crate::error::VmError;
+use cs_model::{FieldType, MethodDescriptor, MethodType};
+use cs_parser::{AttributeInfoInner, ClassFile, MethodAccessFlag, MethodInfo};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Generates Rust code for the public `native` methods of the class, to implement them with
+/// `Interpreter::register_native`. The other methods have code, which is executed instead.
+///
+/// For `pkg/Native` with `public static native int twice(int n)` it generates a trait `NativeNatives`
+/// with `fn twice(context: &mut NativeContext<'_>, n: i32) -> Result<i32, VmError>`, a `register`
+/// function that registers the methods of an implementation and converts the `Value`s to and from
+/// the Rust types, and a struct `Native` implementing the trait with `todo!()`s.
+/// Instance methods get the object as `this`, references are `Option<ObjectRef>`
+pub fn native_stubs(class: &ClassFile) -> Result<String, VmError> {
+    let cp = &class.constant_pool;
+    let class_name = class.this_class.get(cp).name_index.get(cp);
+    let simple_name = class_name.rsplit(['/', '$']).next().unwrap_or(class_name);
+    let type_name = rust_identifier(simple_name, false);
+    let trait_name = format!("{}Natives", type_name);
+
+    let mut methods = Vec::new();
+    for method in &class.methods {
+        if !method.access_flags.contains(MethodAccessFlag::PUBLIC)
+            || !method.access_flags.contains(MethodAccessFlag::NATIVE)
+        {
+            continue;
+        }
+        let name = method.name_index.get(cp);
+        let descriptor_str = method.descriptor_index.get(cp);
+        let descriptor = MethodDescriptor::from_str(descriptor_str).map_err(|err| {
+            VmError::InvalidCode(format!("{}{}: {}", name, descriptor_str, err.0))
+        })?;
+        methods.push(Stub {
+            name,
+            descriptor_str,
+            function: rust_identifier(name, true),
+            is_static: method.access_flags.contains(MethodAccessFlag::STATIC),
+            parameters: parameter_names(class, method, &descriptor),
+            descriptor,
+        });
+    }
+    // overloads are numbered in the order of the class file
+    let mut overloads = HashMap::<String, usize>::new();
+    for stub in &methods {
+        *overloads.entry(stub.function.clone()).or_default() += 1;
+    }
+    let mut numbers = HashMap::<String, usize>::new();
+    for stub in &mut methods {
+        if overloads[&stub.function] > 1 {
+            let number = numbers.entry(stub.function.clone()).or_default();
+            *number += 1;
+            stub.function = format!("{}_{}", stub.function.trim_start_matches("r#"), number);
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "//! The `native` methods of `{}`", class_name);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "use cs_vm::error::VmError;");
+    let _ = writeln!(
+        out,
+        "use cs_vm::{{Interpreter, NativeContext, ObjectRef, Value}};"
+    );
+    let _ = writeln!(out);
+    let _ = writeln!(out, "pub trait {} {{", trait_name);
+    for stub in &methods {
+        let _ = writeln!(out, "    /// `{}{}`", stub.name, stub.descriptor_str);
+        let _ = writeln!(out, "    {};", stub.signature());
+        let _ = writeln!(out);
+    }
+    let _ = writeln!(
+        out,
+        "    /// Registers the methods as the natives of `{}`",
+        class_name
+    );
+    let _ = writeln!(out, "    fn register(interpreter: &mut Interpreter) {{");
+    for stub in &methods {
+        let _ = writeln!(out, "        interpreter.register_native(");
+        let _ = writeln!(out, "            {:?},", class_name);
+        let _ = writeln!(out, "            {:?},", stub.name);
+        let _ = writeln!(out, "            {:?},", stub.descriptor_str);
+        let _ = writeln!(out, "            |context, args| {{");
+        let mut args = vec!["context".to_owned()];
+        let offset = usize::from(!stub.is_static);
+        if !stub.is_static {
+            args.push("args[0].as_reference()?.expect(\"a checked object\")".to_owned());
+        }
+        for (index, parameter) in stub.descriptor.parameters().iter().enumerate() {
+            args.push(from_value(parameter, &format!("args[{}]", index + offset)));
+        }
+        let call = format!("Self::{}({})?", stub.function, args.join(", "));
+        match stub.descriptor.return_type() {
+            MethodType::Void => {
+                let _ = writeln!(out, "                {};", call);
+                let _ = writeln!(out, "                Ok(None)");
+            }
+            MethodType::Some(field_type) => {
+                let _ = writeln!(out, "                let value = {};", call);
+                let _ = writeln!(out, "                Ok(Some({}))", to_value(field_type));
+            }
+        }
+        let _ = writeln!(out, "            }},");
+        let _ = writeln!(out, "        );");
+    }
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "pub struct {};", type_name);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "#[allow(unused_variables)]");
+    let _ = writeln!(out, "impl {} for {} {{", trait_name, type_name);
+    for (index, stub) in methods.iter().enumerate() {
+        if index > 0 {
+            let _ = writeln!(out);
+        }
+        let _ = writeln!(out, "    {} {{", stub.signature());
+        let _ = writeln!(out, "        todo!()");
+        let _ = writeln!(out, "    }}");
+    }
+    let _ = writeln!(out, "}}");
+    Ok(out)
+}
+
+struct Stub<'a> {
+    name: &'a str,
+    descriptor_str: &'a str,
+    descriptor: MethodDescriptor,
+    /// The name of the Rust function
+    function: String,
+    is_static: bool,
+    parameters: Vec<String>,
+}
+
+impl Stub<'_> {
+    /// The signature of the function in the trait
+    fn signature(&self) -> String {
+        let mut parameters = vec!["context: &mut NativeContext<'_>".to_owned()];
+        if !self.is_static {
+            parameters.push("this: ObjectRef".to_owned());
+        }
+        for (name, field_type) in self.parameters.iter().zip(self.descriptor.parameters()) {
+            parameters.push(format!("{}: {}", name, rust_type(field_type)));
+        }
+        let return_type = match self.descriptor.return_type() {
+            MethodType::Some(field_type) => rust_type(field_type),
+            MethodType::Void => "()",
+        };
+        format!(
+            "fn {}({}) -> Result<{}, VmError>",
+            self.function,
+            parameters.join(", "),
+            return_type
+        )
+    }
+}
+
+/// The names from the `MethodParameters` attribute, or `arg0`, `arg1`...
+fn parameter_names(
+    class: &ClassFile,
+    method: &MethodInfo,
+    descriptor: &MethodDescriptor,
+) -> Vec<String> {
+    let cp = &class.constant_pool;
+    let names = method.attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::MethodParameters { parameters } => Some(parameters),
+        _ => None,
+    });
+    (0..descriptor.arity())
+        .map(|index| {
+            names
+                .and_then(|names| names.get(index))
+                .and_then(|parameter| parameter.name_index.maybe_get(cp))
+                .map(|name| rust_identifier(name, true))
+                .filter(|name| name != "context" && name != "this" && name != "args")
+                .unwrap_or_else(|| format!("arg{}", index))
+        })
+        .collect()
+}
+
+fn rust_type(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::Boolean => "bool",
+        FieldType::Byte => "i8",
+        FieldType::Char => "u16",
+        FieldType::Short => "i16",
+        FieldType::Int => "i32",
+        FieldType::Long => "i64",
+        FieldType::Float => "f32",
+        FieldType::Double => "f64",
+        FieldType::Object(_) | FieldType::Array(_) => "Option<ObjectRef>",
+    }
+}
+
+/// The expression that converts the `Value` to the Rust type
+fn from_value(field_type: &FieldType, value: &str) -> String {
+    match field_type {
+        FieldType::Boolean => format!("{}.as_int()? != 0", value),
+        FieldType::Byte => format!("{}.as_int()? as i8", value),
+        FieldType::Char => format!("{}.as_int()? as u16", value),
+        FieldType::Short => format!("{}.as_int()? as i16", value),
+        FieldType::Int => format!("{}.as_int()?", value),
+        FieldType::Long => format!("{}.as_long()?", value),
+        FieldType::Float => format!("{}.as_float()?", value),
+        FieldType::Double => format!("{}.as_double()?", value),
+        FieldType::Object(_) | FieldType::Array(_) => format!("{}.as_reference()?", value),
+    }
+}
+
+/// The expression that converts the returned `value` to a `Value`
+fn to_value(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::Boolean
+        | FieldType::Byte
+        | FieldType::Char
+        | FieldType::Short
+        | FieldType::Int => "Value::Int(value.into())",
+        FieldType::Long => "Value::Long(value)",
+        FieldType::Float => "Value::Float(value)",
+        FieldType::Double => "Value::Double(value)",
+        FieldType::Object(_) | FieldType::Array(_) => "Value::Reference(value)",
+    }
+}
+
+/// A Rust identifier for a Java name, in snake case for functions and parameters, like
+/// `is_alive` for `isAlive`. Keywords are raw identifiers, the ones that can't be get a `_`
+fn rust_identifier(name: &str, snake_case: bool) -> String {
+    let mut identifier = String::new();
+    let mut previous_lowercase = false;
+    for char in name.chars() {
+        let char = if char.is_alphanumeric() || char == '_' {
+            char
+        } else {
+            '_'
+        };
+        if snake_case && char.is_uppercase() {
+            if previous_lowercase {
+                identifier.push('_');
+            }
+            identifier.extend(char.to_lowercase());
+        } else {
+            identifier.push(char);
+        }
+        previous_lowercase = char.is_lowercase() || char.is_numeric();
+    }
+    if identifier.starts_with(|char: char| char.is_numeric()) || identifier.is_empty() {
+        identifier.insert(0, '_');
+    }
+    if matches!(identifier.as_str(), "crate" | "self" | "Self" | "super") {
+        identifier.push('_');
+    }
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try",
+        "type", "unsafe", "use", "where", "while", "yield",
+    ];
+    if KEYWORDS.contains(&identifier.as_str()) {
+        identifier.insert_str(0, "r#");
+    }
+    identifier
+}
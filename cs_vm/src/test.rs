@@ -0,0 +1,2579 @@
+use super::*;
+use cs_archive::ClassPath;
+use cs_parser::{
+    encode_code, Assembler, AttributeCodeException, AttributeInfo, AttributeInfoInner,
+    ClassAccessFlag, ClassFile, ClassFileBuilder, ConstantPoolBuilder, FieldAccessFlag,
+    Instruction, MethodAccessFlag, MethodCode,
+};
+use error::{LinkageError, VmError};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Adds a method with the code and room for 8 values on the stack and 8 local variables
+fn add_code(
+    builder: ClassFileBuilder,
+    access_flags: MethodAccessFlag,
+    name: &str,
+    descriptor: &str,
+    code: &[Instruction],
+) -> ClassFileBuilder {
+    let code = MethodCode {
+        max_stack: 8,
+        max_locals: 8,
+        code: encode_code(code).unwrap(),
+        exception_table: Vec::new(),
+    };
+    builder.add_method(access_flags, name, descriptor, Some(code))
+}
+
+fn add_static(
+    builder: ClassFileBuilder,
+    name: &str,
+    descriptor: &str,
+    code: &[Instruction],
+) -> ClassFileBuilder {
+    add_code(builder, MethodAccessFlag::STATIC, name, descriptor, code)
+}
+
+/// A class `Test` with a single static method `run` with the code
+fn single_method(descriptor: &str, code: &[Instruction]) -> Arc<ClassFile> {
+    Arc::new(add_static(ClassFileBuilder::new("Test"), "run", descriptor, code).build())
+}
+
+#[test]
+fn return_argument() {
+    let class = single_method("(I)I", &[Instruction::Iload0, Instruction::Ireturn]);
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+        interpreter.run(class, "run", "(I)I", &[Value::Int(42)]),
+        Ok(Some(Value::Int(42)))
+    );
+    assert!(interpreter.frames().is_empty());
+}
+
+#[test]
+fn nested_calls() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let second = pool.method_ref("Test", "second", "(I)I").inner();
+    let nothing = pool.method_ref("Test", "nothing", "()V").inner();
+    let builder = add_static(
+        builder,
+        "run",
+        "(II)I",
+        &[
+            Instruction::Invokestatic(nothing),
+            Instruction::Iload0,
+            Instruction::Iload1,
+            Instruction::Invokestatic(second),
+            Instruction::Ireturn,
+        ],
+    );
+    // returns the second argument, the first one stays on the stack of the caller
+    let builder = add_static(
+        builder,
+        "second",
+        "(I)I",
+        &[Instruction::Iload0, Instruction::Ireturn],
+    );
+    let builder = add_static(builder, "nothing", "()V", &[Instruction::Return]);
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(class, "run", "(II)I", &[Value::Int(1), Value::Int(2)]),
+        Ok(Some(Value::Int(2)))
+    );
+    assert!(interpreter.frames().is_empty());
+}
+
+#[test]
+fn stack_overflow() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let depth = pool.method_ref("Test", "depth", "(I)I").inner();
+    let stack_overflow = pool.class("java/lang/StackOverflowError").inner();
+    // `n == 0 ? 0 : depth(n - 1) + 1`, which uses n + 1 frames
+    let builder = add_static(
+        builder,
+        "depth",
+        "(I)I",
+        &[
+            Instruction::Iload0,
+            Instruction::Ifne(5),
+            Instruction::Iconst0,
+            Instruction::Ireturn,
+            Instruction::Iload0,
+            Instruction::Iconst1,
+            Instruction::Isub,
+            Instruction::Invokestatic(depth),
+            Instruction::Iconst1,
+            Instruction::Iadd,
+            Instruction::Ireturn,
+        ],
+    );
+    // returns -1 instead of the `StackOverflowError`
+    let code = MethodCode {
+        max_stack: 1,
+        max_locals: 1,
+        code: encode_code(&[
+            Instruction::Iload0,
+            Instruction::Invokestatic(depth),
+            Instruction::Ireturn,
+            Instruction::Pop,
+            Instruction::IconstM1,
+            Instruction::Ireturn,
+        ])
+        .unwrap(),
+        exception_table: vec![AttributeCodeException {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc: 5,
+            catch_type: stack_overflow,
+        }],
+    };
+    let builder = builder.add_method(MethodAccessFlag::STATIC, "run", "(I)I", Some(code));
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    let mut run = |name, n| interpreter.run(Arc::clone(&class), name, "(I)I", &[Value::Int(n)]);
+    assert_eq!(run("depth", 1000), Ok(Some(Value::Int(1000))));
+    assert_eq!(
+        run("depth", DEFAULT_MAX_FRAMES as i32),
+        Err(VmError::throw("java/lang/StackOverflowError", ""))
+    );
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_max_frames(10);
+    let mut run = |name, n| interpreter.run(Arc::clone(&class), name, "(I)I", &[Value::Int(n)]);
+    assert_eq!(run("run", 8), Ok(Some(Value::Int(8))));
+    assert_eq!(run("run", 9), Ok(Some(Value::Int(-1))));
+    assert_eq!(run("depth", 9), Ok(Some(Value::Int(9))));
+    assert_eq!(
+        run("depth", 10),
+        Err(VmError::throw("java/lang/StackOverflowError", ""))
+    );
+    assert!(interpreter.frames().is_empty());
+}
+
+#[test]
+fn run_errors() {
+    let builder = ClassFileBuilder::new("Test").add_method(
+        MethodAccessFlag::ABSTRACT,
+        "abstract",
+        "()V",
+        None,
+    );
+    let class = Arc::new(add_static(builder, "run", "()V", &[Instruction::Instanceof(1)]).build());
+    let mut interpreter = Interpreter::new();
+
+    assert!(matches!(
+        interpreter.run(Arc::clone(&class), "missing", "()V", &[]),
+        Err(VmError::NoSuchMethod(_))
+    ));
+    assert!(matches!(
+        interpreter.run(Arc::clone(&class), "abstract", "()V", &[]),
+        Err(VmError::NoSuchMethod(_))
+    ));
+    assert_eq!(
+        interpreter.run(class, "run", "()V", &[]),
+        Err(VmError::Unsupported("instanceof in Test.run()V".to_owned()))
+    );
+    assert!(interpreter.frames().is_empty());
+}
+
+/// Runs the code as a static `(II)I` method, with `max_stack` and `max_locals` of 8
+fn run_int(code: &[Instruction], a: i32, b: i32) -> Result<i32, VmError> {
+    let class = single_method("(II)I", code);
+    let result = Interpreter::new().run(class, "run", "(II)I", &[Value::Int(a), Value::Int(b)])?;
+    Ok(result.expect("a returned int").as_int().unwrap())
+}
+
+/// The instructions of the assembled code
+fn assembled(asm: &Assembler) -> Vec<Instruction> {
+    cs_parser::decode_code(&asm.assemble().unwrap().code)
+        .unwrap()
+        .into_iter()
+        .map(|(_, instruction)| instruction)
+        .collect()
+}
+
+/// Code that stores the `n` ints on the stack and returns them as the digits of a number,
+/// the bottom one first
+fn stack_digits(n: u16) -> Vec<Instruction> {
+    let mut code = (0..n).rev().map(Instruction::Istore).collect::<Vec<_>>();
+    code.push(Instruction::Iload(0));
+    for i in 1..n {
+        code.extend([
+            Instruction::Bipush(10),
+            Instruction::Imul,
+            Instruction::Iload(i),
+            Instruction::Iadd,
+        ]);
+    }
+    code.push(Instruction::Ireturn);
+    code
+}
+
+#[test]
+fn int_constants() {
+    for (instruction, value) in [
+        (Instruction::IconstM1, -1),
+        (Instruction::Iconst0, 0),
+        (Instruction::Iconst1, 1),
+        (Instruction::Iconst2, 2),
+        (Instruction::Iconst3, 3),
+        (Instruction::Iconst4, 4),
+        (Instruction::Iconst5, 5),
+        (Instruction::Bipush(-128), -128),
+        (Instruction::Sipush(1000), 1000),
+    ] {
+        let code = [Instruction::Nop, instruction.clone(), Instruction::Ireturn];
+        assert_eq!(run_int(&code, 0, 0), Ok(value), "{:?}", instruction);
+    }
+}
+
+#[test]
+fn int_arithmetic() {
+    for (instruction, a, b, result) in [
+        (Instruction::Iadd, 3, 4, 7),
+        (Instruction::Iadd, i32::MAX, 1, i32::MIN),
+        (Instruction::Isub, 3, 4, -1),
+        (Instruction::Imul, -3, 4, -12),
+        (Instruction::Imul, 0x10000, 0x10000, 0),
+        (Instruction::Idiv, 7, 2, 3),
+        (Instruction::Idiv, -7, 2, -3),
+        (Instruction::Idiv, i32::MIN, -1, i32::MIN),
+        (Instruction::Irem, 7, 2, 1),
+        (Instruction::Irem, -7, 2, -1),
+        (Instruction::Irem, i32::MIN, -1, 0),
+        (Instruction::Ishl, 1, 4, 16),
+        (Instruction::Ishl, 1, 33, 2),
+        (Instruction::Ishr, -8, 1, -4),
+        (Instruction::Iushr, -1, 28, 15),
+        (Instruction::Iand, 0b1100, 0b1010, 0b1000),
+        (Instruction::Ior, 0b1100, 0b1010, 0b1110),
+        (Instruction::Ixor, 0b1100, 0b1010, 0b0110),
+    ] {
+        let code = [
+            Instruction::Iload0,
+            Instruction::Iload1,
+            instruction.clone(),
+            Instruction::Ireturn,
+        ];
+        assert_eq!(
+            run_int(&code, a, b),
+            Ok(result),
+            "{:?} {} {}",
+            instruction,
+            a,
+            b
+        );
+    }
+
+    for instruction in [Instruction::Idiv, Instruction::Irem] {
+        let code = [
+            Instruction::Iload0,
+            Instruction::Iload1,
+            instruction,
+            Instruction::Ireturn,
+        ];
+        assert_eq!(
+            run_int(&code, 1, 0),
+            Err(VmError::throw("java/lang/ArithmeticException", "/ by zero"))
+        );
+    }
+}
+
+#[test]
+fn int_unary() {
+    for (instruction, a, result) in [
+        (Instruction::Ineg, 5, -5),
+        (Instruction::Ineg, i32::MIN, i32::MIN),
+        (Instruction::I2b, 0x1ff, -1),
+        (Instruction::I2b, 0x17f, 127),
+        (Instruction::I2c, -1, 0xffff),
+        (Instruction::I2s, 0x18000, -0x8000),
+    ] {
+        let code = [
+            Instruction::Iload0,
+            instruction.clone(),
+            Instruction::Ireturn,
+        ];
+        assert_eq!(run_int(&code, a, 0), Ok(result), "{:?} {}", instruction, a);
+    }
+}
+
+#[test]
+fn int_locals() {
+    let code = [
+        Instruction::Iload0,
+        Instruction::Istore3,
+        Instruction::Iload1,
+        Instruction::Istore2,
+        Instruction::Iload2,
+        Instruction::Istore(5),
+        Instruction::Iinc {
+            index: 5,
+            value: 1000,
+        },
+        Instruction::Iinc {
+            index: 3,
+            value: -1,
+        },
+        Instruction::Iload3,
+        Instruction::Istore0,
+        Instruction::Iload(5),
+        Instruction::Istore1,
+        Instruction::Iload0,
+        Instruction::Iload1,
+        Instruction::Iadd,
+        Instruction::Ireturn,
+    ];
+    assert_eq!(run_int(&code, 10, 20), Ok(9 + 1020));
+}
+
+#[test]
+fn stack_manipulation() {
+    for (instruction, result) in [
+        (Instruction::Pop, 12),
+        (Instruction::Pop2, 1),
+        (Instruction::Dup, 1233),
+        (Instruction::DupX1, 1323),
+        (Instruction::DupX2, 3123),
+        (Instruction::Dup2, 12323),
+        (Instruction::Dup2X1, 23123),
+        (Instruction::Swap, 132),
+    ] {
+        let depth = match instruction {
+            Instruction::Pop => 2,
+            Instruction::Pop2 => 1,
+            Instruction::Dup | Instruction::DupX1 | Instruction::DupX2 => 4,
+            Instruction::Dup2 | Instruction::Dup2X1 => 5,
+            _ => 3,
+        };
+        let mut code = vec![
+            Instruction::Iconst1,
+            Instruction::Iconst2,
+            Instruction::Iconst3,
+            instruction.clone(),
+        ];
+        code.extend(stack_digits(depth));
+        assert_eq!(run_int(&code, 0, 0), Ok(result), "{:?}", instruction);
+    }
+
+    let mut code = vec![
+        Instruction::Iconst1,
+        Instruction::Iconst2,
+        Instruction::Iconst3,
+        Instruction::Iconst4,
+        Instruction::Dup2X2,
+    ];
+    code.extend(stack_digits(6));
+    assert_eq!(run_int(&code, 0, 0), Ok(341234));
+}
+
+#[test]
+fn int_branches() {
+    let if_taken = |branch: fn(i32) -> Instruction, compare: bool| {
+        let mut asm = Assembler::new();
+        let taken = asm.new_label();
+        asm.push(Instruction::Iload0);
+        if compare {
+            asm.push(Instruction::Iload1);
+        }
+        asm.branch(branch, taken);
+        asm.push(Instruction::Iconst0);
+        asm.push(Instruction::Ireturn);
+        asm.place(taken);
+        asm.push(Instruction::Iconst1);
+        asm.push(Instruction::Ireturn);
+        assembled(&asm)
+    };
+
+    for (branch, taken_for) in [
+        (
+            Instruction::Ifeq as fn(i32) -> Instruction,
+            [false, true, false],
+        ),
+        (Instruction::Ifne, [true, false, true]),
+        (Instruction::Iflt, [true, false, false]),
+        (Instruction::Ifge, [false, true, true]),
+        (Instruction::Ifgt, [false, false, true]),
+        (Instruction::Ifle, [true, true, false]),
+    ] {
+        let code = if_taken(branch, false);
+        for (n, taken) in [-1, 0, 1].into_iter().zip(taken_for) {
+            assert_eq!(
+                run_int(&code, n, 0),
+                Ok(taken.into()),
+                "{:?} {}",
+                code[1],
+                n
+            );
+        }
+    }
+
+    for (branch, taken_for) in [
+        (
+            Instruction::IfIcmpeq as fn(i32) -> Instruction,
+            [false, true, false],
+        ),
+        (Instruction::IfIcmpne, [true, false, true]),
+        (Instruction::IfIcmplt, [true, false, false]),
+        (Instruction::IfIcmpge, [false, true, true]),
+        (Instruction::IfIcmpgt, [false, false, true]),
+        (Instruction::IfIcmple, [true, true, false]),
+    ] {
+        let code = if_taken(branch, true);
+        for (a, taken) in [-1, 0, 1].into_iter().zip(taken_for) {
+            assert_eq!(
+                run_int(&code, a, 0),
+                Ok(taken.into()),
+                "{:?} {}",
+                code[2],
+                a
+            );
+        }
+    }
+}
+
+#[test]
+fn loop_with_goto() {
+    // the sum of 1..=n
+    let mut asm = Assembler::new();
+    let condition = asm.new_label();
+    let body = asm.new_label();
+    asm.push(Instruction::Iconst0);
+    asm.push(Instruction::Istore1);
+    asm.branch(Instruction::Goto, condition);
+    asm.place(body);
+    asm.push(Instruction::Iload1);
+    asm.push(Instruction::Iload0);
+    asm.push(Instruction::Iadd);
+    asm.push(Instruction::Istore1);
+    asm.push(Instruction::Iinc {
+        index: 0,
+        value: -1,
+    });
+    asm.place(condition);
+    asm.push(Instruction::Iload0);
+    asm.branch(Instruction::Ifgt, body);
+    asm.push(Instruction::Iload1);
+    asm.push(Instruction::Ireturn);
+
+    assert_eq!(run_int(&assembled(&asm), 100, 0), Ok(5050));
+    assert_eq!(run_int(&assembled(&asm), 0, 0), Ok(0));
+}
+
+#[test]
+fn switches() {
+    let switch = |table: bool| {
+        let mut asm = Assembler::new();
+        let default = asm.new_label();
+        let targets = [asm.new_label(), asm.new_label(), asm.new_label()];
+        asm.push(Instruction::Iload0);
+        if table {
+            asm.tableswitch(5, default, targets.to_vec());
+        } else {
+            asm.lookupswitch(
+                default,
+                vec![(-10, targets[0]), (5, targets[1]), (100, targets[2])],
+            );
+        }
+        for (target, value) in targets.into_iter().zip([10, 20, 30]) {
+            asm.place(target);
+            asm.push(Instruction::Bipush(value));
+            asm.push(Instruction::Ireturn);
+        }
+        asm.place(default);
+        asm.push(Instruction::IconstM1);
+        asm.push(Instruction::Ireturn);
+        assembled(&asm)
+    };
+
+    let table = switch(true);
+    for (n, result) in [(4, -1), (5, 10), (6, 20), (7, 30), (8, -1)] {
+        assert_eq!(run_int(&table, n, 0), Ok(result), "tableswitch {}", n);
+    }
+    let lookup = switch(false);
+    for (n, result) in [(-10, 10), (5, 20), (100, 30), (0, -1), (101, -1)] {
+        assert_eq!(run_int(&lookup, n, 0), Ok(result), "lookupswitch {}", n);
+    }
+}
+
+/// Runs the code as a static method with the descriptor and the arguments
+fn run_static(
+    descriptor: &str,
+    code: &[Instruction],
+    args: &[Value],
+) -> Result<Option<Value>, VmError> {
+    let class = single_method(descriptor, code);
+    Interpreter::new().run(class, "run", descriptor, args)
+}
+
+#[test]
+fn wide_constants() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let int = pool.integer(100_000).inner() as u8;
+    let float = pool.float(1.5).inner();
+    let long = pool.long(1 << 40).inner();
+    let double = pool.double(-0.25).inner();
+
+    for (instruction, descriptor, return_, result) in [
+        (
+            Instruction::Lconst1,
+            "()J",
+            Instruction::Lreturn,
+            Value::Long(1),
+        ),
+        (
+            Instruction::Fconst2,
+            "()F",
+            Instruction::Freturn,
+            Value::Float(2.0),
+        ),
+        (
+            Instruction::Dconst1,
+            "()D",
+            Instruction::Dreturn,
+            Value::Double(1.0),
+        ),
+        (
+            Instruction::Ldc(int),
+            "()I",
+            Instruction::Ireturn,
+            Value::Int(100_000),
+        ),
+        (
+            Instruction::LdcW(float),
+            "()F",
+            Instruction::Freturn,
+            Value::Float(1.5),
+        ),
+        (
+            Instruction::Ldc2W(long),
+            "()J",
+            Instruction::Lreturn,
+            Value::Long(1 << 40),
+        ),
+        (
+            Instruction::Ldc2W(double),
+            "()D",
+            Instruction::Dreturn,
+            Value::Double(-0.25),
+        ),
+    ] {
+        let code = [instruction.clone(), return_];
+        let class = Arc::new(add_static(builder.clone(), "run", descriptor, &code).build());
+        assert_eq!(
+            Interpreter::new().run(class, "run", descriptor, &[]),
+            Ok(Some(result)),
+            "{:?}",
+            instruction
+        );
+    }
+
+    // longs and doubles can only be loaded with ldc2_w
+    let code = [Instruction::LdcW(long), Instruction::Lreturn];
+    let class = Arc::new(add_static(builder, "run", "()J", &code).build());
+    assert!(matches!(
+        Interpreter::new().run(class, "run", "()J", &[]),
+        Err(VmError::InvalidCode(_))
+    ));
+}
+
+#[test]
+fn long_arithmetic() {
+    for (instruction, a, b, result) in [
+        (Instruction::Ladd, i64::MAX, 1, i64::MIN),
+        (Instruction::Lsub, 3, 4, -1),
+        (Instruction::Lmul, 1 << 32, 1 << 31, i64::MIN),
+        (Instruction::Ldiv, -7, 2, -3),
+        (Instruction::Ldiv, i64::MIN, -1, i64::MIN),
+        (Instruction::Lrem, -7, 2, -1),
+        (Instruction::Land, 0b1100, 0b1010, 0b1000),
+        (Instruction::Lor, 0b1100, 0b1010, 0b1110),
+        (Instruction::Lxor, 0b1100, 0b1010, 0b0110),
+    ] {
+        let code = [
+            Instruction::Lload0,
+            Instruction::Lload2,
+            instruction.clone(),
+            Instruction::Lreturn,
+        ];
+        assert_eq!(
+            run_static("(JJ)J", &code, &[Value::Long(a), Value::Long(b)]),
+            Ok(Some(Value::Long(result))),
+            "{:?} {} {}",
+            instruction,
+            a,
+            b
+        );
+    }
+
+    for (instruction, a, distance, result) in [
+        (Instruction::Lshl, 1, 40, 1 << 40),
+        (Instruction::Lshl, 1, 65, 2),
+        (Instruction::Lshr, -8, 1, -4),
+        (Instruction::Lushr, -1, 60, 15),
+    ] {
+        let code = [
+            Instruction::Lload0,
+            Instruction::Iload2,
+            instruction.clone(),
+            Instruction::Lreturn,
+        ];
+        assert_eq!(
+            run_static("(JI)J", &code, &[Value::Long(a), Value::Int(distance)]),
+            Ok(Some(Value::Long(result))),
+            "{:?} {} {}",
+            instruction,
+            a,
+            distance
+        );
+    }
+
+    let code = [
+        Instruction::Lload0,
+        Instruction::Lconst0,
+        Instruction::Lrem,
+        Instruction::Lreturn,
+    ];
+    assert_eq!(
+        run_static("(J)J", &code, &[Value::Long(1)]),
+        Err(VmError::throw("java/lang/ArithmeticException", "/ by zero"))
+    );
+}
+
+#[test]
+fn floating_point_arithmetic() {
+    for (instruction, a, b, result) in [
+        (Instruction::Fadd, 0.5, 0.25, 0.75),
+        (Instruction::Fsub, 0.5, 0.25, 0.25),
+        (Instruction::Fmul, 1.5, -2.0, -3.0),
+        (Instruction::Fdiv, 1.0, 0.0, f32::INFINITY),
+        (Instruction::Frem, -5.5, 2.0, -1.5),
+    ] {
+        let code = [
+            Instruction::Fload0,
+            Instruction::Fload1,
+            instruction.clone(),
+            Instruction::Freturn,
+        ];
+        assert_eq!(
+            run_static("(FF)F", &code, &[Value::Float(a), Value::Float(b)]),
+            Ok(Some(Value::Float(result))),
+            "{:?} {} {}",
+            instruction,
+            a,
+            b
+        );
+    }
+
+    for (instruction, a, b, result) in [
+        (Instruction::Dadd, 0.5, 0.25, 0.75),
+        (Instruction::Dsub, 0.5, 0.25, 0.25),
+        (Instruction::Dmul, 1.5, -2.0, -3.0),
+        (Instruction::Ddiv, -1.0, 0.0, f64::NEG_INFINITY),
+        (Instruction::Drem, 5.5, -2.0, 1.5),
+    ] {
+        let code = [
+            Instruction::Dload0,
+            Instruction::Dload2,
+            instruction.clone(),
+            Instruction::Dreturn,
+        ];
+        assert_eq!(
+            run_static("(DD)D", &code, &[Value::Double(a), Value::Double(b)]),
+            Ok(Some(Value::Double(result))),
+            "{:?} {} {}",
+            instruction,
+            a,
+            b
+        );
+    }
+
+    let code = [Instruction::Dload0, Instruction::Dneg, Instruction::Dreturn];
+    assert_eq!(
+        run_static("(D)D", &code, &[Value::Double(0.0)]),
+        Ok(Some(Value::Double(-0.0)))
+    );
+    let code = [
+        Instruction::Fconst0,
+        Instruction::Fconst0,
+        Instruction::Fdiv,
+        Instruction::Freturn,
+    ];
+    let nan = run_static("()F", &code, &[]).unwrap().unwrap();
+    assert!(nan.as_float().unwrap().is_nan());
+}
+
+#[test]
+fn conversions() {
+    for (instruction, descriptor, arg, result) in [
+        (Instruction::I2l, "(I)J", Value::Int(-1), Value::Long(-1)),
+        (Instruction::I2f, "(I)F", Value::Int(3), Value::Float(3.0)),
+        (
+            Instruction::I2d,
+            "(I)D",
+            Value::Int(-3),
+            Value::Double(-3.0),
+        ),
+        (
+            Instruction::L2i,
+            "(J)I",
+            Value::Long(0x1_0000_0002),
+            Value::Int(2),
+        ),
+        (
+            Instruction::L2f,
+            "(J)F",
+            Value::Long(1 << 40),
+            Value::Float(1099511627776.0),
+        ),
+        (
+            Instruction::L2d,
+            "(J)D",
+            Value::Long(-5),
+            Value::Double(-5.0),
+        ),
+        (Instruction::F2i, "(F)I", Value::Float(-2.9), Value::Int(-2)),
+        (
+            Instruction::F2i,
+            "(F)I",
+            Value::Float(f32::NAN),
+            Value::Int(0),
+        ),
+        (
+            Instruction::F2i,
+            "(F)I",
+            Value::Float(1e20),
+            Value::Int(i32::MAX),
+        ),
+        (
+            Instruction::F2l,
+            "(F)J",
+            Value::Float(f32::NEG_INFINITY),
+            Value::Long(i64::MIN),
+        ),
+        (
+            Instruction::F2d,
+            "(F)D",
+            Value::Float(0.5),
+            Value::Double(0.5),
+        ),
+        (
+            Instruction::D2i,
+            "(D)I",
+            Value::Double(-1e100),
+            Value::Int(i32::MIN),
+        ),
+        (Instruction::D2l, "(D)J", Value::Double(2.5), Value::Long(2)),
+        (
+            Instruction::D2f,
+            "(D)F",
+            Value::Double(1e300),
+            Value::Float(f32::INFINITY),
+        ),
+    ] {
+        let return_ = match result {
+            Value::Int(_) => Instruction::Ireturn,
+            Value::Long(_) => Instruction::Lreturn,
+            Value::Float(_) => Instruction::Freturn,
+            _ => Instruction::Dreturn,
+        };
+        let load = match arg {
+            Value::Int(_) => Instruction::Iload0,
+            Value::Long(_) => Instruction::Lload0,
+            Value::Float(_) => Instruction::Fload0,
+            _ => Instruction::Dload0,
+        };
+        let code = [load, instruction.clone(), return_];
+        assert_eq!(
+            run_static(descriptor, &code, &[arg]),
+            Ok(Some(result)),
+            "{:?} {:?}",
+            instruction,
+            arg
+        );
+    }
+}
+
+#[test]
+fn comparisons() {
+    let compare = |a: Value, b: Value, instruction: Instruction| {
+        let (load_a, load_b, descriptor) = match a {
+            Value::Long(_) => (Instruction::Lload0, Instruction::Lload2, "(JJ)I"),
+            Value::Float(_) => (Instruction::Fload0, Instruction::Fload1, "(FF)I"),
+            _ => (Instruction::Dload0, Instruction::Dload2, "(DD)I"),
+        };
+        let code = [load_a, load_b, instruction, Instruction::Ireturn];
+        run_static(descriptor, &code, &[a, b]).unwrap().unwrap()
+    };
+
+    for (a, b, result) in [(1, 2, -1), (2, 2, 0), (i64::MAX, i64::MIN, 1)] {
+        assert_eq!(
+            compare(Value::Long(a), Value::Long(b), Instruction::Lcmp),
+            Value::Int(result)
+        );
+    }
+    for (a, b, l, g) in [
+        (1.0, 2.0, -1, -1),
+        (0.0, -0.0, 0, 0),
+        (2.0, 1.0, 1, 1),
+        (f64::NAN, 1.0, -1, 1),
+        (1.0, f64::NAN, -1, 1),
+    ] {
+        assert_eq!(
+            compare(Value::Double(a), Value::Double(b), Instruction::Dcmpl),
+            Value::Int(l)
+        );
+        assert_eq!(
+            compare(Value::Double(a), Value::Double(b), Instruction::Dcmpg),
+            Value::Int(g)
+        );
+        let (a, b) = (a as f32, b as f32);
+        assert_eq!(
+            compare(Value::Float(a), Value::Float(b), Instruction::Fcmpl),
+            Value::Int(l)
+        );
+        assert_eq!(
+            compare(Value::Float(a), Value::Float(b), Instruction::Fcmpg),
+            Value::Int(g)
+        );
+    }
+}
+
+#[test]
+fn wide_values_in_frames() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let add = builder
+        .constant_pool()
+        .method_ref("Test", "add", "(JIJ)J")
+        .inner();
+    // dup2 and pop2 move a long as a whole, the int argument sits between two longs
+    let builder = add_static(
+        builder,
+        "run",
+        "(J)J",
+        &[
+            Instruction::Lload0,
+            Instruction::Dup2,
+            Instruction::Dup2,
+            Instruction::Pop2,
+            Instruction::Lstore2,
+            Instruction::Iconst5,
+            Instruction::Lload2,
+            Instruction::Invokestatic(add),
+            Instruction::Lreturn,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "add",
+        "(JIJ)J",
+        &[
+            Instruction::Lload0,
+            Instruction::Iload2,
+            Instruction::I2l,
+            Instruction::Ladd,
+            Instruction::Lload3,
+            Instruction::Ladd,
+            Instruction::Lreturn,
+        ],
+    );
+    let class = Arc::new(builder.build());
+
+    assert_eq!(
+        Interpreter::new().run(class, "run", "(J)J", &[Value::Long(1 << 33)]),
+        Ok(Some(Value::Long((1 << 34) + 5)))
+    );
+}
+
+#[test]
+fn type_errors() {
+    for code in [
+        [Instruction::Iload0, Instruction::Ireturn],
+        [Instruction::Lload1, Instruction::Lreturn],
+        [Instruction::Lload0, Instruction::Ireturn],
+        [Instruction::Lload0, Instruction::Swap],
+    ] {
+        assert!(
+            matches!(
+                run_static("(J)V", &code, &[Value::Long(1)]),
+                Err(VmError::InvalidCode(_))
+            ),
+            "{:?}",
+            code
+        );
+    }
+
+    // the methods of the tests have room for 8 values on the stack
+    let mut code = vec![Instruction::Iconst0; 9];
+    code.push(Instruction::Ireturn);
+    assert_eq!(
+        run_static("()I", &code, &[]),
+        Err(VmError::InvalidCode(
+            "Operand stack overflow, it only has 8 slots".to_owned()
+        ))
+    );
+    assert_eq!(
+        run_static(
+            "()I",
+            &[Instruction::Pop, Instruction::Iconst0, Instruction::Ireturn],
+            &[]
+        ),
+        Err(VmError::InvalidCode("Operand stack underflow".to_owned()))
+    );
+}
+
+#[test]
+fn objects_and_fields() {
+    let point = ClassFileBuilder::new("Point")
+        .add_field(FieldAccessFlag::PUBLIC, "x", "I")
+        .add_field(FieldAccessFlag::PUBLIC, "y", "J")
+        .add_field(FieldAccessFlag::STATIC, "count", "I")
+        .build();
+    // hides `x` of `Point`
+    let mut builder = ClassFileBuilder::new("Point3")
+        .super_class("Point")
+        .add_field(FieldAccessFlag::PUBLIC, "x", "I")
+        .add_field(FieldAccessFlag::PUBLIC, "next", "LPoint3;");
+    let pool = builder.constant_pool();
+    let class = pool.class("Point3").inner();
+    let point_x = pool.field_ref("Point", "x", "I").inner();
+    let point_y = pool.field_ref("Point", "y", "J").inner();
+    let point3_x = pool.field_ref("Point3", "x", "I").inner();
+    let point3_y = pool.field_ref("Point3", "y", "J").inner();
+    let next = pool.field_ref("Point3", "next", "LPoint3;").inner();
+    let long = pool.long(300).inner();
+    let builder = add_static(
+        builder,
+        "run",
+        "()J",
+        &[
+            Instruction::New(class),
+            Instruction::Astore0,
+            Instruction::Aload0,
+            Instruction::Iconst1,
+            Instruction::Putfield(point_x),
+            Instruction::Aload0,
+            Instruction::Bipush(20),
+            Instruction::Putfield(point3_x),
+            Instruction::Aload0,
+            Instruction::Ldc2W(long),
+            Instruction::Putfield(point3_y),
+            // the new object is `next` of the first one
+            Instruction::Aload0,
+            Instruction::New(class),
+            Instruction::Putfield(next),
+            // 1 + 20 + 300 + the y of `next`, which is still 0
+            Instruction::Aload0,
+            Instruction::Getfield(point_x),
+            Instruction::Aload0,
+            Instruction::Getfield(point3_x),
+            Instruction::Iadd,
+            Instruction::I2l,
+            Instruction::Aload0,
+            Instruction::Getfield(point_y),
+            Instruction::Ladd,
+            Instruction::Aload0,
+            Instruction::Getfield(next),
+            Instruction::Getfield(point_y),
+            Instruction::Ladd,
+            Instruction::Lreturn,
+        ],
+    );
+
+    let mut interpreter = Interpreter::new();
+    interpreter.add_class(Arc::new(point)).unwrap();
+    assert_eq!(
+        interpreter.run(Arc::new(builder.build()), "run", "()J", &[]),
+        Ok(Some(Value::Long(321)))
+    );
+    let heap = interpreter.heap();
+    assert_eq!(heap.len(), 2);
+    assert_eq!(
+        heap.get(ObjectRef(1)).fields,
+        [Value::Int(0), Value::Long(0), Value::Int(0), Value::NULL]
+    );
+}
+
+#[test]
+fn null_references() {
+    let mut builder = ClassFileBuilder::new("Test").add_field(FieldAccessFlag::PUBLIC, "x", "I");
+    let pool = builder.constant_pool();
+    let class = pool.class("Test").inner();
+    let x = pool.field_ref("Test", "x", "I").inner();
+
+    // returns 1 for null, 2 for the same object, 3 for a different one
+    let mut asm = Assembler::new();
+    let null = asm.new_label();
+    let same = asm.new_label();
+    asm.push(Instruction::Aload0);
+    asm.branch(Instruction::Ifnull, null);
+    asm.push(Instruction::Aload0);
+    asm.push(Instruction::Aload1);
+    asm.branch(Instruction::IfAcmpeq, same);
+    asm.push(Instruction::Iconst3);
+    asm.push(Instruction::Ireturn);
+    asm.place(null);
+    asm.push(Instruction::Iconst1);
+    asm.push(Instruction::Ireturn);
+    asm.place(same);
+    asm.push(Instruction::Iconst2);
+    asm.push(Instruction::Ireturn);
+    let builder = add_static(builder, "compare", "(LTest;LTest;)I", &assembled(&asm));
+    let builder = add_static(
+        builder,
+        "run",
+        "()I",
+        &[
+            Instruction::AconstNull,
+            Instruction::Getfield(x),
+            Instruction::Ireturn,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "create",
+        "()LTest;",
+        &[Instruction::New(class), Instruction::Areturn],
+    );
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    let a = interpreter
+        .run(Arc::clone(&class), "create", "()LTest;", &[])
+        .unwrap()
+        .unwrap();
+    let b = interpreter
+        .run(Arc::clone(&class), "create", "()LTest;", &[])
+        .unwrap()
+        .unwrap();
+    assert_ne!(a, b);
+    for (args, result) in [
+        ([Value::NULL, a], 1),
+        ([a, a], 2),
+        ([a, b], 3),
+        ([b, Value::NULL], 3),
+    ] {
+        assert_eq!(
+            interpreter.run(Arc::clone(&class), "compare", "(LTest;LTest;)I", &args),
+            Ok(Some(Value::Int(result))),
+            "{:?}",
+            args
+        );
+    }
+    assert!(matches!(
+        interpreter.run(class, "run", "()I", &[]),
+        Err(VmError::Throw { class, .. }) if class == "java/lang/NullPointerException"
+    ));
+}
+
+#[test]
+fn object_errors() {
+    let abstract_class = ClassFileBuilder::new("Abstract")
+        .access_flags(ClassAccessFlag::Abstract)
+        .build();
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let abstract_ = pool.class("Abstract").inner();
+    let missing = pool.class("Missing").inner();
+    let test = pool.class("Test").inner();
+    let field = pool.field_ref("Test", "missing", "I").inner();
+    let builder = add_static(builder, "abstract", "()V", &[Instruction::New(abstract_)]);
+    let builder = add_static(builder, "missing", "()V", &[Instruction::New(missing)]);
+    let builder = add_static(
+        builder,
+        "field",
+        "()V",
+        &[Instruction::New(test), Instruction::Getfield(field)],
+    );
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    interpreter.add_class(Arc::new(abstract_class)).unwrap();
+    for (method, error) in [
+        (
+            "abstract",
+            VmError::throw("java/lang/InstantiationError", "Abstract"),
+        ),
+        (
+            "missing",
+            VmError::throw("java/lang/NoClassDefFoundError", "Missing"),
+        ),
+        (
+            "field",
+            VmError::throw("java/lang/NoSuchFieldError", "missing"),
+        ),
+    ] {
+        assert_eq!(
+            interpreter.run(Arc::clone(&class), method, "()V", &[]),
+            Err(error)
+        );
+    }
+}
+
+#[test]
+fn exception_handlers() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let arithmetic = pool.class("java/lang/ArithmeticException").inner();
+    let null_pointer = pool.class("java/lang/NullPointerException").inner();
+    let handler = |handler_pc, catch_type| AttributeCodeException {
+        start_pc: 0,
+        end_pc: 4,
+        handler_pc,
+        catch_type,
+    };
+    // 1 / 0, the handler at 4 returns 2 and the one at 7 returns 3
+    let divide = [
+        Instruction::Iconst1,
+        Instruction::Iconst0,
+        Instruction::Idiv,
+        Instruction::Ireturn,
+        Instruction::Pop,
+        Instruction::Iconst2,
+        Instruction::Ireturn,
+        Instruction::Pop,
+        Instruction::Iconst3,
+        Instruction::Ireturn,
+    ];
+    let throw_null = [
+        Instruction::AconstNull,
+        Instruction::Athrow,
+        Instruction::Pop,
+        Instruction::Iconst3,
+        Instruction::Ireturn,
+    ];
+    for (name, code, exception_table) in [
+        (
+            "caught",
+            &divide[..],
+            vec![handler(4, arithmetic), handler(7, 0)],
+        ),
+        (
+            "catchAll",
+            &divide[..],
+            vec![handler(4, null_pointer), handler(7, 0)],
+        ),
+        ("uncaught", &throw_null[..], vec![handler(2, arithmetic)]),
+    ] {
+        let code = MethodCode {
+            max_stack: 2,
+            max_locals: 0,
+            code: encode_code(code).unwrap(),
+            exception_table,
+        };
+        builder = builder.add_method(MethodAccessFlag::STATIC, name, "()I", Some(code));
+    }
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    for (method, result) in [
+        ("caught", Ok(Some(Value::Int(2)))),
+        ("catchAll", Ok(Some(Value::Int(3)))),
+        (
+            "uncaught",
+            Err(VmError::throw(
+                "java/lang/NullPointerException",
+                "Cannot throw exception because the value is null",
+            )),
+        ),
+    ] {
+        assert_eq!(
+            interpreter.run(Arc::clone(&class), method, "()I", &[]),
+            result,
+            "{}",
+            method
+        );
+        assert!(interpreter.frames().is_empty());
+    }
+}
+
+#[test]
+fn string_constants() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let hello = pool.string("hello").inner();
+    let other = pool.string("other").inner();
+    let builder = add_static(
+        builder,
+        "hello",
+        "()Ljava/lang/String;",
+        &[Instruction::LdcW(hello), Instruction::Areturn],
+    );
+    let builder = add_static(
+        builder,
+        "other",
+        "()Ljava/lang/String;",
+        &[Instruction::Ldc(other as u8), Instruction::Areturn],
+    );
+    let test = Arc::new(builder.build());
+    // the same constant in another class
+    let mut builder = ClassFileBuilder::new("Other");
+    let hello = builder.constant_pool().string("hello").inner();
+    let builder = add_static(
+        builder,
+        "hello",
+        "()Ljava/lang/String;",
+        &[Instruction::LdcW(hello), Instruction::Areturn],
+    );
+    let other_class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    let mut run = |class: &Arc<ClassFile>, name| {
+        interpreter
+            .run(Arc::clone(class), name, "()Ljava/lang/String;", &[])
+            .unwrap()
+            .unwrap()
+            .as_reference()
+            .unwrap()
+            .unwrap()
+    };
+    let a = run(&test, "hello");
+    let b = run(&test, "hello");
+    let c = run(&other_class, "hello");
+    let d = run(&test, "other");
+    assert_eq!(a, b);
+    assert_eq!(a, c);
+    assert_ne!(a, d);
+
+    let heap = interpreter.heap();
+    assert_eq!(heap.len(), 2);
+    assert_eq!(heap.string(a), Some("hello"));
+    assert_eq!(heap.string(d), Some("other"));
+    let cp = &heap.get(a).class.constant_pool;
+    assert_eq!(
+        heap.get(a).class.this_class.get(cp).name_index.get(cp),
+        "java/lang/String"
+    );
+}
+
+/// Builds the class, and gives the fields a `ConstantValue` attribute with the constant at the
+/// index
+fn build_with_constants(mut builder: ClassFileBuilder, constants: &[(usize, u16)]) -> ClassFile {
+    let name = builder.constant_pool().utf8("ConstantValue");
+    let mut class = builder.build();
+    for &(field, constant) in constants {
+        class.fields[field].attributes.push(AttributeInfo {
+            attribute_name_index: name,
+            attribute_length: 2,
+            inner: AttributeInfoInner::ConstantValue {
+                constantvalue_index: constant.into(),
+            },
+            original: None,
+        });
+    }
+    class
+}
+
+#[test]
+fn static_fields() {
+    let mut builder = ClassFileBuilder::new("Counter")
+        .add_field(FieldAccessFlag::STATIC, "count", "I")
+        .add_field(FieldAccessFlag::STATIC, "big", "J")
+        .add_field(FieldAccessFlag::STATIC, "name", "Ljava/lang/String;")
+        .add_field(FieldAccessFlag::PUBLIC, "instance", "I");
+    let pool = builder.constant_pool();
+    let count = pool.field_ref("Counter", "count", "I").inner();
+    let big = pool.field_ref("Counter", "big", "J").inner();
+    let instance = pool.field_ref("Counter", "instance", "I").inner();
+    let big_value = pool.long(1 << 40).inner();
+    let name_value = pool.string("counter").inner();
+    // uses the class while it is initialized
+    let builder = add_static(
+        builder,
+        "<clinit>",
+        "()V",
+        &[
+            Instruction::Getstatic(count),
+            Instruction::Bipush(10),
+            Instruction::Iadd,
+            Instruction::Putstatic(count),
+            Instruction::Return,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "increment",
+        "()I",
+        &[
+            Instruction::Getstatic(count),
+            Instruction::Iconst1,
+            Instruction::Iadd,
+            Instruction::Dup,
+            Instruction::Putstatic(count),
+            Instruction::Ireturn,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "big",
+        "()J",
+        &[Instruction::Getstatic(big), Instruction::Lreturn],
+    );
+    let builder = add_static(
+        builder,
+        "instance",
+        "()I",
+        &[Instruction::Getstatic(instance), Instruction::Ireturn],
+    );
+    let class = Arc::new(build_with_constants(
+        builder,
+        &[(1, big_value), (2, name_value)],
+    ));
+
+    let mut interpreter = Interpreter::new();
+    let mut run = |name, descriptor| interpreter.run(Arc::clone(&class), name, descriptor, &[]);
+    assert_eq!(run("increment", "()I"), Ok(Some(Value::Int(11))));
+    assert_eq!(run("increment", "()I"), Ok(Some(Value::Int(12))));
+    assert_eq!(run("big", "()J"), Ok(Some(Value::Long(1 << 40))));
+    assert_eq!(
+        run("instance", "()I"),
+        Err(VmError::throw(
+            "java/lang/IncompatibleClassChangeError",
+            "Expected static field Counter.instance"
+        ))
+    );
+
+    let statics = interpreter.statics("Counter").unwrap();
+    assert_eq!(statics.state, InitState::Initialized);
+    assert_eq!(statics.get("count", "I"), Some(Value::Int(12)));
+    let name = statics.get("name", "Ljava/lang/String;").unwrap();
+    let name = name.as_reference().unwrap().unwrap();
+    assert_eq!(interpreter.heap().string(name), Some("counter"));
+}
+
+/// A class with a `<clinit>` that appends the digit to `Log.value`
+fn logging_class(name: &str, super_class: &str, digit: i8) -> ClassFile {
+    let mut builder = ClassFileBuilder::new(name)
+        .super_class(super_class)
+        .add_field(FieldAccessFlag::STATIC, "field", "I");
+    let log = builder
+        .constant_pool()
+        .field_ref("Log", "value", "I")
+        .inner();
+    add_static(
+        builder,
+        "<clinit>",
+        "()V",
+        &[
+            Instruction::Getstatic(log),
+            Instruction::Bipush(10),
+            Instruction::Imul,
+            Instruction::Bipush(digit),
+            Instruction::Iadd,
+            Instruction::Putstatic(log),
+            Instruction::Return,
+        ],
+    )
+    .build()
+}
+
+#[test]
+fn class_initialization() {
+    let log = ClassFileBuilder::new("Log")
+        .add_field(FieldAccessFlag::STATIC, "value", "I")
+        .build();
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let sub = pool.class("Sub").inner();
+    let log_value = pool.field_ref("Log", "value", "I").inner();
+    let super_field = pool.field_ref("Middle", "field", "I").inner();
+    let builder = add_static(
+        builder,
+        "create",
+        "()I",
+        &[
+            Instruction::New(sub),
+            Instruction::New(sub),
+            Instruction::Pop2,
+            Instruction::Getstatic(log_value),
+            Instruction::Ireturn,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "inherited",
+        "()I",
+        &[
+            Instruction::Getstatic(super_field),
+            Instruction::Getstatic(log_value),
+            Instruction::Ireturn,
+        ],
+    );
+    let class = Arc::new(builder.build());
+
+    let classes = [
+        Arc::new(log),
+        Arc::new(logging_class("Super", "java/lang/Object", 1)),
+        // declares no static fields, so `Middle.field` is the one of `Super`
+        Arc::new(ClassFileBuilder::new("Middle").super_class("Super").build()),
+        Arc::new(logging_class("Sub", "Middle", 2)),
+    ];
+    let interpreter = || {
+        let mut interpreter = Interpreter::new();
+        for class in &classes {
+            interpreter.add_class(Arc::clone(class)).unwrap();
+        }
+        interpreter
+    };
+
+    // the superclass is initialized first, and every class only once
+    let mut first = interpreter();
+    assert_eq!(
+        first.run(Arc::clone(&class), "create", "()I", &[]),
+        Ok(Some(Value::Int(12)))
+    );
+    assert_eq!(
+        first.statics("Middle").unwrap().state,
+        InitState::Initialized
+    );
+
+    // only the class that declares the field is initialized
+    let mut second = interpreter();
+    assert_eq!(
+        second.run(Arc::clone(&class), "inherited", "()I", &[]),
+        Ok(Some(Value::Int(1)))
+    );
+    assert!(second.statics("Middle").is_none());
+    assert!(second.statics("Sub").is_none());
+}
+
+#[test]
+fn failed_initialization() {
+    let mut builder = ClassFileBuilder::new("Test").add_field(FieldAccessFlag::STATIC, "x", "I");
+    let x = builder.constant_pool().field_ref("Test", "x", "I").inner();
+    let builder = add_static(
+        builder,
+        "<clinit>",
+        "()V",
+        &[
+            Instruction::Iconst1,
+            Instruction::Iconst0,
+            Instruction::Idiv,
+            Instruction::Putstatic(x),
+            Instruction::Return,
+        ],
+    );
+    let builder = add_static(builder, "run", "()V", &[Instruction::Return]);
+    let class = Arc::new(builder.build());
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(Arc::clone(&class), "run", "()V", &[]),
+        Err(VmError::throw("java/lang/ArithmeticException", "/ by zero"))
+    );
+    assert!(interpreter.frames().is_empty());
+    assert_eq!(
+        interpreter.statics("Test").unwrap().state,
+        InitState::Erroneous
+    );
+    assert_eq!(
+        interpreter.run(class, "run", "()V", &[]),
+        Err(VmError::throw(
+            "java/lang/NoClassDefFoundError",
+            "Could not initialize class Test"
+        ))
+    );
+}
+
+/// A class with a default constructor and a public method `()I` for each of the names, which
+/// returns the number
+fn returning_class(name: &str, super_class: &str, methods: &[(&str, i8)]) -> ClassFileBuilder {
+    let mut builder = ClassFileBuilder::new(name)
+        .super_class(super_class)
+        .add_default_constructor();
+    for &(method, n) in methods {
+        let code = [Instruction::Bipush(n), Instruction::Ireturn];
+        builder = add_code(builder, MethodAccessFlag::PUBLIC, method, "()I", &code);
+    }
+    builder
+}
+
+/// Runs `static run()I` with the code on a class with all the classes added, after the
+/// instructions that create an instance of `object` on the stack
+fn run_on_object(
+    classes: &[ClassFileBuilder],
+    object: &str,
+    code: impl FnOnce(&mut cs_parser::ConstantPoolBuilder) -> Vec<Instruction>,
+) -> Result<Option<Value>, VmError> {
+    let mut builder = ClassFileBuilder::new("Test");
+    let pool = builder.constant_pool();
+    let class = pool.class(object).inner();
+    let constructor = pool.method_ref(object, "<init>", "()V").inner();
+    let mut instructions = vec![
+        Instruction::New(class),
+        Instruction::Dup,
+        Instruction::Invokespecial(constructor),
+    ];
+    instructions.extend(code(pool));
+    let test = Arc::new(add_static(builder, "run", "()I", &instructions).build());
+
+    let mut interpreter = Interpreter::new();
+    for class in classes {
+        interpreter
+            .add_class(Arc::new(class.clone().build()))
+            .unwrap();
+    }
+    interpreter.run(test, "run", "()I", &[])
+}
+
+#[test]
+fn virtual_dispatch() {
+    let named = ClassFileBuilder::new("Named").access_flags(
+        ClassAccessFlag::Public | ClassAccessFlag::Interface | ClassAccessFlag::Abstract,
+    );
+    let named = add_code(
+        named,
+        MethodAccessFlag::PUBLIC,
+        "name",
+        "()I",
+        &[Instruction::Iconst5, Instruction::Ireturn],
+    )
+    .add_method(
+        MethodAccessFlag::PUBLIC | MethodAccessFlag::ABSTRACT,
+        "missing",
+        "()I",
+        None,
+    );
+    let mut animal = returning_class("Animal", "java/lang/Object", &[("sound", 1), ("secret", 7)])
+        .add_interface("Named");
+    let sound = animal
+        .constant_pool()
+        .method_ref("Animal", "sound", "()I")
+        .inner();
+    // the private method isn't overridden by the one of `Dog`
+    let animal = add_code(
+        animal,
+        MethodAccessFlag::PUBLIC,
+        "describe",
+        "()I",
+        &[
+            Instruction::Aload0,
+            Instruction::Invokevirtual(sound),
+            Instruction::Ireturn,
+        ],
+    );
+    let mut dog = returning_class("Dog", "Animal", &[("sound", 2)]);
+    let pool = dog.constant_pool();
+    let animal_sound = pool.method_ref("Animal", "sound", "()I").inner();
+    let dog_secret = pool.method_ref("Dog", "secret", "()I").inner();
+    let dog = add_code(
+        dog,
+        MethodAccessFlag::PUBLIC,
+        "parentSound",
+        "()I",
+        &[
+            Instruction::Aload0,
+            Instruction::Invokespecial(animal_sound),
+            Instruction::Ireturn,
+        ],
+    );
+    let dog = add_code(
+        dog,
+        MethodAccessFlag::PRIVATE,
+        "secret",
+        "()I",
+        &[Instruction::Bipush(8), Instruction::Ireturn],
+    );
+    let dog = add_code(
+        dog,
+        MethodAccessFlag::PUBLIC,
+        "dogSecret",
+        "()I",
+        &[
+            Instruction::Aload0,
+            Instruction::Invokevirtual(dog_secret),
+            Instruction::Ireturn,
+        ],
+    );
+    let puppy = returning_class("Puppy", "Dog", &[]);
+    let classes = [named, animal, dog, puppy];
+
+    let call = |object, class, name| {
+        run_on_object(&classes, object, |pool| {
+            let method = pool.method_ref(class, name, "()I").inner();
+            vec![Instruction::Invokevirtual(method), Instruction::Ireturn]
+        })
+    };
+    for (object, class, name, result) in [
+        ("Animal", "Animal", "describe", 1),
+        ("Puppy", "Animal", "describe", 2),
+        ("Puppy", "Puppy", "sound", 2),
+        // `super.sound()` in `Dog`
+        ("Puppy", "Dog", "parentSound", 1),
+        ("Dog", "Animal", "secret", 7),
+        ("Puppy", "Dog", "dogSecret", 8),
+        // the default method of the interface
+        ("Puppy", "Named", "name", 5),
+        ("Puppy", "Puppy", "name", 5),
+    ] {
+        assert_eq!(
+            call(object, class, name),
+            Ok(Some(Value::Int(result))),
+            "{}.{} on {}",
+            class,
+            name,
+            object
+        );
+    }
+
+    assert_eq!(
+        call("Puppy", "Named", "missing"),
+        Err(VmError::throw(
+            "java/lang/AbstractMethodError",
+            "Puppy.missing()I"
+        ))
+    );
+    assert_eq!(
+        call("Puppy", "Puppy", "unknown"),
+        Err(VmError::throw(
+            "java/lang/NoSuchMethodError",
+            "Puppy.unknown()I"
+        ))
+    );
+}
+
+#[test]
+fn invocation_errors() {
+    let mut builder = returning_class("Util", "java/lang/Object", &[("instance", 1)]);
+    let counter = builder
+        .constant_pool()
+        .field_ref("Util", "counter", "I")
+        .inner();
+    let builder = add_static(
+        builder.add_field(FieldAccessFlag::STATIC, "counter", "I"),
+        "<clinit>",
+        "()V",
+        &[
+            Instruction::Bipush(40),
+            Instruction::Putstatic(counter),
+            Instruction::Return,
+        ],
+    );
+    let builder = add_static(
+        builder,
+        "add",
+        "(IJ)I",
+        &[
+            Instruction::Getstatic(counter),
+            Instruction::Iload0,
+            Instruction::Iadd,
+            Instruction::Lload1,
+            Instruction::L2i,
+            Instruction::Iadd,
+            Instruction::Ireturn,
+        ],
+    );
+    let classes = [builder];
+
+    // the class of a static method is initialized before it is called
+    let result = run_on_object(&classes, "Util", |pool| {
+        let add = pool.method_ref("Util", "add", "(IJ)I").inner();
+        vec![
+            Instruction::Pop,
+            Instruction::Iconst1,
+            Instruction::Lconst1,
+            Instruction::Invokestatic(add),
+            Instruction::Ireturn,
+        ]
+    });
+    assert_eq!(result, Ok(Some(Value::Int(42))));
+
+    let result = run_on_object(&classes, "Util", |pool| {
+        let instance = pool.method_ref("Util", "instance", "()I").inner();
+        vec![Instruction::Invokestatic(instance), Instruction::Ireturn]
+    });
+    assert_eq!(
+        result,
+        Err(VmError::throw(
+            "java/lang/IncompatibleClassChangeError",
+            "Expected static method Util.instance()I"
+        ))
+    );
+    let result = run_on_object(&classes, "Util", |pool| {
+        let add = pool.method_ref("Util", "add", "(IJ)I").inner();
+        vec![Instruction::Invokevirtual(add), Instruction::Ireturn]
+    });
+    assert_eq!(
+        result,
+        Err(VmError::throw(
+            "java/lang/IncompatibleClassChangeError",
+            "Expected non-static method Util.add(IJ)I"
+        ))
+    );
+    let result = run_on_object(&classes, "Util", |pool| {
+        let instance = pool.method_ref("Util", "instance", "()I").inner();
+        vec![
+            Instruction::AconstNull,
+            Instruction::Invokevirtual(instance),
+            Instruction::Ireturn,
+        ]
+    });
+    assert_eq!(
+        result,
+        Err(VmError::throw(
+            "java/lang/NullPointerException",
+            "Cannot invoke \"Util.instance()I\" because the object is null"
+        ))
+    );
+}
+
+/// A `System.out` whose output can be read after the interpreter wrote to it
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[test]
+fn hello_world() {
+    let class = cs_parser::parse_class_file(include_bytes!("../testdata/Hello.class")).unwrap();
+    let mut interpreter = Interpreter::new();
+    let output = Output::default();
+    interpreter.set_stdout(output.clone());
+    let result = interpreter.run(
+        Arc::new(class),
+        "main",
+        "([Ljava/lang/String;)V",
+        &[Value::NULL],
+    );
+    assert_eq!(result, Ok(None));
+    assert_eq!(
+        output.text(),
+        "Hello, World!\n55\n1.5\ntrue\nc\n10000000000\n"
+    );
+}
+
+/// Prints the values with `System.out.print` and the descriptor, and runs the code that
+/// pushes each of them
+fn printed(
+    descriptor: &str,
+    values: impl Fn(&mut ConstantPoolBuilder) -> Vec<Instruction>,
+) -> String {
+    let mut builder = ClassFileBuilder::new("Printer");
+    let pool = builder.constant_pool();
+    let out = pool
+        .field_ref("java/lang/System", "out", "Ljava/io/PrintStream;")
+        .inner();
+    let print = pool
+        .method_ref("java/io/PrintStream", "print", descriptor)
+        .inner();
+    let println = pool
+        .method_ref("java/io/PrintStream", "println", "()V")
+        .inner();
+    let mut code = Vec::new();
+    for value in values(pool) {
+        code.extend([
+            Instruction::Getstatic(out),
+            value,
+            Instruction::Invokevirtual(print),
+        ]);
+        code.extend([
+            Instruction::Getstatic(out),
+            Instruction::Invokevirtual(println),
+        ]);
+    }
+    code.push(Instruction::Return);
+    let class = add_static(builder, "print", "()V", &code).build();
+
+    let mut interpreter = Interpreter::new();
+    let output = Output::default();
+    interpreter.set_stdout(output.clone());
+    assert_eq!(
+        interpreter.run(Arc::new(class), "print", "()V", &[]),
+        Ok(None)
+    );
+    output.text()
+}
+
+#[test]
+fn printing() {
+    let doubles = [
+        0.0,
+        -0.0,
+        1.0,
+        -2.5,
+        0.001,
+        0.0001,
+        1234567.0,
+        1e7,
+        1.25e-10,
+        f64::MAX,
+        f64::NAN,
+        f64::NEG_INFINITY,
+    ];
+    let text = printed("(D)V", |pool| {
+        doubles
+            .iter()
+            .map(|&n| Instruction::Ldc2W(pool.double(n).inner()))
+            .collect()
+    });
+    assert_eq!(
+        text,
+        "0.0\n-0.0\n1.0\n-2.5\n0.001\n1.0E-4\n1234567.0\n1.0E7\n1.25E-10\n\
+         1.7976931348623157E308\nNaN\n-Infinity\n"
+    );
+    let text = printed("(F)V", |pool| {
+        [0.1, 3.4028235e38, 100.0]
+            .iter()
+            .map(|&n| Instruction::LdcW(pool.float(n).inner()))
+            .collect()
+    });
+    assert_eq!(text, "0.1\n3.4028235E38\n100.0\n");
+    let text = printed("(C)V", |_| {
+        vec![Instruction::Bipush(b'A' as i8), Instruction::Sipush(0x00e9)]
+    });
+    assert_eq!(text, "A\n\u{e9}\n");
+    let text = printed("(Ljava/lang/Object;)V", |pool| {
+        vec![
+            Instruction::AconstNull,
+            Instruction::LdcW(pool.string("text").inner()),
+        ]
+    });
+    assert_eq!(text, "null\ntext\n");
+}
+
+#[test]
+fn natives() {
+    fn twice(_: &mut NativeContext<'_>, args: &[Value]) -> Result<Option<Value>, VmError> {
+        Ok(Some(Value::Int(args[0].as_int()? * 2)))
+    }
+    let flags = MethodAccessFlag::PUBLIC | MethodAccessFlag::STATIC | MethodAccessFlag::NATIVE;
+    let mut builder = ClassFileBuilder::new("Native").add_method(flags, "twice", "(I)I", None);
+    let twice_ref = builder
+        .constant_pool()
+        .method_ref("Native", "twice", "(I)I")
+        .inner();
+    let code = [
+        Instruction::Bipush(21),
+        Instruction::Invokestatic(twice_ref),
+        Instruction::Ireturn,
+    ];
+    let class = Arc::new(add_static(builder, "run", "()I", &code).build());
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(Arc::clone(&class), "run", "()I", &[]),
+        Err(VmError::throw(
+            "java/lang/UnsatisfiedLinkError",
+            "Native.twice(I)I"
+        ))
+    );
+    interpreter.register_native("Native", "twice", "(I)I", twice);
+    assert_eq!(
+        interpreter.run(class, "run", "()I", &[]),
+        Ok(Some(Value::Int(42)))
+    );
+}
+
+#[test]
+fn stubs() {
+    let native = MethodAccessFlag::PUBLIC | MethodAccessFlag::NATIVE;
+    let class = ClassFileBuilder::new("pkg/Native")
+        .add_method(native | MethodAccessFlag::STATIC, "twice", "(I)I", None)
+        .add_method(native, "setName", "(Ljava/lang/String;)V", None)
+        .add_method(native, "type", "(J)Z", None)
+        .add_method(native, "type", "([C)Z", None)
+        .add_method(MethodAccessFlag::NATIVE, "hidden", "()V", None)
+        .add_default_constructor()
+        .build();
+    let stubs = native_stubs(&class).unwrap();
+    let expected = r#"//! The `native` methods of `pkg/Native`
+
+use cs_vm::error::VmError;
+use cs_vm::{Interpreter, NativeContext, ObjectRef, Value};
+
+pub trait NativeNatives {
+    /// `twice(I)I`
+    fn twice(context: &mut NativeContext<'_>, arg0: i32) -> Result<i32, VmError>;
+
+    /// `setName(Ljava/lang/String;)V`
+    fn set_name(context: &mut NativeContext<'_>, this: ObjectRef, arg0: Option<ObjectRef>) -> Result<(), VmError>;
+
+    /// `type(J)Z`
+    fn type_1(context: &mut NativeContext<'_>, this: ObjectRef, arg0: i64) -> Result<bool, VmError>;
+
+    /// `type([C)Z`
+    fn type_2(context: &mut NativeContext<'_>, this: ObjectRef, arg0: Option<ObjectRef>) -> Result<bool, VmError>;
+
+    /// Registers the methods as the natives of `pkg/Native`
+    fn register(interpreter: &mut Interpreter) {
+        interpreter.register_native(
+            "pkg/Native",
+            "twice",
+            "(I)I",
+            |context, args| {
+                let value = Self::twice(context, args[0].as_int()?)?;
+                Ok(Some(Value::Int(value.into())))
+            },
+        );
+        interpreter.register_native(
+            "pkg/Native",
+            "setName",
+            "(Ljava/lang/String;)V",
+            |context, args| {
+                Self::set_name(context, args[0].as_reference()?.expect("a checked object"), args[1].as_reference()?)?;
+                Ok(None)
+            },
+        );
+        interpreter.register_native(
+            "pkg/Native",
+            "type",
+            "(J)Z",
+            |context, args| {
+                let value = Self::type_1(context, args[0].as_reference()?.expect("a checked object"), args[1].as_long()?)?;
+                Ok(Some(Value::Int(value.into())))
+            },
+        );
+        interpreter.register_native(
+            "pkg/Native",
+            "type",
+            "([C)Z",
+            |context, args| {
+                let value = Self::type_2(context, args[0].as_reference()?.expect("a checked object"), args[1].as_reference()?)?;
+                Ok(Some(Value::Int(value.into())))
+            },
+        );
+    }
+}
+
+pub struct Native;
+
+#[allow(unused_variables)]
+impl NativeNatives for Native {
+    fn twice(context: &mut NativeContext<'_>, arg0: i32) -> Result<i32, VmError> {
+        todo!()
+    }
+
+    fn set_name(context: &mut NativeContext<'_>, this: ObjectRef, arg0: Option<ObjectRef>) -> Result<(), VmError> {
+        todo!()
+    }
+
+    fn type_1(context: &mut NativeContext<'_>, this: ObjectRef, arg0: i64) -> Result<bool, VmError> {
+        todo!()
+    }
+
+    fn type_2(context: &mut NativeContext<'_>, this: ObjectRef, arg0: Option<ObjectRef>) -> Result<bool, VmError> {
+        todo!()
+    }
+}
+"#;
+    assert_eq!(stubs, expected);
+}
+
+/// An interpreter that loads the classes in `testdata` and writes to the output
+fn testdata_interpreter(output: &Output) -> Interpreter {
+    let mut class_path = ClassPath::new();
+    class_path.push_directory(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+    let mut interpreter = Interpreter::with_class_path(class_path);
+    interpreter.set_stdout(output.clone());
+    interpreter
+}
+
+/// Runs the `main` method of the class in `testdata`
+fn run_main(interpreter: &mut Interpreter, class: &str) -> Result<Option<Value>, VmError> {
+    let class = interpreter.loader().load(class)?;
+    interpreter.run(class, "main", "([Ljava/lang/String;)V", &[Value::NULL])
+}
+
+#[test]
+fn class_loading() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    interpreter.loader().load("Loading").unwrap();
+    assert!(interpreter.loader().loaded("Counter").is_none());
+
+    assert_eq!(run_main(&mut interpreter, "Loading"), Ok(None));
+    assert_eq!(output.text(), "7\n1\n144\n");
+    for name in ["Counter", "Base", "Named", "Squares"] {
+        assert!(interpreter.loader().loaded(name).is_some(), "{}", name);
+    }
+    assert!(interpreter.loader().loaded("Unused").is_none());
+
+    assert_eq!(
+        interpreter.loader().load("Missing"),
+        Err(VmError::throw("java/lang/NoClassDefFoundError", "Missing"))
+    );
+    let counter = ClassFileBuilder::new("Counter").build();
+    assert_eq!(
+        interpreter.add_class(Arc::new(counter)),
+        Err(VmError::Linkage(LinkageError::DuplicateClass(
+            "Counter".to_owned()
+        )))
+    );
+}
+
+#[test]
+fn catching_exceptions() {
+    // broken versions of the classes in `testdata`, which are found first
+    let dir = std::env::temp_dir().join(format!("cs_vm_catching_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let testdata = |name: &str| {
+        let path = format!("{}/testdata/{}.class", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    };
+    let mut malformed = testdata("Malformed");
+    malformed.truncate(malformed.len() - 10);
+    std::fs::write(dir.join("Malformed.class"), malformed).unwrap();
+    let mut future = cs_parser::parse_class_file(&testdata("Future")).unwrap();
+    future.major_version = 99;
+    let future = cs_parser::write_class_file(&future).unwrap();
+    std::fs::write(dir.join("Future.class"), future).unwrap();
+    // `run()I` returns the int as a reference
+    let mut unverifiable = cs_parser::parse_class_file(&testdata("Unverifiable")).unwrap();
+    for method in &mut unverifiable.methods {
+        for attribute in &mut method.attributes {
+            if let AttributeInfoInner::Code { code, .. } = &mut attribute.inner {
+                if code.as_ref()
+                    == encode_code(&[Instruction::Iconst1, Instruction::Ireturn]).unwrap()
+                {
+                    *code = encode_code(&[Instruction::Iconst1, Instruction::Areturn])
+                        .unwrap()
+                        .into();
+                }
+            }
+        }
+    }
+    let unverifiable = cs_parser::write_class_file(&unverifiable).unwrap();
+    std::fs::write(dir.join("Unverifiable.class"), unverifiable).unwrap();
+
+    let mut class_path = ClassPath::new();
+    class_path.push_directory(&dir);
+    class_path.push_directory(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata"));
+    let output = Output::default();
+    let mut interpreter = Interpreter::with_class_path(class_path);
+    interpreter.set_stdout(output.clone());
+
+    // the last exception is not caught
+    assert_eq!(
+        run_main(&mut interpreter, "Catching"),
+        Err(VmError::throw("Failure", "failed"))
+    );
+    assert_eq!(
+        output.text(),
+        "ClassFormatError\n\
+         Unsupported class file major version 99.0\n\
+         VerifyError\n\
+         / by zero\n\
+         finally\n\
+         failed\n"
+    );
+    assert!(interpreter.frames().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn subroutines() {
+    // `int x = 0; try {} finally { x += 21; } try {} finally { x += 21; } return x;`
+    let mut asm = Assembler::new();
+    let subroutine = asm.new_label();
+    asm.push(Instruction::Iconst0);
+    asm.push(Instruction::Istore0);
+    asm.branch(Instruction::Jsr, subroutine);
+    asm.branch(Instruction::Jsr, subroutine);
+    asm.push(Instruction::Iload0);
+    asm.push(Instruction::Ireturn);
+    asm.place(subroutine);
+    asm.push(Instruction::Astore1);
+    asm.push(Instruction::Iinc {
+        index: 0,
+        value: 21,
+    });
+    asm.push(Instruction::Ret(1));
+    let code = MethodCode {
+        max_stack: 1,
+        max_locals: 2,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let class = ClassFileBuilder::new("Subroutines")
+        .version(49, 0)
+        .add_method(MethodAccessFlag::STATIC, "run", "()I", Some(code))
+        .build();
+    let dir = std::env::temp_dir().join(format!("cs_vm_subroutines_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let bytes = cs_parser::write_class_file(&class).unwrap();
+    std::fs::write(dir.join("Subroutines.class"), bytes).unwrap();
+
+    let mut class_path = ClassPath::new();
+    class_path.push_directory(&dir);
+    let mut interpreter = Interpreter::with_class_path(class_path);
+    // the subroutines are inlined while loading, so the class passes verification
+    let class = interpreter.loader().load("Subroutines").unwrap();
+    assert_eq!(
+        interpreter.run(class, "run", "()I", &[]),
+        Ok(Some(Value::Int(42)))
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn threads() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    assert_eq!(run_main(&mut interpreter, "Threads"), Ok(None));
+    assert_eq!(output.text(), "true\nfalse\n10000\n10000\n");
+
+    let mut interpreter = testdata_interpreter(&output);
+    assert_eq!(
+        run_main(&mut interpreter, "Deadlock"),
+        Err(VmError::Deadlock)
+    );
+    assert_eq!(interpreter.frames().len(), 0);
+
+    let result = run_static(
+        "()V",
+        &[
+            Instruction::Aload0,
+            Instruction::Monitorenter,
+            Instruction::Return,
+        ],
+        &[Value::NULL],
+    );
+    assert_eq!(
+        result,
+        Err(VmError::throw(
+            "java/lang/NullPointerException",
+            "Cannot enter synchronized block because the object is null"
+        ))
+    );
+    let result = run_on_object(&[], "java/lang/Object", |_| {
+        vec![
+            Instruction::Astore0,
+            Instruction::Aload0,
+            Instruction::Monitorenter,
+            Instruction::Aload0,
+            Instruction::Monitorexit,
+            Instruction::Aload0,
+            Instruction::Monitorexit,
+            Instruction::Iconst0,
+            Instruction::Ireturn,
+        ]
+    });
+    assert_eq!(
+        result,
+        Err(VmError::throw(
+            "java/lang/IllegalMonitorStateException",
+            "current thread is not owner"
+        ))
+    );
+}
+
+#[test]
+fn thread_dump() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    // the started thread owns `FIRST` and enters `SECOND`, which `main` owns
+    interpreter.add_breakpoint(Breakpoint {
+        class: "Deadlock".to_owned(),
+        method: "run".to_owned(),
+        descriptor: "()V".to_owned(),
+        pc: 14,
+    });
+    let class = interpreter.loader().load("Deadlock").unwrap();
+    interpreter
+        .start(class, "main", "([Ljava/lang/String;)V", &[Value::NULL])
+        .unwrap();
+    assert_eq!(interpreter.resume(), Ok(Execution::Paused));
+    let dump = interpreter.thread_dump();
+    let locked = |line: &str| line.trim_start().starts_with("- locked java.lang.Object@");
+    let lines = dump
+        .lines()
+        .map(|line| if locked(line) { "\t- locked" } else { line })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "\"main\" #0 BLOCKED",
+            "\tat Deadlock.main:17",
+            "\t- locked",
+            "",
+            "\"Thread-0\" #1 RUNNABLE",
+            "\tat Deadlock.run:8",
+            "\t- locked",
+            "",
+        ]
+    );
+    assert_eq!(interpreter.resume(), Err(VmError::Deadlock));
+    assert_eq!(interpreter.thread_dump(), "\"main\" #0 RUNNABLE\n\n");
+}
+
+#[test]
+fn debugging() {
+    let code = [
+        Instruction::Iload0,
+        Instruction::Iload1,
+        Instruction::Iadd,
+        Instruction::Iconst2,
+        Instruction::Imul,
+        Instruction::Ireturn,
+    ];
+    let class = single_method("(II)I", &code);
+    let args = [Value::Int(3), Value::Int(4)];
+    let mut interpreter = Interpreter::new();
+    let visited = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&visited);
+    interpreter.set_debugger(move |frame: &Frame, instruction: &Instruction| {
+        log.lock()
+            .unwrap()
+            .push((frame.pc(), instruction.mnemonic()));
+        match instruction {
+            Instruction::Imul => DebugAction::Pause,
+            _ => DebugAction::Continue,
+        }
+    });
+    let breakpoint = Breakpoint {
+        class: "Test".to_owned(),
+        method: "run".to_owned(),
+        descriptor: "(II)I".to_owned(),
+        pc: 2,
+    };
+    assert!(interpreter.add_breakpoint(breakpoint.clone()));
+
+    interpreter
+        .start(Arc::clone(&class), "run", "(II)I", &args)
+        .unwrap();
+    assert_eq!(interpreter.resume(), Ok(Execution::Paused));
+    let frame = interpreter.frames().last().unwrap();
+    assert_eq!(frame.pc(), 2);
+    let stack = frame.stack.values().collect::<Vec<_>>();
+    assert_eq!(stack, [Value::Int(3), Value::Int(4)]);
+    assert_eq!(frame.locals.len(), 8);
+
+    assert_eq!(interpreter.step(), Ok(Execution::Paused));
+    assert_eq!(interpreter.frames().last().unwrap().pc(), 3);
+    assert_eq!(interpreter.resume(), Ok(Execution::Paused));
+    assert_eq!(interpreter.frames().last().unwrap().pc(), 4);
+    assert_eq!(
+        interpreter.resume(),
+        Ok(Execution::Returned(Some(Value::Int(14))))
+    );
+    assert_eq!(
+        *visited.lock().unwrap(),
+        [
+            (0, "iload_0"),
+            (1, "iload_1"),
+            (2, "iadd"),
+            (3, "iconst_2"),
+            (4, "imul"),
+            (5, "ireturn")
+        ]
+    );
+    assert!(interpreter.frames().is_empty());
+    assert_eq!(
+        interpreter.resume(),
+        Err(VmError::Unsupported(
+            "resuming without a started method".to_owned()
+        ))
+    );
+
+    // `run` doesn't stop at pauses
+    assert!(interpreter.remove_breakpoint(&breakpoint));
+    assert_eq!(
+        interpreter.run(class, "run", "(II)I", &args),
+        Ok(Some(Value::Int(14)))
+    );
+}
+
+#[cfg(debug_assertions)]
+/// Records the calls of the interpreter with the pc and the size of the stack
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Hook for Recorder {
+    fn before_instruction(&mut self, frame: &Frame, instruction: &Instruction) {
+        let event = format!("before {} {}", frame.pc(), instruction.mnemonic());
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn after_instruction(&mut self, frame: &Frame, instruction: &Instruction) {
+        let stack = frame.stack.values().count();
+        let event = format!(
+            "after {} {} [{}]",
+            frame.pc(),
+            instruction.mnemonic(),
+            stack
+        );
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn method_entry(&mut self, frame: &Frame) {
+        let event = format!("enter {}", frame.method_name());
+        self.0.lock().unwrap().push(event);
+    }
+
+    fn method_exit(&mut self, frame: &Frame, exit: MethodExit) {
+        let exit = match exit {
+            MethodExit::Returned(value) => format!("{:?}", value),
+            MethodExit::Threw(_) => "threw".to_owned(),
+        };
+        let event = format!("exit {} {}", frame.method_name(), exit);
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn hooks() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let check = builder
+        .constant_pool()
+        .method_ref("Test", "check", "(I)I")
+        .inner();
+    // `1 / n`
+    let builder = add_static(
+        builder,
+        "check",
+        "(I)I",
+        &[
+            Instruction::Iconst1,
+            Instruction::Iload0,
+            Instruction::Idiv,
+            Instruction::Ireturn,
+        ],
+    );
+    // `check(n)`, or -1 if it throws
+    let code = MethodCode {
+        max_stack: 1,
+        max_locals: 1,
+        code: encode_code(&[
+            Instruction::Iload0,
+            Instruction::Invokestatic(check),
+            Instruction::Ireturn,
+            Instruction::Pop,
+            Instruction::IconstM1,
+            Instruction::Ireturn,
+        ])
+        .unwrap(),
+        exception_table: vec![AttributeCodeException {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc: 5,
+            catch_type: 0,
+        }],
+    };
+    let class = Arc::new(
+        builder
+            .add_method(MethodAccessFlag::STATIC, "run", "(I)I", Some(code))
+            .build(),
+    );
+    let mut interpreter = Interpreter::new();
+    let recorder = Recorder::default();
+    interpreter.add_hook(recorder.clone());
+
+    assert_eq!(
+        interpreter.run(Arc::clone(&class), "run", "(I)I", &[Value::Int(0)]),
+        Ok(Some(Value::Int(-1)))
+    );
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [
+            "enter Test.run(I)I",
+            "before 0 iload_0",
+            "after 0 iload_0 [1]",
+            "before 1 invokestatic",
+            "after 1 invokestatic [0]",
+            "enter Test.check(I)I",
+            "before 0 iconst_1",
+            "after 0 iconst_1 [1]",
+            "before 1 iload_0",
+            "after 1 iload_0 [2]",
+            "before 2 idiv",
+            "after 2 idiv [0]",
+            "exit Test.check(I)I threw",
+            "before 5 pop",
+            "after 5 pop [0]",
+            "before 6 iconst_m1",
+            "after 6 iconst_m1 [1]",
+            "before 7 ireturn",
+            "after 7 ireturn [0]",
+            "exit Test.run(I)I Some(Int(-1))",
+        ]
+    );
+
+    interpreter.remove_hooks();
+    recorder.0.lock().unwrap().clear();
+    assert_eq!(
+        interpreter.run(class, "run", "(I)I", &[Value::Int(1)]),
+        Ok(Some(Value::Int(1)))
+    );
+    assert!(recorder.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn tracing() {
+    let mut builder = ClassFileBuilder::new("Test");
+    let long = builder.constant_pool().long(1 << 40).inner();
+    let code = [
+        Instruction::Iload0,
+        Instruction::Ldc2W(long),
+        Instruction::AconstNull,
+        Instruction::Pop,
+        Instruction::Pop2,
+        Instruction::Ireturn,
+    ];
+    let class = add_static(builder, "run", "(I)I", &code).build();
+    let mut interpreter = Interpreter::new();
+    let output = Output::default();
+    interpreter.set_trace(output.clone());
+    let result = interpreter.run(Arc::new(class), "run", "(I)I", &[Value::Int(-3)]);
+    assert_eq!(result, Ok(Some(Value::Int(-3))));
+    assert_eq!(
+        output.text(),
+        "#0 Test.run(I)I 0: iload_0 []\n\
+         #0 Test.run(I)I 1: ldc2_w 1099511627776L [-3]\n\
+         #0 Test.run(I)I 4: aconst_null [-3, 1099511627776L]\n\
+         #0 Test.run(I)I 5: pop [-3, 1099511627776L, null]\n\
+         #0 Test.run(I)I 6: pop2 [-3, 1099511627776L]\n\
+         #0 Test.run(I)I 7: ireturn [-3]\n"
+    );
+}
+
+#[test]
+fn arrays() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    assert_eq!(run_main(&mut interpreter, "Arrays"), Ok(None));
+    assert_eq!(
+        output.text(),
+        "16\n1099511627776\n2.0\n-56\n-25536\nb\nfalse\ntrue\n\
+         counter\n\
+         Base\n\
+         [I\n\
+         Index 5 out of bounds for length 5\n\
+         -1\n\
+         Cannot read the array length because the array is null\n\
+         14\n0\ntrue\ntrue\n-1\n"
+    );
+
+    // `iaload` on a `byte[]`, which the verifier would reject
+    let result = run_static(
+        "()I",
+        &[
+            Instruction::Iconst1,
+            Instruction::Newarray(8),
+            Instruction::Iconst0,
+            Instruction::Iaload,
+            Instruction::Ireturn,
+        ],
+        &[],
+    );
+    assert_eq!(
+        result,
+        Err(VmError::InvalidCode(
+            "Expected int array in Test.run()I".to_owned()
+        ))
+    );
+    // `multianewarray` with more dimensions than the type
+    let mut builder = ClassFileBuilder::new("Test");
+    let grid = builder.constant_pool().class("[[I").inner();
+    let code = [
+        Instruction::Iconst1,
+        Instruction::Iconst1,
+        Instruction::Iconst1,
+        Instruction::Multianewarray {
+            index: grid,
+            dimensions: 3,
+        },
+        Instruction::Areturn,
+    ];
+    let class = add_static(builder, "run", "()[[I", &code).build();
+    assert!(matches!(
+        Interpreter::new().run(Arc::new(class), "run", "()[[I", &[]),
+        Err(VmError::InvalidCode(_))
+    ));
+}
+
+#[test]
+fn string_arrays() {
+    // prints the number of arguments and the second one
+    let mut builder = ClassFileBuilder::new("Echo");
+    let pool = builder.constant_pool();
+    let out = pool
+        .field_ref("java/lang/System", "out", "Ljava/io/PrintStream;")
+        .inner();
+    let print_int = pool
+        .method_ref("java/io/PrintStream", "println", "(I)V")
+        .inner();
+    let print_string = pool
+        .method_ref("java/io/PrintStream", "println", "(Ljava/lang/String;)V")
+        .inner();
+    let code = [
+        Instruction::Getstatic(out),
+        Instruction::Aload0,
+        Instruction::Arraylength,
+        Instruction::Invokevirtual(print_int),
+        Instruction::Getstatic(out),
+        Instruction::Aload0,
+        Instruction::Iconst1,
+        Instruction::Aaload,
+        Instruction::Invokevirtual(print_string),
+        Instruction::Return,
+    ];
+    let class = add_static(builder, "main", "([Ljava/lang/String;)V", &code).build();
+
+    let mut interpreter = Interpreter::new();
+    let output = Output::default();
+    interpreter.set_stdout(output.clone());
+    let args = interpreter.string_array(&["a", "b\u{e9}"]).unwrap();
+    let result = interpreter.run(
+        Arc::new(class),
+        "main",
+        "([Ljava/lang/String;)V",
+        &[Value::Reference(Some(args))],
+    );
+    assert_eq!(result, Ok(None));
+    assert_eq!(output.text(), "2\nb\u{e9}\n");
+}
+
+#[test]
+fn garbage_collection() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    assert_eq!(run_main(&mut interpreter, "Garbage"), Ok(None));
+    assert_eq!(output.text(), "4950\n1990000\n");
+    // collected at the safepoints, without freeing the objects of the static field and the
+    // local variables of the thread
+    assert!(
+        interpreter.heap().len() < 2048,
+        "{}",
+        interpreter.heap().len()
+    );
+
+    // the nodes in `kept`, and `System.out`
+    interpreter.collect_garbage();
+    assert_eq!(interpreter.heap().len(), 101);
+    // the freed slots are reused, `kept` gets 100 more nodes
+    assert_eq!(run_main(&mut interpreter, "Garbage"), Ok(None));
+    assert_eq!(output.text(), "4950\n1990000\n9900\n1990000\n");
+}
+
+#[test]
+fn weak_references() {
+    let output = Output::default();
+    let mut interpreter = testdata_interpreter(&output);
+    assert_eq!(run_main(&mut interpreter, "References"), Ok(None));
+    // unlike the JDK, references are enqueued right away
+    assert_eq!(output.text(), "true\n".repeat(9));
+}
@@ -0,0 +1,118 @@
+//!
+//! The threads of the interpreter and the monitors they synchronize on
+//!
+
+use crate::error::VmError;
+use crate::frame::Frame;
+use crate::value::ObjectRef;
+use std::collections::HashMap;
+
+/// Identifies a thread for the monitors it owns. The thread that runs the methods of
+/// `Interpreter::run` is the first one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct ThreadId(pub(crate) u32);
+
+/// A thread, which executes its frames when it is its turn
+pub(crate) struct Thread {
+    pub(crate) id: ThreadId,
+    /// The `java/lang/Thread` that was started, `None` for the first thread
+    pub(crate) object: Option<ObjectRef>,
+    /// The current frame is last
+    pub(crate) frames: Vec<Frame>,
+    pub(crate) state: ThreadState,
+}
+
+/// Whether a thread can execute its current instruction, named like the states of
+/// `java/lang/Thread$State` in thread dumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ThreadState {
+    #[default]
+    Runnable,
+    /// Waiting to enter a monitor that another thread owns
+    Blocked,
+    /// Waiting for another thread to finish
+    Waiting,
+}
+
+impl ThreadState {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Runnable => "RUNNABLE",
+            Self::Blocked => "BLOCKED",
+            Self::Waiting => "WAITING",
+        }
+    }
+}
+
+/// What a monitor belongs to: an object, or a class for its `static synchronized` methods
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Lock {
+    Object(ObjectRef),
+    /// The internal name of the class
+    Class(String),
+}
+
+/// The monitors that are owned by a thread. A thread can enter a monitor it owns again, and it
+/// is released once the thread exited it as often as it entered it
+#[derive(Debug, Default)]
+pub(crate) struct Monitors {
+    owners: HashMap<Lock, (ThreadId, u32)>,
+}
+
+impl Monitors {
+    /// Enters the monitor, false if another thread owns it and the thread has to wait
+    pub(crate) fn enter(&mut self, lock: Lock, thread: ThreadId) -> bool {
+        let (owner, count) = self.owners.entry(lock).or_insert((thread, 0));
+        if *owner != thread {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// The monitors the thread owns
+    pub(crate) fn owned_by(&self, thread: ThreadId) -> impl Iterator<Item = &Lock> {
+        self.owners
+            .iter()
+            .filter(move |(_, (owner, _))| *owner == thread)
+            .map(|(lock, _)| lock)
+    }
+
+    /// The objects whose monitors are owned by a thread
+    pub(crate) fn locked_objects(&self) -> impl Iterator<Item = ObjectRef> + '_ {
+        self.owners.keys().filter_map(|lock| match lock {
+            Lock::Object(object) => Some(*object),
+            Lock::Class(_) => None,
+        })
+    }
+
+    /// Exits the monitor, which the thread has to own
+    pub(crate) fn exit(&mut self, lock: &Lock, thread: ThreadId) -> Result<(), VmError> {
+        match self.owners.get_mut(lock) {
+            Some((owner, count)) if *owner == thread => {
+                *count -= 1;
+                if *count == 0 {
+                    self.owners.remove(lock);
+                }
+                Ok(())
+            }
+            _ => Err(VmError::throw(
+                "java/lang/IllegalMonitorStateException",
+                "current thread is not owner",
+            )),
+        }
+    }
+}
+
+/// What the interpreter does after a native method returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Schedule {
+    /// Continue with the next instruction
+    Continue,
+    /// Continue with the next instruction once the other threads had their turn
+    Yield,
+    /// Call the method again later, when another thread made progress
+    Block,
+    /// Start the `java/lang/Thread`, which runs its `run` method
+    Start(ObjectRef),
+}
@@ -0,0 +1,48 @@
+//!
+//! Logging every instruction that is executed, which is only compiled in debug builds
+//!
+
+use crate::frame::Frame;
+use crate::thread::ThreadId;
+use crate::value::Value;
+use cs_parser::Instruction;
+use std::io::Write;
+
+/// Writes a line like `#0 Test.run(II)I 2: iadd [3, 4]` with the thread, the method, the
+/// instruction and the operand stack before it is executed. Errors of the output are ignored
+pub(crate) fn trace(
+    output: &mut dyn Write,
+    thread: ThreadId,
+    frame: &Frame,
+    instruction: &Instruction,
+) {
+    let cp = &frame.class.constant_pool;
+    let stack = frame
+        .stack
+        .values()
+        .map(compact)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let _ = writeln!(
+        output,
+        "#{} {} {}: {} [{}]",
+        thread.0,
+        frame.method_name(),
+        frame.pc(),
+        cs_parser::display_instruction(frame.pc(), instruction, cp),
+        stack
+    );
+}
+
+/// The value like a literal in java, references by the index of the object
+fn compact(value: Value) -> String {
+    match value {
+        Value::Int(n) => n.to_string(),
+        Value::Long(n) => format!("{}L", n),
+        Value::Float(n) => format!("{:?}f", n),
+        Value::Double(n) => format!("{:?}", n),
+        Value::Reference(None) => "null".to_owned(),
+        Value::Reference(Some(reference)) => format!("@{}", reference.0),
+        Value::ReturnAddress(address) => format!("ret {}", address),
+    }
+}
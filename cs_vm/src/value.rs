@@ -0,0 +1,99 @@
+//!
+//! The values the interpreter works with
+//!
+
+use crate::error::VmError;
+use cs_model::FieldType;
+
+/// A value on the operand stack or in a local variable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// Also `boolean`, `byte`, `char` and `short`
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// `None` is `null`
+    Reference(Option<ObjectRef>),
+    /// The address after a `jsr`, for `ret`
+    ReturnAddress(u32),
+}
+
+/// An object on the heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectRef(pub(crate) u32);
+
+impl Value {
+    pub const NULL: Self = Self::Reference(None);
+
+    /// Longs and doubles take up two slots of the operand stack and the local variables
+    pub fn is_wide(self) -> bool {
+        matches!(self, Self::Long(_) | Self::Double(_))
+    }
+
+    /// The value of a field of the type before it is assigned, zero or `null`
+    pub fn default_for(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Boolean
+            | FieldType::Byte
+            | FieldType::Char
+            | FieldType::Short
+            | FieldType::Int => Self::Int(0),
+            FieldType::Long => Self::Long(0),
+            FieldType::Float => Self::Float(0.0),
+            FieldType::Double => Self::Double(0.0),
+            FieldType::Object(_) | FieldType::Array(_) => Self::NULL,
+        }
+    }
+
+    /// The name of the type for error messages
+    pub fn type_name(self) -> &'static str {
+        match self {
+            Self::Int(_) => "int",
+            Self::Long(_) => "long",
+            Self::Float(_) => "float",
+            Self::Double(_) => "double",
+            Self::Reference(_) => "reference",
+            Self::ReturnAddress(_) => "returnAddress",
+        }
+    }
+
+    fn expected(self, expected: &str) -> VmError {
+        VmError::InvalidCode(format!("Expected {}, found {:?}", expected, self))
+    }
+
+    pub fn as_int(self) -> Result<i32, VmError> {
+        match self {
+            Self::Int(n) => Ok(n),
+            _ => Err(self.expected("int")),
+        }
+    }
+
+    pub fn as_long(self) -> Result<i64, VmError> {
+        match self {
+            Self::Long(n) => Ok(n),
+            _ => Err(self.expected("long")),
+        }
+    }
+
+    pub fn as_float(self) -> Result<f32, VmError> {
+        match self {
+            Self::Float(n) => Ok(n),
+            _ => Err(self.expected("float")),
+        }
+    }
+
+    pub fn as_double(self) -> Result<f64, VmError> {
+        match self {
+            Self::Double(n) => Ok(n),
+            _ => Err(self.expected("double")),
+        }
+    }
+
+    pub fn as_reference(self) -> Result<Option<ObjectRef>, VmError> {
+        match self {
+            Self::Reference(reference) => Ok(reference),
+            _ => Err(self.expected("reference")),
+        }
+    }
+}
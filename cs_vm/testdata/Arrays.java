@@ -0,0 +1,78 @@
+public class Arrays {
+    public static void main(String[] args) {
+        int[] squares = new int[5];
+        for (int i = 0; i < squares.length; i++) {
+            squares[i] = i * i;
+        }
+        System.out.println(squares[4]);
+        long[] longs = {1L << 40};
+        System.out.println(longs[0]);
+        double[] doubles = {0.5};
+        float[] floats = {1.5f};
+        System.out.println(doubles[0] + floats[0]);
+        byte[] bytes = new byte[1];
+        bytes[0] = (byte) 200;
+        System.out.println(bytes[0]);
+        short[] shorts = {(short) 40000};
+        System.out.println(shorts[0]);
+        char[] chars = {'a', 'b'};
+        System.out.println(chars[1]);
+        boolean[] flags = new boolean[2];
+        flags[1] = true;
+        System.out.println(flags[0]);
+        System.out.println(flags[1]);
+
+        Named[] named = new Counter[2];
+        named[0] = new Counter();
+        System.out.println(named[0].name());
+        Object[] objects = named;
+        objects[1] = null;
+        try {
+            objects[1] = new Base();
+        } catch (ArrayStoreException e) {
+            System.out.println(e.getMessage());
+        }
+        Object[] nested = new Base[1][];
+        nested[0] = new Counter[2];
+        try {
+            nested[0] = new int[2];
+        } catch (ArrayStoreException e) {
+            System.out.println(e.getMessage());
+        }
+        Object[] anything = new Object[2];
+        anything[0] = squares;
+        anything[1] = objects;
+
+        try {
+            squares[5] = 1;
+        } catch (ArrayIndexOutOfBoundsException e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            System.out.println(new int[-1].length);
+        } catch (NegativeArraySizeException e) {
+            System.out.println(e.getMessage());
+        }
+        int[] missing = null;
+        try {
+            System.out.println(missing.length);
+        } catch (NullPointerException e) {
+            System.out.println(e.getMessage());
+        }
+
+        int[][] grid = new int[3][4];
+        grid[2][3] = 7;
+        System.out.println(grid.length + grid[2].length + grid[2][3]);
+        int[][][] empty = new int[2][0][5];
+        System.out.println(empty[1].length);
+        int[][] rows = new int[2][];
+        System.out.println(rows[1] == null);
+        String[][] names = new String[2][3];
+        System.out.println(names[1][2] == null);
+        try {
+            System.out.println(new int[2][-1][0].length);
+        } catch (NegativeArraySizeException e) {
+            System.out.println(e.getMessage());
+        }
+    }
+}
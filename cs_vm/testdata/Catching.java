@@ -0,0 +1,62 @@
+public class Catching {
+    public static void main(String[] args) {
+        try {
+            Malformed.run();
+        } catch (ClassFormatError e) {
+            System.out.println("ClassFormatError");
+        }
+        try {
+            Future.run();
+        } catch (UnsupportedClassVersionError e) {
+            System.out.println(e.getMessage());
+        }
+        try {
+            Unverifiable.run();
+        } catch (VerifyError e) {
+            System.out.println("VerifyError");
+        }
+        try {
+            System.out.println(divide(1, 0));
+        } catch (ArithmeticException e) {
+            System.out.println(e.getMessage());
+        } finally {
+            System.out.println("finally");
+        }
+        try {
+            fail();
+        } catch (Failure e) {
+            System.out.println(e.getMessage());
+        }
+        fail();
+    }
+
+    static int divide(int a, int b) {
+        return a / b;
+    }
+
+    static void fail() {
+        throw new Failure("failed");
+    }
+}
+
+class Failure extends RuntimeException {
+    Failure(String message) {
+        super(message);
+    }
+}
+
+class Malformed {
+    static void run() {
+    }
+}
+
+class Future {
+    static void run() {
+    }
+}
+
+class Unverifiable {
+    static int run() {
+        return 1;
+    }
+}
@@ -0,0 +1,21 @@
+public class Deadlock extends Thread {
+    static final Object FIRST = new Object();
+    static final Object SECOND = new Object();
+
+    public void run() {
+        synchronized (FIRST) {
+            Thread.yield();
+            synchronized (SECOND) {
+            }
+        }
+    }
+
+    public static void main(String[] args) {
+        new Deadlock().start();
+        synchronized (SECOND) {
+            Thread.yield();
+            synchronized (FIRST) {
+            }
+        }
+    }
+}
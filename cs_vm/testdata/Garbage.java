@@ -0,0 +1,53 @@
+public class Garbage {
+    static Node kept;
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread worker = new Thread(new Worker());
+        worker.start();
+        for (int i = 0; i < 100; i++) {
+            kept = new Node(i, kept);
+        }
+        for (int i = 0; i < 20000; i++) {
+            int[] garbage = new int[4];
+            garbage[0] = i;
+            new Node(i, null);
+        }
+        worker.join();
+        System.out.println(sum(kept));
+        System.out.println(Worker.sum);
+    }
+
+    static int sum(Node node) {
+        int sum = 0;
+        for (; node != null; node = node.next) {
+            sum += node.value;
+        }
+        return sum;
+    }
+
+    static class Node {
+        final int value;
+        final Node next;
+
+        Node(int value, Node next) {
+            this.value = value;
+            this.next = next;
+        }
+    }
+
+    static class Worker implements Runnable {
+        static int sum;
+
+        public void run() {
+            // only reachable from the local variable of this thread
+            Node chain = null;
+            for (int i = 0; i < 20000; i++) {
+                Object garbage = new Object();
+                if (i % 100 == 0) {
+                    chain = new Node(i, chain);
+                }
+            }
+            sum = Garbage.sum(chain);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+public class Hello {
+    public static void main(String[] args) {
+        System.out.println("Hello, World!");
+        int sum = 0;
+        for (int i = 1; i <= 10; i++) {
+            sum += i;
+        }
+        System.out.println(sum);
+        System.out.println(1.5);
+        System.out.println(true);
+        System.out.println('c');
+        System.out.println(10000000000L);
+    }
+}
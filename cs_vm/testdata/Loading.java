@@ -0,0 +1,46 @@
+public class Loading {
+    public static void main(String[] args) {
+        Counter counter = new Counter();
+        counter.add(5);
+        counter.add(2);
+        System.out.println(counter.count);
+        System.out.println(Counter.created);
+        if (counter.count < 0) {
+            new Unused();
+        }
+        System.out.println(Squares.square(12));
+    }
+}
+
+class Base {
+    static int created;
+
+    Base() {
+        created++;
+    }
+}
+
+class Counter extends Base implements Named {
+    int count;
+
+    void add(int n) {
+        count += n;
+    }
+
+    public String name() {
+        return "counter";
+    }
+}
+
+interface Named {
+    String name();
+}
+
+class Squares {
+    static int square(int n) {
+        return n * n;
+    }
+}
+
+class Unused {
+}
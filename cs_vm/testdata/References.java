@@ -0,0 +1,34 @@
+import java.lang.ref.ReferenceQueue;
+import java.lang.ref.WeakReference;
+
+public class References {
+    static Object strong = new Object();
+
+    public static void main(String[] args) {
+        ReferenceQueue<Object> queue = new ReferenceQueue<>();
+        WeakReference<Object> kept = new WeakReference<>(strong, queue);
+        WeakReference<Object> collected = new WeakReference<>(new Object(), queue);
+        WeakReference<Object> unqueued = new WeakReference<>(new int[1]);
+        System.out.println(queue.poll() == null);
+        System.gc();
+        System.out.println(kept.get() == strong);
+        System.out.println(collected.get() == null);
+        System.out.println(unqueued.get() == null);
+        System.out.println(queue.poll() == collected);
+        System.out.println(queue.poll() == null);
+
+        // the referent is collected at a safepoint once enough objects were allocated
+        WeakReference<Object> cache = new WeakReference<>(new Object());
+        int allocations = 0;
+        while (cache.get() != null && allocations < 1000000) {
+            new Object();
+            allocations++;
+        }
+        System.out.println(cache.get() == null);
+        System.out.println(allocations > 0);
+        cache.clear();
+        strong = null;
+        System.gc();
+        System.out.println(kept.get() == null);
+    }
+}
@@ -0,0 +1,42 @@
+public class Threads {
+    static final Object LOCK = new Object();
+    static int synchronizedCalls;
+    static int synchronizedBlocks;
+
+    static synchronized void increment() {
+        synchronizedCalls++;
+    }
+
+    public static void main(String[] args) throws InterruptedException {
+        Thread first = new Thread(new Incrementer());
+        Thread second = new Incrementer.Subclass();
+        first.start();
+        second.start();
+        System.out.println(first.isAlive());
+        Thread.sleep(1);
+        first.join();
+        second.join();
+        System.out.println(first.isAlive());
+        System.out.println(synchronizedCalls);
+        System.out.println(synchronizedBlocks);
+    }
+}
+
+class Incrementer implements Runnable {
+    public void run() {
+        for (int i = 0; i < 5000; i++) {
+            Threads.increment();
+            synchronized (Threads.LOCK) {
+                int value = Threads.synchronizedBlocks;
+                Thread.yield();
+                Threads.synchronizedBlocks = value + 1;
+            }
+        }
+    }
+
+    static class Subclass extends Thread {
+        public void run() {
+            new Incrementer().run();
+        }
+    }
+}
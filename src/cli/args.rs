@@ -0,0 +1,110 @@
+use std::collections::{HashMap, VecDeque};
+
+/// The arguments of a subcommand. Flags and options are taken out by name, the rest is positional
+#[derive(Debug)]
+pub struct Args {
+    args: VecDeque<String>,
+    /// Values for flags and options missing from the arguments, keyed by the long name without `--`
+    defaults: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn new(args: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            args: args.into_iter().collect(),
+            defaults: HashMap::new(),
+        }
+    }
+
+    /// Sets the values used for flags and options that are not given explicitly
+    pub fn set_defaults(&mut self, defaults: HashMap<String, String>) {
+        self.defaults = defaults;
+    }
+
+    /// Puts an argument back in front
+    pub fn push_front(&mut self, arg: String) {
+        self.args.push_front(arg);
+    }
+
+    /// Takes the next argument, if it is not a flag
+    pub fn subcommand(&mut self) -> Option<String> {
+        match self.args.front() {
+            Some(arg) if !arg.starts_with('-') => self.args.pop_front(),
+            _ => None,
+        }
+    }
+
+    /// Removes all occurrences of the flag, returns how often it was present
+    pub fn count(&mut self, names: &[&str]) -> usize {
+        let before = self.args.len();
+        self.args.retain(|arg| !names.contains(&arg.as_str()));
+        before - self.args.len()
+    }
+
+    /// Removes the flag, returns whether it was present or enabled by default
+    pub fn flag(&mut self, names: &[&str]) -> bool {
+        self.switch(names).unwrap_or(false)
+    }
+
+    /// Removes the flag and its negation like `--no-watch` for `--watch`, the last one of them
+    /// wins over the default. `None` if neither is given and there is no default
+    pub fn switch(&mut self, names: &[&str]) -> Option<bool> {
+        let negations = names
+            .iter()
+            .filter_map(|name| name.strip_prefix("--"))
+            .map(|name| format!("--no-{}", name))
+            .collect::<Vec<_>>();
+        let mut enabled = None;
+        self.args.retain(|arg| {
+            if names.contains(&arg.as_str()) {
+                enabled = Some(true);
+            } else if negations.contains(arg) {
+                enabled = Some(false);
+            } else {
+                return true;
+            }
+            false
+        });
+        enabled.or_else(|| match self.default(names)?.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Removes an option with a value, either as `--name value` or `--name=value`
+    pub fn option(&mut self, names: &[&str]) -> super::Result<Option<String>> {
+        for (i, arg) in self.args.iter().enumerate() {
+            if names.contains(&arg.as_str()) {
+                let _ = self.args.remove(i);
+                return match self.args.remove(i) {
+                    Some(value) => Ok(Some(value)),
+                    None => Err(format!("Missing value for {}", names[0]).into()),
+                };
+            }
+            if let Some((name, value)) = arg.split_once('=') {
+                if names.contains(&name) {
+                    let value = value.to_owned();
+                    let _ = self.args.remove(i);
+                    return Ok(Some(value));
+                }
+            }
+        }
+        Ok(self.default(names).cloned())
+    }
+
+    fn default(&self, names: &[&str]) -> Option<&String> {
+        names
+            .iter()
+            .find_map(|name| name.strip_prefix("--"))
+            .and_then(|name| self.defaults.get(name))
+    }
+
+    /// The remaining positional arguments. Fails if there are unknown flags left
+    pub fn positional(self) -> super::Result<Vec<String>> {
+        match self.args.iter().find(|arg| arg.starts_with('-')) {
+            Some(flag) => Err(format!("Unknown flag {}", flag).into()),
+            None => Ok(self.args.into()),
+        }
+    }
+}
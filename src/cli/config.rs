@@ -0,0 +1,106 @@
+use super::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The long names of the flags, which are set with `true` or `false`
+const FLAGS: &[&str] = &[
+    "attributes",
+    "code",
+    "color",
+    "constants",
+    "decompile",
+    "exceptions",
+    "fields",
+    "json",
+    "methods",
+    "thread-dumps",
+    "watch",
+    "yaml",
+];
+
+/// The long names of the options that take a value
+const OPTIONS: &[&str] = &[
+    "classpath",
+    "format",
+    "major",
+    "method",
+    "output",
+    "source-path",
+];
+
+/// Loads the default options from the config file, `~/.config/coldsquare.toml` if none is given.
+///
+/// Every top level key is the long name of an option, like `format = "json"` for `--format json`
+/// or `watch = true` for `--watch`. Flags can also be turned off, like `color = false` or
+/// `no-color = true` for `--no-color`. Arrays are joined into a path list, like
+/// `classpath = ["lib", "app.jar"]`. Unknown keys are skipped with a warning
+pub fn load(file: Option<&str>) -> Result<HashMap<String, String>> {
+    let path = match file {
+        Some(file) => PathBuf::from(file),
+        None => match default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(HashMap::new()),
+        },
+    };
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|err| format!("Could not read config {}: {}", path.display(), err))?;
+    let (defaults, unknown) =
+        parse(&contents).map_err(|err| format!("Invalid config {}: {}", path.display(), err))?;
+    for key in unknown {
+        eprintln!("Unknown key {} in config {}", key, path.display());
+    }
+    Ok(defaults)
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("coldsquare.toml"))
+}
+
+/// The defaults keyed by the long names, and the keys that aren't options
+pub(super) fn parse(contents: &str) -> Result<(HashMap<String, String>, Vec<String>)> {
+    let table = contents.parse::<toml::Table>()?;
+    let mut defaults = HashMap::new();
+    let mut unknown = Vec::new();
+    for (key, value) in table {
+        let negated = key.strip_prefix("no-").filter(|flag| FLAGS.contains(flag));
+        if FLAGS.contains(&key.as_str()) || negated.is_some() {
+            let enabled = value
+                .as_bool()
+                .ok_or_else(|| format!("{} must be true or false, not {}", key, value))?;
+            match negated {
+                Some(flag) => defaults.insert(flag.to_owned(), (!enabled).to_string()),
+                None => defaults.insert(key, enabled.to_string()),
+            };
+        } else if OPTIONS.contains(&key.as_str()) {
+            let value = option_value(&value)
+                .ok_or_else(|| format!("Unsupported value for {}: {}", key, value))?;
+            defaults.insert(key, value);
+        } else {
+            unknown.push(key);
+        }
+    }
+    Ok((defaults, unknown))
+}
+
+fn option_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(str) => Some(str.clone()),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Some(value.to_string())
+        }
+        toml::Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| value.as_str().map(PathBuf::from))
+                .collect::<Option<Vec<_>>>()?;
+            std::env::join_paths(values)
+                .ok()
+                .map(|paths| paths.to_string_lossy().into_owned())
+        }
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
@@ -0,0 +1,57 @@
+use super::{Args, Result};
+use cs_parser::{ClassFile, CpInfoInner};
+use std::collections::BTreeSet;
+
+/// `coldsquare deps <file>`, prints the internal names of all classes the class references
+pub fn run(args: Args) -> Result<()> {
+    let class_file = super::read_class(&super::single_file(args)?)?;
+    for class in dependencies(&class_file) {
+        println!("{}", class);
+    }
+    Ok(())
+}
+
+/// All classes referenced from the constant pool, through class constants and descriptors
+fn dependencies(class: &ClassFile) -> BTreeSet<&str> {
+    let cp = &class.constant_pool;
+    let this_class = class.this_class.get(cp).name_index.get(cp);
+    let mut deps = BTreeSet::new();
+
+    for info in cp {
+        match &info.inner {
+            CpInfoInner::Class(class) => {
+                let name = class.name_index.get(cp);
+                if name.starts_with('[') {
+                    deps.extend(descriptor_classes(name));
+                } else {
+                    deps.insert(name);
+                }
+            }
+            CpInfoInner::NameAndType(name_and_type) => {
+                deps.extend(descriptor_classes(name_and_type.descriptor_index.get(cp)))
+            }
+            CpInfoInner::MethodType(method_type) => {
+                deps.extend(descriptor_classes(method_type.descriptor_index.get(cp)))
+            }
+            _ => {}
+        }
+    }
+    for descriptor in class
+        .fields
+        .iter()
+        .map(|field| field.descriptor_index)
+        .chain(class.methods.iter().map(|method| method.descriptor_index))
+    {
+        deps.extend(descriptor_classes(descriptor.get(cp)));
+    }
+
+    deps.remove(this_class);
+    deps
+}
+
+/// The class names in a field or method descriptor, for example `java/lang/String` in `([Ljava/lang/String;)V`
+fn descriptor_classes(descriptor: &str) -> impl Iterator<Item = &str> {
+    descriptor
+        .split(';')
+        .filter_map(|part| part.find('L').map(|start| &part[start + 1..]))
+}
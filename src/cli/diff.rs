@@ -0,0 +1,14 @@
+use super::{Args, Result};
+
+/// `coldsquare diff <old> <new>`, prints how the members, flags, attributes and constant values
+/// of two class files differ
+pub fn run(args: Args) -> Result<()> {
+    let (old, new) = match &args.positional()?[..] {
+        [old, new] => (super::read_class(old)?, super::read_class(new)?),
+        _ => return Err("Expected two files: diff <old> <new>".into()),
+    };
+    for difference in cs_parser::diff_classes(&old, &new) {
+        println!("{}", difference);
+    }
+    Ok(())
+}
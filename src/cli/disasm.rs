@@ -0,0 +1,96 @@
+use super::{Args, Result};
+use cs_parser::{AttributeInfoInner, ClassFile, ConstantPool, MethodInfo};
+use std::path::Path;
+
+/// `coldsquare disasm <file> [--method <name(descriptor)>] [--decompile] [--source-path <paths>]`,
+/// prints the code of the methods with labels for the branch targets and the source lines they
+/// start
+pub fn run(mut args: Args) -> Result<()> {
+    let method = args.option(&["--method"])?;
+    let decompile = args.flag(&["--decompile"]);
+    let source_path = args.option(&["--source-path"])?;
+    let class = super::read_class(&super::single_file(args)?)?;
+    let cp = &class.constant_pool;
+    let source = match &source_path {
+        Some(source_path) => find_source(&class, source_path)?,
+        None => None,
+    };
+
+    let methods = match &method {
+        Some(spec) => vec![super::find_method(&class, spec)?],
+        None => class.methods.iter().collect(),
+    };
+    for (i, method) in methods.into_iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{}{}",
+            method.name_index.get(cp),
+            method.descriptor_index.get(cp)
+        );
+        let code = match decompile {
+            true => cs_parser::decompile(&class, method)?,
+            false => listing(method, source.as_deref(), cp)?,
+        };
+        match code {
+            Some(listing) => {
+                for line in listing.lines() {
+                    println!("  {}", line);
+                }
+            }
+            None => println!("  no code"),
+        }
+    }
+    Ok(())
+}
+
+/// The disassembled code of the method, `None` for abstract and native methods
+fn listing(method: &MethodInfo, source: Option<&str>, cp: &ConstantPool) -> Result<Option<String>> {
+    let Some((code, attributes)) = method.attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::Code {
+            code, attributes, ..
+        } => Some((code, attributes)),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let line_numbers = attributes
+        .iter()
+        .filter_map(|attr| match &attr.inner {
+            AttributeInfoInner::LineNumberTable { line_number_table } => Some(line_number_table),
+            _ => None,
+        })
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    Ok(Some(match source {
+        Some(source) => cs_parser::disassemble_with_source(code, &line_numbers, source, cp)?,
+        None => cs_parser::disassemble_listing(code, &line_numbers, cp)?,
+    }))
+}
+
+/// The content of the file named by the `SourceFile` attribute, in the directory of the package of
+/// the class or directly in one of the directories of the source path. Without the file, the line
+/// numbers are printed like without a source path
+fn find_source(class: &ClassFile, source_path: &str) -> Result<Option<String>> {
+    let cp = &class.constant_pool;
+    let Some(file) = class.attributes.iter().find_map(|attr| match &attr.inner {
+        AttributeInfoInner::SourceFile { sourcefile_index } => Some(sourcefile_index.get(cp)),
+        _ => None,
+    }) else {
+        eprintln!("The class has no SourceFile attribute");
+        return Ok(None);
+    };
+    let name = class.this_class.get(cp).name_index.get(cp);
+    let package = name.rsplit_once('/').map_or("", |(package, _)| package);
+    for directory in std::env::split_paths(source_path) {
+        for path in [directory.join(package).join(file), directory.join(file)] {
+            if Path::new(&path).is_file() {
+                return Ok(Some(std::fs::read_to_string(path)?));
+            }
+        }
+    }
+    eprintln!("{} was not found on the source path", file);
+    Ok(None)
+}
@@ -0,0 +1,64 @@
+use super::{Args, Result};
+use cs_parser::{AttributeCodeException, AttributeInfoInner, ClassFile, CpInfoInner};
+
+/// `coldsquare extract <file> --method <name(descriptor)> -o <out> [--exceptions]`,
+/// writes the raw bytecode of a method to a file
+pub fn run(mut args: Args) -> Result<()> {
+    let method = args
+        .option(&["--method"])?
+        .ok_or("Missing --method <name(descriptor)>")?;
+    let output = args
+        .option(&["-o", "--output"])?
+        .ok_or("Missing output file -o <file>")?;
+    let exceptions = args.flag(&["--exceptions"]);
+    let class = super::read_class(&super::single_file(args)?)?;
+
+    let method = super::find_method(&class, &method)?;
+    let (code, exception_table) = method
+        .attributes
+        .iter()
+        .find_map(|attr| match &attr.inner {
+            AttributeInfoInner::Code {
+                code,
+                exception_table,
+                ..
+            } => Some((code, exception_table)),
+            _ => None,
+        })
+        .ok_or("The method has no code")?;
+
+    std::fs::write(&output, code)?;
+    if exceptions {
+        std::fs::write(
+            format!("{}.json", output),
+            exception_table_json(&class, exception_table),
+        )?;
+    }
+    Ok(())
+}
+
+/// The exception table as a JSON array, with the catch type as the class name or `null` for any
+fn exception_table_json(class: &ClassFile, exception_table: &[AttributeCodeException]) -> String {
+    let cp = &class.constant_pool;
+    let entries = exception_table
+        .iter()
+        .map(|exception| {
+            let catch_type = match cp.entry(exception.catch_type) {
+                Some(info) => match &info.inner {
+                    CpInfoInner::Class(class) => super::json_string(class.name_index.get(cp)),
+                    _ => "null".to_owned(),
+                },
+                None => "null".to_owned(),
+            };
+            format!(
+                "  {{\"start_pc\": {}, \"end_pc\": {}, \"handler_pc\": {}, \"catch_type\": {}}}",
+                exception.start_pc, exception.end_pc, exception.handler_pc, catch_type
+            )
+        })
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        "[]\n".to_owned()
+    } else {
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Args, Result};
+use cs_parser::{AttributeInfoInner, ClassFile, CpInfoInner, ParseErr};
+
+/// A constant pool string that contains the pattern
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Match<'a> {
+    pub(super) value: &'a str,
+    /// Whether the entry is the value of a `CONSTANT_String`, and not just a name or descriptor
+    pub(super) is_string: bool,
+    /// The fields that are initialized to this string through a `ConstantValue`
+    pub(super) fields: Vec<&'a str>,
+    /// The methods that load this string with `ldc` or `ldc_w`, like `main([Ljava/lang/String;)V`
+    pub(super) methods: Vec<String>,
+}
+
+/// `coldsquare grep <pattern> <file|directory|jar>`, lists the classes with string constants containing the pattern
+pub fn run(mut args: Args) -> Result<()> {
+    let pattern = args.subcommand().ok_or("Missing pattern to search for")?;
+    let path = super::single_file(args)?;
+
+    for super::ParsedInput { input, class } in super::parse_inputs(&path)? {
+        let class = match class {
+            Ok(class) => class,
+            Err(err) => {
+                eprintln!("{}: {}", input.name, err);
+                continue;
+            }
+        };
+        let cp = &class.constant_pool;
+        let name = class.this_class.get(cp).name_index.get(cp);
+        let found = match matches(&class, &pattern) {
+            Ok(found) => found,
+            Err(err) => {
+                eprintln!("{}: {}", input.name, err);
+                continue;
+            }
+        };
+        for found in found {
+            print!(
+                "{}: {} {:?}",
+                name,
+                if found.is_string { "string" } else { "utf8" },
+                found.value
+            );
+            if !found.fields.is_empty() {
+                print!(" (field {})", found.fields.join(", "));
+            }
+            if !found.methods.is_empty() {
+                print!(" (method {})", found.methods.join(", "));
+            }
+            println!();
+        }
+    }
+    Ok(())
+}
+
+/// All utf8 entries of the constant pool that contain the pattern, the code of the methods is
+/// decoded to find the ones that load them
+pub(super) fn matches<'a>(
+    class: &'a ClassFile,
+    pattern: &str,
+) -> std::result::Result<Vec<Match<'a>>, ParseErr> {
+    let cp = &class.constant_pool;
+    let loads = class.string_loads()?;
+    let string_value = |info: &'a CpInfoInner| match info {
+        CpInfoInner::String(string) => Some(string.string_index.get(cp)),
+        _ => None,
+    };
+
+    Ok(cp
+        .iter()
+        .filter_map(|info| match &info.inner {
+            CpInfoInner::Utf8(utf8) if utf8.bytes.contains(pattern) => Some(utf8.bytes.as_str()),
+            _ => None,
+        })
+        .map(|value| Match {
+            value,
+            is_string: cp
+                .iter()
+                .filter_map(|info| string_value(&info.inner))
+                .any(|string| std::ptr::eq(string, value)),
+            fields: class
+                .fields
+                .iter()
+                .filter(|field| {
+                    field.attributes.iter().any(|attr| match &attr.inner {
+                        AttributeInfoInner::ConstantValue {
+                            constantvalue_index,
+                        } => string_value(constantvalue_index.get(cp))
+                            .is_some_and(|string| std::ptr::eq(string, value)),
+                        _ => false,
+                    })
+                })
+                .map(|field| field.name_index.get(cp))
+                .collect(),
+            methods: {
+                let mut methods = loads
+                    .iter()
+                    .filter(|(_, _, string)| std::ptr::eq(*string, value))
+                    .map(|(method, _, _)| {
+                        format!(
+                            "{}{}",
+                            method.name_index.get(cp),
+                            method.descriptor_index.get(cp)
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                methods.dedup();
+                methods
+            },
+        })
+        .collect())
+}
@@ -0,0 +1,125 @@
+use super::{Args, Result};
+use cs_class_printer::{PrintOptions, Sections};
+use cs_parser::ClassFile;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+
+type PrintFn = dyn Fn(&ClassFile);
+
+/// `coldsquare info [--watch] [--json | --yaml] [--[no-]color] [-v | -vv] [sections] <file|directory>`
+pub fn run(mut args: Args) -> Result<()> {
+    let watch = args.flag(&["-w", "--watch"]);
+    let sections = sections(&mut args);
+    // without --color or --no-color, colors are only used on a terminal, and never if the
+    // `NO_COLOR` convention asks for it
+    let color = args.switch(&["--color"]).unwrap_or_else(|| {
+        std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+    });
+    let options = PrintOptions { color, sections };
+    // the header is printed before each class when a whole directory is printed
+    let (print, header): (Box<PrintFn>, fn(&str)) = if args.flag(&["--json"]) {
+        // the documents are simply concatenated, which tools like jq can read
+        (Box::new(cs_class_printer::print_json), |_| {})
+    } else if args.flag(&["--yaml"]) {
+        (Box::new(cs_class_printer::print_yaml), |name| {
+            println!("--- # {}", name)
+        })
+    } else {
+        (
+            Box::new(move |class_file| cs_class_printer::print_with(class_file, &options)),
+            |name| println!("// {}", name),
+        )
+    };
+    let file = super::single_file(args)?;
+
+    if Path::new(&file).is_dir() {
+        if watch {
+            return Err("--watch only works with a single class file".into());
+        }
+        print_directory(&file, &print, header)
+    } else if watch {
+        watch_file(&file, &print)
+    } else {
+        print(&super::read_class(&file)?);
+        Ok(())
+    }
+}
+
+/// Only the sections given as flags, or the summary with more details for each `-v`
+fn sections(args: &mut Args) -> Sections {
+    let verbosity = args.count(&["-v", "--verbose"]) + 2 * args.count(&["-vv"]);
+    let mut sections = Sections {
+        constants: args.flag(&["--constants"]),
+        attributes: args.flag(&["--attributes"]),
+        fields: args.flag(&["--fields"]),
+        methods: args.flag(&["--methods"]),
+        code: args.flag(&["-c", "--code"]),
+    };
+    if sections == Sections::NONE {
+        sections = Sections::default();
+    }
+    // the code is shown below the methods it belongs to
+    sections.methods |= sections.code;
+    if verbosity >= 1 {
+        sections.code = sections.methods;
+    }
+    if verbosity >= 2 {
+        sections.constants = true;
+    }
+    sections
+}
+
+/// Prints every class file below the directory. Files that can't be parsed are reported and
+/// skipped, and only fail the command after all others have been printed
+fn print_directory(dir: &str, print: &PrintFn, header: fn(&str)) -> Result<()> {
+    let inputs = super::parse_inputs(dir)?;
+    let (mut printed, mut failed) = (0, 0);
+    for super::ParsedInput { input, class } in &inputs {
+        match class {
+            Ok(class_file) => {
+                if printed > 0 {
+                    println!();
+                }
+                printed += 1;
+                header(&input.name);
+                print(class_file);
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("{}: {}", input.name, err);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} class files could not be parsed",
+            failed,
+            inputs.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Prints the file again whenever its modification time changes. Runs until the process is killed
+fn watch_file(file: &str, print: &PrintFn) -> Result<()> {
+    let mut last_modified = None;
+    loop {
+        let modified = std::fs::metadata(file)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified != last_modified {
+            last_modified = modified;
+            // clear the screen and move the cursor to the top left
+            print!("\x1B[2J\x1B[H");
+            // the file might be in the middle of being written, so errors are only reported
+            match super::read_class(file) {
+                Ok(class_file) => print(&class_file),
+                Err(err) => eprintln!("{}", err),
+            }
+            std::io::stdout().flush()?;
+        }
+        std::thread::sleep(Duration::from_millis(300));
+    }
+}
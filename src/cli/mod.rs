@@ -0,0 +1,226 @@
+//!
+//! The subcommands of the `coldsquare` binary
+//!
+
+mod args;
+mod config;
+mod deps;
+mod diff;
+mod disasm;
+mod extract;
+mod grep;
+mod info;
+mod retarget;
+mod run;
+mod stats;
+mod strings;
+mod stubs;
+#[cfg(test)]
+mod test;
+mod verify;
+
+pub use args::Args;
+pub use cs_class_printer::json_string;
+use std::path::{Path, PathBuf};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "Usage: coldsquare <command> [options] <file>
+
+Options:
+  --config <file>  Reads default options from the file instead of ~/.config/coldsquare.toml.
+                     Its keys are the long names, like `watch = true` or `format = 'json'`.
+                     Flags are turned off with --no-<flag>, like --no-watch
+  -w, --watch      info: Prints the file again whenever it changes
+  --json           info: Prints the whole parsed class, including the constant pool, as JSON
+  --yaml           info: Prints the same as --json as YAML
+  --[no-]color     info: Always or never colors the output, which is otherwise colored on a
+                     terminal
+  -v, -vv          info: Also prints the code of the methods, and with -vv the constant pool
+  --constants      info: Prints the constant pool. With any of the section flags, only those
+  --attributes       sections are printed instead of the summary of attributes, fields and methods
+  --fields
+  --methods
+  -c, --code       info: Prints the methods with their code, branch labels and source lines
+  --format <fmt>   stats: Either csv (default) or json
+  --method <name>  extract, disasm: The method, optionally with its descriptor like `compute(I)I`.
+                     disasm prints all methods without it
+  --decompile      disasm: Prints Java-like statements instead, if the code is simple enough
+  --source-path <paths>
+                   disasm: The directories with the sources, separated like PATH. The lines of
+                     the source file of the class are printed above their code
+  --major <n>      retarget: The major version to target, for example 52 for Java 8
+  -o <file>        extract, retarget, stubs: The file the output is written to
+  --exceptions     extract: Also writes the exception table to <out>.json
+  -cp, --classpath run, verify, retarget: The directories and jars classes are loaded from,
+                     separated like PATH. run and retarget use the directory of the file by
+                     default, verify also looks up classes in the checked directory or jar
+  --thread-dumps   run: Prints the stack, state and monitors of every thread to stderr whenever
+                     a line is entered
+
+Commands:
+  info     Prints the structure of a class file, or of every class file below a directory (default)
+  deps     Lists the classes a class file references
+  diff     Prints the differences between two versions of a class: diff <old> <new>
+  disasm   Prints the code of the methods of a class with labels and source lines
+  extract  Writes the bytecode of a method to a file: extract <file> --method <name(descriptor)> -o <out>
+  grep     Lists the string constants containing a pattern and the fields and methods using them:
+           grep <pattern> <file>
+  retarget Changes the class file version of a class: retarget <file> --major <version> -o <out>
+  run      Executes the main method of a class: run <file> [args]
+  stats    Prints size statistics for every class in a file, directory or jar
+  strings  Lists the string and numeric literals of every class in a file, directory or jar
+  stubs    Prints Rust stubs for the native methods of a class, to run it with natives
+  verify   Verifies the code of every class in a file, directory or jar";
+
+/// Runs the subcommand given in the arguments
+pub fn run(mut args: Args) -> Result<()> {
+    if args.flag(&["-h", "--help"]) {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let config = args.option(&["--config"])?;
+    args.set_defaults(config::load(config.as_deref())?);
+
+    match args.subcommand().as_deref() {
+        Some("info") => info::run(args),
+        Some("deps") => deps::run(args),
+        Some("diff") => diff::run(args),
+        Some("disasm") => disasm::run(args),
+        Some("extract") => extract::run(args),
+        Some("grep") => grep::run(args),
+        Some("retarget") => retarget::run(args),
+        Some("run") => run::run(args),
+        Some("stats") => stats::run(args),
+        Some("strings") => strings::run(args),
+        Some("stubs") => stubs::run(args),
+        Some("verify") => verify::run(args),
+        Some("help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        // a plain `coldsquare Foo.class` prints the file info
+        Some(file) if file.ends_with(".class") => {
+            args.push_front(file.to_owned());
+            info::run(args)
+        }
+        Some(command) => Err(format!("Unknown command {}\n\n{}", command, USAGE).into()),
+        None => Err(USAGE.into()),
+    }
+}
+
+/// The single file given in the arguments
+pub fn single_file(args: Args) -> Result<String> {
+    match &args.positional()?[..] {
+        [file] => Ok(file.clone()),
+        [] => Err("No file provided".into()),
+        _ => Err("Too many files provided".into()),
+    }
+}
+
+/// Reads and parses a class file
+pub fn read_class(file: &str) -> Result<cs_parser::ClassFile> {
+    let contents =
+        std::fs::read(file).map_err(|err| format!("Could not read file {}: {}", file, err))?;
+    Ok(cs_parser::parse_class_file(&contents)?)
+}
+
+/// Finds a method by its name and descriptor, like `compute(I)I`. The descriptor can be left out
+/// if the name is unique
+pub fn find_method<'a>(
+    class: &'a cs_parser::ClassFile,
+    spec: &str,
+) -> Result<&'a cs_parser::MethodInfo> {
+    let cp = &class.constant_pool;
+    let (name, descriptor) = match spec.find('(') {
+        Some(start) => (&spec[..start], Some(&spec[start..])),
+        None => (spec, None),
+    };
+
+    let mut candidates = class.methods.iter().filter(|method| {
+        method.name_index.get(cp) == name
+            && descriptor.is_none_or(|descriptor| method.descriptor_index.get(cp) == descriptor)
+    });
+    match (candidates.next(), candidates.next()) {
+        (Some(method), None) => Ok(method),
+        (None, _) => Err(format!("No method {} found", spec).into()),
+        (Some(_), Some(_)) => Err(format!(
+            "Method {} is overloaded, add the descriptor like {}(I)V",
+            spec, name
+        )
+        .into()),
+    }
+}
+
+/// A class file read from the file system or from inside an archive
+pub struct ClassInput {
+    /// The path of the file, or the name of the archive entry
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Reads the class files at `path`: a single class file, all class files below a directory,
+/// or all class files in a jar. They are sorted by name
+pub fn read_inputs(path: &str) -> Result<Vec<ClassInput>> {
+    use rayon::prelude::*;
+
+    let metadata =
+        std::fs::metadata(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+    let mut inputs = if metadata.is_dir() {
+        let mut files = Vec::new();
+        walk_class_files(Path::new(path), &mut files)?;
+        files
+            .into_iter()
+            .map(|file| {
+                Ok(ClassInput {
+                    bytes: std::fs::read(&file)?,
+                    name: file.display().to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else if path.ends_with(".jar") {
+        cs_archive::archive_entries(path, &cs_archive::ArchiveOptions::default())?
+            .map(|entry| entry.map(|(name, bytes)| ClassInput { name, bytes }))
+            .collect::<cs_archive::Result<Vec<_>>>()?
+    } else {
+        vec![ClassInput {
+            name: path.to_owned(),
+            bytes: std::fs::read(path)?,
+        }]
+    };
+    inputs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(inputs)
+}
+
+/// A class file from `read_inputs` with the result of parsing it
+pub struct ParsedInput {
+    pub input: ClassInput,
+    pub class: std::result::Result<cs_parser::ClassFile, cs_parser::ParseErr>,
+}
+
+/// Reads the class files at `path` like `read_inputs` and parses them on the rayon thread pool.
+/// The results are in the same order as the inputs
+pub fn parse_inputs(path: &str) -> Result<Vec<ParsedInput>> {
+    use rayon::prelude::*;
+
+    Ok(read_inputs(path)?
+        .into_par_iter()
+        .map(|input| ParsedInput {
+            class: cs_parser::parse_class_file(&input.bytes),
+            input,
+        })
+        .collect())
+}
+
+/// Collects all `.class` files below `dir`
+fn walk_class_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_class_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
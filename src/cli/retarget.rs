@@ -0,0 +1,25 @@
+use super::verify::ClassPathHierarchy;
+use super::{Args, Result};
+
+/// `coldsquare retarget <file> --major <version> -o <out> [-cp <classpath>]`, changes the class
+/// file version of a class. The stack map frames of upgraded classes need the classes they use,
+/// which are looked up in the directory of the file and then on the class path
+pub fn run(mut args: Args) -> Result<()> {
+    let major = args
+        .option(&["--major"])?
+        .ok_or("Missing target version --major <version>, for example 52 for Java 8")?;
+    let major = major
+        .parse()
+        .map_err(|_| format!("Invalid major version {}", major))?;
+    let output = args
+        .option(&["-o", "--output"])?
+        .ok_or("Missing output file -o <file>")?;
+    let class_path = args.option(&["-cp", "--classpath"])?;
+    let path = super::single_file(args)?;
+    let mut class = super::read_class(&path)?;
+
+    let hierarchy = ClassPathHierarchy::new(&path, class_path)?;
+    cs_verifier::retarget(&mut class, major, &hierarchy)?;
+    std::fs::write(&output, cs_parser::write_class_file(&class)?)?;
+    Ok(())
+}
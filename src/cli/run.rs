@@ -0,0 +1,34 @@
+use super::{Args, Result};
+use coldsquare::run_main;
+use cs_archive::ClassPath;
+use std::path::Path;
+
+/// `coldsquare run <file> [args]`, executes the `main` method of a class. The other classes are
+/// loaded from `--classpath`, or from the directory of the file. With `--thread-dumps`, the
+/// threads are printed to stderr whenever a line is entered
+pub fn run(mut args: Args) -> Result<()> {
+    let class_path = args.option(&["-cp", "--classpath"])?;
+    let thread_dumps = args.flag(&["--thread-dumps"]);
+    let mut positional = args.positional()?.into_iter();
+    let file = positional.next().ok_or("No file provided")?;
+    let main_args = positional.collect::<Vec<_>>();
+
+    let class_path = match class_path {
+        Some(list) => ClassPath::from_list(&list)?,
+        None => {
+            let mut class_path = ClassPath::new();
+            class_path.push_directory(Path::new(&file).parent().unwrap_or(Path::new(".")));
+            class_path
+        }
+    };
+    let code = run_main(
+        super::read_class(&file)?,
+        class_path,
+        &main_args,
+        thread_dumps,
+    )?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use super::{Args, Result};
+use cs_parser::{AttributeInfoInner, ClassFile};
+use std::io::Write;
+
+/// The statistics of a single class
+pub(super) struct ClassStats {
+    name: String,
+    major_version: u16,
+    minor_version: u16,
+    /// The size of the class file in bytes
+    size: usize,
+    methods: usize,
+    /// The summed up length of all method bodies
+    code_bytes: usize,
+    constant_pool: usize,
+}
+
+impl ClassStats {
+    pub(super) fn new(class: &ClassFile, size: usize) -> Self {
+        let cp = &class.constant_pool;
+        Self {
+            name: class.this_class.get(cp).name_index.get(cp).to_owned(),
+            major_version: class.major_version,
+            minor_version: class.minor_version,
+            size,
+            methods: class.methods.len(),
+            code_bytes: class
+                .methods
+                .iter()
+                .flat_map(|method| &method.attributes)
+                .map(|attr| match &attr.inner {
+                    AttributeInfoInner::Code { code, .. } => code.len(),
+                    _ => 0,
+                })
+                .sum(),
+            constant_pool: cp.len(),
+        }
+    }
+}
+
+/// `coldsquare stats [--format csv|json] <file|directory|jar>`
+pub fn run(mut args: Args) -> Result<()> {
+    let format = args.option(&["--format"])?;
+    let path = super::single_file(args)?;
+
+    let mut stats = Vec::new();
+    for super::ParsedInput { input, class } in super::parse_inputs(&path)? {
+        match class {
+            Ok(class) => stats.push(ClassStats::new(&class, input.bytes.len())),
+            Err(err) => eprintln!("{}: {}", input.name, err),
+        }
+    }
+
+    match format.as_deref() {
+        None | Some("csv") => write_csv(std::io::stdout().lock(), &stats)?,
+        Some("json") => print_json(&stats),
+        Some(format) => return Err(format!("Unknown format {}", format).into()),
+    }
+    Ok(())
+}
+
+/// Writes a header and a row for every class, the names are quoted if they contain a comma,
+/// quote or newline
+pub(super) fn write_csv<W: Write>(mut w: W, stats: &[ClassStats]) -> std::io::Result<()> {
+    writeln!(w, "name,version,size,methods,code_bytes,constant_pool")?;
+    for class in stats {
+        let name = if class.name.contains([',', '"', '\n']) {
+            format!("\"{}\"", class.name.replace('"', "\"\""))
+        } else {
+            class.name.clone()
+        };
+        writeln!(
+            w,
+            "{},{}.{},{},{},{},{}",
+            name,
+            class.major_version,
+            class.minor_version,
+            class.size,
+            class.methods,
+            class.code_bytes,
+            class.constant_pool
+        )?;
+    }
+    Ok(())
+}
+
+fn print_json(stats: &[ClassStats]) {
+    println!("[");
+    for (i, class) in stats.iter().enumerate() {
+        println!(
+            "  {{\"name\": {}, \"version\": \"{}.{}\", \"size\": {}, \"methods\": {}, \"code_bytes\": {}, \"constant_pool\": {}}}{}",
+            super::json_string(&class.name),
+            class.major_version,
+            class.minor_version,
+            class.size,
+            class.methods,
+            class.code_bytes,
+            class.constant_pool,
+            if i + 1 == stats.len() { "" } else { "," }
+        );
+    }
+    println!("]");
+}
@@ -0,0 +1,40 @@
+use super::{Args, Result};
+use cs_parser::{ClassFile, CpInfoInner};
+
+/// `coldsquare strings <file|directory|jar>`, lists the string and numeric literals of every class
+pub fn run(args: Args) -> Result<()> {
+    let path = super::single_file(args)?;
+
+    for super::ParsedInput { input, class } in super::parse_inputs(&path)? {
+        let class = match class {
+            Ok(class) => class,
+            Err(err) => {
+                eprintln!("{}: {}", input.name, err);
+                continue;
+            }
+        };
+        let cp = &class.constant_pool;
+        println!("{}:", class.this_class.get(cp).name_index.get(cp));
+        for literal in literals(&class) {
+            println!("  {}", literal);
+        }
+    }
+    Ok(())
+}
+
+/// The literals in the constant pool, formatted with their type
+fn literals(class: &ClassFile) -> Vec<String> {
+    let cp = &class.constant_pool;
+    cp.iter()
+        .filter_map(|info| match &info.inner {
+            CpInfoInner::String(string) => {
+                Some(format!("string {:?}", string.string_index.get(cp)))
+            }
+            CpInfoInner::Integer(int) => Some(format!("int {}", int.value())),
+            CpInfoInner::Float(float) => Some(format!("float {:?}", float.value())),
+            CpInfoInner::Long(long) => Some(format!("long {}", long.value())),
+            CpInfoInner::Double(double) => Some(format!("double {:?}", double.value())),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,14 @@
+use super::{Args, Result};
+
+/// `coldsquare stubs <file> [-o <out>]`, prints the Rust code to implement the `native` methods of
+/// a class with, or writes it to the output file
+pub fn run(mut args: Args) -> Result<()> {
+    let output = args.option(&["-o", "--output"])?;
+    let class = super::read_class(&super::single_file(args)?)?;
+    let stubs = cs_vm::native_stubs(&class)?;
+    match output {
+        Some(output) => std::fs::write(output, stubs)?,
+        None => print!("{}", stubs),
+    }
+    Ok(())
+}
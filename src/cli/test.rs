@@ -0,0 +1,141 @@
+use super::grep::{matches, Match};
+use super::stats::{write_csv, ClassStats};
+use super::{config, Args};
+use cs_parser::{
+    Assembler, AttributeInfo, AttributeInfoInner, ClassFile, ClassFileBuilder, FieldAccessFlag,
+    Instruction, MethodAccessFlag, MethodCode,
+};
+
+/// A class whose method `load` returns the string, and whose field `URL` is initialized to it
+fn class_with_string(name: &str, string: &str) -> ClassFile {
+    let mut builder = ClassFileBuilder::new(name).add_field(
+        FieldAccessFlag::STATIC | FieldAccessFlag::FINAL,
+        "URL",
+        "Ljava/lang/String;",
+    );
+    let pool = builder.constant_pool();
+    let value = pool.string(string).inner();
+    let constant_value = pool.utf8("ConstantValue");
+    let mut asm = Assembler::new();
+    asm.push(Instruction::LdcW(value));
+    asm.push(Instruction::Areturn);
+    let code = MethodCode {
+        max_stack: 1,
+        max_locals: 0,
+        code: asm.assemble().unwrap().code,
+        exception_table: Vec::new(),
+    };
+    let mut class = builder
+        .add_method(
+            MethodAccessFlag::STATIC,
+            "load",
+            "()Ljava/lang/String;",
+            Some(code),
+        )
+        .add_method(MethodAccessFlag::ABSTRACT, "unused", "()V", None)
+        .build();
+    class.fields[0].attributes.push(AttributeInfo {
+        attribute_name_index: constant_value,
+        attribute_length: 2,
+        inner: AttributeInfoInner::ConstantValue {
+            constantvalue_index: value.into(),
+        },
+        original: None,
+    });
+    class
+}
+
+#[test]
+fn grep_matches() {
+    let class = class_with_string("pkg/Config", "https://secret.example");
+    assert_eq!(
+        matches(&class, "secret").unwrap(),
+        [Match {
+            value: "https://secret.example",
+            is_string: true,
+            fields: vec!["URL"],
+            methods: vec!["load()Ljava/lang/String;".to_string()],
+        }]
+    );
+    // names and descriptors are matched too, but nothing loads them
+    assert_eq!(
+        matches(&class, "unuse").unwrap(),
+        [Match {
+            value: "unused",
+            is_string: false,
+            fields: Vec::new(),
+            methods: Vec::new(),
+        }]
+    );
+    assert_eq!(matches(&class, "missing").unwrap(), []);
+}
+
+#[test]
+fn stats_csv() {
+    let stats = [
+        ClassStats::new(&class_with_string("pkg/Plain", ""), 100),
+        ClassStats::new(&class_with_string("pkg/Odd,\"Name\"", ""), 200),
+    ];
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &stats).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "name,version,size,methods,code_bytes,constant_pool\n\
+         pkg/Plain,52.0,100,2,4,14\n\
+         \"pkg/Odd,\"\"Name\"\"\",52.0,200,2,4,14\n"
+    );
+}
+
+/// The arguments with the defaults from the config
+fn args_with_config(args: &[&str], config: &str) -> Args {
+    let mut args = Args::new(args.iter().map(|arg| arg.to_string()));
+    let (defaults, unknown) = config::parse(config).unwrap();
+    assert_eq!(unknown, Vec::<String>::new());
+    args.set_defaults(defaults);
+    args
+}
+
+#[test]
+fn config_defaults() {
+    let config = "watch = true\nformat = 'json'\nclasspath = ['lib', 'app.jar']";
+    let mut args = args_with_config(&[], config);
+    assert!(args.flag(&["-w", "--watch"]));
+    assert!(!args.flag(&["--json"]));
+    assert_eq!(args.option(&["--format"]).unwrap().as_deref(), Some("json"));
+    let class_path = std::env::join_paths(["lib", "app.jar"]).unwrap();
+    assert_eq!(
+        args.option(&["-cp", "--classpath"]).unwrap(),
+        class_path.to_str().map(str::to_owned)
+    );
+
+    // the command line wins, also for turning flags off
+    let mut args = args_with_config(&["--no-watch", "--format", "csv", "A.class"], config);
+    assert!(!args.flag(&["-w", "--watch"]));
+    assert_eq!(args.option(&["--format"]).unwrap().as_deref(), Some("csv"));
+    assert_eq!(args.positional().unwrap(), ["A.class"]);
+    let mut args = args_with_config(&["--no-watch", "-w"], config);
+    assert!(args.flag(&["-w", "--watch"]));
+}
+
+#[test]
+fn config_color() {
+    let color = |args: &[&str], config: &str| args_with_config(args, config).switch(&["--color"]);
+    assert_eq!(color(&[], ""), None);
+    assert_eq!(color(&[], "color = false"), Some(false));
+    assert_eq!(color(&[], "no-color = true"), Some(false));
+    assert_eq!(color(&[], "color = true"), Some(true));
+    assert_eq!(color(&["--color"], "color = false"), Some(true));
+    assert_eq!(color(&["--no-color"], "color = true"), Some(false));
+}
+
+#[test]
+fn config_errors() {
+    let (defaults, unknown) = config::parse("colour = false\nyaml = true").unwrap();
+    assert_eq!(unknown, ["colour"]);
+    assert_eq!(defaults.get("yaml").map(String::as_str), Some("true"));
+    assert_eq!(
+        config::parse("watch = 'yes'").unwrap_err().to_string(),
+        "watch must be true or false, not \"yes\""
+    );
+    assert!(config::parse("format = { name = 'csv' }").is_err());
+}
@@ -0,0 +1,64 @@
+use super::{Args, ParsedInput, Result};
+use cs_archive::ClassPath;
+use cs_verifier::{ClassHierarchy, ClassInfo};
+use std::path::Path;
+
+/// `coldsquare verify <path> [-cp <classpath>]`, verifies every class in a file, directory or jar
+/// and prints the ones that fail. The classes they use are looked up in the directory or jar that
+/// is checked, or the directory of a single file, and then on the class path
+pub fn run(mut args: Args) -> Result<()> {
+    let class_path = args.option(&["-cp", "--classpath"])?;
+    let path = super::single_file(args)?;
+    let hierarchy = ClassPathHierarchy::new(&path, class_path)?;
+
+    let inputs = super::parse_inputs(&path)?;
+    let mut failed = 0;
+    for ParsedInput { input, class } in &inputs {
+        let result = match class {
+            Ok(class) => {
+                cs_verifier::verify_class(class, &hierarchy).map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        };
+        if let Err(err) = result {
+            println!("{}: {}", input.name, err);
+            failed += 1;
+        }
+    }
+    match failed {
+        0 => {
+            println!("Verified {} classes", inputs.len());
+            Ok(())
+        }
+        _ => Err(format!("{} of {} classes failed verification", failed, inputs.len()).into()),
+    }
+}
+
+/// Looks up the classes on the class path. Classes that are missing or can't be parsed are
+/// assumed to be assignable, like the verifier does for unknown classes
+pub(super) struct ClassPathHierarchy(ClassPath);
+
+impl ClassPathHierarchy {
+    /// The classes in the directory or jar at `path`, or the directory of a single class file,
+    /// and then the ones on the class path
+    pub(super) fn new(path: &str, class_path: Option<String>) -> Result<Self> {
+        let mut hierarchy = ClassPath::new();
+        match Path::new(path).is_file() && path.ends_with(".class") {
+            true => hierarchy.push_directory(Path::new(path).parent().unwrap_or(Path::new("."))),
+            false => hierarchy.push(path)?,
+        }
+        if let Some(list) = class_path {
+            for entry in std::env::split_paths(&list) {
+                hierarchy.push(entry)?;
+            }
+        }
+        Ok(Self(hierarchy))
+    }
+}
+
+impl ClassHierarchy for ClassPathHierarchy {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        let class = self.0.resolve(name).ok().flatten()?;
+        Some(ClassInfo::of(&class).1)
+    }
+}
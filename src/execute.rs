@@ -0,0 +1,118 @@
+//!
+//! Executing the `main` method or any other static method of a class
+//!
+
+use cs_archive::ClassPath;
+use cs_model::names::internal_to_binary;
+use cs_parser::{ClassFile, Instruction};
+use cs_vm::error::VmError;
+use cs_vm::{DebugAction, Execution, Frame, Interpreter, Value};
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Executes `public static void main(String[])` like `java` with the arguments in a `String[]`,
+/// and returns the exit code. An exception that the code doesn't catch is printed and exits
+/// with 1. See `run_method` for `thread_dumps`
+pub fn run_main(
+    class: ClassFile,
+    class_path: ClassPath,
+    args: &[String],
+    thread_dumps: bool,
+) -> Result<i32, VmError> {
+    let (mut interpreter, class) = interpreter(class, class_path)?;
+    let args = interpreter.string_array(args)?;
+    let result = execute(
+        &mut interpreter,
+        class,
+        "main",
+        "([Ljava/lang/String;)V",
+        &[Value::Reference(Some(args))],
+        thread_dumps,
+    );
+    match result {
+        Ok(_) => Ok(0),
+        Err(VmError::Throw { class, message }) => {
+            let class = internal_to_binary(&class);
+            match message.as_str() {
+                "" => eprintln!("Exception in thread \"main\" {}", class),
+                _ => eprintln!("Exception in thread \"main\" {}: {}", class, message),
+            }
+            Ok(1)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Executes a static method of the class with the arguments and returns its result. The classes
+/// it uses are loaded from the class path. With `thread_dumps`, a thread dump is printed to
+/// stderr for every line that is entered on stdin, which the code can't read anyway
+pub fn run_method(
+    class: ClassFile,
+    class_path: ClassPath,
+    name: &str,
+    descriptor: &str,
+    args: &[Value],
+    thread_dumps: bool,
+) -> Result<Option<Value>, VmError> {
+    let (mut interpreter, class) = interpreter(class, class_path)?;
+    execute(
+        &mut interpreter,
+        class,
+        name,
+        descriptor,
+        args,
+        thread_dumps,
+    )
+}
+
+/// An interpreter that loads classes from the class path, to which the class was added
+fn interpreter(
+    class: ClassFile,
+    class_path: ClassPath,
+) -> Result<(Interpreter, Arc<ClassFile>), VmError> {
+    let class = Arc::new(class);
+    let mut interpreter = Interpreter::with_class_path(class_path);
+    interpreter.add_class(Arc::clone(&class))?;
+    Ok((interpreter, class))
+}
+
+/// Runs the method, and pauses for the thread dumps if they are requested
+fn execute(
+    interpreter: &mut Interpreter,
+    class: Arc<ClassFile>,
+    name: &str,
+    descriptor: &str,
+    args: &[Value],
+    thread_dumps: bool,
+) -> Result<Option<Value>, VmError> {
+    if !thread_dumps {
+        return interpreter.run(class, name, descriptor, args);
+    }
+
+    let requested = Arc::new(AtomicBool::new(false));
+    let input = Arc::clone(&requested);
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            if line.is_err() {
+                break;
+            }
+            input.store(true, Ordering::Relaxed);
+        }
+    });
+    // the interpreter pauses before the next instruction, so the dump shows a consistent state
+    interpreter.set_debugger(move |_: &Frame, _: &Instruction| {
+        if requested.swap(false, Ordering::Relaxed) {
+            DebugAction::Pause
+        } else {
+            DebugAction::Continue
+        }
+    });
+    interpreter.start(class, name, descriptor, args)?;
+    loop {
+        match interpreter.resume()? {
+            Execution::Returned(value) => return Ok(value),
+            Execution::Paused => eprint!("{}", interpreter.thread_dump()),
+        }
+    }
+}
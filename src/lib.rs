@@ -0,0 +1,8 @@
+//!
+//! Running classes with the interpreter of `cs_vm` like `java` does, which the `run` command of
+//! the binary uses
+//!
+
+pub mod execute;
+
+pub use execute::{run_main, run_method};